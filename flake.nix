@@ -438,6 +438,11 @@
           
           config = mkIf config.programs.nix-for-humanity.enable {
             environment.systemPackages = [ self.packages.${pkgs.system}.nix-for-humanity ];
+            # Where the sessions of all users take turns with system changes
+            systemd.tmpfiles.rules = [
+              "d /run/luminous-nix 1777 root root -"
+              "f /run/luminous-nix/system.lock 0444 root root -"
+            ];
           };
         };
         
@@ -1,3 +1,16 @@
+use std::path::Path;
+
+// Bundled for the accessibility audit, which never loads it from anywhere else
+const AXE_CORE: &str = "resources/axe-core/axe.min.js";
+
 fn main() {
+    println!("cargo:rerun-if-changed={}", AXE_CORE);
+    // The audit reports it missing at runtime; nothing else depends on it
+    if !Path::new(AXE_CORE).is_file() {
+        println!(
+            "cargo:warning={} is missing, so the accessibility audit won't run; fetch it with scripts/fetch-axe-core.sh",
+            AXE_CORE
+        );
+    }
     tauri_build::build()
-}
\ No newline at end of file
+}
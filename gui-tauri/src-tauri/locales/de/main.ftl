@@ -0,0 +1,46 @@
+# Beschreibungen von Absichten für Bestätigungen, Rückfragen und Verlauf
+intent-install = { $packages } installieren
+intent-remove = { $packages } entfernen
+intent-search = Nach „{ $query }“ suchen
+intent-list-installed = Installierte Pakete anzeigen
+intent-update = System aktualisieren
+intent-rollback-to = Auf Generation { $generation } zurücksetzen
+intent-rollback = Auf die vorherige Generation zurücksetzen
+intent-garbage-collect = Alte Generationen und ungenutzte Pakete aufräumen
+intent-set-boot-default = Standardmäßig Generation { $generation } starten
+intent-delete-generations = Generationen { $generations } löschen
+intent-switch-specialisation = Zur Spezialisierung { $name } wechseln
+intent-leave-specialisation = Zur Grundkonfiguration zurückwechseln
+intent-explain = { $topic } erklären
+intent-enable = { $setting } aktivieren
+intent-disable = { $setting } deaktivieren
+intent-configure = { $setting } konfigurieren
+intent-set-default-app = { $app } als Standard für { $role } festlegen
+intent-scaffold-project = Neues { $template }-Projekt anlegen
+intent-plugin = { $plugin }: { $action }
+intent-unknown = Nichts erkannt
+
+# Antworten
+error-no-package = Kein Paket angegeben
+error-unknown-action = Unbekannte Aktion
+error-no-explanation = Für „{ $topic }“ habe ich noch keine Erklärung
+error-cannot-configure = Ich weiß noch nicht, wie man „{ $setting }“ konfiguriert
+error-scaffold-path = Wo soll das neue Projekt angelegt werden?
+error-unsupported-language = „{ $language }“ wird noch nicht unterstützt; wähle eine von { $supported }
+clarify-guessed = Hier musste ich raten. Hast du das gemeint?
+
+# Risikozusammenfassungen in Bestätigungen; für die Sprachausgabe zu einem Satz verbunden
+risk-reversible = Das lässt sich rückgängig machen.
+risk-irreversible = Das lässt sich nicht rückgängig machen.
+risk-downtime-none = Nichts muss neu gestartet werden.
+risk-downtime-restart = Geänderte Dienste starten kurz neu.
+risk-downtime-reboot = Danach ist ein Neustart nötig.
+risk-disk-unchanged = Der Speicherverbrauch bleibt gleich.
+risk-disk-grows = Der Speicherverbrauch steigt.
+risk-disk-grows-by = Der Speicherverbrauch steigt um etwa { $size } MB.
+risk-disk-shrinks = Speicherplatz wird frei.
+risk-disk-shrinks-by = Etwa { $size } MB Speicherplatz werden frei.
+risk-disk-mixed = Manche Schritte brauchen mehr Speicher, andere geben welchen frei.
+risk-network-none = Kein Netzwerk nötig.
+risk-network-light = Nutzt kurz das Netzwerk.
+risk-network-heavy = Lädt Pakete herunter, was bei langsamer Verbindung dauern kann.
@@ -0,0 +1,46 @@
+# Intent descriptions, used in confirmations, clarifications and history
+intent-install = Install { $packages }
+intent-remove = Remove { $packages }
+intent-search = Search for "{ $query }"
+intent-list-installed = List installed packages
+intent-update = Update the system
+intent-rollback-to = Roll back to generation { $generation }
+intent-rollback = Roll back to the previous generation
+intent-garbage-collect = Clean up old generations and unused packages
+intent-set-boot-default = Boot generation { $generation } by default
+intent-delete-generations = Delete generations { $generations }
+intent-switch-specialisation = Switch to the { $name } specialisation
+intent-leave-specialisation = Switch back to the base configuration
+intent-explain = Explain { $topic }
+intent-enable = Enable { $setting }
+intent-disable = Disable { $setting }
+intent-configure = Configure { $setting }
+intent-set-default-app = Make { $app } the default { $role }
+intent-scaffold-project = Start a new { $template } project
+intent-plugin = { $plugin }: { $action }
+intent-unknown = Nothing recognised
+
+# Responses
+error-no-package = No package given
+error-unknown-action = Unknown action
+error-no-explanation = I don't have an explanation for '{ $topic }' yet
+error-cannot-configure = I don't know how to configure '{ $setting }' yet
+error-scaffold-path = Where should the new project be created?
+error-unsupported-language = '{ $language }' is not supported yet; choose one of { $supported }
+clarify-guessed = I had to guess here. Is this what you meant?
+
+# Risk summaries in confirmations; joined into one sentence for TTS
+risk-reversible = This can be undone.
+risk-irreversible = This cannot be undone.
+risk-downtime-none = Nothing needs to restart.
+risk-downtime-restart = Services that changed will restart briefly.
+risk-downtime-reboot = A reboot is needed afterwards.
+risk-disk-unchanged = Disk use stays the same.
+risk-disk-grows = Disk use will grow.
+risk-disk-grows-by = Disk use will grow by about { $size } MB.
+risk-disk-shrinks = Disk space will be freed.
+risk-disk-shrinks-by = About { $size } MB of disk space will be freed.
+risk-disk-mixed = Some steps use more disk space, others free it.
+risk-network-none = No network is needed.
+risk-network-light = Uses the network briefly.
+risk-network-heavy = Downloads packages, which can take a while on a slow connection.
@@ -0,0 +1,46 @@
+# Descripciones de intenciones para confirmaciones, aclaraciones e historial
+intent-install = Instalar { $packages }
+intent-remove = Eliminar { $packages }
+intent-search = Buscar «{ $query }»
+intent-list-installed = Mostrar los paquetes instalados
+intent-update = Actualizar el sistema
+intent-rollback-to = Volver a la generación { $generation }
+intent-rollback = Volver a la generación anterior
+intent-garbage-collect = Limpiar generaciones antiguas y paquetes sin usar
+intent-set-boot-default = Arrancar la generación { $generation } por defecto
+intent-delete-generations = Borrar las generaciones { $generations }
+intent-switch-specialisation = Cambiar a la especialización { $name }
+intent-leave-specialisation = Volver a la configuración base
+intent-explain = Explicar { $topic }
+intent-enable = Activar { $setting }
+intent-disable = Desactivar { $setting }
+intent-configure = Configurar { $setting }
+intent-set-default-app = Usar { $app } como { $role } predeterminado
+intent-scaffold-project = Crear un proyecto nuevo de { $template }
+intent-plugin = { $plugin }: { $action }
+intent-unknown = No se reconoció nada
+
+# Respuestas
+error-no-package = No se indicó ningún paquete
+error-unknown-action = Acción desconocida
+error-no-explanation = Todavía no tengo una explicación para «{ $topic }»
+error-cannot-configure = Todavía no sé cómo configurar «{ $setting }»
+error-scaffold-path = ¿Dónde se debe crear el proyecto nuevo?
+error-unsupported-language = «{ $language }» aún no es compatible; elige uno de { $supported }
+clarify-guessed = Aquí tuve que adivinar. ¿Es esto lo que querías decir?
+
+# Resúmenes de riesgo en las confirmaciones; se unen en una frase para la lectura en voz alta
+risk-reversible = Se puede deshacer.
+risk-irreversible = No se puede deshacer.
+risk-downtime-none = No hace falta reiniciar nada.
+risk-downtime-restart = Los servicios que cambien se reiniciarán brevemente.
+risk-downtime-reboot = Después hará falta reiniciar.
+risk-disk-unchanged = El uso de disco no cambia.
+risk-disk-grows = El uso de disco aumentará.
+risk-disk-grows-by = El uso de disco aumentará unos { $size } MB.
+risk-disk-shrinks = Se liberará espacio en disco.
+risk-disk-shrinks-by = Se liberarán unos { $size } MB de espacio en disco.
+risk-disk-mixed = Algunos pasos usan más disco y otros lo liberan.
+risk-network-none = No se necesita red.
+risk-network-light = Usa la red brevemente.
+risk-network-heavy = Descarga paquetes, lo que puede tardar con una conexión lenta.
@@ -0,0 +1,17 @@
+#!/usr/bin/env bash
+# Fetch the pinned axe-core release into resources/axe-core for bundling.
+# npm checks the package against the registry's integrity hash.
+set -euo pipefail
+
+VERSION="4.10.2"
+HERE="$(cd "$(dirname "$0")/.." && pwd)"
+DEST="$HERE/resources/axe-core"
+WORK="$(mktemp -d)"
+trap 'rm -rf "$WORK"' EXIT
+
+cd "$WORK"
+npm pack --silent "axe-core@$VERSION" >/dev/null
+tar -xzf "axe-core-$VERSION.tgz"
+install -m 0644 package/axe.min.js "$DEST/axe.min.js"
+install -m 0644 package/LICENSE "$DEST/LICENSE"
+echo "axe-core $VERSION is in $DEST"
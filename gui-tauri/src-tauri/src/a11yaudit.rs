@@ -0,0 +1,213 @@
+// Accessibility audit of the rendered pages with axe-core
+//
+// The self-test in a11ycheck reasons about the theme, layout and persona; this
+// looks at what is actually on screen. axe-core is injected into every window
+// of the app (unless the page already loaded it) and run over the whole
+// document. Each failing element becomes an issue, attributed to the component
+// it sits in and mapped to the WCAG success criteria the rule covers, with
+// axe's description of what to change. axe-core ships with the app as a
+// bundled resource and is only ever loaded from there: the windows it runs in
+// can call every command, so nothing the user's account can write is injected.
+
+use anyhow::{anyhow, bail, Context};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::Duration;
+use tauri::path::BaseDirectory;
+use tauri::{AppHandle, Manager};
+
+use crate::a11ycheck::Severity;
+use crate::probe;
+
+// Where tauri.conf.json bundles it, relative to the resource dir
+const AXE_RESOURCE: &str = "resources/axe-core/axe.min.js";
+const RUN_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Per violated rule: what it is, how bad, and each element failing it; `attr`
+// and `aaa` are set in front of it
+const AUDIT_SCRIPT: &str = "var options=aaa?{rules:{'color-contrast-enhanced':{enabled:true}}}:{};\
+var r=await window.axe.run(document,options);\
+return {version:window.axe.version,passes:r.passes.length,incomplete:r.incomplete.length,\
+violations:r.violations.map(function(v){return {id:v.id,impact:v.impact||'minor',tags:v.tags,\
+help:v.help,help_url:v.helpUrl,nodes:v.nodes.map(function(n){var el=null;\
+try{el=document.querySelector(n.target[0]);}catch(e){}\
+var c=el&&el.closest('['+attr+']');\
+return {target:n.target.join(' '),component:c?c.getAttribute(attr):null,summary:n.failureSummary||''};})};})};";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Impact {
+    Critical,
+    Serious,
+    Moderate,
+    Minor,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditIssue {
+    pub rule: String,
+    pub impact: Impact,
+    pub severity: Severity,
+    // Success criteria, "1.4.3"
+    pub wcag: Vec<String>,
+    // "A", "AA" or "AAA"; None for axe's best practices
+    pub level: Option<String>,
+    pub window: String,
+    // None when the element is outside every component
+    pub component: Option<String>,
+    // CSS selector of the failing element
+    pub target: String,
+    pub message: String,
+    pub fix: String,
+    pub help_url: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditReport {
+    pub axe_version: String,
+    pub windows: Vec<String>,
+    // Rules every element passed, and ones axe couldn't decide
+    pub passes: usize,
+    pub incomplete: usize,
+    pub issues: Vec<AuditIssue>,
+}
+
+impl AuditReport {
+    // Issues by component id; "" for those outside every component
+    pub fn by_component(&self) -> BTreeMap<String, Vec<&AuditIssue>> {
+        let mut grouped: BTreeMap<String, Vec<&AuditIssue>> = BTreeMap::new();
+        for issue in &self.issues {
+            let id = issue.component.clone().unwrap_or_default();
+            grouped.entry(id).or_default().push(issue);
+        }
+        grouped
+    }
+}
+
+// What the audit script returns
+#[derive(Debug, Deserialize)]
+struct AxeResults {
+    version: String,
+    passes: usize,
+    incomplete: usize,
+    violations: Vec<AxeViolation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AxeViolation {
+    id: String,
+    impact: Impact,
+    tags: Vec<String>,
+    help: String,
+    help_url: String,
+    nodes: Vec<AxeNode>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AxeNode {
+    target: String,
+    component: Option<String>,
+    summary: String,
+}
+
+fn axe_source(app: &AppHandle) -> anyhow::Result<String> {
+    let path = app.path().resolve(AXE_RESOURCE, BaseDirectory::Resource)?;
+    // Fetched separately before bundling; see resources/axe-core/README.md
+    if !path.is_file() {
+        bail!("axe-core isn't bundled with this build, so the accessibility audit can't run");
+    }
+    fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))
+}
+
+// "wcag1410" is success criterion 1.4.10
+fn criterion(tag: &str) -> Option<String> {
+    let digits = tag.strip_prefix("wcag")?;
+    if digits.len() < 3 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!(
+        "{}.{}.{}",
+        &digits[..1],
+        &digits[1..2],
+        &digits[2..]
+    ))
+}
+
+// "wcag2aa" and "wcag21aa" are level AA
+fn level(tags: &[String]) -> Option<String> {
+    tags.iter().find_map(|tag| {
+        let level = tag
+            .strip_prefix("wcag")?
+            .trim_start_matches(|c: char| c.is_ascii_digit());
+        matches!(level, "a" | "aa" | "aaa").then(|| level.to_uppercase())
+    })
+}
+
+fn severity(impact: Impact) -> Severity {
+    match impact {
+        Impact::Critical | Impact::Serious => Severity::Error,
+        Impact::Moderate | Impact::Minor => Severity::Warning,
+    }
+}
+
+fn audit_window(
+    app: &AppHandle,
+    label: &str,
+    source: &str,
+    aaa: bool,
+) -> anyhow::Result<AxeResults> {
+    let window = app
+        .get_webview_window(label)
+        .ok_or_else(|| anyhow!("There is no window \"{}\"", label))?;
+    // Evaluated as it is, so it defines window.axe like a script tag would
+    window.eval(&format!("if(!window.axe){{{}\n}}", source))?;
+    let script = format!(
+        "var attr={};var aaa={};{}",
+        serde_json::to_string(probe::COMPONENT_ATTRIBUTE)?,
+        aaa,
+        AUDIT_SCRIPT
+    );
+    let value = probe::eval(app, label, &script, RUN_TIMEOUT)?;
+    serde_json::from_value(value).context("Reading axe-core's results")
+}
+
+// Audit every window against level AA, or AAA when the user needs it
+pub fn run(app: &AppHandle, aaa: bool) -> anyhow::Result<AuditReport> {
+    let source = axe_source(app)?;
+    let mut labels: Vec<String> = app.webview_windows().into_keys().collect();
+    labels.sort();
+    if labels.is_empty() {
+        bail!("There is no window to audit");
+    }
+    let mut report = AuditReport::default();
+    for label in labels {
+        let results = audit_window(app, &label, &source, aaa)
+            .with_context(|| format!("Auditing {}", label))?;
+        report.axe_version = results.version;
+        report.passes += results.passes;
+        report.incomplete += results.incomplete;
+        for violation in results.violations {
+            let wcag: Vec<String> = violation.tags.iter().filter_map(|t| criterion(t)).collect();
+            let level = level(&violation.tags);
+            for node in violation.nodes {
+                report.issues.push(AuditIssue {
+                    rule: violation.id.clone(),
+                    impact: violation.impact,
+                    severity: severity(violation.impact),
+                    wcag: wcag.clone(),
+                    level: level.clone(),
+                    window: label.clone(),
+                    component: node.component,
+                    target: node.target,
+                    message: violation.help.clone(),
+                    fix: node.summary,
+                    help_url: violation.help_url.clone(),
+                });
+            }
+        }
+        report.windows.push(label);
+    }
+    report.issues.sort_by_key(|i| i.impact);
+    Ok(report)
+}
@@ -0,0 +1,379 @@
+// Accessibility self-test
+//
+// Checks the combination actually in use (the saved theme, the active layout
+// and the persona and accessibility needs of the user) instead of trusting
+// that each was fine on its own: text contrast against WCAG, the size of
+// click targets once text is scaled, motion when reduced motion was asked for,
+// and whether a screen reader can reach every registered component (it has a
+// role and a name, and sits in a cell of the layout grid). Runs at startup
+// unless turned off; every issue comes with what to change.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use tauri::AppHandle;
+
+use crate::onboarding::AccessibilityNeeds;
+use crate::personas::Persona;
+use crate::{layouts, storage, tasks, themes, AppState};
+
+const SETTINGS_FILE: &str = "a11y-selftest.json";
+// Control height assumed when the theme doesn't set `target-size`
+const DEFAULT_TARGET_PX: f32 = 40.0;
+
+// (foreground token, background token, minimum for AA, minimum for AAA)
+const CONTRAST_PAIRS: &[(&str, &str, f32, f32)] = &[
+    ("text", "background", 4.5, 7.0),
+    ("text-muted", "background", 4.5, 7.0),
+    ("link", "background", 4.5, 7.0),
+    ("error", "background", 4.5, 7.0),
+    ("primary-text", "primary", 4.5, 7.0),
+    ("text", "surface", 4.5, 7.0),
+    // Not text, so the non-text minimum applies
+    ("focus-ring", "background", 3.0, 3.0),
+];
+
+// Component types the frontend renders with a role and an accessible name;
+// anything else has to bring its own
+const NAMED_TYPES: &[&str] = &[
+    "SearchInput",
+    "ResultsList",
+    "GuidePanel",
+    "HistoryList",
+    "CommandDetails",
+    "ProgressPanel",
+    "TerminalPanel",
+    "LiveRegion",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SelfTestSettings {
+    pub on_startup: bool,
+}
+
+impl Default for SelfTestSettings {
+    fn default() -> Self {
+        SelfTestSettings { on_startup: true }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Check {
+    Contrast,
+    TargetSize,
+    Motion,
+    ScreenReader,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Issue {
+    pub check: Check,
+    pub severity: Severity,
+    pub component: Option<String>,
+    pub message: String,
+    pub fix: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfTestReport {
+    pub persona: String,
+    pub layout: String,
+    // "AAA" when the user's needs call for it, otherwise "AA"
+    pub level: String,
+    // False when no theme has been saved, so there were no colours to check
+    pub contrast_checked: bool,
+    pub issues: Vec<Issue>,
+}
+
+impl SelfTestReport {
+    pub fn passed(&self) -> bool {
+        !self.issues.iter().any(|i| i.severity == Severity::Error)
+    }
+}
+
+pub fn settings() -> SelfTestSettings {
+    storage::load(SETTINGS_FILE).unwrap_or_default()
+}
+
+// "textMuted", "text_muted" and "--text-muted" all name the same token
+fn token<'a>(theme: &'a BTreeMap<String, String>, name: &str) -> Option<&'a str> {
+    let normalize = |s: &str| {
+        s.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase()
+    };
+    let wanted = normalize(name);
+    theme
+        .iter()
+        .find(|(key, _)| normalize(key) == wanted)
+        .map(|(_, value)| value.as_str())
+}
+
+// #rgb, #rrggbb or rgb(r, g, b)
+pub fn parse_color(value: &str) -> Option<[u8; 3]> {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#').filter(|h| h.is_ascii()) {
+        let hex: String = match hex.len() {
+            3 => hex.chars().flat_map(|c| [c, c]).collect(),
+            6 => hex.to_string(),
+            _ => return None,
+        };
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        return Some([channel(0)?, channel(2)?, channel(4)?]);
+    }
+    let inner = value.strip_prefix("rgb(")?.strip_suffix(')')?;
+    let channels: Vec<u8> = inner
+        .split(',')
+        .filter_map(|c| c.trim().parse().ok())
+        .collect();
+    (channels.len() == 3).then(|| [channels[0], channels[1], channels[2]])
+}
+
+// WCAG relative luminance
+fn luminance([r, g, b]: [u8; 3]) -> f32 {
+    let linear = |c: u8| {
+        let c = c as f32 / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+fn contrast(a: [u8; 3], b: [u8; 3]) -> f32 {
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+fn check_contrast(theme: &BTreeMap<String, String>, strict: bool, issues: &mut Vec<Issue>) {
+    for (fg, bg, aa, aaa) in CONTRAST_PAIRS {
+        let (Some(fg_value), Some(bg_value)) = (token(theme, fg), token(theme, bg)) else {
+            continue;
+        };
+        let (Some(fg_color), Some(bg_color)) = (parse_color(fg_value), parse_color(bg_value))
+        else {
+            issues.push(Issue {
+                check: Check::Contrast,
+                severity: Severity::Warning,
+                component: None,
+                message: format!("Couldn't read the colours of `{}` or `{}`", fg, bg),
+                fix: "Use #rrggbb or rgb(r, g, b) for theme colours".to_string(),
+            });
+            continue;
+        };
+        let required = if strict { *aaa } else { *aa };
+        let ratio = contrast(fg_color, bg_color);
+        if ratio < required {
+            issues.push(Issue {
+                check: Check::Contrast,
+                severity: Severity::Error,
+                component: None,
+                message: format!(
+                    "`{}` on `{}` has a contrast of {:.1}:1; at least {:.1}:1 is needed",
+                    fg, bg, ratio, required
+                ),
+                fix: format!(
+                    "Make `{}` darker or `{}` lighter (or the other way round) in the theme",
+                    fg, bg
+                ),
+            });
+        }
+    }
+}
+
+fn check_components(
+    state: &AppState,
+    template: Option<&str>,
+    needs: &AccessibilityNeeds,
+    issues: &mut Vec<Issue>,
+) {
+    let components = state.components.blocking_lock();
+    let mut seen = HashSet::new();
+    for component in components.iter() {
+        let id = Some(component.id.clone());
+        if !seen.insert(component.id.as_str()) {
+            issues.push(Issue {
+                check: Check::ScreenReader,
+                severity: Severity::Error,
+                component: id.clone(),
+                message: format!("Two components share the id `{}`", component.id),
+                fix: "Give every component in the layout its own id".to_string(),
+            });
+        }
+        let builtin = NAMED_TYPES.contains(&component.component_type.as_str());
+        let field = |name: &str| component.state.get(name).and_then(|v| v.as_str());
+        if !builtin && field("role").is_none() {
+            issues.push(Issue {
+                check: Check::ScreenReader,
+                severity: Severity::Error,
+                component: id.clone(),
+                message: format!(
+                    "Screen readers can't tell what `{}` ({}) is",
+                    component.id, component.component_type
+                ),
+                fix: "Set a `role` in the component's state".to_string(),
+            });
+        }
+        if !builtin && field("label").or(field("aria_label")).is_none() {
+            issues.push(Issue {
+                check: Check::ScreenReader,
+                severity: Severity::Error,
+                component: id.clone(),
+                message: format!("`{}` has no accessible name", component.id),
+                fix: "Set a `label` in the component's state".to_string(),
+            });
+        }
+        // A component without a grid cell is never rendered, so never reached
+        if let (Some(template), Some(area)) = (template, field("area")) {
+            let placed = template
+                .split(|c: char| c == '"' || c.is_whitespace())
+                .any(|cell| cell == area);
+            if !placed {
+                issues.push(Issue {
+                    check: Check::ScreenReader,
+                    severity: Severity::Error,
+                    component: id.clone(),
+                    message: format!(
+                        "`{}` sits in the `{}` area, which the layout grid doesn't have",
+                        component.id, area
+                    ),
+                    fix: format!("Add `{}` to the layout preset's template", area),
+                });
+            }
+        }
+    }
+    if needs.screen_reader
+        && !components
+            .iter()
+            .any(|c| c.capabilities.iter().any(|cap| cap.name == "announce"))
+    {
+        issues.push(Issue {
+            check: Check::ScreenReader,
+            severity: Severity::Warning,
+            component: None,
+            message: "Nothing in this layout announces results to a screen reader".to_string(),
+            fix: "Switch to the low-vision layout, or add a LiveRegion component".to_string(),
+        });
+    }
+}
+
+pub fn needs(state: &AppState) -> AccessibilityNeeds {
+    state
+        .user_profile
+        .blocking_lock()
+        .as_ref()
+        .and_then(|p| serde_json::from_value(p.preferences.get("accessibility")?.clone()).ok())
+        .unwrap_or_default()
+}
+
+pub fn run(state: &AppState, persona: &Persona) -> SelfTestReport {
+    let needs = needs(state);
+    // The active layout, or the one the persona starts with
+    let (layout, template, animate, min_font_scale) =
+        match state.current_layout.blocking_lock().as_ref() {
+            Some(layout) => (
+                layout.id.clone(),
+                layout.grid["template"].as_str().map(String::from),
+                layout.grid["animate"].as_bool().unwrap_or(false),
+                layout.grid["font_scale"].as_f64().unwrap_or(1.0) as f32,
+            ),
+            None => match layouts::resolve("persona", persona) {
+                Ok(preset) => (
+                    preset.id,
+                    Some(preset.template),
+                    preset.animate && persona.animations(),
+                    preset.min_font_scale,
+                ),
+                Err(_) => (persona.layout.to_string(), None, persona.animations(), 1.0),
+            },
+        };
+    let strict = needs.high_contrast || needs.font_scale >= 1.4 || persona.font_scale >= 1.4;
+    let mut issues = Vec::new();
+
+    let theme = themes::resolve(&themes::active(), &needs).tokens;
+    check_contrast(&theme, strict, &mut issues);
+
+    let font_scale = persona.font_scale.max(needs.font_scale).max(min_font_scale);
+    let target = token(&theme, "target-size")
+        .and_then(|v| v.trim().trim_end_matches("px").parse::<f32>().ok())
+        .unwrap_or(DEFAULT_TARGET_PX);
+    // Controls are sized in rem, so they grow with the text
+    let effective = target * font_scale;
+    let required = if strict { 44.0 } else { 24.0 };
+    if effective < required {
+        issues.push(Issue {
+            check: Check::TargetSize,
+            severity: Severity::Error,
+            component: None,
+            message: format!(
+                "Click targets are about {:.0}px; at least {:.0}px is needed",
+                effective, required
+            ),
+            fix: format!(
+                "Raise `target-size` in the theme to {:.0}px or more",
+                (required / font_scale).ceil()
+            ),
+        });
+    }
+
+    if animate && (needs.reduced_motion || !persona.animations()) {
+        issues.push(Issue {
+            check: Check::Motion,
+            severity: Severity::Error,
+            component: None,
+            message: format!(
+                "The {} layout animates although reduced motion was asked for",
+                layout
+            ),
+            fix: "Turn off `animate` in the layout preset, or pick a layout without motion"
+                .to_string(),
+        });
+    }
+
+    check_components(state, template.as_deref(), &needs, &mut issues);
+
+    SelfTestReport {
+        persona: persona.id.to_string(),
+        layout,
+        level: if strict { "AAA" } else { "AA" }.to_string(),
+        contrast_checked: !theme.is_empty(),
+        issues,
+    }
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn run_accessibility_selftest(app: AppHandle) -> SelfTestReport {
+    tasks::blocking_value(&app, "Run accessibility selftest", |state| {
+        run(state, crate::current_persona(state))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_accessibility_selftest(app: AppHandle) -> SelfTestSettings {
+    tasks::blocking_value(&app, "Get accessibility selftest", |_| settings()).await
+}
+
+#[tauri::command]
+pub async fn set_accessibility_selftest(
+    settings: SelfTestSettings,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set accessibility selftest", move |_| {
+        crate::respond(storage::save(SETTINGS_FILE, &settings).map(|_| settings))
+    })
+    .await
+}
@@ -0,0 +1,198 @@
+// The action registry: everything the user can do by name
+//
+// The command palette and the tray menu list their actions from here, so an
+// action looks and runs the same wherever it is offered. Each one says how to
+// carry it out: a Tauri command with its arguments (system changes go through
+// perform_action, which applies the confirmation policy), or a frontend action
+// with the same name as its keyboard shortcut. Actions with a shortcut show
+// the accelerator the current profile binds them to.
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::nlp::Intent;
+use crate::safety::{self, BlastRadius};
+use crate::{shortcuts, tasks, AppState};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Invocation {
+    // Invoke this Tauri command with these arguments
+    Command {
+        command: String,
+        args: serde_json::Value,
+    },
+    // Handled in the frontend, like the "shortcut" event of the same action
+    Frontend {
+        action: String,
+        #[serde(default)]
+        args: serde_json::Value,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Action {
+    pub id: String,
+    pub title: String,
+    // Other words people use for it
+    pub keywords: Vec<String>,
+    // "system", "appearance", "app", ...
+    pub category: String,
+    pub risk: BlastRadius,
+    pub shortcut: Option<String>,
+    pub invocation: Invocation,
+}
+
+fn command(
+    id: &str,
+    title: &str,
+    keywords: &[&str],
+    category: &str,
+    risk: BlastRadius,
+    command: &str,
+    args: serde_json::Value,
+) -> Action {
+    Action {
+        id: id.to_string(),
+        title: title.to_string(),
+        keywords: keywords.iter().map(|k| k.to_string()).collect(),
+        category: category.to_string(),
+        risk,
+        shortcut: None,
+        invocation: Invocation::Command {
+            command: command.to_string(),
+            args,
+        },
+    }
+}
+
+// Carried out through perform_action, so confirmations apply
+fn intent(id: &str, title: &str, keywords: &[&str], action: &str, intent: Intent) -> Action {
+    command(
+        id,
+        title,
+        keywords,
+        "system",
+        safety::classify(&intent),
+        "perform_action",
+        serde_json::json!({"action": action, "params": {}}),
+    )
+}
+
+fn theme_mode(mode: &str, title: &str, keywords: &[&str]) -> Action {
+    command(
+        &format!("theme-{}", mode),
+        title,
+        keywords,
+        "appearance",
+        BlastRadius::ReadOnly,
+        "set_theme_mode",
+        serde_json::json!({"mode": mode}),
+    )
+}
+
+fn builtin() -> Vec<Action> {
+    vec![
+        intent(
+            "update-system",
+            "Update the system",
+            &["upgrade", "rebuild", "switch", "flake update"],
+            "update",
+            Intent::Update,
+        ),
+        intent(
+            "collect-garbage",
+            "Free up disk space",
+            &["garbage collect", "gc", "clean", "delete old generations"],
+            "garbage_collect",
+            Intent::GarbageCollect,
+        ),
+        intent(
+            "rollback",
+            "Roll back to the previous generation",
+            &["undo update", "revert", "previous system"],
+            "rollback",
+            Intent::Rollback { generation: None },
+        ),
+        intent(
+            "list-installed",
+            "Show installed packages",
+            &["installed", "my packages", "list"],
+            "list",
+            Intent::ListInstalled,
+        ),
+        command(
+            "check-updates",
+            "Check for updates",
+            &["updates available", "outdated", "new versions"],
+            "system",
+            BlastRadius::ReadOnly,
+            "check_for_updates",
+            serde_json::json!({}),
+        ),
+        command(
+            "optimise-store",
+            "Deduplicate the Nix store",
+            &["optimise", "optimize", "hard link", "disk space"],
+            "system",
+            BlastRadius::Reversible,
+            "optimise_store",
+            serde_json::json!({}),
+        ),
+        command(
+            "enable-flakes",
+            "Enable flakes",
+            &["experimental features", "nix-command"],
+            "system",
+            BlastRadius::Reversible,
+            "enable_nix_flakes",
+            serde_json::json!({"user_level": false}),
+        ),
+        command(
+            "accessibility-check",
+            "Check accessibility",
+            &["contrast", "screen reader", "a11y"],
+            "app",
+            BlastRadius::ReadOnly,
+            "run_accessibility_selftest",
+            serde_json::json!({}),
+        ),
+        theme_mode("auto", "Follow the desktop theme", &["system theme"]),
+        theme_mode("light", "Use the light theme", &["bright"]),
+        theme_mode("dark", "Use the dark theme", &["night", "dim"]),
+        theme_mode(
+            "high-contrast",
+            "Use the high-contrast theme",
+            &["contrast", "low vision"],
+        ),
+    ]
+}
+
+// Every action, with the shortcut actions as the current profile binds them
+pub fn all(state: &AppState) -> Vec<Action> {
+    let mut actions = builtin();
+    actions.extend(
+        shortcuts::assigned(state)
+            .into_iter()
+            .map(|binding| Action {
+                id: binding.action.clone(),
+                title: binding.description,
+                keywords: vec![binding.action.replace('-', " ")],
+                category: "app".to_string(),
+                risk: BlastRadius::ReadOnly,
+                shortcut: Some(binding.accelerator),
+                invocation: Invocation::Frontend {
+                    action: binding.action,
+                    args: serde_json::Value::Null,
+                },
+            }),
+    );
+    actions
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn list_actions(app: AppHandle) -> Vec<Action> {
+    tasks::blocking_value(&app, "List actions", |state| all(state)).await
+}
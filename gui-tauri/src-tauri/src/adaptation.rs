@@ -0,0 +1,277 @@
+// Declarative rules that turn the user's state into interface adaptations
+//
+// Each rule is "when every condition holds, apply these adaptations". Rules
+// are read from adaptation-rules.json in the config dir (the built-in set is
+// used when it's absent) and applied in order, so later rules refine earlier
+// ones. Every application is logged so the user can see why the interface
+// changed.
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::{clock, storage, tasks};
+
+const RULES_FILE: &str = "adaptation-rules.json";
+pub const LOG_FILE: &str = "adaptation-log.json";
+const MAX_LOG_ENTRIES: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Op {
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Eq,
+    Ne,
+}
+
+// `metric` names a value in the state map: cognitive_load, frustration,
+// persona, pacing, local_hour, or anything the frontend sends
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Condition {
+    pub metric: String,
+    pub op: Op,
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Adaptation {
+    Layout { layout: String },
+    // Multiplies the current font scale
+    FontScale { factor: f32 },
+    // Show at most this many options or suggestions at once
+    ReduceChoices { max: u32 },
+    Animations { enabled: bool },
+    // Minimum seconds between unprompted suggestions
+    ThrottleSuggestions { min_interval_secs: u64 },
+    Verbosity { verbosity: String },
+    Pacing { pacing: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    pub id: String,
+    pub description: String,
+    pub when: Vec<Condition>,
+    pub then: Vec<Adaptation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleSet {
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp: u64,
+    pub rule: String,
+    pub description: String,
+    // The metric values that made the rule apply
+    pub because: serde_json::Value,
+}
+
+fn condition(metric: &str, op: Op, value: serde_json::Value) -> Condition {
+    Condition {
+        metric: metric.to_string(),
+        op,
+        value,
+    }
+}
+
+fn rule(id: &str, description: &str, when: Vec<Condition>, then: Vec<Adaptation>) -> Rule {
+    Rule {
+        id: id.to_string(),
+        description: description.to_string(),
+        when,
+        then,
+    }
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        use serde_json::json;
+        RuleSet {
+            rules: vec![
+                rule(
+                    "elevated-load-larger-text",
+                    "Slightly larger text while things are getting busy",
+                    vec![condition("cognitive_load", Op::Gte, json!(0.6))],
+                    vec![Adaptation::FontScale { factor: 1.1 }],
+                ),
+                rule(
+                    "high-load-simplify",
+                    "Simplify the interface under high cognitive load",
+                    vec![condition("cognitive_load", Op::Gte, json!(0.8))],
+                    vec![
+                        Adaptation::Layout {
+                            layout: "minimal".to_string(),
+                        },
+                        Adaptation::FontScale { factor: 1.1 },
+                        Adaptation::ReduceChoices { max: 3 },
+                        Adaptation::Verbosity {
+                            verbosity: "concise".to_string(),
+                        },
+                        Adaptation::Pacing {
+                            pacing: "gentle".to_string(),
+                        },
+                    ],
+                ),
+                rule(
+                    "frustration-back-off",
+                    "Fewer interruptions and choices when things keep going wrong",
+                    vec![condition("frustration", Op::Gte, json!(0.6))],
+                    vec![
+                        Adaptation::ThrottleSuggestions {
+                            min_interval_secs: 300,
+                        },
+                        Adaptation::ReduceChoices { max: 2 },
+                    ],
+                ),
+                rule(
+                    "gentle-pacing-still",
+                    "No animations at a gentle pace",
+                    vec![condition("pacing", Op::Eq, json!("gentle"))],
+                    vec![Adaptation::Animations { enabled: false }],
+                ),
+                rule(
+                    "small-hours-calm",
+                    "A calmer interface in the small hours",
+                    vec![condition("local_hour", Op::Lt, json!(5))],
+                    vec![
+                        Adaptation::Animations { enabled: false },
+                        Adaptation::ThrottleSuggestions {
+                            min_interval_secs: 600,
+                        },
+                    ],
+                ),
+            ],
+        }
+    }
+}
+
+// The configured rules, or the built-in set when none are configured
+pub fn load() -> RuleSet {
+    storage::load::<Option<RuleSet>>(RULES_FILE)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+fn holds(condition: &Condition, state: &serde_json::Value) -> bool {
+    let Some(actual) = state.get(&condition.metric) else {
+        return false;
+    };
+    match (actual.as_f64(), condition.value.as_f64()) {
+        (Some(a), Some(b)) => match condition.op {
+            Op::Gt => a > b,
+            Op::Gte => a >= b,
+            Op::Lt => a < b,
+            Op::Lte => a <= b,
+            Op::Eq => a == b,
+            Op::Ne => a != b,
+        },
+        // Strings and booleans only compare for (in)equality
+        _ => match condition.op {
+            Op::Eq => *actual == condition.value,
+            Op::Ne => *actual != condition.value,
+            _ => false,
+        },
+    }
+}
+
+fn apply_one(adaptation: &Adaptation, ui: &mut serde_json::Value) {
+    use serde_json::json;
+    match adaptation {
+        Adaptation::Layout { layout } => ui["layout"] = json!(layout),
+        Adaptation::FontScale { factor } => {
+            let scale = ui["font_scale"].as_f64().unwrap_or(1.0) as f32 * factor;
+            let increase = ui["font_size_increase"].as_f64().unwrap_or(1.0) as f32 * factor;
+            ui["font_scale"] = json!(scale);
+            ui["font_size_increase"] = json!(increase);
+        }
+        Adaptation::ReduceChoices { max } => {
+            let current = ui["max_choices"].as_u64().unwrap_or(u64::MAX);
+            ui["max_choices"] = json!(current.min(*max as u64));
+        }
+        Adaptation::Animations { enabled } => ui["animations"] = json!(enabled),
+        Adaptation::ThrottleSuggestions { min_interval_secs } => {
+            let current = ui["suggestion_interval_secs"].as_u64().unwrap_or(0);
+            ui["suggestion_interval_secs"] = json!(current.max(*min_interval_secs));
+        }
+        Adaptation::Verbosity { verbosity } => ui["verbosity"] = json!(verbosity),
+        Adaptation::Pacing { pacing } => ui["pacing"] = json!(pacing),
+    }
+}
+
+// Apply every matching rule to `ui` in order; returns the log entries for the rules that fired.
+// Conditions see the state as updated by earlier rules (e.g. pacing set to gentle)
+pub fn apply(
+    rules: &RuleSet,
+    state: &serde_json::Value,
+    ui: &mut serde_json::Value,
+) -> Vec<LogEntry> {
+    let timestamp = clock::now();
+    let mut applied = Vec::new();
+    for rule in &rules.rules {
+        let mut view = state.clone();
+        if let (Some(view), Some(ui)) = (view.as_object_mut(), ui.as_object()) {
+            for (key, value) in ui {
+                view.insert(key.clone(), value.clone());
+            }
+        }
+        if rule.when.is_empty() || !rule.when.iter().all(|c| holds(c, &view)) {
+            continue;
+        }
+        for adaptation in &rule.then {
+            apply_one(adaptation, ui);
+        }
+        let because: serde_json::Map<String, serde_json::Value> = rule
+            .when
+            .iter()
+            .filter_map(|c| view.get(&c.metric).map(|v| (c.metric.clone(), v.clone())))
+            .collect();
+        applied.push(LogEntry {
+            timestamp,
+            rule: rule.id.clone(),
+            description: rule.description.clone(),
+            because: serde_json::Value::Object(because),
+        });
+    }
+    applied
+}
+
+pub fn log(entries: &[LogEntry]) -> anyhow::Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let mut log: Vec<LogEntry> = storage::load_data(LOG_FILE)?;
+    log.extend_from_slice(entries);
+    let excess = log.len().saturating_sub(MAX_LOG_ENTRIES);
+    log.drain(..excess);
+    storage::save_data(LOG_FILE, &log).map(|_| ())
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_adaptation_rules(app: AppHandle) -> RuleSet {
+    tasks::blocking_value(&app, "Get adaptation rules", |_| load()).await
+}
+
+#[tauri::command]
+pub async fn set_adaptation_rules(rules: RuleSet, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set adaptation rules", move |_| {
+        crate::respond(storage::save(RULES_FILE, &rules).map(|_| rules))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_adaptation_log(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Get adaptation log", |_| {
+        crate::respond(storage::load_data::<Vec<LogEntry>>(LOG_FILE))
+    })
+    .await
+}
@@ -0,0 +1,267 @@
+// Frustration estimate from how the user is typing and what keeps failing
+//
+// Signals come from the recent interaction history: the same request retried
+// in quick succession, bursts of backspaces while typing it, and commands that
+// keep failing. Only timings and counts are recorded, never the keys
+// themselves, and nothing here leaves the machine. When the user allows it,
+// the tone of voice requests counts too: higher, louder or faster than they
+// usually speak adds to frustration, and calmer than usual takes some away.
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::prosody::Prosody;
+use crate::{clock, fuzzy, tasks};
+
+// Interactions older than this don't say anything about the current mood
+const WINDOW_MS: u64 = 10 * 60 * 1000;
+const RETRY_GAP_MS: u64 = 30 * 1000;
+const RETRY_SIMILARITY: f32 = 0.8;
+// Voice requests needed before there is a "usual" to compare against
+const VOICE_BASELINE: usize = 3;
+// How much a calm voice can lower the estimate
+const CALM_WEIGHT: f32 = 0.2;
+
+// Keystroke metadata the frontend attaches to a typed request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KeystrokeTiming {
+    pub keystrokes: u32,
+    pub backspaces: u32,
+    // Longest run of consecutive backspaces
+    #[serde(default)]
+    pub max_backspace_run: u32,
+    // Milliseconds between keystrokes
+    #[serde(default)]
+    pub intervals_ms: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AffectLevel {
+    Calm,
+    Uneasy,
+    Frustrated,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Signal {
+    pub name: String,
+    // 0.0 (absent) to 1.0 (strong)
+    pub score: f32,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AffectState {
+    pub frustration: f32,
+    pub level: AffectLevel,
+    pub signals: Vec<Signal>,
+    pub sample_size: usize,
+}
+
+fn timestamp(interaction: &serde_json::Value) -> u64 {
+    interaction
+        .get("timestamp_ms")
+        .and_then(|t| t.as_u64())
+        .unwrap_or(0)
+}
+
+fn text(interaction: &serde_json::Value) -> &str {
+    ["query", "command", "text"]
+        .iter()
+        .find_map(|k| interaction.get(*k).and_then(|v| v.as_str()))
+        .unwrap_or("")
+}
+
+fn failed(interaction: &serde_json::Value) -> bool {
+    interaction.get("success").and_then(|s| s.as_bool()) == Some(false)
+}
+
+fn keystrokes(interaction: &serde_json::Value) -> Option<KeystrokeTiming> {
+    serde_json::from_value(interaction.get("keystrokes")?.clone()).ok()
+}
+
+// Near-identical requests sent again within half a minute
+fn rapid_retries(recent: &[&serde_json::Value]) -> Signal {
+    let retries = recent
+        .windows(2)
+        .filter(|pair| {
+            let (a, b) = (text(pair[0]), text(pair[1]));
+            !a.is_empty()
+                && timestamp(pair[1]).saturating_sub(timestamp(pair[0])) <= RETRY_GAP_MS
+                && (a == b || fuzzy::score(a, b) >= RETRY_SIMILARITY)
+        })
+        .count();
+    Signal {
+        name: "rapid_retries".to_string(),
+        score: (retries as f32 / 3.0).min(1.0),
+        detail: format!("{} quick retry(ies) of the same request", retries),
+    }
+}
+
+fn backspace_bursts(recent: &[&serde_json::Value]) -> Signal {
+    let timings: Vec<KeystrokeTiming> = recent.iter().filter_map(|i| keystrokes(i)).collect();
+    let (keys, backspaces) = timings
+        .iter()
+        .fold((0, 0), |(k, b), t| (k + t.keystrokes, b + t.backspaces));
+    let longest = timings
+        .iter()
+        .map(|t| t.max_backspace_run)
+        .max()
+        .unwrap_or(0);
+    let ratio = if keys > 0 {
+        backspaces as f32 / keys as f32
+    } else {
+        0.0
+    };
+    // A quarter of keystrokes being deletions, or wiping ten characters at once, is a lot
+    let score = (ratio / 0.25).max(longest as f32 / 10.0).min(1.0);
+    Signal {
+        name: "backspace_bursts".to_string(),
+        score,
+        detail: format!(
+            "{:.0}% of keystrokes were deletions, longest run {}",
+            ratio * 100.0,
+            longest
+        ),
+    }
+}
+
+fn repeated_failures(recent: &[&serde_json::Value]) -> Signal {
+    let failures = recent.iter().filter(|i| failed(i)).count();
+    let streak = recent.iter().rev().take_while(|i| failed(i)).count();
+    Signal {
+        name: "repeated_failures".to_string(),
+        score: (failures as f32 / 5.0).max(streak as f32 / 3.0).min(1.0),
+        detail: format!("{} failed, {} in a row just now", failures, streak),
+    }
+}
+
+// Uneven rhythm: bursts of fast typing broken by long stalls
+fn erratic_tempo(recent: &[&serde_json::Value]) -> Signal {
+    let intervals: Vec<u32> = recent
+        .iter()
+        .filter_map(|i| keystrokes(i))
+        .flat_map(|t| t.intervals_ms)
+        .filter(|ms| *ms > 0 && *ms < 5000)
+        .collect();
+    let score = if intervals.len() < 10 {
+        0.0
+    } else {
+        let mean = intervals.iter().sum::<u32>() as f32 / intervals.len() as f32;
+        let variance = intervals
+            .iter()
+            .map(|ms| (*ms as f32 - mean).powi(2))
+            .sum::<f32>()
+            / intervals.len() as f32;
+        // Coefficient of variation; steady typing stays well below 1
+        ((variance.sqrt() / mean - 0.8) / 1.2).clamp(0.0, 1.0)
+    };
+    Signal {
+        name: "erratic_tempo".to_string(),
+        score,
+        detail: format!("{} keystroke intervals", intervals.len()),
+    }
+}
+
+fn prosody(interaction: &serde_json::Value) -> Option<Prosody> {
+    serde_json::from_value(interaction.get("prosody")?.clone()).ok()
+}
+
+// How aroused recent voice requests sound next to this user's usual, from -1
+// (calmer) to 1 (more agitated)
+fn voice_arousal(history: &[serde_json::Value], recent: &[&serde_json::Value]) -> (f32, usize) {
+    let all: Vec<Prosody> = history
+        .iter()
+        .filter_map(prosody)
+        .filter(|p| p.speaking_secs > 0.0)
+        .collect();
+    let latest: Vec<Prosody> = recent
+        .iter()
+        .filter_map(|i| prosody(i))
+        .filter(|p| p.speaking_secs > 0.0)
+        .collect();
+    if all.len() < VOICE_BASELINE || latest.is_empty() {
+        return (0.0, latest.len());
+    }
+    let mean = |values: Vec<f32>| values.iter().sum::<f32>() / values.len().max(1) as f32;
+    let pitch = mean(all.iter().filter_map(|p| p.pitch_hz).collect());
+    let energy = mean(all.iter().map(|p| p.energy).collect());
+    let tempo = mean(all.iter().map(|p| p.tempo).collect());
+    // Relative change that counts as fully agitated, per feature
+    let change = |value: f32, usual: f32, full: f32| {
+        if usual > 0.0 {
+            ((value / usual - 1.0) / full).clamp(-1.0, 1.0)
+        } else {
+            0.0
+        }
+    };
+    let arousal = mean(
+        latest
+            .iter()
+            .map(|p| {
+                let pitch = p.pitch_hz.map(|hz| change(hz, pitch, 0.25)).unwrap_or(0.0);
+                (pitch + change(p.energy, energy, 0.6) + change(p.tempo, tempo, 0.3)) / 3.0
+            })
+            .collect(),
+    );
+    (arousal, latest.len())
+}
+
+fn voice_tone(arousal: f32, samples: usize) -> Signal {
+    Signal {
+        name: "voice_tone".to_string(),
+        score: arousal.max(0.0),
+        detail: match samples {
+            0 => "No voice requests".to_string(),
+            _ if arousal < 0.0 => format!("{} voice request(s), calmer than usual", samples),
+            _ => format!(
+                "{} voice request(s), tenser than usual ({:.2})",
+                samples, arousal
+            ),
+        },
+    }
+}
+
+pub fn assess(history: &[serde_json::Value]) -> AffectState {
+    let cutoff = clock::now_ms().saturating_sub(WINDOW_MS);
+    let recent: Vec<&serde_json::Value> =
+        history.iter().filter(|i| timestamp(i) >= cutoff).collect();
+    let (arousal, voice_samples) = voice_arousal(history, &recent);
+    let signals = vec![
+        rapid_retries(&recent),
+        backspace_bursts(&recent),
+        repeated_failures(&recent),
+        erratic_tempo(&recent),
+        voice_tone(arousal, voice_samples),
+    ];
+    const WEIGHTS: [f32; 5] = [0.3, 0.2, 0.4, 0.1, 0.2];
+    let calm = (-arousal).max(0.0) * CALM_WEIGHT;
+    let frustration = (signals
+        .iter()
+        .zip(WEIGHTS)
+        .map(|(s, w)| s.score * w)
+        .sum::<f32>()
+        - calm)
+        .clamp(0.0, 1.0);
+    AffectState {
+        frustration,
+        level: match frustration {
+            f if f >= 0.6 => AffectLevel::Frustrated,
+            f if f >= 0.3 => AffectLevel::Uneasy,
+            _ => AffectLevel::Calm,
+        },
+        signals,
+        sample_size: recent.len(),
+    }
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_affect_state(app: AppHandle) -> AffectState {
+    tasks::blocking_value(&app, "Get affect state", |state| {
+        assess(&state.interaction_history.blocking_lock())
+    })
+    .await
+}
@@ -0,0 +1,319 @@
+// User-defined aliases and learned shortcuts
+//
+// Aliases map a phrase either to a package ("my editor" -> neovim) or to a
+// saved list of intents ("update everything"). The parser consults them before
+// anything else. Phrasings that keep needing clarification and end up as the
+// same intent are proposed as new aliases.
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::AppHandle;
+
+use crate::nlp::{self, Intent};
+use crate::{storage, tasks};
+
+pub const ALIASES_FILE: &str = "aliases.json";
+pub const PHRASINGS_FILE: &str = "phrasings.json";
+// How often a phrasing has to resolve the same way before we suggest an alias
+const SUGGEST_AFTER: u32 = 3;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AliasTarget {
+    Package { name: String },
+    Intents { intents: Vec<Intent> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alias {
+    pub phrase: String,
+    pub target: AliasTarget,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Phrasing {
+    intent: Option<Intent>,
+    count: u32,
+    dismissed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AliasSuggestion {
+    pub phrase: String,
+    pub intent: Intent,
+    pub description: String,
+    pub times_seen: u32,
+}
+
+// What the aliases made of a query
+pub enum Expansion {
+    // The whole query is an alias for these intents
+    Intents(Vec<Intent>),
+    // Package aliases inside the query were replaced
+    Rewritten(String),
+    Unchanged,
+}
+
+pub fn list() -> Vec<Alias> {
+    storage::load(ALIASES_FILE).unwrap_or_default()
+}
+
+fn key(phrase: &str) -> String {
+    nlp::normalize(phrase)
+}
+
+pub fn add(phrase: &str, target: AliasTarget) -> anyhow::Result<Vec<Alias>> {
+    let phrase = key(phrase);
+    if phrase.is_empty() {
+        bail!("The alias phrase is empty");
+    }
+    match &target {
+        AliasTarget::Package { name } if name.trim().is_empty() => bail!("No package given"),
+        AliasTarget::Intents { intents } if intents.is_empty() => bail!("No actions given"),
+        _ => {}
+    }
+    let mut aliases = list();
+    aliases.retain(|a| a.phrase != phrase);
+    aliases.push(Alias { phrase, target });
+    set_all(aliases)
+}
+
+pub fn set_all(mut aliases: Vec<Alias>) -> anyhow::Result<Vec<Alias>> {
+    // Longest first, so "my work editor" wins over "my editor"
+    aliases.sort_by_key(|a| std::cmp::Reverse(a.phrase.len()));
+    storage::save(ALIASES_FILE, &aliases)?;
+    Ok(aliases)
+}
+
+pub fn remove(phrase: &str) -> anyhow::Result<Vec<Alias>> {
+    let phrase = key(phrase);
+    let mut aliases = list();
+    let before = aliases.len();
+    aliases.retain(|a| a.phrase != phrase);
+    if aliases.len() == before {
+        bail!("No alias named '{}'", phrase);
+    }
+    storage::save(ALIASES_FILE, &aliases)?;
+    Ok(aliases)
+}
+
+// Replace `phrase` in `text` when it appears as whole words
+fn replace_words(text: &str, phrase: &str, with: &str) -> Option<String> {
+    let padded = format!(" {} ", text);
+    let needle = format!(" {} ", phrase);
+    padded.contains(&needle).then(|| {
+        padded
+            .replace(&needle, &format!(" {} ", with))
+            .trim()
+            .to_string()
+    })
+}
+
+pub fn expand(query: &str) -> Expansion {
+    let aliases = list();
+    if aliases.is_empty() {
+        return Expansion::Unchanged;
+    }
+    let normalized = key(query);
+    if let Some(alias) = aliases.iter().find(|a| a.phrase == normalized) {
+        if let AliasTarget::Intents { intents } = &alias.target {
+            return Expansion::Intents(intents.clone());
+        }
+    }
+    let mut text = normalized.clone();
+    for alias in &aliases {
+        if let AliasTarget::Package { name } = &alias.target {
+            if let Some(rewritten) = replace_words(&text, &alias.phrase, name) {
+                text = rewritten;
+            }
+        }
+    }
+    if text == normalized {
+        Expansion::Unchanged
+    } else {
+        Expansion::Rewritten(text)
+    }
+}
+
+// Note which intent a phrasing ended up as
+pub fn observe(query: &str, intent: &Intent) -> anyhow::Result<()> {
+    if matches!(intent, Intent::Unknown) {
+        return Ok(());
+    }
+    let phrase = key(query);
+    let mut phrasings: BTreeMap<String, Phrasing> = storage::load_data(PHRASINGS_FILE)?;
+    let entry = phrasings.entry(phrase).or_default();
+    if entry.intent.as_ref() == Some(intent) {
+        entry.count += 1;
+    } else {
+        entry.intent = Some(intent.clone());
+        entry.count = 1;
+    }
+    storage::save_data(PHRASINGS_FILE, &phrasings)?;
+    Ok(())
+}
+
+pub fn suggestions() -> Vec<AliasSuggestion> {
+    let phrasings: BTreeMap<String, Phrasing> =
+        storage::load_data(PHRASINGS_FILE).unwrap_or_default();
+    let existing = list();
+    phrasings
+        .into_iter()
+        .filter(|(phrase, p)| {
+            p.count >= SUGGEST_AFTER
+                && !p.dismissed
+                && !existing.iter().any(|a| &a.phrase == phrase)
+        })
+        .filter_map(|(phrase, p)| {
+            let intent = p.intent?;
+            Some(AliasSuggestion {
+                description: intent.describe(),
+                phrase,
+                intent,
+                times_seen: p.count,
+            })
+        })
+        .collect()
+}
+
+pub fn accept_suggestion(phrase: &str) -> anyhow::Result<Vec<Alias>> {
+    let Some(suggestion) = suggestions().into_iter().find(|s| s.phrase == key(phrase)) else {
+        bail!("No alias suggestion for '{}'", phrase);
+    };
+    add(
+        &suggestion.phrase,
+        AliasTarget::Intents {
+            intents: vec![suggestion.intent],
+        },
+    )
+}
+
+pub fn dismiss_suggestion(phrase: &str) -> anyhow::Result<()> {
+    let mut phrasings: BTreeMap<String, Phrasing> = storage::load_data(PHRASINGS_FILE)?;
+    if let Some(entry) = phrasings.get_mut(&key(phrase)) {
+        entry.dismissed = true;
+    }
+    storage::save_data(PHRASINGS_FILE, &phrasings)?;
+    Ok(())
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn list_aliases(app: AppHandle) -> Vec<Alias> {
+    tasks::blocking_value(&app, "List aliases", |_| list()).await
+}
+
+#[tauri::command]
+pub async fn add_alias(phrase: String, target: AliasTarget, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Add alias", move |_| {
+        crate::respond(add(&phrase, target))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn remove_alias(phrase: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Remove alias", move |_| {
+        crate::respond(remove(&phrase))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_alias_suggestions(app: AppHandle) -> Vec<AliasSuggestion> {
+    tasks::blocking_value(&app, "Get alias suggestions", |_| suggestions()).await
+}
+
+#[tauri::command]
+pub async fn accept_alias_suggestion(phrase: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Accept alias suggestion", move |_| {
+        crate::respond(accept_suggestion(&phrase))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn dismiss_alias_suggestion(phrase: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Dismiss alias suggestion", move |_| {
+        crate::respond(dismiss_suggestion(&phrase))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str) -> AliasTarget {
+        AliasTarget::Package {
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn package_aliases_replace_whole_words_only() {
+        storage::with_root(&storage::scratch_dir("aliases"), || {
+            add("my editor", package("neovim")).unwrap();
+            add("my work editor", package("vscode")).unwrap();
+            assert!(matches!(
+                expand("install my work editor"),
+                Expansion::Rewritten(text) if text == "install vscode"
+            ));
+            assert!(matches!(
+                expand("Install my editor!"),
+                Expansion::Rewritten(text) if text == "install neovim"
+            ));
+            assert!(matches!(expand("install my editors"), Expansion::Unchanged));
+        });
+    }
+
+    #[test]
+    fn a_whole_query_alias_becomes_its_intents() {
+        storage::with_root(&storage::scratch_dir("aliases"), || {
+            let intents = vec![Intent::Update, Intent::GarbageCollect];
+            add(
+                "tidy up",
+                AliasTarget::Intents {
+                    intents: intents.clone(),
+                },
+            )
+            .unwrap();
+            assert!(matches!(expand("tidy up"), Expansion::Intents(found) if found == intents));
+            remove("tidy up").unwrap();
+            assert!(matches!(expand("tidy up"), Expansion::Unchanged));
+            assert!(remove("tidy up").is_err());
+        });
+    }
+
+    #[test]
+    fn empty_aliases_are_refused() {
+        storage::with_root(&storage::scratch_dir("aliases"), || {
+            assert!(add("  ", package("neovim")).is_err());
+            assert!(add("my editor", package(" ")).is_err());
+            let nothing = AliasTarget::Intents {
+                intents: Vec::new(),
+            };
+            assert!(add("nothing", nothing).is_err());
+            assert!(list().is_empty());
+        });
+    }
+
+    #[test]
+    fn phrasings_are_suggested_after_repeating() {
+        storage::with_root(&storage::scratch_dir("aliases"), || {
+            for _ in 0..SUGGEST_AFTER - 1 {
+                observe("freshen things up", &Intent::Update).unwrap();
+            }
+            assert!(suggestions().is_empty());
+            observe("freshen things up", &Intent::Update).unwrap();
+            let suggested = suggestions();
+            assert_eq!(suggested.len(), 1);
+            assert_eq!(suggested[0].times_seen, SUGGEST_AFTER);
+
+            dismiss_suggestion("freshen things up").unwrap();
+            assert!(suggestions().is_empty());
+        });
+    }
+}
@@ -0,0 +1,255 @@
+// The workspace survives restarts, and crashes
+//
+// The open components, the current layout and the user profile are written to
+// the data dir once they have been still for a couple of seconds (at most every
+// fifteen while they keep changing), and the next start picks up from there.
+// While it runs, each instance keeps a marker file that goes away when it exits
+// normally; a marker whose process is gone means that session crashed. Its
+// workspace isn't brought back unasked then, as it may be what made it crash:
+// it is set aside and offered as "session-recovery" to restore or discard.
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::userprofile::UserProfile;
+use crate::{clock, panels, sessions, storage, tasks, AppState, ComponentState, Layout};
+
+pub const SNAPSHOT_FILE: &str = "workspace.json";
+pub const RECOVERY_FILE: &str = "workspace-recovery.json";
+const RUNNING_PREFIX: &str = "running-";
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+// Quiet this long before a change is written...
+const DEBOUNCE_MS: u64 = 2 * 1000;
+// ...unless changes have kept coming for this long
+const MAX_DELAY_MS: u64 = 15 * 1000;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Snapshot {
+    pub saved_at: u64,
+    pub components: Vec<ComponentState>,
+    pub current_layout: Option<Layout>,
+    pub user_profile: Option<UserProfile>,
+}
+
+// What the crashed session had open, for the offer to restore it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveryOffer {
+    pub saved_at: u64,
+    pub layout: Option<String>,
+    // Component types, in order
+    pub components: Vec<String>,
+}
+
+fn marker(pid: u32) -> PathBuf {
+    storage::data_dir().join(format!("{}{}", RUNNING_PREFIX, pid))
+}
+
+fn recovery_path() -> PathBuf {
+    storage::data_dir().join(RECOVERY_FILE)
+}
+
+// Clears the markers of processes that are gone; any of them means a crash.
+// One with our own pid was left by an earlier process the pid was reused from.
+fn clear_stale_markers() -> bool {
+    let Ok(entries) = fs::read_dir(storage::data_dir()) else {
+        return false;
+    };
+    let mut crashed = false;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(pid) = name
+            .strip_prefix(RUNNING_PREFIX)
+            .and_then(|pid| pid.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        if pid == std::process::id() || !sessions::alive(pid) {
+            crashed = true;
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+    crashed
+}
+
+// Called once, before the state is built: the workspace to start from when
+// the last session ended normally
+pub fn start_session() -> Option<Snapshot> {
+    let crashed = clear_stale_markers();
+    if let Err(e) = storage::write_json(&marker(std::process::id()), sessions::current()) {
+        eprintln!("Could not mark the session as running: {}", e);
+    }
+    let saved = storage::data_dir().join(SNAPSHOT_FILE);
+    if !saved.exists() {
+        return None;
+    }
+    if crashed {
+        if let Err(e) = fs::rename(&saved, recovery_path()) {
+            eprintln!("Could not keep the crashed session for recovery: {}", e);
+        }
+        return None;
+    }
+    storage::read_json(&saved)
+        .map_err(|e| eprintln!("Could not restore the last session: {}", e))
+        .ok()
+}
+
+fn snapshot(state: &AppState) -> Snapshot {
+    Snapshot {
+        saved_at: 0,
+        components: state.components.blocking_lock().clone(),
+        current_layout: state.current_layout.blocking_lock().clone(),
+        user_profile: state.user_profile.blocking_lock().clone(),
+    }
+}
+
+// The profile is saved on every change by itself; the snapshot's only wins
+// when it is newer
+pub fn apply(state: &AppState, snapshot: Snapshot) {
+    *state.components.blocking_lock() = snapshot.components;
+    *state.current_layout.blocking_lock() = snapshot.current_layout;
+    let mut profile = state.user_profile.blocking_lock();
+    if let Some(saved) = snapshot.user_profile {
+        if profile
+            .as_ref()
+            .is_none_or(|p| p.updated_at < saved.updated_at)
+        {
+            *profile = Some(saved);
+        }
+    }
+}
+
+fn save(state: &AppState) -> anyhow::Result<()> {
+    let mut snapshot = snapshot(state);
+    snapshot.saved_at = clock::now_ms();
+    storage::save_data(SNAPSHOT_FILE, &snapshot)?;
+    Ok(())
+}
+
+pub fn start(app: AppHandle) {
+    std::thread::spawn(move || {
+        // The snapshot on disk and the one seen last, serialized
+        let mut saved: Option<String> = None;
+        let mut seen: Option<String> = None;
+        let mut changed_ms = 0;
+        let mut dirty_since: Option<u64> = None;
+        loop {
+            std::thread::sleep(CHECK_INTERVAL);
+            let snapshot = snapshot(&app.state::<AppState>());
+            let Ok(current) = serde_json::to_string(&snapshot) else {
+                continue;
+            };
+            let now = clock::now_ms();
+            if seen.as_ref() != Some(&current) {
+                changed_ms = now;
+                seen = Some(current.clone());
+            }
+            if saved.as_ref() == Some(&current) {
+                dirty_since = None;
+                continue;
+            }
+            let dirty_ms = now.saturating_sub(*dirty_since.get_or_insert(now));
+            if now.saturating_sub(changed_ms) < DEBOUNCE_MS && dirty_ms < MAX_DELAY_MS {
+                continue;
+            }
+            let written = storage::save_data(
+                SNAPSHOT_FILE,
+                &Snapshot {
+                    saved_at: now,
+                    ..snapshot
+                },
+            );
+            match written {
+                Ok(_) => {
+                    saved = Some(current);
+                    dirty_since = None;
+                }
+                Err(e) => eprintln!("Could not save the workspace: {}", e),
+            }
+        }
+    });
+}
+
+// On a normal exit: the latest workspace, and no marker left to look like a crash
+pub fn end_session(app: &AppHandle) {
+    if let Err(e) = save(&app.state::<AppState>()) {
+        eprintln!("Could not save the workspace: {}", e);
+    }
+    let _ = fs::remove_file(marker(std::process::id()));
+}
+
+fn offer(snapshot: &Snapshot) -> RecoveryOffer {
+    RecoveryOffer {
+        saved_at: snapshot.saved_at,
+        layout: snapshot.current_layout.as_ref().map(|l| l.name.clone()),
+        components: snapshot
+            .components
+            .iter()
+            .map(|c| c.component_type.clone())
+            .collect(),
+    }
+}
+
+// The crashed session waiting to be restored or discarded
+pub fn recovery() -> Option<RecoveryOffer> {
+    let path = recovery_path();
+    if !path.exists() {
+        return None;
+    }
+    match storage::read_json::<Snapshot>(&path) {
+        Ok(snapshot) => Some(offer(&snapshot)),
+        Err(e) => {
+            eprintln!("Could not read the crashed session: {}", e);
+            None
+        }
+    }
+}
+
+fn recover(state: &AppState) -> anyhow::Result<RecoveryOffer> {
+    let path = recovery_path();
+    if !path.exists() {
+        bail!("There is no crashed session to restore");
+    }
+    let snapshot: Snapshot = storage::read_json(&path)?;
+    let offer = offer(&snapshot);
+    apply(state, snapshot);
+    fs::remove_file(&path).with_context(|| format!("removing {}", path.display()))?;
+    Ok(offer)
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_session_recovery(app: AppHandle) -> Option<RecoveryOffer> {
+    tasks::blocking_value(&app, "Get session recovery", |_| recovery()).await
+}
+
+#[tauri::command]
+pub async fn restore_last_session(app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Restore last session", move |state| {
+        let response = crate::respond(recover(state));
+        // Panels follow the restored components
+        panels::reconcile(&handle, &state.components.blocking_lock());
+        response
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn discard_last_session(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Discard last session", |_| {
+        let path = recovery_path();
+        let result = if path.exists() {
+            fs::remove_file(&path).with_context(|| format!("removing {}", path.display()))
+        } else {
+            Ok(())
+        };
+        crate::respond(result)
+    })
+    .await
+}
@@ -0,0 +1,294 @@
+// Compound requests executed as an ordered plan
+//
+// "install firefox and vim then run garbage collection" becomes a plan that
+// is shown for confirmation first, then runs step by step on a worker thread
+// as one "plan" operation, reporting each step and stopping at the first
+// failure. Steps that restart services wait for a maintenance window like any
+// other request.
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::nlp::{self, Intent, ParsedIntent};
+use crate::progress::{self, StepStatus};
+use crate::safety::{self, RiskSummary};
+use crate::{clock, tasks, tone, AppState};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanStep {
+    pub index: usize,
+    pub intent: Intent,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Plan {
+    pub id: String,
+    pub query: String,
+    pub steps: Vec<PlanStep>,
+    // Steps the parser could not make sense of; the plan can't run with these
+    pub unparsed: Vec<String>,
+    #[serde(default)]
+    pub risk: RiskSummary,
+}
+
+// Where a plan stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Outcome {
+    completed: usize,
+    failed_at: Option<usize>,
+    cancelled: bool,
+}
+
+fn plan_id() -> String {
+    format!("plan-{}", clock::now_ms())
+}
+
+// A plan for the query, or None when it is a single step
+pub fn plan(query: &str) -> Option<Plan> {
+    let segments = nlp::split_steps(query);
+    if segments.len() < 2 {
+        return None;
+    }
+    let mut steps = Vec::new();
+    let mut unparsed = Vec::new();
+    for segment in segments {
+        let ParsedIntent { intent, .. } = nlp::parse(&segment);
+        if intent == Intent::Unknown {
+            unparsed.push(segment);
+            continue;
+        }
+        steps.push(PlanStep {
+            index: steps.len(),
+            description: intent.describe(),
+            intent,
+        });
+    }
+    let intents: Vec<Intent> = steps.iter().map(|s| s.intent.clone()).collect();
+    Some(Plan {
+        id: plan_id(),
+        query: query.to_string(),
+        steps,
+        unparsed,
+        risk: safety::assess(&intents),
+    })
+}
+
+// A plan from intents that are already known, e.g. a saved alias
+pub fn from_intents(query: &str, intents: Vec<Intent>) -> Plan {
+    let risk = safety::assess(&intents);
+    Plan {
+        id: plan_id(),
+        query: query.to_string(),
+        steps: intents
+            .into_iter()
+            .enumerate()
+            .map(|(index, intent)| PlanStep {
+                index,
+                description: intent.describe(),
+                intent,
+            })
+            .collect(),
+        unparsed: Vec::new(),
+        risk,
+    }
+}
+
+fn report(
+    reporter: &progress::Reporter,
+    plan: &Plan,
+    index: usize,
+    status: StepStatus,
+    result: Option<serde_json::Value>,
+) {
+    let message = match status {
+        StepStatus::Running => Some(tone::progress(
+            tone::current(),
+            &plan.steps[index].description,
+        )),
+        _ => result
+            .as_ref()
+            .and_then(|r| r.get("message"))
+            .and_then(|m| m.as_str())
+            .map(String::from),
+    };
+    reporter.step(index, status, message, result);
+}
+
+// Run the steps in order through `dispatch`; stop at the first failure
+fn run_steps(
+    plan: &Plan,
+    reporter: &mut progress::Reporter,
+    mut dispatch: impl FnMut(&Intent) -> serde_json::Value,
+) -> Outcome {
+    let mut outcome = Outcome {
+        completed: 0,
+        failed_at: None,
+        cancelled: false,
+    };
+    for step in &plan.steps {
+        // Cancelling lets the running step finish; the rest are skipped
+        outcome.cancelled |= reporter.is_cancelled();
+        if outcome.failed_at.is_some() || outcome.cancelled {
+            report(reporter, plan, step.index, StepStatus::Skipped, None);
+            continue;
+        }
+        reporter.advance(step.index as u64, &step.description);
+        report(reporter, plan, step.index, StepStatus::Running, None);
+        let result = dispatch(&step.intent);
+        let succeeded = result.get("success").and_then(|s| s.as_bool()) == Some(true);
+        let staged = result.get("staged").and_then(|s| s.as_bool()) == Some(true);
+        let status = if staged {
+            StepStatus::Staged
+        } else if succeeded {
+            outcome.completed += 1;
+            StepStatus::Succeeded
+        } else {
+            outcome.failed_at = Some(step.index);
+            StepStatus::Failed
+        };
+        report(reporter, plan, step.index, status, Some(result));
+    }
+    outcome
+}
+
+// Run the steps on a worker thread as a "plan" operation, whose id this
+// returns; its Step events follow each step
+pub fn execute(plan: Plan, options: serde_json::Value, app: AppHandle) -> String {
+    let mut reporter =
+        progress::Reporter::start("plan", &plan.query, Some(plan.steps.len() as u64), true);
+    let id = reporter.id().to_string();
+    std::thread::spawn(move || {
+        let state = app.state::<AppState>();
+        let outcome = run_steps(&plan, &mut reporter, |intent| {
+            crate::dispatch(intent.clone(), &options, &state)
+        });
+        match outcome.failed_at {
+            Some(index) => reporter.fail(&format!("Step {} failed", index + 1)),
+            None if outcome.cancelled => reporter.cancel("Stopped before the remaining steps"),
+            None => reporter.complete(
+                &plan.query,
+                &serde_json::json!({"plan_id": plan.id, "completed": outcome.completed}),
+            ),
+        }
+    });
+    id
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn plan_query(query: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Plan query", move |_| match plan(&query) {
+        Some(plan) => serde_json::json!({"success": true, "data": plan}),
+        None => serde_json::json!({"success": false, "error": "This request has only one step"}),
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn execute_plan(
+    plan: Plan,
+    options: Option<serde_json::Value>,
+    app: AppHandle,
+) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Execute plan", move |state| {
+        if !plan.unparsed.is_empty() {
+            return serde_json::json!({
+                "success": false,
+                "error": format!("Some steps were not understood: {}", plan.unparsed.join("; ")),
+            });
+        }
+        // One confirmation covers every step that needs it
+        let options = options.unwrap_or_default();
+        let intents: Vec<Intent> = plan.steps.iter().map(|s| s.intent.clone()).collect();
+        if let Err(response) = crate::admit(&intents, &options, state) {
+            return response;
+        }
+        let plan_id = plan.id.clone();
+        let operation_id = execute(plan, options, handle);
+        serde_json::json!({
+            "success": true,
+            "data": {"plan_id": plan_id, "operation_id": operation_id},
+        })
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage;
+    use serde_json::json;
+
+    fn install(package: &str) -> Intent {
+        Intent::Install {
+            packages: vec![package.to_string()],
+        }
+    }
+
+    // Runs `results` in order as the steps' responses, returning the outcome
+    // and the intents that were dispatched
+    fn run(results: Vec<serde_json::Value>, cancel: bool) -> (Outcome, Vec<Intent>) {
+        storage::with_root(&storage::scratch_dir("batch"), || {
+            let intents = vec![install("firefox"), install("vim"), Intent::GarbageCollect];
+            let plan = from_intents("install firefox and vim then collect garbage", intents);
+            let mut reporter = progress::Reporter::start("plan", &plan.query, Some(3), true);
+            if cancel {
+                progress::cancel(reporter.id());
+            }
+            let mut results = results.into_iter();
+            let mut dispatched = Vec::new();
+            let outcome = run_steps(&plan, &mut reporter, |intent| {
+                dispatched.push(intent.clone());
+                results.next().unwrap()
+            });
+            reporter.succeed("Done");
+            (outcome, dispatched)
+        })
+    }
+
+    #[test]
+    fn every_step_runs_in_order() {
+        let ok = json!({"success": true});
+        let (outcome, dispatched) = run(vec![ok.clone(), ok.clone(), ok], false);
+        assert_eq!(
+            outcome,
+            Outcome {
+                completed: 3,
+                failed_at: None,
+                cancelled: false,
+            }
+        );
+        assert_eq!(dispatched[0], install("firefox"));
+        assert_eq!(dispatched[2], Intent::GarbageCollect);
+    }
+
+    #[test]
+    fn the_first_failure_skips_the_rest() {
+        let results = vec![json!({"success": true}), json!({"success": false})];
+        let (outcome, dispatched) = run(results, false);
+        assert_eq!(outcome.completed, 1);
+        assert_eq!(outcome.failed_at, Some(1));
+        assert_eq!(dispatched.len(), 2);
+    }
+
+    #[test]
+    fn staged_steps_neither_count_nor_stop_the_plan() {
+        let staged = json!({"success": true, "staged": true});
+        let ok = json!({"success": true});
+        let (outcome, dispatched) = run(vec![ok.clone(), staged, ok], false);
+        assert_eq!(outcome.completed, 2);
+        assert_eq!(outcome.failed_at, None);
+        assert_eq!(dispatched.len(), 3);
+    }
+
+    #[test]
+    fn a_cancelled_plan_runs_no_more_steps() {
+        let (outcome, dispatched) = run(Vec::new(), true);
+        assert!(outcome.cancelled);
+        assert_eq!(outcome.completed, 0);
+        assert!(dispatched.is_empty());
+    }
+}
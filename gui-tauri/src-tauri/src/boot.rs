@@ -0,0 +1,378 @@
+// Boot entry and specialisation manager
+//
+// Lists systemd-boot/GRUB entries alongside the system generations they boot,
+// marks a generation as the default, deletes old generations (which removes
+// their boot entries) and switches between specialisations. The changes are
+// intents, so they go through the same confirmation policy as typed requests.
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::AppHandle;
+
+use crate::nlp::Intent;
+use crate::{system, tasks};
+
+const PROFILES_DIR: &str = "/nix/var/nix/profiles";
+const SYSTEMD_BOOT_ENTRIES: &str = "/boot/loader/entries";
+const GRUB_CONFIG: &str = "/boot/grub/grub.cfg";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Bootloader {
+    SystemdBoot,
+    Grub,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Generation {
+    pub number: u32,
+    pub path: PathBuf,
+    pub created: u64,
+    pub current: bool,
+    pub booted: bool,
+    pub specialisations: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootEntry {
+    pub id: String,
+    pub title: String,
+    pub generation: Option<u32>,
+    pub specialisation: Option<String>,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootOverview {
+    pub bootloader: Bootloader,
+    pub entries: Vec<BootEntry>,
+    pub generations: Vec<Generation>,
+    pub active_specialisation: Option<String>,
+}
+
+pub fn detect_bootloader() -> Bootloader {
+    if Path::new("/boot/loader/loader.conf").exists() {
+        Bootloader::SystemdBoot
+    } else if Path::new(GRUB_CONFIG).exists() {
+        Bootloader::Grub
+    } else {
+        Bootloader::Unknown
+    }
+}
+
+fn canonical(path: &Path) -> Option<PathBuf> {
+    fs::canonicalize(path).ok()
+}
+
+fn specialisations_of(system_path: &Path) -> Vec<String> {
+    fs::read_dir(system_path.join("specialisation"))
+        .map(|entries| {
+            let mut names: Vec<String> = entries
+                .flatten()
+                .map(|e| e.file_name().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            names
+        })
+        .unwrap_or_default()
+}
+
+pub fn list_generations() -> Vec<Generation> {
+    let current = canonical(&Path::new(PROFILES_DIR).join("system"));
+    let booted = canonical(Path::new("/run/booted-system"));
+    let Ok(entries) = fs::read_dir(PROFILES_DIR) else {
+        return Vec::new();
+    };
+
+    let mut generations: Vec<Generation> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let number = name
+                .strip_prefix("system-")?
+                .strip_suffix("-link")?
+                .parse()
+                .ok()?;
+            let path = entry.path();
+            let target = canonical(&path);
+            let created = fs::symlink_metadata(&path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            Some(Generation {
+                number,
+                created,
+                current: target.is_some() && target == current,
+                booted: target.is_some() && target == booted,
+                specialisations: specialisations_of(&path),
+                path,
+            })
+        })
+        .collect();
+    generations.sort_by_key(|g| g.number);
+    generations
+}
+
+// "nixos-generation-42-specialisation-gaming.conf" -> (Some(42), Some("gaming"))
+fn parse_entry_id(id: &str) -> (Option<u32>, Option<String>) {
+    let Some(rest) = id
+        .trim_end_matches(".conf")
+        .strip_prefix("nixos-generation-")
+    else {
+        return (None, None);
+    };
+    match rest.split_once("-specialisation-") {
+        Some((number, name)) => (number.parse().ok(), Some(name.to_string())),
+        None => (rest.parse().ok(), None),
+    }
+}
+
+fn systemd_boot_default() -> Option<String> {
+    let output = system::run("bootctl", &["status", "--no-pager"]).ok()?;
+    output
+        .lines()
+        .map(str::trim)
+        .find_map(|l| {
+            l.strip_prefix("Default Boot Loader Entry:")
+                .or(l.strip_prefix("Default:"))
+        })
+        .map(|id| id.trim().to_string())
+        .or_else(|| {
+            let conf = fs::read_to_string("/boot/loader/loader.conf").ok()?;
+            conf.lines()
+                .find_map(|l| l.strip_prefix("default"))
+                .map(|v| v.trim().to_string())
+        })
+}
+
+fn systemd_boot_entries() -> Vec<BootEntry> {
+    let default = systemd_boot_default();
+    let Ok(entries) = fs::read_dir(SYSTEMD_BOOT_ENTRIES) else {
+        return Vec::new();
+    };
+    let mut result: Vec<BootEntry> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let id = entry.file_name().to_string_lossy().into_owned();
+            let contents = fs::read_to_string(entry.path()).ok()?;
+            let title = contents
+                .lines()
+                .find_map(|l| l.strip_prefix("title"))
+                .map(|t| t.trim().to_string())
+                .unwrap_or_else(|| id.clone());
+            let version = contents
+                .lines()
+                .find_map(|l| l.strip_prefix("version"))
+                .map(|v| format!(" ({})", v.trim()))
+                .unwrap_or_default();
+            let (generation, specialisation) = parse_entry_id(&id);
+            Some(BootEntry {
+                is_default: default
+                    .as_deref()
+                    .is_some_and(|d| d == id || d == id.trim_end_matches(".conf")),
+                title: format!("{}{}", title, version),
+                id,
+                generation,
+                specialisation,
+            })
+        })
+        .collect();
+    result.sort_by_key(|e| std::cmp::Reverse(e.generation));
+    result
+}
+
+fn grub_entries() -> Vec<BootEntry> {
+    let Ok(config) = fs::read_to_string(GRUB_CONFIG) else {
+        return Vec::new();
+    };
+    config
+        .lines()
+        .map(str::trim)
+        .filter(|l| l.starts_with("menuentry "))
+        .enumerate()
+        .map(|(index, line)| {
+            let title = line.split('"').nth(1).unwrap_or(line).to_string();
+            // NixOS titles look like "NixOS - Configuration 42 (2024-05-01 - 24.05)"
+            let generation = title
+                .split("Configuration ")
+                .nth(1)
+                .and_then(|rest| rest.split_whitespace().next())
+                .and_then(|n| n.parse().ok());
+            BootEntry {
+                id: index.to_string(),
+                is_default: index == 0,
+                title,
+                generation,
+                specialisation: None,
+            }
+        })
+        .collect()
+}
+
+fn active_specialisation() -> Option<String> {
+    let current = canonical(Path::new("/run/current-system"))?;
+    list_generations().into_iter().find_map(|g| {
+        g.specialisations
+            .iter()
+            .find(|name| {
+                canonical(&g.path.join("specialisation").join(name)) == Some(current.clone())
+            })
+            .cloned()
+    })
+}
+
+pub fn overview() -> BootOverview {
+    let bootloader = detect_bootloader();
+    let entries = match bootloader {
+        Bootloader::SystemdBoot => systemd_boot_entries(),
+        Bootloader::Grub => grub_entries(),
+        Bootloader::Unknown => Vec::new(),
+    };
+    BootOverview {
+        bootloader,
+        entries,
+        generations: list_generations(),
+        active_specialisation: active_specialisation(),
+    }
+}
+
+fn find_generation(number: u32) -> anyhow::Result<Generation> {
+    list_generations()
+        .into_iter()
+        .find(|g| g.number == number)
+        .ok_or_else(|| anyhow!("System generation {} does not exist", number))
+}
+
+// Make a generation the default boot entry without activating it now
+pub fn set_default_generation(number: u32) -> anyhow::Result<Generation> {
+    let generation = find_generation(number)?;
+    let script = generation.path.join("bin/switch-to-configuration");
+    system::run_privileged(&script.to_string_lossy(), &["boot"])?;
+    Ok(generation)
+}
+
+// Delete generations (never the current or booted one) and refresh boot entries
+pub fn delete_generations(numbers: &[u32]) -> anyhow::Result<Vec<u32>> {
+    if numbers.is_empty() {
+        bail!("No generations selected");
+    }
+    let generations = list_generations();
+    for number in numbers {
+        let generation = generations
+            .iter()
+            .find(|g| g.number == *number)
+            .ok_or_else(|| anyhow!("System generation {} does not exist", number))?;
+        if generation.current || generation.booted {
+            bail!(
+                "Generation {} is in use and cannot be deleted; roll back or reboot first",
+                number
+            );
+        }
+    }
+
+    let profile = format!("{}/system", PROFILES_DIR);
+    let ids: Vec<String> = numbers.iter().map(u32::to_string).collect();
+    let mut args = vec!["--profile", profile.as_str(), "--delete-generations"];
+    args.extend(ids.iter().map(String::as_str));
+    system::run_privileged("nix-env", &args)?;
+
+    // Regenerate the bootloader menu so the deleted entries disappear
+    system::run_privileged("/run/current-system/bin/switch-to-configuration", &["boot"])?;
+    Ok(numbers.to_vec())
+}
+
+// Activate an older generation now, or the previous one when none is given
+pub fn rollback(number: Option<u32>) -> anyhow::Result<Option<u32>> {
+    match number {
+        Some(number) => {
+            let generation = find_generation(number)?;
+            let profile = format!("{}/system", PROFILES_DIR);
+            let id = number.to_string();
+            system::run_privileged(
+                "nix-env",
+                &["--profile", profile.as_str(), "--switch-generation", &id],
+            )?;
+            let script = generation.path.join("bin/switch-to-configuration");
+            system::run_privileged(&script.to_string_lossy(), &["switch"])?;
+        }
+        None => {
+            system::run_privileged("nixos-rebuild", &["switch", "--rollback"])?;
+        }
+    }
+    Ok(list_generations()
+        .into_iter()
+        .find(|g| g.current)
+        .map(|g| g.number))
+}
+
+// Switch the running system into a specialisation, or back to the base config
+pub fn switch_specialisation(name: Option<&str>) -> anyhow::Result<()> {
+    let current = list_generations()
+        .into_iter()
+        .find(|g| g.current)
+        .ok_or_else(|| anyhow!("Could not determine the current system generation"))?;
+    let target = match name {
+        Some(name) => {
+            if !current.specialisations.iter().any(|s| s == name) {
+                bail!("The current system has no specialisation named '{}'", name);
+            }
+            current.path.join("specialisation").join(name)
+        }
+        None => current.path.clone(),
+    };
+    let script = target.join("bin/switch-to-configuration");
+    system::run_privileged(&script.to_string_lossy(), &["switch"])?;
+    Ok(())
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn list_boot_entries(app: AppHandle) -> BootOverview {
+    tasks::blocking_value(&app, "List boot entries", |_| overview()).await
+}
+
+// `options` carries the confirmation token and phrase once they were asked for
+async fn run(
+    app: AppHandle,
+    intent: Intent,
+    options: Option<serde_json::Value>,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, &intent.describe(), move |state| {
+        crate::run_intent(intent, &options.unwrap_or_default(), state)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn set_default_boot_generation(
+    generation: u32,
+    options: Option<serde_json::Value>,
+    app: AppHandle,
+) -> serde_json::Value {
+    run(app, Intent::SetBootDefault { generation }, options).await
+}
+
+#[tauri::command]
+pub async fn delete_boot_generations(
+    generations: Vec<u32>,
+    options: Option<serde_json::Value>,
+    app: AppHandle,
+) -> serde_json::Value {
+    run(app, Intent::DeleteGenerations { generations }, options).await
+}
+
+#[tauri::command]
+pub async fn switch_to_specialisation(
+    name: Option<String>,
+    options: Option<serde_json::Value>,
+    app: AppHandle,
+) -> serde_json::Value {
+    run(app, Intent::SwitchSpecialisation { name }, options).await
+}
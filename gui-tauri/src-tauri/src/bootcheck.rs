@@ -0,0 +1,318 @@
+// Failed-boot detection
+//
+// At startup, checks whether the machine came up on an older generation than
+// the newest one, whether the previous boot ended abruptly, and what the
+// journal says went wrong — then explains it in plain language with the
+// matching recovery walkthrough.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tauri::AppHandle;
+
+use crate::{boot, storage, system, tasks};
+
+const ACK_FILE: &str = "boot-check.json";
+const SYSTEMD_BOOT_ENTRIES: &str = "/boot/loader/entries";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    OlderGenerationBooted,
+    EntryMarkedBad,
+    UncleanShutdown,
+    KernelPanic,
+    FilesystemErrors,
+    GraphicsErrors,
+    OutOfMemory,
+    FailedUnits,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Finding {
+    pub kind: FindingKind,
+    pub title: String,
+    pub explanation: String,
+    pub walkthrough: Vec<String>,
+    pub evidence: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootReport {
+    pub boot_id: String,
+    pub booted_generation: Option<u32>,
+    pub latest_generation: Option<u32>,
+    pub findings: Vec<Finding>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Acknowledged {
+    boot_id: String,
+}
+
+fn finding(kind: FindingKind, title: &str, explanation: String, walkthrough: &[&str]) -> Finding {
+    Finding {
+        kind,
+        title: title.to_string(),
+        explanation,
+        walkthrough: walkthrough.iter().map(|s| s.to_string()).collect(),
+        evidence: Vec::new(),
+    }
+}
+
+pub fn boot_id() -> String {
+    fs::read_to_string("/proc/sys/kernel/random/boot_id")
+        .map(|id| id.trim().to_string())
+        .unwrap_or_default()
+}
+
+// Messages from the previous boot at the given priority range ("0..3")
+fn previous_boot(priority: Option<&str>, lines: &str) -> Vec<String> {
+    let mut args = vec!["-b", "-1", "-o", "cat", "--no-pager", "-n", lines];
+    if let Some(priority) = priority {
+        args.extend(["-p", priority]);
+    }
+    system::run("journalctl", &args)
+        .map(|out| out.lines().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+fn matching(lines: &[String], needles: &[&str]) -> Vec<String> {
+    lines
+        .iter()
+        .filter(|l| {
+            let lower = l.to_lowercase();
+            needles.iter().any(|n| lower.contains(n))
+        })
+        .take(5)
+        .cloned()
+        .collect()
+}
+
+fn generation_findings(report: &mut BootReport) {
+    let generations = boot::list_generations();
+    report.booted_generation = generations.iter().find(|g| g.booted).map(|g| g.number);
+    report.latest_generation = generations.last().map(|g| g.number);
+    let (Some(booted), Some(latest)) = (report.booted_generation, report.latest_generation) else {
+        return;
+    };
+    if booted < latest {
+        report.findings.push(finding(
+            FindingKind::OlderGenerationBooted,
+            "Started an older generation",
+            format!(
+                "This boot used generation {} although {} is the newest. Either it was picked in the boot menu, \
+                 or generation {} failed to start and an older one was chosen to get you running.",
+                booted, latest, latest
+            ),
+            &[
+                "Check what changed: nix store diff-closures between the two generations",
+                "If the newest generation is broken, make this one the default so every boot uses it",
+                "Fix the configuration, rebuild, and try the new generation with `nixos-rebuild test` first",
+            ],
+        ));
+    }
+}
+
+// systemd-boot renames entries to "+0-N" once every boot attempt has failed
+fn boot_counting_findings(report: &mut BootReport) {
+    let Ok(entries) = fs::read_dir(SYSTEMD_BOOT_ENTRIES) else {
+        return;
+    };
+    let bad: Vec<String> = entries
+        .flatten()
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.contains("+0-"))
+        .collect();
+    if bad.is_empty() {
+        return;
+    }
+    let mut f = finding(
+        FindingKind::EntryMarkedBad,
+        "A boot entry failed repeatedly",
+        "The boot loader tried these entries several times and marked them as bad, so it now skips them."
+            .to_string(),
+        &[
+            "Look at the journal of the failed attempts for the first error",
+            "Fix the cause and rebuild; the new entry starts with fresh boot attempts",
+        ],
+    );
+    f.evidence = bad;
+    report.findings.push(f);
+}
+
+struct JournalPattern {
+    kind: FindingKind,
+    title: &'static str,
+    needles: &'static [&'static str],
+    explanation: &'static str,
+    walkthrough: &'static [&'static str],
+}
+
+// Error signatures looked for in the previous boot's journal
+const JOURNAL_PATTERNS: &[JournalPattern] = &[
+    JournalPattern {
+        kind: FindingKind::KernelPanic,
+        title: "The kernel crashed",
+        needles: &["kernel panic", "oops:", "bug: unable to handle"],
+        explanation: "The kernel hit an unrecoverable error. This is often a driver problem after a kernel update.",
+        walkthrough: &[
+            "Boot the previous generation from the boot menu",
+            "Pin the older kernel (boot.kernelPackages) until a fixed one is available",
+        ],
+    },
+    JournalPattern {
+        kind: FindingKind::FilesystemErrors,
+        title: "Disk or filesystem errors",
+        needles: &["i/o error", "ext4-fs error", "btrfs error", "failed to mount", "fsck"],
+        explanation: "Reading or mounting a filesystem failed. A missing disk, a wrong UUID in fileSystems, or a failing drive can cause this.",
+        walkthrough: &[
+            "Check that every fileSystems entry points at an existing UUID",
+            "Add nofail to non-essential mounts so they cannot block booting",
+            "Check drive health with smartctl",
+        ],
+    },
+    JournalPattern {
+        kind: FindingKind::GraphicsErrors,
+        title: "Graphics driver errors",
+        needles: &["nvidia", "amdgpu", "i915", "drm"],
+        explanation: "The graphics driver reported errors, which can leave you with a black screen or frozen desktop.",
+        walkthrough: &[
+            "Boot the previous generation to get a working desktop",
+            "Match the driver to the kernel, or switch between the open and proprietary NVIDIA modules",
+        ],
+    },
+    JournalPattern {
+        kind: FindingKind::OutOfMemory,
+        title: "The system ran out of memory",
+        needles: &["out of memory", "oom-kill", "oom killer"],
+        explanation: "Programs were killed because memory ran out, which can make the machine unresponsive.",
+        walkthrough: &[
+            "Enable zram or add swap in the swap settings",
+            "Consider systemd-oomd so the desktop stays responsive under pressure",
+        ],
+    },
+];
+
+fn journal_findings(report: &mut BootReport) {
+    let tail = previous_boot(None, "40");
+    if tail.is_empty() {
+        // No persistent journal or no previous boot to look at
+        return;
+    }
+    let clean = !matching(
+        &tail,
+        &[
+            "systemd-shutdown",
+            "reached target system power off",
+            "reached target system reboot",
+            "shutting down",
+        ],
+    )
+    .is_empty();
+    if !clean {
+        let mut f = finding(
+            FindingKind::UncleanShutdown,
+            "The last session ended abruptly",
+            "The previous boot never shut down properly. That points to a freeze, crash, power loss or the power button being held down."
+                .to_string(),
+            &[
+                "If the screen froze, look for graphics or kernel errors below",
+                "If it happened during an update, run the rebuild again; generations are never half-applied",
+                "Enable the hardware watchdog or kdump if it keeps happening",
+            ],
+        );
+        f.evidence = tail.iter().rev().take(3).cloned().collect();
+        report.findings.push(f);
+    }
+
+    let errors = previous_boot(Some("0..3"), "400");
+    for pattern in JOURNAL_PATTERNS {
+        let evidence = matching(&errors, pattern.needles);
+        if !evidence.is_empty() {
+            let mut f = finding(
+                pattern.kind,
+                pattern.title,
+                pattern.explanation.to_string(),
+                pattern.walkthrough,
+            );
+            f.evidence = evidence;
+            report.findings.push(f);
+        }
+    }
+}
+
+fn failed_unit_findings(report: &mut BootReport) {
+    let Ok(output) = system::run(
+        "systemctl",
+        &[
+            "list-units",
+            "--failed",
+            "--plain",
+            "--no-legend",
+            "--no-pager",
+        ],
+    ) else {
+        return;
+    };
+    let units: Vec<String> = output
+        .lines()
+        .filter_map(|l| l.split_whitespace().next().map(String::from))
+        .collect();
+    if units.is_empty() {
+        return;
+    }
+    let mut f = finding(
+        FindingKind::FailedUnits,
+        "Some services failed to start",
+        format!(
+            "{} service(s) failed during this boot. The system is running, but the features they provide may be missing.",
+            units.len()
+        ),
+        &[
+            "Open the service's log with journalctl -u <name> -b",
+            "Restart it once the cause is fixed, or disable it if you don't need it",
+        ],
+    );
+    f.evidence = units;
+    report.findings.push(f);
+}
+
+pub fn analyze() -> BootReport {
+    let mut report = BootReport {
+        boot_id: boot_id(),
+        booted_generation: None,
+        latest_generation: None,
+        findings: Vec::new(),
+    };
+    generation_findings(&mut report);
+    boot_counting_findings(&mut report);
+    journal_findings(&mut report);
+    failed_unit_findings(&mut report);
+    report
+}
+
+// Whether the user already dismissed the report for this boot
+pub fn acknowledged() -> bool {
+    storage::load::<Acknowledged>(ACK_FILE)
+        .map(|ack| !ack.boot_id.is_empty() && ack.boot_id == boot_id())
+        .unwrap_or(false)
+}
+
+pub fn acknowledge() -> anyhow::Result<()> {
+    storage::save(ACK_FILE, &Acknowledged { boot_id: boot_id() }).map(|_| ())
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_boot_report(app: AppHandle) -> BootReport {
+    tasks::blocking_value(&app, "Get boot report", |_| analyze()).await
+}
+
+#[tauri::command]
+pub async fn acknowledge_boot_report(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Acknowledge boot report", |_| {
+        crate::respond(acknowledge())
+    })
+    .await
+}
@@ -0,0 +1,503 @@
+// What this machine can do, and what that means for each feature
+//
+// A handful of probes look for the things features depend on: a recent enough
+// Nix, NixOS itself, polkit for running as root, a microphone, a GPU for local
+// models, a network connection, and which optional parts this build was
+// compiled with. The degradation matrix maps them onto features: a missing
+// requirement disables a feature, a missing nice-to-have only degrades it, and
+// either way the reason is spelled out ("disabled because a microphone is
+// missing") so nothing fails without saying why. Probes run fresh on every
+// check, and only the ones the features in question depend on.
+//
+// Components declare capabilities ("voice", "rerun", ...) and each is served
+// with the availability of the feature behind it, so the frontend can grey out
+// a microphone button instead of offering something that will fail. The
+// frontend opens with negotiate_capabilities, which probes afresh; serving
+// components reuses those answers for a minute so it never waits on probes.
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+use tokio::sync::Mutex;
+
+use crate::{system, tasks};
+
+// Oldest Nix with the `nix` command, flakes and `nix search --json`
+const MIN_NIX: (u32, u32) = (2, 4);
+const NETWORK_HOST: &str = "cache.nixos.org:443";
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(2);
+// How long a negotiated feature status is reused when serving components
+const NEGOTIATED_TTL: Duration = Duration::from_secs(60);
+
+// Feature statuses from the last negotiation, and when each was found
+static NEGOTIATED: Mutex<Option<HashMap<&'static str, (Instant, FeatureStatus)>>> =
+    Mutex::const_new(None);
+
+// Component capabilities that rely on a feature; a capability named after a
+// feature relies on it, and the rest (display, sort, copy, ...) only need
+// the window
+const CAPABILITY_FEATURES: &[(&str, &str)] = &[
+    ("rerun", "system-changes"),
+    ("undo", "system-changes"),
+    ("profile-select", "system-changes"),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Probe {
+    pub id: String,
+    pub available: bool,
+    // What was found, or why it doesn't count
+    pub detail: String,
+    // How to say it's missing: "disabled because <missing> is missing"
+    pub missing: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Availability {
+    Available,
+    // Works, but worse: see the reasons
+    Degraded,
+    Disabled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureStatus {
+    pub id: String,
+    pub name: String,
+    pub availability: Availability,
+    pub reasons: Vec<String>,
+}
+
+// A capability a component offers, and whether it can be fulfilled here
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "CapabilityField")]
+pub struct Capability {
+    pub name: String,
+    pub availability: Availability,
+    // The feature it relies on, if any
+    pub feature: Option<String>,
+    pub reasons: Vec<String>,
+}
+
+// Capabilities are read as plain names too, as layouts saved them before
+// they had a status; a stored status is never trusted, it is negotiated again
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CapabilityField {
+    Name(String),
+    Typed { name: String },
+}
+
+impl From<CapabilityField> for Capability {
+    fn from(field: CapabilityField) -> Self {
+        match field {
+            CapabilityField::Name(name) | CapabilityField::Typed { name } => {
+                Capability::named(&name)
+            }
+        }
+    }
+}
+
+impl Capability {
+    // Declared but not negotiated yet
+    pub fn named(name: &str) -> Capability {
+        Capability {
+            name: name.to_string(),
+            availability: Availability::Available,
+            feature: feature_for(name).map(String::from),
+            reasons: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Handshake {
+    pub capabilities: Vec<Capability>,
+    pub features: Vec<FeatureStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapabilityReport {
+    pub probes: Vec<Probe>,
+    pub features: Vec<FeatureStatus>,
+}
+
+struct Feature {
+    id: &'static str,
+    name: &'static str,
+    // Probes the feature can't work without
+    needs: &'static [&'static str],
+    // Probes it works better with, and what is lost without them
+    helped_by: &'static [(&'static str, &'static str)],
+}
+
+// The degradation matrix
+const FEATURES: &[Feature] = &[
+    Feature {
+        id: "search",
+        name: "Package search",
+        needs: &["nix"],
+        helped_by: &[],
+    },
+    Feature {
+        id: "system-changes",
+        name: "Installing, removing and rebuilding",
+        needs: &["nix", "nixos", "polkit"],
+        helped_by: &[],
+    },
+    Feature {
+        id: "updates",
+        name: "System updates",
+        needs: &["nix", "nixos", "polkit", "network"],
+        helped_by: &[],
+    },
+    Feature {
+        id: "flakes",
+        name: "Flakes",
+        needs: &["nix", "flakes"],
+        helped_by: &[],
+    },
+    Feature {
+        id: "voice",
+        name: "Voice input",
+        needs: &["voice-build", "audio"],
+        helped_by: &[("gpu", "speech is transcribed on the CPU, which is slower")],
+    },
+    Feature {
+        id: "wake-word",
+        name: "\"Hey Nix\" wake word",
+        needs: &["wake-word-build", "audio"],
+        helped_by: &[],
+    },
+    Feature {
+        id: "model-downloads",
+        name: "Downloading speech models",
+        needs: &["network", "curl"],
+        helped_by: &[],
+    },
+    Feature {
+        id: "llm",
+        name: "Local language model fallback",
+        needs: &["llm-build"],
+        helped_by: &[("gpu", "the model runs on the CPU and answers slowly")],
+    },
+    Feature {
+        id: "homeassistant",
+        name: "Home Assistant",
+        needs: &["homeassistant-build", "network"],
+        helped_by: &[],
+    },
+];
+
+fn probe_result(id: &str, missing: &str, found: Result<String, String>) -> Probe {
+    let (available, detail) = match found {
+        Ok(detail) => (true, detail),
+        Err(detail) => (false, detail),
+    };
+    Probe {
+        id: id.to_string(),
+        available,
+        detail,
+        missing: missing.to_string(),
+    }
+}
+
+// "nix (Nix) 2.24.9" -> (2, 24)
+fn parse_nix_version(output: &str) -> Option<(u32, u32)> {
+    let version = output.split_whitespace().last()?;
+    let mut parts = version.split('.').map(|p| p.parse::<u32>().ok());
+    Some((parts.next()??, parts.next()??))
+}
+
+fn probe_nix() -> Result<String, String> {
+    let output = system::run("nix", &["--version"]).map_err(|_| "nix not found".to_string())?;
+    let output = output.trim().to_string();
+    match parse_nix_version(&output) {
+        Some(version) if version >= MIN_NIX => Ok(output),
+        Some(_) => Err(format!(
+            "{} is older than {}.{}",
+            output, MIN_NIX.0, MIN_NIX.1
+        )),
+        None => Err(format!("Couldn't read the version from \"{}\"", output)),
+    }
+}
+
+fn probe_nixos() -> Result<String, String> {
+    if !Path::new("/run/current-system").exists() {
+        return Err("/run/current-system doesn't exist".to_string());
+    }
+    Ok(system::run("nixos-version", &[])
+        .map(|v| format!("NixOS {}", v.trim()))
+        .unwrap_or_else(|_| "NixOS".to_string()))
+}
+
+fn probe_polkit() -> Result<String, String> {
+    // pkexec only works through the setuid wrapper
+    let pkexec = system::find_in_path("pkexec").ok_or("pkexec not found")?;
+    if pkexec.starts_with("/nix/store") {
+        return Err(format!(
+            "{} isn't the setuid wrapper; enable security.polkit",
+            pkexec.display()
+        ));
+    }
+    Ok(pkexec.display().to_string())
+}
+
+fn probe_flakes() -> Result<String, String> {
+    // `nix show-config` before Nix 2.20
+    let config = system::run("nix", &["config", "show"])
+        .or_else(|_| system::run("nix", &["show-config"]))
+        .map_err(|_| "Couldn't read the Nix configuration".to_string())?;
+    let features = config
+        .lines()
+        .find_map(|line| line.strip_prefix("experimental-features = "))
+        .unwrap_or_default()
+        .trim();
+    if features.split_whitespace().any(|f| f == "flakes") {
+        Ok(format!("experimental-features = {}", features))
+    } else {
+        Err("flakes isn't in experimental-features".to_string())
+    }
+}
+
+fn device_names(dir: &str) -> Vec<String> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect()
+}
+
+// ALSA capture devices are pcmC<card>D<device>c, whatever sound server runs on top
+fn probe_audio() -> Result<String, String> {
+    let inputs = device_names("/dev/snd")
+        .into_iter()
+        .filter(|name| name.starts_with("pcmC") && name.ends_with('c'))
+        .count();
+    match inputs {
+        0 => Err("No sound input devices in /dev/snd".to_string()),
+        1 => Ok("1 input device".to_string()),
+        n => Ok(format!("{} input devices", n)),
+    }
+}
+
+fn probe_gpu() -> Result<String, String> {
+    if Path::new("/dev/nvidia0").exists() {
+        return Ok("NVIDIA (/dev/nvidia0)".to_string());
+    }
+    device_names("/dev/dri")
+        .into_iter()
+        .find(|name| name.starts_with("renderD"))
+        .map(|node| format!("/dev/dri/{}", node))
+        .ok_or_else(|| "No render node in /dev/dri".to_string())
+}
+
+fn probe_network() -> Result<String, String> {
+    let address = NETWORK_HOST
+        .to_socket_addrs()
+        .map_err(|e| format!("Couldn't resolve {}: {}", NETWORK_HOST, e))?
+        .next()
+        .ok_or_else(|| format!("Couldn't resolve {}", NETWORK_HOST))?;
+    TcpStream::connect_timeout(&address, NETWORK_TIMEOUT)
+        .map(|_| format!("Reached {}", NETWORK_HOST))
+        .map_err(|e| format!("Couldn't reach {}: {}", NETWORK_HOST, e))
+}
+
+fn probe_build(feature: &str, included: bool) -> Result<String, String> {
+    if included {
+        Ok(format!("Built with the `{}` feature", feature))
+    } else {
+        Err(format!("Built without the `{}` feature", feature))
+    }
+}
+
+pub fn probe(id: &str) -> Probe {
+    let (missing, found) = match id {
+        "nix" => (
+            format!("Nix {}.{} or newer", MIN_NIX.0, MIN_NIX.1),
+            probe_nix(),
+        ),
+        "nixos" => ("NixOS".to_string(), probe_nixos()),
+        "polkit" => ("polkit (pkexec)".to_string(), probe_polkit()),
+        "flakes" => ("flakes support in nix.conf".to_string(), probe_flakes()),
+        "audio" => ("a microphone".to_string(), probe_audio()),
+        "gpu" => ("a GPU".to_string(), probe_gpu()),
+        "network" => ("a network connection".to_string(), probe_network()),
+        "curl" => (
+            "curl".to_string(),
+            system::find_in_path("curl")
+                .map(|p| p.display().to_string())
+                .ok_or_else(|| "curl not found".to_string()),
+        ),
+        "voice-build" => (
+            "voice support in this build".to_string(),
+            probe_build("voice", cfg!(feature = "voice")),
+        ),
+        "wake-word-build" => (
+            "wake word support in this build".to_string(),
+            probe_build("wake-word", cfg!(feature = "wake-word")),
+        ),
+        "llm-build" => (
+            "language model support in this build".to_string(),
+            probe_build("llm", cfg!(feature = "llm")),
+        ),
+        "homeassistant-build" => (
+            "Home Assistant support in this build".to_string(),
+            probe_build("homeassistant", cfg!(feature = "homeassistant")),
+        ),
+        _ => (id.to_string(), Err(format!("Unknown probe \"{}\"", id))),
+    };
+    probe_result(id, &missing, found)
+}
+
+fn evaluate(feature: &Feature, probes: &mut Vec<Probe>) -> FeatureStatus {
+    let mut result = |id: &str| -> Probe {
+        if let Some(known) = probes.iter().find(|p| p.id == id) {
+            return known.clone();
+        }
+        let probed = probe(id);
+        probes.push(probed.clone());
+        probed
+    };
+    let mut reasons = Vec::new();
+    for id in feature.needs {
+        let probe = result(id);
+        if !probe.available {
+            reasons.push(format!(
+                "disabled because {} is missing ({})",
+                probe.missing, probe.detail
+            ));
+        }
+    }
+    let disabled = !reasons.is_empty();
+    if !disabled {
+        for (id, without) in feature.helped_by {
+            let probe = result(id);
+            if !probe.available {
+                reasons.push(format!("{} is missing, so {}", probe.missing, without));
+            }
+        }
+    }
+    FeatureStatus {
+        id: feature.id.to_string(),
+        name: feature.name.to_string(),
+        availability: match (disabled, reasons.is_empty()) {
+            (true, _) => Availability::Disabled,
+            (false, true) => Availability::Available,
+            (false, false) => Availability::Degraded,
+        },
+        reasons,
+    }
+}
+
+pub fn report() -> CapabilityReport {
+    let mut probes = Vec::new();
+    let features = FEATURES
+        .iter()
+        .map(|feature| evaluate(feature, &mut probes))
+        .collect();
+    CapabilityReport { probes, features }
+}
+
+pub fn feature(id: &str) -> Option<FeatureStatus> {
+    let feature = FEATURES.iter().find(|f| f.id == id)?;
+    Some(evaluate(feature, &mut Vec::new()))
+}
+
+// Fail with the reason up front instead of somewhere deep inside the feature
+pub fn require(id: &str) -> anyhow::Result<()> {
+    let Some(status) = feature(id) else {
+        return Ok(());
+    };
+    if status.availability == Availability::Disabled {
+        bail!("{} is {}", status.name, status.reasons.join("; "));
+    }
+    Ok(())
+}
+
+fn feature_for(capability: &str) -> Option<&'static str> {
+    CAPABILITY_FEATURES
+        .iter()
+        .find(|(name, _)| *name == capability)
+        .map(|(_, feature)| *feature)
+        .or_else(|| FEATURES.iter().find(|f| f.id == capability).map(|f| f.id))
+}
+
+// The status of each feature, probing the ones not seen within the TTL (or
+// all of them when `fresh`)
+fn negotiated(ids: &[&'static str], fresh: bool) -> Vec<FeatureStatus> {
+    let mut cache = NEGOTIATED.blocking_lock();
+    let cache = cache.get_or_insert_with(HashMap::new);
+    let mut probes = Vec::new();
+    for feature in FEATURES.iter().filter(|f| ids.contains(&f.id)) {
+        let stale = cache
+            .get(feature.id)
+            .is_none_or(|(at, _)| at.elapsed() > NEGOTIATED_TTL);
+        if fresh || stale {
+            let status = evaluate(feature, &mut probes);
+            cache.insert(feature.id, (Instant::now(), status));
+        }
+    }
+    ids.iter()
+        .filter_map(|id| cache.get(id).map(|(_, status)| status.clone()))
+        .collect()
+}
+
+// Each capability with the availability of the feature behind it
+pub fn negotiate(names: &[String], fresh: bool) -> Vec<Capability> {
+    let mut ids: Vec<&'static str> = names.iter().filter_map(|n| feature_for(n)).collect();
+    ids.sort();
+    ids.dedup();
+    let statuses = negotiated(&ids, fresh);
+    names
+        .iter()
+        .map(|name| {
+            let mut capability = Capability::named(name);
+            if let Some(status) = statuses
+                .iter()
+                .find(|s| Some(s.id.as_str()) == capability.feature.as_deref())
+            {
+                capability.availability = status.availability;
+                capability.reasons = status.reasons.clone();
+            }
+            capability
+        })
+        .collect()
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_capabilities(app: AppHandle) -> CapabilityReport {
+    tasks::blocking_value(&app, "Get capabilities", |_| report()).await
+}
+
+#[tauri::command]
+pub async fn get_feature_status(id: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Get feature status", move |_| {
+        crate::respond(feature(&id).ok_or_else(|| anyhow::anyhow!("Unknown feature \"{}\"", id)))
+    })
+    .await
+}
+
+// The frontend's opening question: which of these capabilities can the
+// backend fulfil, and how is every feature doing
+#[tauri::command]
+pub async fn negotiate_capabilities(requested: Vec<String>, app: AppHandle) -> Handshake {
+    tasks::blocking_value(&app, "Negotiate capabilities", move |_| {
+        let ids: Vec<&'static str> = FEATURES.iter().map(|f| f.id).collect();
+        let features = negotiated(&ids, true);
+        Handshake {
+            capabilities: negotiate(&requested, false),
+            features,
+        }
+    })
+    .await
+}
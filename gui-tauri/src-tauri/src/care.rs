@@ -0,0 +1,583 @@
+// Weekly system care session
+//
+// An optional, unhurried walk through the week's maintenance: updating,
+// letting go of old generations, checking the system's health and making sure
+// backups actually ran. Each step explains itself before it does anything and
+// ends at a stop point; an unfinished session is kept and picks up where it
+// was left next time.
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::nlp::Intent;
+use crate::{bootcheck, clock, services, storage, system, tasks, AppState};
+
+const STATE_FILE: &str = "care.json";
+const WEEK: u64 = 7 * 24 * 60 * 60;
+// Backup units whose last success is older than this are flagged
+const BACKUP_MAX_AGE: u64 = 8 * 24 * 60 * 60;
+const DISK_WARN_PERCENT: u64 = 90;
+const BACKUP_HINTS: &[&str] = &[
+    "backup",
+    "restic",
+    "borg",
+    "btrbk",
+    "snapper",
+    "syncoid",
+    "sanoid",
+    "rsnapshot",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CareStep {
+    Arrive,
+    Update,
+    CleanUp,
+    HealthCheck,
+    Backups,
+    Reflect,
+}
+
+const STEPS: &[CareStep] = &[
+    CareStep::Arrive,
+    CareStep::Update,
+    CareStep::CleanUp,
+    CareStep::HealthCheck,
+    CareStep::Backups,
+    CareStep::Reflect,
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepGuide {
+    pub step: CareStep,
+    pub title: String,
+    // Read before anything happens
+    pub invitation: String,
+    pub what_happens: String,
+    pub can_skip: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Done,
+    NeedsAttention,
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Check {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepOutcome {
+    pub step: CareStep,
+    pub status: StepStatus,
+    pub summary: String,
+    pub checks: Vec<Check>,
+    pub finished_at: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CareSession {
+    pub started: u64,
+    pub outcomes: Vec<StepOutcome>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CareState {
+    pub enabled: bool,
+    pub last_completed: Option<u64>,
+    pub session: Option<CareSession>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CareOverview {
+    pub enabled: bool,
+    pub due: bool,
+    pub days_since_last: Option<u64>,
+    pub session: Option<CareSession>,
+    pub next_step: Option<StepGuide>,
+    pub steps: Vec<StepGuide>,
+}
+
+fn load() -> CareState {
+    storage::load(STATE_FILE).unwrap_or_default()
+}
+
+fn save(state: &CareState) -> anyhow::Result<()> {
+    storage::save(STATE_FILE, state).map(|_| ())
+}
+
+fn check(name: &str, ok: bool, detail: impl Into<String>) -> Check {
+    Check {
+        name: name.to_string(),
+        ok,
+        detail: detail.into(),
+    }
+}
+
+pub fn guide(step: CareStep) -> StepGuide {
+    let (title, invitation, what_happens, can_skip) = match step {
+        CareStep::Arrive => (
+            "Arrive",
+            "Take a breath. Nothing changes in this step; it is just a look at where things stand.",
+            "Shows how long it has been since the last session and what is waiting.",
+            false,
+        ),
+        CareStep::Update => (
+            "Update",
+            "Bring in this week's updates. This can take a while, so it is a good moment to step away.",
+            "Updates the system inputs and switches to the result. The previous generation stays available to roll back to.",
+            true,
+        ),
+        CareStep::CleanUp => (
+            "Let go",
+            "Release generations you no longer need. Keep going only if the last update is working well for you.",
+            "Deletes generations older than 30 days and the packages only they used. This cannot be undone.",
+            true,
+        ),
+        CareStep::HealthCheck => (
+            "Health check",
+            "A gentle look under the hood.",
+            "Checks for failed services, boot problems and disks that are filling up. Nothing is changed.",
+            true,
+        ),
+        CareStep::Backups => (
+            "Backups",
+            "Make sure what matters is safe.",
+            "Looks for backup services and when each last succeeded. Nothing is changed.",
+            true,
+        ),
+        CareStep::Reflect => (
+            "Reflect",
+            "That is this week's care done. Here is what happened.",
+            "Summarises the session and schedules the next one a week from now.",
+            false,
+        ),
+    };
+    StepGuide {
+        step,
+        title: title.to_string(),
+        invitation: invitation.to_string(),
+        what_happens: what_happens.to_string(),
+        can_skip,
+    }
+}
+
+fn next_step(session: Option<&CareSession>) -> Option<CareStep> {
+    // Failed steps stay open so they can be retried or skipped
+    let done: Vec<CareStep> = session
+        .map(|s| {
+            s.outcomes
+                .iter()
+                .filter(|o| o.status != StepStatus::Failed)
+                .map(|o| o.step)
+                .collect()
+        })
+        .unwrap_or_default();
+    STEPS.iter().copied().find(|s| !done.contains(s))
+}
+
+pub fn overview() -> CareOverview {
+    let state = load();
+    let days_since_last = state
+        .last_completed
+        .map(|at| clock::now().saturating_sub(at) / (24 * 60 * 60));
+    let due = state
+        .last_completed
+        .is_none_or(|at| clock::now().saturating_sub(at) >= WEEK);
+    CareOverview {
+        enabled: state.enabled,
+        due,
+        days_since_last,
+        next_step: next_step(state.session.as_ref()).map(guide),
+        steps: STEPS.iter().copied().map(guide).collect(),
+        session: state.session,
+    }
+}
+
+// Whether to invite the user to a session when the app starts
+pub fn should_invite() -> bool {
+    let overview = overview();
+    overview.enabled && (overview.due || overview.session.is_some())
+}
+
+pub fn failed_units() -> Vec<String> {
+    services::systemctl(false, &["list-units", "--failed", "--plain", "--no-legend"])
+        .map(|output| {
+            output
+                .lines()
+                .filter_map(|l| l.split_whitespace().next().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// (mount point, percent used) for /, /nix and /boot, each mount once
+fn disk_usage() -> Vec<(String, u64)> {
+    let mut args = vec!["-P"];
+    args.extend(
+        ["/", "/nix", "/boot"]
+            .into_iter()
+            .filter(|p| std::path::Path::new(p).exists()),
+    );
+    let Ok(output) = system::run("df", &args) else {
+        return Vec::new();
+    };
+    let mut seen: Vec<(String, u64)> = Vec::new();
+    for line in output.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (Some(percent), Some(mount)) = (fields.get(4), fields.get(5)) else {
+            continue;
+        };
+        let Ok(percent) = percent.trim_end_matches('%').parse() else {
+            continue;
+        };
+        if !seen.iter().any(|(m, _)| m == mount) {
+            seen.push((mount.to_string(), percent));
+        }
+    }
+    seen
+}
+
+fn health_checks() -> Vec<Check> {
+    let mut checks = Vec::new();
+    let failed = failed_units();
+    checks.push(check(
+        "services",
+        failed.is_empty(),
+        if failed.is_empty() {
+            "Every service is running as it should".to_string()
+        } else {
+            format!("Not running properly: {}", failed.join(", "))
+        },
+    ));
+    let report = bootcheck::analyze();
+    checks.push(check(
+        "boot",
+        report.findings.is_empty(),
+        match report.findings.first() {
+            None => "The last boot went smoothly".to_string(),
+            Some(finding) => finding.title.clone(),
+        },
+    ));
+    for (mount, percent) in disk_usage() {
+        checks.push(check(
+            &format!("disk {}", mount),
+            percent < DISK_WARN_PERCENT,
+            format!("{} is {}% full", mount, percent),
+        ));
+    }
+    checks
+}
+
+fn backup_units() -> Vec<String> {
+    let Ok(output) = services::systemctl(
+        false,
+        &[
+            "list-units",
+            "--all",
+            "--type=service",
+            "--plain",
+            "--no-legend",
+        ],
+    ) else {
+        return Vec::new();
+    };
+    output
+        .lines()
+        .filter_map(|l| l.split_whitespace().next())
+        .filter(|unit| BACKUP_HINTS.iter().any(|hint| unit.contains(hint)))
+        .map(String::from)
+        .collect()
+}
+
+fn backup_checks() -> Vec<Check> {
+    let units = backup_units();
+    if units.is_empty() {
+        return vec![check(
+            "backups",
+            false,
+            "No backup service was found. Consider setting one up (restic, borg or a simple timer)",
+        )];
+    }
+    units
+        .iter()
+        .map(|unit| {
+            let props = services::systemctl(
+                false,
+                &[
+                    "show",
+                    "--timestamp=unix",
+                    "--property=Result,ExecMainExitTimestamp",
+                    unit,
+                ],
+            )
+            .unwrap_or_default();
+            let prop = |name: &str| {
+                props
+                    .lines()
+                    .find_map(|l| l.strip_prefix(name)?.strip_prefix('='))
+                    .unwrap_or("")
+                    .to_string()
+            };
+            let result = prop("Result");
+            let finished: Option<u64> = prop("ExecMainExitTimestamp")
+                .trim_start_matches('@')
+                .parse()
+                .ok();
+            match finished {
+                None => check(unit, false, format!("{} has not run yet", unit)),
+                Some(_) if result != "success" => check(
+                    unit,
+                    false,
+                    format!("{} last ended with '{}'", unit, result),
+                ),
+                Some(at) => {
+                    let days = clock::now().saturating_sub(at) / (24 * 60 * 60);
+                    check(
+                        unit,
+                        clock::now().saturating_sub(at) <= BACKUP_MAX_AGE,
+                        format!("{} last succeeded {} day(s) ago", unit, days),
+                    )
+                }
+            }
+        })
+        .collect()
+}
+
+fn intent_for(step: CareStep) -> Option<Intent> {
+    match step {
+        CareStep::Update => Some(Intent::Update),
+        CareStep::CleanUp => Some(Intent::GarbageCollect),
+        _ => None,
+    }
+}
+
+fn outcome(step: CareStep, status: StepStatus, summary: String, checks: Vec<Check>) -> StepOutcome {
+    StepOutcome {
+        step,
+        status,
+        summary,
+        checks,
+        finished_at: clock::now(),
+    }
+}
+
+fn from_checks(step: CareStep, checks: Vec<Check>) -> StepOutcome {
+    let attention = checks.iter().filter(|c| !c.ok).count();
+    let (status, summary) = if attention == 0 {
+        (StepStatus::Done, "All is well".to_string())
+    } else {
+        (
+            StepStatus::NeedsAttention,
+            format!("{} thing(s) could use your attention", attention),
+        )
+    };
+    outcome(step, status, summary, checks)
+}
+
+fn reflect(session: &CareSession) -> StepOutcome {
+    let count = |status: StepStatus| {
+        session
+            .outcomes
+            .iter()
+            .filter(|o| o.status == status)
+            .count()
+    };
+    let checks = session
+        .outcomes
+        .iter()
+        .map(|o| {
+            check(
+                &guide(o.step).title,
+                matches!(o.status, StepStatus::Done | StepStatus::Skipped),
+                o.summary.clone(),
+            )
+        })
+        .collect();
+    let summary = format!(
+        "{} step(s) done, {} skipped, {} needing attention, {} failed. See you next week.",
+        count(StepStatus::Done),
+        count(StepStatus::Skipped),
+        count(StepStatus::NeedsAttention),
+        count(StepStatus::Failed),
+    );
+    outcome(CareStep::Reflect, StepStatus::Done, summary, checks)
+}
+
+pub fn start() -> anyhow::Result<CareOverview> {
+    let mut state = load();
+    if state.session.is_none() {
+        state.session = Some(CareSession {
+            started: clock::now(),
+            outcomes: Vec::new(),
+        });
+        save(&state)?;
+    }
+    Ok(overview())
+}
+
+fn record(mut state: CareState, outcome: StepOutcome) -> anyhow::Result<StepOutcome> {
+    let session = state.session.get_or_insert_with(|| CareSession {
+        started: clock::now(),
+        outcomes: Vec::new(),
+    });
+    session.outcomes.retain(|o| o.step != outcome.step);
+    session.outcomes.push(outcome.clone());
+    // Reflecting closes the session and starts the week over
+    if outcome.step == CareStep::Reflect {
+        state.session = None;
+        state.last_completed = Some(clock::now());
+    }
+    save(&state)?;
+    Ok(outcome)
+}
+
+fn expect_next(state: &CareState, step: CareStep) -> anyhow::Result<()> {
+    if state.session.is_none() {
+        bail!("Start a care session first");
+    }
+    match next_step(state.session.as_ref()) {
+        Some(next) if next == step => Ok(()),
+        Some(next) => bail!("The next step is {}", guide(next).title),
+        None => bail!("This session is already complete"),
+    }
+}
+
+pub fn skip(step: CareStep) -> anyhow::Result<StepOutcome> {
+    let state = load();
+    expect_next(&state, step)?;
+    if !guide(step).can_skip {
+        bail!("{} can't be skipped", guide(step).title);
+    }
+    record(
+        state,
+        outcome(
+            step,
+            StepStatus::Skipped,
+            "Skipped for this week".to_string(),
+            Vec::new(),
+        ),
+    )
+}
+
+// Stop here; the session is discarded and nothing further runs
+pub fn end() -> anyhow::Result<()> {
+    let mut state = load();
+    state.session = None;
+    save(&state)
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_care_overview(app: AppHandle) -> CareOverview {
+    tasks::blocking_value(&app, "Get care overview", |_| overview()).await
+}
+
+#[tauri::command]
+pub async fn set_care_enabled(enabled: bool, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set care enabled", move |_| {
+        let mut state = load();
+        state.enabled = enabled;
+        crate::respond(save(&state).map(|()| overview()))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn start_care_session(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Start care session", |_| crate::respond(start())).await
+}
+
+// Run the next step; updates and clean-up are checked and staged like any
+// other request
+pub fn run_step(
+    step: CareStep,
+    options: Option<serde_json::Value>,
+    state: &State<AppState>,
+) -> serde_json::Value {
+    let care = load();
+    if let Err(e) = expect_next(&care, step) {
+        return crate::respond::<()>(Err(e));
+    }
+    let options = options.unwrap_or_default();
+    let result = match step {
+        CareStep::Arrive => {
+            let overview = overview();
+            let summary = match overview.days_since_last {
+                Some(days) => format!("It has been {} day(s) since the last session", days),
+                None => "This is your first care session".to_string(),
+            };
+            outcome(step, StepStatus::Done, summary, Vec::new())
+        }
+        CareStep::Update | CareStep::CleanUp => {
+            let Some(intent) = intent_for(step) else {
+                return crate::respond::<()>(Err(anyhow::anyhow!("Nothing to run")));
+            };
+            if let Err(response) = crate::admit(std::slice::from_ref(&intent), &options, state) {
+                return response;
+            }
+            let response = crate::dispatch(intent, &options, state);
+            let succeeded = response.get("success").and_then(|s| s.as_bool()) == Some(true);
+            if response.get("staged").and_then(|s| s.as_bool()) == Some(true) {
+                let message = response
+                    .get("message")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("Staged for the next maintenance window")
+                    .to_string();
+                outcome(step, StepStatus::Done, message, Vec::new())
+            } else if succeeded {
+                outcome(step, StepStatus::Done, "Finished".to_string(), Vec::new())
+            } else {
+                let error = response
+                    .get("error")
+                    .and_then(|e| e.as_str())
+                    .unwrap_or("Something went wrong")
+                    .to_string();
+                outcome(step, StepStatus::Failed, error, Vec::new())
+            }
+        }
+        CareStep::HealthCheck => from_checks(step, health_checks()),
+        CareStep::Backups => from_checks(step, backup_checks()),
+        CareStep::Reflect => reflect(care.session.as_ref().unwrap_or(&CareSession::default())),
+    };
+    crate::respond(record(care, result).map(|outcome| {
+        let next = match outcome.step {
+            CareStep::Reflect => None,
+            _ => next_step(load().session.as_ref()).map(guide),
+        };
+        // Every step is a stop point; this is what comes after it
+        serde_json::json!({"outcome": outcome, "next_step": next})
+    }))
+}
+
+#[tauri::command]
+pub async fn run_care_step(
+    step: CareStep,
+    options: Option<serde_json::Value>,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Care step", move |state| {
+        run_step(step, options, state)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn skip_care_step(step: CareStep, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Skip care step", move |_| crate::respond(skip(step))).await
+}
+
+#[tauri::command]
+pub async fn end_care_session(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "End care session", |_| crate::respond(end())).await
+}
@@ -0,0 +1,121 @@
+// Clarification dialog for ambiguous requests
+//
+// Instead of guessing, low-confidence parses and package names that match
+// several nixpkgs attributes come back as ranked interpretations the frontend
+// can offer ("did you mean python311 or python312?").
+
+use serde::{Deserialize, Serialize};
+
+use crate::nix;
+use crate::nlp::{Intent, ParsedIntent};
+
+// Below this confidence we ask rather than act
+pub const CONFIDENCE_THRESHOLD: f32 = 0.7;
+const MAX_INTERPRETATIONS: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interpretation {
+    pub intent: Intent,
+    pub description: String,
+    pub confidence: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Clarification {
+    pub question: String,
+    pub interpretations: Vec<Interpretation>,
+}
+
+fn interpretation(intent: Intent, confidence: f32) -> Interpretation {
+    Interpretation {
+        description: intent.describe(),
+        intent,
+        confidence,
+    }
+}
+
+// Rank nixpkgs candidates for a name: prefix matches, then shorter attrs
+fn package_candidates(name: &str) -> Vec<nix::Package> {
+    let Ok(mut results) = nix::search(&format!("^{}", name)) else {
+        return Vec::new();
+    };
+    results.sort_by_key(|p| {
+        (
+            !p.attr.starts_with(name),
+            p.attr.contains('.'),
+            p.attr.len(),
+        )
+    });
+    results
+}
+
+// A package is ambiguous when it is not an attribute itself but several are close
+fn ambiguous_package(packages: &[String]) -> Option<(usize, Vec<nix::Package>)> {
+    packages.iter().enumerate().find_map(|(index, name)| {
+        let candidates = package_candidates(name);
+        if candidates.iter().any(|p| &p.attr == name) || candidates.len() < 2 {
+            None
+        } else {
+            Some((index, candidates))
+        }
+    })
+}
+
+pub fn check(parsed: &ParsedIntent, readings: &[ParsedIntent]) -> Option<Clarification> {
+    if parsed.confidence < CONFIDENCE_THRESHOLD {
+        let interpretations: Vec<Interpretation> = readings
+            .iter()
+            .filter(|r| r.intent != Intent::Unknown)
+            .take(MAX_INTERPRETATIONS)
+            .map(|r| interpretation(r.intent.clone(), r.confidence))
+            .collect();
+        return Some(Clarification {
+            question: if interpretations.is_empty() {
+                "I'm not sure what you'd like to do. Could you rephrase it?".to_string()
+            } else {
+                "I'm not sure I understood. Did you mean one of these?".to_string()
+            },
+            interpretations,
+        });
+    }
+
+    let Intent::Install { packages } = &parsed.intent else {
+        return None;
+    };
+    let (index, candidates) = ambiguous_package(packages)?;
+    let count = candidates.len().min(MAX_INTERPRETATIONS);
+    let interpretations = candidates
+        .iter()
+        .take(count)
+        .enumerate()
+        .map(|(rank, candidate)| {
+            let mut chosen = packages.clone();
+            chosen[index] = candidate.attr.clone();
+            let mut option = interpretation(
+                Intent::Install { packages: chosen },
+                parsed.confidence * (1.0 - rank as f32 / (count as f32 * 2.0)),
+            );
+            if !candidate.description.is_empty() {
+                option.description = format!("{} ({})", option.description, candidate.description);
+            }
+            option
+        })
+        .collect();
+    let names: Vec<&str> = candidates
+        .iter()
+        .take(count)
+        .map(|c| c.attr.as_str())
+        .collect();
+    let question = match names.as_slice() {
+        [first, second] => format!("Did you mean {} or {}?", first, second),
+        _ => format!(
+            "'{}' matches several packages: {}. Which one?",
+            packages[index],
+            names.join(", ")
+        ),
+    };
+    Some(Clarification {
+        question,
+        interpretations,
+    })
+}
@@ -0,0 +1,154 @@
+// Clipboard-aware assistance (opt-in)
+//
+// When turned on, the clipboard is checked every couple of seconds for text
+// the assistant can help with: a Nix error message, offered as "explain this
+// error", or a `nix-shell -p` / `nix shell` command, offered as the same
+// packages in the system configuration. Suggestions go out as a
+// "clipboard_suggestions" event carrying only the recognised part, never the
+// rest of the clipboard, and nothing is stored. Text that was already on the
+// clipboard when watching began is left alone.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::actions::Invocation;
+use crate::{explain, nixgen, storage, tasks};
+
+const SETTINGS_FILE: &str = "clipboard.json";
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+// Longer text is a document, not something copied from a terminal
+const MAX_TEXT: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipboardSettings {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionKind {
+    ExplainError,
+    MakeDeclarative,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipboardSuggestion {
+    pub kind: SuggestionKind,
+    pub title: String,
+    // The part of the clipboard it is about: the error line or the command
+    pub excerpt: String,
+    pub snippet: Option<String>,
+    pub invocation: Invocation,
+}
+
+pub fn settings() -> ClipboardSettings {
+    storage::load(SETTINGS_FILE).unwrap_or_default()
+}
+
+// The packages of `nix-shell -p a b` or `nix shell nixpkgs#a nixpkgs#b`
+fn shell_packages(line: &str) -> Option<Vec<String>> {
+    let tokens = explain::tokenize(line.trim().trim_start_matches("$ "));
+    let (program, args) = tokens.split_first()?;
+    let packages: Vec<String> = match program.as_str() {
+        "nix-shell" => {
+            let start = args.iter().position(|a| a == "-p" || a == "--packages")?;
+            args[start + 1..]
+                .iter()
+                .take_while(|a| !a.starts_with('-'))
+                .cloned()
+                .collect()
+        }
+        "nix" if args.first().is_some_and(|a| a == "shell") => args[1..]
+            .iter()
+            .take_while(|a| !a.starts_with('-'))
+            .filter_map(|a| a.split_once('#').map(|(_, attr)| attr.to_string()))
+            .collect(),
+        _ => return None,
+    };
+    (!packages.is_empty()).then_some(packages)
+}
+
+pub fn suggest(text: &str) -> Vec<ClipboardSuggestion> {
+    let mut suggestions = Vec::new();
+    if let Some(explanation) = explain::explain_error(text) {
+        suggestions.push(ClipboardSuggestion {
+            kind: SuggestionKind::ExplainError,
+            title: "Explain this error".to_string(),
+            excerpt: explanation.error,
+            snippet: explanation.snippet,
+            invocation: Invocation::Command {
+                command: "explain_nix_error".to_string(),
+                args: serde_json::json!({"error": text}),
+            },
+        });
+    }
+    for line in text.lines() {
+        let Some(packages) = shell_packages(line) else {
+            continue;
+        };
+        suggestions.push(ClipboardSuggestion {
+            kind: SuggestionKind::MakeDeclarative,
+            title: format!("Add {} to your configuration", packages.join(", ")),
+            excerpt: line.trim().to_string(),
+            snippet: Some(format!(
+                "environment.systemPackages = with pkgs; {};",
+                nixgen::list(&packages)
+            )),
+            // The system profile keeps its packages in a generated module
+            invocation: Invocation::Command {
+                command: "perform_action".to_string(),
+                args: serde_json::json!({
+                    "action": "install",
+                    "params": {"packages": packages, "profile": "system"},
+                }),
+            },
+        });
+    }
+    suggestions
+}
+
+pub fn start_watcher(app: AppHandle) {
+    std::thread::spawn(move || {
+        // What was on the clipboard last time; None until watching starts
+        let mut last: Option<String> = None;
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            if !settings().enabled {
+                last = None;
+                continue;
+            }
+            let Ok(text) = app.clipboard().read_text() else {
+                continue;
+            };
+            let seen = last.replace(text.clone());
+            if seen.as_ref().is_none_or(|seen| *seen == text) || text.len() > MAX_TEXT {
+                continue;
+            }
+            let suggestions = suggest(&text);
+            if !suggestions.is_empty() {
+                let _ = app.emit("clipboard_suggestions", &suggestions);
+            }
+        }
+    });
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_clipboard_settings(app: AppHandle) -> ClipboardSettings {
+    tasks::blocking_value(&app, "Get clipboard settings", |_| settings()).await
+}
+
+#[tauri::command]
+pub async fn set_clipboard_settings(
+    settings: ClipboardSettings,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set clipboard settings", move |_| {
+        crate::respond(storage::save(SETTINGS_FILE, &settings).map(|_| settings))
+    })
+    .await
+}
@@ -0,0 +1,23 @@
+// Wall-clock time for stored timestamps
+//
+// Timestamps are kept as Unix seconds, or milliseconds where interactions
+// arrive faster than that. A clock set before 1970 reads as 0 rather than
+// failing.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn since_epoch() -> std::time::Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+// Unix seconds
+pub fn now() -> u64 {
+    since_epoch().as_secs()
+}
+
+// Unix milliseconds
+pub fn now_ms() -> u64 {
+    since_epoch().as_millis() as u64
+}
@@ -0,0 +1,257 @@
+// Cognitive load estimate from the recent interaction history
+//
+// Four heuristics, each scored 0.0-1.0: how often things fail, how fast
+// interactions arrive, how often the user jumps between tasks, and the time of
+// day. They are combined into one continuous load value; the confidence grows
+// with the number of recent interactions the estimate is based on, and without
+// a measured value to blend with, the load is only as high as it is trusted.
+
+use serde::{Deserialize, Serialize};
+
+// Only the last stretch of activity reflects the current load
+const WINDOW_MS: u64 = 15 * 60 * 1000;
+// Interactions needed before the estimate is fully trusted
+const FULL_CONFIDENCE_SAMPLES: usize = 12;
+// Interactions per minute that count as hurried
+const HURRIED_RATE: f32 = 6.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Factor {
+    pub name: String,
+    pub value: f32,
+    pub weight: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadEstimate {
+    pub load: f32,
+    pub confidence: f32,
+    pub factors: Vec<Factor>,
+    pub sample_size: usize,
+}
+
+fn timestamp(interaction: &serde_json::Value) -> u64 {
+    interaction
+        .get("timestamp_ms")
+        .and_then(|t| t.as_u64())
+        .unwrap_or(0)
+}
+
+// What the interaction was about: the intent kind, else the component or action
+fn task(interaction: &serde_json::Value) -> Option<&str> {
+    interaction
+        .pointer("/intent/kind")
+        .or_else(|| interaction.get("component_id"))
+        .or_else(|| interaction.get("action"))
+        .or_else(|| interaction.get("type"))
+        .and_then(|v| v.as_str())
+}
+
+fn error_rate(recent: &[&serde_json::Value]) -> f32 {
+    let outcomes: Vec<bool> = recent
+        .iter()
+        .filter_map(|i| i.get("success").and_then(|s| s.as_bool()))
+        .collect();
+    if outcomes.is_empty() {
+        return 0.0;
+    }
+    outcomes.iter().filter(|ok| !**ok).count() as f32 / outcomes.len() as f32
+}
+
+// Interactions per minute over the span they cover, relative to a hurried pace
+fn tempo(recent: &[&serde_json::Value]) -> f32 {
+    let (Some(first), Some(last)) = (recent.first(), recent.last()) else {
+        return 0.0;
+    };
+    if recent.len() < 3 {
+        return 0.0;
+    }
+    // At least a minute, so a quick burst of three isn't read as 180 per minute
+    let minutes = (timestamp(last).saturating_sub(timestamp(first)) as f32 / 60_000.0).max(1.0);
+    (recent.len() as f32 / minutes / HURRIED_RATE).min(1.0)
+}
+
+// Share of consecutive interactions that moved to a different task
+fn task_switching(recent: &[&serde_json::Value]) -> f32 {
+    let tasks: Vec<&str> = recent.iter().filter_map(|i| task(i)).collect();
+    if tasks.len() < 2 {
+        return 0.0;
+    }
+    let switches = tasks.windows(2).filter(|w| w[0] != w[1]).count();
+    switches as f32 / (tasks.len() - 1) as f32
+}
+
+// Late nights and the post-lunch dip carry more load than mid-morning
+fn time_of_day(local_hour: u32) -> f32 {
+    match local_hour {
+        0..=4 => 0.9,
+        5..=6 | 22..=23 => 0.6,
+        13..=14 | 19..=21 => 0.4,
+        _ => 0.2,
+    }
+}
+
+pub fn estimate(history: &[serde_json::Value], now_ms: u64, local_hour: u32) -> LoadEstimate {
+    let cutoff = now_ms.saturating_sub(WINDOW_MS);
+    let recent: Vec<&serde_json::Value> =
+        history.iter().filter(|i| timestamp(i) >= cutoff).collect();
+    let factors = vec![
+        Factor {
+            name: "error_rate".to_string(),
+            value: error_rate(&recent),
+            weight: 0.35,
+        },
+        Factor {
+            name: "tempo".to_string(),
+            value: tempo(&recent),
+            weight: 0.25,
+        },
+        Factor {
+            name: "task_switching".to_string(),
+            value: task_switching(&recent),
+            weight: 0.25,
+        },
+        Factor {
+            name: "time_of_day".to_string(),
+            value: time_of_day(local_hour),
+            weight: 0.15,
+        },
+    ];
+    let load = factors
+        .iter()
+        .map(|f| f.value * f.weight)
+        .sum::<f32>()
+        .clamp(0.0, 1.0);
+    LoadEstimate {
+        load,
+        confidence: (recent.len() as f32 / FULL_CONFIDENCE_SAMPLES as f32).min(1.0),
+        factors,
+        sample_size: recent.len(),
+    }
+}
+
+// Blend the estimate with a value the frontend measured itself, trusting the
+// estimate in proportion to its confidence. Alone, an estimate from a couple
+// of interactions (or just the clock) stays close to no load at all
+pub fn blend(estimate: &LoadEstimate, reported: Option<f32>) -> f32 {
+    let trusted = estimate.load * estimate.confidence;
+    match reported {
+        Some(reported) => trusted + reported.clamp(0.0, 1.0) * (1.0 - estimate.confidence),
+        None => trusted,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn interaction(timestamp_ms: u64, kind: &str, success: bool) -> serde_json::Value {
+        json!({
+            "timestamp_ms": timestamp_ms,
+            "intent": {"kind": kind},
+            "success": success,
+        })
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn error_rate_counts_failures_among_known_outcomes() {
+        let history = [
+            interaction(0, "search", true),
+            interaction(1, "search", false),
+            interaction(2, "install", true),
+            interaction(3, "install", true),
+            json!({"timestamp_ms": 4, "type": "click"}),
+        ];
+        let recent: Vec<&serde_json::Value> = history.iter().collect();
+        assert!(close(error_rate(&recent), 0.25));
+        assert_eq!(error_rate(&[]), 0.0);
+    }
+
+    #[test]
+    fn tempo_is_relative_to_a_hurried_pace() {
+        let burst: Vec<serde_json::Value> = (0..3)
+            .map(|i| interaction(i * 1000, "search", true))
+            .collect();
+        let recent: Vec<&serde_json::Value> = burst.iter().collect();
+        // Three within seconds still count over a whole minute
+        assert!(close(tempo(&recent), 0.5));
+        assert_eq!(tempo(&recent[..2]), 0.0);
+
+        let steady: Vec<serde_json::Value> = (0..6)
+            .map(|i| interaction(i * 24_000, "search", true))
+            .collect();
+        let recent: Vec<&serde_json::Value> = steady.iter().collect();
+        assert!(close(tempo(&recent), 0.5));
+
+        let hurried: Vec<serde_json::Value> = (0..20)
+            .map(|i| interaction(i * 3_000, "search", true))
+            .collect();
+        let recent: Vec<&serde_json::Value> = hurried.iter().collect();
+        assert_eq!(tempo(&recent), 1.0);
+    }
+
+    #[test]
+    fn task_switching_is_the_share_of_changes() {
+        let history = [
+            interaction(0, "search", true),
+            interaction(1, "search", true),
+            interaction(2, "install", true),
+            interaction(3, "search", true),
+        ];
+        let recent: Vec<&serde_json::Value> = history.iter().collect();
+        assert!(close(task_switching(&recent), 2.0 / 3.0));
+        assert_eq!(task_switching(&recent[..1]), 0.0);
+    }
+
+    #[test]
+    fn late_nights_weigh_most() {
+        assert_eq!(time_of_day(2), 0.9);
+        assert_eq!(time_of_day(6), 0.6);
+        assert_eq!(time_of_day(23), 0.6);
+        assert_eq!(time_of_day(13), 0.4);
+        assert_eq!(time_of_day(10), 0.2);
+    }
+
+    #[test]
+    fn estimate_only_looks_at_the_recent_window() {
+        let now = WINDOW_MS * 4;
+        let mut history = vec![interaction(0, "install", false)];
+        history.extend((0..12).map(|i| interaction(now - 11_000 + i * 1_000, "search", true)));
+        let estimate = estimate(&history, now, 10);
+        assert_eq!(estimate.sample_size, 12);
+        assert_eq!(estimate.confidence, 1.0);
+        let error_rate = estimate.factors.iter().find(|f| f.name == "error_rate");
+        assert_eq!(error_rate.map(|f| f.value), Some(0.0));
+        // Twelve in twelve seconds is hurried; nothing else adds load at 10am
+        assert!(close(estimate.load, 0.25 + 0.15 * 0.2));
+    }
+
+    #[test]
+    fn estimate_of_no_history_has_no_confidence() {
+        let estimate = estimate(&[], WINDOW_MS, 2);
+        assert_eq!(estimate.sample_size, 0);
+        assert_eq!(estimate.confidence, 0.0);
+        assert!(close(estimate.load, 0.15 * 0.9));
+    }
+
+    #[test]
+    fn blend_scales_by_confidence() {
+        let estimate = |load: f32, confidence: f32| LoadEstimate {
+            load,
+            confidence,
+            factors: Vec::new(),
+            sample_size: 0,
+        };
+        // The clock alone doesn't make an empty history look loaded
+        assert_eq!(blend(&estimate(0.135, 0.0), None), 0.0);
+        assert!(close(blend(&estimate(0.8, 0.5), None), 0.4));
+        assert!(close(blend(&estimate(0.8, 0.5), Some(0.2)), 0.5));
+        assert!(close(blend(&estimate(0.8, 1.0), Some(0.0)), 0.8));
+        assert!(close(blend(&estimate(0.0, 0.0), Some(1.5)), 1.0));
+    }
+}
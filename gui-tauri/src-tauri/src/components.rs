@@ -0,0 +1,393 @@
+// Component type registry
+//
+// Every component the frontend can render is a registered type that declares
+// its capabilities, its default state and the grid area it usually takes. The
+// built-in types are always there; plugins bring their own types along with
+// their phrases, and more can be registered at runtime and are kept in the
+// config dir for the next start. Components are created from their type, and
+// get_components serves them with the capabilities their type, and every
+// plugin, currently adds, each negotiated against what this machine can do.
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::AppHandle;
+
+use crate::capabilities::{self, Capability};
+use crate::plugins::{IntentPlugin, PluginRegistry};
+use crate::{storage, tasks, ComponentState};
+
+const TYPES_FILE: &str = "component-types.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentType {
+    // "SearchInput", "ResultsList", ...
+    pub component_type: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    #[serde(default)]
+    pub default_state: serde_json::Value,
+    // The grid area it usually takes; also the prefix of its ids
+    #[serde(default)]
+    pub area: Option<String>,
+    #[serde(default)]
+    pub builtin: bool,
+    // The plugin that registered it
+    #[serde(default)]
+    pub plugin: Option<String>,
+}
+
+impl ComponentType {
+    // "search" for SearchInput; "my-widget" for a MyWidget without an area
+    pub fn slug(&self) -> String {
+        self.area.clone().unwrap_or_else(|| {
+            let mut slug = String::new();
+            for c in self.component_type.chars() {
+                if c.is_ascii_uppercase() && !slug.is_empty() {
+                    slug.push('-');
+                }
+                slug.push(c.to_ascii_lowercase());
+            }
+            slug
+        })
+    }
+
+    // A component of this type with its default state
+    pub fn instance(&self, id: &str) -> ComponentState {
+        let mut state = match &self.default_state {
+            serde_json::Value::Null => serde_json::json!({}),
+            state => state.clone(),
+        };
+        if let Some(area) = &self.area {
+            state["area"] = serde_json::json!(area);
+        }
+        ComponentState {
+            id: id.to_string(),
+            component_type: self.component_type.clone(),
+            state,
+            capabilities: self
+                .capabilities
+                .iter()
+                .map(|name| Capability::named(name))
+                .collect(),
+        }
+    }
+}
+
+fn builtin_type(
+    component_type: &str,
+    description: &str,
+    area: &str,
+    default_state: serde_json::Value,
+    capabilities: &[&str],
+) -> ComponentType {
+    ComponentType {
+        component_type: component_type.to_string(),
+        description: description.to_string(),
+        capabilities: capabilities.iter().map(|c| c.to_string()).collect(),
+        default_state,
+        area: Some(area.to_string()),
+        builtin: true,
+        plugin: None,
+    }
+}
+
+pub fn builtin() -> Vec<ComponentType> {
+    vec![
+        builtin_type(
+            "SearchInput",
+            "The search box, typed or spoken into",
+            "search",
+            serde_json::json!({"value": "", "suggestions": []}),
+            &["search", "voice"],
+        ),
+        builtin_type(
+            "ResultsList",
+            "Search results and the outcome of each command",
+            "results",
+            serde_json::json!({"results": []}),
+            &["display", "sort", "profile-select"],
+        ),
+        builtin_type(
+            "GuidePanel",
+            "Explains each step and what to try next",
+            "guide",
+            serde_json::json!({"tips": [], "step": null}),
+            &["display", "explain"],
+        ),
+        builtin_type(
+            "HistoryList",
+            "Earlier commands, to run again or undo",
+            "history",
+            serde_json::json!({"entries": []}),
+            &["display", "rerun", "undo"],
+        ),
+        builtin_type(
+            "CommandDetails",
+            "The exact command behind a result",
+            "details",
+            serde_json::json!({"command": null, "explanation": null}),
+            &["display", "copy"],
+        ),
+        builtin_type(
+            "ProgressPanel",
+            "Operations that are still running",
+            "status",
+            serde_json::json!({"operations": []}),
+            &["display", "cancel"],
+        ),
+        builtin_type(
+            "TerminalPanel",
+            "A supervised terminal",
+            "terminal",
+            serde_json::json!({"id": null, "supervised": true}),
+            &["terminal"],
+        ),
+        builtin_type(
+            "LiveRegion",
+            "Announces results to screen readers",
+            "announcer",
+            serde_json::json!({"message": "", "politeness": "polite"}),
+            &["announce", "voice"],
+        ),
+    ]
+}
+
+// A built-in type; the names are fixed, so a missing one is a bug
+pub fn builtin_named(component_type: &str) -> ComponentType {
+    builtin()
+        .into_iter()
+        .find(|t| t.component_type == component_type)
+        .unwrap_or_else(|| panic!("{} is not a built-in component type", component_type))
+}
+
+fn valid_type_name(name: &str) -> bool {
+    name.len() <= 64
+        && name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn valid_slug(name: &str) -> bool {
+    name.len() <= 64
+        && name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+fn check(spec: &ComponentType) -> anyhow::Result<()> {
+    if !valid_type_name(&spec.component_type) {
+        bail!(
+            "\"{}\" isn't a component type name; use letters and digits, starting with a capital",
+            spec.component_type
+        );
+    }
+    if let Some(capability) = spec.capabilities.iter().find(|c| !valid_slug(c)) {
+        bail!(
+            "{} declares the capability \"{}\"; use lower-case words joined by '-'",
+            spec.component_type,
+            capability
+        );
+    }
+    if let Some(area) = spec.area.as_deref().filter(|a| !valid_slug(a)) {
+        bail!(
+            "{} usually takes the area \"{}\"; use lower-case words joined by '-'",
+            spec.component_type,
+            area
+        );
+    }
+    if !(spec.default_state.is_object() || spec.default_state.is_null()) {
+        bail!(
+            "The default state of {} must be an object",
+            spec.component_type
+        );
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct ComponentRegistry {
+    types: BTreeMap<String, ComponentType>,
+}
+
+impl ComponentRegistry {
+    // The built-in types, the ones registered at runtime and every plugin's
+    pub fn load(plugins: &PluginRegistry) -> Self {
+        let mut registry = ComponentRegistry::default();
+        for spec in builtin() {
+            registry.types.insert(spec.component_type.clone(), spec);
+        }
+        let stored: Vec<ComponentType> = storage::load(TYPES_FILE).unwrap_or_default();
+        for spec in stored {
+            let _ = registry.register(spec);
+        }
+        for plugin in plugins.iter() {
+            if let Err(e) = registry.register_plugin(plugin) {
+                eprintln!("Skipping the component types of {}: {}", plugin.id(), e);
+            }
+        }
+        registry
+    }
+
+    pub fn get(&self, component_type: &str) -> Option<&ComponentType> {
+        self.types.get(component_type)
+    }
+
+    pub fn list(&self) -> Vec<ComponentType> {
+        self.types.values().cloned().collect()
+    }
+
+    pub fn register(&mut self, spec: ComponentType) -> anyhow::Result<()> {
+        check(&spec)?;
+        if self.types.contains_key(&spec.component_type) {
+            bail!(
+                "A component type called {} is already registered",
+                spec.component_type
+            );
+        }
+        let spec = ComponentType {
+            builtin: false,
+            ..spec
+        };
+        self.types.insert(spec.component_type.clone(), spec);
+        Ok(())
+    }
+
+    pub fn unregister(&mut self, component_type: &str) -> anyhow::Result<ComponentType> {
+        match self.types.get(component_type) {
+            Some(spec) if spec.builtin => bail!("Built-in component types cannot be removed"),
+            Some(spec) if spec.plugin.is_some() => bail!(
+                "{} belongs to the {} plugin; remove the plugin instead",
+                component_type,
+                spec.plugin.as_deref().unwrap_or_default()
+            ),
+            Some(_) => self
+                .types
+                .remove(component_type)
+                .ok_or_else(|| anyhow!("No component type called {}", component_type)),
+            None => bail!("No component type called {}", component_type),
+        }
+    }
+
+    // Every type a plugin declares, or none of them
+    pub fn register_plugin(&mut self, plugin: &dyn IntentPlugin) -> anyhow::Result<()> {
+        let specs = plugin.component_types();
+        for spec in &specs {
+            check(spec)?;
+            if self.types.contains_key(&spec.component_type) {
+                bail!(
+                    "{} declares {}, which is already registered",
+                    plugin.id(),
+                    spec.component_type
+                );
+            }
+        }
+        for spec in specs {
+            let spec = ComponentType {
+                builtin: false,
+                plugin: Some(plugin.id().to_string()),
+                ..spec
+            };
+            self.types.insert(spec.component_type.clone(), spec);
+        }
+        Ok(())
+    }
+
+    pub fn unregister_plugin(&mut self, plugin: &str) {
+        self.types
+            .retain(|_, spec| spec.plugin.as_deref() != Some(plugin));
+    }
+
+    // The types registered at runtime, kept for the next start
+    fn save(&self) -> anyhow::Result<()> {
+        let runtime: Vec<&ComponentType> = self
+            .types
+            .values()
+            .filter(|spec| !spec.builtin && spec.plugin.is_none())
+            .collect();
+        storage::save(TYPES_FILE, &runtime).map(|_| ())
+    }
+
+    // A new component of a registered type
+    pub fn instantiate(&self, component_type: &str, id: &str) -> anyhow::Result<ComponentState> {
+        self.get(component_type)
+            .map(|spec| spec.instance(id))
+            .ok_or_else(|| anyhow!("Unknown component type \"{}\"", component_type))
+    }
+
+    // The components as the frontend sees them: their own capabilities plus
+    // the ones their type and the plugins add now
+    pub fn serve(
+        &self,
+        components: &[ComponentState],
+        plugins: &PluginRegistry,
+    ) -> Vec<ComponentState> {
+        let mut components = components.to_vec();
+        for component in components.iter_mut() {
+            let mut names: Vec<String> = component
+                .capabilities
+                .iter()
+                .map(|c| c.name.clone())
+                .collect();
+            let declared = self
+                .get(&component.component_type)
+                .map(|spec| spec.capabilities.clone())
+                .unwrap_or_default();
+            for capability in declared
+                .into_iter()
+                .chain(plugins.capabilities_for(&component.component_type))
+            {
+                if !names.contains(&capability) {
+                    names.push(capability);
+                }
+            }
+            component.capabilities = capabilities::negotiate(&names, false);
+        }
+        components
+    }
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn list_component_types(app: AppHandle) -> Vec<ComponentType> {
+    tasks::blocking_value(&app, "List component types", |state| {
+        state.component_types.blocking_lock().list()
+    })
+    .await
+}
+
+// Register a component type and keep it for the next start
+#[tauri::command]
+pub async fn register_component_type(spec: ComponentType, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Register component type", move |state| {
+        let mut registry = state.component_types.blocking_lock();
+        let name = spec.component_type.clone();
+        let result = registry.register(spec).and_then(|()| {
+            registry.save()?;
+            registry
+                .get(&name)
+                .cloned()
+                .ok_or_else(|| anyhow!("{} wasn't registered", name))
+        });
+        crate::respond(result)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn unregister_component_type(
+    component_type: String,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Unregister component type", move |state| {
+        let mut registry = state.component_types.blocking_lock();
+        let result = registry
+            .unregister(&component_type)
+            .and_then(|spec| registry.save().map(|()| spec));
+        crate::respond(result)
+    })
+    .await
+}
@@ -0,0 +1,326 @@
+// Semantic comparison of two NixOS configurations
+//
+// Each side is a file, a directory of modules, a pasted snippet or a path at
+// a git revision. Every .nix file in it is read into option assignments
+// (nested attrsets flattened to "services.openssh.enable"), and the two sides
+// are compared option by option rather than line by line: moving an option to
+// another file or reformatting it is no difference, and lists such as
+// environment.systemPackages are compared item by item. Differences are
+// grouped by area (packages, services, boot, ...) for the compare view.
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::{explain, system, tasks};
+
+// Category and the option prefixes that belong to it, in display order
+const CATEGORIES: &[(&str, &[&str])] = &[
+    (
+        "packages",
+        &[
+            "environment.systemPackages",
+            "home.packages",
+            "fonts.packages",
+        ],
+    ),
+    ("services", &["services.", "systemd."]),
+    ("boot", &["boot."]),
+    ("networking", &["networking."]),
+    ("hardware", &["hardware.", "powerManagement."]),
+    ("users", &["users."]),
+    ("security", &["security."]),
+    ("programs", &["programs."]),
+    ("nix", &["nix.", "nixpkgs."]),
+    ("locale", &["time.", "i18n.", "console.", "location."]),
+    (
+        "system",
+        &["system.", "environment.", "fileSystems.", "swapDevices"],
+    ),
+];
+const OTHER: &str = "other";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConfigSource {
+    // A single file or a directory of modules
+    Path {
+        path: String,
+    },
+    // Shared text, e.g. a configuration pasted by a friend
+    Text {
+        text: String,
+        label: Option<String>,
+    },
+    // A file or directory as of a revision of a git repository
+    Git {
+        repo: String,
+        rev: String,
+        path: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    // Only set on the right
+    Added,
+    // Only set on the left
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Difference {
+    pub option: String,
+    pub kind: ChangeKind,
+    pub left: Option<String>,
+    pub right: Option<String>,
+    // For lists: items only on one side
+    pub added_items: Vec<String>,
+    pub removed_items: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryDiff {
+    pub category: String,
+    pub differences: Vec<Difference>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigDiff {
+    pub left: String,
+    pub right: String,
+    pub categories: Vec<CategoryDiff>,
+    // Options set to the same value on both sides
+    pub unchanged: usize,
+}
+
+// All values an option is given across the files of one side
+type Options = BTreeMap<String, Vec<String>>;
+
+impl ConfigSource {
+    fn label(&self) -> String {
+        match self {
+            ConfigSource::Path { path } => path.clone(),
+            ConfigSource::Text { label, .. } => {
+                label.clone().unwrap_or_else(|| "shared".to_string())
+            }
+            ConfigSource::Git { rev, path, .. } => match path {
+                Some(path) => format!("{}:{}", rev, path),
+                None => rev.clone(),
+            },
+        }
+    }
+
+    // (name, contents) of every .nix file
+    fn files(&self) -> anyhow::Result<Vec<(String, String)>> {
+        match self {
+            ConfigSource::Path { path } => {
+                let path = Path::new(path);
+                if path.is_dir() {
+                    let mut files = Vec::new();
+                    collect_dir(path, &mut files)?;
+                    Ok(files)
+                } else {
+                    let text = fs::read_to_string(path)
+                        .with_context(|| format!("reading {}", path.display()))?;
+                    Ok(vec![(path.display().to_string(), text)])
+                }
+            }
+            ConfigSource::Text { text, .. } => Ok(vec![(self.label(), text.clone())]),
+            ConfigSource::Git { repo, rev, path } => {
+                let repo = Path::new(repo);
+                let mut args = vec!["ls-tree", "-r", "--name-only", rev.as_str()];
+                if let Some(path) = path {
+                    args.extend(["--", path.as_str()]);
+                }
+                let listing = system::run_in(repo, "git", &args)?;
+                listing
+                    .lines()
+                    .filter(|name| name.ends_with(".nix"))
+                    .map(|name| {
+                        let spec = format!("{}:{}", rev, name);
+                        let text = system::run_in(repo, "git", &["show", &spec])?;
+                        Ok((name.to_string(), text))
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+fn collect_dir(dir: &Path, files: &mut Vec<(String, String)>) -> anyhow::Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("reading {}", dir.display()))?
+        .flatten()
+        .map(|e| e.path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        let hidden = path
+            .file_name()
+            .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+        if hidden || path.is_symlink() {
+            continue;
+        }
+        if path.is_dir() {
+            collect_dir(&path, files)?;
+        } else if path.extension().is_some_and(|e| e == "nix") {
+            let text =
+                fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+            files.push((path.display().to_string(), text));
+        }
+    }
+    Ok(())
+}
+
+fn normalize(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn options(source: &ConfigSource) -> anyhow::Result<Options> {
+    let files = source.files()?;
+    if files.is_empty() {
+        bail!("No .nix files found in {}", source.label());
+    }
+    let mut options = Options::new();
+    for (_, text) in files {
+        for (path, value) in explain::assignments(&text) {
+            options.entry(path).or_default().push(normalize(&value));
+        }
+    }
+    Ok(options)
+}
+
+fn is_list(values: &[String]) -> bool {
+    values.iter().all(|v| v.contains('['))
+}
+
+// List definitions merge in Nix, so a side's items are the union of them all
+fn items(values: &[String]) -> BTreeSet<String> {
+    values
+        .iter()
+        .flat_map(|v| explain::packages_in(v))
+        .collect()
+}
+
+fn category(option: &str) -> &'static str {
+    CATEGORIES
+        .iter()
+        .find(|(_, prefixes)| prefixes.iter().any(|p| option.starts_with(p)))
+        .map_or(OTHER, |(name, _)| name)
+}
+
+fn compare_option(
+    option: &str,
+    left: Option<&[String]>,
+    right: Option<&[String]>,
+) -> Option<Difference> {
+    let shown = |values: Option<&[String]>| values.map(|v| v.join(" ++ "));
+    let (kind, added_items, removed_items) = match (left, right) {
+        (None, None) => return None,
+        (None, Some(_)) => (ChangeKind::Added, Vec::new(), Vec::new()),
+        (Some(_), None) => (ChangeKind::Removed, Vec::new(), Vec::new()),
+        (Some(l), Some(r)) if is_list(l) && is_list(r) => {
+            let (l, r) = (items(l), items(r));
+            if l == r {
+                return None;
+            }
+            (
+                ChangeKind::Changed,
+                r.difference(&l).cloned().collect(),
+                l.difference(&r).cloned().collect(),
+            )
+        }
+        (Some(l), Some(r)) => {
+            if l == r {
+                return None;
+            }
+            (ChangeKind::Changed, Vec::new(), Vec::new())
+        }
+    };
+    Some(Difference {
+        option: option.to_string(),
+        kind,
+        left: shown(left),
+        right: shown(right),
+        added_items,
+        removed_items,
+    })
+}
+
+fn diff(left: String, right: String, l: &Options, r: &Options) -> ConfigDiff {
+    let names: BTreeSet<&String> = l.keys().chain(r.keys()).collect();
+    let mut grouped: BTreeMap<&str, Vec<Difference>> = BTreeMap::new();
+    let mut unchanged = 0;
+    for name in names {
+        match compare_option(
+            name,
+            l.get(name).map(Vec::as_slice),
+            r.get(name).map(Vec::as_slice),
+        ) {
+            Some(difference) => grouped.entry(category(name)).or_default().push(difference),
+            None => unchanged += 1,
+        }
+    }
+    let order = CATEGORIES.iter().map(|(name, _)| *name).chain([OTHER]);
+    let categories = order
+        .filter_map(|name| {
+            grouped.remove(name).map(|differences| CategoryDiff {
+                category: name.to_string(),
+                differences,
+            })
+        })
+        .collect();
+    ConfigDiff {
+        left,
+        right,
+        categories,
+        unchanged,
+    }
+}
+
+pub fn compare(left: &ConfigSource, right: &ConfigSource) -> anyhow::Result<ConfigDiff> {
+    let (l, r) = (options(left)?, options(right)?);
+    Ok(diff(left.label(), right.label(), &l, &r))
+}
+
+// What merging `addition` into `base` would change: definitions add up the
+// way Nix merges modules, so lists gain items and a scalar set on both sides
+// shows as changed (a conflict the merge would have to settle)
+pub fn merge_preview(base: &ConfigSource, addition: &ConfigSource) -> anyhow::Result<ConfigDiff> {
+    let before = options(base)?;
+    let mut after = before.clone();
+    for (name, values) in options(addition)? {
+        let merged = after.entry(name).or_default();
+        for value in values {
+            if !merged.contains(&value) {
+                merged.push(value);
+            }
+        }
+    }
+    Ok(diff(
+        base.label(),
+        format!("{} + {}", base.label(), addition.label()),
+        &before,
+        &after,
+    ))
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn compare_configs(
+    left: ConfigSource,
+    right: ConfigSource,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Compare configs", move |_| {
+        crate::respond(compare(&left, &right))
+    })
+    .await
+}
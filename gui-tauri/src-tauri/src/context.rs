@@ -0,0 +1,137 @@
+// Multi-turn conversation context
+//
+// Remembers what the last turns were about so follow-ups like "install it",
+// "remove that one" or "the second result" resolve to concrete packages.
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+
+use crate::nlp::Intent;
+
+const PRONOUNS: &[&str] = &[
+    "it", "that", "this", "that one", "this one", "them", "those", "these",
+];
+const ORDINALS: &[&str] = &[
+    "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth", "tenth",
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConversationContext {
+    pub last_intent: Option<Intent>,
+    // Package names from the most recent search, in display order
+    pub results: Vec<String>,
+    // Packages the conversation is currently about
+    pub packages: Vec<String>,
+    pub turns: usize,
+}
+
+// "second result" / "the 2nd one" / "number 2" / "last" -> index into results
+fn result_index(reference: &str, len: usize) -> Option<usize> {
+    let words: Vec<&str> = reference
+        .split(|c: char| c.is_whitespace() || c == '-')
+        .filter(|w| {
+            !matches!(
+                *w,
+                "the" | "one" | "result" | "package" | "option" | "number"
+            )
+        })
+        .collect();
+    let [word] = words.as_slice() else {
+        return None;
+    };
+    if *word == "last" {
+        return len.checked_sub(1);
+    }
+    if let Some(position) = ORDINALS.iter().position(|o| o == word) {
+        return Some(position);
+    }
+    let word = word.trim_start_matches('#');
+    let digits = ["st", "nd", "rd", "th"]
+        .iter()
+        .find_map(|suffix| word.strip_suffix(suffix))
+        .unwrap_or(word);
+    digits.parse::<usize>().ok().and_then(|n| n.checked_sub(1))
+}
+
+fn is_reference(word: &str) -> bool {
+    PRONOUNS.contains(&word) || word.contains("result") || result_index(word, 1).is_some()
+}
+
+impl ConversationContext {
+    fn resolve_reference(&self, reference: &str) -> anyhow::Result<Vec<String>> {
+        if PRONOUNS.contains(&reference) {
+            if !self.packages.is_empty() {
+                return Ok(self.packages.clone());
+            }
+            if let Some(top) = self.results.first() {
+                return Ok(vec![top.clone()]);
+            }
+            bail!("I'm not sure what \"{}\" refers to yet", reference);
+        }
+        let Some(index) = result_index(reference, self.results.len()) else {
+            bail!("I couldn't tell which result \"{}\" means", reference);
+        };
+        match self.results.get(index) {
+            Some(name) => Ok(vec![name.clone()]),
+            None if self.results.is_empty() => {
+                bail!("There are no earlier results to pick from; try a search first")
+            }
+            None => bail!(
+                "There are only {} results; pick one of those",
+                self.results.len()
+            ),
+        }
+    }
+
+    // Replace pronouns and result references in an intent with package names
+    pub fn resolve(&self, intent: &mut Intent) -> anyhow::Result<()> {
+        let (Intent::Install { packages } | Intent::Remove { packages }) = intent else {
+            return Ok(());
+        };
+        let mut resolved = Vec::new();
+        for package in packages.iter() {
+            if is_reference(package) {
+                for name in self.resolve_reference(package)? {
+                    if !resolved.contains(&name) {
+                        resolved.push(name);
+                    }
+                }
+            } else {
+                resolved.push(package.clone());
+            }
+        }
+        *packages = resolved;
+        Ok(())
+    }
+
+    // Remember what a completed turn was about
+    pub fn remember(&mut self, intent: &Intent, response: &serde_json::Value) {
+        self.turns += 1;
+        match intent {
+            Intent::Search { .. } => {
+                let results = response
+                    .get("results")
+                    .or_else(|| response.get("data"))
+                    .and_then(|r| r.as_array());
+                self.results = results
+                    .map(|list| {
+                        list.iter()
+                            .filter_map(|r| {
+                                r.get("attr")
+                                    .or_else(|| r.get("name"))
+                                    .and_then(|n| n.as_str())
+                                    .map(String::from)
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                self.packages.clear();
+            }
+            Intent::Install { packages } | Intent::Remove { packages } => {
+                self.packages = packages.clone();
+            }
+            _ => {}
+        }
+        self.last_intent = Some(intent.clone());
+    }
+}
@@ -0,0 +1,261 @@
+// Context-menu actions for packages, generations and log lines
+//
+// Right-click menus anywhere in the UI ask here what applies to the thing
+// under the pointer. Each action says whether it can run right now (and why
+// not), its blast radius from the safety classification, and the command and
+// arguments that carry it out, so every menu offers the same actions with
+// the same risk labels.
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::inventory::{self, Source};
+use crate::nlp::Intent;
+use crate::safety::{self, BlastRadius};
+use crate::{boot, nix, tasks};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MenuContext {
+    // A row in search results or the installed list
+    Package {
+        attr: String,
+        // Looked up in the profile when the caller doesn't know
+        installed: Option<bool>,
+    },
+    Generation {
+        number: u32,
+    },
+    LogLine {
+        text: String,
+        // The systemd unit the line came from, if any
+        unit: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MenuAction {
+    pub id: String,
+    pub label: String,
+    pub enabled: bool,
+    // Why the action is disabled
+    pub reason: Option<String>,
+    pub risk: BlastRadius,
+    // The Tauri command to invoke and its arguments
+    pub command: String,
+    pub args: serde_json::Value,
+}
+
+impl MenuAction {
+    fn new(
+        id: &str,
+        label: &str,
+        risk: BlastRadius,
+        command: &str,
+        args: serde_json::Value,
+    ) -> Self {
+        MenuAction {
+            id: id.to_string(),
+            label: label.to_string(),
+            enabled: true,
+            reason: None,
+            risk,
+            command: command.to_string(),
+            args,
+        }
+    }
+
+    // Runs `intent` through perform_action, which applies the confirmation policy
+    fn intent(
+        id: &str,
+        label: &str,
+        action: &str,
+        intent: Intent,
+        params: serde_json::Value,
+    ) -> Self {
+        MenuAction::new(
+            id,
+            label,
+            safety::classify(&intent),
+            "perform_action",
+            serde_json::json!({"action": action, "params": params}),
+        )
+    }
+
+    fn disabled_unless(mut self, condition: bool, reason: &str) -> Self {
+        if !condition {
+            self.enabled = false;
+            self.reason = Some(reason.to_string());
+        }
+        self
+    }
+}
+
+fn is_installed(attr: &str) -> bool {
+    let attr = nix::short_attr(attr);
+    inventory::collect()
+        .iter()
+        .filter(|item| item.source == Source::NixProfile)
+        .any(|item| nix::short_attr(&item.id) == attr || item.name == attr)
+}
+
+fn package_actions(attr: &str, installed: Option<bool>) -> Vec<MenuAction> {
+    let attr = nix::short_attr(attr);
+    let installed = installed.unwrap_or_else(|| is_installed(&attr));
+    let packages = vec![attr.clone()];
+    vec![
+        MenuAction::intent(
+            "install",
+            "Install",
+            "install",
+            Intent::Install {
+                packages: packages.clone(),
+            },
+            serde_json::json!({"package": attr}),
+        )
+        .disabled_unless(!installed, "Already installed"),
+        MenuAction::intent(
+            "remove",
+            "Remove",
+            "remove",
+            Intent::Remove { packages },
+            serde_json::json!({"package": attr}),
+        )
+        .disabled_unless(installed, "Not installed"),
+        MenuAction::new(
+            "check-license",
+            "Check license",
+            BlastRadius::ReadOnly,
+            "check_package_license",
+            serde_json::json!({"package": attr}),
+        ),
+        MenuAction::intent(
+            "explain",
+            "What is this?",
+            "explain",
+            Intent::Explain {
+                topic: attr.clone(),
+            },
+            serde_json::json!({"topic": attr}),
+        ),
+    ]
+}
+
+fn generation_actions(number: u32) -> anyhow::Result<Vec<MenuAction>> {
+    let generation = boot::list_generations()
+        .into_iter()
+        .find(|g| g.number == number)
+        .ok_or_else(|| anyhow!("Generation {} doesn't exist", number))?;
+    let mut actions = vec![
+        MenuAction::intent(
+            "rollback",
+            "Switch to this generation",
+            "rollback",
+            Intent::Rollback {
+                generation: Some(number),
+            },
+            serde_json::json!({"generation": number}),
+        )
+        .disabled_unless(!generation.current, "This generation is already running"),
+        // Only changes which entry the bootloader picks; the old one stays
+        MenuAction::new(
+            "set-default-boot",
+            "Boot this by default",
+            BlastRadius::Reversible,
+            "set_default_boot_generation",
+            serde_json::json!({"generation": number}),
+        ),
+        MenuAction::new(
+            "delete",
+            "Delete generation",
+            BlastRadius::Destructive,
+            "delete_boot_generations",
+            serde_json::json!({"generations": [number]}),
+        )
+        .disabled_unless(
+            !generation.current && !generation.booted,
+            "The running or booted generation can't be deleted",
+        ),
+    ];
+    // Specialisations can only be switched to within the running generation
+    for name in &generation.specialisations {
+        actions.push(
+            MenuAction::new(
+                &format!("specialisation:{}", name),
+                &format!("Switch to specialisation \"{}\"", name),
+                BlastRadius::Reversible,
+                "switch_to_specialisation",
+                serde_json::json!({"name": name}),
+            )
+            .disabled_unless(generation.current, "Switch to this generation first"),
+        );
+    }
+    Ok(actions)
+}
+
+// The package a log line is about: a store path, or an attribute nix says is missing
+fn package_in(text: &str) -> Option<String> {
+    if let Some(start) = text.find("/nix/store/") {
+        let path = text[start..]
+            .split(|c: char| c.is_whitespace() || "'\"`:,".contains(c))
+            .next()
+            .unwrap_or("");
+        let name = inventory::store_path_name(path.split('/').nth(3).unwrap_or(""));
+        return Some(name).filter(|n| !n.is_empty());
+    }
+    let rest = text.split_once("attribute '")?.1;
+    let (attr, after) = rest.split_once('\'')?;
+    after.contains("missing").then(|| attr.to_string())
+}
+
+fn log_line_actions(text: &str, unit: Option<&str>) -> Vec<MenuAction> {
+    let text = text.trim();
+    let mut actions = vec![MenuAction::new(
+        "explain",
+        "Explain this line",
+        BlastRadius::ReadOnly,
+        "explain",
+        serde_json::json!({"commandOrConfig": text}),
+    )
+    .disabled_unless(!text.is_empty(), "The line is empty")];
+    if let Some(package) = package_in(text) {
+        actions.push(MenuAction::intent(
+            "search-package",
+            &format!("Search for \"{}\"", package),
+            "search",
+            Intent::Search {
+                query: package.clone(),
+            },
+            serde_json::json!({"query": package}),
+        ));
+    }
+    if let Some(unit) = unit {
+        actions.push(MenuAction::new(
+            "unit-logs",
+            &format!("Show all logs of {}", unit),
+            BlastRadius::ReadOnly,
+            "get_user_service_logs",
+            serde_json::json!({"unit": unit}),
+        ));
+    }
+    actions
+}
+
+pub fn actions(context: &MenuContext) -> anyhow::Result<Vec<MenuAction>> {
+    match context {
+        MenuContext::Package { attr, installed } => Ok(package_actions(attr, *installed)),
+        MenuContext::Generation { number } => generation_actions(*number),
+        MenuContext::LogLine { text, unit } => Ok(log_line_actions(text, unit.as_deref())),
+    }
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_context_actions(context: MenuContext, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Get context actions", move |_| {
+        crate::respond(actions(&context))
+    })
+    .await
+}
@@ -0,0 +1,500 @@
+// Demo mode: a guided tour on a pretend system
+//
+// While a demo runs, intents are answered by a small mock Nix backend (a
+// package catalog, an installed set and a list of generations, all in memory)
+// instead of the real one, so installing, updating and rolling back look and
+// feel real without changing anything. Privileged commands are refused
+// outright as a second line of defence, and nothing done in the demo goes into
+// the history. The scenario runner walks through a script of narrated
+// requests, one step at a time or playing by itself, each going through the
+// same query path as typed input (confirmations included). The built-in
+// script can be replaced with demo-scenario.json in the config dir. Exiting at
+// any point throws the pretend system away.
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::Mutex;
+
+use crate::nix::Package;
+use crate::nlp::Intent;
+use crate::{progress, storage, tasks, AppState};
+
+const SCENARIO_FILE: &str = "demo-scenario.json";
+// How long a pretend package takes to install, so progress is visible
+const STEP_DELAY: Duration = Duration::from_millis(400);
+
+// (attribute, version, description)
+const CATALOG: &[(&str, &str, &str)] = &[
+    (
+        "firefox",
+        "131.0",
+        "Web browser built from Firefox source tree",
+    ),
+    (
+        "chromium",
+        "129.0.6668.100",
+        "Open source web browser from Google",
+    ),
+    ("vscode", "1.94.2", "Code editor developed by Microsoft"),
+    (
+        "neovim",
+        "0.10.2",
+        "Vim text editor fork focused on extensibility",
+    ),
+    ("htop", "3.3.0", "Interactive process viewer"),
+    ("git", "2.46.1", "Distributed version control system"),
+    ("ripgrep", "14.1.1", "Fast line-oriented search tool"),
+    (
+        "vlc",
+        "3.0.21",
+        "Cross-platform media player and streaming server",
+    ),
+    ("gimp", "2.10.38", "GNU Image Manipulation Program"),
+    (
+        "libreoffice",
+        "24.2.6.2",
+        "Comprehensive, professional-quality productivity suite",
+    ),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Step {
+    // Shown (or read out) before the request runs
+    pub narration: String,
+    pub query: String,
+    // Answer a confirmation prompt the way the user would
+    #[serde(default = "yes")]
+    pub confirm: bool,
+    // Pause before this step when playing by itself
+    #[serde(default = "default_pause")]
+    pub pause_ms: u64,
+}
+
+fn yes() -> bool {
+    true
+}
+
+fn default_pause() -> u64 {
+    4000
+}
+
+fn step(narration: &str, query: &str) -> Step {
+    Step {
+        narration: narration.to_string(),
+        query: query.to_string(),
+        confirm: true,
+        pause_ms: default_pause(),
+    }
+}
+
+fn builtin_scenario() -> Vec<Step> {
+    vec![
+        step(
+            "Looking for software? Just ask for it in your own words.",
+            "search firefox",
+        ),
+        step(
+            "Found it. Installing is one sentence too.",
+            "install firefox",
+        ),
+        step("Everything you installed, at a glance.", "what's installed"),
+        step(
+            "Add a couple more tools at once.",
+            "install htop and ripgrep",
+        ),
+        step(
+            "Keeping the system up to date makes a new generation.",
+            "update my system",
+        ),
+        step(
+            "Something broke after the update? Go back to how it was.",
+            "roll back",
+        ),
+        step(
+            "Old generations take disk space; clean them up when you're sure.",
+            "collect garbage",
+        ),
+    ]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Generation {
+    pub number: u32,
+    pub packages: Vec<String>,
+    pub description: String,
+}
+
+// The pretend system the demo changes
+#[derive(Debug, Clone)]
+struct MockSystem {
+    generations: Vec<Generation>,
+    current: usize,
+}
+
+impl MockSystem {
+    fn new() -> Self {
+        MockSystem {
+            generations: vec![Generation {
+                number: 1,
+                packages: vec!["git".to_string()],
+                description: "Initial system".to_string(),
+            }],
+            current: 0,
+        }
+    }
+
+    fn installed(&self) -> &[String] {
+        &self.generations[self.current].packages
+    }
+
+    fn commit(&mut self, packages: Vec<String>, description: String) -> u32 {
+        let number = self.generations.iter().map(|g| g.number).max().unwrap_or(0) + 1;
+        self.generations.push(Generation {
+            number,
+            packages,
+            description,
+        });
+        self.current = self.generations.len() - 1;
+        number
+    }
+}
+
+struct Demo {
+    steps: Vec<Step>,
+    next: usize,
+    system: MockSystem,
+    autoplay: Option<Arc<AtomicBool>>,
+}
+
+static DEMO: Mutex<Option<Demo>> = Mutex::const_new(None);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DemoStatus {
+    pub active: bool,
+    pub playing: bool,
+    pub step: usize,
+    pub total: usize,
+    pub installed: Vec<String>,
+    pub generations: Vec<Generation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepResult {
+    pub index: usize,
+    pub total: usize,
+    pub narration: String,
+    pub query: String,
+    // The confirmation that was asked for and given, if any
+    pub confirmation: Option<serde_json::Value>,
+    pub response: serde_json::Value,
+}
+
+pub fn active() -> bool {
+    DEMO.blocking_lock().is_some()
+}
+
+pub fn scenario() -> Vec<Step> {
+    storage::load(SCENARIO_FILE)
+        .ok()
+        .filter(|steps: &Vec<Step>| !steps.is_empty())
+        .unwrap_or_else(builtin_scenario)
+}
+
+fn catalog_package((attr, version, description): &(&str, &str, &str)) -> Package {
+    Package {
+        attr: attr.to_string(),
+        name: format!("{}-{}", attr, version),
+        version: version.to_string(),
+        description: description.to_string(),
+    }
+}
+
+fn search(state: &State<AppState>, query: &str) -> serde_json::Value {
+    let query = query.to_lowercase();
+    let results: Vec<Package> = CATALOG
+        .iter()
+        .filter(|(attr, _, description)| {
+            attr.contains(&query) || description.to_lowercase().contains(&query)
+        })
+        .map(catalog_package)
+        .collect();
+    for component in state.components.blocking_lock().iter_mut() {
+        if component.component_type == "ResultsList" {
+            component.state = serde_json::json!({"results": results, "did_you_mean": []});
+        }
+    }
+    serde_json::json!({"success": true, "results": results, "did_you_mean": [], "demo": true})
+}
+
+// Install or remove, one pretend package at a time with progress
+fn change(system: &mut MockSystem, intent: &Intent, packages: &[String]) -> anyhow::Result<u32> {
+    let install = matches!(intent, Intent::Install { .. });
+    if packages.is_empty() {
+        bail!("No package given");
+    }
+    let mut next = system.installed().to_vec();
+    for package in packages {
+        let known = CATALOG.iter().any(|(attr, _, _)| *attr == package.as_str());
+        match (install, next.contains(package)) {
+            (true, _) if !known => bail!("There's no package called \"{}\" in the demo", package),
+            (true, true) => bail!("{} is already installed", package),
+            (false, false) => bail!("{} isn't installed", package),
+            (true, false) => next.push(package.clone()),
+            (false, true) => next.retain(|p| p != package),
+        }
+    }
+    let mut reporter = progress::Reporter::start(
+        if install { "install" } else { "remove" },
+        &intent.describe(),
+        Some(packages.len() as u64),
+        false,
+    );
+    for (index, package) in packages.iter().enumerate() {
+        let verb = if install { "Installing" } else { "Removing" };
+        reporter.advance(index as u64, &format!("{} {}", verb, package));
+        std::thread::sleep(STEP_DELAY);
+    }
+    let result = Ok(system.commit(next, intent.describe()));
+    reporter.finish(&result, &intent.describe());
+    result
+}
+
+fn generation(system: &MockSystem) -> serde_json::Value {
+    serde_json::json!(system.generations[system.current])
+}
+
+// Answer `intent` from the pretend system; None when no demo runs, or for
+// requests that only read (explanations), which are safe to answer for real
+pub fn execute(intent: &Intent, state: &State<AppState>) -> Option<serde_json::Value> {
+    if matches!(intent, Intent::Explain { .. }) {
+        return None;
+    }
+    let mut demo = DEMO.blocking_lock();
+    let system = &mut demo.as_mut()?.system;
+    let mut response = match intent {
+        Intent::Search { query } => return Some(search(state, query)),
+        Intent::Install { packages } | Intent::Remove { packages } => {
+            crate::respond(change(system, intent, packages).map(|_| generation(system)))
+        }
+        Intent::ListInstalled => crate::respond(Ok(system.installed().to_vec())),
+        Intent::Update => {
+            std::thread::sleep(STEP_DELAY * 3);
+            let packages = system.installed().to_vec();
+            system.commit(packages, "System update".to_string());
+            crate::respond(Ok(generation(system)))
+        }
+        Intent::Rollback { generation: number } => {
+            let target = match number {
+                Some(number) => system.generations.iter().position(|g| g.number == *number),
+                None => system.current.checked_sub(1),
+            };
+            match target {
+                Some(index) => {
+                    system.current = index;
+                    crate::respond(Ok(Some(system.generations[index].number)))
+                }
+                None => crate::respond::<()>(Err(anyhow::anyhow!(
+                    "There is no earlier generation to go back to"
+                ))),
+            }
+        }
+        Intent::GarbageCollect => {
+            let current = system.generations[system.current].clone();
+            let removed = system.generations.len() - 1;
+            system.generations = vec![current];
+            system.current = 0;
+            crate::respond(Ok(serde_json::json!({
+                "generations_removed": removed,
+                "freed_mb": removed * 180,
+            })))
+        }
+        _ => serde_json::json!({
+            "success": true,
+            "message": format!("In the demo, \"{}\" is only pretended", intent.describe()),
+        }),
+    };
+    response["demo"] = true.into();
+    Some(response)
+}
+
+pub fn status() -> DemoStatus {
+    match DEMO.blocking_lock().as_ref() {
+        Some(demo) => DemoStatus {
+            active: true,
+            playing: demo.autoplay.is_some(),
+            step: demo.next,
+            total: demo.steps.len(),
+            installed: demo.system.installed().to_vec(),
+            generations: demo.system.generations.clone(),
+        },
+        None => DemoStatus {
+            active: false,
+            playing: false,
+            step: 0,
+            total: 0,
+            installed: Vec::new(),
+            generations: Vec::new(),
+        },
+    }
+}
+
+// Run the next scripted request; None once the script is done
+pub fn next(app: &AppHandle, state: &State<AppState>) -> anyhow::Result<Option<StepResult>> {
+    let (index, total, step) = {
+        let mut demo = DEMO.blocking_lock();
+        let Some(demo) = demo.as_mut() else {
+            bail!("No demo is running");
+        };
+        let Some(step) = demo.steps.get(demo.next).cloned() else {
+            return Ok(None);
+        };
+        demo.next += 1;
+        (demo.next - 1, demo.steps.len(), step)
+    };
+    let _ = app.emit(
+        "demo-narration",
+        serde_json::json!({"index": index, "total": total, "narration": step.narration}),
+    );
+    let mut response = crate::answer_query(step.query.clone(), None, state);
+    let mut confirmation = None;
+    if step.confirm && response["needs_confirmation"] == true {
+        let request = response["confirmation"].clone();
+        let options = serde_json::json!({
+            "confirmation_token": request["token"],
+            "confirmation_phrase": request["phrase"],
+        });
+        response = crate::answer_query(step.query.clone(), Some(options), state);
+        confirmation = Some(request);
+    }
+    let result = StepResult {
+        index,
+        total,
+        narration: step.narration,
+        query: step.query,
+        confirmation,
+        response,
+    };
+    let _ = app.emit("demo-step", &result);
+    if index + 1 == total {
+        let _ = app.emit("demo-finished", status());
+    }
+    Ok(Some(result))
+}
+
+fn play(app: AppHandle, stop: Arc<AtomicBool>) {
+    std::thread::spawn(move || loop {
+        let pause = {
+            let demo = DEMO.blocking_lock();
+            let Some(demo) = demo.as_ref() else {
+                return;
+            };
+            match demo.steps.get(demo.next) {
+                Some(step) => Duration::from_millis(step.pause_ms),
+                None => break,
+            }
+        };
+        // Check often, so leaving the demo mid-pause is immediate
+        let mut waited = Duration::ZERO;
+        while waited < pause && !stop.load(Ordering::Relaxed) {
+            std::thread::sleep(Duration::from_millis(100));
+            waited += Duration::from_millis(100);
+        }
+        if stop.load(Ordering::Relaxed) {
+            return;
+        }
+        let state = app.state::<AppState>();
+        if !matches!(next(&app, &state), Ok(Some(_))) {
+            break;
+        }
+    });
+}
+
+pub fn start(app: &AppHandle, autoplay: bool) -> DemoStatus {
+    stop(app);
+    let flag = autoplay.then(|| Arc::new(AtomicBool::new(false)));
+    *DEMO.blocking_lock() = Some(Demo {
+        steps: scenario(),
+        next: 0,
+        system: MockSystem::new(),
+        autoplay: flag.clone(),
+    });
+    if let Some(flag) = flag {
+        play(app.clone(), flag);
+    }
+    let status = status();
+    let _ = app.emit("demo-started", &status);
+    status
+}
+
+// The pretend system without the tour, for recording and replaying test
+// scenarios; false when a demo is already running
+pub fn start_mock() -> bool {
+    let mut demo = DEMO.blocking_lock();
+    if demo.is_some() {
+        return false;
+    }
+    *demo = Some(Demo {
+        steps: Vec::new(),
+        next: 0,
+        system: MockSystem::new(),
+        autoplay: None,
+    });
+    true
+}
+
+pub fn stop_mock() {
+    DEMO.blocking_lock().take();
+}
+
+// Leave the demo, whatever it was doing, and forget the pretend system
+pub fn stop(app: &AppHandle) -> bool {
+    let Some(demo) = DEMO.blocking_lock().take() else {
+        return false;
+    };
+    if let Some(autoplay) = demo.autoplay {
+        autoplay.store(true, Ordering::Relaxed);
+    }
+    let state = app.state::<AppState>();
+    for component in state.components.blocking_lock().iter_mut() {
+        if component.component_type == "ResultsList" {
+            component.state = serde_json::json!({"results": [], "did_you_mean": []});
+        }
+    }
+    let _ = app.emit("demo-ended", ());
+    true
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn start_demo(autoplay: Option<bool>, app: AppHandle) -> DemoStatus {
+    let handle = app.clone();
+    tasks::blocking_value(&app, "Start demo", move |_| {
+        start(&handle, autoplay.unwrap_or(false))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn demo_next_step(app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Demo step", move |state| {
+        crate::respond(next(&handle, state))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn exit_demo(app: AppHandle) -> bool {
+    let handle = app.clone();
+    tasks::blocking_value(&app, "Exit demo", move |_| stop(&handle)).await
+}
+
+#[tauri::command]
+pub async fn get_demo_status(app: AppHandle) -> DemoStatus {
+    tasks::blocking_value(&app, "Get demo status", |_| status()).await
+}
@@ -0,0 +1,406 @@
+// Full-disk encryption status and LUKS key slot management
+//
+// Reports which disks are encrypted, what that does and does not protect, and
+// adds or removes passphrases. Slot changes need the user to type an explicit
+// confirmation phrase, and passphrases only ever travel over stdin or a
+// private key file — they are never logged or echoed back.
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::secrets::{self, Passphrase};
+use crate::{system, tasks};
+
+const MIN_PASSPHRASE_LEN: usize = 12;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedVolume {
+    pub device: String,
+    pub uuid: Option<String>,
+    pub size: String,
+    pub luks_version: Option<u32>,
+    // None when the header could not be read without administrator access
+    pub key_slots: Option<Vec<u32>>,
+    // /dev/mapper name while unlocked
+    pub mapping: Option<String>,
+    pub mountpoints: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlainVolume {
+    pub device: String,
+    pub fs_type: Option<String>,
+    pub mountpoints: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptionStatus {
+    pub encrypted: Vec<EncryptedVolume>,
+    pub unencrypted: Vec<PlainVolume>,
+    pub protected: Vec<String>,
+    pub not_protected: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeySlotChange {
+    pub device: String,
+    pub key_slots: Vec<u32>,
+}
+
+fn str_field(value: &serde_json::Value, name: &str) -> Option<String> {
+    value
+        .get(name)
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+}
+
+fn mountpoints(device: &serde_json::Value) -> Vec<String> {
+    device
+        .get("mountpoints")
+        .and_then(|m| m.as_array())
+        .map(|list| {
+            list.iter()
+                .filter_map(|m| m.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Mountpoints of a device and everything stacked on top of it (LVM, crypt)
+fn nested_mountpoints(device: &serde_json::Value) -> Vec<String> {
+    let mut all = mountpoints(device);
+    if let Some(children) = device.get("children").and_then(|c| c.as_array()) {
+        for child in children {
+            all.extend(nested_mountpoints(child));
+        }
+    }
+    all
+}
+
+// Key slot numbers from `cryptsetup luksDump`, JSON for LUKS2 and text for LUKS1
+fn parse_key_slots(dump: &str) -> Vec<u32> {
+    if let Ok(json) = serde_json::from_str::<serde_json::Value>(dump) {
+        let mut slots: Vec<u32> = json
+            .get("keyslots")
+            .and_then(|k| k.as_object())
+            .map(|k| k.keys().filter_map(|id| id.parse().ok()).collect())
+            .unwrap_or_default();
+        slots.sort_unstable();
+        return slots;
+    }
+    dump.lines()
+        .filter_map(|l| {
+            let rest = l.trim().strip_prefix("Key Slot ")?;
+            let (number, state) = rest.split_once(':')?;
+            (state.trim() == "ENABLED").then(|| number.parse().ok())?
+        })
+        .collect()
+}
+
+fn luks_dump(device: &str, privileged: bool) -> anyhow::Result<String> {
+    let run = if privileged {
+        system::run_privileged
+    } else {
+        system::run
+    };
+    run("cryptsetup", &["luksDump", "--dump-json-metadata", device])
+        .or_else(|_| run("cryptsetup", &["luksDump", device]))
+}
+
+fn luks_version(device: &str) -> Option<u32> {
+    let output = system::run(
+        "blkid",
+        &["--match-tag", "VERSION", "--output", "value", device],
+    )
+    .ok()?;
+    output.trim().parse().ok()
+}
+
+fn walk(devices: &[serde_json::Value], privileged: bool, status: &mut EncryptionStatus) {
+    for device in devices {
+        let Some(path) = str_field(device, "path") else {
+            continue;
+        };
+        let fs_type = str_field(device, "fstype");
+        let children = device.get("children").and_then(|c| c.as_array());
+        if fs_type.as_deref() == Some("crypto_LUKS") {
+            let mapping = children
+                .and_then(|c| {
+                    c.iter()
+                        .find(|d| str_field(d, "type").as_deref() == Some("crypt"))
+                })
+                .and_then(|d| str_field(d, "name"));
+            status.encrypted.push(EncryptedVolume {
+                key_slots: luks_dump(&path, privileged)
+                    .ok()
+                    .map(|dump| parse_key_slots(&dump)),
+                luks_version: luks_version(&path),
+                uuid: str_field(device, "uuid"),
+                size: str_field(device, "size").unwrap_or_default(),
+                mapping,
+                mountpoints: nested_mountpoints(device),
+                device: path,
+            });
+            continue;
+        }
+        let points = mountpoints(device);
+        if !points.is_empty() {
+            status.unencrypted.push(PlainVolume {
+                device: path,
+                fs_type,
+                mountpoints: points,
+            });
+        }
+        if let Some(children) = children {
+            walk(children, privileged, status);
+        }
+    }
+}
+
+fn explain(status: &mut EncryptionStatus) {
+    for volume in &status.encrypted {
+        for point in &volume.mountpoints {
+            let what = if point == "[SWAP]" {
+                "Swap (memory written to disk, including hibernation images)".to_string()
+            } else {
+                format!("Files under {}", point)
+            };
+            status.protected.push(format!(
+                "{} — unreadable without a passphrase while the machine is off",
+                what
+            ));
+        }
+    }
+    for volume in &status.unencrypted {
+        for point in &volume.mountpoints {
+            status.not_protected.push(match point.as_str() {
+                "/boot" | "/boot/efi" | "/efi" => format!(
+                    "{} holds the kernel and initrd, not your files, but could be tampered with (Secure Boot helps)",
+                    point
+                ),
+                "[SWAP]" => format!(
+                    "Swap on {} is unencrypted and may contain memory contents such as passwords",
+                    volume.device
+                ),
+                _ => format!("Files under {} ({}) are stored unencrypted", point, volume.device),
+            });
+        }
+    }
+    status.not_protected.push(
+        "Anything while the machine is running, locked or suspended: the keys stay in memory"
+            .to_string(),
+    );
+    status
+        .not_protected
+        .push("Data you copy to other drives or cloud storage".to_string());
+}
+
+pub fn status(privileged: bool) -> anyhow::Result<EncryptionStatus> {
+    let output = system::run(
+        "lsblk",
+        &[
+            "--json",
+            "--output",
+            "NAME,PATH,TYPE,FSTYPE,UUID,SIZE,MOUNTPOINTS",
+        ],
+    )?;
+    let parsed: serde_json::Value = serde_json::from_str(&output)?;
+    let mut status = EncryptionStatus {
+        encrypted: Vec::new(),
+        unencrypted: Vec::new(),
+        protected: Vec::new(),
+        not_protected: Vec::new(),
+    };
+    if let Some(devices) = parsed.get("blockdevices").and_then(|d| d.as_array()) {
+        walk(devices, privileged, &mut status);
+    }
+    explain(&mut status);
+    Ok(status)
+}
+
+// The phrase the user has to type before a slot change goes ahead
+pub fn confirmation_phrase(action: &str, device: &str, slot: Option<u32>) -> String {
+    match slot {
+        Some(slot) => format!("{} slot {} on {}", action, slot, device),
+        None => format!("{} key on {}", action, device),
+    }
+}
+
+fn require_confirmation(expected: &str, given: &str) -> anyhow::Result<()> {
+    if given.trim() != expected {
+        bail!("Type \"{}\" to confirm", expected);
+    }
+    Ok(())
+}
+
+fn key_slots(device: &str) -> anyhow::Result<Vec<u32>> {
+    Ok(parse_key_slots(&luks_dump(device, true)?))
+}
+
+fn ensure_luks(device: &str) -> anyhow::Result<()> {
+    system::run("cryptsetup", &["isLuks", device])
+        .map(|_| ())
+        .map_err(|_| anyhow!("{} is not a LUKS volume", device))
+}
+
+// Which slot a passphrase opens; fails if it opens none
+fn unlocked_slot(device: &str, passphrase: &Passphrase) -> anyhow::Result<u32> {
+    let output = system::run_privileged_with_input(
+        "cryptsetup",
+        &[
+            "open",
+            "--test-passphrase",
+            "--verbose",
+            "--key-file=-",
+            device,
+        ],
+        passphrase.expose().as_bytes(),
+    )
+    .map_err(|_| anyhow!("That passphrase does not unlock {}", device))?;
+    output
+        .lines()
+        .find_map(|l| {
+            l.strip_prefix("Key slot ")?
+                .split_whitespace()
+                .next()?
+                .parse()
+                .ok()
+        })
+        .ok_or_else(|| anyhow!("Could not tell which key slot the passphrase unlocked"))
+}
+
+pub fn add_key(
+    device: &str,
+    existing: &Passphrase,
+    new: &Passphrase,
+    confirmation: &str,
+) -> anyhow::Result<KeySlotChange> {
+    require_confirmation(&confirmation_phrase("add", device, None), confirmation)?;
+    ensure_luks(device)?;
+    if new.expose().chars().count() < MIN_PASSPHRASE_LEN {
+        bail!(
+            "Use at least {} characters; a few random words work well",
+            MIN_PASSPHRASE_LEN
+        );
+    }
+    if new.expose() == existing.expose() {
+        bail!("The new passphrase must differ from the existing one");
+    }
+    unlocked_slot(device, existing)?;
+    secrets::with_key_file(new, |new_key_file| {
+        system::run_privileged_with_input(
+            "cryptsetup",
+            &[
+                "luksAddKey",
+                "--key-file=-",
+                device,
+                &new_key_file.to_string_lossy(),
+            ],
+            existing.expose().as_bytes(),
+        )
+    })?;
+    unlocked_slot(device, new)?;
+    Ok(KeySlotChange {
+        device: device.to_string(),
+        key_slots: key_slots(device)?,
+    })
+}
+
+pub fn remove_key(
+    device: &str,
+    slot: u32,
+    remaining: &Passphrase,
+    confirmation: &str,
+) -> anyhow::Result<KeySlotChange> {
+    require_confirmation(
+        &confirmation_phrase("remove", device, Some(slot)),
+        confirmation,
+    )?;
+    ensure_luks(device)?;
+    let slots = key_slots(device)?;
+    if !slots.contains(&slot) {
+        bail!("{} has no key in slot {}", device, slot);
+    }
+    if slots.len() < 2 {
+        bail!(
+            "Slot {} holds the only key; removing it would lock the disk forever",
+            slot
+        );
+    }
+    // The passphrase that stays must open a different slot
+    if unlocked_slot(device, remaining)? == slot {
+        bail!(
+            "That passphrase is the one in slot {}; enter a passphrase you are keeping",
+            slot
+        );
+    }
+    let slot_id = slot.to_string();
+    system::run_privileged_with_input(
+        "cryptsetup",
+        &["luksKillSlot", "--key-file=-", device, &slot_id],
+        remaining.expose().as_bytes(),
+    )?;
+    Ok(KeySlotChange {
+        device: device.to_string(),
+        key_slots: key_slots(device)?,
+    })
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_encryption_status(privileged: bool, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Get encryption status", move |_| {
+        crate::respond(status(privileged))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_key_slot_confirmation(
+    action: String,
+    device: String,
+    slot: Option<u32>,
+) -> String {
+    confirmation_phrase(&action, &device, slot)
+}
+
+#[tauri::command]
+pub async fn add_luks_key(
+    device: String,
+    existing_passphrase: Passphrase,
+    new_passphrase: Passphrase,
+    confirmation: String,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Add LUKS key", move |_| {
+        crate::respond(add_key(
+            &device,
+            &existing_passphrase,
+            &new_passphrase,
+            &confirmation,
+        ))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn remove_luks_key(
+    device: String,
+    slot: u32,
+    remaining_passphrase: Passphrase,
+    confirmation: String,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Remove LUKS key", move |_| {
+        crate::respond(remove_key(
+            &device,
+            slot,
+            &remaining_passphrase,
+            &confirmation,
+        ))
+    })
+    .await
+}
@@ -0,0 +1,289 @@
+// Environment variable and shell configuration manager
+//
+// Shows the effective environment, explains where each variable comes from
+// (NixOS config, home-manager, shell rc files, or nowhere persistent) and adds
+// new variables declaratively so they survive rebuilds and new sessions.
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+use crate::nixgen::{self, NixModule, NixOption, Target};
+use crate::{managed, storage, system, tasks};
+
+const DECLARED_FILE: &str = "envvars.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OriginKind {
+    // environment.variables / environment.sessionVariables (rendered into /etc/set-environment)
+    NixosConfig,
+    // home.sessionVariables (rendered into hm-session-vars.sh)
+    HomeManager,
+    // An `export` in a shell startup file
+    ShellRc,
+    // Declared through this assistant but not yet activated
+    Pending,
+    // Set in the running session only
+    SessionOnly,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Origin {
+    pub kind: OriginKind,
+    pub file: Option<PathBuf>,
+    pub line: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvVar {
+    pub name: String,
+    pub value: String,
+    pub origins: Vec<Origin>,
+    pub explanation: String,
+}
+
+// Variables declared through the assistant, per target configuration
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct Declared {
+    nixos: BTreeMap<String, String>,
+    home: BTreeMap<String, String>,
+}
+
+fn source_files() -> Vec<(OriginKind, PathBuf)> {
+    let home = system::home_dir();
+    let user = system::username();
+    vec![
+        (
+            OriginKind::NixosConfig,
+            PathBuf::from("/etc/set-environment"),
+        ),
+        (
+            OriginKind::HomeManager,
+            home.join(".nix-profile/etc/profile.d/hm-session-vars.sh"),
+        ),
+        (
+            OriginKind::HomeManager,
+            PathBuf::from(format!(
+                "/etc/profiles/per-user/{}/etc/profile.d/hm-session-vars.sh",
+                user
+            )),
+        ),
+        (OriginKind::ShellRc, home.join(".profile")),
+        (OriginKind::ShellRc, home.join(".bash_profile")),
+        (OriginKind::ShellRc, home.join(".bashrc")),
+        (OriginKind::ShellRc, home.join(".zshenv")),
+        (OriginKind::ShellRc, home.join(".zshrc")),
+        (OriginKind::ShellRc, home.join(".config/fish/config.fish")),
+    ]
+}
+
+// Whether a line assigns the variable in sh or fish syntax
+fn assigns(line: &str, name: &str) -> bool {
+    let line = line.trim();
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    if let Some(rest) = line.strip_prefix(name) {
+        return rest.starts_with('=');
+    }
+    line.strip_prefix("set -gx ")
+        .or_else(|| line.strip_prefix("set -x "))
+        .is_some_and(|rest| rest.split_whitespace().next() == Some(name))
+}
+
+fn find_origins(name: &str, declared: &Declared) -> Vec<Origin> {
+    let mut origins = Vec::new();
+    for (kind, file) in source_files() {
+        let Ok(contents) = fs::read_to_string(&file) else {
+            continue;
+        };
+        if let Some(index) = contents.lines().position(|l| assigns(l, name)) {
+            origins.push(Origin {
+                kind,
+                file: Some(file),
+                line: Some(index + 1),
+            });
+        }
+    }
+    let declared_here = declared.nixos.contains_key(name) || declared.home.contains_key(name);
+    if declared_here && origins.iter().all(|o| o.kind == OriginKind::ShellRc) {
+        origins.push(Origin {
+            kind: OriginKind::Pending,
+            file: None,
+            line: None,
+        });
+    }
+    if origins.is_empty() {
+        origins.push(Origin {
+            kind: OriginKind::SessionOnly,
+            file: None,
+            line: None,
+        });
+    }
+    origins
+}
+
+// A home-manager managed rc file is a symlink into the store and gets replaced on switch
+fn is_store_managed(file: &PathBuf) -> bool {
+    fs::canonicalize(file)
+        .map(|p| p.starts_with("/nix/store"))
+        .unwrap_or(false)
+}
+
+fn explain(origins: &[Origin]) -> String {
+    let first = &origins[0];
+    match first.kind {
+        OriginKind::NixosConfig => "Set declaratively by your NixOS configuration; it applies to every user and survives rebuilds.".to_string(),
+        OriginKind::HomeManager => "Set declaratively by home-manager (home.sessionVariables); it survives rebuilds but only takes effect in new login sessions.".to_string(),
+        OriginKind::ShellRc => {
+            let file = first.file.as_ref();
+            if file.is_some_and(is_store_managed) {
+                "Exported from a shell rc file that home-manager generates, so manual edits are overwritten on the next switch. Declare it instead.".to_string()
+            } else {
+                "Exported from a shell startup file; terminals see it but graphical apps launched from the desktop usually do not.".to_string()
+            }
+        }
+        OriginKind::Pending => "Declared through the assistant; rebuild (or run home-manager switch) and log in again to activate it.".to_string(),
+        OriginKind::SessionOnly => "Only set in the current session (for example with `export` in a terminal); it will disappear after logout or a rebuild. Declare it to make it permanent.".to_string(),
+    }
+}
+
+pub fn list() -> anyhow::Result<Vec<EnvVar>> {
+    let declared: Declared = storage::load(DECLARED_FILE)?;
+    let mut vars: Vec<EnvVar> = std::env::vars()
+        .map(|(name, value)| {
+            let origins = find_origins(&name, &declared);
+            EnvVar {
+                explanation: explain(&origins),
+                name,
+                value,
+                origins,
+            }
+        })
+        .collect();
+    // Declared but not yet present in this session
+    for (name, value) in declared.nixos.iter().chain(declared.home.iter()) {
+        if !vars.iter().any(|v| &v.name == name) {
+            let origins = vec![Origin {
+                kind: OriginKind::Pending,
+                file: None,
+                line: None,
+            }];
+            vars.push(EnvVar {
+                explanation: explain(&origins),
+                name: name.clone(),
+                value: value.clone(),
+                origins,
+            });
+        }
+    }
+    vars.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(vars)
+}
+
+fn valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn write_modules(declared: &Declared) -> anyhow::Result<Vec<PathBuf>> {
+    let mut nixos = NixModule::new(
+        "environment",
+        "session environment variables",
+        Target::Nixos,
+    );
+    for (name, value) in &declared.nixos {
+        nixos.set(NixOption::new(
+            format!("environment.sessionVariables.{}", name),
+            nixgen::string(value),
+        ));
+    }
+    let mut home = NixModule::new(
+        "environment",
+        "session environment variables",
+        Target::HomeManager,
+    );
+    for (name, value) in &declared.home {
+        home.set(NixOption::new(
+            format!("home.sessionVariables.{}", name),
+            nixgen::string(value),
+        ));
+    }
+    Ok(vec![nixos.write()?, home.write()?])
+}
+
+// Declare (or with value None, remove) a variable for the given target
+pub fn declare(name: &str, value: Option<&str>, target: Target) -> anyhow::Result<Vec<PathBuf>> {
+    if !valid_name(name) {
+        bail!("'{}' is not a valid environment variable name", name);
+    }
+    let mut declared: Declared = storage::load(DECLARED_FILE)?;
+    let map = match target {
+        Target::Nixos => &mut declared.nixos,
+        Target::HomeManager => &mut declared.home,
+    };
+    match value {
+        Some(value) => map.insert(name.to_string(), value.to_string()),
+        None => map.remove(name),
+    };
+    storage::save(DECLARED_FILE, &declared)?;
+    write_modules(&declared)
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn list_env_vars(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "List environment variables", |_| {
+        crate::respond(list())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn explain_env_var(name: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Explain environment variable", move |_| {
+        crate::respond(list().map(|vars| vars.into_iter().find(|v| v.name == name)))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn add_env_var(
+    name: String,
+    value: String,
+    target: Target,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Add environment variable", move |_| {
+        if let Some(refusal) = managed::guard("configure", &format!("Set {}", name)) {
+            return refusal;
+        }
+        crate::respond(declare(&name, Some(&value), target).map(|paths| {
+            serde_json::json!({
+                "module_paths": paths,
+                "next_step": match target {
+                    Target::Nixos => "Rebuild the system and log in again to see the variable everywhere",
+                    Target::HomeManager => "Run home-manager switch and log in again",
+                },
+            })
+        }))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn remove_env_var(name: String, target: Target, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Remove environment variable", move |_| {
+        if let Some(refusal) = managed::guard("configure", &format!("Remove {}", name)) {
+            return refusal;
+        }
+        crate::respond(declare(&name, None, target))
+    })
+    .await
+}
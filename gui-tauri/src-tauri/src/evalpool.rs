@@ -0,0 +1,162 @@
+// Bounded worker pool for Nix evaluations, with priority classes
+//
+// Evaluations are expensive and some are only housekeeping (rebuilding the
+// package index, sizing the store). Interactive jobs are always taken first,
+// and background jobs may never occupy the last worker, so something the user
+// is waiting for always has a worker ready instead of queueing behind a
+// minutes-long background evaluation.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::mpsc;
+use std::sync::OnceLock;
+use tauri::AppHandle;
+use tokio::sync::{Mutex, Notify};
+
+use crate::tasks;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    // Someone is waiting for the answer
+    Interactive,
+    Background,
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+#[derive(Default)]
+struct Queues {
+    interactive: VecDeque<Job>,
+    background: VecDeque<Job>,
+    busy_background: usize,
+    busy_interactive: usize,
+}
+
+impl Queues {
+    // Interactive jobs first; background ones only while a worker stays free
+    fn next(&mut self, workers: usize) -> Option<(Job, Priority)> {
+        if let Some(job) = self.interactive.pop_front() {
+            self.busy_interactive += 1;
+            return Some((job, Priority::Interactive));
+        }
+        if self.busy_background + 1 < workers {
+            if let Some(job) = self.background.pop_front() {
+                self.busy_background += 1;
+                return Some((job, Priority::Background));
+            }
+        }
+        None
+    }
+}
+
+struct Pool {
+    queues: Mutex<Queues>,
+    ready: Notify,
+    workers: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolStatus {
+    pub workers: usize,
+    pub queued_interactive: usize,
+    pub queued_background: usize,
+    pub busy_interactive: usize,
+    pub busy_background: usize,
+}
+
+static POOL: OnceLock<Pool> = OnceLock::new();
+
+fn pool() -> &'static Pool {
+    let mut started = false;
+    let pool = POOL.get_or_init(|| {
+        started = true;
+        // Evaluations are memory-hungry; half the cores, but at least two
+        // so one is always free for interactive work
+        let workers = std::thread::available_parallelism()
+            .map(|n| n.get() / 2)
+            .unwrap_or(2)
+            .clamp(2, 8);
+        Pool {
+            queues: Mutex::const_new(Queues::default()),
+            ready: Notify::new(),
+            workers,
+        }
+    });
+    if started {
+        for _ in 0..pool.workers {
+            tauri::async_runtime::spawn(work(pool));
+        }
+    }
+    pool
+}
+
+async fn work(pool: &'static Pool) {
+    loop {
+        // Registered before looking, so a job queued in between still wakes us
+        let ready = pool.ready.notified();
+        let next = pool.queues.lock().await.next(pool.workers);
+        let Some((job, priority)) = next else {
+            ready.await;
+            continue;
+        };
+        // Evaluations wait on nix, so on the blocking pool; a panicking job
+        // only ends its own thread, not the worker
+        let _ = tauri::async_runtime::spawn_blocking(job).await;
+        let mut queues = pool.queues.lock().await;
+        match priority {
+            Priority::Interactive => queues.busy_interactive -= 1,
+            Priority::Background => queues.busy_background -= 1,
+        }
+        // A background slot may have opened up
+        pool.ready.notify_waiters();
+    }
+}
+
+// Queue a job without waiting for it; it belongs to the task queueing it, so
+// cancelling that task cancels the job too
+pub fn spawn(priority: Priority, job: impl FnOnce() + Send + 'static) {
+    let pool = pool();
+    let cancelled = tasks::current();
+    let job: Job = Box::new(move || tasks::within(cancelled, job));
+    let mut queues = pool.queues.blocking_lock();
+    match priority {
+        Priority::Interactive => queues.interactive.push_back(job),
+        Priority::Background => queues.background.push_back(job),
+    }
+    drop(queues);
+    pool.ready.notify_waiters();
+}
+
+// Run a job on the pool and wait for its result
+pub fn run<T: Send + 'static>(
+    priority: Priority,
+    job: impl FnOnce() -> anyhow::Result<T> + Send + 'static,
+) -> anyhow::Result<T> {
+    let (sender, receiver) = mpsc::channel();
+    spawn(priority, move || {
+        let _ = sender.send(job());
+    });
+    receiver
+        .recv()
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("The evaluation stopped unexpectedly")))
+}
+
+pub fn status() -> PoolStatus {
+    let pool = pool();
+    let queues = pool.queues.blocking_lock();
+    PoolStatus {
+        workers: pool.workers,
+        queued_interactive: queues.interactive.len(),
+        queued_background: queues.background.len(),
+        busy_interactive: queues.busy_interactive,
+        busy_background: queues.busy_background,
+    }
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_eval_pool_status(app: AppHandle) -> PoolStatus {
+    tasks::blocking_value(&app, "Evaluation pool", |_| status()).await
+}
@@ -0,0 +1,281 @@
+// Adaptive verbosity from what the user has already done successfully
+//
+// Every intent that runs exercises one or more glossary concepts (a rollback
+// is about generations, an install about profiles). Successes and failures
+// per concept are counted in the profile, and explanations are pitched from
+// there: a step shorter than the persona's verbosity for concepts the user
+// has mastered, a step longer for ones they have never succeeded with.
+// set_verbosity pins one level for everything instead.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::AppHandle;
+
+use crate::explain::Explanation;
+use crate::glossary::{self, Topic};
+use crate::nlp::Intent;
+use crate::personas::{self, Verbosity};
+use crate::userprofile::{self, UserProfile};
+use crate::{clock, tasks, AppState};
+
+const PREFERENCE_KEY: &str = "expertise";
+// Successes (outnumbering failures) before a concept counts as mastered
+const MASTERY_SUCCESSES: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mastery {
+    New,
+    Familiar,
+    Mastered,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConceptRecord {
+    pub successes: u32,
+    pub failures: u32,
+    pub last_success: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Expertise {
+    // Glossary topic id -> record
+    pub concepts: BTreeMap<String, ConceptRecord>,
+    // Set by set_verbosity; None adapts per concept
+    pub verbosity: Option<Verbosity>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConceptStatus {
+    pub id: String,
+    pub title: String,
+    pub mastery: Mastery,
+    pub record: ConceptRecord,
+    pub verbosity: Verbosity,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpertiseStatus {
+    pub baseline: Verbosity,
+    pub verbosity_override: Option<Verbosity>,
+    pub concepts: Vec<ConceptStatus>,
+}
+
+impl ConceptRecord {
+    pub fn mastery(&self) -> Mastery {
+        if self.successes >= MASTERY_SUCCESSES && self.successes > self.failures {
+            Mastery::Mastered
+        } else if self.successes > 0 {
+            Mastery::Familiar
+        } else {
+            Mastery::New
+        }
+    }
+}
+
+impl Expertise {
+    pub fn mastery(&self, concept: &str) -> Mastery {
+        self.concepts
+            .get(concept)
+            .map_or(Mastery::New, ConceptRecord::mastery)
+    }
+
+    // How much to say about `concept` to someone whose persona starts at
+    // `baseline`
+    pub fn verbosity_for(&self, concept: &str, baseline: Verbosity) -> Verbosity {
+        if let Some(fixed) = self.verbosity {
+            return fixed;
+        }
+        match self.mastery(concept) {
+            Mastery::Mastered => baseline.shorter(),
+            Mastery::Familiar => baseline,
+            Mastery::New => baseline.longer(),
+        }
+    }
+}
+
+// The glossary concepts an intent puts into practice
+pub fn concepts(intent: &Intent) -> &'static [&'static str] {
+    match intent {
+        Intent::Install { .. } | Intent::Remove { .. } | Intent::ListInstalled => &["profiles"],
+        Intent::Update => &["rebuild", "generations"],
+        Intent::Rollback { .. }
+        | Intent::SetBootDefault { .. }
+        | Intent::DeleteGenerations { .. } => &["generations"],
+        Intent::GarbageCollect => &["garbage-collection", "nix-store"],
+        Intent::Configure { setting, .. } if setting.contains("flake") => &["flakes"],
+        Intent::Configure { .. } => &["configuration-nix"],
+        _ => &[],
+    }
+}
+
+fn load(profile: &UserProfile) -> Expertise {
+    profile
+        .preferences
+        .get(PREFERENCE_KEY)
+        .and_then(|e| serde_json::from_value(e.clone()).ok())
+        .unwrap_or_default()
+}
+
+pub fn current(state: &AppState) -> Expertise {
+    state
+        .user_profile
+        .blocking_lock()
+        .as_ref()
+        .map(load)
+        .unwrap_or_default()
+}
+
+fn update(state: &AppState, change: impl FnOnce(&mut Expertise)) -> anyhow::Result<Expertise> {
+    let mut profile = state.user_profile.blocking_lock();
+    let profile = profile.get_or_insert_with(UserProfile::default);
+    let mut expertise = load(profile);
+    change(&mut expertise);
+    if !profile.preferences.is_object() {
+        profile.preferences = serde_json::json!({});
+    }
+    profile.preferences[PREFERENCE_KEY] = serde_json::to_value(&expertise)?;
+    userprofile::save(profile)?;
+    Ok(expertise)
+}
+
+// Count the outcome of an intent that ran towards its concepts
+pub fn observe(state: &AppState, intent: &Intent, response: &serde_json::Value) {
+    let concepts = concepts(intent);
+    if concepts.is_empty() {
+        return;
+    }
+    // A prompt for confirmation or a license override isn't an outcome yet
+    let Some(succeeded) = response.get("success").and_then(|s| s.as_bool()) else {
+        return;
+    };
+    if !succeeded && response.get("error").is_none() {
+        return;
+    }
+    let now = clock::now();
+    let _ = update(state, |expertise| {
+        for concept in concepts {
+            let record = expertise.concepts.entry(concept.to_string()).or_default();
+            if succeeded {
+                record.successes += 1;
+                record.last_success = Some(now);
+            } else {
+                record.failures += 1;
+            }
+        }
+    });
+}
+
+fn baseline(state: &AppState) -> Verbosity {
+    let profile = state.user_profile.blocking_lock();
+    personas::resolve(profile.as_ref().map(|p| p.persona.as_str())).verbosity
+}
+
+fn first_sentence(text: &str) -> &str {
+    text.find(". ").map_or(text, |end| &text[..=end])
+}
+
+pub fn topic_text(topic: &Topic, verbosity: Verbosity) -> String {
+    match verbosity {
+        Verbosity::Minimal | Verbosity::Concise => first_sentence(topic.summary).to_string(),
+        Verbosity::Balanced => topic.summary.to_string(),
+        Verbosity::Detailed => format!("{} {}", topic.summary, topic.detail),
+    }
+}
+
+// A glossary entry pitched at the user's experience with it
+pub fn explain_topic(state: &AppState, topic: &Topic) -> serde_json::Value {
+    let expertise = current(state);
+    let verbosity = expertise.verbosity_for(topic.id, baseline(state));
+    let mut data = serde_json::json!(topic);
+    data["text"] = serde_json::json!(topic_text(topic, verbosity));
+    data["verbosity"] = serde_json::json!(verbosity);
+    data["mastery"] = serde_json::json!(expertise.mastery(topic.id));
+    data
+}
+
+// Links to concepts the user has mastered are left out; concepts they are new
+// to get their background inline when the pitch is detailed
+pub fn explain_snippet(state: &AppState, explanation: Explanation) -> serde_json::Value {
+    let expertise = current(state);
+    let baseline = baseline(state);
+    let mut explanation = explanation;
+    explanation
+        .related
+        .retain(|id| expertise.mastery(id) != Mastery::Mastered);
+    let verbosity = explanation
+        .related
+        .iter()
+        .map(|id| expertise.verbosity_for(id, baseline))
+        .max()
+        .unwrap_or_else(|| expertise.verbosity.unwrap_or(baseline));
+    let background: Vec<serde_json::Value> = explanation
+        .related
+        .iter()
+        .filter(|id| expertise.verbosity_for(id, baseline) == Verbosity::Detailed)
+        .filter_map(|id| glossary::find(id))
+        .map(|topic| {
+            serde_json::json!({
+                "id": topic.id,
+                "title": topic.title,
+                "text": topic_text(topic, Verbosity::Detailed),
+            })
+        })
+        .collect();
+    let mut data = serde_json::json!(explanation);
+    data["verbosity"] = serde_json::json!(verbosity);
+    data["background"] = serde_json::json!(background);
+    data
+}
+
+pub fn status(state: &AppState) -> ExpertiseStatus {
+    let expertise = current(state);
+    let baseline = baseline(state);
+    ExpertiseStatus {
+        baseline,
+        verbosity_override: expertise.verbosity,
+        concepts: glossary::TOPICS
+            .iter()
+            .map(|topic| ConceptStatus {
+                id: topic.id.to_string(),
+                title: topic.title.to_string(),
+                mastery: expertise.mastery(topic.id),
+                record: expertise
+                    .concepts
+                    .get(topic.id)
+                    .cloned()
+                    .unwrap_or_default(),
+                verbosity: expertise.verbosity_for(topic.id, baseline),
+            })
+            .collect(),
+    }
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_expertise(app: AppHandle) -> ExpertiseStatus {
+    tasks::blocking_value(&app, "Get expertise", |state| status(state)).await
+}
+
+// Pin every explanation to one verbosity; null goes back to adapting
+#[tauri::command]
+pub async fn set_verbosity(verbosity: Option<Verbosity>, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set verbosity", move |state| {
+        let result = update(state, |expertise| expertise.verbosity = verbosity);
+        crate::respond(result.map(|_| status(state)))
+    })
+    .await
+}
+
+// Forget what has been learned, so every concept is explained as new again
+#[tauri::command]
+pub async fn reset_expertise(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Reset expertise", |state| {
+        let result = update(state, |expertise| expertise.concepts.clear());
+        crate::respond(result.map(|_| status(state)))
+    })
+    .await
+}
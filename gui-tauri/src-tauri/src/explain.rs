@@ -0,0 +1,773 @@
+// Explain a pasted command or configuration snippet
+//
+// "sudo nixos-rebuild switch --upgrade" or "services.openssh.enable = true;"
+// becomes a plain-language breakdown with a note per flag, argument and
+// option. Options are looked up in the NixOS options index of the running
+// system (options.json) and nix.conf settings in the managed settings table;
+// risky patterns get a warning and related glossary topics are linked.
+// Pasted Nix error messages get the same treatment: what the error means and
+// what usually fixes it.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tauri::AppHandle;
+use tokio::sync::Mutex;
+
+use crate::evalpool::{self, Priority};
+use crate::{fuzzy, glossary, nixconf, storage, tasks};
+
+// Shipped when documentation.nixos.enable is on (the default)
+const OPTIONS_INDEX: &str = "/run/current-system/sw/share/doc/nixos/options.json";
+// Below this an option isn't worth listing in a search
+const OPTION_MATCH_THRESHOLD: f32 = 0.6;
+
+type OptionsIndex = serde_json::Map<String, serde_json::Value>;
+
+// The parsed index and the modification time it was read at; a rebuild
+// replaces the file, so a newer one is parsed again
+static PARSED_INDEX: Mutex<Option<(SystemTime, Arc<OptionsIndex>)>> = Mutex::const_new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnippetKind {
+    Command,
+    Config,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PartKind {
+    Program,
+    Subcommand,
+    Flag,
+    Argument,
+    Option,
+    Package,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptionMatch {
+    // "services.openssh.enable"
+    pub path: String,
+    pub description: Option<String>,
+    pub score: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    pub text: String,
+    pub kind: PartKind,
+    pub meaning: String,
+    // Whether the meaning came from a table or index rather than a guess
+    pub known: bool,
+    pub value: Option<String>,
+    pub option_type: Option<String>,
+    pub default_value: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Explanation {
+    pub input: String,
+    pub kind: SnippetKind,
+    pub summary: String,
+    pub parts: Vec<Annotation>,
+    pub warnings: Vec<String>,
+    // Glossary topic ids worth reading next
+    pub related: Vec<String>,
+}
+
+// (program, subcommand or "", what it does)
+const COMMANDS: &[(&str, &str, &str)] = &[
+    ("nixos-rebuild", "switch", "Build the system configuration and switch to it right away; it also becomes the boot default"),
+    ("nixos-rebuild", "boot", "Build the system configuration and make it the boot default, without switching now"),
+    ("nixos-rebuild", "test", "Build and switch to the configuration until the next reboot, without adding a boot entry"),
+    ("nixos-rebuild", "build", "Only build the configuration into ./result; nothing is activated"),
+    ("nixos-rebuild", "dry-build", "Show what would be built or downloaded, without doing it"),
+    ("nixos-rebuild", "build-vm", "Build a virtual machine running this configuration, to try it safely"),
+    ("nixos-rebuild", "list-generations", "List the system generations"),
+    ("nixos-rebuild", "", "Build and activate NixOS system configurations"),
+    ("nix", "build", "Build a package or flake output and link the result as ./result"),
+    ("nix", "run", "Build an app and run it once, without installing it"),
+    ("nix", "shell", "Open a shell where the given packages are available, without installing them"),
+    ("nix", "develop", "Open the development environment of a flake or package"),
+    ("nix", "search", "Search a package set by name and description"),
+    ("nix", "profile", "Manage packages installed imperatively into your profile"),
+    ("nix", "flake", "Work with flakes: init, update, lock, show, check"),
+    ("nix", "store", "Inspect and maintain the Nix store"),
+    ("nix", "eval", "Evaluate a Nix expression and print the result"),
+    ("nix", "repl", "Start an interactive Nix session"),
+    ("nix", "copy", "Copy store paths between machines or stores"),
+    ("nix", "why-depends", "Explain why one store path depends on another"),
+    ("nix", "path-info", "Show information about store paths, such as their size"),
+    ("nix", "", "The modern Nix command line"),
+    ("nix-env", "", "Manage the legacy per-user profile imperatively"),
+    ("nix-shell", "", "Open a shell with the given packages or a shell.nix environment"),
+    ("nix-build", "", "Build a Nix expression and link the result as ./result"),
+    ("nix-collect-garbage", "", "Delete unreachable store paths to free disk space"),
+    ("nix-store", "", "Low-level store operations"),
+    ("nix-channel", "", "Manage channels, the legacy way of picking a nixpkgs version"),
+    ("home-manager", "switch", "Build your Home Manager configuration and activate it"),
+    ("home-manager", "", "Manage your user environment declaratively"),
+    ("sudo", "", "Run the rest of the command as root"),
+];
+
+// (program or "*", flag, takes a value, meaning)
+const FLAGS: &[(&str, &str, bool, &str)] = &[
+    ("nixos-rebuild", "--upgrade", false, "Update the channels first, so you get newer packages"),
+    ("nixos-rebuild", "--upgrade-all", false, "Update every channel, including ones not used by the system"),
+    ("nixos-rebuild", "--flake", true, "Use this flake's nixosConfigurations output instead of /etc/nixos/configuration.nix"),
+    ("nixos-rebuild", "--rollback", false, "Go back to the previous generation instead of building"),
+    ("nixos-rebuild", "--install-bootloader", false, "Reinstall the bootloader as part of the switch"),
+    ("nixos-rebuild", "--target-host", true, "Deploy to another machine over SSH"),
+    ("nixos-rebuild", "--build-host", true, "Build on another machine over SSH"),
+    ("nixos-rebuild", "--use-remote-sudo", false, "Use sudo on the target host for activation"),
+    ("nixos-rebuild", "--specialisation", true, "Switch to the named specialisation"),
+    ("nixos-rebuild", "-p", true, "Use a separate system profile with this name"),
+    ("nix-env", "-i", false, "Install packages into your user profile (imperative; not recorded in any configuration)"),
+    ("nix-env", "-iA", true, "Install a package by its attribute path, e.g. nixpkgs.firefox"),
+    ("nix-env", "-e", false, "Uninstall packages from your user profile"),
+    ("nix-env", "-u", false, "Upgrade installed packages"),
+    ("nix-env", "-q", false, "List installed packages"),
+    ("nix-env", "--rollback", false, "Return your user profile to its previous generation"),
+    ("nix-env", "--list-generations", false, "List your user profile generations"),
+    ("nix-env", "--delete-generations", true, "Delete the given profile generations"),
+    ("nix-shell", "-p", false, "Make the following packages available in the shell"),
+    ("nix-shell", "--pure", false, "Start from an almost empty environment so only declared tools are visible"),
+    ("nix-shell", "--run", true, "Run this command in the shell and exit"),
+    ("nix-collect-garbage", "-d", false, "Also delete every old generation of every profile first. You can no longer roll back afterwards"),
+    ("nix-collect-garbage", "--delete-old", false, "Same as -d: delete all old generations, then collect garbage"),
+    ("nix-collect-garbage", "--delete-older-than", true, "Delete generations older than this age (e.g. 30d) first"),
+    ("nix-collect-garbage", "--dry-run", false, "Only show what would be deleted"),
+    ("nix-store", "--gc", false, "Collect garbage"),
+    ("nix-store", "--optimise", false, "Hard-link identical files to save space"),
+    ("nix-store", "--verify", false, "Check the store database for consistency"),
+    ("nix-store", "--repair", false, "Repair corrupted store paths (with --verify)"),
+    ("nix-store", "-q", false, "Query information about store paths"),
+    ("nix-channel", "--update", false, "Download the latest version of each channel"),
+    ("nix-channel", "--add", true, "Subscribe to a channel URL"),
+    ("nix-channel", "--list", false, "List subscribed channels"),
+    ("*", "--impure", false, "Allow access to environment variables and files outside the flake"),
+    ("*", "--refresh", false, "Re-download cached flake inputs and tarballs"),
+    ("*", "--show-trace", false, "Print the full evaluation trace when something fails"),
+    ("*", "-L", false, "Print full build logs"),
+    ("*", "--print-build-logs", false, "Print full build logs"),
+    ("*", "--no-link", false, "Don't create the ./result symlink"),
+    ("*", "-o", true, "Name the result symlink"),
+    ("*", "--out-link", true, "Name the result symlink"),
+    ("*", "--dry-run", false, "Only show what would happen"),
+    ("*", "-j", true, "How many builds may run in parallel"),
+    ("*", "--max-jobs", true, "How many builds may run in parallel"),
+    ("*", "--cores", true, "How many CPU cores each build may use"),
+    ("*", "--fallback", false, "Build from source when a download from the binary cache fails"),
+    ("*", "--offline", false, "Don't use the network; only what is already cached"),
+    ("*", "--keep-going", false, "Keep building other things after one build fails"),
+    ("*", "-k", false, "Keep building other things after one build fails"),
+    ("*", "--option", true, "Override a nix.conf setting for this command"),
+    ("*", "--extra-experimental-features", true, "Enable experimental Nix features for this command only"),
+    ("*", "--experimental-features", true, "Set the experimental Nix features for this command"),
+    ("*", "--update-input", true, "Update only this flake input in the lock file"),
+    ("*", "--recreate-lock-file", false, "Update every flake input"),
+    ("*", "--verbose", false, "Print more details"),
+    ("*", "-v", false, "Print more details"),
+    ("*", "--help", false, "Show the manual page"),
+];
+
+// Common options, used when the system has no options index
+const FALLBACK_OPTIONS: &[(&str, &str)] = &[
+    ("environment.systemPackages", "Packages installed for every user on the system"),
+    ("users.users", "User accounts and their settings"),
+    ("networking.hostName", "The machine's name on the network"),
+    ("networking.networkmanager.enable", "Manage network connections with NetworkManager"),
+    ("networking.firewall.enable", "Turn the firewall on or off"),
+    ("networking.firewall.allowedTCPPorts", "TCP ports other machines may connect to"),
+    ("services.openssh.enable", "Run an SSH server so you can log in remotely"),
+    ("services.xserver.enable", "Enable the X11 display server"),
+    ("services.printing.enable", "Enable printing with CUPS"),
+    ("services.pipewire.enable", "Use PipeWire for audio"),
+    ("boot.loader.systemd-boot.enable", "Boot with systemd-boot (UEFI systems)"),
+    ("boot.loader.grub.enable", "Boot with GRUB"),
+    ("time.timeZone", "The system time zone"),
+    ("i18n.defaultLocale", "The default language and formatting locale"),
+    ("nixpkgs.config.allowUnfree", "Allow installing packages with non-free licenses"),
+    ("system.stateVersion", "The NixOS release whose defaults stateful data was created with. Don't change it when upgrading"),
+    ("nix.settings.experimental-features", "Opt-in Nix features such as flakes"),
+    ("nix.gc.automatic", "Collect garbage automatically on a schedule"),
+    ("programs.git.enable", "Install and configure git"),
+];
+
+// Patterns that deserve a heads-up, matched against the whole snippet
+const WARNINGS: &[(&str, &str)] = &[
+    ("nix-collect-garbage -d", "Deleting all old generations means you can no longer roll back to them"),
+    ("--delete-old", "Deleting all old generations means you can no longer roll back to them"),
+    ("nix-env -i", "nix-env installs are imperative: they aren't in your configuration and are easy to forget. Consider environment.systemPackages or home.packages"),
+    ("curl", "Piping downloaded scripts into a shell runs code you haven't read"),
+    ("system.stateVersion", "system.stateVersion should stay at the release you first installed; changing it doesn't upgrade NixOS"),
+    ("allowUnfree", "Unfree packages may have licenses that restrict use or redistribution"),
+    ("--impure", "Impure evaluation can make builds depend on your local environment and stop being reproducible"),
+    ("trusted-users", "Trusted users can change daemon settings and are effectively root"),
+];
+
+// (pattern in the error, what it means, what to do, a configuration fix)
+const NIX_ERRORS: &[(&str, &str, &str, Option<&str>)] = &[
+    ("has an unfree license", "The package's license isn't free, and Nix refuses those until you allow them", "Allow unfree packages in your configuration, or for one command with NIXPKGS_ALLOW_UNFREE=1 and --impure", Some("nixpkgs.config.allowUnfree = true;")),
+    ("is marked as broken", "The package is known not to build or work on this system", "Use another version or package; allowBroken only hides the problem", None),
+    ("is marked as insecure", "The package has known security problems", "Prefer a newer version; if you must, list it in nixpkgs.config.permittedInsecurePackages", None),
+    ("experimental Nix feature", "The command uses a Nix feature that is turned off, usually flakes or the new nix command", "Turn the features on in your configuration", Some("nix.settings.experimental-features = [ \"nix-command\" \"flakes\" ];")),
+    ("undefined variable", "The expression uses a name that isn't defined where it is used", "Check the spelling; packages usually need a pkgs. prefix or a surrounding with pkgs;", None),
+    ("does not exist", "The option isn't defined by any module this configuration imports", "Check the spelling against the options search, or import the module that defines it", None),
+    ("infinite recursion encountered", "A value ended up depending on itself, often config used to decide imports or a self-referencing attribute", "Look at the lines in the trace; use lib.mkIf instead of if around config, and don't compute imports from config", None),
+    ("hash mismatch", "A download didn't match the hash the expression expects; the source changed or the hash is a placeholder", "Put the hash from the 'got:' line into the expression, if you trust the new source", None),
+    ("collision between", "Two packages in the same profile provide the same file", "Remove one of them, or give one a lower priority with lib.lowPrio", None),
+    ("No space left on device", "The disk holding the Nix store is full", "Free up space by collecting garbage and deleting old generations", Some("nix.gc = { automatic = true; options = \"--delete-older-than 30d\"; };")),
+    ("syntax error", "The Nix file doesn't parse; often a missing semicolon, bracket or quote", "Look just before the position in the error for what is missing", None),
+    ("cannot coerce", "A value of one type was used where a string was needed, such as an attribute set or a function", "Use the attribute you meant (e.g. pkg.outPath) or convert it explicitly", None),
+    ("was expected", "A value has a different type than the place it is used requires", "Compare the option's type with what you wrote; lists need [ ], sets need { }", None),
+    ("cannot connect to socket", "The Nix daemon isn't running or you aren't allowed to talk to it", "Check that nix-daemon.service is running and that you are in a group allowed to use it", None),
+    ("failed with exit code", "A package failed to build; the reason is in its build log", "Read the last lines of the log (nix log <drv>); a newer nixpkgs may have fixed it", None),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorExplanation {
+    // The error line itself, without the trace
+    pub error: String,
+    pub meaning: String,
+    pub fix: Option<String>,
+    pub snippet: Option<String>,
+    // Whether a known error was recognised rather than a generic Nix error
+    pub known: bool,
+}
+
+fn annotation(text: &str, kind: PartKind, meaning: impl Into<String>, known: bool) -> Annotation {
+    Annotation {
+        text: text.to_string(),
+        kind,
+        meaning: meaning.into(),
+        known,
+        value: None,
+        option_type: None,
+        default_value: None,
+    }
+}
+
+// Split like a shell would for the common cases: whitespace, quotes, backslashes
+pub fn tokenize(command: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '\\') => current.extend(chars.next()),
+            (None, c) if c.is_whitespace() => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            (None, c) => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+pub fn detect(input: &str) -> SnippetKind {
+    let first = input.split_whitespace().next().unwrap_or("");
+    let program = first.rsplit('/').next().unwrap_or(first);
+    if first == "$" || COMMANDS.iter().any(|(p, _, _)| *p == program) {
+        SnippetKind::Command
+    } else if input.contains('=') && (input.contains(';') || input.contains('{')) {
+        SnippetKind::Config
+    } else {
+        SnippetKind::Unknown
+    }
+}
+
+fn command_meaning(program: &str, subcommand: &str) -> Option<&'static str> {
+    COMMANDS
+        .iter()
+        .find(|(p, s, _)| *p == program && *s == subcommand)
+        .map(|(_, _, meaning)| *meaning)
+}
+
+fn flag_meaning(program: &str, flag: &str) -> Option<(bool, &'static str)> {
+    FLAGS
+        .iter()
+        .find(|(p, f, _, _)| *p == program && *f == flag)
+        .or_else(|| FLAGS.iter().find(|(p, f, _, _)| *p == "*" && *f == flag))
+        .map(|(_, _, takes_value, meaning)| (*takes_value, *meaning))
+}
+
+// "nixpkgs#hello", ".#web", "/etc/nixos#laptop", "nixpkgs.firefox"
+fn argument_meaning(argument: &str) -> (PartKind, String) {
+    if let Some((flake, output)) = argument.split_once('#') {
+        let source = match flake {
+            "" | "." => "the flake in this directory".to_string(),
+            "nixpkgs" => "nixpkgs".to_string(),
+            other if other.starts_with("github:") => {
+                format!("the GitHub flake {}", other.trim_start_matches("github:"))
+            }
+            other => format!("the flake at {}", other),
+        };
+        let kind = if flake == "nixpkgs" {
+            PartKind::Package
+        } else {
+            PartKind::Argument
+        };
+        return (kind, format!("The output \"{}\" of {}", output, source));
+    }
+    if let Some(package) = argument.strip_prefix("nixpkgs.") {
+        return (
+            PartKind::Package,
+            format!("The package {} from nixpkgs", package),
+        );
+    }
+    if argument.starts_with('/') || argument.starts_with("./") {
+        return (PartKind::Argument, format!("The path {}", argument));
+    }
+    (PartKind::Argument, format!("\"{}\"", argument))
+}
+
+fn explain_command(input: &str) -> Explanation {
+    let mut tokens = tokenize(input.trim_start_matches('$').trim());
+    let mut parts = Vec::new();
+    let mut summaries = Vec::new();
+
+    if tokens.first().map(String::as_str) == Some("sudo") {
+        tokens.remove(0);
+        parts.push(annotation(
+            "sudo",
+            PartKind::Program,
+            command_meaning("sudo", "").unwrap_or(""),
+            true,
+        ));
+        summaries.push("as root".to_string());
+    }
+    let Some(first) = tokens.first().cloned() else {
+        return unknown(input);
+    };
+    let program = first.rsplit('/').next().unwrap_or(&first).to_string();
+    parts.push(annotation(
+        &first,
+        PartKind::Program,
+        command_meaning(&program, "").unwrap_or("A program outside Nix's own tools"),
+        command_meaning(&program, "").is_some(),
+    ));
+
+    let mut index = 1;
+    // Subcommands ("nix profile install", "nixos-rebuild switch")
+    while let Some(token) = tokens.get(index) {
+        if token.starts_with('-') {
+            break;
+        }
+        let Some(meaning) = command_meaning(&program, token) else {
+            // Second-level subcommands of nix (profile install, flake update) have no table entry
+            if program == "nix" && index == 2 {
+                parts.push(annotation(
+                    token,
+                    PartKind::Subcommand,
+                    format!("The \"{}\" action", token),
+                    false,
+                ));
+                index += 1;
+            }
+            break;
+        };
+        parts.push(annotation(token, PartKind::Subcommand, meaning, true));
+        summaries.insert(0, meaning.to_string());
+        index += 1;
+    }
+    if summaries.is_empty() || summaries == ["as root"] {
+        if let Some(meaning) = command_meaning(&program, "") {
+            summaries.insert(0, meaning.to_string());
+        }
+    }
+
+    while let Some(token) = tokens.get(index).cloned() {
+        index += 1;
+        if token.starts_with('-') {
+            // --flag=value
+            let (flag, inline) = match token.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (token.clone(), None),
+            };
+            let mut part = match flag_meaning(&program, &flag) {
+                Some((takes_value, meaning)) => {
+                    let mut part = annotation(&flag, PartKind::Flag, meaning, true);
+                    part.value = inline.clone();
+                    if takes_value && inline.is_none() {
+                        part.value = tokens.get(index).cloned();
+                        index += 1;
+                    }
+                    part
+                }
+                None => annotation(
+                    &flag,
+                    PartKind::Flag,
+                    "A flag this assistant doesn't have notes on yet",
+                    false,
+                ),
+            };
+            // --option name value: explain the nix.conf setting too
+            if flag == "--option" {
+                if let Some(name) = part.value.clone() {
+                    if let Ok(spec) = nixconf::spec(&name) {
+                        part.meaning = format!("{}: {}", part.meaning, spec.explanation);
+                    }
+                    part.value = Some(format!(
+                        "{} {}",
+                        name,
+                        tokens.get(index).cloned().unwrap_or_default()
+                    ));
+                    index += 1;
+                }
+            }
+            parts.push(part);
+        } else {
+            let (kind, meaning) = argument_meaning(&token);
+            parts.push(annotation(&token, kind, meaning, true));
+        }
+    }
+
+    let mut explanation = Explanation {
+        input: input.to_string(),
+        kind: SnippetKind::Command,
+        summary: summaries.join(", "),
+        parts,
+        warnings: Vec::new(),
+        related: Vec::new(),
+    };
+    add_context(&mut explanation);
+    explanation
+}
+
+fn describe_option(option: &serde_json::Value) -> Option<String> {
+    let description = option.get("description")?;
+    let text = description
+        .as_str()
+        .or_else(|| description.get("text").and_then(|t| t.as_str()))?;
+    Some(text.trim().to_string())
+}
+
+fn render(value: Option<&serde_json::Value>) -> Option<String> {
+    let value = value?;
+    value
+        .get("text")
+        .and_then(|t| t.as_str())
+        .map(String::from)
+        .or_else(|| value.as_str().map(String::from))
+        .or_else(|| Some(value.to_string()))
+}
+
+fn options_index() -> Option<Arc<OptionsIndex>> {
+    let modified = std::fs::metadata(OPTIONS_INDEX)
+        .and_then(|m| m.modified())
+        .ok()?;
+    if let Some((at, index)) = PARSED_INDEX.blocking_lock().as_ref() {
+        if *at == modified {
+            return Some(index.clone());
+        }
+    }
+    // The index is tens of megabytes; parse it on the evaluation pool
+    let index: OptionsIndex = evalpool::run(Priority::Interactive, || {
+        storage::read_json(Path::new(OPTIONS_INDEX))
+    })
+    .ok()?;
+    let index = Arc::new(index);
+    *PARSED_INDEX.blocking_lock() = Some((modified, index.clone()));
+    Some(index)
+}
+
+// The best match over the whole path and each of its segments
+fn option_score(query: &str, path: &str) -> f32 {
+    std::iter::once(path)
+        .chain(path.split('.'))
+        .map(|text| fuzzy::score(query, text))
+        .fold(0.0, f32::max)
+}
+
+// Options whose path matches the query, best first; without an index on this
+// system only the common options are searched
+pub fn search_options(query: &str, limit: usize) -> Vec<OptionMatch> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let mut matches: Vec<OptionMatch> = match options_index() {
+        Some(index) => index
+            .iter()
+            .filter_map(|(path, option)| {
+                let score = option_score(query, path);
+                (score >= OPTION_MATCH_THRESHOLD).then(|| OptionMatch {
+                    path: path.clone(),
+                    description: describe_option(option),
+                    score,
+                })
+            })
+            .collect(),
+        None => FALLBACK_OPTIONS
+            .iter()
+            .filter_map(|(path, meaning)| {
+                let score = option_score(query, path);
+                (score >= OPTION_MATCH_THRESHOLD).then(|| OptionMatch {
+                    path: path.to_string(),
+                    description: Some(meaning.to_string()),
+                    score,
+                })
+            })
+            .collect(),
+    };
+    // Shorter paths first within equal scores: "services.openssh.enable"
+    // before everything nested under it
+    matches.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(a.path.len().cmp(&b.path.len()))
+    });
+    matches.truncate(limit);
+    matches
+}
+
+// (option path, value) pairs from a NixOS module, following nested attrsets
+pub fn assignments(config: &str) -> Vec<(String, String)> {
+    let mut found = Vec::new();
+    let mut prefix: Vec<String> = Vec::new();
+    let mut pending: Option<(String, String)> = None;
+    for raw in config.lines() {
+        let line = raw.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        // Continuation of a multi-line list or string
+        if let Some((path, mut value)) = pending.take() {
+            value.push(' ');
+            value.push_str(line);
+            if line.contains("];") || line.contains("'';") {
+                found.push((path, value.trim_end_matches(';').to_string()));
+            } else {
+                pending = Some((path, value));
+            }
+            continue;
+        }
+        if line.starts_with('}') {
+            prefix.pop();
+            continue;
+        }
+        let Some((left, right)) = line.split_once('=') else {
+            continue;
+        };
+        let left = left.trim();
+        let right = right.trim();
+        if left.is_empty() || left.contains(' ') || left.contains('{') {
+            continue;
+        }
+        let path = prefix
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once(left))
+            .collect::<Vec<_>>()
+            .join(".");
+        if right == "{" || (right.ends_with('{') && !right.contains('}')) {
+            prefix.push(left.to_string());
+        } else if right.ends_with(';') {
+            found.push((path, right.trim_end_matches(';').trim().to_string()));
+        } else {
+            pending = Some((path, right.to_string()));
+        }
+    }
+    found
+}
+
+// Package names in "with pkgs; [ firefox git ]" or "[ pkgs.firefox ]"
+pub fn packages_in(value: &str) -> Vec<String> {
+    let Some(list) = value.split_once('[').map(|(_, rest)| rest) else {
+        return Vec::new();
+    };
+    list.trim_end_matches([']', ';', ' '])
+        .split_whitespace()
+        .map(|p| p.trim_start_matches("pkgs.").to_string())
+        .filter(|p| !p.is_empty() && p != "]")
+        .collect()
+}
+
+fn explain_config(input: &str) -> Explanation {
+    let index = options_index();
+    let mut parts = Vec::new();
+    let found = assignments(input);
+    for (path, value) in &found {
+        let indexed = index.as_ref().and_then(|options| options.get(path));
+        let nix_setting = path
+            .strip_prefix("nix.settings.")
+            .and_then(|name| nixconf::spec(name).ok());
+        let fallback = FALLBACK_OPTIONS
+            .iter()
+            .find(|(p, _)| p == path)
+            .map(|(_, m)| *m);
+        let meaning = indexed
+            .and_then(describe_option)
+            .or_else(|| nix_setting.map(|s| s.explanation.to_string()))
+            .or_else(|| fallback.map(String::from));
+        let mut part = annotation(
+            path,
+            PartKind::Option,
+            meaning.clone().unwrap_or_else(|| {
+                "Not an option this system knows; check the spelling".to_string()
+            }),
+            meaning.is_some(),
+        );
+        part.value = Some(value.clone());
+        part.option_type = indexed
+            .and_then(|o| o.get("type"))
+            .and_then(|t| t.as_str())
+            .map(String::from);
+        part.default_value = render(indexed.and_then(|o| o.get("default")));
+        parts.push(part);
+        if path.ends_with("Packages") || path.ends_with("packages") {
+            for package in packages_in(value) {
+                parts.push(annotation(
+                    &package,
+                    PartKind::Package,
+                    format!("The {} package", package),
+                    true,
+                ));
+            }
+        }
+    }
+    let unknown_count = parts
+        .iter()
+        .filter(|p| p.kind == PartKind::Option && !p.known)
+        .count();
+    let mut explanation = Explanation {
+        input: input.to_string(),
+        kind: SnippetKind::Config,
+        summary: format!(
+            "Sets {} option(s){}",
+            found.len(),
+            if unknown_count > 0 {
+                format!(", {} of which this system doesn't recognise", unknown_count)
+            } else {
+                String::new()
+            }
+        ),
+        parts,
+        warnings: Vec::new(),
+        related: vec!["configuration-nix".to_string()],
+    };
+    if index.is_none() {
+        explanation.warnings.push(
+            "The NixOS options index isn't installed (documentation.nixos.enable), so only common options are explained".to_string(),
+        );
+    }
+    add_context(&mut explanation);
+    explanation
+}
+
+fn unknown(input: &str) -> Explanation {
+    Explanation {
+        input: input.to_string(),
+        kind: SnippetKind::Unknown,
+        summary: "This doesn't look like a Nix command or a NixOS configuration snippet"
+            .to_string(),
+        parts: Vec::new(),
+        warnings: Vec::new(),
+        related: Vec::new(),
+    }
+}
+
+// Warnings and glossary links that apply to the snippet as a whole
+fn add_context(explanation: &mut Explanation) {
+    for (pattern, warning) in WARNINGS {
+        if explanation.input.contains(pattern) && !explanation.warnings.iter().any(|w| w == warning)
+        {
+            explanation.warnings.push(warning.to_string());
+        }
+    }
+    let words = explanation.parts.iter().map(|p| p.text.as_str()).chain(
+        ["flake", "generation", "garbage collection"]
+            .into_iter()
+            .filter(|w| explanation.input.contains(w)),
+    );
+    for word in words.collect::<Vec<_>>() {
+        if let Some(topic) = glossary::find(word) {
+            if !explanation.related.iter().any(|r| r == topic.id) {
+                explanation.related.push(topic.id.to_string());
+            }
+        }
+    }
+}
+
+pub fn breakdown(input: &str) -> Explanation {
+    let input = input.trim();
+    match detect(input) {
+        SnippetKind::Command => explain_command(input),
+        SnippetKind::Config => explain_config(input),
+        SnippetKind::Unknown => unknown(input),
+    }
+}
+
+// A Nix error message in plain language, or None when the text isn't one
+pub fn explain_error(text: &str) -> Option<ErrorExplanation> {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|l| l.starts_with("error:"))?;
+    let error = line.trim_start_matches("error:").trim().to_string();
+    let known = NIX_ERRORS
+        .iter()
+        .find(|(pattern, ..)| text.contains(pattern));
+    if let Some((_, meaning, fix, snippet)) = known {
+        return Some(ErrorExplanation {
+            error,
+            meaning: meaning.to_string(),
+            fix: Some(fix.to_string()),
+            snippet: snippet.map(String::from),
+            known: true,
+        });
+    }
+    // Plenty of tools print "error:"; only take it as Nix's with a Nix marker
+    let nix_markers = [
+        "/nix/store",
+        "derivation",
+        ".nix:",
+        "nixpkgs",
+        "nix-daemon",
+        "«",
+    ];
+    nix_markers
+        .iter()
+        .any(|marker| text.contains(marker))
+        .then(|| ErrorExplanation {
+            error,
+            meaning: "Nix stopped with an error it doesn't explain further".to_string(),
+            fix: Some(
+                "Run the command again with --show-trace to see where it comes from".to_string(),
+            ),
+            snippet: None,
+            known: false,
+        })
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn explain(command_or_config: String, app: AppHandle) -> Explanation {
+    tasks::blocking_value(&app, "Explain", move |_| breakdown(&command_or_config)).await
+}
+
+#[tauri::command]
+pub async fn explain_nix_error(error: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Explain Nix error", move |_| {
+        crate::respond(
+            explain_error(&error)
+                .ok_or_else(|| anyhow::anyhow!("That doesn't look like a Nix error")),
+        )
+    })
+    .await
+}
@@ -0,0 +1,266 @@
+// Flatpak and AppImage awareness
+//
+// Finds software installed outside of Nix, and bridges it back: either by
+// managing flatpak remotes/apps declaratively (nix-flatpak) or by finding the
+// native nixpkgs package that could replace it.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+use crate::nix::{self, Package};
+use crate::nixgen::{self, NixModule, NixOption, Target};
+use crate::{managed, system, tasks};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlatpakApp {
+    pub app_id: String,
+    pub name: String,
+    pub version: String,
+    pub origin: String,
+    // "system" or "user"
+    pub installation: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlatpakRemote {
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppImage {
+    pub name: String,
+    pub path: PathBuf,
+    pub size_bytes: u64,
+}
+
+// Candidate nixpkgs replacements for a foreign install
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NativeEquivalent {
+    pub source_name: String,
+    pub candidates: Vec<Package>,
+}
+
+fn columns(line: &str) -> Vec<String> {
+    line.split('\t').map(|c| c.trim().to_string()).collect()
+}
+
+pub fn list_apps() -> Vec<FlatpakApp> {
+    let Ok(output) = system::run(
+        "flatpak",
+        &[
+            "list",
+            "--app",
+            "--columns=application,name,version,origin,installation",
+        ],
+    ) else {
+        return Vec::new();
+    };
+    output
+        .lines()
+        .map(columns)
+        .filter(|c| c.len() >= 5)
+        .map(|c| FlatpakApp {
+            app_id: c[0].clone(),
+            name: c[1].clone(),
+            version: c[2].clone(),
+            origin: c[3].clone(),
+            installation: c[4].clone(),
+        })
+        .collect()
+}
+
+pub fn list_remotes() -> Vec<FlatpakRemote> {
+    let Ok(output) = system::run("flatpak", &["remotes", "--columns=name,url"]) else {
+        return Vec::new();
+    };
+    output
+        .lines()
+        .map(columns)
+        .filter(|c| c.len() >= 2)
+        .map(|c| FlatpakRemote {
+            name: c[0].clone(),
+            url: c[1].clone(),
+        })
+        .collect()
+}
+
+// Directories where people usually leave AppImages
+fn appimage_dirs() -> Vec<PathBuf> {
+    let home = system::home_dir();
+    vec![
+        home.clone(),
+        home.join("Applications"),
+        home.join("AppImages"),
+        home.join("Downloads"),
+        home.join(".local/bin"),
+    ]
+}
+
+// "Obsidian-1.5.3.AppImage" -> "obsidian"
+fn appimage_name(file_name: &str) -> String {
+    let stem = file_name
+        .rsplit_once('.')
+        .map(|(stem, _)| stem)
+        .unwrap_or(file_name);
+    stem.split(['-', '_'])
+        .take_while(|part| !part.starts_with(|c: char| c.is_ascii_digit()) && *part != "x86")
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase()
+}
+
+pub fn list_appimages() -> Vec<AppImage> {
+    let mut found = Vec::new();
+    for dir in appimage_dirs() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if !file_name.to_lowercase().ends_with(".appimage") {
+                continue;
+            }
+            found.push(AppImage {
+                name: appimage_name(&file_name),
+                size_bytes: entry.metadata().map(|m| m.len()).unwrap_or(0),
+                path: entry.path(),
+            });
+        }
+    }
+    found
+}
+
+// "org.mozilla.firefox" -> "firefox"
+fn flatpak_short_name(app: &FlatpakApp) -> String {
+    app.app_id
+        .rsplit('.')
+        .next()
+        .unwrap_or(&app.app_id)
+        .to_lowercase()
+}
+
+fn find_equivalent(source_name: &str, search_terms: &[String]) -> NativeEquivalent {
+    let mut candidates: Vec<Package> = Vec::new();
+    for term in search_terms {
+        let Ok(results) = nix::search(&format!("^{}$", regex_escape(term))) else {
+            continue;
+        };
+        for package in results {
+            if !candidates.iter().any(|c| c.attr == package.attr) {
+                candidates.push(package);
+            }
+        }
+        if !candidates.is_empty() {
+            break;
+        }
+    }
+    NativeEquivalent {
+        source_name: source_name.to_string(),
+        candidates,
+    }
+}
+
+fn regex_escape(term: &str) -> String {
+    term.chars()
+        .flat_map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                vec![c]
+            } else {
+                vec!['\\', c]
+            }
+        })
+        .collect()
+}
+
+pub fn native_equivalents() -> Vec<NativeEquivalent> {
+    let flatpaks = list_apps().into_iter().map(|app| {
+        let terms = vec![
+            flatpak_short_name(&app),
+            app.name.to_lowercase().replace(' ', "-"),
+        ];
+        find_equivalent(&app.app_id, &terms)
+    });
+    let appimages = list_appimages().into_iter().map(|image| {
+        find_equivalent(
+            &image.path.to_string_lossy(),
+            std::slice::from_ref(&image.name),
+        )
+    });
+    flatpaks.chain(appimages).collect()
+}
+
+// Module for the nix-flatpak NixOS module mirroring the current remotes and apps
+pub fn declarative_module() -> NixModule {
+    let mut module = NixModule::new("flatpak", "flatpak remotes and apps", Target::Nixos);
+    module.set(
+        NixOption::new("services.flatpak.enable", nixgen::bool(true))
+            .with_comment("Requires importing nix-flatpak's nixosModules.nix-flatpak"),
+    );
+
+    let remotes: Vec<String> = list_remotes()
+        .iter()
+        .map(|r| {
+            format!(
+                "{{ name = {}; location = {}; }}",
+                nixgen::string(&r.name),
+                nixgen::string(&r.url)
+            )
+        })
+        .collect();
+    if !remotes.is_empty() {
+        module.set(NixOption::new(
+            "services.flatpak.remotes",
+            nixgen::list(&remotes),
+        ));
+    }
+
+    let apps: Vec<String> = list_apps()
+        .iter()
+        .filter(|a| a.installation == "system")
+        .map(|a| {
+            format!(
+                "{{ appId = {}; origin = {}; }}",
+                nixgen::string(&a.app_id),
+                nixgen::string(&a.origin)
+            )
+        })
+        .collect();
+    if !apps.is_empty() {
+        module.set(NixOption::new(
+            "services.flatpak.packages",
+            nixgen::list(&apps),
+        ));
+    }
+    module
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn find_native_equivalents(app: AppHandle) -> Vec<NativeEquivalent> {
+    tasks::blocking_value(&app, "Find native equivalents", |_| native_equivalents()).await
+}
+
+#[tauri::command]
+pub async fn manage_flatpak_declaratively(apply: bool, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Manage flatpak declaratively", move |_| {
+        let module = declarative_module();
+        if !apply {
+            return serde_json::json!({"success": true, "data": {"preview": module.render()}});
+        }
+        if let Some(refusal) = managed::guard("configure", "Manage flatpak declaratively") {
+            return refusal;
+        }
+        crate::respond(module.write().map(|path| {
+            serde_json::json!({
+                "module_path": path,
+                "preview": module.render(),
+                "next_step": "Rebuild the system so flatpak state is managed by NixOS",
+            })
+        }))
+    })
+    .await
+}
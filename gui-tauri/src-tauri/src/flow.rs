@@ -0,0 +1,159 @@
+// Flow-state protection: hold back non-critical interruptions while the user
+// is working quickly and successfully
+//
+// Flow is read from the interaction history: a sustained stretch of frequent
+// interactions that nearly all succeed. While it lasts, non-critical events
+// (boot reports, care invitations, suggestions) are queued instead of shown,
+// and they are delivered at the next natural break, a pause in activity.
+// Every change is emitted as a "flow-state" event so the frontend can show
+// that protection is on and hold back its own prompts.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Mutex;
+
+use crate::{clock, tasks, AppState};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+// Stretch of activity that counts as sustained
+pub const MIN_FLOW_MS: u64 = 5 * 60 * 1000;
+pub const MIN_INTERACTIONS: usize = 8;
+pub const MIN_SUCCESS_RATE: f32 = 0.9;
+// Gaps longer than this end the stretch; a quiet spell this long is a break
+pub const MAX_GAP_MS: u64 = 90 * 1000;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FlowState {
+    pub in_flow: bool,
+    // When the current stretch of activity began
+    pub since_ms: Option<u64>,
+    pub interactions: usize,
+    pub success_rate: f32,
+    // Notifications waiting for the next break
+    pub deferred: usize,
+}
+
+struct Deferred {
+    event: String,
+    payload: serde_json::Value,
+}
+
+#[derive(Default)]
+struct Tracker {
+    state: FlowState,
+    queue: Vec<Deferred>,
+}
+
+static TRACKER: Mutex<Option<Tracker>> = Mutex::const_new(None);
+
+fn timestamp(interaction: &serde_json::Value) -> Option<u64> {
+    interaction.get("timestamp_ms").and_then(|t| t.as_u64())
+}
+
+// The unbroken stretch of activity leading up to now, if it is flow
+pub fn detect(history: &[serde_json::Value], now_ms: u64) -> FlowState {
+    let mut stretch: Vec<&serde_json::Value> = Vec::new();
+    let mut next = now_ms;
+    for interaction in history.iter().rev() {
+        let Some(at) = timestamp(interaction) else {
+            continue;
+        };
+        if next.saturating_sub(at) > MAX_GAP_MS {
+            break;
+        }
+        stretch.push(interaction);
+        next = at;
+    }
+    let outcomes: Vec<bool> = stretch
+        .iter()
+        .filter_map(|i| i.get("success").and_then(|s| s.as_bool()))
+        .collect();
+    let success_rate = if outcomes.is_empty() {
+        0.0
+    } else {
+        outcomes.iter().filter(|ok| **ok).count() as f32 / outcomes.len() as f32
+    };
+    let since_ms = stretch.last().and_then(|i| timestamp(i));
+    let sustained = since_ms.is_some_and(|since| now_ms.saturating_sub(since) >= MIN_FLOW_MS);
+    FlowState {
+        in_flow: sustained && stretch.len() >= MIN_INTERACTIONS && success_rate >= MIN_SUCCESS_RATE,
+        since_ms,
+        interactions: stretch.len(),
+        success_rate,
+        deferred: 0,
+    }
+}
+
+pub fn current() -> FlowState {
+    TRACKER
+        .blocking_lock()
+        .as_ref()
+        .map(|t| t.state.clone())
+        .unwrap_or_default()
+}
+
+pub fn in_flow() -> bool {
+    current().in_flow
+}
+
+// Emit `event` now, or hold it until the next break when it isn't critical
+// and the user is in flow
+pub fn notify<S: Serialize>(app: &AppHandle, event: &str, payload: S, critical: bool) {
+    {
+        let mut tracker = TRACKER.blocking_lock();
+        let tracker = tracker.get_or_insert_with(Tracker::default);
+        if !critical && tracker.state.in_flow {
+            tracker.queue.push(Deferred {
+                event: event.to_string(),
+                payload: serde_json::json!(payload),
+            });
+            tracker.state.deferred = tracker.queue.len();
+            return;
+        }
+    }
+    let _ = app.emit(event, payload);
+}
+
+fn update(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let detected = detect(&state.interaction_history.blocking_lock(), clock::now_ms());
+    let (changed, released) = {
+        let mut tracker = TRACKER.blocking_lock();
+        let tracker = tracker.get_or_insert_with(Tracker::default);
+        let was_in_flow = tracker.state.in_flow;
+        let released = if detected.in_flow {
+            Vec::new()
+        } else {
+            std::mem::take(&mut tracker.queue)
+        };
+        let next = FlowState {
+            deferred: tracker.queue.len(),
+            ..detected
+        };
+        // Only entering or leaving flow (or the queue size) is news
+        let changed = next.in_flow != was_in_flow || next.deferred != tracker.state.deferred;
+        tracker.state = next;
+        (changed.then(|| tracker.state.clone()), released)
+    };
+    if let Some(state) = changed {
+        let _ = app.emit("flow-state", state);
+    }
+    for deferred in released {
+        let _ = app.emit(&deferred.event, deferred.payload);
+    }
+}
+
+pub fn start_watcher(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        update(&app);
+        std::thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_flow_state(app: AppHandle) -> FlowState {
+    tasks::blocking_value(&app, "Get flow state", |_| current()).await
+}
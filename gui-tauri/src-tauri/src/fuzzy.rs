@@ -0,0 +1,109 @@
+// Approximate string matching for package names and commands
+//
+// Combines edit distance, trigram overlap, prefix matching and a Soundex-style
+// phonetic key so "pyton", "fire fox" and "chrom" still find their targets.
+
+use std::collections::HashSet;
+
+// Lowercase and drop separators: "Fire Fox" and "fire-fox" both become "firefox"
+pub fn squash(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn trigrams(text: &str) -> HashSet<[char; 3]> {
+    let padded: Vec<char> = format!("  {} ", text).chars().collect();
+    padded.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+}
+
+// Jaccard similarity of the padded trigram sets
+pub fn trigram_similarity(a: &str, b: &str) -> f32 {
+    let (a, b) = (trigrams(a), trigrams(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f32 / union as f32
+}
+
+// Soundex key, digits included so "python3" and "python312" stay distinct
+pub fn phonetic_key(text: &str) -> String {
+    let code = |c: char| match c {
+        'b' | 'f' | 'p' | 'v' => Some('1'),
+        'c' | 'g' | 'j' | 'k' | 'q' | 's' | 'x' | 'z' => Some('2'),
+        'd' | 't' => Some('3'),
+        'l' => Some('4'),
+        'm' | 'n' => Some('5'),
+        'r' => Some('6'),
+        _ => None,
+    };
+    let mut chars = text.chars().filter(|c| c.is_ascii_alphanumeric());
+    let Some(first) = chars.next() else {
+        return String::new();
+    };
+    let mut key = first.to_ascii_uppercase().to_string();
+    let mut last = code(first);
+    for c in chars {
+        if c.is_ascii_digit() {
+            key.push(c);
+            last = None;
+            continue;
+        }
+        let current = code(c);
+        if current.is_some() && current != last {
+            key.extend(current);
+        }
+        // h and w do not separate equal codes; vowels do
+        if !matches!(c, 'h' | 'w') {
+            last = current;
+        }
+    }
+    key
+}
+
+// Similarity in [0, 1] between what the user typed and a candidate name
+pub fn score(query: &str, candidate: &str) -> f32 {
+    let (query, candidate) = (squash(query), squash(candidate));
+    if query.is_empty() || candidate.is_empty() {
+        return 0.0;
+    }
+    if query == candidate {
+        return 1.0;
+    }
+    let longest = query.chars().count().max(candidate.chars().count()) as f32;
+    let edit = 1.0 - levenshtein(&query, &candidate) as f32 / longest;
+    let mut best = 0.5 * edit + 0.5 * trigram_similarity(&query, &candidate);
+    if query.len() >= 3 {
+        if candidate.starts_with(&query) {
+            best = best.max(0.9 - 0.01 * (candidate.len() - query.len()) as f32);
+        } else if candidate.contains(&query) {
+            best = best.max(0.7);
+        }
+    }
+    // Version suffixes shouldn't hide a phonetic match ("pyton" ~ "python3")
+    let sound = |text: &str| {
+        phonetic_key(text)
+            .trim_end_matches(|c: char| c.is_ascii_digit())
+            .to_string()
+    };
+    if sound(&query) == sound(&candidate) {
+        best += 0.15;
+    }
+    best.min(0.99)
+}
@@ -0,0 +1,210 @@
+// One search box for the whole app
+//
+// global_search asks every source at once: the package search, the NixOS
+// options index, the interaction history and the help topics. Each source runs
+// on its own thread, so a slow nix search doesn't hold up the others, and
+// answers with its own group of results tagged with where they came from. A
+// source that fails reports its error in its group and the rest still show.
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::{explain, fuzzy, glossary, history, search, tasks};
+
+const DEFAULT_LIMIT: usize = 10;
+// Below this a help topic isn't worth showing
+const TOPIC_THRESHOLD: f32 = 0.6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    Packages,
+    Options,
+    History,
+    Help,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hit {
+    pub source: Source,
+    pub id: String,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub score: f32,
+    // What the frontend needs to act on it: the package, the history match
+    // with its rerun and undo, ...
+    pub data: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResultGroup {
+    pub source: Source,
+    pub results: Vec<Hit>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlobalResults {
+    pub query: String,
+    pub groups: Vec<ResultGroup>,
+}
+
+fn packages(query: &str, limit: usize) -> anyhow::Result<Vec<Hit>> {
+    let outcome = search::search(query)?;
+    let mut hits: Vec<Hit> = outcome
+        .results
+        .into_iter()
+        .map(|package| Hit {
+            source: Source::Packages,
+            id: format!("package:{}", package.attr),
+            title: package.attr.clone(),
+            subtitle: Some(package.description.clone()).filter(|d| !d.is_empty()),
+            score: fuzzy::score(query, &package.attr),
+            data: serde_json::to_value(&package).unwrap_or_default(),
+        })
+        .collect();
+    // Nothing found; offer the close names instead
+    if hits.is_empty() {
+        hits = outcome
+            .did_you_mean
+            .into_iter()
+            .map(|suggestion| Hit {
+                source: Source::Packages,
+                id: format!("package:{}", suggestion.attr),
+                title: suggestion.attr.clone(),
+                subtitle: Some("Did you mean this?".to_string()),
+                score: suggestion.score,
+                data: serde_json::json!({"attr": suggestion.attr}),
+            })
+            .collect();
+    }
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(limit);
+    Ok(hits)
+}
+
+fn options(query: &str, limit: usize) -> Vec<Hit> {
+    explain::search_options(query, limit)
+        .into_iter()
+        .map(|option| Hit {
+            source: Source::Options,
+            id: format!("option:{}", option.path),
+            title: option.path.clone(),
+            subtitle: option.description.clone(),
+            score: option.score,
+            data: serde_json::to_value(&option).unwrap_or_default(),
+        })
+        .collect()
+}
+
+fn past(query: &str, limit: usize) -> Vec<Hit> {
+    history::search(query)
+        .matches
+        .into_iter()
+        .take(limit)
+        .map(|m| Hit {
+            source: Source::History,
+            id: format!("history:{}", m.entry.id),
+            title: m.entry.description.clone(),
+            subtitle: Some(if m.entry.succeeded {
+                "Done earlier".to_string()
+            } else {
+                "Failed earlier".to_string()
+            }),
+            score: m.score,
+            data: serde_json::to_value(&m).unwrap_or_default(),
+        })
+        .collect()
+}
+
+fn help(query: &str, limit: usize) -> Vec<Hit> {
+    let exact = glossary::find(query).map(|topic| topic.id);
+    let mut hits: Vec<Hit> = glossary::TOPICS
+        .iter()
+        .filter_map(|topic| {
+            let score = if exact == Some(topic.id) {
+                1.0
+            } else {
+                std::iter::once(topic.id)
+                    .chain(std::iter::once(topic.title))
+                    .chain(topic.aliases.iter().copied())
+                    .map(|text| fuzzy::score(query, text))
+                    .fold(0.0, f32::max)
+            };
+            (score >= TOPIC_THRESHOLD).then(|| Hit {
+                source: Source::Help,
+                id: format!("help:{}", topic.id),
+                title: topic.title.to_string(),
+                subtitle: Some(topic.summary.to_string()),
+                score,
+                data: serde_json::json!({"topic": topic.id}),
+            })
+        })
+        .collect();
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(limit);
+    hits
+}
+
+fn group(source: Source, results: anyhow::Result<Vec<Hit>>) -> ResultGroup {
+    match results {
+        Ok(results) => ResultGroup {
+            source,
+            results,
+            error: None,
+        },
+        Err(e) => ResultGroup {
+            source,
+            results: Vec::new(),
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+// Every source at once, at most `limit` results from each
+pub fn search(query: &str, limit: usize) -> GlobalResults {
+    let query = query.trim();
+    if query.is_empty() {
+        return GlobalResults {
+            query: String::new(),
+            groups: Vec::new(),
+        };
+    }
+    let groups = std::thread::scope(|scope| {
+        let packages_thread = scope.spawn(|| packages(query, limit));
+        let options_thread = scope.spawn(|| options(query, limit));
+        let past_thread = scope.spawn(|| past(query, limit));
+        let help_thread = scope.spawn(|| help(query, limit));
+        let joined = |source: Source, result: std::thread::Result<anyhow::Result<Vec<Hit>>>| {
+            group(
+                source,
+                result.unwrap_or_else(|_| Err(anyhow::anyhow!("The search stopped unexpectedly"))),
+            )
+        };
+        vec![
+            joined(Source::Packages, packages_thread.join()),
+            joined(Source::Options, options_thread.join().map(Ok)),
+            joined(Source::History, past_thread.join().map(Ok)),
+            joined(Source::Help, help_thread.join().map(Ok)),
+        ]
+    });
+    GlobalResults {
+        query: query.to_string(),
+        groups,
+    }
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn global_search(
+    query: String,
+    limit: Option<usize>,
+    app: AppHandle,
+) -> serde_json::Value {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    tasks::blocking_json(&app, "Searching everything", move |_| {
+        crate::respond(Ok(search(&query, limit)))
+    })
+    .await
+}
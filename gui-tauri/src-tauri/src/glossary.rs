@@ -0,0 +1,103 @@
+// Plain-language explanations of core Nix/NixOS concepts
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Topic {
+    pub id: &'static str,
+    pub title: &'static str,
+    pub aliases: &'static [&'static str],
+    pub summary: &'static str,
+    // More background for someone meeting the concept for the first time
+    pub detail: &'static str,
+}
+
+pub const TOPICS: &[Topic] = &[
+    Topic {
+        id: "flakes",
+        title: "Flakes",
+        aliases: &["flake", "flake.nix", "flake.lock"],
+        summary: "A flake is a folder with a flake.nix that declares its inputs and outputs. The flake.lock pins every input to an exact revision, so builds are reproducible.",
+        detail: "Flakes are still marked experimental, so they have to be enabled in nix.conf first. `nix flake update` moves the pins forward; until you run it, everyone using the same flake.lock gets exactly the same versions.",
+    },
+    Topic {
+        id: "generations",
+        title: "Generations",
+        aliases: &["generation", "rollback", "roll back"],
+        summary: "Every rebuild creates a new generation of your system. Older generations stay on disk and in the boot menu, so you can always go back to one that worked.",
+        detail: "Generations are numbered, and `nixos-rebuild switch --rollback` or picking an older entry at boot takes you back. They take up disk space until you delete old ones and collect garbage.",
+    },
+    Topic {
+        id: "profiles",
+        title: "Profiles",
+        aliases: &["profile", "nix profile", "nix-env"],
+        summary: "A profile is a set of installed packages. The system profile belongs to NixOS, each user has their own, and projects can have separate ones.",
+        detail: "Installing with `nix profile install` changes only your own profile, while packages in configuration.nix go into the system profile. Each profile has generations of its own, so installs can be rolled back too.",
+    },
+    Topic {
+        id: "home-manager",
+        title: "Home Manager",
+        aliases: &["home manager", "hm", "home.nix"],
+        summary: "Home Manager applies the NixOS idea to your user account: dotfiles, user services and per-user packages are declared in home.nix.",
+        detail: "It can run as a NixOS module, so `nixos-rebuild` applies it, or standalone with the `home-manager switch` command. Either way, files it manages should be changed in home.nix rather than edited directly.",
+    },
+    Topic {
+        id: "garbage-collection",
+        title: "Garbage collection",
+        aliases: &["gc", "garbage", "nix-collect-garbage", "clean up"],
+        summary: "Garbage collection deletes store paths nothing refers to anymore. Deleting old generations first lets it free much more space.",
+        detail: "Anything a generation or a running program still uses is kept, so collecting garbage never breaks the current system. `nix-collect-garbage -d` also deletes old generations, which means they can no longer be rolled back to.",
+    },
+    Topic {
+        id: "nix-store",
+        title: "The Nix store",
+        aliases: &["store", "/nix/store", "store path"],
+        summary: "All packages live read-only under /nix/store, each in a folder named after a hash of how it was built. That is why different versions never conflict.",
+        detail: "Nothing in the store is edited in place; a change builds a new path instead. Paths are shared between generations and users, so installing something twice takes no extra space.",
+    },
+    Topic {
+        id: "channels",
+        title: "Channels",
+        aliases: &["channel", "nix-channel"],
+        summary: "Channels are the older way of choosing which nixpkgs version you follow. Flakes replace them with explicit, locked inputs.",
+        detail: "`nix-channel --update` fetches the latest nixpkgs from a channel, and the next rebuild uses it. Because the version isn't recorded anywhere, two machines on the same channel can end up with different packages.",
+    },
+    Topic {
+        id: "derivations",
+        title: "Derivations",
+        aliases: &["derivation", "drv"],
+        summary: "A derivation is a precise recipe for building something: its inputs, build script and environment. Nix builds derivations into store paths.",
+        detail: "You rarely write derivations by hand: functions like mkDerivation and the package definitions in nixpkgs create them for you. The same derivation always produces the same store path, which is what makes binary caches work.",
+    },
+    Topic {
+        id: "configuration-nix",
+        title: "configuration.nix",
+        aliases: &["configuration", "config", "nixos config", "configuration.nix"],
+        summary: "configuration.nix describes your whole system. Changing it and running nixos-rebuild switch makes the system match the description.",
+        detail: "It lives in /etc/nixos and usually imports hardware-configuration.nix, which describes disks and drivers. Editing the file alone changes nothing until you rebuild.",
+    },
+    Topic {
+        id: "rebuild",
+        title: "Rebuilding",
+        aliases: &["nixos-rebuild", "rebuild", "switch"],
+        summary: "nixos-rebuild builds the system described by your configuration. `switch` activates it now, `boot` on next boot, and `test` only until reboot.",
+        detail: "A rebuild that fails leaves the running system untouched. Adding `--upgrade` fetches newer packages first; without it, the rebuild only applies your configuration changes.",
+    },
+];
+
+pub fn find(query: &str) -> Option<&'static Topic> {
+    let query = query.trim().to_lowercase();
+    let query = query
+        .trim_start_matches("a ")
+        .trim_start_matches("an ")
+        .trim_start_matches("the ")
+        .trim_end_matches('?');
+    TOPICS
+        .iter()
+        .find(|t| t.id == query || t.title.to_lowercase() == query || t.aliases.contains(&query))
+        .or_else(|| {
+            TOPICS.iter().find(|t| {
+                query.contains(t.id) || t.aliases.iter().any(|a| a.len() > 3 && query.contains(a))
+            })
+        })
+}
@@ -0,0 +1,370 @@
+// Hardware configuration scanner
+//
+// nixos-generate-config style detection (lspci, lsusb, sysfs) that reports the
+// GPUs, network adapters and peripherals it finds together with the NixOS
+// options each one needs, ready for the intent-to-nix generator.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::nixgen::{self, NixModule, NixOption, Target};
+use crate::{system, tasks};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceKind {
+    Gpu,
+    Wifi,
+    Ethernet,
+    Bluetooth,
+    Audio,
+    Fingerprint,
+    Webcam,
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Device {
+    pub kind: DeviceKind,
+    pub bus: String,
+    pub vendor: String,
+    pub vendor_id: String,
+    pub product: String,
+    pub product_id: String,
+}
+
+// Options recommended for one detected device, with a plain-language reason
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recommendation {
+    pub id: String,
+    pub device: Option<Device>,
+    pub reason: String,
+    pub options: Vec<NixOption>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HardwareReport {
+    pub cpu_vendor: Option<String>,
+    pub is_laptop: bool,
+    pub gpus: Vec<Device>,
+    pub network: Vec<Device>,
+    pub peripherals: Vec<Device>,
+    pub recommendations: Vec<Recommendation>,
+    pub module_preview: String,
+    // Output of `nixos-generate-config --show-hardware-config` when available
+    pub generated_config: Option<String>,
+}
+
+// Split an `lspci -mm -nn` line into its quoted fields
+fn quoted_fields(line: &str) -> Vec<String> {
+    line.split('"')
+        .enumerate()
+        .filter(|(i, _)| i % 2 == 1)
+        .map(|(_, field)| field.to_string())
+        .collect()
+}
+
+// "Intel Corporation [8086]" -> ("Intel Corporation", "8086")
+fn split_id(field: &str) -> (String, String) {
+    match field.rsplit_once(" [") {
+        Some((name, id)) => (name.to_string(), id.trim_end_matches(']').to_string()),
+        None => (field.to_string(), String::new()),
+    }
+}
+
+fn scan_pci() -> Vec<Device> {
+    let Ok(output) = system::run("lspci", &["-mm", "-nn"]) else {
+        return Vec::new();
+    };
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields = quoted_fields(line);
+            if fields.len() < 3 {
+                return None;
+            }
+            let (_, class_id) = split_id(&fields[0]);
+            let (vendor, vendor_id) = split_id(&fields[1]);
+            let (product, product_id) = split_id(&fields[2]);
+            let kind = match class_id.get(..2).unwrap_or("") {
+                "03" => DeviceKind::Gpu,
+                "02" if class_id == "0280" => DeviceKind::Wifi,
+                "02" => DeviceKind::Ethernet,
+                "04" if class_id == "0403" => DeviceKind::Audio,
+                _ => DeviceKind::Other,
+            };
+            Some(Device {
+                kind,
+                bus: "pci".to_string(),
+                vendor,
+                vendor_id,
+                product,
+                product_id,
+            })
+        })
+        .collect()
+}
+
+// USB vendors that only show up as fingerprint readers on laptops
+const FINGERPRINT_VENDORS: &[&str] = &["06cb", "27c6", "138a", "04f3", "1c7a"];
+
+fn scan_usb() -> Vec<Device> {
+    let Ok(output) = system::run("lsusb", &[]) else {
+        return Vec::new();
+    };
+    output
+        .lines()
+        .filter_map(|line| {
+            // Bus 001 Device 003: ID 06cb:00bd Synaptics, Inc. Fingerprint Reader
+            let rest = line.split_once(" ID ")?.1;
+            let (ids, description) = rest.split_once(' ').unwrap_or((rest, ""));
+            let (vendor_id, product_id) = ids.split_once(':')?;
+            let lower = description.to_lowercase();
+            let kind = if lower.contains("fingerprint") || FINGERPRINT_VENDORS.contains(&vendor_id)
+            {
+                DeviceKind::Fingerprint
+            } else if lower.contains("bluetooth") {
+                DeviceKind::Bluetooth
+            } else if lower.contains("camera") || lower.contains("webcam") {
+                DeviceKind::Webcam
+            } else if lower.contains("wireless")
+                || lower.contains("wlan")
+                || lower.contains("802.11")
+            {
+                DeviceKind::Wifi
+            } else if lower.contains("root hub") {
+                return None;
+            } else {
+                DeviceKind::Other
+            };
+            Some(Device {
+                kind,
+                bus: "usb".to_string(),
+                vendor: description.to_string(),
+                vendor_id: vendor_id.to_string(),
+                product: description.to_string(),
+                product_id: product_id.to_string(),
+            })
+        })
+        .collect()
+}
+
+pub fn cpu_vendor() -> Option<String> {
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+    cpuinfo
+        .lines()
+        .find(|l| l.starts_with("vendor_id"))
+        .and_then(|l| l.split(':').nth(1))
+        .map(|v| v.trim().to_string())
+}
+
+pub fn is_laptop() -> bool {
+    fs::read_dir("/sys/class/power_supply")
+        .map(|entries| {
+            entries
+                .flatten()
+                .any(|e| e.file_name().to_string_lossy().starts_with("BAT"))
+        })
+        .unwrap_or(false)
+}
+
+fn recommend_for(device: &Device) -> Option<Recommendation> {
+    let (id, reason, options) = match (device.kind, device.vendor_id.as_str()) {
+        (DeviceKind::Gpu, "10de") => (
+            "gpu-nvidia",
+            "NVIDIA GPU detected: the proprietary driver gives working acceleration and Wayland support",
+            vec![
+                NixOption::new("hardware.graphics.enable", nixgen::bool(true)),
+                NixOption::new("services.xserver.videoDrivers", nixgen::string_list(&["nvidia"])),
+                NixOption::new("hardware.nvidia.modesetting.enable", nixgen::bool(true)),
+                NixOption::new("hardware.nvidia.open", nixgen::bool(false))
+                    .with_comment("Switch to true on Turing (RTX 20xx) or newer cards"),
+            ],
+        ),
+        (DeviceKind::Gpu, "1002") => (
+            "gpu-amd",
+            "AMD GPU detected: loading amdgpu early avoids a low-resolution console during boot",
+            vec![
+                NixOption::new("hardware.graphics.enable", nixgen::bool(true)),
+                NixOption::new("boot.initrd.kernelModules", nixgen::string_list(&["amdgpu"])),
+            ],
+        ),
+        (DeviceKind::Gpu, "8086") => (
+            "gpu-intel",
+            "Intel graphics detected: the media driver enables hardware video decoding",
+            vec![
+                NixOption::new("hardware.graphics.enable", nixgen::bool(true)),
+                NixOption::new(
+                    "hardware.graphics.extraPackages",
+                    "with pkgs; [ intel-media-driver ]",
+                ),
+            ],
+        ),
+        (DeviceKind::Wifi, "14e4") => (
+            "wifi-broadcom",
+            "Broadcom WiFi detected: it needs the out-of-tree broadcom_sta driver",
+            vec![
+                NixOption::new("boot.kernelModules", nixgen::string_list(&["wl"])),
+                NixOption::new(
+                    "boot.extraModulePackages",
+                    "[ config.boot.kernelPackages.broadcom_sta ]",
+                ),
+                NixOption::new("nixpkgs.config.allowUnfree", nixgen::bool(true))
+                    .with_comment("broadcom_sta is unfree"),
+            ],
+        ),
+        (DeviceKind::Wifi, _) => (
+            "wifi-firmware",
+            "WiFi adapter detected: most chipsets need redistributable firmware to come up",
+            vec![
+                NixOption::new("hardware.enableRedistributableFirmware", nixgen::bool(true)),
+                NixOption::new("networking.networkmanager.enable", nixgen::bool(true)),
+            ],
+        ),
+        (DeviceKind::Bluetooth, _) => (
+            "bluetooth",
+            "Bluetooth adapter detected",
+            vec![
+                NixOption::new("hardware.bluetooth.enable", nixgen::bool(true)),
+                NixOption::new("hardware.bluetooth.powerOnBoot", nixgen::bool(true)),
+            ],
+        ),
+        (DeviceKind::Fingerprint, _) => (
+            "fingerprint",
+            "Fingerprint reader detected: fprintd lets you unlock and sudo with a fingerprint",
+            vec![NixOption::new("services.fprintd.enable", nixgen::bool(true))],
+        ),
+        (DeviceKind::Audio, _) => (
+            "audio",
+            "Audio controller detected: PipeWire covers PulseAudio and JACK applications",
+            vec![
+                NixOption::new("services.pipewire.enable", nixgen::bool(true)),
+                NixOption::new("services.pipewire.pulse.enable", nixgen::bool(true)),
+                NixOption::new("security.rtkit.enable", nixgen::bool(true)),
+            ],
+        ),
+        _ => return None,
+    };
+    Some(Recommendation {
+        id: id.to_string(),
+        device: Some(device.clone()),
+        reason: reason.to_string(),
+        options,
+    })
+}
+
+fn cpu_recommendation(vendor: &str) -> Option<Recommendation> {
+    let (id, option) = match vendor {
+        "GenuineIntel" => ("cpu-intel", "hardware.cpu.intel.updateMicrocode"),
+        "AuthenticAMD" => ("cpu-amd", "hardware.cpu.amd.updateMicrocode"),
+        _ => return None,
+    };
+    Some(Recommendation {
+        id: id.to_string(),
+        device: None,
+        reason: format!(
+            "{} CPU: microcode updates fix hardware bugs and security issues",
+            vendor
+        ),
+        options: vec![NixOption::new(
+            option,
+            "lib.mkDefault config.hardware.enableRedistributableFirmware",
+        )],
+    })
+}
+
+pub fn scan() -> HardwareReport {
+    let devices: Vec<Device> = scan_pci().into_iter().chain(scan_usb()).collect();
+    let cpu_vendor = cpu_vendor();
+
+    let mut recommendations: Vec<Recommendation> = Vec::new();
+    for rec in cpu_vendor
+        .as_deref()
+        .and_then(cpu_recommendation)
+        .into_iter()
+        .chain(devices.iter().filter_map(recommend_for))
+    {
+        if !recommendations.iter().any(|r| r.id == rec.id) {
+            recommendations.push(rec);
+        }
+    }
+
+    let generated_config = if Path::new("/run/current-system").exists() {
+        system::run(
+            "nixos-generate-config",
+            &["--show-hardware-config", "--no-filesystems"],
+        )
+        .ok()
+    } else {
+        None
+    };
+
+    let module_preview = build_module(&recommendations, None).render();
+    let of_kind = |kinds: &[DeviceKind]| -> Vec<Device> {
+        devices
+            .iter()
+            .filter(|d| kinds.contains(&d.kind))
+            .cloned()
+            .collect()
+    };
+
+    HardwareReport {
+        cpu_vendor,
+        is_laptop: is_laptop(),
+        gpus: of_kind(&[DeviceKind::Gpu]),
+        network: of_kind(&[DeviceKind::Wifi, DeviceKind::Ethernet]),
+        peripherals: of_kind(&[
+            DeviceKind::Bluetooth,
+            DeviceKind::Audio,
+            DeviceKind::Fingerprint,
+            DeviceKind::Webcam,
+        ]),
+        recommendations,
+        module_preview,
+        generated_config,
+    }
+}
+
+// Build the generated module from all recommendations, or only the selected ids
+fn build_module(recommendations: &[Recommendation], selected: Option<&[String]>) -> NixModule {
+    let mut module = NixModule::new("hardware", "detected hardware support", Target::Nixos);
+    for rec in recommendations {
+        if selected.is_some_and(|ids| !ids.contains(&rec.id)) {
+            continue;
+        }
+        for option in &rec.options {
+            if !module.options.iter().any(|o| o.path == option.path) {
+                module.set(option.clone());
+            }
+        }
+    }
+    module
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn scan_hardware(app: AppHandle) -> HardwareReport {
+    tasks::blocking_value(&app, "Scan hardware", |_| scan()).await
+}
+
+#[tauri::command]
+pub async fn apply_hardware_recommendations(
+    ids: Option<Vec<String>>,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Apply hardware recommendations", move |_| {
+        let report = scan();
+        let module = build_module(&report.recommendations, ids.as_deref());
+        crate::respond(module.write().map(|path| {
+            serde_json::json!({
+                "module_path": path,
+                "options": module.options,
+                "next_step": "Rebuild the system to activate the new hardware support",
+            })
+        }))
+    })
+    .await
+}
@@ -0,0 +1,421 @@
+// Persistent history of executed intents and natural-language recall over it
+//
+// Every intent that runs is appended to history.json in the data dir. Recall
+// questions like "what did I install last week?" or "that thing I removed
+// yesterday" are split into a time window, the kinds of action asked about and
+// any remaining keywords, which are matched fuzzily against the entries.
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tauri::AppHandle;
+
+use crate::nlp::{self, Intent};
+use crate::{clock, fuzzy, privacy, storage, system, tasks};
+
+pub const HISTORY_FILE: &str = "history.json";
+const MAX_ENTRIES: usize = 5000;
+const DAY: u64 = 24 * 60 * 60;
+// Keywords scoring below this against an entry don't count as a match
+const KEYWORD_THRESHOLD: f32 = 0.6;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub id: u64,
+    // Unix seconds
+    pub timestamp: u64,
+    pub intent: Intent,
+    pub description: String,
+    pub succeeded: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub label: String,
+    pub from: u64,
+    pub to: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecallMatch {
+    pub entry: HistoryEntry,
+    pub score: f32,
+    // Run the same intent again
+    pub rerun: Option<Intent>,
+    // The opposite action, for installs and removals
+    pub undo: Option<Intent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecallResult {
+    pub query: String,
+    pub window: Option<TimeWindow>,
+    pub kinds: Vec<String>,
+    pub keywords: Vec<String>,
+    pub matches: Vec<RecallMatch>,
+}
+
+static OFFSET: OnceLock<i64> = OnceLock::new();
+
+// Seconds east of UTC, so "today" and "yesterday" follow the local calendar.
+// Asked of `date` once per run; it's needed on every adapt call
+pub fn local_offset() -> i64 {
+    *OFFSET.get_or_init(|| {
+        let Ok(output) = system::run("date", &["+%z"]) else {
+            return 0;
+        };
+        let text = output.trim();
+        let sign = if text.starts_with('-') { -1 } else { 1 };
+        let digits = text.trim_start_matches(['+', '-']);
+        let hours: i64 = digits.get(..2).and_then(|h| h.parse().ok()).unwrap_or(0);
+        let minutes: i64 = digits.get(2..4).and_then(|m| m.parse().ok()).unwrap_or(0);
+        sign * (hours * 3600 + minutes * 60)
+    })
+}
+
+// Words people use when asking about past actions of each kind
+const KIND_WORDS: &[(&str, &str)] = &[
+    ("install", "install"),
+    ("installed", "install"),
+    ("added", "install"),
+    ("remove", "remove"),
+    ("removed", "remove"),
+    ("uninstalled", "remove"),
+    ("deleted", "remove"),
+    ("search", "search"),
+    ("searched", "search"),
+    ("looked", "search"),
+    ("update", "update"),
+    ("updated", "update"),
+    ("upgraded", "update"),
+    ("rollback", "rollback"),
+    ("rolled", "rollback"),
+    ("reverted", "rollback"),
+    ("cleaned", "garbage_collect"),
+    ("configured", "configure"),
+    ("enabled", "configure"),
+    ("disabled", "configure"),
+    ("explained", "explain"),
+];
+
+// Words that carry no meaning for matching entries
+const STOPWORDS: &[&str] = &[
+    "what",
+    "which",
+    "when",
+    "did",
+    "do",
+    "i",
+    "me",
+    "my",
+    "we",
+    "the",
+    "a",
+    "an",
+    "that",
+    "this",
+    "thing",
+    "things",
+    "stuff",
+    "one",
+    "package",
+    "packages",
+    "app",
+    "program",
+    "was",
+    "were",
+    "is",
+    "it",
+    "show",
+    "list",
+    "find",
+    "all",
+    "everything",
+    "have",
+    "has",
+    "had",
+    "ago",
+    "days",
+    "day",
+    "weeks",
+    "week",
+    "month",
+    "months",
+    "last",
+    "past",
+    "today",
+    "yesterday",
+    "recently",
+    "recent",
+    "earlier",
+    "before",
+    "on",
+    "in",
+    "for",
+    "up",
+    "back",
+    "out",
+    "at",
+    "to",
+];
+
+fn number_word(word: &str) -> Option<u64> {
+    match word {
+        "a" | "an" | "one" => Some(1),
+        "two" | "couple" => Some(2),
+        "three" | "few" => Some(3),
+        "four" => Some(4),
+        "five" => Some(5),
+        other => other.parse().ok(),
+    }
+}
+
+fn parse_window(text: &str, now: u64, offset: i64) -> Option<TimeWindow> {
+    let local_now = (now as i64 + offset).max(0) as u64;
+    let midnight = ((local_now / DAY) * DAY) as i64 - offset;
+    let midnight = midnight.max(0) as u64;
+    let window = |label: &str, from: u64, to: u64| {
+        Some(TimeWindow {
+            label: label.to_string(),
+            from,
+            to,
+        })
+    };
+    let words: Vec<&str> = text.split_whitespace().collect();
+    // "3 days ago", "a couple of weeks ago"
+    if let Some(pos) = words.iter().position(|w| *w == "ago") {
+        let unit = words.get(pos.checked_sub(1)?)?;
+        let count = words[..pos - 1]
+            .iter()
+            .rev()
+            .find_map(|w| number_word(w))
+            .unwrap_or(1);
+        let span = match unit.trim_end_matches('s') {
+            "day" => DAY,
+            "week" => 7 * DAY,
+            "month" => 30 * DAY,
+            "hour" => 3600,
+            _ => return None,
+        };
+        let from = now.saturating_sub(count * span);
+        // Anything around that point in time, give or take one unit
+        return window(
+            &format!("about {} {} ago", count, unit),
+            from.saturating_sub(span),
+            from + span,
+        );
+    }
+    if text.contains("today") {
+        return window("today", midnight, now);
+    }
+    if text.contains("yesterday") {
+        return window("yesterday", midnight.saturating_sub(DAY), midnight);
+    }
+    if text.contains("this week") {
+        return window("this week", now.saturating_sub(7 * DAY), now);
+    }
+    if text.contains("last week") || text.contains("past week") {
+        return window("last week", now.saturating_sub(14 * DAY), now);
+    }
+    if text.contains("this month") || text.contains("last month") || text.contains("past month") {
+        return window("last month", now.saturating_sub(30 * DAY), now);
+    }
+    if text.contains("recent") || text.contains("earlier") {
+        return window("recently", now.saturating_sub(3 * DAY), now);
+    }
+    None
+}
+
+fn entry_terms(entry: &HistoryEntry) -> Vec<String> {
+    let mut terms: Vec<String> = match &entry.intent {
+        Intent::Install { packages } | Intent::Remove { packages } => packages.clone(),
+        Intent::Search { query } => vec![query.clone()],
+        Intent::Explain { topic } => vec![topic.clone()],
+        Intent::Configure { setting, .. } => vec![setting.clone()],
+        Intent::SetDefaultApp { app, role } => vec![app.clone(), role.clone()],
+        Intent::Plugin { plugin, args, .. } => std::iter::once(plugin.clone())
+            .chain(args.iter().cloned())
+            .collect(),
+        _ => Vec::new(),
+    };
+    terms.extend(
+        entry
+            .description
+            .split(|c: char| !c.is_alphanumeric() && c != '-')
+            .filter(|w| w.len() > 2)
+            .map(str::to_lowercase),
+    );
+    terms
+}
+
+pub fn undo_for(intent: &Intent) -> Option<Intent> {
+    match intent {
+        Intent::Install { packages } => Some(Intent::Remove {
+            packages: packages.clone(),
+        }),
+        Intent::Remove { packages } => Some(Intent::Install {
+            packages: packages.clone(),
+        }),
+        _ => None,
+    }
+}
+
+// Entries past the privacy retention are never handed out, even before the
+// next write prunes them
+pub fn load() -> Vec<HistoryEntry> {
+    let mut entries: Vec<HistoryEntry> = storage::load_data(HISTORY_FILE).unwrap_or_default();
+    if let Some(cutoff) = privacy::retention_cutoff() {
+        entries.retain(|e| e.timestamp >= cutoff);
+    }
+    entries
+}
+
+pub fn record(intent: &Intent, response: &serde_json::Value) -> anyhow::Result<()> {
+    if matches!(intent, Intent::Unknown) {
+        return Ok(());
+    }
+    let mut entries = load();
+    let id = entries.last().map(|e| e.id + 1).unwrap_or(1);
+    entries.push(HistoryEntry {
+        id,
+        timestamp: clock::now(),
+        intent: intent.clone(),
+        description: intent.describe(),
+        succeeded: response
+            .get("success")
+            .and_then(|s| s.as_bool())
+            .unwrap_or(false),
+        error: response
+            .get("error")
+            .and_then(|e| e.as_str())
+            .map(String::from),
+    });
+    if entries.len() > MAX_ENTRIES {
+        entries.drain(..entries.len() - MAX_ENTRIES);
+    }
+    storage::save_data(HISTORY_FILE, &entries)?;
+    Ok(())
+}
+
+// Drop entries older than `cutoff` from disk
+pub fn prune(cutoff: Option<u64>) -> anyhow::Result<()> {
+    let stored: Vec<HistoryEntry> = storage::load_data(HISTORY_FILE).unwrap_or_default();
+    let kept: Vec<HistoryEntry> = stored
+        .iter()
+        .filter(|e| cutoff.is_none_or(|cutoff| e.timestamp >= cutoff))
+        .cloned()
+        .collect();
+    if kept.len() < stored.len() {
+        storage::save_data(HISTORY_FILE, &kept)?;
+    }
+    Ok(())
+}
+
+// Entries from a session export merged into these, in time order; one already
+// here (same time and description) isn't added twice. Ids are handed out again
+// so they keep increasing. Returns the merged history and how many were new.
+pub fn merge(imported: &[HistoryEntry]) -> (Vec<HistoryEntry>, usize) {
+    let mut entries = load();
+    let cutoff = privacy::retention_cutoff();
+    let mut added = 0;
+    for entry in imported {
+        let known = entries
+            .iter()
+            .any(|e| e.timestamp == entry.timestamp && e.description == entry.description);
+        if !known && cutoff.is_none_or(|cutoff| entry.timestamp >= cutoff) {
+            entries.push(entry.clone());
+            added += 1;
+        }
+    }
+    entries.sort_by_key(|e| e.timestamp);
+    if entries.len() > MAX_ENTRIES {
+        entries.drain(..entries.len() - MAX_ENTRIES);
+    }
+    for (i, entry) in entries.iter_mut().enumerate() {
+        entry.id = i as u64 + 1;
+    }
+    (entries, added)
+}
+
+pub fn replace(entries: &[HistoryEntry]) -> anyhow::Result<()> {
+    storage::save_data(HISTORY_FILE, &entries).map(|_| ())
+}
+
+// Forget every entry; recall and the wellbeing report start from nothing
+pub fn clear() -> anyhow::Result<()> {
+    storage::save_data(HISTORY_FILE, &Vec::<HistoryEntry>::new()).map(|_| ())
+}
+
+pub fn find(id: u64) -> anyhow::Result<HistoryEntry> {
+    load()
+        .into_iter()
+        .find(|e| e.id == id)
+        .ok_or_else(|| anyhow!("No history entry {}", id))
+}
+
+pub fn search(query: &str) -> RecallResult {
+    let text = nlp::normalize(query);
+    let window = parse_window(&text, clock::now(), local_offset());
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut kinds: Vec<String> = Vec::new();
+    for word in &words {
+        if let Some((_, kind)) = KIND_WORDS.iter().find(|(w, _)| w == word) {
+            if !kinds.iter().any(|k| k == kind) {
+                kinds.push(kind.to_string());
+            }
+        }
+    }
+    let keywords: Vec<String> = words
+        .iter()
+        .filter(|w| !STOPWORDS.contains(w) && number_word(w).is_none())
+        .filter(|w| !KIND_WORDS.iter().any(|(k, _)| k == *w))
+        .map(|w| w.to_string())
+        .collect();
+
+    let mut matches: Vec<RecallMatch> = load()
+        .into_iter()
+        .rev()
+        .filter(|e| {
+            window
+                .as_ref()
+                .is_none_or(|w| e.timestamp >= w.from && e.timestamp <= w.to)
+        })
+        .filter(|e| kinds.is_empty() || kinds.iter().any(|k| k == e.intent.kind()))
+        .filter_map(|entry| {
+            let score = if keywords.is_empty() {
+                1.0
+            } else {
+                let terms = entry_terms(&entry);
+                keywords
+                    .iter()
+                    .map(|k| terms.iter().map(|t| fuzzy::score(k, t)).fold(0.0, f32::max))
+                    .fold(0.0, f32::max)
+            };
+            (score >= KEYWORD_THRESHOLD).then(|| RecallMatch {
+                rerun: Some(entry.intent.clone()),
+                undo: undo_for(&entry.intent),
+                score,
+                entry,
+            })
+        })
+        .collect();
+    // Newest first within equal scores; the iterator above is already newest first
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+    matches.truncate(50);
+
+    RecallResult {
+        query: query.to_string(),
+        window,
+        kinds,
+        keywords,
+        matches,
+    }
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn recall(query: String, app: AppHandle) -> RecallResult {
+    tasks::blocking_value(&app, "Recall", move |_| search(&query)).await
+}
@@ -0,0 +1,505 @@
+// Optional Home Assistant integration over MQTT
+//
+// Publishes the machine's update status (pending flake updates, generation
+// age, reboot pending, failed units) as Home Assistant entities through MQTT
+// discovery, and offers the actions the user pre-approved here (garbage
+// collection, a system update) as buttons. A press runs through the same
+// confirmation policy as the app itself, but anyone on the broker can press,
+// so when the policy asks for confirmation it is asked on this desktop and
+// the action only runs once someone here accepts. Tokens and phrases never go
+// to the broker, and results aren't retained. Needs the `homeassistant` build
+// feature.
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tauri::AppHandle;
+use tokio::sync::Mutex;
+
+use crate::{boot, care, clock, managed, metrics, storage, tasks};
+
+const SETTINGS_FILE: &str = "homeassistant.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteAction {
+    GarbageCollect,
+    Update,
+}
+
+impl RemoteAction {
+    fn label(self) -> &'static str {
+        match self {
+            RemoteAction::GarbageCollect => "Collect garbage",
+            RemoteAction::Update => "Update system",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HomeAssistantSettings {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    // A file holding the broker password, so it isn't kept in this config
+    pub password_file: Option<String>,
+    pub discovery_prefix: String,
+    pub node_id: String,
+    // Only these can be triggered from Home Assistant
+    pub allowed_actions: Vec<RemoteAction>,
+    pub interval_secs: u64,
+}
+
+impl Default for HomeAssistantSettings {
+    fn default() -> Self {
+        HomeAssistantSettings {
+            enabled: false,
+            host: "homeassistant.local".to_string(),
+            port: 1883,
+            username: None,
+            password_file: None,
+            discovery_prefix: "homeassistant".to_string(),
+            node_id: "luminous_nix".to_string(),
+            allowed_actions: Vec::new(),
+            interval_secs: 300,
+        }
+    }
+}
+
+impl HomeAssistantSettings {
+    fn topic(&self, name: &str) -> String {
+        format!("luminous-nix/{}/{}", self.node_id, name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateStatus {
+    pub updates_pending: usize,
+    pub pending_inputs: Vec<String>,
+    pub generation: Option<u32>,
+    pub generation_age_days: Option<u64>,
+    pub reboot_pending: bool,
+    pub failed_units: Vec<String>,
+}
+
+struct Running {
+    stop: Arc<AtomicBool>,
+    #[cfg(feature = "homeassistant")]
+    client: rumqttc::Client,
+}
+
+static RUNNING: Mutex<Option<Running>> = Mutex::const_new(None);
+
+pub fn settings() -> HomeAssistantSettings {
+    storage::load(SETTINGS_FILE).unwrap_or_default()
+}
+
+pub fn update_status() -> UpdateStatus {
+    let generations = boot::list_generations();
+    let current = generations.iter().find(|g| g.current);
+    let pending_inputs = metrics::pending_updates();
+    UpdateStatus {
+        updates_pending: pending_inputs.len(),
+        pending_inputs,
+        generation: current.map(|g| g.number),
+        generation_age_days: current.map(|g| clock::now().saturating_sub(g.created) / 86_400),
+        reboot_pending: current.is_some_and(|g| !g.booted),
+        failed_units: care::failed_units(),
+    }
+}
+
+// Retained discovery configs for every entity
+fn discovery(settings: &HomeAssistantSettings) -> Vec<(String, serde_json::Value)> {
+    let hostname = fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|h| h.trim().to_string())
+        .unwrap_or_else(|_| "nixos".to_string());
+    let device = serde_json::json!({
+        "identifiers": [settings.node_id],
+        "name": format!("Luminous Nix ({})", hostname),
+        "manufacturer": "Luminous Dynamics",
+    });
+    let availability = settings.topic("availability");
+    let state = settings.topic("state");
+    let entity = |component: &str, object: &str, config: serde_json::Value| {
+        let mut config = config;
+        config["unique_id"] = serde_json::json!(format!("{}_{}", settings.node_id, object));
+        config["availability_topic"] = serde_json::json!(availability);
+        config["device"] = device.clone();
+        let topic = format!(
+            "{}/{}/{}/{}/config",
+            settings.discovery_prefix, component, settings.node_id, object
+        );
+        (topic, config)
+    };
+    let mut entities = vec![
+        entity(
+            "binary_sensor",
+            "updates",
+            serde_json::json!({
+                "name": "Updates available",
+                "device_class": "update",
+                "state_topic": state,
+                "value_template": "{{ 'ON' if value_json.updates_pending > 0 else 'OFF' }}",
+                "json_attributes_topic": state,
+            }),
+        ),
+        entity(
+            "binary_sensor",
+            "reboot_pending",
+            serde_json::json!({
+                "name": "Reboot pending",
+                "state_topic": state,
+                "value_template": "{{ 'ON' if value_json.reboot_pending else 'OFF' }}",
+            }),
+        ),
+        entity(
+            "sensor",
+            "generation_age",
+            serde_json::json!({
+                "name": "Generation age",
+                "unit_of_measurement": "d",
+                "state_topic": state,
+                "value_template": "{{ value_json.generation_age_days }}",
+            }),
+        ),
+        entity(
+            "sensor",
+            "failed_units",
+            serde_json::json!({
+                "name": "Failed units",
+                "state_topic": state,
+                "value_template": "{{ value_json.failed_units | count }}",
+            }),
+        ),
+        entity(
+            "sensor",
+            "last_action",
+            serde_json::json!({
+                "name": "Last action",
+                "state_topic": settings.topic("result"),
+                "value_template": "{{ value_json.status }}",
+                "json_attributes_topic": settings.topic("result"),
+            }),
+        ),
+    ];
+    for action in &settings.allowed_actions {
+        let id = serde_json::json!(action);
+        let object = id.as_str().unwrap_or_default().to_string();
+        entities.push(entity(
+            "button",
+            &object,
+            serde_json::json!({
+                "name": action.label(),
+                "command_topic": settings.topic("action"),
+                "payload_press": serde_json::json!({"action": action}).to_string(),
+            }),
+        ));
+    }
+    entities
+}
+
+// The MQTT side, only built with the `homeassistant` feature
+#[cfg(feature = "homeassistant")]
+mod mqtt {
+    use rumqttc::{Client, Event, LastWill, MqttOptions, Packet, QoS};
+    use serde::Deserialize;
+    use std::fs;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tauri::{AppHandle, Manager};
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    use super::{discovery, update_status, HomeAssistantSettings, RemoteAction, Running};
+    use crate::clock;
+    use crate::nlp::Intent;
+    use crate::AppState;
+
+    const MIN_INTERVAL_SECS: u64 = 60;
+
+    // What a press sends; confirmations are never taken from the broker
+    #[derive(Debug, Clone, Deserialize)]
+    struct ActionRequest {
+        action: RemoteAction,
+    }
+
+    fn intent(action: RemoteAction) -> Intent {
+        match action {
+            RemoteAction::GarbageCollect => Intent::GarbageCollect,
+            RemoteAction::Update => Intent::Update,
+        }
+    }
+
+    fn password(settings: &HomeAssistantSettings) -> anyhow::Result<Option<String>> {
+        match &settings.password_file {
+            Some(path) => Ok(Some(fs::read_to_string(path)?.trim().to_string())),
+            None => Ok(None),
+        }
+    }
+
+    // Ask whoever is at this machine. A phrase the policy requires becomes the
+    // button, so accepting still means choosing those words
+    fn confirm_locally(app: &AppHandle, confirmation: &serde_json::Value) -> bool {
+        let summary: Vec<&str> = confirmation["summary"]
+            .as_array()
+            .map(|items| items.iter().filter_map(|i| i.as_str()).collect())
+            .unwrap_or_default();
+        let message = format!(
+            "Home Assistant asked to run: {}.\n\n{}",
+            summary.join(", "),
+            confirmation["risk"]["spoken"].as_str().unwrap_or_default()
+        );
+        let accept = confirmation["phrase"].as_str().unwrap_or("Run it");
+        app.dialog()
+            .message(message)
+            .title("Confirm a request from Home Assistant")
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancelCustom(
+                accept.to_string(),
+                "Not now".to_string(),
+            ))
+            .blocking_show()
+    }
+
+    // Run a press from Home Assistant under the local confirmation policy
+    fn handle_action(
+        app: &AppHandle,
+        settings: &HomeAssistantSettings,
+        payload: &[u8],
+    ) -> serde_json::Value {
+        let request: ActionRequest = match serde_json::from_slice(payload) {
+            Ok(request) => request,
+            Err(e) => {
+                return serde_json::json!({
+                    "status": "refused",
+                    "error": format!("Unreadable request: {}", e),
+                })
+            }
+        };
+        if !settings.allowed_actions.contains(&request.action) {
+            return serde_json::json!({
+                "action": request.action,
+                "status": "refused",
+                "error": "This action hasn't been approved for Home Assistant",
+            });
+        }
+        let state = app.state::<AppState>();
+        let mut response =
+            crate::run_intent(intent(request.action), &serde_json::json!({}), &state);
+        if response.get("needs_confirmation").is_some() {
+            let confirmation = &response["confirmation"];
+            if !confirm_locally(app, confirmation) {
+                return serde_json::json!({
+                    "action": request.action,
+                    "status": "declined",
+                    "at": clock::now(),
+                });
+            }
+            // The token stays in this process; the broker only sees the outcome
+            let options = serde_json::json!({
+                "confirmation_token": confirmation["token"],
+                "confirmation_phrase": confirmation["phrase"],
+            });
+            response = crate::run_intent(intent(request.action), &options, &state);
+        }
+        let status = if response.get("success").and_then(|s| s.as_bool()) == Some(true) {
+            "done"
+        } else {
+            "failed"
+        };
+        serde_json::json!({
+            "action": request.action,
+            "status": status,
+            "message": response.get("message"),
+            "error": response.get("error"),
+            "at": clock::now(),
+        })
+    }
+
+    pub fn connect(app: &AppHandle, settings: HomeAssistantSettings) -> anyhow::Result<Running> {
+        let mut options = MqttOptions::new(&settings.node_id, &settings.host, settings.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some(username) = &settings.username {
+            options.set_credentials(username, password(&settings)?.unwrap_or_default());
+        }
+        options.set_last_will(LastWill::new(
+            settings.topic("availability"),
+            "offline",
+            QoS::AtLeastOnce,
+            true,
+        ));
+        let (client, mut connection) = Client::new(options, 16);
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let publish = |client: &Client, topic: String, payload: String, retain: bool| {
+            let _ = client.publish(topic, QoS::AtLeastOnce, retain, payload);
+        };
+
+        // Events: (re)announce on every connect, run presses in their own thread
+        {
+            let (client, stop, app, settings) =
+                (client.clone(), stop.clone(), app.clone(), settings.clone());
+            std::thread::spawn(move || {
+                for event in connection.iter() {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    match event {
+                        Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                            for (topic, config) in discovery(&settings) {
+                                publish(&client, topic, config.to_string(), true);
+                            }
+                            publish(
+                                &client,
+                                settings.topic("availability"),
+                                "online".to_string(),
+                                true,
+                            );
+                            publish(
+                                &client,
+                                settings.topic("state"),
+                                serde_json::json!(update_status()).to_string(),
+                                true,
+                            );
+                            // An empty retained message drops any result retained before
+                            publish(&client, settings.topic("result"), String::new(), true);
+                            let _ = client.subscribe(settings.topic("action"), QoS::AtLeastOnce);
+                        }
+                        Ok(Event::Incoming(Packet::Publish(message)))
+                            if message.topic == settings.topic("action") =>
+                        {
+                            let (client, app, settings) =
+                                (client.clone(), app.clone(), settings.clone());
+                            std::thread::spawn(move || {
+                                let result = handle_action(&app, &settings, &message.payload);
+                                publish(
+                                    &client,
+                                    settings.topic("result"),
+                                    result.to_string(),
+                                    false,
+                                );
+                                publish(
+                                    &client,
+                                    settings.topic("state"),
+                                    serde_json::json!(update_status()).to_string(),
+                                    true,
+                                );
+                            });
+                        }
+                        Ok(_) => {}
+                        // The next iteration reconnects
+                        Err(e) => {
+                            eprintln!("Home Assistant connection: {}", e);
+                            std::thread::sleep(Duration::from_secs(10));
+                        }
+                    }
+                }
+            });
+        }
+
+        // Status on the configured interval
+        {
+            let (client, stop, settings) = (client.clone(), stop.clone(), settings.clone());
+            std::thread::spawn(move || {
+                let interval = Duration::from_secs(settings.interval_secs.max(MIN_INTERVAL_SECS));
+                while !stop.load(Ordering::Relaxed) {
+                    std::thread::sleep(interval);
+                    publish(
+                        &client,
+                        settings.topic("state"),
+                        serde_json::json!(update_status()).to_string(),
+                        true,
+                    );
+                }
+            });
+        }
+
+        Ok(Running { stop, client })
+    }
+}
+
+#[cfg(not(feature = "homeassistant"))]
+mod mqtt {
+    use anyhow::bail;
+    use tauri::AppHandle;
+
+    use super::{HomeAssistantSettings, Running};
+
+    pub fn connect(_app: &AppHandle, _settings: HomeAssistantSettings) -> anyhow::Result<Running> {
+        bail!("This build does not include the Home Assistant integration (enable the `homeassistant` feature)")
+    }
+}
+
+fn stop() {
+    if let Some(running) = RUNNING.blocking_lock().take() {
+        running.stop.store(true, Ordering::Relaxed);
+        #[cfg(feature = "homeassistant")]
+        {
+            use rumqttc::QoS;
+            let topic = settings().topic("availability");
+            let _ = running
+                .client
+                .publish(topic, QoS::AtLeastOnce, true, "offline");
+            let _ = running.client.disconnect();
+        }
+    }
+}
+
+// (Re)connect with the saved settings, or stay disconnected when disabled
+pub fn start(app: &AppHandle) -> anyhow::Result<()> {
+    stop();
+    let settings = settings();
+    if !settings.enabled {
+        return Ok(());
+    }
+    if settings.node_id.is_empty() || settings.node_id.contains(['/', '#', '+']) {
+        bail!("The node id can't be empty or contain '/', '#' or '+'");
+    }
+    let running = mqtt::connect(app, settings)?;
+    *RUNNING.blocking_lock() = Some(running);
+    Ok(())
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_homeassistant_settings(app: AppHandle) -> HomeAssistantSettings {
+    tasks::blocking_value(&app, "Get Home Assistant settings", |_| settings()).await
+}
+
+#[tauri::command]
+pub async fn set_homeassistant_settings(
+    settings: HomeAssistantSettings,
+    app: AppHandle,
+) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Set Home Assistant settings", move |_| {
+        if let Some(refusal) =
+            managed::guard("homeassistant", "Change the Home Assistant connection")
+        {
+            return refusal;
+        }
+        let result = storage::save(SETTINGS_FILE, &settings).and_then(|_| start(&handle));
+        crate::respond(result.map(|()| settings))
+    })
+    .await
+}
+
+// What Home Assistant is shown, for previewing before enabling
+#[tauri::command]
+pub async fn get_homeassistant_status(app: AppHandle) -> UpdateStatus {
+    tasks::blocking_value(&app, "Get Home Assistant status", |_| update_status()).await
+}
+
+// The entities that would be announced, as (discovery topic, config)
+#[tauri::command]
+pub async fn get_homeassistant_entities(app: AppHandle) -> Vec<(String, serde_json::Value)> {
+    tasks::blocking_value(&app, "Get Home Assistant entities", |_| {
+        discovery(&settings())
+    })
+    .await
+}
@@ -0,0 +1,290 @@
+// Localization for parsing and responses
+//
+// Queries in German or Spanish are mapped onto the English keywords the rule
+// based parser knows, through per-language keyword tables. Responses come
+// from Fluent catalogs in locales/<lang>/main.ftl, falling back to English for
+// any message a catalog doesn't have. The catalogs are parsed once, and the
+// active language is read from language.json once and kept until it changes.
+
+use anyhow::bail;
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+use tauri::AppHandle;
+use unic_langid::LanguageIdentifier;
+
+use crate::{nlp, storage, tasks};
+
+pub const SETTINGS_FILE: &str = "language.json";
+const DEFAULT_LANGUAGE: &str = "en";
+
+// (code, native name, Fluent catalog)
+const LANGUAGES: &[(&str, &str, &str)] = &[
+    ("en", "English", include_str!("../locales/en/main.ftl")),
+    ("de", "Deutsch", include_str!("../locales/de/main.ftl")),
+    ("es", "Español", include_str!("../locales/es/main.ftl")),
+];
+
+// Local phrase -> English phrase the parser understands. Checked in order, so
+// longer phrases come before the words they contain.
+const DE_KEYWORDS: &[(&str, &str)] = &[
+    ("zeige installierte pakete", "list installed"),
+    ("was ist installiert", "what's installed"),
+    ("alte generationen löschen", "delete old generations"),
+    ("speicher freigeben", "free up space"),
+    ("räume auf", "clean up"),
+    ("aufräumen", "clean up"),
+    ("mach rückgängig", "undo"),
+    ("zurücksetzen", "roll back"),
+    ("setze zurück", "roll back"),
+    ("deinstalliere", "uninstall"),
+    ("deinstallieren", "uninstall"),
+    ("entferne", "remove"),
+    ("entfernen", "remove"),
+    ("lösche", "delete"),
+    ("suche nach", "search for"),
+    ("suche", "search"),
+    ("finde", "find"),
+    ("gibt es", "is there"),
+    ("aktualisiere", "update"),
+    ("aktualisieren", "update"),
+    ("erzähl mir von", "tell me about"),
+    ("erkläre", "explain"),
+    ("was ist", "what is"),
+    ("was sind", "what are"),
+    ("aktiviere", "enable"),
+    ("schalte ein", "turn on"),
+    ("deaktiviere", "disable"),
+    ("schalte aus", "turn off"),
+    ("konfiguriere", "configure"),
+    ("installiere", "install"),
+    ("installieren", "install"),
+    ("ich brauche", "i need"),
+    ("für alle benutzer", "for all users"),
+    ("für alle", "for everyone"),
+    ("systemweit", "system-wide"),
+    ("nur für mich", "just for me"),
+    ("für mich", "for me"),
+    ("kannst du", "can you"),
+    ("könntest du", "could you"),
+    ("bitte", "please"),
+    ("und dann", "and then"),
+    ("danach", "after that"),
+    ("dann", "then"),
+    ("und", "and"),
+];
+
+const ES_KEYWORDS: &[(&str, &str)] = &[
+    ("qué está instalado", "what's installed"),
+    ("muestra los paquetes instalados", "list installed"),
+    ("borra las generaciones antiguas", "delete old generations"),
+    ("libera espacio", "free up space"),
+    ("limpia", "clean up"),
+    ("deshaz", "undo"),
+    ("deshacer", "undo"),
+    ("revierte", "revert"),
+    ("vuelve atrás", "roll back"),
+    ("desinstala", "uninstall"),
+    ("desinstalar", "uninstall"),
+    ("elimina", "remove"),
+    ("eliminar", "remove"),
+    ("quita", "remove"),
+    ("borra", "delete"),
+    ("busca", "search for"),
+    ("buscar", "search for"),
+    ("encuentra", "find"),
+    ("hay algún", "is there"),
+    ("actualiza", "update"),
+    ("actualizar", "update"),
+    ("háblame de", "tell me about"),
+    ("explica", "explain"),
+    ("qué es", "what is"),
+    ("que es", "what is"),
+    ("qué son", "what are"),
+    ("activa", "enable"),
+    ("habilita", "enable"),
+    ("desactiva", "disable"),
+    ("deshabilita", "disable"),
+    ("configura", "configure"),
+    ("instala", "install"),
+    ("instalar", "install"),
+    ("necesito", "i need"),
+    ("para todos los usuarios", "for all users"),
+    ("para todos", "for everyone"),
+    ("solo para mí", "just for me"),
+    ("solo para mi", "just for me"),
+    ("para mí", "for me"),
+    ("puedes", "can you"),
+    ("podrías", "could you"),
+    ("por favor", "please"),
+    ("y luego", "and then"),
+    ("después", "after that"),
+    ("luego", "then"),
+    ("y", "and"),
+];
+
+// Messages are looked up from async code as well, so these aren't tokio locks
+static ACTIVE: RwLock<Option<String>> = RwLock::new(None);
+static BUNDLES: OnceLock<HashMap<&'static str, FluentBundle<FluentResource>>> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LanguageSetting {
+    language: String,
+}
+
+impl Default for LanguageSetting {
+    fn default() -> Self {
+        LanguageSetting {
+            language: DEFAULT_LANGUAGE.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanguageInfo {
+    pub code: String,
+    pub name: String,
+    pub active: bool,
+}
+
+pub fn current() -> String {
+    // A replaying scenario has a language.json of its own, read every time
+    if storage::root().is_none() {
+        if let Some(code) = ACTIVE.read().ok().and_then(|active| active.clone()) {
+            return code;
+        }
+    }
+    let code = storage::load::<LanguageSetting>(SETTINGS_FILE)
+        .map(|s| s.language)
+        .ok()
+        .filter(|code| LANGUAGES.iter().any(|(c, _, _)| c == code))
+        .unwrap_or_else(|| DEFAULT_LANGUAGE.to_string());
+    remember(&code);
+    code
+}
+
+// Cache the user's language; a scenario's never replaces it
+fn remember(code: &str) {
+    if storage::root().is_none() {
+        *ACTIVE.write().unwrap_or_else(|e| e.into_inner()) = Some(code.to_string());
+    }
+}
+
+pub fn set(code: &str) -> anyhow::Result<()> {
+    let code = code.trim().to_lowercase();
+    // "de-AT" and "es_MX" use the base catalog
+    let base = code.split(['-', '_']).next().unwrap_or("");
+    if !LANGUAGES.iter().any(|(c, _, _)| *c == base) {
+        let supported: Vec<&str> = LANGUAGES.iter().map(|(c, _, _)| *c).collect();
+        bail!(
+            "{}",
+            message(
+                "error-unsupported-language",
+                &[("language", &code), ("supported", &supported.join(", "))],
+            )
+        );
+    }
+    storage::save(
+        SETTINGS_FILE,
+        &LanguageSetting {
+            language: base.to_string(),
+        },
+    )?;
+    remember(base);
+    Ok(())
+}
+
+pub fn languages() -> Vec<LanguageInfo> {
+    let active = current();
+    LANGUAGES
+        .iter()
+        .map(|(code, name, _)| LanguageInfo {
+            code: code.to_string(),
+            name: name.to_string(),
+            active: *code == active,
+        })
+        .collect()
+}
+
+fn keywords(code: &str) -> &'static [(&'static str, &'static str)] {
+    match code {
+        "de" => DE_KEYWORDS,
+        "es" => ES_KEYWORDS,
+        _ => &[],
+    }
+}
+
+// Replace the active language's keywords with their English equivalents;
+// package names and anything else unknown pass through untouched
+pub fn to_english(text: &str) -> String {
+    let table = keywords(&current());
+    let mut text = text.to_string();
+    for (local, english) in table {
+        let mut start = 0;
+        while let Some(at) = nlp::find_phrase(&text[start..], local).map(|at| at + start) {
+            text.replace_range(at..at + local.len(), english);
+            start = at + english.len();
+        }
+    }
+    text
+}
+
+fn parse(code: &str, source: &str) -> Option<FluentBundle<FluentResource>> {
+    let langid: LanguageIdentifier = code.parse().ok()?;
+    let resource = FluentResource::try_new(source.to_string()).ok()?;
+    let mut bundle = FluentBundle::new_concurrent(vec![langid]);
+    // Isolation marks end up verbatim in notifications and TTS
+    bundle.set_use_isolating(false);
+    bundle.add_resource(resource).ok()?;
+    Some(bundle)
+}
+
+fn bundle(code: &str) -> Option<&'static FluentBundle<FluentResource>> {
+    BUNDLES
+        .get_or_init(|| {
+            LANGUAGES
+                .iter()
+                .filter_map(|(code, _, source)| Some((*code, parse(code, source)?)))
+                .collect()
+        })
+        .get(code)
+}
+
+fn format_message(code: &str, id: &str, args: &FluentArgs) -> Option<String> {
+    let bundle = bundle(code)?;
+    let pattern = bundle.get_message(id)?.value()?;
+    let mut errors = Vec::new();
+    Some(
+        bundle
+            .format_pattern(pattern, Some(args), &mut errors)
+            .into_owned(),
+    )
+}
+
+// Look up a message in the active language, falling back to English and then the id
+pub fn message(id: &str, args: &[(&str, &str)]) -> String {
+    let mut fluent_args = FluentArgs::new();
+    for (name, value) in args {
+        fluent_args.set(*name, FluentValue::from(value.to_string()));
+    }
+    format_message(&current(), id, &fluent_args)
+        .or_else(|| format_message(DEFAULT_LANGUAGE, id, &fluent_args))
+        .unwrap_or_else(|| id.to_string())
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_languages(app: AppHandle) -> Vec<LanguageInfo> {
+    tasks::blocking_value(&app, "Get languages", |_| languages()).await
+}
+
+#[tauri::command]
+pub async fn set_language(language: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set language", move |_| {
+        crate::respond(set(&language).map(|()| languages()))
+    })
+    .await
+}
@@ -0,0 +1,198 @@
+// Incremental updates for the offline package index
+//
+// Instead of re-listing every package when nixpkgs moves, ask GitHub which
+// files changed between the indexed revision and the current one and work out
+// the added and removed attributes from that: pkgs/by-name directories map
+// straight to names, and the top-level package sets are read from their
+// patches. When a change can't be expressed that way (too many files, a patch
+// GitHub won't show, an unknown top-level file) the caller rebuilds in full.
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::system;
+
+// GitHub's compare endpoint lists at most this many files
+const COMPARE_FILE_LIMIT: usize = 300;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Revision {
+    pub owner: String,
+    pub repo: String,
+    pub rev: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Delta {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed_files: usize,
+    // Size of the compare response, for the data-usage report
+    pub bytes: usize,
+}
+
+// The nixpkgs revision `nix search nixpkgs` would use right now
+pub fn current_revision() -> Option<Revision> {
+    let output = system::run("nix", &["flake", "metadata", "nixpkgs", "--json"]).ok()?;
+    let metadata: serde_json::Value = serde_json::from_str(&output).ok()?;
+    let locked = metadata.get("locked")?;
+    let field = |name: &str| locked.get(name).and_then(|v| v.as_str()).map(String::from);
+    Some(Revision {
+        owner: field("owner").unwrap_or_else(|| "NixOS".to_string()),
+        repo: field("repo").unwrap_or_else(|| "nixpkgs".to_string()),
+        rev: field("rev")?,
+    })
+}
+
+// Whether NetworkManager marks any active connection as metered
+pub fn metered() -> bool {
+    system::run("nmcli", &["-g", "GENERAL.METERED", "device", "show"])
+        .map(|out| out.lines().any(|l| l.starts_with("yes")))
+        .unwrap_or(false)
+}
+
+// Attribute sets whose members a top-level file defines, e.g. python-packages.nix
+// defines python312Packages.* and python313Packages.*; "" is the top level
+fn set_prefix(file: &str) -> Option<(&'static str, &'static str)> {
+    match file {
+        "pkgs/top-level/all-packages.nix" => Some(("", "")),
+        "pkgs/top-level/python-packages.nix" => Some(("python3", "Packages")),
+        "pkgs/top-level/perl-packages.nix" => Some(("perl", "Packages")),
+        "pkgs/top-level/lua-packages.nix" => Some(("lua", "Packages")),
+        _ => None,
+    }
+}
+
+// "  firefox = callPackage ..." at the set's own indentation
+fn defined_attr(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix("  ")?;
+    if rest.starts_with(' ') {
+        return None;
+    }
+    let (name, value) = rest.split_once(" = ")?;
+    let plain = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '\'');
+    (plain && !name.is_empty() && !value.trim().is_empty()).then_some(name)
+}
+
+// "pkgs/by-name/fi/firefox/package.nix" -> "firefox"
+fn by_name_attr(file: &str) -> Option<&str> {
+    let rest = file.strip_prefix("pkgs/by-name/")?;
+    let mut parts = rest.split('/');
+    let (_shard, name, leaf) = (parts.next()?, parts.next()?, parts.next()?);
+    (leaf == "package.nix").then_some(name)
+}
+
+fn fetch_compare(from: &Revision, to: &Revision) -> anyhow::Result<String> {
+    if system::find_in_path("curl").is_none() {
+        bail!("curl isn't available");
+    }
+    let url = format!(
+        "https://api.github.com/repos/{}/{}/compare/{}...{}",
+        to.owner, to.repo, from.rev, to.rev
+    );
+    system::run(
+        "curl",
+        &[
+            "--fail",
+            "--silent",
+            "--show-error",
+            "--location",
+            "--compressed",
+            "--max-time",
+            "60",
+            "-H",
+            "Accept: application/vnd.github+json",
+            &url,
+        ],
+    )
+}
+
+// Attribute changes between two revisions; None when a full rebuild is needed.
+// `sets` are the nested attribute sets present in the index (python312Packages, ...)
+pub fn between(
+    from: &Revision,
+    to: &Revision,
+    sets: &BTreeSet<String>,
+) -> anyhow::Result<Option<Delta>> {
+    if (&from.owner, &from.repo) != (&to.owner, &to.repo) {
+        return Ok(None);
+    }
+    let body = fetch_compare(from, to)?;
+    let compare: serde_json::Value = serde_json::from_str(&body)?;
+    let files = compare
+        .get("files")
+        .and_then(|f| f.as_array())
+        .cloned()
+        .unwrap_or_default();
+    if files.len() >= COMPARE_FILE_LIMIT {
+        return Ok(None);
+    }
+
+    let mut added = BTreeSet::new();
+    let mut removed = BTreeSet::new();
+    for file in &files {
+        let text = |name: &str| file.get(name).and_then(|v| v.as_str()).unwrap_or("");
+        let (name, status) = (text("filename"), text("status"));
+        if let Some(attr) = by_name_attr(name) {
+            match status {
+                "added" => {
+                    added.insert(attr.to_string());
+                }
+                "removed" => {
+                    removed.insert(attr.to_string());
+                }
+                "renamed" => {
+                    added.insert(attr.to_string());
+                    if let Some(old) = by_name_attr(text("previous_filename")) {
+                        removed.insert(old.to_string());
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        if !name.starts_with("pkgs/top-level/") || name.ends_with("aliases.nix") {
+            continue;
+        }
+        let Some((starts, ends)) = set_prefix(name) else {
+            return Ok(None);
+        };
+        let Some(patch) = file.get("patch").and_then(|p| p.as_str()) else {
+            // Too large for GitHub to show
+            return Ok(None);
+        };
+        let qualified = |attr: &str| -> Vec<String> {
+            if starts.is_empty() {
+                return vec![attr.to_string()];
+            }
+            sets.iter()
+                .filter(|s| s.starts_with(starts) && s.ends_with(ends))
+                .map(|s| format!("{}.{}", s, attr))
+                .collect()
+        };
+        let (mut plus, mut minus) = (BTreeSet::new(), BTreeSet::new());
+        for line in patch.lines() {
+            if let Some(attr) = line.strip_prefix('+').and_then(defined_attr) {
+                plus.insert(attr.to_string());
+            } else if let Some(attr) = line.strip_prefix('-').and_then(defined_attr) {
+                minus.insert(attr.to_string());
+            }
+        }
+        // A definition that was only edited shows up on both sides
+        for attr in plus.difference(&minus) {
+            added.extend(qualified(attr));
+        }
+        for attr in minus.difference(&plus) {
+            removed.extend(qualified(attr));
+        }
+    }
+    Ok(Some(Delta {
+        added: added.difference(&removed).cloned().collect(),
+        removed: removed.difference(&added).cloned().collect(),
+        changed_files: files.len(),
+        bytes: body.len(),
+    }))
+}
@@ -0,0 +1,124 @@
+// Unified inventory of installed software across Nix profiles, flatpak and AppImages
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::{flatpak, nix, system, tasks};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    NixProfile,
+    Flatpak,
+    AppImage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventoryItem {
+    pub name: String,
+    pub source: Source,
+    pub version: Option<String>,
+    // Source-specific identifier: attr path, flatpak app id or AppImage path
+    pub id: String,
+    pub location: Option<String>,
+}
+
+// Parse `nix profile list --json`, which is a map of elements on Nix >= 2.20
+// and an array on older versions
+pub fn parse_profile_list(output: &str) -> Vec<InventoryItem> {
+    let Ok(parsed) = serde_json::from_str::<serde_json::Value>(output) else {
+        return Vec::new();
+    };
+    let to_item = |name: Option<&str>, element: &serde_json::Value| {
+        let attr = element
+            .get("attrPath")
+            .and_then(|a| a.as_str())
+            .map(nix::short_attr);
+        let store_path = element
+            .get("storePaths")
+            .and_then(|p| p.get(0))
+            .and_then(|p| p.as_str());
+        let name = name
+            .map(String::from)
+            .or_else(|| attr.clone())
+            .or_else(|| store_path.map(store_path_name))
+            .unwrap_or_default();
+        InventoryItem {
+            version: store_path.and_then(store_path_version),
+            id: attr.unwrap_or_else(|| name.clone()),
+            location: store_path.map(String::from),
+            source: Source::NixProfile,
+            name,
+        }
+    };
+    match parsed.get("elements") {
+        Some(serde_json::Value::Object(elements)) => elements
+            .iter()
+            .map(|(name, element)| to_item(Some(name), element))
+            .collect(),
+        Some(serde_json::Value::Array(elements)) => elements
+            .iter()
+            .map(|element| to_item(None, element))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// "/nix/store/<hash>-firefox-128.0" -> "firefox-128.0"
+fn store_path_stem(path: &str) -> &str {
+    let base = path.rsplit('/').next().unwrap_or(path);
+    base.split_once('-').map(|(_, rest)| rest).unwrap_or(base)
+}
+
+pub fn store_path_name(path: &str) -> String {
+    let stem = store_path_stem(path);
+    match stem.find(|c: char| c.is_ascii_digit()) {
+        Some(i) if i > 0 && stem.as_bytes()[i - 1] == b'-' => stem[..i - 1].to_string(),
+        _ => stem.to_string(),
+    }
+}
+
+pub fn store_path_version(path: &str) -> Option<String> {
+    let stem = store_path_stem(path);
+    let name = store_path_name(path);
+    stem.strip_prefix(name.as_str())
+        .and_then(|rest| rest.strip_prefix('-'))
+        .map(String::from)
+}
+
+fn nix_profile_items() -> Vec<InventoryItem> {
+    system::run("nix", &["profile", "list", "--json"])
+        .map(|output| parse_profile_list(&output))
+        .unwrap_or_default()
+}
+
+pub fn collect() -> Vec<InventoryItem> {
+    let mut items = nix_profile_items();
+    items.extend(flatpak::list_apps().into_iter().map(|app| InventoryItem {
+        name: app.name,
+        source: Source::Flatpak,
+        version: Some(app.version).filter(|v| !v.is_empty()),
+        id: app.app_id,
+        location: Some(app.installation),
+    }));
+    items.extend(flatpak::list_appimages().into_iter().map(|image| {
+        InventoryItem {
+            name: image.name,
+            source: Source::AppImage,
+            version: None,
+            id: image.path.to_string_lossy().into_owned(),
+            location: image
+                .path
+                .parent()
+                .map(|p| p.to_string_lossy().into_owned()),
+        }
+    }));
+    items
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_inventory(app: AppHandle) -> Vec<InventoryItem> {
+    tasks::blocking_value(&app, "Get inventory", |_| collect()).await
+}
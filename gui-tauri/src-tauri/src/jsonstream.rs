@@ -0,0 +1,98 @@
+// Incremental parsing of large `nix ... --json` outputs
+//
+// `nix search nixpkgs ^ --json` or `nix path-info --all --json` can print tens
+// of thousands of entries. Instead of collecting stdout into one string and
+// parsing it into a tree, the top-level object (or array) is read straight
+// from the pipe and each entry is handed to a callback as soon as it is
+// complete, so memory stays at one entry however long the output is.
+
+use anyhow::{bail, Context};
+use serde::de::{DeserializeOwned, Deserializer, MapAccess, SeqAccess, Visitor};
+use std::fmt;
+use std::io::{BufReader, Read};
+use std::marker::PhantomData;
+use std::process::{Command, Stdio};
+
+struct Entries<'f, V, F> {
+    callback: &'f mut F,
+    marker: PhantomData<V>,
+}
+
+impl<'de, V, F> Visitor<'de> for Entries<'_, V, F>
+where
+    V: DeserializeOwned,
+    F: FnMut(Option<String>, V),
+{
+    type Value = usize;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a JSON object or array")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<usize, A::Error> {
+        let mut count = 0;
+        while let Some((key, value)) = map.next_entry::<String, V>()? {
+            (self.callback)(Some(key), value);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<usize, A::Error> {
+        let mut count = 0;
+        while let Some(value) = seq.next_element::<V>()? {
+            (self.callback)(None, value);
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+// Call `f` with each entry of the top-level object (with its key) or array
+// (without); returns how many there were
+pub fn for_each<R: Read, V: DeserializeOwned>(
+    reader: R,
+    mut f: impl FnMut(Option<String>, V),
+) -> anyhow::Result<usize> {
+    let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(reader));
+    let count = deserializer.deserialize_any(Entries {
+        callback: &mut f,
+        marker: PhantomData,
+    })?;
+    deserializer.end()?;
+    Ok(count)
+}
+
+// Run a command and stream its JSON stdout through `for_each`
+pub fn run<V: DeserializeOwned>(
+    program: &str,
+    args: &[&str],
+    f: impl FnMut(Option<String>, V),
+) -> anyhow::Result<usize> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to start {}", program))?;
+    // Drained on the side so a chatty stderr can't fill its pipe and stall the child
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let stderr = std::thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text);
+        text
+    });
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let parsed = for_each(stdout, f);
+    // On a parse error the child may still be writing; don't wait on a full pipe
+    if parsed.is_err() {
+        let _ = child.kill();
+    }
+    let status = child.wait()?;
+    let stderr = stderr.join().unwrap_or_default();
+    if !status.success() {
+        bail!("{} exited with {}: {}", program, status, stderr.trim());
+    }
+    parsed
+}
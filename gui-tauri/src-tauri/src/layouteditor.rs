@@ -0,0 +1,452 @@
+// Rearranging the layout on screen by dragging components around
+//
+// begin_layout_edit puts the current layout into edit mode; move_component,
+// add_component and remove_component then change it one step at a time, and
+// end_layout_edit keeps the result or puts the original back. The grid is held
+// here as a matrix of named cells parsed from the CSS grid-template, so every
+// change is checked in Rust: a component only lands on free cells (or its
+// own), every area stays a rectangle, rows and columns left empty are dropped,
+// and the template is written back out from the matrix. Whatever the frontend
+// sends, what comes out is a grid CSS can render.
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tokio::sync::Mutex;
+
+use crate::personas::Persona;
+use crate::{layouts, tasks, AppState, ComponentState, Layout};
+
+const EMPTY: &str = ".";
+// Sizes of rows and columns the editor adds
+const NEW_ROW: &str = "auto";
+const NEW_COLUMN: &str = "1fr";
+// Keeps a runaway drag from producing an enormous grid
+const MAX_TRACKS: usize = 12;
+
+// The layout as it was when editing began, to go back to
+static EDITING: Mutex<Option<Layout>> = Mutex::const_new(None);
+
+fn one() -> usize {
+    1
+}
+
+// Cells from `row`, `column` (0-based), `row_span` down and `column_span` across
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GridArea {
+    pub row: usize,
+    pub column: usize,
+    #[serde(default = "one")]
+    pub row_span: usize,
+    #[serde(default = "one")]
+    pub column_span: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Grid {
+    // Area name per cell, "." for empty ones
+    pub cells: Vec<Vec<String>>,
+    pub rows: Vec<String>,
+    pub columns: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditView {
+    pub layout: Layout,
+    pub grid: Grid,
+}
+
+// Split on whitespace outside quotes and brackets
+fn tokens(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let (mut quoted, mut depth) = (false, 0usize);
+    for c in text.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth = depth.saturating_sub(1),
+            c if c.is_whitespace() && !quoted && depth == 0 => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+// "\"search search\" auto \"results guide\" 1fr / 2fr 1fr"
+pub fn parse(template: &str) -> anyhow::Result<Grid> {
+    let (rows_part, columns_part) = template
+        .rsplit_once('/')
+        .ok_or_else(|| anyhow!("The grid template has no column sizes"))?;
+    let mut cells: Vec<Vec<String>> = Vec::new();
+    let mut rows: Vec<String> = Vec::new();
+    for token in tokens(rows_part) {
+        if let Some(row) = token.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+            cells.push(row.split_whitespace().map(String::from).collect());
+            rows.push(NEW_ROW.to_string());
+        } else if let Some(size) = rows.last_mut() {
+            *size = token;
+        } else {
+            bail!("Unexpected \"{}\" in the grid template", token);
+        }
+    }
+    let columns = tokens(columns_part);
+    if cells.is_empty() || columns.is_empty() {
+        bail!("The grid template has no cells");
+    }
+    if let Some(row) = cells.iter().position(|row| row.len() != columns.len()) {
+        bail!(
+            "Row {} of the grid template has {} cells for {} columns",
+            row + 1,
+            cells[row].len(),
+            columns.len()
+        );
+    }
+    let grid = Grid {
+        cells,
+        rows,
+        columns,
+    };
+    grid.check()?;
+    Ok(grid)
+}
+
+impl Grid {
+    pub fn render(&self) -> String {
+        let rows: Vec<String> = self
+            .cells
+            .iter()
+            .zip(&self.rows)
+            .map(|(row, size)| format!("\"{}\" {}", row.join(" "), size))
+            .collect();
+        format!("{} / {}", rows.join(" "), self.columns.join(" "))
+    }
+
+    fn positions(&self) -> impl Iterator<Item = (usize, usize, &str)> + '_ {
+        self.cells.iter().enumerate().flat_map(|(r, row)| {
+            row.iter()
+                .enumerate()
+                .map(move |(c, name)| (r, c, name.as_str()))
+        })
+    }
+
+    // The smallest rectangle holding every cell of `name`
+    fn area_of(&self, name: &str) -> Option<GridArea> {
+        let cells: Vec<(usize, usize)> = self
+            .positions()
+            .filter(|(_, _, cell)| *cell == name)
+            .map(|(r, c, _)| (r, c))
+            .collect();
+        let top = cells.iter().map(|(r, _)| *r).min()?;
+        let left = cells.iter().map(|(_, c)| *c).min()?;
+        let bottom = cells.iter().map(|(r, _)| *r).max()?;
+        let right = cells.iter().map(|(_, c)| *c).max()?;
+        Some(GridArea {
+            row: top,
+            column: left,
+            row_span: bottom - top + 1,
+            column_span: right - left + 1,
+        })
+    }
+
+    // CSS grid only accepts areas that are filled rectangles
+    fn check(&self) -> anyhow::Result<()> {
+        let mut names: Vec<&str> = self.positions().map(|(_, _, name)| name).collect();
+        names.sort_unstable();
+        names.dedup();
+        for name in names.into_iter().filter(|n| *n != EMPTY) {
+            let Some(area) = self.area_of(name) else {
+                continue;
+            };
+            let count = self.positions().filter(|(_, _, n)| *n == name).count();
+            if count != area.row_span * area.column_span {
+                bail!("The area \"{}\" isn't a rectangle", name);
+            }
+        }
+        Ok(())
+    }
+
+    // The first name other than `except` in the cells of `area` that exist
+    fn occupant(&self, area: GridArea, except: &str) -> Option<String> {
+        self.positions()
+            .filter(|(r, c, _)| {
+                (area.row..area.row + area.row_span).contains(r)
+                    && (area.column..area.column + area.column_span).contains(c)
+            })
+            .map(|(_, _, name)| name)
+            .find(|name| *name != EMPTY && *name != except)
+            .map(String::from)
+    }
+
+    fn clear(&mut self, name: &str) {
+        for cell in self.cells.iter_mut().flatten() {
+            if *cell == name {
+                *cell = EMPTY.to_string();
+            }
+        }
+    }
+
+    // Add empty rows and columns until `area` fits
+    fn grow(&mut self, area: GridArea) {
+        while self.columns.len() < area.column + area.column_span {
+            self.columns.push(NEW_COLUMN.to_string());
+            for row in &mut self.cells {
+                row.push(EMPTY.to_string());
+            }
+        }
+        while self.rows.len() < area.row + area.row_span {
+            self.rows.push(NEW_ROW.to_string());
+            self.cells.push(vec![EMPTY.to_string(); self.columns.len()]);
+        }
+    }
+
+    fn place(&mut self, name: &str, area: GridArea) {
+        self.grow(area);
+        for row in &mut self.cells[area.row..area.row + area.row_span] {
+            for cell in &mut row[area.column..area.column + area.column_span] {
+                *cell = name.to_string();
+            }
+        }
+    }
+
+    // Drop rows and columns nothing is in, keeping at least one cell
+    fn compact(&mut self) {
+        let mut r = 0;
+        while r < self.cells.len() && self.cells.len() > 1 {
+            if self.cells[r].iter().all(|cell| cell == EMPTY) {
+                self.cells.remove(r);
+                self.rows.remove(r);
+            } else {
+                r += 1;
+            }
+        }
+        let mut c = 0;
+        while c < self.columns.len() && self.columns.len() > 1 {
+            if self.cells.iter().all(|row| row[c] == EMPTY) {
+                for row in &mut self.cells {
+                    row.remove(c);
+                }
+                self.columns.remove(c);
+            } else {
+                c += 1;
+            }
+        }
+    }
+
+    fn free_cell(&self) -> Option<GridArea> {
+        self.positions()
+            .find(|(_, _, name)| *name == EMPTY)
+            .map(|(row, column, _)| GridArea {
+                row,
+                column,
+                row_span: 1,
+                column_span: 1,
+            })
+    }
+}
+
+fn area_name(component: &ComponentState) -> String {
+    component.state["area"]
+        .as_str()
+        .map(String::from)
+        .unwrap_or_else(|| component.id.clone())
+}
+
+// Which component an area belongs to, for saying what is in the way
+fn owner(layout: &Layout, area: &str) -> String {
+    layout
+        .components
+        .iter()
+        .find(|c| area_name(c) == area)
+        .map(|c| c.id.clone())
+        .unwrap_or_else(|| area.to_string())
+}
+
+fn grid_of(layout: &Layout) -> anyhow::Result<Grid> {
+    let template = layout.grid["template"]
+        .as_str()
+        .ok_or_else(|| anyhow!("The layout has no grid template"))?;
+    parse(template)
+}
+
+// The layout being edited and its grid
+fn editing(state: &AppState) -> anyhow::Result<(Layout, Grid)> {
+    if EDITING.blocking_lock().is_none() {
+        bail!("The layout isn't being edited; start with begin_layout_edit");
+    }
+    let layout = state
+        .current_layout
+        .blocking_lock()
+        .clone()
+        .ok_or_else(|| anyhow!("There is no layout on screen"))?;
+    let grid = grid_of(&layout)?;
+    Ok((layout, grid))
+}
+
+// Write the edited grid back and make it the layout on screen
+fn commit(state: &AppState, mut layout: Layout, mut grid: Grid) -> anyhow::Result<EditView> {
+    grid.compact();
+    grid.check()?;
+    layout.grid["template"] = grid.render().into();
+    let layout = layouts::restore(state, layout);
+    Ok(EditView { layout, grid })
+}
+
+fn validate_area(area: GridArea) -> anyhow::Result<()> {
+    if area.row_span == 0 || area.column_span == 0 {
+        bail!("A component needs at least one cell");
+    }
+    if area.row + area.row_span > MAX_TRACKS || area.column + area.column_span > MAX_TRACKS {
+        bail!(
+            "The grid can't be more than {} cells in either direction",
+            MAX_TRACKS
+        );
+    }
+    Ok(())
+}
+
+pub fn begin(state: &AppState, persona: &Persona) -> anyhow::Result<EditView> {
+    let current = state.current_layout.blocking_lock().clone();
+    let layout = match current {
+        Some(layout) => layout,
+        None => layouts::switch(state, "persona", persona)?,
+    };
+    let grid = grid_of(&layout)?;
+    *EDITING.blocking_lock() = Some(layout.clone());
+    Ok(EditView { layout, grid })
+}
+
+pub fn move_to(state: &AppState, id: &str, area: GridArea) -> anyhow::Result<EditView> {
+    validate_area(area)?;
+    let (mut layout, mut grid) = editing(state)?;
+    let component = layout
+        .components
+        .iter()
+        .find(|c| c.id == id)
+        .ok_or_else(|| anyhow!("There is no component \"{}\"", id))?;
+    let name = area_name(component);
+    if let Some(other) = grid.occupant(area, &name) {
+        bail!("That space is taken by {}", owner(&layout, &other));
+    }
+    grid.clear(&name);
+    grid.place(&name, area);
+    if let Some(component) = layout.components.iter_mut().find(|c| c.id == id) {
+        component.state["area"] = name.into();
+    }
+    commit(state, layout, grid)
+}
+
+// A new component of a registered type, in the first free cell
+pub fn add(state: &AppState, component_type: &str) -> anyhow::Result<EditView> {
+    let (mut layout, mut grid) = editing(state)?;
+    let spec = state
+        .component_types
+        .blocking_lock()
+        .get(component_type)
+        .cloned()
+        .ok_or_else(|| anyhow!("Unknown component type \"{}\"", component_type))?;
+    let prefix = spec.slug();
+    let id = (1..)
+        .map(|n| format!("{}-{}", prefix, n))
+        .find(|id| !layout.components.iter().any(|c| &c.id == id))
+        .unwrap_or_default();
+    let model = spec.instance(&id);
+    // The type's usual area name while it is free, the id after that
+    let usual = area_name(&model);
+    let name = if grid.area_of(&usual).is_some() {
+        id.clone()
+    } else {
+        usual
+    };
+    let area = grid.free_cell().unwrap_or(GridArea {
+        row: grid.rows.len(),
+        column: 0,
+        row_span: 1,
+        column_span: grid.columns.len(),
+    });
+    validate_area(area)?;
+    grid.place(&name, area);
+    let mut component = model;
+    component.state["area"] = name.into();
+    layout.components.push(component);
+    commit(state, layout, grid)
+}
+
+pub fn remove(state: &AppState, id: &str) -> anyhow::Result<EditView> {
+    let (mut layout, mut grid) = editing(state)?;
+    let position = layout
+        .components
+        .iter()
+        .position(|c| c.id == id)
+        .ok_or_else(|| anyhow!("There is no component \"{}\"", id))?;
+    let component = layout.components.remove(position);
+    grid.clear(&area_name(&component));
+    commit(state, layout, grid)
+}
+
+// Leave edit mode, keeping the changes or going back to where editing began
+pub fn end(state: &AppState, keep: bool) -> anyhow::Result<Layout> {
+    let original = EDITING
+        .blocking_lock()
+        .take()
+        .ok_or_else(|| anyhow!("The layout isn't being edited"))?;
+    if keep {
+        return state
+            .current_layout
+            .blocking_lock()
+            .clone()
+            .ok_or_else(|| anyhow!("There is no layout on screen"));
+    }
+    Ok(layouts::restore(state, original))
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn begin_layout_edit(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Begin layout edit", |state| {
+        crate::respond(begin(state, crate::current_persona(state)))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn move_component(id: String, grid_area: GridArea, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Move component", move |state| {
+        crate::respond(move_to(state, &id, grid_area))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn add_component(component_type: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Add component", move |state| {
+        crate::respond(add(state, &component_type))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn remove_component(id: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Remove component", move |state| {
+        crate::respond(remove(state, &id))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn end_layout_edit(keep: bool, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "End layout edit", move |state| {
+        crate::respond(end(state, keep))
+    })
+    .await
+}
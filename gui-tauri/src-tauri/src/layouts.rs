@@ -0,0 +1,495 @@
+// Layout presets: the grid and components each kind of user starts with
+//
+// Four presets ship built in (minimal, guided, power-user, low-vision) and are
+// written to layouts.json in the config dir on first use, where they can be
+// edited or joined by new ones. A preset is looked up by id, by one of its
+// aliases (the layout names personas use) or by persona, and switching to it
+// replaces the component list while keeping the state of components that
+// stay.
+// Saved layouts are different: an exact arrangement (components with their
+// state, and the grid) stored in saved-layouts.json under its own id. Switching
+// to one restores it as saved, and takes precedence over a preset of the same
+// id. Each is checked against the layout schema when read, so a hand-edited or
+// damaged entry is reported instead of being put on screen.
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tauri::AppHandle;
+
+use crate::personas::{self, Persona};
+use crate::{components, storage, tasks, AppState, ComponentState, Layout};
+
+const LAYOUTS_FILE: &str = "layouts.json";
+const SAVED_FILE: &str = "saved-layouts.json";
+// The fields of the layout schema; anything else wasn't written by us
+const LAYOUT_FIELDS: &[&str] = &["id", "name", "components", "grid"];
+const COMPONENT_FIELDS: &[&str] = &["id", "component_type", "state", "capabilities"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutPreset {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    // Other names that resolve to this preset
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    // CSS grid-template; each component's "area" names its cell
+    pub template: String,
+    // Text is never smaller than this, whatever the persona's own scale
+    pub min_font_scale: f32,
+    pub animate: bool,
+    pub components: Vec<ComponentState>,
+}
+
+// A component of a built-in type, in the area that type usually takes
+fn component(id: &str, component_type: &str) -> ComponentState {
+    components::builtin_named(component_type).instance(id)
+}
+
+fn search() -> ComponentState {
+    component("search-1", "SearchInput")
+}
+
+fn results() -> ComponentState {
+    component("results-1", "ResultsList")
+}
+
+fn preset(
+    id: &str,
+    name: &str,
+    description: &str,
+    aliases: &[&str],
+    template: &str,
+    components: Vec<ComponentState>,
+) -> LayoutPreset {
+    LayoutPreset {
+        id: id.to_string(),
+        name: name.to_string(),
+        description: description.to_string(),
+        aliases: aliases.iter().map(|a| a.to_string()).collect(),
+        template: template.to_string(),
+        min_font_scale: 1.0,
+        animate: true,
+        components,
+    }
+}
+
+pub fn builtin() -> Vec<LayoutPreset> {
+    let minimal = preset(
+        "minimal",
+        "Minimal",
+        "Just the search box and its results, nothing else competing for attention",
+        &["focus"],
+        "\"search\" auto \"results\" 1fr / 1fr",
+        vec![search(), results()],
+    );
+    let guided = preset(
+        "guided",
+        "Guided",
+        "A guide beside the results that explains each step and what to try next",
+        &["default"],
+        "\"search search\" auto \"results guide\" 1fr / 2fr 1fr",
+        vec![search(), results(), component("guide-1", "GuidePanel")],
+    );
+    let mut power_user = preset(
+        "power-user",
+        "Power user",
+        "Everything at once: results, exact commands, running operations and a terminal",
+        &["dense"],
+        concat!(
+            "\"search search status\" auto ",
+            "\"history results details\" 1fr ",
+            "\"terminal terminal terminal\" 240px ",
+            "/ 240px 1fr 320px",
+        ),
+        vec![
+            search(),
+            results(),
+            component("history-1", "HistoryList"),
+            component("details-1", "CommandDetails"),
+            component("status-1", "ProgressPanel"),
+            component("terminal-1", "TerminalPanel"),
+        ],
+    );
+    power_user.animate = false;
+    let mut low_vision = preset(
+        "low-vision",
+        "Low vision",
+        "One column in reading order with large text, no motion and everything announced",
+        &["linear"],
+        "\"search\" auto \"announcer\" auto \"results\" 1fr / 1fr",
+        vec![search(), component("announcer-1", "LiveRegion"), results()],
+    );
+    low_vision.min_font_scale = 1.5;
+    low_vision.animate = false;
+    vec![minimal, guided, power_user, low_vision]
+}
+
+// The presets on disk, seeded with the built-in ones on first use
+pub fn presets() -> anyhow::Result<Vec<LayoutPreset>> {
+    let stored: Vec<LayoutPreset> = storage::load(LAYOUTS_FILE)?;
+    if !stored.is_empty() {
+        return Ok(stored);
+    }
+    let builtin = builtin();
+    storage::save(LAYOUTS_FILE, &builtin)?;
+    Ok(builtin)
+}
+
+// By preset id or alias, by persona, or "persona" for the current persona's
+pub fn resolve(id: &str, persona: &Persona) -> anyhow::Result<LayoutPreset> {
+    let presets = presets()?;
+    let key = id.trim().to_lowercase();
+    let find = |key: &str| {
+        presets
+            .iter()
+            .find(|p| p.id == key || p.aliases.iter().any(|a| a == key))
+            .cloned()
+    };
+    if key == "persona" {
+        return find(persona.layout)
+            .ok_or_else(|| anyhow!("No layout preset matches '{}'", persona.layout));
+    }
+    find(&key)
+        .or_else(|| personas::find(&key).and_then(|p| find(p.layout)))
+        .ok_or_else(|| anyhow!("Unknown layout '{}'", id))
+}
+
+fn grid(preset: &LayoutPreset, persona: &Persona) -> serde_json::Value {
+    serde_json::json!({
+        "template": preset.template,
+        "font_scale": persona.font_scale.max(preset.min_font_scale),
+        "animate": preset.animate && persona.animations(),
+    })
+}
+
+// Make `preset` the current layout; components that stay keep their state
+pub fn apply(state: &AppState, preset: LayoutPreset, persona: &Persona) -> Layout {
+    let mut components = state.components.blocking_lock();
+    let next: Vec<ComponentState> = preset
+        .components
+        .iter()
+        .map(|fresh| {
+            let mut component = fresh.clone();
+            if let Some(current) = components.iter().find(|c| c.id == fresh.id) {
+                let mut kept = current.state.clone();
+                if let Some(map) = kept.as_object_mut() {
+                    map.insert("area".to_string(), fresh.state["area"].clone());
+                }
+                component.state = kept;
+            }
+            component
+        })
+        .collect();
+    *components = next.clone();
+    let layout = Layout {
+        id: preset.id.clone(),
+        name: preset.name.clone(),
+        components: next,
+        grid: grid(&preset, persona),
+    };
+    *state.current_layout.blocking_lock() = Some(layout.clone());
+    layout
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvalidLayout {
+    // When the entry has one
+    pub id: Option<String>,
+    pub problems: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedLayouts {
+    pub layouts: Vec<Layout>,
+    // Entries in the file that don't match the schema
+    pub invalid: Vec<InvalidLayout>,
+}
+
+fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// The cell names of a grid-template: the words inside its quoted rows
+fn template_areas(template: &str) -> HashSet<&str> {
+    template
+        .split('"')
+        .skip(1)
+        .step_by(2)
+        .flat_map(str::split_whitespace)
+        .filter(|area| *area != ".")
+        .collect()
+}
+
+fn check_component(
+    index: usize,
+    value: &serde_json::Value,
+    areas: &HashSet<&str>,
+    problems: &mut Vec<String>,
+) -> Option<String> {
+    let Some(fields) = value.as_object() else {
+        problems.push(format!("component {} is not an object", index + 1));
+        return None;
+    };
+    for key in fields.keys() {
+        if !COMPONENT_FIELDS.contains(&key.as_str()) {
+            problems.push(format!(
+                "component {} has an unknown field \"{}\"",
+                index + 1,
+                key
+            ));
+        }
+    }
+    let id = fields.get("id").and_then(|v| v.as_str());
+    if !id.is_some_and(valid_id) {
+        problems.push(format!("component {} needs an id", index + 1));
+    }
+    if !fields
+        .get("component_type")
+        .and_then(|v| v.as_str())
+        .is_some_and(|t| !t.trim().is_empty())
+    {
+        problems.push(format!("component {} needs a component_type", index + 1));
+    }
+    match fields.get("state") {
+        Some(state) if state.is_object() => match state.get("area") {
+            None => {}
+            Some(serde_json::Value::String(area)) if areas.contains(area.as_str()) => {}
+            Some(area) => problems.push(format!(
+                "component {} is placed in {}, which isn't in the grid template",
+                index + 1,
+                area
+            )),
+        },
+        _ => problems.push(format!("component {} needs a state object", index + 1)),
+    }
+    let capabilities = fields.get("capabilities").and_then(|v| v.as_array());
+    // Names, or capabilities as served with their status
+    let named = |c: &serde_json::Value| c.is_string() || c["name"].is_string();
+    if !capabilities.is_some_and(|c| c.iter().all(named)) {
+        problems.push(format!(
+            "component {} needs a list of capability names",
+            index + 1
+        ));
+    }
+    id.map(String::from)
+}
+
+// Check a stored layout against the layout schema before trusting it
+pub fn check(value: &serde_json::Value) -> Result<Layout, Vec<String>> {
+    let Some(fields) = value.as_object() else {
+        return Err(vec!["the layout is not an object".to_string()]);
+    };
+    let mut problems = Vec::new();
+    for key in fields.keys() {
+        if !LAYOUT_FIELDS.contains(&key.as_str()) {
+            problems.push(format!("unknown field \"{}\"", key));
+        }
+    }
+    if !fields
+        .get("id")
+        .and_then(|v| v.as_str())
+        .is_some_and(valid_id)
+    {
+        problems.push("the id must be letters, digits, - and _".to_string());
+    }
+    if !fields
+        .get("name")
+        .and_then(|v| v.as_str())
+        .is_some_and(|n| !n.trim().is_empty())
+    {
+        problems.push("the layout needs a name".to_string());
+    }
+    let template = fields
+        .get("grid")
+        .and_then(|g| g.get("template"))
+        .and_then(|t| t.as_str())
+        .filter(|t| !t.trim().is_empty());
+    if template.is_none() {
+        problems.push("the grid needs a template".to_string());
+    }
+    let areas = template.map(template_areas).unwrap_or_default();
+    match fields.get("components").and_then(|c| c.as_array()) {
+        Some(components) => {
+            let mut ids = HashSet::new();
+            for (index, component) in components.iter().enumerate() {
+                if let Some(id) = check_component(index, component, &areas, &mut problems) {
+                    if !ids.insert(id.clone()) {
+                        problems.push(format!("two components are called \"{}\"", id));
+                    }
+                }
+            }
+        }
+        None => problems.push("the layout needs a list of components".to_string()),
+    }
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+    serde_json::from_value(value.clone()).map_err(|e| vec![e.to_string()])
+}
+
+// The saved layouts as they are on disk, damaged entries included
+pub fn stored() -> anyhow::Result<Vec<serde_json::Value>> {
+    storage::load(SAVED_FILE)
+}
+
+// Replace every saved layout at once, as an import does
+pub fn set_stored(entries: &[serde_json::Value]) -> anyhow::Result<()> {
+    storage::save(SAVED_FILE, &entries).map(|_| ())
+}
+
+pub fn set_presets(presets: &[LayoutPreset]) -> anyhow::Result<()> {
+    storage::save(LAYOUTS_FILE, &presets).map(|_| ())
+}
+
+pub fn saved() -> anyhow::Result<SavedLayouts> {
+    let mut layouts = Vec::new();
+    let mut invalid = Vec::new();
+    for value in stored()? {
+        match check(&value) {
+            Ok(layout) => layouts.push(layout),
+            Err(problems) => invalid.push(InvalidLayout {
+                id: value.get("id").and_then(|i| i.as_str()).map(String::from),
+                problems,
+            }),
+        }
+    }
+    Ok(SavedLayouts { layouts, invalid })
+}
+
+// The saved layout `id`; an error when it is there but doesn't pass the schema
+pub fn find_saved(id: &str) -> anyhow::Result<Option<Layout>> {
+    let Some(value) = stored()?.into_iter().find(|v| v["id"] == id) else {
+        return Ok(None);
+    };
+    check(&value).map(Some).map_err(|problems| {
+        anyhow!(
+            "The saved layout \"{}\" is damaged: {}",
+            id,
+            problems.join("; ")
+        )
+    })
+}
+
+pub fn save(
+    state: &AppState,
+    persona: &Persona,
+    id: &str,
+    name: Option<String>,
+    layout: Option<Layout>,
+) -> anyhow::Result<Layout> {
+    let mut layout = match layout {
+        Some(layout) => layout,
+        None => {
+            let current = state.current_layout.blocking_lock().clone();
+            let grid = match &current {
+                Some(current) => current.grid.clone(),
+                None => grid(&resolve("persona", persona)?, persona),
+            };
+            Layout {
+                id: String::new(),
+                name: String::new(),
+                components: state.components.blocking_lock().clone(),
+                grid,
+            }
+        }
+    };
+    layout.id = id.trim().to_string();
+    layout.name = name.unwrap_or_else(|| id.to_string());
+    let value = serde_json::to_value(&layout)?;
+    if let Err(problems) = check(&value) {
+        bail!("This layout can't be saved: {}", problems.join("; "));
+    }
+    let mut entries = stored()?;
+    entries.retain(|v| v["id"] != layout.id.as_str());
+    entries.push(value);
+    storage::save(SAVED_FILE, &entries)?;
+    Ok(layout)
+}
+
+pub fn delete(id: &str) -> anyhow::Result<SavedLayouts> {
+    let mut entries = stored()?;
+    let before = entries.len();
+    entries.retain(|v| v["id"] != id);
+    if entries.len() == before {
+        bail!("There is no saved layout \"{}\"", id);
+    }
+    storage::save(SAVED_FILE, &entries)?;
+    saved()
+}
+
+// Put a saved layout on screen exactly as it was stored
+pub fn restore(state: &AppState, layout: Layout) -> Layout {
+    *state.components.blocking_lock() = layout.components.clone();
+    *state.current_layout.blocking_lock() = Some(layout.clone());
+    layout
+}
+
+// A saved layout by id, or else a preset
+pub fn switch(state: &AppState, id: &str, persona: &Persona) -> anyhow::Result<Layout> {
+    if let Some(layout) = find_saved(id.trim())? {
+        return Ok(restore(state, layout));
+    }
+    resolve(id, persona).map(|preset| apply(state, preset, persona))
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn list_layout_presets(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "List layout presets", |_| crate::respond(presets())).await
+}
+
+// Add a preset, or replace the one with the same id
+#[tauri::command]
+pub async fn save_layout_preset(preset: LayoutPreset, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Save layout preset", move |_| {
+        let result = presets().and_then(|mut presets| {
+            presets.retain(|p| p.id != preset.id);
+            presets.push(preset);
+            storage::save(LAYOUTS_FILE, &presets)?;
+            Ok(presets)
+        });
+        crate::respond(result)
+    })
+    .await
+}
+
+// Put the built-in presets back, dropping edits and added ones
+#[tauri::command]
+pub async fn reset_layout_presets(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Reset layout presets", |_| {
+        crate::respond(storage::save(LAYOUTS_FILE, &builtin()).map(|_| builtin()))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn list_layouts(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "List layouts", |_| crate::respond(saved())).await
+}
+
+// Save `layout`, or what is on screen now, under `id`
+#[tauri::command]
+pub async fn save_layout(
+    id: String,
+    name: Option<String>,
+    layout: Option<Layout>,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Save layout", move |state| {
+        let persona = crate::current_persona(state);
+        crate::respond(save(state, persona, &id, name, layout))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn delete_layout(id: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Delete layout", move |_| crate::respond(delete(&id))).await
+}
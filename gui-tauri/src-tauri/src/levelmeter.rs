@@ -0,0 +1,156 @@
+// Live input level, for trying a microphone before turning voice on
+//
+// While the meter runs, the loudness of the selected microphone is sent as
+// "input-level" events about 20 times a second: RMS and peak from 0 to 1 and
+// the RMS in dBFS, enough to draw a meter and see whether the right device
+// hears anything. Samples are measured and dropped, never kept. The meter
+// stops itself after a while so the microphone isn't left open behind a
+// forgotten settings page. Needs the `voice` build feature.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tauri::AppHandle;
+use tokio::sync::Mutex;
+
+use crate::{capabilities, tasks};
+
+static RUNNING: Mutex<Option<Arc<AtomicBool>>> = Mutex::const_new(None);
+
+// The capture thread, only built with the `voice` feature
+#[cfg(feature = "voice")]
+mod meter {
+    use anyhow::anyhow;
+    use serde::{Deserialize, Serialize};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc};
+    use std::time::{Duration, Instant};
+    use tauri::{AppHandle, Emitter};
+
+    use crate::voice;
+
+    const INTERVAL: Duration = Duration::from_millis(50);
+    const MAX_RUN: Duration = Duration::from_secs(120);
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct InputLevel {
+        pub rms: f32,
+        pub peak: f32,
+        // Silence reads as -100
+        pub dbfs: f32,
+    }
+
+    fn level(sum_squares: f32, count: usize, peak: f32) -> InputLevel {
+        let rms = if count > 0 {
+            (sum_squares / count as f32).sqrt()
+        } else {
+            0.0
+        };
+        InputLevel {
+            rms,
+            peak,
+            dbfs: if rms > 0.0 {
+                (20.0 * rms.log10()).max(-100.0)
+            } else {
+                -100.0
+            },
+        }
+    }
+
+    pub fn start(app: &AppHandle, stop: Arc<AtomicBool>) -> anyhow::Result<()> {
+        let (ready, started) = mpsc::channel::<anyhow::Result<()>>();
+        let app = app.clone();
+        std::thread::spawn(move || {
+            let (sender, chunks) = mpsc::channel::<Vec<f32>>();
+            let stream = match voice::open_stream(move |data| {
+                let _ = sender.send(data.to_vec());
+            }) {
+                Ok((stream, _, _)) => {
+                    let _ = ready.send(Ok(()));
+                    stream
+                }
+                Err(e) => {
+                    let _ = ready.send(Err(e));
+                    return;
+                }
+            };
+            let began = Instant::now();
+            let (mut sum_squares, mut count, mut peak) = (0.0f32, 0usize, 0.0f32);
+            let mut last_emit = Instant::now();
+            while !stop.load(Ordering::Relaxed) && began.elapsed() < MAX_RUN {
+                if let Ok(chunk) = chunks.recv_timeout(INTERVAL) {
+                    for sample in &chunk {
+                        sum_squares += sample * sample;
+                        peak = peak.max(sample.abs());
+                    }
+                    count += chunk.len();
+                }
+                if last_emit.elapsed() >= INTERVAL {
+                    let _ = app.emit("input-level", level(sum_squares, count, peak));
+                    (sum_squares, count, peak) = (0.0, 0, 0.0);
+                    last_emit = Instant::now();
+                }
+            }
+            drop(stream);
+            stop.store(true, Ordering::Relaxed);
+            let _ = app.emit("input-level-stopped", ());
+        });
+        started
+            .recv()
+            .map_err(|_| anyhow!("The microphone thread stopped"))?
+    }
+}
+
+#[cfg(not(feature = "voice"))]
+mod meter {
+    use anyhow::bail;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use tauri::AppHandle;
+
+    pub fn start(_app: &AppHandle, _stop: Arc<AtomicBool>) -> anyhow::Result<()> {
+        bail!("This build does not include voice input (enable the `voice` feature)")
+    }
+}
+
+pub fn running() -> bool {
+    RUNNING
+        .blocking_lock()
+        .as_ref()
+        .is_some_and(|stop| !stop.load(Ordering::Relaxed))
+}
+
+pub fn stop() {
+    if let Some(stop) = RUNNING.blocking_lock().take() {
+        stop.store(true, Ordering::Relaxed);
+    }
+}
+
+// (Re)start on the selected microphone
+pub fn start(app: &AppHandle) -> anyhow::Result<()> {
+    stop();
+    capabilities::require("voice")?;
+    let flag = Arc::new(AtomicBool::new(false));
+    meter::start(app, flag.clone())?;
+    *RUNNING.blocking_lock() = Some(flag);
+    Ok(())
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn start_input_meter(app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Start input meter", move |_| {
+        crate::respond(start(&handle).map(|()| running()))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn stop_input_meter(app: AppHandle) -> bool {
+    tasks::blocking_value(&app, "Stop input meter", |_| {
+        stop();
+        true
+    })
+    .await
+}
@@ -0,0 +1,231 @@
+// License policy engine
+//
+// Users define an allow/deny policy in settings; the install pipeline checks the
+// package's meta.license against it and asks for an explicit override instead
+// of installing something the user ruled out.
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::{managed, storage, system, tasks, warmeval};
+
+const POLICY_FILE: &str = "license-policy.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LicensePolicy {
+    pub deny_unfree: bool,
+    // SPDX ids or prefixes, e.g. "AGPL" matches AGPL-3.0-only and AGPL-3.0-or-later
+    pub denied: Vec<String>,
+    // When non-empty, only these licenses (ids or prefixes) are acceptable
+    pub allowed: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct License {
+    pub spdx_id: Option<String>,
+    pub short_name: String,
+    pub free: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Violation {
+    pub license: License,
+    pub rule: String,
+}
+
+// Structured warning returned to the frontend when an install needs an override
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LicenseVerdict {
+    pub package: String,
+    pub licenses: Vec<License>,
+    pub violations: Vec<Violation>,
+    pub allowed: bool,
+}
+
+pub fn load_policy() -> anyhow::Result<LicensePolicy> {
+    storage::load(POLICY_FILE)
+}
+
+pub fn save_policy(policy: &LicensePolicy) -> anyhow::Result<()> {
+    storage::save(POLICY_FILE, policy).map(|_| ())
+}
+
+fn parse_license(value: &serde_json::Value) -> License {
+    // Some packages carry a bare string instead of a license attrset
+    if let Some(name) = value.as_str() {
+        return License {
+            spdx_id: None,
+            short_name: name.to_string(),
+            free: true,
+        };
+    }
+    let field = |name: &str| value.get(name).and_then(|v| v.as_str()).map(String::from);
+    License {
+        spdx_id: field("spdxId"),
+        short_name: field("shortName")
+            .or_else(|| field("fullName"))
+            .unwrap_or_else(|| "unknown".to_string()),
+        free: value.get("free").and_then(|v| v.as_bool()).unwrap_or(true),
+    }
+}
+
+// meta.license may be absent, a single license, or a list of licenses
+pub fn parse_licenses(value: &serde_json::Value) -> Vec<License> {
+    match value {
+        serde_json::Value::Array(items) => items.iter().map(parse_license).collect(),
+        serde_json::Value::Null => Vec::new(),
+        other => vec![parse_license(other)],
+    }
+}
+
+pub fn package_licenses(package: &str) -> anyhow::Result<Vec<License>> {
+    // The warm evaluator answers in milliseconds once nixpkgs is loaded
+    if warmeval::is_attr_path(package) {
+        let expression = format!("pkgs.{}.meta.license or null", package);
+        if let Ok(value) = warmeval::eval_json(&expression) {
+            return Ok(parse_licenses(&value));
+        }
+    }
+    let attr = format!("nixpkgs#{}.meta.license", package);
+    // Packages without meta.license fail to evaluate the attribute; treat as unknown
+    let output = system::run("nix", &["eval", "--json", &attr]).unwrap_or_else(|_| "null".into());
+    Ok(parse_licenses(&serde_json::from_str(&output)?))
+}
+
+fn matches(license: &License, pattern: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    [
+        license.spdx_id.as_deref(),
+        Some(license.short_name.as_str()),
+    ]
+    .into_iter()
+    .flatten()
+    .any(|id| id.to_lowercase().starts_with(&pattern))
+}
+
+pub fn evaluate(policy: &LicensePolicy, package: &str, licenses: Vec<License>) -> LicenseVerdict {
+    let mut violations = Vec::new();
+    for license in &licenses {
+        if policy.deny_unfree && !license.free {
+            violations.push(Violation {
+                license: license.clone(),
+                rule: "unfree licenses are denied".to_string(),
+            });
+        }
+        if let Some(pattern) = policy.denied.iter().find(|p| matches(license, p)) {
+            violations.push(Violation {
+                license: license.clone(),
+                rule: format!("{} is on the deny list", pattern),
+            });
+        }
+        if !policy.allowed.is_empty() && !policy.allowed.iter().any(|p| matches(license, p)) {
+            violations.push(Violation {
+                license: license.clone(),
+                rule: "not on the allow list".to_string(),
+            });
+        }
+    }
+    LicenseVerdict {
+        package: package.to_string(),
+        allowed: violations.is_empty(),
+        licenses,
+        violations,
+    }
+}
+
+pub fn check(package: &str) -> anyhow::Result<LicenseVerdict> {
+    let policy = load_policy()?;
+    Ok(evaluate(&policy, package, package_licenses(package)?))
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_license_policy(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(
+        &app,
+        "Get license policy",
+        |_| crate::respond(load_policy()),
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn set_license_policy(policy: LicensePolicy, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set license policy", move |_| {
+        if let Some(refusal) = managed::guard("license_policy", "Change the license policy") {
+            return refusal;
+        }
+        crate::respond(save_policy(&policy))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn check_package_license(package: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Check package license", move |_| {
+        crate::respond(check(&package))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn licenses() -> Vec<License> {
+        parse_licenses(&json!([
+            {"spdxId": "AGPL-3.0-or-later", "shortName": "agpl3Plus", "free": true},
+            {"shortName": "unfree", "free": false},
+        ]))
+    }
+
+    #[test]
+    fn meta_license_comes_in_three_shapes() {
+        assert!(parse_licenses(&json!(null)).is_empty());
+        let bare = parse_licenses(&json!("MIT"));
+        assert_eq!(bare[0].short_name, "MIT");
+        assert!(bare[0].free);
+        let listed = licenses();
+        assert_eq!(listed[0].spdx_id.as_deref(), Some("AGPL-3.0-or-later"));
+        assert!(!listed[1].free);
+    }
+
+    #[test]
+    fn the_default_policy_allows_everything() {
+        let verdict = evaluate(&LicensePolicy::default(), "hello", licenses());
+        assert!(verdict.allowed);
+        assert!(verdict.violations.is_empty());
+    }
+
+    #[test]
+    fn each_rule_reports_its_own_violation() {
+        let policy = LicensePolicy {
+            deny_unfree: true,
+            denied: vec!["agpl".to_string()],
+            allowed: Vec::new(),
+        };
+        let verdict = evaluate(&policy, "hello", licenses());
+        assert!(!verdict.allowed);
+        let rules: Vec<&str> = verdict.violations.iter().map(|v| v.rule.as_str()).collect();
+        assert_eq!(
+            rules,
+            ["agpl is on the deny list", "unfree licenses are denied"]
+        );
+    }
+
+    #[test]
+    fn an_allow_list_matches_by_prefix() {
+        let policy = LicensePolicy {
+            allowed: vec!["AGPL".to_string(), "unfree".to_string()],
+            ..LicensePolicy::default()
+        };
+        assert!(evaluate(&policy, "hello", licenses()).allowed);
+        let narrow = LicensePolicy {
+            allowed: vec!["MIT".to_string()],
+            ..LicensePolicy::default()
+        };
+        assert_eq!(evaluate(&narrow, "hello", licenses()).violations.len(), 2);
+    }
+}
@@ -0,0 +1,236 @@
+// Optional local LLM fallback for intent parsing
+//
+// Only consulted when the rule-based parser gives up. The model's answer must
+// match the Intent schema exactly; anything else is discarded. Candidates are
+// never executed from here — they are returned as clarification options the
+// user has to pick explicitly. Talking to the model requires the `llm` build
+// feature and a local ollama or llama.cpp server.
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::clarify::{Clarification, Interpretation};
+use crate::nlp::Intent;
+use crate::{i18n, storage, tasks};
+
+const CONFIG_FILE: &str = "llm.json";
+const MAX_CANDIDATES: usize = 3;
+// Model suggestions always sit below the clarification threshold
+const CANDIDATE_CONFIDENCE: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+    Ollama,
+    LlamaCpp,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmConfig {
+    pub enabled: bool,
+    pub backend: Backend,
+    pub endpoint: String,
+    pub model: String,
+    pub timeout_secs: u64,
+}
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        LlmConfig {
+            enabled: false,
+            backend: Backend::Ollama,
+            endpoint: "http://127.0.0.1:11434".to_string(),
+            model: "llama3.2".to_string(),
+            timeout_secs: 20,
+        }
+    }
+}
+
+pub fn load_config() -> LlmConfig {
+    storage::load(CONFIG_FILE).unwrap_or_default()
+}
+
+fn prompt(query: &str) -> String {
+    format!(
+        r#"You translate requests about a NixOS system into JSON. Reply with JSON only, shaped as
+{{"intents": [ ... ]}} holding at most {max} objects, most likely first. Allowed objects:
+{{"kind": "install", "packages": ["<nixpkgs attribute>"]}}
+{{"kind": "remove", "packages": ["<nixpkgs attribute>"]}}
+{{"kind": "search", "query": "<text>"}}
+{{"kind": "list_installed"}}
+{{"kind": "update"}}
+{{"kind": "rollback", "generation": <number or null>}}
+{{"kind": "garbage_collect"}}
+{{"kind": "explain", "topic": "<text>"}}
+{{"kind": "configure", "setting": "<text>", "enable": <true, false or null>}}
+{{"kind": "set_default_app", "app": "<application>", "role": "<browser, mail, pdf-viewer, ...>"}}
+Reply {{"intents": []}} if the request is none of these.
+Request: {query}"#,
+        max = MAX_CANDIDATES,
+        query = query.replace('\n', " ")
+    )
+}
+
+#[cfg(feature = "llm")]
+fn complete(config: &LlmConfig, prompt: &str) -> anyhow::Result<String> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(std::time::Duration::from_secs(config.timeout_secs))
+        .build()?;
+    let endpoint = config.endpoint.trim_end_matches('/');
+    let (url, body, field) = match config.backend {
+        Backend::Ollama => (
+            format!("{}/api/generate", endpoint),
+            serde_json::json!({
+                "model": config.model,
+                "prompt": prompt,
+                "format": "json",
+                "stream": false,
+                "options": {"temperature": 0},
+            }),
+            "response",
+        ),
+        Backend::LlamaCpp => (
+            format!("{}/completion", endpoint),
+            serde_json::json!({
+                "prompt": prompt,
+                "n_predict": 256,
+                "temperature": 0,
+                "json_schema": {"type": "object", "required": ["intents"]},
+            }),
+            "content",
+        ),
+    };
+    let reply: serde_json::Value = client
+        .post(url)
+        .json(&body)
+        .send()?
+        .error_for_status()?
+        .json()?;
+    match reply.get(field).and_then(|r| r.as_str()) {
+        Some(text) => Ok(text.to_string()),
+        None => bail!("The model server replied without a '{}' field", field),
+    }
+}
+
+#[cfg(not(feature = "llm"))]
+fn complete(_config: &LlmConfig, _prompt: &str) -> anyhow::Result<String> {
+    bail!("This build does not include the local LLM backend (enable the `llm` feature)")
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 100
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._+-".contains(c))
+}
+
+fn valid_text(text: &str) -> bool {
+    !text.trim().is_empty() && text.len() <= 200 && !text.chars().any(char::is_control)
+}
+
+// Keep only objects that are exactly one of the allowed intents
+fn validate(candidate: &serde_json::Value) -> Option<Intent> {
+    let intent: Intent = serde_json::from_value(candidate.clone()).ok()?;
+    // Reject extra fields the schema does not know about
+    let known = serde_json::to_value(&intent).ok()?;
+    let fields = candidate.as_object()?;
+    if fields.keys().any(|key| known.get(key).is_none()) {
+        return None;
+    }
+    let ok = match &intent {
+        Intent::Install { packages } | Intent::Remove { packages } => {
+            !packages.is_empty() && packages.len() <= 10 && packages.iter().all(|p| valid_name(p))
+        }
+        Intent::Search { query } => valid_text(query),
+        Intent::Explain { topic } => valid_text(topic),
+        Intent::Configure { setting, .. } => valid_text(setting),
+        Intent::SetDefaultApp { app, role } => valid_text(app) && valid_name(role),
+        Intent::ListInstalled
+        | Intent::Update
+        | Intent::Rollback { .. }
+        | Intent::GarbageCollect => true,
+        // Scaffolding writes to arbitrary paths; leave that to the rule-based parser
+        Intent::ScaffoldProject { .. } | Intent::Plugin { .. } | Intent::Unknown => false,
+        // Picked from the boot manager's list, never guessed from a sentence
+        Intent::SetBootDefault { .. }
+        | Intent::DeleteGenerations { .. }
+        | Intent::SwitchSpecialisation { .. } => false,
+    };
+    ok.then_some(intent)
+}
+
+pub fn parse_reply(reply: &str) -> anyhow::Result<Vec<Intent>> {
+    let parsed: serde_json::Value = serde_json::from_str(reply.trim())?;
+    let Some(candidates) = parsed.get("intents").and_then(|i| i.as_array()) else {
+        bail!("The model reply has no intents list");
+    };
+    let mut intents: Vec<Intent> = Vec::new();
+    for intent in candidates.iter().filter_map(validate) {
+        if !intents.contains(&intent) {
+            intents.push(intent);
+        }
+    }
+    intents.truncate(MAX_CANDIDATES);
+    Ok(intents)
+}
+
+// Candidate intents from the model, or None when the fallback is off or fails
+pub fn clarification(query: &str) -> Option<Clarification> {
+    let config = load_config();
+    if !config.enabled {
+        return None;
+    }
+    let intents = complete(&config, &prompt(query))
+        .and_then(|reply| parse_reply(&reply))
+        .ok()?;
+    if intents.is_empty() {
+        return None;
+    }
+    Some(Clarification {
+        question: i18n::message("clarify-guessed", &[]),
+        interpretations: intents
+            .into_iter()
+            .map(|intent| Interpretation {
+                description: intent.describe(),
+                intent,
+                confidence: CANDIDATE_CONFIDENCE,
+            })
+            .collect(),
+    })
+}
+
+// Queries and system details must not leave the machine
+fn is_local(endpoint: &str) -> bool {
+    let Some(rest) = endpoint.strip_prefix("http://") else {
+        return false;
+    };
+    let authority = rest.split('/').next().unwrap_or("");
+    let host = match authority.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().unwrap_or(""),
+        None => authority.split(':').next().unwrap_or(""),
+    };
+    matches!(host, "127.0.0.1" | "localhost" | "::1")
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_llm_config(app: AppHandle) -> LlmConfig {
+    tasks::blocking_value(&app, "Get LLM config", |_| load_config()).await
+}
+
+#[tauri::command]
+pub async fn set_llm_config(config: LlmConfig, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set LLM config", move |_| {
+        if !is_local(&config.endpoint) {
+            return serde_json::json!({
+                "success": false,
+                "error": "Only a model server on this machine is allowed",
+            });
+        }
+        crate::respond(storage::save(CONFIG_FILE, &config))
+    })
+    .await
+}
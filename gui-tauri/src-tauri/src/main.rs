@@ -4,9 +4,114 @@
     windows_subsystem = "windows"
 )]
 
+mod a11yaudit;
+mod a11ycheck;
+mod actions;
+mod adaptation;
+mod affect;
+mod aliases;
+mod autosave;
+mod batch;
+mod boot;
+mod bootcheck;
+mod capabilities;
+mod care;
+mod clarify;
+mod clipboard;
+mod clock;
+mod cogload;
+mod components;
+mod configdiff;
+mod context;
+mod contextmenu;
+mod demo;
+mod encryption;
+mod envvars;
+mod evalpool;
+mod expertise;
+mod explain;
+mod flatpak;
+mod flow;
+mod fuzzy;
+mod glossary;
+mod globalsearch;
+mod hardware;
+mod history;
+mod homeassistant;
+mod i18n;
+mod indexdelta;
+mod inventory;
+mod jsonstream;
+mod layouts;
+mod layouteditor;
+mod levelmeter;
+mod license;
+mod llm;
+mod maintenance;
+mod maintwindows;
+mod managed;
+mod metrics;
+mod mimeapps;
+mod monitor;
+mod mounts;
+mod nix;
+mod nixconf;
+mod nixdrop;
+mod nixgen;
+mod nlp;
+mod notifications;
+mod onboarding;
+mod optimise;
+mod palette;
+mod panels;
+mod personas;
+mod plugins;
+mod power;
+mod privacy;
+mod probe;
+mod processes;
+mod profiles;
+mod progress;
+mod prosody;
+mod reminders;
+mod retry;
+mod remoteunlock;
+mod safety;
+mod sandbox;
+mod scaffold;
+mod screenshot;
+mod search;
+mod secrets;
+mod secureboot;
+mod services;
+mod sessionbundle;
+mod sessions;
+mod shortcuts;
+mod storage;
+mod swap;
+mod system;
+mod tasks;
+mod terminal;
+mod testing;
+mod themes;
+mod timers;
+mod tone;
+mod tonedetect;
+mod tray;
+mod uidriver;
+mod userprofile;
+mod userservices;
+mod voice;
+mod voiceconfirm;
+mod voicemodels;
+mod wakeword;
+mod warmeval;
+mod wellbeing;
+mod wellbeingreport;
+
 use serde::{Deserialize, Serialize};
-use std::sync::Mutex;
-use tauri::State;
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::Mutex;
 
 // Component state that can be shared between Rust and JS
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,7 +119,7 @@ struct ComponentState {
     id: String,
     component_type: String,
     state: serde_json::Value,
-    capabilities: Vec<String>,
+    capabilities: Vec<capabilities::Capability>,
 }
 
 // Layout configuration
@@ -26,33 +131,53 @@ struct Layout {
     grid: serde_json::Value,
 }
 
-// User profile for customization
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct UserProfile {
-    id: String,
-    persona: String,
-    preferences: serde_json::Value,
-    consciousness_state: f32,
-}
-
-// Application state
-struct AppState {
+// Application state, locked with tokio mutexes: `.lock().await` in async
+// commands, `blocking_lock()` in the synchronous code they run through tasks
+pub struct AppState {
     components: Mutex<Vec<ComponentState>>,
+    component_types: Mutex<components::ComponentRegistry>,
     current_layout: Mutex<Option<Layout>>,
-    user_profile: Mutex<Option<UserProfile>>,
+    user_profile: Mutex<Option<userprofile::UserProfile>>,
     interaction_history: Mutex<Vec<serde_json::Value>>,
+    profiles: Mutex<profiles::ProfileRegistry>,
+    conversation: Mutex<context::ConversationContext>,
+    confirmations: Mutex<safety::ConfirmationGate>,
+    monitor: Mutex<monitor::Monitor>,
+    plugins: Mutex<plugins::PluginRegistry>,
+}
+
+// Wrap a fallible backend result in the {"success", ...} envelope the frontend expects
+fn respond<T: Serialize>(result: anyhow::Result<T>) -> serde_json::Value {
+    match result {
+        Ok(data) => serde_json::json!({"success": true, "data": data}),
+        Err(e) => serde_json::json!({
+            "success": false,
+            "error": e.to_string(),
+            "message": tone::error(tone::current(), &e.to_string()),
+        }),
+    }
 }
 
 // ========== Tauri Commands (callable from frontend) ==========
 
 #[tauri::command]
-fn get_components(state: State<AppState>) -> Vec<ComponentState> {
-    state.components.lock().unwrap().clone()
+async fn get_components(app: AppHandle) -> Vec<ComponentState> {
+    // Type and plugin capabilities are merged in on read so unregistering drops
+    // them again; negotiating them may probe the system, so off the IPC thread
+    tasks::blocking(&app, "Components", |state| {
+        let plugins = state.plugins.blocking_lock();
+        let registry = state.component_types.blocking_lock();
+        let components = state.components.blocking_lock();
+        registry.serve(&components, &plugins)
+    })
+    .await
+    .unwrap_or_default()
 }
 
 #[tauri::command]
-fn get_component_state(id: String, state: State<AppState>) -> Option<serde_json::Value> {
-    let components = state.components.lock().unwrap();
+async fn get_component_state(id: String, app: AppHandle) -> Option<serde_json::Value> {
+    let state = app.state::<AppState>();
+    let components = state.components.lock().await;
     components
         .iter()
         .find(|c| c.id == id)
@@ -60,89 +185,666 @@ fn get_component_state(id: String, state: State<AppState>) -> Option<serde_json:
 }
 
 #[tauri::command]
-fn set_component_state(
-    id: String,
-    new_state: serde_json::Value,
-    state: State<AppState>,
-) -> bool {
-    let mut components = state.components.lock().unwrap();
-    if let Some(component) = components.iter_mut().find(|c| c.id == id) {
+async fn set_component_state(id: String, new_state: serde_json::Value, app: AppHandle) -> bool {
+    let handle = app.clone();
+    tasks::blocking_value(&app, "Component state", move |state| {
+        let mut components = state.components.blocking_lock();
+        let Some(component) = components.iter_mut().find(|c| c.id == id) else {
+            return false;
+        };
         component.state = new_state;
-        return true;
-    }
-    false
+        // To the main window, or the panel it is detached into
+        panels::emit(
+            &handle,
+            &id,
+            "component-state",
+            serde_json::json!({"id": id, "state": component.state}),
+        );
+        if testing::recording() {
+            let action = testing::Action::SetComponentState {
+                component_id: id,
+                state: component.state.clone(),
+            };
+            let snapshot = testing::Snapshot {
+                components: components.clone(),
+                current_layout: state.current_layout.blocking_lock().clone(),
+            };
+            testing::record(action, &serde_json::json!(true), snapshot);
+        }
+        true
+    })
+    .await
 }
 
-#[tauri::command]
-fn perform_action(action: String, params: serde_json::Value) -> serde_json::Value {
-    // Handle high-level actions
-    match action.as_str() {
-        "search" => {
-            // Simulate search
-            serde_json::json!({
+// Carry out a typed intent; `options` holds flags such as override_license,
+// declarative, init_git and an explicit profile id
+fn execute_intent(
+    intent: &nlp::Intent,
+    options: &serde_json::Value,
+    state: &State<AppState>,
+) -> serde_json::Value {
+    let flag = |name: &str| options.get(name).and_then(|v| v.as_bool()).unwrap_or(false);
+    // Package intents target the profile named in options, or the active one
+    let profile = || {
+        state
+            .profiles
+            .blocking_lock()
+            .resolve(options.get("profile").and_then(|p| p.as_str()))
+    };
+
+    match intent {
+        nlp::Intent::Search { query } => match search::search(query) {
+            Ok(outcome) => {
+                // Keep every ResultsList in sync, including the suggestions
+                for component in state.components.blocking_lock().iter_mut() {
+                    if component.component_type == "ResultsList" {
+                        component.state = serde_json::json!({
+                            "results": outcome.results,
+                            "did_you_mean": outcome.did_you_mean,
+                        });
+                    }
+                }
+                serde_json::json!({
+                    "success": true,
+                    "results": outcome.results,
+                    "did_you_mean": outcome.did_you_mean,
+                })
+            }
+            Err(e) => serde_json::json!({"success": false, "error": e.to_string()}),
+        },
+        nlp::Intent::Install { packages } => {
+            if packages.is_empty() {
+                return serde_json::json!({
+                    "success": false,
+                    "error": i18n::message("error-no-package", &[]),
+                });
+            }
+            if !flag("override_license") {
+                for package in packages {
+                    match license::check(package) {
+                        Ok(verdict) if !verdict.allowed => {
+                            return serde_json::json!({
+                                "success": false,
+                                "needs_override": true,
+                                "license_warning": verdict,
+                            })
+                        }
+                        Err(e) => {
+                            return serde_json::json!({"success": false, "error": e.to_string()})
+                        }
+                        Ok(_) => {}
+                    }
+                }
+            }
+            respond(profile().and_then(|p| {
+                let mut reporter = progress::Reporter::start(
+                    "install",
+                    &intent.describe(),
+                    Some(packages.len() as u64),
+                    false,
+                );
+                let result = packages
+                    .iter()
+                    .enumerate()
+                    .map(|(index, package)| {
+                        reporter.advance(index as u64, &format!("Installing {}", package));
+                        profiles::install(&p, package)
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>();
+                reporter.finish(&result, &intent.describe());
+                result
+            }))
+        }
+        nlp::Intent::Remove { packages } => {
+            if packages.is_empty() {
+                return serde_json::json!({
+                    "success": false,
+                    "error": i18n::message("error-no-package", &[]),
+                });
+            }
+            respond(profile().and_then(|p| {
+                let mut reporter = progress::Reporter::start(
+                    "remove",
+                    &intent.describe(),
+                    Some(packages.len() as u64),
+                    false,
+                );
+                let result = packages
+                    .iter()
+                    .enumerate()
+                    .map(|(index, package)| {
+                        reporter.advance(index as u64, &format!("Removing {}", package));
+                        profiles::remove(&p, package)
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>();
+                reporter.finish(&result, &intent.describe());
+                result
+            }))
+        }
+        nlp::Intent::ListInstalled => respond(profile().and_then(|p| profiles::list(&p))),
+        // Several privileged steps each, so other sessions wait for all of them
+        nlp::Intent::Update => respond(sessions::exclusive(
+            &intent.describe(),
+            maintenance::update_system,
+        )),
+        nlp::Intent::Rollback { generation } => {
+            respond(sessions::exclusive(&intent.describe(), || {
+                boot::rollback(*generation)
+            }))
+        }
+        nlp::Intent::GarbageCollect => respond(sessions::exclusive(&intent.describe(), || {
+            maintenance::collect_garbage("30d")
+        })),
+        nlp::Intent::SetBootDefault { generation } => {
+            respond(sessions::exclusive(&intent.describe(), || {
+                boot::set_default_generation(*generation)
+            }))
+        }
+        nlp::Intent::DeleteGenerations { generations } => {
+            respond(sessions::exclusive(&intent.describe(), || {
+                boot::delete_generations(generations)
+            }))
+        }
+        nlp::Intent::SwitchSpecialisation { name } => {
+            respond(sessions::exclusive(&intent.describe(), || {
+                boot::switch_specialisation(name.as_deref())
+            }))
+        }
+        nlp::Intent::Explain { topic } => match glossary::find(topic) {
+            Some(topic) => serde_json::json!({
                 "success": true,
-                "results": [
-                    {"name": "firefox", "description": "Web browser"},
-                    {"name": "vim", "description": "Text editor"}
-                ]
-            })
-        }
-        "install" => {
-            let package = params.get("package").and_then(|p| p.as_str()).unwrap_or("");
-            serde_json::json!({
+                "data": expertise::explain_topic(state, topic),
+            }),
+            // "explain nix-collect-garbage -d" or a pasted option assignment
+            None if explain::detect(topic) != explain::SnippetKind::Unknown => serde_json::json!({
                 "success": true,
-                "message": format!("Would install {}", package)
-            })
+                "data": expertise::explain_snippet(state, explain::breakdown(topic)),
+            }),
+            None => serde_json::json!({
+                "success": false,
+                "error": i18n::message("error-no-explanation", &[("topic", topic)]),
+            }),
+        },
+        nlp::Intent::Configure { setting, enable } => match (setting.as_str(), enable) {
+            ("flakes" | "nix flakes", Some(true) | None) => {
+                respond(nixconf::enable_flakes(flag("user_level")))
+            }
+            _ => serde_json::json!({
+                "success": false,
+                "error": i18n::message("error-cannot-configure", &[("setting", setting)]),
+            }),
+        },
+        nlp::Intent::SetDefaultApp { app, role } => {
+            let declarative = flag("declarative");
+            if role.contains('/') {
+                respond(mimeapps::set_default(app, &[role.as_str()], declarative))
+            } else {
+                respond(mimeapps::set_default_for_role(role, app, declarative))
+            }
         }
-        _ => serde_json::json!({"success": false, "error": "Unknown action"}),
+        nlp::Intent::ScaffoldProject { template, path } => match path {
+            Some(path) => scaffold::scaffold_project(
+                template.clone(),
+                path.clone(),
+                None,
+                options.get("init_git").and_then(|g| g.as_bool()),
+            ),
+            None => serde_json::json!({
+                "success": false,
+                "error": i18n::message("error-scaffold-path", &[]),
+            }),
+        },
+        nlp::Intent::Plugin {
+            plugin,
+            action,
+            args,
+        } => respond(state.plugins.blocking_lock().handle(plugin, action, args)),
+        nlp::Intent::Unknown => serde_json::json!({
+            "success": false,
+            "error": i18n::message("error-unknown-action", &[]),
+        }),
+    }
+}
+
+// The path every intent takes, whether it was typed, came from a plan, a care
+// session, Home Assistant or a button: admit, then dispatch. run_intent does
+// both for a single intent; plans admit all their steps at once and dispatch
+// them one by one.
+
+// The managed policy, then the confirmation policy for the blast radius of
+// `intents` together. Err is the response to send instead: a refusal or a
+// request for confirmation
+pub fn admit(
+    intents: &[nlp::Intent],
+    options: &serde_json::Value,
+    state: &State<AppState>,
+) -> Result<(), serde_json::Value> {
+    // Refused before asking for confirmation of something that can't run anyway
+    if let Some(refusal) = intents.iter().find_map(managed::refusal) {
+        return Err(refusal);
+    }
+    safety::guard(&mut state.confirmations.blocking_lock(), intents, options)
+}
+
+// Run an admitted intent, unless it is disruptive and has to wait for a
+// maintenance window; then the response says it was staged
+pub fn dispatch(
+    intent: nlp::Intent,
+    options: &serde_json::Value,
+    state: &State<AppState>,
+) -> serde_json::Value {
+    // Nothing real happens in a demo, so there's nothing to wait for
+    if !demo::active() {
+        if let Some(staged) = maintwindows::stage_if_closed(&intent, options) {
+            return staged;
+        }
+    }
+    perform_intent(intent, options, state)
+}
+
+// Execute an intent once the policies for it are met
+pub fn run_intent(
+    intent: nlp::Intent,
+    options: &serde_json::Value,
+    state: &State<AppState>,
+) -> serde_json::Value {
+    match admit(std::slice::from_ref(&intent), options, state) {
+        Ok(()) => dispatch(intent, options, state),
+        Err(response) => response,
+    }
+}
+
+// Execute an already-confirmed intent and remember it for follow-up questions
+pub fn perform_intent(
+    intent: nlp::Intent,
+    options: &serde_json::Value,
+    state: &State<AppState>,
+) -> serde_json::Value {
+    // Staged operations come straight here when their window opens
+    if let Some(refusal) = managed::refusal(&intent) {
+        return refusal;
+    }
+    // A demo answers from its pretend system instead
+    let mut response =
+        demo::execute(&intent, state).unwrap_or_else(|| execute_intent(&intent, options, state));
+    tone::apply(&mut response, &intent.describe());
+    state
+        .conversation
+        .blocking_lock()
+        .remember(&intent, &response);
+    if demo::active() {
+        return response;
+    }
+    if privacy::allowed(privacy::Collector::History) {
+        let _ = history::record(&intent, &response);
+    }
+    if privacy::allowed(privacy::Collector::ExpertiseTracking) {
+        expertise::observe(state, &intent, &response);
     }
+    response
 }
 
+// Run a past action again, or its opposite (remove what was installed)
 #[tauri::command]
-fn switch_layout(layout_id: String, state: State<AppState>) -> bool {
-    // In real implementation, would load layout from storage
-    let new_layout = Layout {
-        id: layout_id.clone(),
-        name: format!("Layout {}", layout_id),
-        components: vec![],
-        grid: serde_json::json!({"template": "1fr / 1fr"}),
+async fn rerun_history_entry(
+    id: u64,
+    undo: Option<bool>,
+    options: Option<serde_json::Value>,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Run again", move |state| {
+        rerun(id, undo, options, state)
+    })
+    .await
+}
+
+fn rerun(
+    id: u64,
+    undo: Option<bool>,
+    options: Option<serde_json::Value>,
+    state: &State<AppState>,
+) -> serde_json::Value {
+    let entry = match history::find(id) {
+        Ok(entry) => entry,
+        Err(e) => return serde_json::json!({"success": false, "error": e.to_string()}),
     };
-    
-    *state.current_layout.lock().unwrap() = Some(new_layout);
-    true
+    let intent = if undo.unwrap_or(false) {
+        match history::undo_for(&entry.intent) {
+            Some(intent) => intent,
+            None => {
+                return serde_json::json!({
+                    "success": false,
+                    "error": format!("\"{}\" can't be undone from here", entry.description),
+                })
+            }
+        }
+    } else {
+        entry.intent
+    };
+    run_intent(intent, &options.unwrap_or_default(), state)
+}
+
+#[tauri::command]
+async fn perform_action(
+    action: String,
+    params: serde_json::Value,
+    app: AppHandle,
+) -> serde_json::Value {
+    let Some(intent) = nlp::from_action(&action, &params) else {
+        return serde_json::json!({
+            "success": false,
+            "error": i18n::message("error-unknown-action", &[]),
+        });
+    };
+    tasks::blocking_json(&app, &intent.describe(), move |state| {
+        run_intent(intent, &params, state)
+    })
+    .await
 }
 
 #[tauri::command]
-fn customize_theme(tokens: serde_json::Value) -> bool {
-    // Theme customization would be applied here
-    println!("Applying theme: {:?}", tokens);
+async fn parse_intent(query: String, app: AppHandle) -> nlp::ParsedIntent {
+    // Aliases and the language come from disk
+    tasks::blocking_value(&app, "Parse", move |_| nlp::parse(&query)).await
+}
+
+// Carry out the interpretation the user picked from a clarification
+#[tauri::command]
+async fn resolve_clarification(
+    intent: nlp::Intent,
+    query: Option<String>,
+    options: Option<serde_json::Value>,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, &intent.describe(), move |state| {
+        // Phrasings that keep needing clarification become alias suggestions
+        if privacy::allowed(privacy::Collector::PhrasingLearning) {
+            if let Some(query) = &query {
+                let _ = aliases::observe(query, &intent);
+            }
+        }
+        run_intent(intent, &options.unwrap_or_default(), state)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn get_context(app: AppHandle) -> context::ConversationContext {
+    app.state::<AppState>().conversation.lock().await.clone()
+}
+
+// Forget earlier turns so references start fresh
+#[tauri::command]
+async fn reset_context(app: AppHandle) -> bool {
+    *app.state::<AppState>().conversation.lock().await = context::ConversationContext::default();
     true
 }
 
+// Parse free text and carry out the resulting intent
 #[tauri::command]
-fn adapt_to_user_state(user_state: serde_json::Value, state: State<AppState>) -> serde_json::Value {
-    let cognitive_load = user_state
+async fn process_query(
+    query: String,
+    options: Option<serde_json::Value>,
+    app: AppHandle,
+) -> serde_json::Value {
+    let label = query.clone();
+    tasks::blocking_json(&app, &label, move |state| {
+        // Before answering, so a style switch already applies to this response
+        let style_switch = if privacy::allowed(privacy::Collector::StyleDetection) {
+            tonedetect::observe(state, &query)
+        } else {
+            None
+        };
+        let action = testing::recording().then(|| testing::Action::Query {
+            query: query.clone(),
+            options: options.clone(),
+            confirm: false,
+        });
+        let mut response = answer_query(query, options, state);
+        if let Some(action) = action {
+            testing::record(action, &response, testing::snapshot(state));
+        }
+        if let Some(style_switch) = style_switch {
+            response["personality_switch"] = serde_json::json!(style_switch);
+        }
+        response
+    })
+    .await
+}
+
+pub fn answer_query(
+    query: String,
+    options: Option<serde_json::Value>,
+    state: &State<AppState>,
+) -> serde_json::Value {
+    // Aliases come first: a saved action list, or package names to substitute
+    let query = match aliases::expand(&query) {
+        aliases::Expansion::Intents(intents) if intents.len() == 1 => {
+            let options = options.unwrap_or_default();
+            return run_intent(intents[0].clone(), &options, state);
+        }
+        aliases::Expansion::Intents(intents) => {
+            return serde_json::json!({
+                "success": false,
+                "needs_confirmation": true,
+                "plan": batch::from_intents(&query, intents),
+            });
+        }
+        aliases::Expansion::Rewritten(text) => text,
+        aliases::Expansion::Unchanged => query,
+    };
+    if reminders::is_request(&query) {
+        return match reminders::add_from_text(&query) {
+            Ok(reminder) => serde_json::json!({
+                "success": true,
+                "reminder": reminder,
+                "when": reminder.trigger.describe(),
+            }),
+            Err(e) => serde_json::json!({"success": false, "error": e.to_string()}),
+        };
+    }
+    // Compound requests are confirmed as a whole before anything runs
+    if let Some(plan) = batch::plan(&query) {
+        return serde_json::json!({
+            "success": false,
+            "needs_confirmation": true,
+            "plan": plan,
+        });
+    }
+    // Plugin phrases match whole queries and take precedence over the built-in verbs
+    let plugin_intent = state.plugins.blocking_lock().parse(&query);
+    if let Some(intent) = plugin_intent {
+        let options = options.unwrap_or_default();
+        let mut response = run_intent(intent.clone(), &options, state);
+        response["intent"] = serde_json::json!(nlp::ParsedIntent {
+            intent,
+            entities: nlp::Entities::default(),
+            confidence: 1.0,
+            raw: query,
+        });
+        return response;
+    }
+    let readings = nlp::parse_all(&query);
+    let mut parsed = readings.first().cloned().unwrap_or_else(|| nlp::parse(&query));
+    // The model only proposes; the user still has to pick an interpretation
+    if parsed.intent == nlp::Intent::Unknown {
+        if let Some(clarification) = llm::clarification(&query) {
+            return serde_json::json!({
+                "success": false,
+                "needs_clarification": true,
+                "clarification": clarification,
+                "intent": parsed,
+            });
+        }
+    }
+    // "install it" / "the second result" refer back to earlier turns
+    if let Err(e) = state.conversation.blocking_lock().resolve(&mut parsed.intent) {
+        return serde_json::json!({
+            "success": false,
+            "needs_clarification": true,
+            "error": e.to_string(),
+            "intent": parsed,
+        });
+    }
+    if let Some(clarification) = clarify::check(&parsed, &readings) {
+        return serde_json::json!({
+            "success": false,
+            "needs_clarification": true,
+            "clarification": clarification,
+            "intent": parsed,
+        });
+    }
+    let mut options = options.unwrap_or_else(|| serde_json::json!({}));
+    if let (Some(profile), Some(map)) = (&parsed.entities.profile, options.as_object_mut()) {
+        map.entry("profile").or_insert_with(|| profile.clone().into());
+    }
+    let mut response = run_intent(parsed.intent.clone(), &options, state);
+    response["intent"] = serde_json::json!(parsed);
+    response
+}
+
+// Switch to a saved layout, or a preset by id, alias or persona ("persona" for the current one)
+#[tauri::command]
+async fn switch_layout(layout_id: String, app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Switch layout", move |state| {
+        let persona = current_persona(state);
+        let response = respond(layouts::switch(state, &layout_id, persona));
+        if testing::recording() {
+            let action = testing::Action::SwitchLayout { layout_id };
+            testing::record(action, &response, testing::snapshot(state));
+        }
+        // Panels follow the components of the new layout
+        panels::reconcile(&handle, &state.components.blocking_lock());
+        response
+    })
+    .await
+}
+
+fn current_persona(state: &AppState) -> &'static personas::Persona {
+    let profile = state.user_profile.blocking_lock();
+    personas::resolve(profile.as_ref().map(|p| p.persona.as_str()))
+}
+
+#[tauri::command]
+async fn set_persona(persona_id: String, app: AppHandle) -> serde_json::Value {
+    let Some(persona) = personas::find(&persona_id) else {
+        return serde_json::json!({
+            "success": false,
+            "error": format!("Unknown persona '{}'", persona_id),
+        });
+    };
+    let state = app.state::<AppState>();
+    let mut profile = state.user_profile.lock().await;
+    let profile = profile.get_or_insert_with(userprofile::UserProfile::default);
+    profile.persona = persona.id.to_string();
+    respond(userprofile::save(profile).map(|()| persona))
+}
+
+// Checked against the token schema, then kept and applied to every window
+#[tauri::command]
+async fn customize_theme(tokens: serde_json::Value, app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Theme", move |_| {
+        let theme = themes::validate(&tokens).and_then(|tokens| {
+            themes::set(
+                &handle,
+                themes::Theme {
+                    tokens,
+                    ..themes::active()
+                },
+            )
+        });
+        respond(theme)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn adapt_to_user_state(user_state: serde_json::Value, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Adapt", move |state| adapt(user_state, state)).await
+}
+
+fn adapt(user_state: serde_json::Value, state: &State<AppState>) -> serde_json::Value {
+    let now_ms = clock::now_ms();
+    let local_secs = (now_ms / 1000) as i64 + history::local_offset();
+    let local_hour = local_secs.rem_euclid(86_400) as u32 / 3600;
+    let estimate = cogload::estimate(&state.interaction_history.blocking_lock(), now_ms, local_hour);
+    let reported = user_state
         .get("cognitive_load")
         .and_then(|v| v.as_f64())
-        .unwrap_or(0.5);
+        .map(|v| v as f32);
+    let cognitive_load = cogload::blend(&estimate, reported);
+    let frustration = affect::assess(&state.interaction_history.blocking_lock()).frustration;
+    let persona = match user_state.get("persona").and_then(|v| v.as_str()) {
+        Some(id) => personas::resolve(Some(id)),
+        None => current_persona(state),
+    };
     
-    let mut adaptations = serde_json::json!({});
+    let mut adaptations = serde_json::json!({
+        "persona": persona.id,
+        "layout": persona.layout,
+        // Pinned with set_verbosity, or the persona's
+        "verbosity": expertise::current(state).verbosity.unwrap_or(persona.verbosity),
+        "font_scale": persona.font_scale,
+        "confirmation": persona.confirmation,
+        "pacing": persona.pacing,
+        "response_delay_ms": persona.response_delay_ms(),
+        "voice_first": persona.voice_first,
+        "plain_language": persona.plain_language,
+        "max_choices": persona.max_choices(),
+        "animations": persona.animations(),
+        "suggestion_interval_secs": 0,
+        // Suggestions and update prompts wait for a break while the user is in flow
+        "defer_suggestions": flow::in_flow(),
+        "cognitive_load": cognitive_load,
+        "load_estimate": estimate,
+    });
     
-    if cognitive_load > 0.8 {
-        // Simplify interface
-        adaptations["layout"] = serde_json::json!("minimal");
-        adaptations["font_size_increase"] = serde_json::json!(1.2);
+    // What rule conditions can refer to, on top of whatever the frontend sent
+    let mut metrics = user_state.clone();
+    if let Some(map) = metrics.as_object_mut() {
+        map.insert("cognitive_load".to_string(), cognitive_load.into());
+        map.insert("frustration".to_string(), frustration.into());
+        map.insert("local_hour".to_string(), local_hour.into());
+        map.insert("in_flow".to_string(), flow::in_flow().into());
     }
+    let applied = adaptation::apply(&adaptation::load(), &metrics, &mut adaptations);
+    let _ = adaptation::log(&applied);
+    adaptations["applied_rules"] = serde_json::json!(applied);
     
     adaptations
 }
 
 #[tauri::command]
-fn record_interaction(
+async fn record_interaction(
     interaction: serde_json::Value,
-    state: State<AppState>,
+    keystrokes: Option<affect::KeystrokeTiming>,
+    app: AppHandle,
 ) {
-    let mut history = state.interaction_history.lock().unwrap();
+    let state = app.state::<AppState>();
+    remember_interaction(
+        interaction,
+        keystrokes,
+        &mut *state.interaction_history.lock().await,
+    );
+}
+
+pub fn remember_interaction(
+    mut interaction: serde_json::Value,
+    keystrokes: Option<affect::KeystrokeTiming>,
+    history: &mut Vec<serde_json::Value>,
+) {
+    if let Some(map) = interaction.as_object_mut() {
+        map.entry("timestamp_ms")
+            .or_insert_with(|| clock::now_ms().into());
+        // Timing and counts only; the frontend never sends the keys themselves
+        if privacy::allowed(privacy::Collector::TypingAnalysis) {
+            if let Some(keystrokes) = keystrokes {
+                map.insert("keystrokes".to_string(), serde_json::json!(keystrokes));
+            }
+        }
+    }
     history.push(interaction);
     
     // Keep only last 1000 interactions
@@ -152,8 +854,9 @@ fn record_interaction(
 }
 
 #[tauri::command]
-fn get_interaction_patterns(state: State<AppState>) -> serde_json::Value {
-    let history = state.interaction_history.lock().unwrap();
+async fn get_interaction_patterns(app: AppHandle) -> serde_json::Value {
+    let state = app.state::<AppState>();
+    let history = state.interaction_history.lock().await;
     
     // Analyze patterns (simplified)
     let total = history.len();
@@ -171,77 +874,536 @@ fn get_interaction_patterns(state: State<AppState>) -> serde_json::Value {
 
 // AI Testing Interface Commands
 
+// Click a component, or the element matching `selector` inside it
 #[tauri::command]
-fn ai_click(component_id: String) -> bool {
-    println!("AI clicked: {}", component_id);
-    true
+async fn ai_click(
+    component_id: String,
+    selector: Option<String>,
+    app: AppHandle,
+) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Click", move |state| {
+        let response = respond(uidriver::click(&handle, &component_id, selector.as_deref()));
+        if testing::recording() {
+            let action = testing::Action::Click {
+                component_id,
+                selector,
+            };
+            testing::record(action, &response, testing::snapshot(state));
+        }
+        response
+    })
+    .await
 }
 
 #[tauri::command]
-fn ai_type(component_id: String, text: String) -> bool {
-    println!("AI typed '{}' into {}", text, component_id);
-    true
+async fn ai_type(component_id: String, text: String, app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Type", move |state| {
+        let response = respond(uidriver::type_text(&handle, &component_id, &text));
+        if testing::recording() {
+            let action = testing::Action::Type { component_id, text };
+            testing::record(action, &response, testing::snapshot(state));
+        }
+        response
+    })
+    .await
 }
 
+// Wait until `selector` meets the condition, in the main window or `window`
 #[tauri::command]
-fn ai_get_screenshot() -> Vec<u8> {
-    // Would capture actual screenshot
-    vec![0u8; 100]  // Dummy data
+async fn ai_wait_for(
+    selector: String,
+    condition: uidriver::Condition,
+    timeout_ms: Option<u64>,
+    window: Option<String>,
+    app: AppHandle,
+) -> serde_json::Value {
+    let handle = app.clone();
+    let timeout = std::time::Duration::from_millis(timeout_ms.unwrap_or(5000));
+    tasks::blocking_json(&app, "Wait for the page", move |_| {
+        let window = window.as_deref().unwrap_or("main");
+        respond(uidriver::wait_for(
+            &handle, window, &selector, &condition, timeout,
+        ))
+    })
+    .await
+}
+
+#[tauri::command]
+async fn ai_get_text(component_id: String, app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Read text", move |_| {
+        respond(uidriver::text(&handle, &component_id))
+    })
+    .await
 }
 
+// The fields of `expected` against the component's state in the backend
 #[tauri::command]
-fn ai_validate_accessibility() -> serde_json::Value {
+async fn ai_assert_state(
+    component_id: String,
+    expected: serde_json::Value,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Assert state", move |state| {
+        respond(uidriver::assert_state(state, &component_id, &expected))
+    })
+    .await
+}
+
+// A PNG of the main window, or of one component with `component_id`, as raw
+// bytes so it reaches the page as an ArrayBuffer rather than a JSON array. The
+// size, time and region come first, in the header screenshot::encode describes
+#[tauri::command]
+async fn ai_get_screenshot(
+    component_id: Option<String>,
+    app: AppHandle,
+) -> Result<tauri::ipc::Response, String> {
+    let handle = app.clone();
+    tasks::blocking(&app, "Screenshot", move |_| {
+        screenshot::capture(&handle, component_id.as_deref())
+    })
+    .await
+    .and_then(|shot| shot)
+    .and_then(|shot| screenshot::encode(&shot))
+    .map(tauri::ipc::Response::new)
+    .map_err(|e| e.to_string())
+}
+
+// The self-test of theme, layout and persona, and an axe-core audit of what
+// the windows actually show
+#[tauri::command]
+async fn ai_validate_accessibility(app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Accessibility check", move |state| {
+        validate_accessibility(&handle, state)
+    })
+    .await
+}
+
+fn validate_accessibility(app: &AppHandle, state: &State<AppState>) -> serde_json::Value {
+    let report = a11ycheck::run(state, current_persona(state));
+    let audit = a11yaudit::run(app, report.level == "AAA");
+    // A rule the pages break counts once, however many elements break it
+    let mut rules = std::collections::HashSet::new();
+    let severities: Vec<a11ycheck::Severity> = report
+        .issues
+        .iter()
+        .map(|i| i.severity)
+        .chain(
+            audit
+                .iter()
+                .flat_map(|a| &a.issues)
+                .filter(|i| rules.insert(i.rule.as_str()))
+                .map(|i| i.severity),
+        )
+        .collect();
+    let errors = severities
+        .iter()
+        .filter(|s| **s == a11ycheck::Severity::Error)
+        .count();
+    let warnings = severities.len() - errors;
+    let compliance = match &audit {
+        Ok(_) if errors == 0 => report.level.as_str(),
+        Ok(_) => "none",
+        // Without looking at the pages there is no telling
+        Err(_) => "unverified",
+    };
     serde_json::json!({
-        "score": 95,
-        "issues": [],
-        "wcag_compliance": "AAA"
+        "score": 100usize.saturating_sub(errors * 10 + warnings * 3),
+        "wcag_compliance": compliance,
+        "level": report.level,
+        "issues": report.issues,
+        "violations": audit.as_ref().ok().map(|a| &a.issues),
+        "components": audit.as_ref().ok().map(|a| a.by_component()),
+        "axe_version": audit.as_ref().ok().map(|a| &a.axe_version),
+        "audit_error": audit.as_ref().err().map(|e| format!("{:#}", e)),
     })
 }
 
 fn main() {
+    let plugins = plugins::PluginRegistry::load();
+    let component_types = components::ComponentRegistry::load(&plugins);
     let app_state = AppState {
         components: Mutex::new(vec![
-            ComponentState {
-                id: "search-1".to_string(),
-                component_type: "SearchInput".to_string(),
-                state: serde_json::json!({"value": "", "suggestions": []}),
-                capabilities: vec!["search".to_string(), "voice".to_string()],
-            },
-            ComponentState {
-                id: "results-1".to_string(),
-                component_type: "ResultsList".to_string(),
-                state: serde_json::json!({"results": []}),
-                capabilities: vec!["display".to_string(), "sort".to_string()],
-            },
+            components::builtin_named("SearchInput").instance("search-1"),
+            components::builtin_named("ResultsList").instance("results-1"),
         ]),
+        component_types: Mutex::new(component_types),
         current_layout: Mutex::new(None),
-        user_profile: Mutex::new(None),
+        user_profile: Mutex::new(userprofile::load().unwrap_or_else(|e| {
+            eprintln!("Could not load the user profile: {}", e);
+            None
+        })),
         interaction_history: Mutex::new(Vec::new()),
+        profiles: Mutex::new(profiles::ProfileRegistry::load()),
+        conversation: Mutex::new(context::ConversationContext::default()),
+        confirmations: Mutex::new(safety::ConfirmationGate::default()),
+        monitor: Mutex::new(monitor::Monitor::default()),
+        plugins: Mutex::new(plugins),
     };
-    
+    // Pick up the workspace where the last session left it, unless it crashed
+    if let Some(snapshot) = autosave::start_session() {
+        autosave::apply(&app_state, snapshot);
+    }
+
+    power::start_sampler();
+
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .plugin(tauri_plugin_websocket::init())
         .manage(app_state)
+        // The saved theme goes on every page as it loads, from the first one
+        .on_page_load(|webview, payload| {
+            if payload.event() == tauri::webview::PageLoadEvent::Finished {
+                themes::apply(webview);
+            }
+        })
+        // In auto mode the theme follows the desktop's colour scheme; window
+        // geometry is kept for the next start, focus after a completion
+        // notification leads to what it was about, and dropped .nix files are
+        // analysed
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::ThemeChanged(theme) = event {
+                themes::system_changed(window.app_handle(), *theme);
+            }
+            panels::window_event(window, event);
+            notifications::window_event(window, event);
+            nixdrop::window_event(window, event);
+        })
+        .setup(|app| {
+            progress::init(app.handle().clone());
+            // Detached panels reopen where they were
+            panels::restore(
+                app.handle(),
+                &app.state::<AppState>().components.blocking_lock(),
+            );
+            notifications::init(app.handle());
+            autosave::start(app.handle().clone());
+            // A crashed session's workspace is offered rather than restored
+            if let Some(offer) = autosave::recovery() {
+                flow::notify(app.handle(), "session-recovery", offer, false);
+            }
+            if let Err(e) = tray::init(app.handle()) {
+                eprintln!("Could not create the tray icon: {}", e);
+            }
+            if let Err(e) = sessions::register() {
+                eprintln!("Could not register the session: {}", e);
+            }
+            // Report failed or rolled-back boots once the window is up
+            let handle = app.handle().clone();
+            std::thread::spawn(move || {
+                if bootcheck::acknowledged() {
+                    return;
+                }
+                let report = bootcheck::analyze();
+                if !report.findings.is_empty() {
+                    flow::notify(&handle, "boot-check", report, false);
+                }
+            });
+            // Offer the weekly care session when it is due (opt-in)
+            if care::should_invite() {
+                flow::notify(app.handle(), "care-invitation", care::overview(), false);
+            }
+            reminders::start_watcher(app.handle().clone());
+            flow::start_watcher(app.handle().clone());
+            wellbeing::start_watcher(app.handle().clone());
+            clipboard::start_watcher(app.handle().clone());
+            maintwindows::start_scheduler(app.handle().clone());
+            metrics::start_exporter();
+            if let Err(e) = homeassistant::start(app.handle()) {
+                eprintln!("Could not connect to Home Assistant: {}", e);
+            }
+            if let Err(e) = wakeword::start(app.handle()) {
+                eprintln!("Could not start listening for the wake word: {}", e);
+            }
+            // Check the theme, layout and persona in use together (can be turned off)
+            if a11ycheck::settings().on_startup {
+                let state = app.state::<AppState>();
+                let report = a11ycheck::run(&state, current_persona(&state));
+                if !report.issues.is_empty() {
+                    flow::notify(app.handle(), "accessibility-issues", report, false);
+                }
+            }
+            shortcuts::register_global(app.handle());
+            // Load nixpkgs ahead of the first search or lookup
+            warmeval::start();
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             get_components,
+            actions::list_actions,
+            palette::palette_search,
+            panels::detach_component,
+            panels::dock_component,
+            panels::list_detached_panels,
+            panels::send_component_event,
+            autosave::get_session_recovery,
+            autosave::restore_last_session,
+            autosave::discard_last_session,
+            components::list_component_types,
+            components::register_component_type,
+            components::unregister_component_type,
             get_component_state,
             set_component_state,
             perform_action,
+            rerun_history_entry,
+            parse_intent,
+            process_query,
+            resolve_clarification,
+            get_context,
+            reset_context,
             switch_layout,
+            layouts::list_layout_presets,
+            layouts::save_layout_preset,
+            layouts::reset_layout_presets,
+            layouts::list_layouts,
+            layouts::save_layout,
+            layouts::delete_layout,
+            layouteditor::begin_layout_edit,
+            layouteditor::move_component,
+            layouteditor::add_component,
+            layouteditor::remove_component,
+            layouteditor::end_layout_edit,
+            set_persona,
+            personas::list_personas,
+            onboarding::get_onboarding,
+            onboarding::answer_onboarding_step,
+            onboarding::finish_onboarding,
+            onboarding::restart_onboarding,
+            userprofile::export_profile,
+            userprofile::import_profile,
+            sessionbundle::export_session,
+            sessionbundle::import_session,
             customize_theme,
+            themes::get_theme,
+            themes::get_effective_theme,
+            themes::set_theme_mode,
+            themes::get_theme_schema,
+            themes::export_theme,
+            themes::import_theme,
             adapt_to_user_state,
             record_interaction,
             get_interaction_patterns,
+            affect::get_affect_state,
+            adaptation::get_adaptation_rules,
+            adaptation::set_adaptation_rules,
+            adaptation::get_adaptation_log,
             ai_click,
             ai_type,
+            ai_wait_for,
+            ai_get_text,
+            ai_assert_state,
+            testing::start_test_recording,
+            testing::stop_test_recording,
+            testing::list_test_scenarios,
+            testing::replay_test_scenario,
             ai_get_screenshot,
+            probe::probe_result,
             ai_validate_accessibility,
+            a11ycheck::run_accessibility_selftest,
+            a11ycheck::get_accessibility_selftest,
+            a11ycheck::set_accessibility_selftest,
+            mimeapps::list_default_apps,
+            mimeapps::list_desktop_apps,
+            mimeapps::set_default_app,
+            mimeapps::set_default_app_for_role,
+            hardware::scan_hardware,
+            hardware::apply_hardware_recommendations,
+            boot::list_boot_entries,
+            boot::set_default_boot_generation,
+            boot::delete_boot_generations,
+            boot::switch_to_specialisation,
+            flatpak::find_native_equivalents,
+            flatpak::manage_flatpak_declaratively,
+            inventory::get_inventory,
+            envvars::list_env_vars,
+            envvars::explain_env_var,
+            envvars::add_env_var,
+            envvars::remove_env_var,
+            evalpool::get_eval_pool_status,
+            explain::explain,
+            explain::explain_nix_error,
+            expertise::get_expertise,
+            expertise::set_verbosity,
+            expertise::reset_expertise,
+            contextmenu::get_context_actions,
+            configdiff::compare_configs,
+            nixdrop::analyze_nix_file,
+            nixdrop::merge_nix_file,
+            flow::get_flow_state,
+            search::refresh_package_index,
+            shortcuts::list_shortcuts,
+            shortcuts::set_shortcut,
+            shortcuts::reset_shortcuts,
+            search::search_packages_streaming,
+            warmeval::get_eval_worker_status,
+            warmeval::restart_eval_worker,
+            wellbeing::get_wellbeing,
+            clipboard::get_clipboard_settings,
+            clipboard::set_clipboard_settings,
+            wellbeing::get_wellbeing_metrics,
+            wellbeing::set_wellbeing_settings,
+            wellbeing::begin_pause,
+            wellbeing::end_pause,
+            wellbeing::skip_break,
+            wellbeingreport::get_wellbeing_report,
+            wellbeingreport::wipe_wellbeing_report,
+            optimise::optimise_store,
+            optimise::get_optimise_recommendation,
+            optimise::schedule_store_optimise,
+            license::get_license_policy,
+            license::set_license_policy,
+            license::check_package_license,
+            terminal::open_terminal,
+            terminal::write_terminal,
+            terminal::resize_terminal,
+            terminal::set_terminal_supervision,
+            terminal::close_terminal,
+            terminal::review_terminal_command,
+            timers::plan_timer,
+            timers::apply_timer,
+            scaffold::list_flake_templates,
+            scaffold::scaffold_project,
+            mounts::list_attached_drives,
+            mounts::mount_drive_now,
+            mounts::plan_mount,
+            mounts::apply_mount,
+            profiles::list_profiles,
+            profiles::register_profile,
+            profiles::unregister_profile,
+            profiles::set_active_profile,
+            profiles::list_profile_packages,
+            swap::review_swap,
+            swap::apply_swap,
+            nixconf::get_nix_settings,
+            nixconf::set_nix_setting,
+            nixconf::enable_nix_flakes,
+            power::get_power_status,
+            power::propose_power_profile,
+            power::apply_power_profile,
+            power::get_battery_impact,
+            secureboot::get_secure_boot_status,
+            secureboot::run_secure_boot_step,
+            secureboot::abort_secure_boot_setup,
+            encryption::get_encryption_status,
+            encryption::get_key_slot_confirmation,
+            encryption::add_luks_key,
+            encryption::remove_luks_key,
+            remoteunlock::plan_remote_unlock,
+            remoteunlock::apply_remote_unlock,
+            bootcheck::get_boot_report,
+            bootcheck::acknowledge_boot_report,
+            batch::plan_query,
+            batch::execute_plan,
+            llm::get_llm_config,
+            llm::set_llm_config,
+            services::service_graph,
+            sessions::get_sessions,
+            sessions::get_audit_log,
+            safety::classify_intent,
+            safety::assess_intents,
+            safety::get_safety_policy,
+            safety::set_safety_policy,
+            monitor::subscribe_resources,
+            monitor::unsubscribe_resources,
+            processes::list_processes,
+            processes::kill_process,
+            processes::restart_process,
+            aliases::list_aliases,
+            aliases::add_alias,
+            aliases::remove_alias,
+            aliases::get_alias_suggestions,
+            aliases::accept_alias_suggestion,
+            aliases::dismiss_alias_suggestion,
+            userservices::list_user_services,
+            userservices::set_user_service_enabled,
+            userservices::get_user_service_logs,
+            userservices::scaffold_user_service,
+            userservices::save_user_service,
+            userservices::remove_user_service,
+            voice::get_voice_settings,
+            voice::set_voice_settings,
+            voice::get_voice_status,
+            voice::download_voice_model,
+            voice::start_listening,
+            voice::stop_listening,
+            voice::list_audio_devices,
+            voice::set_input_device,
+            voiceconfirm::get_spoken_confirmation,
+            voiceconfirm::cancel_spoken_confirmation,
+            voicemodels::list_voice_models,
+            voicemodels::download_model,
+            voicemodels::verify_model,
+            voicemodels::delete_model,
+            voicemodels::get_model_disk_usage,
+            levelmeter::start_input_meter,
+            levelmeter::stop_input_meter,
+            demo::start_demo,
+            demo::demo_next_step,
+            demo::exit_demo,
+            demo::get_demo_status,
+            wakeword::get_wake_word_settings,
+            wakeword::set_wake_word_settings,
+            wakeword::set_wake_word,
+            wakeword::get_wake_word_status,
+            capabilities::get_capabilities,
+            capabilities::get_feature_status,
+            capabilities::negotiate_capabilities,
+            history::recall,
+            globalsearch::global_search,
+            homeassistant::get_homeassistant_settings,
+            homeassistant::set_homeassistant_settings,
+            homeassistant::get_homeassistant_status,
+            homeassistant::get_homeassistant_entities,
+            i18n::get_languages,
+            i18n::set_language,
+            tone::get_personalities,
+            tone::set_personality,
+            tonedetect::get_personality_detection,
+            tonedetect::set_personality_detection,
+            care::get_care_overview,
+            care::set_care_enabled,
+            care::start_care_session,
+            care::run_care_step,
+            care::skip_care_step,
+            care::end_care_session,
+            metrics::get_metrics,
+            metrics::check_for_updates,
+            metrics::get_metrics_export,
+            metrics::set_metrics_export,
+            maintwindows::get_maintenance_windows,
+            maintwindows::set_maintenance_windows,
+            maintwindows::import_maintenance_windows,
+            maintwindows::run_staged_operation,
+            maintwindows::cancel_staged_operation,
+            managed::get_managed_policy,
+            managed::get_managed_policies,
+            managed::set_managed_policy,
+            progress::get_operations,
+            progress::cancel_operation,
+            tasks::list_tasks,
+            plugins::list_plugins,
+            privacy::get_privacy_settings,
+            privacy::set_privacy_settings,
+            privacy::purge_all_data,
+            plugins::register_plugin,
+            plugins::unregister_plugin,
+            reminders::list_reminders,
+            reminders::add_reminder,
+            reminders::cancel_reminder,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                autosave::end_session(app);
+            }
+        });
 }
\ No newline at end of file
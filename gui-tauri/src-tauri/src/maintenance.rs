@@ -0,0 +1,100 @@
+// System-wide maintenance: channel/flake updates and garbage collection
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::{progress, retry};
+
+const SYSTEM_FLAKE: &str = "/etc/nixos/flake.nix";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceResult {
+    pub commands: Vec<String>,
+    pub output: String,
+    // Every attempt, including retries after transient failures
+    #[serde(default)]
+    pub attempts: Vec<retry::Attempt>,
+    // Which alternative strategy got a failing step through, if one was needed
+    #[serde(default)]
+    pub recovered_with: Vec<String>,
+}
+
+// (description, program, args) steps, reported as one operation
+type Step<'a> = (&'a str, &'a str, &'a [&'a str]);
+
+fn run_all(operation: &str, summary: &str, steps: &[Step]) -> anyhow::Result<MaintenanceResult> {
+    let mut reporter =
+        progress::Reporter::start(operation, summary, Some(steps.len() as u64), false);
+    let mut result = MaintenanceResult {
+        commands: Vec::new(),
+        output: String::new(),
+        attempts: Vec::new(),
+        recovered_with: Vec::new(),
+    };
+    for (index, (description, program, args)) in steps.iter().enumerate() {
+        reporter.advance(index as u64, description);
+        result
+            .commands
+            .push(format!("{} {}", program, args.join(" ")));
+        let report = match retry::run(program, args, true) {
+            Ok(report) => report,
+            Err(e) => {
+                reporter.fail(&e.to_string());
+                return Err(e);
+            }
+        };
+        for line in report.output.lines().filter(|l| !l.trim().is_empty()) {
+            reporter.log(line);
+        }
+        result.output.push_str(&report.output);
+        result.attempts.extend(report.attempts);
+        result.recovered_with.extend(report.succeeded_with);
+    }
+    reporter.succeed(summary);
+    Ok(result)
+}
+
+// Update the system inputs and switch to the result
+pub fn update_system() -> anyhow::Result<MaintenanceResult> {
+    if Path::new(SYSTEM_FLAKE).exists() {
+        run_all(
+            "update",
+            "Updating the system",
+            &[
+                (
+                    "Fetching the latest versions",
+                    "nix",
+                    &["flake", "update", "--flake", "/etc/nixos"],
+                ),
+                (
+                    "Building and switching to the new system",
+                    "nixos-rebuild",
+                    &["switch", "--flake", "/etc/nixos"],
+                ),
+            ],
+        )
+    } else {
+        run_all(
+            "update",
+            "Updating the system",
+            &[(
+                "Upgrading channels and switching to the new system",
+                "nixos-rebuild",
+                &["switch", "--upgrade"],
+            )],
+        )
+    }
+}
+
+// Delete generations older than `older_than` (e.g. "30d") and collect garbage
+pub fn collect_garbage(older_than: &str) -> anyhow::Result<MaintenanceResult> {
+    run_all(
+        "garbage_collect",
+        "Cleaning up old generations",
+        &[(
+            "Deleting old generations and unused packages",
+            "nix-collect-garbage",
+            &["--delete-older-than", older_than],
+        )],
+    )
+}
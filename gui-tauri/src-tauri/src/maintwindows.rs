@@ -0,0 +1,716 @@
+// Maintenance windows: when disruptive operations may run
+//
+// Windows repeat weekly ("Saturdays 02:00 for three hours") or happen once,
+// and can be imported from an .ics calendar export. While windows are on,
+// a confirmed operation that restarts services or needs a reboot (an update,
+// a rollback) is staged instead of run when no window is open, and the
+// scheduler runs staged operations in order once one opens. Passing
+// `override_window` with the request, or run_staged_operation later, runs it
+// right away regardless. The staged list is a file the user's account can
+// write, so a staged operation is checked and confirmed again when it runs;
+// one that needs confirmation then waits for run_staged_operation with the
+// token. Times are local.
+
+use anyhow::{anyhow, bail, Context};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Mutex;
+
+use crate::nlp::Intent;
+use crate::safety::{self, Downtime};
+use crate::{clock, flow, history, managed, storage, tasks, AppState};
+
+const WINDOWS_FILE: &str = "maintenance-windows.json";
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const DAY: u64 = 24 * 60 * 60;
+// Staged operations the scheduler found waiting for the user, by id
+static WAITING: Mutex<Vec<u64>> = Mutex::const_new(Vec::new());
+const WEEKDAYS: &[(Weekday, &str, &str)] = &[
+    (Weekday::Monday, "MO", "Mon"),
+    (Weekday::Tuesday, "TU", "Tue"),
+    (Weekday::Wednesday, "WE", "Wed"),
+    (Weekday::Thursday, "TH", "Thu"),
+    (Weekday::Friday, "FR", "Fri"),
+    (Weekday::Saturday, "SA", "Sat"),
+    (Weekday::Sunday, "SU", "Sun"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MaintenanceWindow {
+    // Opens at `start` ("HH:MM") on each of `days`; may run past midnight
+    Weekly {
+        days: Vec<Weekday>,
+        start: String,
+        duration_minutes: u64,
+        label: Option<String>,
+    },
+    // Unix seconds
+    Once {
+        start: u64,
+        end: u64,
+        label: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StagedOperation {
+    pub id: u64,
+    pub intent: Intent,
+    pub description: String,
+    // The request's options, minus the spent confirmation token
+    pub options: serde_json::Value,
+    pub staged_at: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowSettings {
+    pub enabled: bool,
+    pub windows: Vec<MaintenanceWindow>,
+    pub staged: Vec<StagedOperation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Opening {
+    pub start: u64,
+    pub end: u64,
+    // e.g. "Sat 02:00"
+    pub starts: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowStatus {
+    pub enabled: bool,
+    pub windows: Vec<MaintenanceWindow>,
+    pub open_now: bool,
+    pub next: Option<Opening>,
+    pub staged: Vec<StagedOperation>,
+}
+
+fn load() -> WindowSettings {
+    storage::load(WINDOWS_FILE).unwrap_or_default()
+}
+
+fn save(settings: &WindowSettings) -> anyhow::Result<()> {
+    storage::save(WINDOWS_FILE, settings).map(|_| ())
+}
+
+fn weekday_of(local_day: u64) -> Weekday {
+    // 1970-01-01 was a Thursday
+    WEEKDAYS[((local_day + 3) % 7) as usize].0
+}
+
+fn parse_time(text: &str) -> anyhow::Result<u64> {
+    let (hours, minutes) = text
+        .trim()
+        .split_once(':')
+        .ok_or_else(|| anyhow!("Expected a time like 02:00, got '{}'", text))?;
+    let (hours, minutes): (u64, u64) = (hours.parse()?, minutes.parse()?);
+    if hours > 23 || minutes > 59 {
+        bail!("'{}' isn't a time of day", text);
+    }
+    Ok(hours * 3600 + minutes * 60)
+}
+
+// "Sat 02:00" in local time
+fn describe(at: u64, offset: i64) -> String {
+    let local = (at as i64 + offset).max(0) as u64;
+    let day = WEEKDAYS
+        .iter()
+        .find(|(d, _, _)| *d == weekday_of(local / DAY))
+        .map_or("", |(_, _, name)| name);
+    let secs = local % DAY;
+    format!("{} {:02}:{:02}", day, secs / 3600, secs % 3600 / 60)
+}
+
+impl MaintenanceWindow {
+    // The openings of this window that overlap [from, from + 8 days)
+    fn openings(&self, from: u64, offset: i64) -> Vec<(u64, u64)> {
+        match self {
+            MaintenanceWindow::Weekly {
+                days,
+                start,
+                duration_minutes,
+                ..
+            } => {
+                let Ok(start) = parse_time(start) else {
+                    return Vec::new();
+                };
+                let today = (from as i64 + offset).max(0) as u64 / DAY;
+                // From yesterday, for an opening that runs past midnight
+                (today.saturating_sub(1)..today + 8)
+                    .filter(|day| days.contains(&weekday_of(*day)))
+                    .map(|day| {
+                        let opens = (day as i64 * DAY as i64 + start as i64 - offset).max(0) as u64;
+                        (opens, opens + duration_minutes * 60)
+                    })
+                    .filter(|(_, closes)| *closes > from)
+                    .collect()
+            }
+            MaintenanceWindow::Once { start, end, .. } => {
+                if *end > from {
+                    vec![(*start, *end)]
+                } else {
+                    Vec::new()
+                }
+            }
+        }
+    }
+}
+
+impl WindowSettings {
+    // Windows only restrict anything when turned on and at least one is set
+    pub fn active(&self) -> bool {
+        self.enabled && !self.windows.is_empty()
+    }
+
+    pub fn next_opening(&self, at: u64) -> Option<Opening> {
+        let offset = history::local_offset();
+        self.windows
+            .iter()
+            .flat_map(|w| w.openings(at, offset))
+            .min()
+            .map(|(start, end)| Opening {
+                start,
+                end,
+                starts: describe(start.max(at), offset),
+            })
+    }
+
+    pub fn open_at(&self, at: u64) -> bool {
+        !self.active() || self.next_opening(at).is_some_and(|o| o.start <= at)
+    }
+}
+
+// Operations that interrupt running services or need a reboot
+pub fn disruptive(intent: &Intent) -> bool {
+    safety::assess(std::slice::from_ref(intent)).downtime != Downtime::None
+}
+
+// Stage `intent` when it is disruptive and no window is open; returns the
+// response to send instead of running it. Call after confirmation, so what
+// runs later has already been confirmed.
+pub fn stage_if_closed(intent: &Intent, options: &serde_json::Value) -> Option<serde_json::Value> {
+    let overridden = options
+        .get("override_window")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    if overridden || !disruptive(intent) {
+        return None;
+    }
+    let mut settings = load();
+    let now = clock::now();
+    if settings.open_at(now) {
+        return None;
+    }
+    let mut options = options.clone();
+    if let Some(map) = options.as_object_mut() {
+        map.remove("confirmation_token");
+        map.remove("confirmation_phrase");
+    }
+    let operation = StagedOperation {
+        id: settings.staged.iter().map(|s| s.id).max().unwrap_or(0) + 1,
+        intent: intent.clone(),
+        description: intent.describe(),
+        options,
+        staged_at: now,
+    };
+    let next = settings.next_opening(now);
+    let message = match &next {
+        Some(next) => format!(
+            "\"{}\" will run in the next maintenance window ({})",
+            operation.description, next.starts
+        ),
+        None => format!(
+            "\"{}\" is staged, but no maintenance window is coming up",
+            operation.description
+        ),
+    };
+    settings.staged.push(operation.clone());
+    if let Err(e) = save(&settings) {
+        return Some(serde_json::json!({"success": false, "error": e.to_string()}));
+    }
+    Some(serde_json::json!({
+        "success": true,
+        "staged": true,
+        "staged_operation": operation,
+        "next_window": next,
+        "message": message,
+    }))
+}
+
+// Remove and return staged operation `id`, or the oldest one
+fn take_staged(id: Option<u64>) -> anyhow::Result<Option<StagedOperation>> {
+    let mut settings = load();
+    let position = match id {
+        Some(id) => settings.staged.iter().position(|s| s.id == id),
+        None => (!settings.staged.is_empty()).then_some(0),
+    };
+    let Some(position) = position else {
+        return Ok(None);
+    };
+    let operation = settings.staged.remove(position);
+    save(&settings)?;
+    Ok(Some(operation))
+}
+
+// The staged options with the token and phrase of a new confirmation
+fn confirmed(options: &serde_json::Value, confirmation: &serde_json::Value) -> serde_json::Value {
+    let mut options = options.clone();
+    if let Some(map) = options.as_object_mut() {
+        for key in ["confirmation_token", "confirmation_phrase"] {
+            if let Some(value) = confirmation.get(key) {
+                map.insert(key.to_string(), value.clone());
+            }
+        }
+    }
+    options
+}
+
+// Run a staged operation once it passes the managed and confirmation policies
+// again, with the token and phrase from `confirmation` when they are asked
+// for. Err is the policy's response, and the operation stays staged
+fn run(
+    app: &AppHandle,
+    operation: StagedOperation,
+    confirmation: &serde_json::Value,
+) -> Result<serde_json::Value, serde_json::Value> {
+    let state = app.state::<AppState>();
+    let options = confirmed(&operation.options, confirmation);
+    crate::admit(std::slice::from_ref(&operation.intent), &options, &state)?;
+    // Cancelled or already run in the meantime
+    match take_staged(Some(operation.id)) {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return Err(crate::respond::<()>(Err(anyhow!(
+                "No staged operation {}",
+                operation.id
+            ))))
+        }
+        Err(e) => return Err(crate::respond::<()>(Err(e))),
+    }
+    let response = crate::perform_intent(operation.intent.clone(), &options, &state);
+    flow::notify(
+        app,
+        "staged-operation-finished",
+        serde_json::json!({"operation": operation, "response": response}),
+        false,
+    );
+    Ok(response)
+}
+
+// Run staged operations one at a time while a window is open. One that can't
+// run unattended is left staged and the user told once
+fn check(app: &AppHandle) {
+    let mut waiting = WAITING.blocking_lock();
+    while load().open_at(clock::now()) {
+        let next = load().staged.into_iter().find(|s| !waiting.contains(&s.id));
+        let Some(operation) = next else {
+            break;
+        };
+        if let Err(response) = run(app, operation.clone(), &serde_json::json!({})) {
+            waiting.push(operation.id);
+            flow::notify(
+                app,
+                "staged-operation-waiting",
+                serde_json::json!({"operation": operation, "response": response}),
+                false,
+            );
+        }
+    }
+}
+
+pub fn start_scheduler(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        check(&app);
+        std::thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+// ========== .ics import ==========
+
+// Content lines with folded continuations joined, as (name, params, value)
+fn ics_lines(text: &str) -> Vec<(String, String, String)> {
+    let mut unfolded: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        match (line.strip_prefix([' ', '\t']), unfolded.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => unfolded.push(line.to_string()),
+        }
+    }
+    unfolded
+        .iter()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            let (name, params) = key.split_once(';').unwrap_or((key, ""));
+            Some((name.to_uppercase(), params.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+// Days since the epoch for a civil date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// DATE or DATE-TIME; floating and TZID times are taken as local
+fn ics_time(value: &str, offset: i64) -> anyhow::Result<u64> {
+    let digits = |range: std::ops::Range<usize>| -> anyhow::Result<i64> {
+        value
+            .get(range)
+            .and_then(|d| d.parse().ok())
+            .ok_or_else(|| anyhow!("Unreadable date '{}'", value))
+    };
+    let days = days_from_civil(digits(0..4)?, digits(4..6)?, digits(6..8)?);
+    let secs = if value.len() >= 15 {
+        digits(9..11)? * 3600 + digits(11..13)? * 60 + digits(13..15)?
+    } else {
+        0
+    };
+    let utc = value.ends_with('Z');
+    let at = days * DAY as i64 + secs - if utc { 0 } else { offset };
+    Ok(at.max(0) as u64)
+}
+
+// "PT3H", "PT90M", "P1D"
+fn ics_duration(value: &str) -> Option<u64> {
+    let mut total = 0;
+    let mut number = String::new();
+    for c in value.trim_start_matches(['+', 'P']).chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            unit => {
+                let n: u64 = number.parse().ok()?;
+                number.clear();
+                total += n * match unit {
+                    'W' => 7 * DAY,
+                    'D' => DAY,
+                    'H' => 3600,
+                    'M' => 60,
+                    'S' => 1,
+                    _ => return None,
+                };
+            }
+        }
+    }
+    Some(total)
+}
+
+// VEVENTs as windows: weekly and daily RRULEs repeat, anything else happens
+// once. Events that are already over are left out.
+pub fn parse_ics(text: &str) -> anyhow::Result<Vec<MaintenanceWindow>> {
+    let offset = history::local_offset();
+    let now = clock::now();
+    let mut windows = Vec::new();
+    let mut event: Option<Vec<(String, String, String)>> = None;
+    for line in ics_lines(text) {
+        match (line.0.as_str(), line.2.as_str()) {
+            ("BEGIN", "VEVENT") => event = Some(Vec::new()),
+            ("END", "VEVENT") => {
+                let fields = event.take().unwrap_or_default();
+                let field = |name: &str| fields.iter().find(|f| f.0 == name).map(|f| f.2.as_str());
+                let Some(start) = field("DTSTART") else {
+                    continue;
+                };
+                let start = ics_time(start, offset)?;
+                let end = match (field("DTEND"), field("DURATION")) {
+                    (Some(end), _) => ics_time(end, offset)?,
+                    (None, Some(duration)) => start + ics_duration(duration).unwrap_or(0),
+                    (None, None) => start + DAY,
+                };
+                if end <= start {
+                    continue;
+                }
+                let label = field("SUMMARY").map(|s| s.replace("\\,", ",").replace("\\n", " "));
+                let rule: Vec<(&str, &str)> = field("RRULE")
+                    .unwrap_or_default()
+                    .split(';')
+                    .filter_map(|part| part.split_once('='))
+                    .collect();
+                let rule_part = |name: &str| rule.iter().find(|(k, _)| *k == name).map(|(_, v)| *v);
+                let local_start = (start as i64 + offset).max(0) as u64;
+                let days: Vec<Weekday> = match rule_part("FREQ") {
+                    Some("DAILY") => WEEKDAYS.iter().map(|(d, _, _)| *d).collect(),
+                    Some("WEEKLY") => match rule_part("BYDAY") {
+                        Some(by_day) => WEEKDAYS
+                            .iter()
+                            .filter(|(_, code, _)| by_day.split(',').any(|d| d.ends_with(code)))
+                            .map(|(d, _, _)| *d)
+                            .collect(),
+                        None => vec![weekday_of(local_start / DAY)],
+                    },
+                    _ => Vec::new(),
+                };
+                if days.is_empty() {
+                    if end > now {
+                        windows.push(MaintenanceWindow::Once { start, end, label });
+                    }
+                } else {
+                    let secs = local_start % DAY;
+                    windows.push(MaintenanceWindow::Weekly {
+                        days,
+                        start: format!("{:02}:{:02}", secs / 3600, secs % 3600 / 60),
+                        duration_minutes: (end - start) / 60,
+                        label,
+                    });
+                }
+            }
+            _ => {
+                if let Some(fields) = event.as_mut() {
+                    fields.push(line);
+                }
+            }
+        }
+    }
+    if windows.is_empty() {
+        bail!("No upcoming events found in the calendar");
+    }
+    Ok(windows)
+}
+
+fn status() -> WindowStatus {
+    let settings = load();
+    let now = clock::now();
+    WindowStatus {
+        enabled: settings.enabled,
+        open_now: settings.open_at(now),
+        next: settings.next_opening(now),
+        windows: settings.windows,
+        staged: settings.staged,
+    }
+}
+
+fn validate(windows: &[MaintenanceWindow]) -> anyhow::Result<()> {
+    for window in windows {
+        match window {
+            MaintenanceWindow::Weekly {
+                days,
+                start,
+                duration_minutes,
+                ..
+            } => {
+                parse_time(start)?;
+                if days.is_empty() || *duration_minutes == 0 {
+                    bail!("A weekly window needs at least one day and a length");
+                }
+            }
+            MaintenanceWindow::Once { start, end, .. } => {
+                if end <= start {
+                    bail!("A window has to end after it starts");
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_maintenance_windows(app: AppHandle) -> WindowStatus {
+    tasks::blocking_value(&app, "Get maintenance windows", |_| status()).await
+}
+
+#[tauri::command]
+pub async fn set_maintenance_windows(
+    enabled: bool,
+    windows: Vec<MaintenanceWindow>,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set maintenance windows", move |_| {
+        if let Some(refusal) =
+            managed::guard("maintenance_windows", "Change the maintenance windows")
+        {
+            return refusal;
+        }
+        let result = validate(&windows).and_then(|()| {
+            let mut settings = load();
+            settings.enabled = enabled;
+            settings.windows = windows;
+            save(&settings)?;
+            Ok(status())
+        });
+        crate::respond(result)
+    })
+    .await
+}
+
+// Add the events of an .ics file, or replace the current windows with them
+#[tauri::command]
+pub async fn import_maintenance_windows(
+    path: String,
+    replace: Option<bool>,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Import maintenance windows", move |_| {
+        if let Some(refusal) =
+            managed::guard("maintenance_windows", "Change the maintenance windows")
+        {
+            return refusal;
+        }
+        let result = fs::read_to_string(&path)
+            .with_context(|| format!("reading {}", path))
+            .and_then(|text| parse_ics(&text))
+            .and_then(|imported| {
+                let mut settings = load();
+                if replace.unwrap_or(false) {
+                    settings.windows.clear();
+                }
+                settings.windows.extend(imported);
+                save(&settings)?;
+                Ok(status())
+            });
+        crate::respond(result)
+    })
+    .await
+}
+
+// The explicit override: run a staged operation now, window or not. `options`
+// carries the confirmation token and phrase when it asked for them
+#[tauri::command]
+pub async fn run_staged_operation(
+    id: u64,
+    options: Option<serde_json::Value>,
+    app: AppHandle,
+) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Run staged operation", move |_| {
+        if let Some(refusal) = managed::guard(
+            "maintenance_windows",
+            "Run a staged operation outside its window",
+        ) {
+            return refusal;
+        }
+        let staged = load().staged.into_iter().find(|s| s.id == id);
+        let Some(operation) = staged else {
+            return crate::respond::<()>(Err(anyhow!("No staged operation {}", id)));
+        };
+        run(&handle, operation, &options.unwrap_or_default()).unwrap_or_else(|response| response)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn cancel_staged_operation(id: u64, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Cancel staged operation", move |_| {
+        if let Some(refusal) = managed::guard("maintenance_windows", "Cancel a staged operation") {
+            return refusal;
+        }
+        let result = take_staged(Some(id)).and_then(|operation| match operation {
+            Some(_) => Ok(status()),
+            None => bail!("No staged operation {}", id),
+        });
+        crate::respond(result)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn closed_until(start: u64) -> WindowSettings {
+        WindowSettings {
+            enabled: true,
+            windows: vec![MaintenanceWindow::Once {
+                start,
+                end: start + 3600,
+                label: None,
+            }],
+            staged: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn windows_only_restrict_when_turned_on() {
+        let now = clock::now();
+        let mut settings = closed_until(now + 3600);
+        assert!(!settings.open_at(now));
+        assert!(settings.open_at(now + 4000));
+        settings.enabled = false;
+        assert!(settings.open_at(now));
+    }
+
+    #[test]
+    fn disruptive_intents_wait_for_the_window() {
+        let dir = storage::scratch_dir("maintwindows");
+        storage::with_root(&dir, || {
+            save(&closed_until(clock::now() + 3600)).unwrap();
+            let options = json!({"confirmation_token": "ab12", "profile": "system"});
+            let staged = stage_if_closed(&Intent::Update, &options).unwrap();
+            assert_eq!(staged["staged"], true);
+            assert!(staged["next_window"].is_object());
+            let stored = load().staged;
+            assert_eq!(stored.len(), 1);
+            assert_eq!(stored[0].intent, Intent::Update);
+            // The spent token isn't kept
+            assert_eq!(stored[0].options, json!({"profile": "system"}));
+        });
+    }
+
+    #[test]
+    fn quiet_or_overridden_intents_run_now() {
+        let dir = storage::scratch_dir("maintwindows");
+        storage::with_root(&dir, || {
+            save(&closed_until(clock::now() + 3600)).unwrap();
+            let search = Intent::Search {
+                query: "vim".to_string(),
+            };
+            assert!(stage_if_closed(&search, &json!({})).is_none());
+            assert!(stage_if_closed(&Intent::Update, &json!({"override_window": true})).is_none());
+            assert!(load().staged.is_empty());
+        });
+    }
+
+    #[test]
+    fn staged_operations_take_only_a_new_confirmation() {
+        let staged = json!({"profile": "system"});
+        let confirmation = json!({
+            "confirmation_token": "ab12",
+            "confirmation_phrase": "yes",
+            "override_window": true,
+        });
+        assert_eq!(
+            confirmed(&staged, &confirmation),
+            json!({"profile": "system", "confirmation_token": "ab12", "confirmation_phrase": "yes"})
+        );
+        assert_eq!(confirmed(&staged, &json!({})), staged);
+    }
+
+    #[test]
+    fn staged_operations_are_taken_once() {
+        let dir = storage::scratch_dir("maintwindows");
+        storage::with_root(&dir, || {
+            save(&closed_until(clock::now() + 3600)).unwrap();
+            stage_if_closed(&Intent::Update, &json!({})).unwrap();
+            stage_if_closed(&Intent::Rollback { generation: None }, &json!({})).unwrap();
+            let first = take_staged(None).unwrap().unwrap();
+            assert_eq!(first.intent, Intent::Update);
+            assert!(take_staged(Some(first.id)).unwrap().is_none());
+            assert_eq!(load().staged.len(), 1);
+        });
+    }
+}
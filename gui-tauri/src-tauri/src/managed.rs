@@ -0,0 +1,325 @@
+// Policies a benevolent admin sets for managed accounts
+//
+// An admin (anyone who can authenticate through polkit) can limit what a
+// user's sessions may do: which kinds of action, which packages may be
+// installed, and whether anything may run as root, which rules out system
+// rebuilds. Policies live in /etc where the managed user can't change them, and
+// are enforced where intents are dispatched, where privileged commands run, and
+// by guard(), which every command that changes something directly calls first.
+// Nothing is hidden from the managed user: get_managed_policy shows the rules
+// that apply to them, and every refusal says which rule it hit.
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::nlp::Intent;
+use crate::{sessions, storage, system, tasks};
+
+const POLICY_DIR: &str = "/etc/luminous-nix";
+const POLICY_FILE: &str = "/etc/luminous-nix/managed-users.json";
+// What a policy can allow besides the intent kinds (Intent::KINDS); these are
+// only reachable through commands
+const COMMAND_ACTIONS: &[&str] = &[
+    "terminal",
+    "processes",
+    "user_services",
+    "safety_policy",
+    "license_policy",
+    "maintenance_windows",
+    "import_session",
+    "shortcuts",
+    "voice_models",
+    "homeassistant",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ManagedPolicy {
+    // Intent kinds this account may run, like "search" or "install"
+    pub allowed_actions: Vec<String>,
+    // When set, only these packages can be installed
+    pub install_allowlist: Option<Vec<String>>,
+    // Commands run as root: rebuilds, rollbacks, boot and disk changes
+    pub privileged: bool,
+    // From the admin, shown to the managed user with the rules
+    pub note: Option<String>,
+}
+
+impl Default for ManagedPolicy {
+    fn default() -> Self {
+        ManagedPolicy {
+            allowed_actions: ["search", "list_installed", "explain", "install"]
+                .iter()
+                .map(|a| a.to_string())
+                .collect(),
+            install_allowlist: Some(Vec::new()),
+            privileged: false,
+            note: None,
+        }
+    }
+}
+
+// Policies by account name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ManagedPolicies {
+    pub users: BTreeMap<String, ManagedPolicy>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManagedStatus {
+    pub user: String,
+    pub managed: bool,
+    pub policy: Option<ManagedPolicy>,
+}
+
+pub fn policies() -> ManagedPolicies {
+    storage::read_json(Path::new(POLICY_FILE)).unwrap_or_default()
+}
+
+// The account name for our uid; $USER can be set to anything
+fn account() -> String {
+    let uid = sessions::current().uid.to_string();
+    fs::read_to_string("/etc/passwd")
+        .ok()
+        .and_then(|passwd| {
+            passwd.lines().find_map(|line| {
+                let fields: Vec<&str> = line.split(':').collect();
+                (fields.get(2) == Some(&uid.as_str())).then(|| fields[0].to_string())
+            })
+        })
+        .unwrap_or_else(system::username)
+}
+
+pub fn current() -> Option<ManagedPolicy> {
+    policies().users.remove(&account())
+}
+
+fn known_action(action: &str) -> bool {
+    action != Intent::Unknown.kind()
+        && (Intent::KINDS.contains(&action) || COMMAND_ACTIONS.contains(&action))
+}
+
+fn allow(policy: &ManagedPolicy, action: &str, what: &str) -> anyhow::Result<()> {
+    if !policy.allowed_actions.iter().any(|a| a == action) {
+        bail!(
+            "Your administrator hasn't allowed \"{}\" on this account",
+            what
+        );
+    }
+    Ok(())
+}
+
+pub fn check(intent: &Intent) -> anyhow::Result<()> {
+    match current() {
+        Some(policy) => permits(&policy, intent),
+        None => Ok(()),
+    }
+}
+
+// Whether `policy` lets `intent` run: its kind and, for installs, its packages
+fn permits(policy: &ManagedPolicy, intent: &Intent) -> anyhow::Result<()> {
+    if matches!(intent, Intent::Unknown) {
+        return Ok(());
+    }
+    allow(policy, intent.kind(), &intent.describe())?;
+    if let (Intent::Install { packages }, Some(allowlist)) = (intent, &policy.install_allowlist) {
+        let blocked: Vec<&str> = packages
+            .iter()
+            .filter(|p| !allowlist.iter().any(|a| a.eq_ignore_ascii_case(p)))
+            .map(String::as_str)
+            .collect();
+        if !blocked.is_empty() {
+            bail!(
+                "{} isn't on the list of packages your administrator approved",
+                blocked.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+// The response for an intent this account's policy doesn't allow
+pub fn refusal(intent: &Intent) -> Option<serde_json::Value> {
+    check(intent).err().map(refused)
+}
+
+// The check every command that changes something without an intent makes
+// first: terminals, environment variables, default apps, services, plugins,
+// profiles, processes, the safety and license policies, maintenance windows,
+// imported sessions, shortcuts, voice models and Home Assistant. `action` is
+// what the policy has to allow.
+pub fn guard(action: &str, what: &str) -> Option<serde_json::Value> {
+    let policy = current()?;
+    allow(&policy, action, what).err().map(refused)
+}
+
+fn refused(error: anyhow::Error) -> serde_json::Value {
+    serde_json::json!({
+        "success": false,
+        "blocked_by_policy": true,
+        "error": error.to_string(),
+        "policy": current(),
+    })
+}
+
+pub fn check_privileged(command: &str) -> anyhow::Result<()> {
+    if current().is_some_and(|policy| !policy.privileged) {
+        bail!(
+            "Your administrator hasn't allowed system-wide changes on this account ({})",
+            command
+        );
+    }
+    Ok(())
+}
+
+// Written as root, which is also what makes someone an admin here
+fn save(policies: &ManagedPolicies) -> anyhow::Result<()> {
+    system::run_privileged("install", &["-d", "-m", "0755", POLICY_DIR])?;
+    let json = serde_json::to_string_pretty(policies)?;
+    system::run_privileged_with_input("tee", &[POLICY_FILE], json.as_bytes())?;
+    Ok(())
+}
+
+pub fn set(user: &str, policy: Option<ManagedPolicy>) -> anyhow::Result<ManagedPolicies> {
+    if user.is_empty() || user.contains([':', '/']) {
+        bail!("\"{}\" isn't an account name", user);
+    }
+    let mut policies = policies();
+    match policy {
+        Some(policy) => {
+            if let Some(unknown) = policy.allowed_actions.iter().find(|a| !known_action(a)) {
+                bail!("Unknown action \"{}\"", unknown);
+            }
+            policies.users.insert(user.to_string(), policy);
+        }
+        None => {
+            policies.users.remove(user);
+        }
+    }
+    save(&policies)?;
+    Ok(policies)
+}
+
+// ========== Tauri Commands ==========
+
+// What applies to whoever is using this session
+#[tauri::command]
+pub async fn get_managed_policy(app: AppHandle) -> ManagedStatus {
+    tasks::blocking_value(&app, "Get managed policy", |_| {
+        let policy = current();
+        ManagedStatus {
+            user: account(),
+            managed: policy.is_some(),
+            policy,
+        }
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_managed_policies(app: AppHandle) -> ManagedPolicies {
+    tasks::blocking_value(&app, "Get managed policies", |_| policies()).await
+}
+
+// Manage `user` with `policy`, or stop managing them with none
+#[tauri::command]
+pub async fn set_managed_policy(
+    user: String,
+    policy: Option<ManagedPolicy>,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set managed policy", move |_| {
+        crate::respond(set(&user, policy))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn install(names: &[&str]) -> Intent {
+        Intent::Install {
+            packages: names.iter().map(|n| n.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn default_policy_allows_looking_but_not_changing() {
+        let policy = ManagedPolicy::default();
+        let search = Intent::Search {
+            query: "vim".to_string(),
+        };
+        assert!(permits(&policy, &search).is_ok());
+        assert!(permits(&policy, &Intent::ListInstalled).is_ok());
+        assert!(permits(&policy, &Intent::Unknown).is_ok());
+        let refused = permits(&policy, &Intent::Update).unwrap_err();
+        assert!(refused.to_string().contains("hasn't allowed"));
+        // Install is allowed, but nothing is on the empty allowlist yet
+        assert!(permits(&policy, &install(&["firefox"])).is_err());
+    }
+
+    #[test]
+    fn installs_are_held_to_the_allowlist() {
+        let policy = ManagedPolicy {
+            install_allowlist: Some(vec!["Firefox".to_string()]),
+            ..ManagedPolicy::default()
+        };
+        assert!(permits(&policy, &install(&["firefox"])).is_ok());
+        let refused = permits(&policy, &install(&["firefox", "vim"])).unwrap_err();
+        assert_eq!(
+            refused.to_string(),
+            "vim isn't on the list of packages your administrator approved"
+        );
+
+        let open = ManagedPolicy {
+            install_allowlist: None,
+            ..ManagedPolicy::default()
+        };
+        assert!(permits(&open, &install(&["vim"])).is_ok());
+    }
+
+    #[test]
+    fn command_actions_need_allowing_too() {
+        let mut policy = ManagedPolicy::default();
+        let refused = allow(&policy, "terminal", "Open a terminal").unwrap_err();
+        assert_eq!(
+            refused.to_string(),
+            "Your administrator hasn't allowed \"Open a terminal\" on this account"
+        );
+        policy.allowed_actions.push("terminal".to_string());
+        assert!(allow(&policy, "terminal", "Open a terminal").is_ok());
+    }
+
+    #[test]
+    fn every_intent_kind_can_be_allowed() {
+        for kind in [
+            "set_boot_default",
+            "delete_generations",
+            "switch_specialisation",
+        ] {
+            assert!(known_action(kind), "{}", kind);
+        }
+        assert!(known_action("terminal"));
+        assert!(known_action("safety_policy"));
+        assert!(!known_action("unknown"));
+        assert!(!known_action("fly"));
+    }
+
+    #[test]
+    fn bad_accounts_and_actions_are_rejected_before_saving() {
+        assert!(set("", None).is_err());
+        assert!(set("../root", None).is_err());
+        let policy = ManagedPolicy {
+            allowed_actions: vec!["search".to_string(), "fly".to_string()],
+            ..ManagedPolicy::default()
+        };
+        let rejected = set("alex", Some(policy)).unwrap_err();
+        assert_eq!(rejected.to_string(), "Unknown action \"fly\"");
+    }
+}
@@ -0,0 +1,409 @@
+// System state as Prometheus metrics, for existing home-lab monitoring
+//
+// The same facts the assistant reasons about (how old the running generation
+// is, whether flake inputs are behind upstream, store size, failed units and
+// the app's own health) rendered in the text exposition format. When export
+// is enabled the text is written every few minutes to a .prom file for
+// node_exporter's textfile collector; get_metrics returns it on demand. Store
+// size, upstream checks and the boot check are slow or use the network, so
+// they are refreshed at most hourly.
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Mutex;
+
+use crate::{boot, bootcheck, care, clock, evalpool, optimise, storage, system, tasks, warmeval};
+
+const SETTINGS_FILE: &str = "metrics-export.json";
+const SYSTEM_FLAKE_LOCK: &str = "/etc/nixos/flake.lock";
+const SLOW_REFRESH_SECS: u64 = 60 * 60;
+const MIN_INTERVAL_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportSettings {
+    pub enabled: bool,
+    // Where to write the .prom file; defaults to the data dir
+    pub path: Option<String>,
+    pub interval_secs: u64,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        ExportSettings {
+            enabled: false,
+            path: None,
+            interval_secs: 300,
+        }
+    }
+}
+
+impl ExportSettings {
+    pub fn target(&self) -> PathBuf {
+        self.path
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| storage::data_dir().join("metrics/luminous-nix.prom"))
+    }
+}
+
+// A flake input of the system configuration
+#[derive(Debug, Clone)]
+struct InputStatus {
+    name: String,
+    last_modified: u64,
+    // None when upstream couldn't be reached
+    behind: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct SlowMetrics {
+    collected_at: u64,
+    store_bytes: Option<u64>,
+    inputs: Vec<InputStatus>,
+    boot_findings: usize,
+}
+
+static SLOW: Mutex<Option<SlowMetrics>> = Mutex::const_new(None);
+
+pub fn settings() -> ExportSettings {
+    storage::load(SETTINGS_FILE).unwrap_or_default()
+}
+
+// The head of `reference` (or the default branch) upstream
+fn upstream_rev(url: &str, reference: Option<&str>) -> Option<String> {
+    let output = system::run("git", &["ls-remote", url, reference.unwrap_or("HEAD")]).ok()?;
+    output.split_whitespace().next().map(String::from)
+}
+
+fn flake_inputs() -> Vec<InputStatus> {
+    let Ok(lock) = storage::read_json::<serde_json::Value>(Path::new(SYSTEM_FLAKE_LOCK)) else {
+        return Vec::new();
+    };
+    let nodes = &lock["nodes"];
+    let root = lock["root"].as_str().unwrap_or("root");
+    let Some(inputs) = nodes[root]["inputs"].as_object() else {
+        return Vec::new();
+    };
+    inputs
+        .iter()
+        // Inputs that follow another input are listed as a path, not a node
+        .filter_map(|(name, node)| Some((name, &nodes[node.as_str()?])))
+        .map(|(name, node)| {
+            let locked = &node["locked"];
+            let field = |name: &str| locked[name].as_str();
+            let url = match field("type") {
+                Some("github") => field("owner")
+                    .zip(field("repo"))
+                    .map(|(owner, repo)| format!("https://github.com/{}/{}", owner, repo)),
+                Some("git") => field("url").map(String::from),
+                _ => None,
+            };
+            let behind = url.and_then(|url| {
+                let upstream = upstream_rev(&url, node["original"]["ref"].as_str())?;
+                Some(Some(upstream.as_str()) != field("rev"))
+            });
+            InputStatus {
+                name: name.clone(),
+                last_modified: locked["lastModified"].as_u64().unwrap_or(0),
+                behind,
+            }
+        })
+        .collect()
+}
+
+fn slow_metrics() -> SlowMetrics {
+    let mut slow = SLOW.blocking_lock();
+    if let Some(cached) = slow.as_ref() {
+        if clock::now().saturating_sub(cached.collected_at) < SLOW_REFRESH_SECS {
+            return cached.clone();
+        }
+    }
+    let fresh = SlowMetrics {
+        collected_at: clock::now(),
+        store_bytes: optimise::store_bytes().ok(),
+        inputs: flake_inputs(),
+        boot_findings: bootcheck::analyze().findings.len(),
+    };
+    *slow = Some(fresh.clone());
+    fresh
+}
+
+// Check upstream now instead of waiting for the hourly refresh
+pub fn check_updates() -> Vec<String> {
+    *SLOW.blocking_lock() = None;
+    pending_updates()
+}
+
+// System flake inputs upstream has moved past, from the hourly check
+pub fn pending_updates() -> Vec<String> {
+    slow_metrics()
+        .inputs
+        .into_iter()
+        .filter(|i| i.behind == Some(true))
+        .map(|i| i.name)
+        .collect()
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// One metric family: HELP, TYPE and its samples as (labels, value)
+fn family(out: &mut String, name: &str, help: &str, samples: &[(String, f64)]) {
+    let _ = writeln!(out, "# HELP luminous_nix_{} {}", name, help);
+    let _ = writeln!(out, "# TYPE luminous_nix_{} gauge", name);
+    for (labels, value) in samples {
+        let _ = writeln!(out, "luminous_nix_{}{} {}", name, labels, value);
+    }
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    family(out, name, help, &[(String::new(), value)]);
+}
+
+fn label(name: &str, value: &str) -> String {
+    format!("{{{}=\"{}\"}}", name, escape(value))
+}
+
+fn flag(value: bool) -> f64 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+// Everything in the text exposition format
+pub fn render() -> String {
+    let now = clock::now();
+    let mut out = String::new();
+    gauge(
+        &mut out,
+        "up",
+        "Whether the assistant backend is running",
+        1.0,
+    );
+
+    let generations = boot::list_generations();
+    gauge(
+        &mut out,
+        "generations",
+        "System generations on disk",
+        generations.len() as f64,
+    );
+    if let Some(current) = generations.iter().find(|g| g.current) {
+        gauge(
+            &mut out,
+            "generation_current",
+            "Number of the running system generation",
+            current.number as f64,
+        );
+        gauge(
+            &mut out,
+            "generation_age_seconds",
+            "Seconds since the running generation was built",
+            now.saturating_sub(current.created) as f64,
+        );
+        gauge(
+            &mut out,
+            "reboot_pending",
+            "Whether the booted generation differs from the running one",
+            flag(!current.booted),
+        );
+    }
+
+    let slow = slow_metrics();
+    let inputs = &slow.inputs;
+    family(
+        &mut out,
+        "flake_input_age_seconds",
+        "Seconds since each system flake input was last updated upstream",
+        &inputs
+            .iter()
+            .filter(|i| i.last_modified > 0)
+            .map(|i| {
+                (
+                    label("input", &i.name),
+                    now.saturating_sub(i.last_modified) as f64,
+                )
+            })
+            .collect::<Vec<_>>(),
+    );
+    family(
+        &mut out,
+        "flake_input_update_pending",
+        "Whether upstream has moved past the locked revision of each input",
+        &inputs
+            .iter()
+            .filter_map(|i| Some((label("input", &i.name), flag(i.behind?))))
+            .collect::<Vec<_>>(),
+    );
+    gauge(
+        &mut out,
+        "updates_pending",
+        "Flake inputs with a newer upstream revision",
+        inputs.iter().filter(|i| i.behind == Some(true)).count() as f64,
+    );
+    if let Some(bytes) = slow.store_bytes {
+        gauge(
+            &mut out,
+            "store_size_bytes",
+            "Total NAR size of the Nix store",
+            bytes as f64,
+        );
+    }
+
+    let failed = care::failed_units();
+    gauge(
+        &mut out,
+        "failed_units",
+        "systemd units in the failed state",
+        failed.len() as f64,
+    );
+    family(
+        &mut out,
+        "unit_failed",
+        "Set for each failed systemd unit",
+        &failed
+            .iter()
+            .map(|unit| (label("unit", unit), 1.0))
+            .collect::<Vec<_>>(),
+    );
+    gauge(
+        &mut out,
+        "boot_findings",
+        "Problems found in the last boot",
+        slow.boot_findings as f64,
+    );
+
+    let pool = evalpool::status();
+    gauge(
+        &mut out,
+        "eval_pool_workers",
+        "Evaluation pool threads",
+        pool.workers as f64,
+    );
+    family(
+        &mut out,
+        "eval_pool_queued",
+        "Evaluations waiting for a worker",
+        &[
+            (
+                label("priority", "interactive"),
+                pool.queued_interactive as f64,
+            ),
+            (
+                label("priority", "background"),
+                pool.queued_background as f64,
+            ),
+        ],
+    );
+    family(
+        &mut out,
+        "eval_pool_busy",
+        "Evaluation workers currently busy",
+        &[
+            (
+                label("priority", "interactive"),
+                pool.busy_interactive as f64,
+            ),
+            (label("priority", "background"), pool.busy_background as f64),
+        ],
+    );
+    gauge(
+        &mut out,
+        "eval_worker_running",
+        "Whether the warm nix repl is running",
+        flag(warmeval::status().running),
+    );
+    gauge(
+        &mut out,
+        "slow_metrics_timestamp_seconds",
+        "When store size, upstream and boot checks were last collected",
+        slow.collected_at as f64,
+    );
+    gauge(
+        &mut out,
+        "scrape_timestamp_seconds",
+        "When these metrics were collected",
+        now as f64,
+    );
+    out
+}
+
+// Replace the file in one step; the textfile collector may read at any time
+pub fn export(settings: &ExportSettings) -> anyhow::Result<PathBuf> {
+    let path = settings.target();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+    }
+    // node_exporter only reads *.prom, so the partial file is never picked up
+    let tmp = path.with_extension("prom.tmp");
+    fs::write(&tmp, render()).with_context(|| format!("writing {}", tmp.display()))?;
+    fs::rename(&tmp, &path).with_context(|| format!("replacing {}", path.display()))?;
+    Ok(path)
+}
+
+// Write the file on the configured interval while export is enabled
+pub fn start_exporter() {
+    std::thread::spawn(|| loop {
+        let settings = settings();
+        if settings.enabled {
+            if let Err(e) = export(&settings) {
+                eprintln!("Could not export metrics: {}", e);
+            }
+        }
+        std::thread::sleep(Duration::from_secs(
+            settings.interval_secs.max(MIN_INTERVAL_SECS),
+        ));
+    });
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_metrics(app: AppHandle) -> String {
+    tasks::blocking_value(&app, "Get metrics", |_| render()).await
+}
+
+// The flake inputs with updates waiting, checked against upstream now
+#[tauri::command]
+pub async fn check_for_updates(app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Check for updates", move |_| {
+        let pending = check_updates();
+        let _ = handle.emit("updates-checked", &pending);
+        crate::respond(Ok(pending))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_metrics_export(app: AppHandle) -> ExportSettings {
+    tasks::blocking_value(&app, "Get metrics export", |_| settings()).await
+}
+
+// Save the settings and, when enabled, write the file right away
+#[tauri::command]
+pub async fn set_metrics_export(settings: ExportSettings, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set metrics export", move |_| {
+        let result = storage::save(SETTINGS_FILE, &settings).and_then(|_| {
+            if settings.enabled {
+                export(&settings).map(Some)
+            } else {
+                Ok(None)
+            }
+        });
+        crate::respond(result)
+    })
+    .await
+}
@@ -0,0 +1,313 @@
+// Default application (mimeapps) manager
+//
+// Reads the XDG mimeapps.list chain, resolves friendly names like "firefox" to
+// desktop entries, and sets defaults either imperatively through xdg-mime or
+// declaratively as a generated home-manager module.
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+use crate::nixgen::{self, NixModule, NixOption, Target};
+use crate::{managed, storage, system, tasks};
+
+const DECLARED_FILE: &str = "mimeapps.json";
+
+// A desktop entry that can be chosen as a default handler
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DesktopApp {
+    pub desktop_id: String,
+    pub name: String,
+    pub mime_types: Vec<String>,
+    pub path: PathBuf,
+}
+
+// Current default for one MIME type or URL scheme, and which file set it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DefaultApp {
+    pub mime_type: String,
+    pub desktop_ids: Vec<String>,
+    pub source: PathBuf,
+}
+
+// Result of a set operation, including the generated module in declarative mode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetDefaultResult {
+    pub desktop_id: String,
+    pub mime_types: Vec<String>,
+    pub declarative: bool,
+    pub module_path: Option<PathBuf>,
+    pub next_step: Option<String>,
+}
+
+// Well-known roles so "default browser" maps to every MIME type/scheme it implies
+pub fn role_mime_types(role: &str) -> Option<&'static [&'static str]> {
+    let types: &'static [&'static str] = match role {
+        "browser" | "web-browser" => &[
+            "text/html",
+            "application/xhtml+xml",
+            "x-scheme-handler/http",
+            "x-scheme-handler/https",
+            "x-scheme-handler/about",
+            "x-scheme-handler/unknown",
+        ],
+        "mail" | "email" => &["x-scheme-handler/mailto"],
+        "pdf" | "pdf-viewer" => &["application/pdf"],
+        "file-manager" => &["inode/directory"],
+        "text-editor" | "editor" => &["text/plain"],
+        "image-viewer" => &["image/png", "image/jpeg", "image/gif", "image/webp"],
+        "video-player" => &["video/mp4", "video/x-matroska", "video/webm"],
+        "music-player" => &["audio/mpeg", "audio/flac", "audio/ogg"],
+        "terminal" => &["x-scheme-handler/terminal"],
+        _ => return None,
+    };
+    Some(types)
+}
+
+// mimeapps.list files in precedence order (first wins)
+fn mimeapps_files() -> Vec<PathBuf> {
+    let mut files = vec![system::xdg_config_home().join("mimeapps.list")];
+    files.extend(
+        system::xdg_data_dirs()
+            .into_iter()
+            .map(|dir| dir.join("applications/mimeapps.list")),
+    );
+    files
+}
+
+// Parse the [Default Applications] section of a mimeapps.list
+fn parse_defaults(contents: &str) -> Vec<(String, Vec<String>)> {
+    let mut in_defaults = false;
+    let mut entries = Vec::new();
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_defaults = line == "[Default Applications]";
+            continue;
+        }
+        if !in_defaults || line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some((mime, apps)) = line.split_once('=') {
+            let ids = apps
+                .split(';')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect();
+            entries.push((mime.trim().to_string(), ids));
+        }
+    }
+    entries
+}
+
+pub fn list_defaults() -> Vec<DefaultApp> {
+    let mut defaults: BTreeMap<String, DefaultApp> = BTreeMap::new();
+    for file in mimeapps_files() {
+        let Ok(contents) = fs::read_to_string(&file) else {
+            continue;
+        };
+        for (mime_type, desktop_ids) in parse_defaults(&contents) {
+            defaults.entry(mime_type.clone()).or_insert(DefaultApp {
+                mime_type,
+                desktop_ids,
+                source: file.clone(),
+            });
+        }
+    }
+    defaults.into_values().collect()
+}
+
+fn parse_desktop_entry(path: PathBuf, desktop_id: String) -> Option<DesktopApp> {
+    let contents = fs::read_to_string(&path).ok()?;
+    let mut in_entry = false;
+    let mut name = None;
+    let mut mime_types = Vec::new();
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_entry {
+            continue;
+        }
+        if let Some(value) = line.strip_prefix("Name=") {
+            name.get_or_insert_with(|| value.to_string());
+        } else if let Some(value) = line.strip_prefix("MimeType=") {
+            mime_types = value
+                .split(';')
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect();
+        } else if line == "NoDisplay=true" || line == "Hidden=true" {
+            return None;
+        }
+    }
+    Some(DesktopApp {
+        name: name.unwrap_or_else(|| desktop_id.trim_end_matches(".desktop").to_string()),
+        desktop_id,
+        mime_types,
+        path,
+    })
+}
+
+// All installed desktop entries, optionally filtered to those handling a MIME type
+pub fn list_applications(mime_type: Option<&str>) -> Vec<DesktopApp> {
+    let mut apps: BTreeMap<String, DesktopApp> = BTreeMap::new();
+    for dir in system::xdg_data_dirs() {
+        let Ok(entries) = fs::read_dir(dir.join("applications")) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let desktop_id = entry.file_name().to_string_lossy().into_owned();
+            if !desktop_id.ends_with(".desktop") || apps.contains_key(&desktop_id) {
+                continue;
+            }
+            if let Some(app) = parse_desktop_entry(entry.path(), desktop_id.clone()) {
+                apps.insert(desktop_id, app);
+            }
+        }
+    }
+    apps.into_values()
+        .filter(|app| mime_type.is_none_or(|m| app.mime_types.iter().any(|t| t == m)))
+        .collect()
+}
+
+// Resolve "firefox", "Firefox" or "firefox.desktop" to an installed desktop id
+pub fn resolve_app(query: &str) -> anyhow::Result<String> {
+    let apps = list_applications(None);
+    let wanted = query.trim().to_lowercase();
+    let stem = wanted.trim_end_matches(".desktop");
+
+    let found = apps
+        .iter()
+        .find(|a| a.desktop_id.to_lowercase() == format!("{}.desktop", stem))
+        .or_else(|| apps.iter().find(|a| a.name.to_lowercase() == stem))
+        .or_else(|| {
+            apps.iter().find(|a| {
+                a.desktop_id.to_lowercase().contains(stem) || a.name.to_lowercase().contains(stem)
+            })
+        });
+
+    found
+        .map(|a| a.desktop_id.clone())
+        .ok_or_else(|| anyhow!("No installed application matches '{}'", query))
+}
+
+// Defaults declared through the assistant, regenerated into one home-manager module
+fn write_declared(declared: &BTreeMap<String, Vec<String>>) -> anyhow::Result<PathBuf> {
+    let mut module = NixModule::new("mimeapps", "default applications", Target::HomeManager);
+    module.set(NixOption::new("xdg.mimeApps.enable", nixgen::bool(true)));
+    for (mime_type, ids) in declared {
+        let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+        module.set(NixOption::new(
+            format!(
+                "xdg.mimeApps.defaultApplications.{}",
+                nixgen::attr(mime_type)
+            ),
+            nixgen::string_list(&ids),
+        ));
+    }
+    module.write()
+}
+
+pub fn set_default(
+    app: &str,
+    mime_types: &[&str],
+    declarative: bool,
+) -> anyhow::Result<SetDefaultResult> {
+    if mime_types.is_empty() {
+        bail!("No MIME types given");
+    }
+    let desktop_id = resolve_app(app)?;
+
+    let (module_path, next_step) = if declarative {
+        let mut declared: BTreeMap<String, Vec<String>> = storage::load(DECLARED_FILE)?;
+        for mime_type in mime_types {
+            declared.insert(mime_type.to_string(), vec![desktop_id.clone()]);
+        }
+        storage::save(DECLARED_FILE, &declared)?;
+        let path = write_declared(&declared)?;
+        (
+            Some(path),
+            Some("Run home-manager switch to apply the new defaults".to_string()),
+        )
+    } else {
+        for mime_type in mime_types {
+            system::run("xdg-mime", &["default", &desktop_id, mime_type])?;
+        }
+        (None, None)
+    };
+
+    Ok(SetDefaultResult {
+        desktop_id,
+        mime_types: mime_types.iter().map(|s| s.to_string()).collect(),
+        declarative,
+        module_path,
+        next_step,
+    })
+}
+
+pub fn set_default_for_role(
+    role: &str,
+    app: &str,
+    declarative: bool,
+) -> anyhow::Result<SetDefaultResult> {
+    let mime_types =
+        role_mime_types(role).ok_or_else(|| anyhow!("Unknown application role '{}'", role))?;
+    set_default(app, mime_types, declarative)
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn list_default_apps(app: AppHandle) -> Vec<DefaultApp> {
+    tasks::blocking_value(&app, "List default apps", |_| list_defaults()).await
+}
+
+#[tauri::command]
+pub async fn list_desktop_apps(mime_type: Option<String>, app: AppHandle) -> Vec<DesktopApp> {
+    tasks::blocking_value(&app, "List desktop apps", move |_| {
+        list_applications(mime_type.as_deref())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn set_default_app(
+    mime_type: String,
+    app: String,
+    declarative: bool,
+    handle: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&handle, "Set default app", move |_| {
+        if let Some(refusal) =
+            managed::guard("set_default_app", &format!("Make {} the default", app))
+        {
+            return refusal;
+        }
+        crate::respond(set_default(&app, &[mime_type.as_str()], declarative))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn set_default_app_for_role(
+    role: String,
+    app: String,
+    declarative: bool,
+    handle: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&handle, "Set default app for role", move |_| {
+        if let Some(refusal) = managed::guard(
+            "set_default_app",
+            &format!("Make {} the default {}", app, role),
+        ) {
+            return refusal;
+        }
+        crate::respond(set_default_for_role(&role, &app, declarative))
+    })
+    .await
+}
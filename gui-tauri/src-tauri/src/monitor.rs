@@ -0,0 +1,254 @@
+// Live resource monitor for dashboard components
+//
+// Samples CPU, memory, disk IO and network throughput straight from /proc and
+// streams them as `resource-sample` events. Each subscription has its own rate
+// and metric selection and runs until it is cancelled.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use crate::{clock, tasks, AppState};
+
+const MIN_INTERVAL_MS: u64 = 250;
+const MAX_INTERVAL_MS: u64 = 60_000;
+const SECTOR_BYTES: u64 = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    Cpu,
+    Memory,
+    Disk,
+    Network,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResourceSample {
+    pub subscription: u32,
+    pub timestamp_ms: u64,
+    pub cpu_percent: Option<f64>,
+    pub memory_used_mb: Option<u64>,
+    pub memory_total_mb: Option<u64>,
+    pub disk_read_bps: Option<u64>,
+    pub disk_write_bps: Option<u64>,
+    pub net_rx_bps: Option<u64>,
+    pub net_tx_bps: Option<u64>,
+}
+
+// Raw cumulative counters; rates are the difference between two readings
+#[derive(Debug, Clone, Copy, Default)]
+struct Counters {
+    cpu_busy: u64,
+    cpu_total: u64,
+    disk_read: u64,
+    disk_write: u64,
+    net_rx: u64,
+    net_tx: u64,
+}
+
+fn read_cpu() -> (u64, u64) {
+    let stat = fs::read_to_string("/proc/stat").unwrap_or_default();
+    let values: Vec<u64> = stat
+        .lines()
+        .next()
+        .unwrap_or("")
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|v| v.parse().ok())
+        .collect();
+    let total: u64 = values.iter().sum();
+    // idle + iowait
+    let idle = values.get(3).copied().unwrap_or(0) + values.get(4).copied().unwrap_or(0);
+    (total.saturating_sub(idle), total)
+}
+
+fn read_disk() -> (u64, u64) {
+    let stats = fs::read_to_string("/proc/diskstats").unwrap_or_default();
+    stats
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let name = *fields.get(2)?;
+            // Whole disks only, so partitions are not counted twice
+            let is_disk = fs::metadata(format!("/sys/block/{}", name)).is_ok()
+                && !name.starts_with("loop")
+                && !name.starts_with("ram");
+            if !is_disk {
+                return None;
+            }
+            Some((
+                fields.get(5)?.parse::<u64>().ok()?,
+                fields.get(9)?.parse::<u64>().ok()?,
+            ))
+        })
+        .fold((0, 0), |(r, w), (sr, sw)| {
+            (r + sr * SECTOR_BYTES, w + sw * SECTOR_BYTES)
+        })
+}
+
+fn read_network() -> (u64, u64) {
+    let dev = fs::read_to_string("/proc/net/dev").unwrap_or_default();
+    dev.lines()
+        .skip(2)
+        .filter_map(|line| {
+            let (name, rest) = line.split_once(':')?;
+            if name.trim() == "lo" {
+                return None;
+            }
+            let fields: Vec<u64> = rest
+                .split_whitespace()
+                .filter_map(|v| v.parse().ok())
+                .collect();
+            Some((*fields.first()?, *fields.get(8)?))
+        })
+        .fold((0, 0), |(rx, tx), (r, t)| (rx + r, tx + t))
+}
+
+// (used, total) in MiB
+pub fn read_memory() -> (u64, u64) {
+    let meminfo = fs::read_to_string("/proc/meminfo").unwrap_or_default();
+    let field = |name: &str| {
+        meminfo
+            .lines()
+            .find(|l| l.starts_with(name))
+            .and_then(|l| l.split_whitespace().nth(1))
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(0)
+    };
+    let total = field("MemTotal:");
+    let available = field("MemAvailable:");
+    (total.saturating_sub(available) / 1024, total / 1024)
+}
+
+fn read_counters(metrics: &[Metric]) -> Counters {
+    let mut counters = Counters::default();
+    if metrics.contains(&Metric::Cpu) {
+        (counters.cpu_busy, counters.cpu_total) = read_cpu();
+    }
+    if metrics.contains(&Metric::Disk) {
+        (counters.disk_read, counters.disk_write) = read_disk();
+    }
+    if metrics.contains(&Metric::Network) {
+        (counters.net_rx, counters.net_tx) = read_network();
+    }
+    counters
+}
+
+fn per_second(now: u64, before: u64, elapsed: Duration) -> u64 {
+    let secs = elapsed.as_secs_f64().max(0.001);
+    (now.saturating_sub(before) as f64 / secs) as u64
+}
+
+fn sample(
+    subscription: u32,
+    metrics: &[Metric],
+    now: &Counters,
+    before: &Counters,
+    elapsed: Duration,
+) -> ResourceSample {
+    let mut sample = ResourceSample {
+        subscription,
+        timestamp_ms: clock::now_ms(),
+        ..Default::default()
+    };
+    for metric in metrics {
+        match metric {
+            Metric::Cpu => {
+                let total = now.cpu_total.saturating_sub(before.cpu_total);
+                let busy = now.cpu_busy.saturating_sub(before.cpu_busy);
+                sample.cpu_percent =
+                    (total > 0).then(|| (busy as f64 / total as f64 * 1000.0).round() / 10.0);
+            }
+            Metric::Memory => {
+                let (used, total) = read_memory();
+                sample.memory_used_mb = Some(used);
+                sample.memory_total_mb = Some(total);
+            }
+            Metric::Disk => {
+                sample.disk_read_bps = Some(per_second(now.disk_read, before.disk_read, elapsed));
+                sample.disk_write_bps =
+                    Some(per_second(now.disk_write, before.disk_write, elapsed));
+            }
+            Metric::Network => {
+                sample.net_rx_bps = Some(per_second(now.net_rx, before.net_rx, elapsed));
+                sample.net_tx_bps = Some(per_second(now.net_tx, before.net_tx, elapsed));
+            }
+        }
+    }
+    sample
+}
+
+// Active subscriptions, kept in AppState
+#[derive(Default)]
+pub struct Monitor {
+    next_id: u32,
+    running: HashMap<u32, Arc<AtomicBool>>,
+}
+
+impl Monitor {
+    pub fn subscribe(&mut self, app: AppHandle, interval_ms: u64, metrics: Vec<Metric>) -> u32 {
+        self.next_id += 1;
+        let id = self.next_id;
+        let interval = Duration::from_millis(interval_ms.clamp(MIN_INTERVAL_MS, MAX_INTERVAL_MS));
+        let active = Arc::new(AtomicBool::new(true));
+        self.running.insert(id, active.clone());
+
+        std::thread::spawn(move || {
+            let mut before = read_counters(&metrics);
+            let mut last = Instant::now();
+            while active.load(Ordering::Relaxed) {
+                std::thread::sleep(interval);
+                let now = read_counters(&metrics);
+                let reading = sample(id, &metrics, &now, &before, last.elapsed());
+                (before, last) = (now, Instant::now());
+                if app.emit("resource-sample", reading).is_err() {
+                    break;
+                }
+            }
+        });
+        id
+    }
+
+    pub fn unsubscribe(&mut self, id: u32) -> bool {
+        match self.running.remove(&id) {
+            Some(active) => {
+                active.store(false, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn subscribe_resources(
+    interval_ms: Option<u64>,
+    metrics: Option<Vec<Metric>>,
+    app: AppHandle,
+) -> u32 {
+    let handle = app.clone();
+    tasks::blocking_value(&app, "Subscribe resources", move |state| {
+        let metrics = metrics
+            .unwrap_or_else(|| vec![Metric::Cpu, Metric::Memory, Metric::Disk, Metric::Network]);
+        state
+            .monitor
+            .blocking_lock()
+            .subscribe(handle, interval_ms.unwrap_or(1000), metrics)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn unsubscribe_resources(subscription: u32, app: AppHandle) -> bool {
+    tasks::blocking_value(&app, "Unsubscribe resources", move |state| {
+        state.monitor.blocking_lock().unsubscribe(subscription)
+    })
+    .await
+}
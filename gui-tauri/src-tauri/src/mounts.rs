@@ -0,0 +1,355 @@
+// Mount and external drive assistant
+//
+// Detects attached drives and network shares, mounts them now through udisks,
+// and/or adds declarative fileSystems entries with safe defaults (nofail,
+// automount) while warning about unstable device names.
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use tauri::AppHandle;
+
+use crate::nixgen::{self, NixModule, NixOption, Target};
+use crate::sandbox::Simulation;
+use crate::{sessions, storage, system, tasks};
+
+const DECLARED_FILE: &str = "mounts.json";
+const NETWORK_FS: &[&str] = &["nfs", "nfs4", "cifs", "smb3", "fuse.sshfs"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Drive {
+    pub path: String,
+    pub uuid: Option<String>,
+    pub label: Option<String>,
+    pub fs_type: Option<String>,
+    pub size: String,
+    pub mountpoint: Option<String>,
+    pub removable: bool,
+    pub transport: Option<String>,
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkShare {
+    pub source: String,
+    pub mountpoint: String,
+    pub fs_type: String,
+}
+
+// A declarative fileSystems entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MountEntry {
+    pub mountpoint: String,
+    pub device: String,
+    pub fs_type: String,
+    pub options: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MountPlan {
+    pub entry: MountEntry,
+    pub warnings: Vec<String>,
+    pub module_preview: String,
+    // Evaluation of the would-be configuration; nothing is written until it passes
+    #[serde(default)]
+    pub simulation: Simulation,
+}
+
+fn str_field(value: &serde_json::Value, name: &str) -> Option<String> {
+    value
+        .get(name)
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+}
+
+fn flatten_devices(
+    devices: &[serde_json::Value],
+    parent: Option<&serde_json::Value>,
+    out: &mut Vec<Drive>,
+) {
+    for device in devices {
+        let parent_or_self = parent.unwrap_or(device);
+        let kind = str_field(device, "type").unwrap_or_default();
+        if kind == "part" || (kind == "disk" && device.get("children").is_none()) {
+            if let Some(path) = str_field(device, "path") {
+                out.push(Drive {
+                    path,
+                    uuid: str_field(device, "uuid"),
+                    label: str_field(device, "label"),
+                    fs_type: str_field(device, "fstype"),
+                    size: str_field(device, "size").unwrap_or_default(),
+                    mountpoint: str_field(device, "mountpoint"),
+                    removable: device.get("rm").and_then(|v| v.as_bool()).unwrap_or(false)
+                        || device
+                            .get("hotplug")
+                            .and_then(|v| v.as_bool())
+                            .unwrap_or(false),
+                    transport: str_field(parent_or_self, "tran"),
+                    model: str_field(parent_or_self, "model"),
+                });
+            }
+        }
+        if let Some(children) = device.get("children").and_then(|c| c.as_array()) {
+            flatten_devices(children, Some(parent_or_self), out);
+        }
+    }
+}
+
+pub fn list_drives() -> anyhow::Result<Vec<Drive>> {
+    let output = system::run(
+        "lsblk",
+        &[
+            "--json",
+            "--output",
+            "NAME,PATH,UUID,LABEL,FSTYPE,SIZE,MOUNTPOINT,RM,HOTPLUG,TYPE,TRAN,MODEL",
+        ],
+    )?;
+    let parsed: serde_json::Value = serde_json::from_str(&output)?;
+    let mut drives = Vec::new();
+    if let Some(devices) = parsed.get("blockdevices").and_then(|d| d.as_array()) {
+        flatten_devices(devices, None, &mut drives);
+    }
+    Ok(drives)
+}
+
+pub fn list_network_shares() -> Vec<NetworkShare> {
+    let mounts = fs::read_to_string("/proc/mounts").unwrap_or_default();
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 3 || !NETWORK_FS.contains(&fields[2]) {
+                return None;
+            }
+            Some(NetworkShare {
+                source: fields[0].to_string(),
+                mountpoint: fields[1].replace("\\040", " "),
+                fs_type: fields[2].to_string(),
+            })
+        })
+        .collect()
+}
+
+// Mount immediately via udisks (no root needed for removable media)
+pub fn mount_now(device: &str) -> anyhow::Result<String> {
+    let output = sessions::exclusive(&format!("Mount {}", device), || {
+        system::run("udisksctl", &["mount", "--block-device", device])
+    })?;
+    // "Mounted /dev/sdb1 at /run/media/alice/USB"
+    Ok(output
+        .trim()
+        .rsplit_once(" at ")
+        .map(|(_, at)| at.trim_end_matches('.').to_string())
+        .unwrap_or_else(|| output.trim().to_string()))
+}
+
+fn default_options(fs_type: &str, network: bool) -> Vec<String> {
+    // nofail + automount: a missing drive never blocks boot, and it mounts on first access
+    let mut options = vec![
+        "nofail".to_string(),
+        "x-systemd.automount".to_string(),
+        "x-systemd.device-timeout=5s".to_string(),
+    ];
+    if network {
+        options.push("_netdev".to_string());
+        options.push("x-systemd.idle-timeout=600".to_string());
+    }
+    match fs_type {
+        "vfat" | "exfat" => {
+            options.push("uid=1000".to_string());
+            options.push("gid=100".to_string());
+            options.push("umask=022".to_string());
+        }
+        "ntfs" | "ntfs3" => options.push("uid=1000".to_string()),
+        "cifs" => {
+            options.push("credentials=/etc/nixos/smb-secrets".to_string());
+            options.push("uid=1000".to_string());
+        }
+        _ => {}
+    }
+    options
+}
+
+// Plan a declarative entry for a local drive; `device` may be a path, UUID or label
+pub fn plan_drive(device: &str, mountpoint: &str) -> anyhow::Result<MountPlan> {
+    let drives = list_drives()?;
+    let drive = drives
+        .iter()
+        .find(|d| {
+            d.path == device
+                || d.uuid.as_deref() == Some(device)
+                || d.label.as_deref() == Some(device)
+        })
+        .ok_or_else(|| anyhow!("No attached drive matches '{}'", device))?;
+
+    let mut warnings = Vec::new();
+    let stable_device = match (&drive.uuid, &drive.label) {
+        (Some(uuid), _) => format!("/dev/disk/by-uuid/{}", uuid),
+        (None, Some(label)) => {
+            warnings.push(
+                "This filesystem has no UUID; using its label, which must stay unique.".to_string(),
+            );
+            format!("/dev/disk/by-label/{}", label)
+        }
+        (None, None) => bail!(
+            "{} has no filesystem yet; format it before mounting",
+            drive.path
+        ),
+    };
+    if device.starts_with("/dev/sd") || device.starts_with("/dev/nvme") {
+        warnings.push(format!(
+            "{} can change between boots (e.g. when another USB drive is plugged in); the entry uses {} instead.",
+            device, stable_device
+        ));
+    }
+    if drive.removable {
+        warnings
+            .push("Removable drive: nofail keeps boot working when it is unplugged.".to_string());
+    }
+
+    let fs_type = match drive.fs_type.as_deref() {
+        Some("ntfs") => "ntfs3".to_string(),
+        Some(other) => other.to_string(),
+        None => bail!("Could not detect the filesystem type of {}", drive.path),
+    };
+    if fs_type == "crypto_LUKS" {
+        bail!(
+            "{} is encrypted; unlock it with boot.initrd.luks.devices first",
+            drive.path
+        );
+    }
+    Ok(finish_plan(
+        MountEntry {
+            mountpoint: mountpoint.to_string(),
+            device: stable_device,
+            options: default_options(&fs_type, false),
+            fs_type,
+        },
+        warnings,
+    ))
+}
+
+// Plan an entry for an NFS/SMB share given as "server:/export" or "//server/share"
+pub fn plan_share(source: &str, mountpoint: &str) -> anyhow::Result<MountPlan> {
+    let fs_type = if source.starts_with("//") {
+        "cifs"
+    } else if source.contains(":/") {
+        "nfs"
+    } else {
+        bail!(
+            "'{}' does not look like an NFS (server:/path) or SMB (//server/share) share",
+            source
+        );
+    };
+    let mut warnings = vec![
+        "Network shares are mounted on first access so boot never waits for the network."
+            .to_string(),
+    ];
+    if fs_type == "cifs" {
+        warnings.push("Put username=/password= in /etc/nixos/smb-secrets (mode 600) rather than in the config.".to_string());
+    }
+    Ok(finish_plan(
+        MountEntry {
+            mountpoint: mountpoint.to_string(),
+            device: source.to_string(),
+            fs_type: fs_type.to_string(),
+            options: default_options(fs_type, true),
+        },
+        warnings,
+    ))
+}
+
+fn finish_plan(entry: MountEntry, mut warnings: Vec<String>) -> MountPlan {
+    if !entry.mountpoint.starts_with('/') {
+        warnings.push("Mountpoints must be absolute paths.".to_string());
+    }
+    let mut declared: BTreeMap<String, MountEntry> =
+        storage::load(DECLARED_FILE).unwrap_or_default();
+    declared.insert(entry.mountpoint.clone(), entry.clone());
+    let module = build_module(&declared);
+    MountPlan {
+        module_preview: module.render(),
+        simulation: module.simulate(),
+        entry,
+        warnings,
+    }
+}
+
+fn build_module(declared: &BTreeMap<String, MountEntry>) -> NixModule {
+    let mut module = NixModule::new("mounts", "additional filesystems", Target::Nixos);
+    for entry in declared.values() {
+        let options: Vec<&str> = entry.options.iter().map(String::as_str).collect();
+        module.set(NixOption::new(
+            format!("fileSystems.{}", nixgen::string(&entry.mountpoint)),
+            format!(
+                "{{\n    device = {};\n    fsType = {};\n    options = {};\n  }}",
+                nixgen::string(&entry.device),
+                nixgen::string(&entry.fs_type),
+                nixgen::string_list(&options)
+            ),
+        ));
+    }
+    module
+}
+
+pub fn apply(entry: MountEntry) -> anyhow::Result<std::path::PathBuf> {
+    if !entry.mountpoint.starts_with('/') || entry.mountpoint == "/" {
+        bail!("Refusing to declare a mount at '{}'", entry.mountpoint);
+    }
+    let operation = format!("Declare the mount at {}", entry.mountpoint);
+    sessions::exclusive(&operation, || {
+        let mut declared: BTreeMap<String, MountEntry> = storage::load(DECLARED_FILE)?;
+        declared.insert(entry.mountpoint.clone(), entry);
+        storage::save(DECLARED_FILE, &declared)?;
+        build_module(&declared).write()
+    })
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn list_attached_drives(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "List attached drives", |_| {
+        crate::respond(list_drives().map(
+            |drives| serde_json::json!({"drives": drives, "network_shares": list_network_shares()}),
+        ))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn mount_drive_now(device: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Mount drive now", move |_| {
+        crate::respond(
+            mount_now(&device).map(|mountpoint| serde_json::json!({"mountpoint": mountpoint})),
+        )
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn plan_mount(source: String, mountpoint: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Plan mount", move |_| {
+        if source.starts_with("//") || (source.contains(":/") && !source.starts_with("/dev")) {
+            crate::respond(plan_share(&source, &mountpoint))
+        } else {
+            crate::respond(plan_drive(&source, &mountpoint))
+        }
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn apply_mount(entry: MountEntry, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Apply mount", move |_| {
+        crate::respond(apply(entry).map(|path| {
+            serde_json::json!({
+                "module_path": path,
+                "next_step": "Rebuild the system to create the mount",
+            })
+        }))
+    })
+    .await
+}
@@ -0,0 +1,56 @@
+// Wrappers around the nix CLI (search, profile listing)
+
+use serde::{Deserialize, Serialize};
+
+use crate::jsonstream;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Package {
+    pub attr: String,
+    pub name: String,
+    pub version: String,
+    pub description: String,
+}
+
+// "legacyPackages.x86_64-linux.python312Packages.numpy" -> "python312Packages.numpy"
+pub fn short_attr(attr: &str) -> String {
+    let parts: Vec<&str> = attr.split('.').collect();
+    if parts.len() > 2 && (parts[0] == "legacyPackages" || parts[0] == "packages") {
+        parts[2..].join(".")
+    } else {
+        attr.to_string()
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchEntry {
+    #[serde(default)]
+    pname: String,
+    #[serde(default)]
+    version: String,
+    #[serde(default)]
+    description: String,
+}
+
+// Call `f` with each match as nix prints it, without holding the whole result
+pub fn search_each(query: &str, mut f: impl FnMut(Package)) -> anyhow::Result<usize> {
+    jsonstream::run(
+        "nix",
+        &["search", "nixpkgs", query, "--json"],
+        |attr, entry: SearchEntry| {
+            f(Package {
+                attr: short_attr(&attr.unwrap_or_default()),
+                name: entry.pname,
+                version: entry.version,
+                description: entry.description,
+            })
+        },
+    )
+}
+
+pub fn search(query: &str) -> anyhow::Result<Vec<Package>> {
+    let mut packages = Vec::new();
+    search_each(query, |package| packages.push(package))?;
+    packages.sort_by(|a, b| a.attr.cmp(&b.attr));
+    Ok(packages)
+}
@@ -0,0 +1,412 @@
+// nix.conf settings manager
+//
+// Structured, validated access to the nix.conf settings people actually change.
+// On NixOS /etc/nix/nix.conf is generated, so writes go to `nix.settings` in a
+// generated module (or ~/.config/nix/nix.conf for per-user settings).
+
+use anyhow::{anyhow, bail, Context};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use tauri::AppHandle;
+
+use crate::nixgen::{self, NixModule, NixOption, Target};
+use crate::{managed, storage, system, tasks};
+
+const DECLARED_FILE: &str = "nix-settings.json";
+
+const KNOWN_EXPERIMENTAL: &[&str] = &[
+    "nix-command",
+    "flakes",
+    "ca-derivations",
+    "recursive-nix",
+    "impure-derivations",
+    "fetch-closure",
+    "auto-allocate-uids",
+    "cgroups",
+    "dynamic-derivations",
+    "parse-toml-timestamps",
+    "pipe-operators",
+    "configurable-impure-env",
+    "daemon-trust-override",
+    "local-overlay-store",
+    "mounted-ssh-store",
+    "verified-fetches",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingKind {
+    Jobs,
+    Integer,
+    Boolean,
+    List,
+}
+
+// A managed setting with the plain-language explanation shown next to it
+pub struct SettingSpec {
+    pub name: &'static str,
+    pub kind: SettingKind,
+    pub explanation: &'static str,
+    // Whether ~/.config/nix/nix.conf may set it (otherwise only the system config)
+    pub user_allowed: bool,
+}
+
+pub const SETTINGS: &[SettingSpec] = &[
+    SettingSpec {
+        name: "max-jobs",
+        kind: SettingKind::Jobs,
+        explanation: "How many derivations build at the same time. \"auto\" uses one per CPU core.",
+        user_allowed: false,
+    },
+    SettingSpec {
+        name: "cores",
+        kind: SettingKind::Integer,
+        explanation: "How many CPU cores a single build may use. 0 means all of them.",
+        user_allowed: false,
+    },
+    SettingSpec {
+        name: "experimental-features",
+        kind: SettingKind::List,
+        explanation: "Opt-in Nix features. \"nix-command flakes\" enables the modern `nix` CLI and flakes.",
+        user_allowed: true,
+    },
+    SettingSpec {
+        name: "trusted-users",
+        kind: SettingKind::List,
+        explanation: "Users allowed to change daemon settings and add substituters. Trusted users are effectively root.",
+        user_allowed: false,
+    },
+    SettingSpec {
+        name: "substituters",
+        kind: SettingKind::List,
+        explanation: "Binary caches Nix downloads prebuilt packages from, in order of preference.",
+        user_allowed: false,
+    },
+    SettingSpec {
+        name: "trusted-public-keys",
+        kind: SettingKind::List,
+        explanation: "Signing keys accepted for downloads from substituters.",
+        user_allowed: false,
+    },
+    SettingSpec {
+        name: "auto-optimise-store",
+        kind: SettingKind::Boolean,
+        explanation: "Hard-link identical files in the store after each build to save disk space.",
+        user_allowed: false,
+    },
+    SettingSpec {
+        name: "keep-outputs",
+        kind: SettingKind::Boolean,
+        explanation: "Keep build outputs of live derivations during garbage collection (handy for development shells).",
+        user_allowed: true,
+    },
+    SettingSpec {
+        name: "warn-dirty",
+        kind: SettingKind::Boolean,
+        explanation: "Warn when a flake's git tree has uncommitted changes.",
+        user_allowed: true,
+    },
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Setting {
+    pub name: String,
+    pub kind: SettingKind,
+    pub value: serde_json::Value,
+    pub default_value: Option<serde_json::Value>,
+    pub declared: Option<serde_json::Value>,
+    pub explanation: String,
+    pub user_allowed: bool,
+}
+
+pub fn spec(name: &str) -> anyhow::Result<&'static SettingSpec> {
+    SETTINGS
+        .iter()
+        .find(|s| s.name == name)
+        .ok_or_else(|| anyhow!("'{}' is not a setting the assistant manages", name))
+}
+
+fn effective_config() -> serde_json::Map<String, serde_json::Value> {
+    let output = system::run("nix", &["config", "show", "--json"])
+        .or_else(|_| system::run("nix", &["show-config", "--json"]))
+        .unwrap_or_default();
+    serde_json::from_str(&output).unwrap_or_default()
+}
+
+pub fn list() -> anyhow::Result<Vec<Setting>> {
+    let config = effective_config();
+    let declared: BTreeMap<String, serde_json::Value> = storage::load(DECLARED_FILE)?;
+    Ok(SETTINGS
+        .iter()
+        .map(|spec| {
+            let entry = config.get(spec.name);
+            Setting {
+                name: spec.name.to_string(),
+                kind: spec.kind,
+                value: entry
+                    .and_then(|e| e.get("value"))
+                    .cloned()
+                    .unwrap_or(serde_json::Value::Null),
+                default_value: entry.and_then(|e| e.get("defaultValue")).cloned(),
+                declared: declared.get(spec.name).cloned(),
+                explanation: spec.explanation.to_string(),
+                user_allowed: spec.user_allowed,
+            }
+        })
+        .collect())
+}
+
+// Validate and normalize a value for a setting; returns warnings worth showing
+pub fn validate(
+    spec: &SettingSpec,
+    value: &serde_json::Value,
+) -> anyhow::Result<(serde_json::Value, Vec<String>)> {
+    let mut warnings = Vec::new();
+    let normalized = match spec.kind {
+        SettingKind::Jobs => match value {
+            serde_json::Value::String(s) if s == "auto" => value.clone(),
+            serde_json::Value::Number(n) if n.as_u64().is_some() => value.clone(),
+            serde_json::Value::String(s) if s.parse::<u64>().is_ok() => {
+                serde_json::json!(s.parse::<u64>()?)
+            }
+            _ => bail!("max-jobs must be \"auto\" or a non-negative number"),
+        },
+        SettingKind::Integer => {
+            let n = value
+                .as_u64()
+                .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+                .ok_or_else(|| anyhow!("{} must be a non-negative number", spec.name))?;
+            serde_json::json!(n)
+        }
+        SettingKind::Boolean => {
+            let b = value
+                .as_bool()
+                .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+                .ok_or_else(|| anyhow!("{} must be true or false", spec.name))?;
+            serde_json::json!(b)
+        }
+        SettingKind::List => {
+            let items: Vec<String> = match value {
+                serde_json::Value::Array(items) => items
+                    .iter()
+                    .filter_map(|i| i.as_str().map(String::from))
+                    .collect(),
+                serde_json::Value::String(s) => s.split_whitespace().map(String::from).collect(),
+                _ => bail!("{} must be a list of strings", spec.name),
+            };
+            match spec.name {
+                "experimental-features" => {
+                    if let Some(unknown) = items
+                        .iter()
+                        .find(|i| !KNOWN_EXPERIMENTAL.contains(&i.as_str()))
+                    {
+                        bail!("Unknown experimental feature '{}'", unknown);
+                    }
+                    if items.iter().any(|i| i == "flakes")
+                        && !items.iter().any(|i| i == "nix-command")
+                    {
+                        warnings.push(
+                            "flakes are hard to use without nix-command; consider enabling both"
+                                .to_string(),
+                        );
+                    }
+                }
+                "trusted-users" if items.iter().any(|i| i != "root" && i != "@wheel") => {
+                    warnings.push(
+                        "Trusted users can bypass store signatures and effectively act as root"
+                            .to_string(),
+                    );
+                }
+                "substituters" => {
+                    if let Some(bad) = items.iter().find(|i| !i.contains("://")) {
+                        bail!("'{}' is not a substituter URL", bad);
+                    }
+                    if !items
+                        .iter()
+                        .any(|i| i.starts_with("https://cache.nixos.org"))
+                    {
+                        warnings.push(
+                            "cache.nixos.org is missing; most packages would build from source"
+                                .to_string(),
+                        );
+                    }
+                }
+                _ => {}
+            }
+            serde_json::json!(items)
+        }
+    };
+    Ok((normalized, warnings))
+}
+
+fn nix_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Array(items) => {
+            let items: Vec<&str> = items.iter().filter_map(|i| i.as_str()).collect();
+            nixgen::string_list(&items)
+        }
+        serde_json::Value::String(s) => nixgen::string(s),
+        other => other.to_string(),
+    }
+}
+
+fn conf_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Array(items) => items
+            .iter()
+            .filter_map(|i| i.as_str())
+            .collect::<Vec<_>>()
+            .join(" "),
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn write_module(
+    declared: &BTreeMap<String, serde_json::Value>,
+) -> anyhow::Result<std::path::PathBuf> {
+    let mut module = NixModule::new("nix-settings", "nix.conf settings", Target::Nixos);
+    for (name, value) in declared {
+        module.set(NixOption::new(
+            format!("nix.settings.{}", nixgen::attr(name)),
+            nix_value(value),
+        ));
+    }
+    module.write()
+}
+
+// Replace (or append) one `name = value` line in the user's nix.conf
+fn write_user_conf(name: &str, value: &serde_json::Value) -> anyhow::Result<std::path::PathBuf> {
+    let path = system::xdg_config_home().join("nix/nix.conf");
+    let existing = fs::read_to_string(&path).unwrap_or_default();
+    let line = format!("{} = {}", name, conf_value(value));
+    let mut replaced = false;
+    let mut lines: Vec<String> = existing
+        .lines()
+        .map(|l| {
+            if l.split('=').next().map(str::trim) == Some(name) {
+                replaced = true;
+                line.clone()
+            } else {
+                l.to_string()
+            }
+        })
+        .collect();
+    if !replaced {
+        lines.push(line);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, lines.join("\n") + "\n")
+        .with_context(|| format!("writing {}", path.display()))?;
+    Ok(path)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingChange {
+    pub name: String,
+    pub value: serde_json::Value,
+    pub warnings: Vec<String>,
+    pub written_to: std::path::PathBuf,
+    pub next_step: String,
+}
+
+pub fn set(
+    name: &str,
+    value: &serde_json::Value,
+    user_level: bool,
+) -> anyhow::Result<SettingChange> {
+    let spec = spec(name)?;
+    let (value, warnings) = validate(spec, value)?;
+    let (written_to, next_step) = if user_level {
+        if !spec.user_allowed {
+            bail!(
+                "{} can only be set system-wide; the daemon ignores it in a user nix.conf",
+                name
+            );
+        }
+        (
+            write_user_conf(name, &value)?,
+            "Takes effect for new nix commands".to_string(),
+        )
+    } else {
+        let mut declared: BTreeMap<String, serde_json::Value> = storage::load(DECLARED_FILE)?;
+        declared.insert(name.to_string(), value.clone());
+        storage::save(DECLARED_FILE, &declared)?;
+        (
+            write_module(&declared)?,
+            "Rebuild the system to apply the new nix.conf".to_string(),
+        )
+    };
+    Ok(SettingChange {
+        name: name.to_string(),
+        value,
+        warnings,
+        written_to,
+        next_step,
+    })
+}
+
+// "enable flakes": merge nix-command + flakes into whatever is already enabled
+pub fn enable_flakes(user_level: bool) -> anyhow::Result<SettingChange> {
+    let current = list()?
+        .into_iter()
+        .find(|s| s.name == "experimental-features")
+        .and_then(|s| s.declared.or(Some(s.value)))
+        .unwrap_or(serde_json::Value::Null);
+    let mut features: Vec<String> = current
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|i| i.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default();
+    for feature in ["nix-command", "flakes"] {
+        if !features.iter().any(|f| f == feature) {
+            features.push(feature.to_string());
+        }
+    }
+    set(
+        "experimental-features",
+        &serde_json::json!(features),
+        user_level,
+    )
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_nix_settings(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Get Nix settings", |_| crate::respond(list())).await
+}
+
+#[tauri::command]
+pub async fn set_nix_setting(
+    name: String,
+    value: serde_json::Value,
+    user_level: bool,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set Nix setting", move |_| {
+        if let Some(refusal) = managed::guard("configure", &format!("Set {}", name)) {
+            return refusal;
+        }
+        crate::respond(set(&name, &value, user_level))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn enable_nix_flakes(user_level: bool, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Enable Nix flakes", move |_| {
+        if let Some(refusal) = managed::guard("configure", "Enable Nix flakes") {
+            return refusal;
+        }
+        crate::respond(enable_flakes(user_level))
+    })
+    .await
+}
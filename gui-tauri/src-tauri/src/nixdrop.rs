@@ -0,0 +1,231 @@
+// Drop a .nix file on the window to see what it does and merge it in
+//
+// A dropped module is read into option assignments, like the compare view
+// does, and summarised: the packages it installs, the services it enables,
+// the overlays and imports it brings. The preview shows what merging it into
+// the system (or Home Manager) configuration would change, option by option.
+// Merging writes the options as a generated module, checked against the real
+// configuration first like every other generated module; imports are left out
+// since their relative paths wouldn't resolve from there.
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, DragDropEvent, Emitter, Manager, Window, WindowEvent};
+
+use crate::configdiff::{self, ConfigDiff, ConfigSource};
+use crate::nixgen::{NixModule, NixOption, Target};
+use crate::{explain, managed, system, tasks};
+
+const SYSTEM_CONFIG: &str = "/etc/nixos";
+// Bigger than any hand-written module
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NixFileAnalysis {
+    pub path: PathBuf,
+    pub target: Target,
+    pub packages: Vec<String>,
+    // Names of the services it enables: "openssh", "printing", ...
+    pub services: Vec<String>,
+    pub overlays: Vec<String>,
+    pub imports: Vec<String>,
+    // Every other option it sets, as (option, value)
+    pub options: Vec<(String, String)>,
+    pub warnings: Vec<String>,
+    // What merging would change; None without a configuration to merge into
+    pub preview: Option<ConfigDiff>,
+    // The module a merge would write
+    pub module: String,
+}
+
+fn is_package_option(option: &str) -> bool {
+    ["environment.systemPackages", "home.packages"].contains(&option)
+}
+
+// Home Manager options live under home.* and friends; everything else is NixOS
+fn target_of(assignments: &[(String, String)]) -> Target {
+    let home = assignments
+        .iter()
+        .any(|(option, _)| option.starts_with("home.") || option.starts_with("xdg."));
+    if home {
+        Target::HomeManager
+    } else {
+        Target::Nixos
+    }
+}
+
+fn base_config(target: Target) -> PathBuf {
+    match target {
+        Target::Nixos => PathBuf::from(SYSTEM_CONFIG),
+        Target::HomeManager => system::xdg_config_home().join("home-manager"),
+    }
+}
+
+// "imported-my-laptop" for my_laptop.nix
+fn module_name(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let stem: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    format!("imported-{}", stem.trim_matches('-'))
+}
+
+fn module(path: &Path, target: Target, assignments: &[(String, String)]) -> NixModule {
+    let mut module = NixModule::new(
+        &module_name(path),
+        &format!("options merged from {}", path.display()),
+        target,
+    );
+    for (option, value) in assignments.iter().filter(|(o, _)| o != "imports") {
+        module.set(NixOption::new(option.as_str(), value.as_str()));
+    }
+    module
+}
+
+fn read(path: &Path) -> anyhow::Result<(String, Vec<(String, String)>)> {
+    if path.extension().is_none_or(|e| e != "nix") {
+        bail!("{} isn't a .nix file", path.display());
+    }
+    let size = fs::metadata(path)
+        .with_context(|| format!("reading {}", path.display()))?
+        .len();
+    if size > MAX_FILE_BYTES {
+        bail!("{} is too big to be a configuration module", path.display());
+    }
+    let text = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let assignments = explain::assignments(&text);
+    if assignments.is_empty() {
+        bail!(
+            "{} doesn't set any options; it may be a package or a flake rather than a module",
+            path.display()
+        );
+    }
+    Ok((text, assignments))
+}
+
+pub fn analyze(path: &Path) -> anyhow::Result<NixFileAnalysis> {
+    let (text, assignments) = read(path)?;
+    let target = target_of(&assignments);
+    let mut analysis = NixFileAnalysis {
+        path: path.to_path_buf(),
+        target,
+        packages: Vec::new(),
+        services: Vec::new(),
+        overlays: Vec::new(),
+        imports: Vec::new(),
+        options: Vec::new(),
+        warnings: Vec::new(),
+        preview: None,
+        module: module(path, target, &assignments).render(),
+    };
+    for (option, value) in &assignments {
+        if is_package_option(option) {
+            analysis.packages.extend(explain::packages_in(value));
+        } else if option == "nixpkgs.overlays" {
+            analysis.overlays.push(value.clone());
+        } else if option == "imports" {
+            analysis.imports.extend(explain::packages_in(value));
+        } else if let Some(service) = option
+            .strip_prefix("services.")
+            .and_then(|o| o.strip_suffix(".enable"))
+            .filter(|_| value == "true")
+        {
+            analysis.services.push(service.to_string());
+        } else {
+            analysis.options.push((option.clone(), value.clone()));
+        }
+    }
+    if !analysis.imports.is_empty() {
+        analysis.warnings.push(
+            "Its imports aren't merged; copy the files they point to and import them yourself"
+                .to_string(),
+        );
+    }
+    let defines_names = text
+        .lines()
+        .map(str::trim)
+        .any(|l| l == "let" || l.starts_with("let "));
+    if defines_names {
+        analysis.warnings.push(
+            "It defines names with let; options that use them won't evaluate once merged"
+                .to_string(),
+        );
+    }
+    analysis.warnings.extend(explain::explain(text).warnings);
+
+    let base = base_config(target);
+    if base.exists() {
+        let base = ConfigSource::Path {
+            path: base.display().to_string(),
+        };
+        let addition = ConfigSource::Path {
+            path: path.display().to_string(),
+        };
+        analysis.preview = Some(configdiff::merge_preview(&base, &addition)?);
+    }
+    Ok(analysis)
+}
+
+// Write the dropped file's options as a generated module
+pub fn merge(path: &Path) -> anyhow::Result<serde_json::Value> {
+    let (_, assignments) = read(path)?;
+    let target = target_of(&assignments);
+    let module_path = module(path, target, &assignments).write()?;
+    Ok(serde_json::json!({
+        "module_path": module_path,
+        "next_step": match target {
+            Target::Nixos => "Rebuild the system to apply the merged options",
+            Target::HomeManager => "Run home-manager switch to apply the merged options",
+        },
+    }))
+}
+
+// Files dropped on a window are analysed off the event loop; the analysis, or
+// why there is none, goes back to that window as "nix-file-dropped"
+pub fn window_event(window: &Window, event: &WindowEvent) {
+    let WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) = event else {
+        return;
+    };
+    let app: AppHandle = window.app_handle().clone();
+    let label = window.label().to_string();
+    let paths = paths.clone();
+    std::thread::spawn(move || {
+        for path in paths {
+            let _ = app.emit_to(
+                label.as_str(),
+                "nix-file-dropped",
+                crate::respond(analyze(&path)),
+            );
+        }
+    });
+}
+
+// ========== Tauri Commands ==========
+
+// The same analysis for a file picked in a dialog
+#[tauri::command]
+pub async fn analyze_nix_file(path: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Analyze Nix file", move |_| {
+        crate::respond(analyze(Path::new(&path)))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn merge_nix_file(path: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Merge Nix file", move |_| {
+        if let Some(refusal) =
+            managed::guard("configure", "Merge a Nix file into the configuration")
+        {
+            return refusal;
+        }
+        crate::respond(merge(Path::new(&path)))
+    })
+    .await
+}
@@ -0,0 +1,167 @@
+// Intent-to-nix generator: renders declarative option sets as Nix modules
+//
+// Generated modules live under ~/.config/luminous-nix/modules/{nixos,home}/ and
+// are imported once from configuration.nix / home.nix, so the assistant never
+// has to rewrite hand-edited files.
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::sandbox;
+use crate::storage;
+
+// Which configuration a generated module belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Target {
+    Nixos,
+    HomeManager,
+}
+
+impl Target {
+    fn dir_name(self) -> &'static str {
+        match self {
+            Target::Nixos => "nixos",
+            Target::HomeManager => "home",
+        }
+    }
+}
+
+// A single `path = value;` assignment; `value` is already a Nix expression
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NixOption {
+    pub path: String,
+    pub value: String,
+    pub comment: Option<String>,
+}
+
+impl NixOption {
+    pub fn new(path: impl Into<String>, value: impl Into<String>) -> Self {
+        NixOption {
+            path: path.into(),
+            value: value.into(),
+            comment: None,
+        }
+    }
+
+    pub fn with_comment(mut self, comment: impl Into<String>) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NixModule {
+    pub name: String,
+    pub description: String,
+    pub target: Target,
+    pub options: Vec<NixOption>,
+}
+
+impl NixModule {
+    pub fn new(name: &str, description: &str, target: Target) -> Self {
+        NixModule {
+            name: name.to_string(),
+            description: description.to_string(),
+            target,
+            options: Vec::new(),
+        }
+    }
+
+    pub fn set(&mut self, option: NixOption) {
+        self.options.push(option);
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "# Generated by Luminous Nix: {}\n# Changes here are overwritten; edit through the assistant instead.\n{{ config, lib, pkgs, ... }}:\n{{\n",
+            self.description
+        );
+        for option in &self.options {
+            if let Some(comment) = &option.comment {
+                out.push_str(&format!("  # {}\n", comment));
+            }
+            out.push_str(&format!("  {} = {};\n", option.path, option.value));
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    pub fn path(&self) -> PathBuf {
+        modules_dir(self.target).join(format!("{}.nix", self.name))
+    }
+
+    // Evaluate the configuration as it would be with this module in place
+    pub fn simulate(&self) -> sandbox::Simulation {
+        sandbox::simulate(self)
+    }
+
+    // Write the module to its managed location and return the path; refuses
+    // when the would-be configuration fails to evaluate
+    pub fn write(&self) -> anyhow::Result<PathBuf> {
+        let simulation = self.simulate();
+        if simulation.failed() {
+            bail!(
+                "The change to {} doesn't evaluate: {}",
+                self.name,
+                simulation.errors.join("; ")
+            );
+        }
+        let path = self.path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, self.render()).with_context(|| format!("writing {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+pub fn modules_dir(target: Target) -> PathBuf {
+    storage::config_dir()
+        .join("modules")
+        .join(target.dir_name())
+}
+
+// ========== Nix value helpers ==========
+
+pub fn string(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("${", "\\${");
+    format!("\"{}\"", escaped)
+}
+
+pub fn bool(value: bool) -> String {
+    value.to_string()
+}
+
+pub fn list(items: &[String]) -> String {
+    if items.is_empty() {
+        return "[ ]".to_string();
+    }
+    format!("[ {} ]", items.join(" "))
+}
+
+pub fn string_list(items: &[&str]) -> String {
+    list(&items.iter().map(|s| string(s)).collect::<Vec<_>>())
+}
+
+// Quote an attribute name when it is not a plain identifier (e.g. "text/html")
+pub fn attr(name: &str) -> String {
+    let plain = name
+        .chars()
+        .next()
+        .map(|c| c.is_ascii_alphabetic() || c == '_')
+        .unwrap_or(false)
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '\'');
+    if plain {
+        name.to_string()
+    } else {
+        string(name)
+    }
+}
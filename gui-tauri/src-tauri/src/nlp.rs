@@ -0,0 +1,853 @@
+// Rule-based intent parser
+//
+// Turns free text ("please install firefox and vim for everyone") into a typed
+// Intent plus extracted entities, so the frontend no longer has to pick raw
+// action strings for perform_action.
+
+use serde::{Deserialize, Serialize};
+
+use crate::i18n;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Intent {
+    Install {
+        packages: Vec<String>,
+    },
+    Remove {
+        packages: Vec<String>,
+    },
+    Search {
+        query: String,
+    },
+    ListInstalled,
+    Update,
+    Rollback {
+        generation: Option<u32>,
+    },
+    GarbageCollect,
+    // Boot management; these only come from the boot manager, not from text
+    SetBootDefault {
+        generation: u32,
+    },
+    DeleteGenerations {
+        generations: Vec<u32>,
+    },
+    SwitchSpecialisation {
+        name: Option<String>,
+    },
+    Explain {
+        topic: String,
+    },
+    Configure {
+        setting: String,
+        enable: Option<bool>,
+    },
+    SetDefaultApp {
+        app: String,
+        role: String,
+    },
+    ScaffoldProject {
+        template: String,
+        path: Option<String>,
+    },
+    // An action contributed through the plugin registry
+    Plugin {
+        plugin: String,
+        action: String,
+        args: Vec<String>,
+    },
+    Unknown,
+}
+
+// Entities that modify how an intent is carried out
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Entities {
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParsedIntent {
+    pub intent: Intent,
+    pub entities: Entities,
+    pub confidence: f32,
+    pub raw: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Verb {
+    ListInstalled,
+    GarbageCollect,
+    Rollback,
+    Remove,
+    Search,
+    Update,
+    Explain,
+    Enable,
+    Disable,
+    Configure,
+    Install,
+}
+
+// Checked in order, so more specific phrases must come before their substrings
+const VERBS: &[(&str, Verb)] = &[
+    ("what's installed", Verb::ListInstalled),
+    ("what is installed", Verb::ListInstalled),
+    ("list installed", Verb::ListInstalled),
+    ("show installed", Verb::ListInstalled),
+    ("list packages", Verb::ListInstalled),
+    ("list my packages", Verb::ListInstalled),
+    ("delete all old generations", Verb::GarbageCollect),
+    ("delete old generations", Verb::GarbageCollect),
+    ("remove old generations", Verb::GarbageCollect),
+    ("garbage collection", Verb::GarbageCollect),
+    ("garbage collect", Verb::GarbageCollect),
+    ("collect garbage", Verb::GarbageCollect),
+    ("free up space", Verb::GarbageCollect),
+    ("free space", Verb::GarbageCollect),
+    ("clean up", Verb::GarbageCollect),
+    ("roll back", Verb::Rollback),
+    ("rollback", Verb::Rollback),
+    ("go back", Verb::Rollback),
+    ("revert", Verb::Rollback),
+    ("undo", Verb::Rollback),
+    ("get rid of", Verb::Remove),
+    ("uninstall", Verb::Remove),
+    ("remove", Verb::Remove),
+    ("delete", Verb::Remove),
+    ("search for", Verb::Search),
+    ("look for", Verb::Search),
+    ("is there", Verb::Search),
+    ("search", Verb::Search),
+    ("find", Verb::Search),
+    ("update", Verb::Update),
+    ("upgrade", Verb::Update),
+    ("tell me about", Verb::Explain),
+    ("what is", Verb::Explain),
+    ("what's", Verb::Explain),
+    ("what are", Verb::Explain),
+    ("explain", Verb::Explain),
+    ("turn on", Verb::Enable),
+    ("enable", Verb::Enable),
+    ("turn off", Verb::Disable),
+    ("disable", Verb::Disable),
+    ("configure", Verb::Configure),
+    ("install", Verb::Install),
+    ("set up", Verb::Install),
+    ("i need", Verb::Install),
+    ("add", Verb::Install),
+    ("get", Verb::Install),
+];
+
+const POLITENESS: &[&str] = &[
+    "please",
+    "can you",
+    "could you",
+    "would you",
+    "will you",
+    "i want to",
+    "i'd like to",
+    "i would like to",
+    "help me",
+    "let's",
+    "lets",
+    "hey",
+];
+
+const FILLER: &[&str] = &[
+    "a",
+    "an",
+    "the",
+    "some",
+    "me",
+    "my",
+    "package",
+    "packages",
+    "program",
+    "programs",
+    "app",
+    "apps",
+    "called",
+    "named",
+    "application",
+    "new",
+    "latest",
+];
+
+// Colloquial names -> nixpkgs attribute names
+const PACKAGE_ALIASES: &[(&str, &str)] = &[
+    ("chrome", "google-chrome"),
+    ("google chrome", "google-chrome"),
+    ("vs code", "vscode"),
+    ("visual studio code", "vscode"),
+    ("code", "vscode"),
+    ("python", "python3"),
+    ("node", "nodejs"),
+    ("node.js", "nodejs"),
+    ("neo vim", "neovim"),
+    ("nvim", "neovim"),
+    ("libre office", "libreoffice"),
+    ("obs", "obs-studio"),
+    ("steam", "steam"),
+];
+
+// The profile phrases we recognise, mapped to profile ids
+const PROFILE_PHRASES: &[(&str, &str)] = &[
+    ("for everyone", "system"),
+    ("for all users", "system"),
+    ("system-wide", "system"),
+    ("system wide", "system"),
+    ("for the system", "system"),
+    ("just for me", "user"),
+    ("only for me", "user"),
+    ("for me", "user"),
+    ("for my user", "user"),
+];
+
+pub fn normalize(text: &str) -> String {
+    let lowered = text.trim().to_lowercase();
+    let mut text = i18n::to_english(lowered.trim_end_matches(['?', '!', '.', '¿', '¡']))
+        .trim_start_matches(['¿', '¡'])
+        .to_string();
+    loop {
+        let before = text.clone();
+        for phrase in POLITENESS {
+            if let Some(rest) = text.strip_prefix(phrase) {
+                if rest.is_empty() || rest.starts_with([' ', ',']) {
+                    text = rest.trim_start_matches([' ', ',']).to_string();
+                }
+            }
+            if let Some(rest) = text.strip_suffix(phrase) {
+                if rest.ends_with([' ', ',']) {
+                    text = rest.trim_end_matches([' ', ',']).to_string();
+                }
+            }
+        }
+        if text == before {
+            return text;
+        }
+    }
+}
+
+// Find a phrase as whole words; returns its byte offset
+pub fn find_phrase(text: &str, phrase: &str) -> Option<usize> {
+    let mut start = 0;
+    while let Some(pos) = text[start..].find(phrase) {
+        let at = start + pos;
+        let end = at + phrase.len();
+        let before_ok = at == 0 || !text.as_bytes()[at - 1].is_ascii_alphanumeric();
+        let after_ok = end == text.len() || !text.as_bytes()[end].is_ascii_alphanumeric();
+        if before_ok && after_ok {
+            return Some(at);
+        }
+        start = at + 1;
+    }
+    None
+}
+
+fn extract_profile(text: &str) -> (String, Option<String>) {
+    for (phrase, profile) in PROFILE_PHRASES {
+        if let Some(at) = find_phrase(text, phrase) {
+            let stripped = format!("{} {}", &text[..at], &text[at + phrase.len()..]);
+            return (stripped.trim().to_string(), Some(profile.to_string()));
+        }
+    }
+    // "in the <name> profile" / "to my <name> profile"
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if let Some(pos) = words.iter().position(|w| *w == "profile") {
+        if pos >= 2 && matches!(words[pos - 2], "in" | "to" | "into" | "the" | "my") {
+            let name = words[pos - 1].to_string();
+            let mut rest: Vec<&str> = words[..pos.saturating_sub(2)].to_vec();
+            if pos >= 3 && matches!(words[pos - 2], "the" | "my") {
+                rest.pop();
+            }
+            rest.extend_from_slice(&words[pos + 1..]);
+            return (rest.join(" "), Some(name));
+        }
+    }
+    (text.to_string(), None)
+}
+
+fn strip_filler(phrase: &str) -> String {
+    let words: Vec<&str> = phrase
+        .split_whitespace()
+        .skip_while(|w| FILLER.contains(w))
+        .collect();
+    words.join(" ")
+}
+
+pub fn resolve_package_alias(name: &str) -> String {
+    PACKAGE_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map(|(_, attr)| attr.to_string())
+        .unwrap_or_else(|| name.replace(' ', "-"))
+}
+
+// "firefox, vim and git" -> ["firefox", "vim", "git"]
+pub fn extract_packages(rest: &str) -> Vec<String> {
+    rest.replace(" and ", ",")
+        .replace(" & ", ",")
+        .replace(" plus ", ",")
+        .split(',')
+        .map(strip_filler)
+        .filter(|p| !p.is_empty())
+        .map(|p| resolve_package_alias(&p))
+        .collect()
+}
+
+fn first_number(text: &str) -> Option<u32> {
+    text.split(|c: char| !c.is_ascii_digit())
+        .find(|s| !s.is_empty())
+        .and_then(|s| s.parse().ok())
+}
+
+// "make firefox my default browser" / "set vlc as the default video player"
+fn parse_default_app(text: &str) -> Option<Intent> {
+    let at = find_phrase(text, "default")?;
+    let before = text[..at].trim();
+    let role = text[at + "default".len()..]
+        .trim()
+        .trim_start_matches("for ")
+        .replace(' ', "-");
+    let app = before
+        .strip_prefix("make ")
+        .or_else(|| before.strip_prefix("set "))
+        .or_else(|| before.strip_prefix("use "))?;
+    let app = app
+        .trim_end_matches(" the")
+        .trim_end_matches(" my")
+        .trim_end_matches(" as")
+        .trim();
+    if app.is_empty() || role.is_empty() {
+        return None;
+    }
+    Some(Intent::SetDefaultApp {
+        app: app.to_string(),
+        role,
+    })
+}
+
+// "start a new rust project in ~/code/hello"
+fn parse_scaffold(text: &str) -> Option<Intent> {
+    let at = find_phrase(text, "project")?;
+    let head = text[..at].trim();
+    let head = head
+        .strip_prefix("start")
+        .or_else(|| head.strip_prefix("create"))
+        .or_else(|| head.strip_prefix("scaffold"))
+        .or_else(|| head.strip_prefix("make"))?;
+    let template = strip_filler(head.trim());
+    let path = text[at + "project".len()..]
+        .split_whitespace()
+        .find(|w| !matches!(*w, "in" | "at" | "under" | "called" | "named"))
+        .map(String::from);
+    Some(Intent::ScaffoldProject {
+        template: if template.is_empty() {
+            "default".to_string()
+        } else {
+            template
+        },
+        path,
+    })
+}
+
+fn intent_for(verb: Verb, rest: &str) -> Intent {
+    match verb {
+        Verb::ListInstalled => Intent::ListInstalled,
+        Verb::GarbageCollect => Intent::GarbageCollect,
+        Verb::Update => Intent::Update,
+        Verb::Rollback => Intent::Rollback {
+            generation: first_number(rest),
+        },
+        Verb::Remove => Intent::Remove {
+            packages: extract_packages(rest),
+        },
+        Verb::Install => Intent::Install {
+            packages: extract_packages(rest),
+        },
+        Verb::Search => Intent::Search {
+            query: strip_filler(rest),
+        },
+        Verb::Explain => Intent::Explain {
+            topic: strip_filler(rest),
+        },
+        Verb::Enable | Verb::Disable | Verb::Configure => Intent::Configure {
+            setting: strip_filler(rest),
+            enable: match verb {
+                Verb::Enable => Some(true),
+                Verb::Disable => Some(false),
+                _ => None,
+            },
+        },
+    }
+}
+
+// An install/remove/search without an object is not actionable
+fn is_incomplete(intent: &Intent) -> bool {
+    match intent {
+        Intent::Install { packages } | Intent::Remove { packages } => packages.is_empty(),
+        Intent::Search { query } => query.is_empty(),
+        Intent::Explain { topic } => topic.is_empty(),
+        Intent::Configure { setting, .. } => setting.is_empty(),
+        _ => false,
+    }
+}
+
+// Every plausible reading of the query, most confident first
+pub fn parse_all(query: &str) -> Vec<ParsedIntent> {
+    let normalized = normalize(query);
+    let (text, profile) = extract_profile(&normalized);
+    let entities = Entities { profile };
+    let done = |intent: Intent, confidence: f32| ParsedIntent {
+        intent,
+        entities: entities.clone(),
+        confidence,
+        raw: query.to_string(),
+    };
+
+    if let Some(intent) = parse_default_app(&text) {
+        return vec![done(intent, 0.9)];
+    }
+    if let Some(intent) = parse_scaffold(&text) {
+        return vec![done(intent, 0.85)];
+    }
+
+    let mut matches: Vec<(usize, &str, Verb)> = VERBS
+        .iter()
+        .filter_map(|(phrase, verb)| find_phrase(&text, phrase).map(|at| (at, *phrase, *verb)))
+        .collect();
+    // Stable sort keeps the VERBS order for phrases starting at the same spot
+    matches.sort_by_key(|(at, _, _)| *at);
+
+    let mut readings: Vec<ParsedIntent> = Vec::new();
+    let mut covered_until = 0;
+    for (at, phrase, verb) in matches {
+        // Skip substrings of a phrase already used ("get" inside "get rid of")
+        if at < covered_until {
+            continue;
+        }
+        covered_until = at + phrase.len();
+        let intent = intent_for(verb, text[covered_until..].trim());
+        if readings.iter().any(|r| r.intent == intent) {
+            continue;
+        }
+        // Verbs leading the sentence are far more reliable than ones found mid-way
+        let confidence = if at == 0 { 0.9 } else { 0.6 };
+        let confidence = if is_incomplete(&intent) {
+            confidence / 3.0
+        } else {
+            confidence
+        };
+        readings.push(done(intent, confidence));
+    }
+    readings.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    readings
+}
+
+pub fn parse(query: &str) -> ParsedIntent {
+    parse_all(query)
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| ParsedIntent {
+            intent: Intent::Unknown,
+            entities: Entities {
+                profile: extract_profile(&normalize(query)).1,
+            },
+            confidence: 0.0,
+            raw: query.to_string(),
+        })
+}
+
+// Words that chain steps: "install firefox then run garbage collection"
+const SEQUENCERS: &[&str] = &[
+    ", and then ",
+    " and then ",
+    ", then ",
+    " then ",
+    " after that ",
+    "; ",
+];
+
+// Split a compound request into its steps. "and" only separates steps when
+// a new verb follows it, so "install firefox and vim" stays one step.
+pub fn split_steps(query: &str) -> Vec<String> {
+    let mut text = normalize(query);
+    for sequencer in SEQUENCERS {
+        text = text.replace(sequencer, "\n");
+    }
+    let mut steps = Vec::new();
+    for segment in text.split('\n') {
+        let mut current = String::new();
+        for (index, part) in segment.split(" and ").enumerate() {
+            let starts_with_verb = VERBS
+                .iter()
+                .any(|(phrase, _)| find_phrase(part.trim(), phrase) == Some(0));
+            if index > 0 && !starts_with_verb {
+                current.push_str(" and ");
+            } else if index > 0 {
+                steps.push(std::mem::take(&mut current));
+            }
+            current.push_str(part);
+        }
+        steps.push(current);
+    }
+    steps
+        .into_iter()
+        .map(|s| s.trim().trim_start_matches("run ").to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+impl Intent {
+    // Every kind() there is, for policies that name kinds
+    pub const KINDS: &'static [&'static str] = &[
+        "install",
+        "remove",
+        "search",
+        "list_installed",
+        "update",
+        "rollback",
+        "garbage_collect",
+        "set_boot_default",
+        "delete_generations",
+        "switch_specialisation",
+        "explain",
+        "configure",
+        "set_default_app",
+        "scaffold_project",
+        "plugin",
+        "unknown",
+    ];
+
+    // The serialized `kind` tag, for matching and policies. A new kind goes in
+    // KINDS as well
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Intent::Install { .. } => "install",
+            Intent::Remove { .. } => "remove",
+            Intent::Search { .. } => "search",
+            Intent::ListInstalled => "list_installed",
+            Intent::Update => "update",
+            Intent::Rollback { .. } => "rollback",
+            Intent::GarbageCollect => "garbage_collect",
+            Intent::SetBootDefault { .. } => "set_boot_default",
+            Intent::DeleteGenerations { .. } => "delete_generations",
+            Intent::SwitchSpecialisation { .. } => "switch_specialisation",
+            Intent::Explain { .. } => "explain",
+            Intent::Configure { .. } => "configure",
+            Intent::SetDefaultApp { .. } => "set_default_app",
+            Intent::ScaffoldProject { .. } => "scaffold_project",
+            Intent::Plugin { .. } => "plugin",
+            Intent::Unknown => "unknown",
+        }
+    }
+
+    // One-line, human readable summary used in confirmations and clarifications,
+    // in the active language
+    pub fn describe(&self) -> String {
+        let m = i18n::message;
+        match self {
+            Intent::Install { packages } => {
+                m("intent-install", &[("packages", &packages.join(", "))])
+            }
+            Intent::Remove { packages } => {
+                m("intent-remove", &[("packages", &packages.join(", "))])
+            }
+            Intent::Search { query } => m("intent-search", &[("query", query)]),
+            Intent::ListInstalled => m("intent-list-installed", &[]),
+            Intent::Update => m("intent-update", &[]),
+            Intent::Rollback {
+                generation: Some(g),
+            } => m("intent-rollback-to", &[("generation", &g.to_string())]),
+            Intent::Rollback { generation: None } => m("intent-rollback", &[]),
+            Intent::GarbageCollect => m("intent-garbage-collect", &[]),
+            Intent::SetBootDefault { generation } => m(
+                "intent-set-boot-default",
+                &[("generation", &generation.to_string())],
+            ),
+            Intent::DeleteGenerations { generations } => {
+                let numbers: Vec<String> = generations.iter().map(u32::to_string).collect();
+                m(
+                    "intent-delete-generations",
+                    &[("generations", &numbers.join(", "))],
+                )
+            }
+            Intent::SwitchSpecialisation { name: Some(name) } => {
+                m("intent-switch-specialisation", &[("name", name)])
+            }
+            Intent::SwitchSpecialisation { name: None } => m("intent-leave-specialisation", &[]),
+            Intent::Explain { topic } => m("intent-explain", &[("topic", topic)]),
+            Intent::Configure {
+                setting,
+                enable: Some(true),
+            } => m("intent-enable", &[("setting", setting)]),
+            Intent::Configure {
+                setting,
+                enable: Some(false),
+            } => m("intent-disable", &[("setting", setting)]),
+            Intent::Configure { setting, .. } => m("intent-configure", &[("setting", setting)]),
+            Intent::SetDefaultApp { app, role } => {
+                m("intent-set-default-app", &[("app", app), ("role", role)])
+            }
+            Intent::ScaffoldProject { template, .. } => {
+                m("intent-scaffold-project", &[("template", template)])
+            }
+            Intent::Plugin {
+                plugin,
+                action,
+                args,
+            } => {
+                let mut action = action.replace('_', " ");
+                if !args.is_empty() {
+                    action = format!("{} {}", action, args.join(" "));
+                }
+                m("intent-plugin", &[("plugin", plugin), ("action", &action)])
+            }
+            Intent::Unknown => m("intent-unknown", &[]),
+        }
+    }
+}
+
+fn param<'a>(params: &'a serde_json::Value, name: &str) -> Option<&'a str> {
+    params.get(name).and_then(|v| v.as_str())
+}
+
+// Map the legacy (action, params) pairs onto intents
+pub fn from_action(action: &str, params: &serde_json::Value) -> Option<Intent> {
+    let packages = || -> Vec<String> {
+        match params.get("packages").and_then(|p| p.as_array()) {
+            Some(list) => list
+                .iter()
+                .filter_map(|p| p.as_str().map(String::from))
+                .collect(),
+            None => param(params, "package")
+                .map(String::from)
+                .into_iter()
+                .collect(),
+        }
+    };
+    let intent = match action {
+        "search" => Intent::Search {
+            query: param(params, "query").unwrap_or("").to_string(),
+        },
+        "install" => Intent::Install {
+            packages: packages(),
+        },
+        "remove" => Intent::Remove {
+            packages: packages(),
+        },
+        "list" => Intent::ListInstalled,
+        "update" => Intent::Update,
+        "rollback" => Intent::Rollback {
+            generation: params
+                .get("generation")
+                .and_then(|g| g.as_u64())
+                .map(|g| g as u32),
+        },
+        "garbage_collect" => Intent::GarbageCollect,
+        "set_boot_default" => Intent::SetBootDefault {
+            generation: params.get("generation")?.as_u64()? as u32,
+        },
+        "delete_generations" => Intent::DeleteGenerations {
+            generations: params
+                .get("generations")?
+                .as_array()?
+                .iter()
+                .filter_map(|g| g.as_u64().map(|g| g as u32))
+                .collect(),
+        },
+        "switch_specialisation" => Intent::SwitchSpecialisation {
+            name: param(params, "name").map(String::from),
+        },
+        "explain" => Intent::Explain {
+            topic: param(params, "topic").unwrap_or("").to_string(),
+        },
+        "configure" => Intent::Configure {
+            setting: param(params, "setting").unwrap_or("").to_string(),
+            enable: params.get("enable").and_then(|e| e.as_bool()),
+        },
+        "set_default_app" => Intent::SetDefaultApp {
+            app: param(params, "app").unwrap_or("").to_string(),
+            role: param(params, "role")
+                .or_else(|| param(params, "mime_type"))
+                .unwrap_or("")
+                .to_string(),
+        },
+        "scaffold_project" => Intent::ScaffoldProject {
+            template: param(params, "template").unwrap_or("").to_string(),
+            path: param(params, "path").map(String::from),
+        },
+        _ => return None,
+    };
+    Some(intent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packages(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn install_takes_every_package_and_the_profile() {
+        let parsed = parse("Please install firefox and vim for everyone");
+        assert_eq!(
+            parsed.intent,
+            Intent::Install {
+                packages: packages(&["firefox", "vim"])
+            }
+        );
+        assert_eq!(parsed.entities.profile.as_deref(), Some("system"));
+        assert_eq!(parsed.confidence, 0.9);
+    }
+
+    #[test]
+    fn colloquial_names_become_attributes() {
+        assert_eq!(
+            parse("install vs code and chrome").intent,
+            Intent::Install {
+                packages: packages(&["vscode", "google-chrome"])
+            }
+        );
+    }
+
+    #[test]
+    fn longer_phrases_win_over_their_substrings() {
+        assert_eq!(
+            parse("get rid of firefox").intent,
+            Intent::Remove {
+                packages: packages(&["firefox"])
+            }
+        );
+        assert_eq!(
+            parse("search for a text editor").intent,
+            Intent::Search {
+                query: "text editor".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn rollback_picks_up_a_generation() {
+        assert_eq!(
+            parse("roll back to generation 42").intent,
+            Intent::Rollback {
+                generation: Some(42)
+            }
+        );
+        assert_eq!(parse("undo").intent, Intent::Rollback { generation: None });
+    }
+
+    #[test]
+    fn named_profiles_are_taken_out_of_the_packages() {
+        let parsed = parse("install git in the work profile");
+        assert_eq!(
+            parsed.intent,
+            Intent::Install {
+                packages: packages(&["git"])
+            }
+        );
+        assert_eq!(parsed.entities.profile.as_deref(), Some("work"));
+    }
+
+    #[test]
+    fn default_apps_and_projects_have_their_own_shapes() {
+        assert_eq!(
+            parse("make firefox my default browser").intent,
+            Intent::SetDefaultApp {
+                app: "firefox".to_string(),
+                role: "browser".to_string()
+            }
+        );
+        assert_eq!(
+            parse("start a new rust project in ~/code/hello").intent,
+            Intent::ScaffoldProject {
+                template: "rust".to_string(),
+                path: Some("~/code/hello".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn a_verb_without_an_object_is_doubtful() {
+        let parsed = parse("install");
+        assert_eq!(parsed.intent, Intent::Install { packages: vec![] });
+        assert!((parsed.confidence - 0.3).abs() < 1e-4);
+    }
+
+    #[test]
+    fn nothing_recognised_is_unknown() {
+        let parsed = parse("hello there");
+        assert_eq!(parsed.intent, Intent::Unknown);
+        assert_eq!(parsed.confidence, 0.0);
+    }
+
+    #[test]
+    fn steps_split_on_sequencers_and_new_verbs() {
+        assert_eq!(
+            split_steps("install firefox and vim then run garbage collection"),
+            vec!["install firefox and vim", "garbage collection"]
+        );
+        assert_eq!(
+            split_steps("install firefox and remove vim"),
+            vec!["install firefox", "remove vim"]
+        );
+    }
+
+    #[test]
+    fn every_kind_is_listed() {
+        let intents = [
+            Intent::Install { packages: vec![] },
+            Intent::Remove { packages: vec![] },
+            Intent::Search {
+                query: String::new(),
+            },
+            Intent::ListInstalled,
+            Intent::Update,
+            Intent::Rollback { generation: None },
+            Intent::GarbageCollect,
+            Intent::SetBootDefault { generation: 1 },
+            Intent::DeleteGenerations {
+                generations: vec![],
+            },
+            Intent::SwitchSpecialisation { name: None },
+            Intent::Explain {
+                topic: String::new(),
+            },
+            Intent::Configure {
+                setting: String::new(),
+                enable: None,
+            },
+            Intent::SetDefaultApp {
+                app: String::new(),
+                role: String::new(),
+            },
+            Intent::ScaffoldProject {
+                template: String::new(),
+                path: None,
+            },
+            Intent::Plugin {
+                plugin: String::new(),
+                action: String::new(),
+                args: vec![],
+            },
+            Intent::Unknown,
+        ];
+        let kinds: Vec<&str> = intents.iter().map(Intent::kind).collect();
+        assert_eq!(kinds, Intent::KINDS);
+        // kind() is the serialized tag
+        for intent in &intents {
+            assert_eq!(serde_json::to_value(intent).unwrap()["kind"], intent.kind());
+        }
+    }
+
+    #[test]
+    fn phrases_only_match_whole_words() {
+        assert_eq!(find_phrase("forget it", "get"), None);
+        assert_eq!(find_phrase("get it", "get"), Some(0));
+        assert_eq!(find_phrase("forget to get it", "get"), Some(10));
+    }
+}
@@ -0,0 +1,177 @@
+// System notifications when a long operation finishes out of sight
+//
+// Installs, removals, system updates, clean-ups and plans can take minutes,
+// long enough to switch to something else. When one of them ends while no
+// window of the app has focus (minimized, hidden or just behind another
+// window) a system notification says whether it worked.
+//
+// The notification plugin reports neither clicks nor actions on desktop, so
+// following a notification is a heuristic, not a click: the first time a
+// window gains focus within FOLLOW_UP_MS of it, the component that shows the
+// outcome is told to bring itself into view. Focusing the app for any other
+// reason in that time does the same.
+//
+// The listener runs on whichever thread emitted the event, which may be a
+// runtime worker, so the state here sits behind std mutexes that are never
+// held across an await.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Listener, Manager, Window, WindowEvent};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::progress::OperationEvent;
+use crate::{clock, panels, AppState};
+
+// (operation, what it is called, the component type showing its outcome)
+const NOTIFIED: &[(&str, &str, &str)] = &[
+    ("install", "Installation", "ResultsList"),
+    ("remove", "Removal", "ResultsList"),
+    ("plan", "Plan", "ResultsList"),
+    ("update", "System update", "ProgressPanel"),
+    ("garbage_collect", "Clean-up", "ProgressPanel"),
+];
+// Quicker ones most likely ended before the user looked away
+const MIN_DURATION_MS: u64 = 10 * 1000;
+// Focus this soon after a notification is taken as following it
+const FOLLOW_UP_MS: u64 = 2 * 60 * 1000;
+
+struct Started {
+    operation: String,
+    started_ms: u64,
+}
+
+struct Pending {
+    component_id: String,
+    operation_id: String,
+    notified_ms: u64,
+}
+
+// Operations still running, by id; Completed and Failed only carry the id
+static STARTED: Mutex<Option<HashMap<String, Started>>> = Mutex::new(None);
+static PENDING: Mutex<Option<Pending>> = Mutex::new(None);
+
+fn in_background(app: &AppHandle) -> bool {
+    !app.webview_windows().values().any(|window| {
+        window.is_focused().unwrap_or(false)
+            && window.is_visible().unwrap_or(false)
+            && !window.is_minimized().unwrap_or(false)
+    })
+}
+
+// The first component of the type that shows the outcome; a ProgressPanel
+// falls back to the results
+fn component_for(app: &AppHandle, component_type: &str) -> Option<String> {
+    let state = app.state::<AppState>();
+    let components = state.components.blocking_lock();
+    [component_type, "ResultsList"]
+        .iter()
+        .find_map(|wanted| components.iter().find(|c| c.component_type == *wanted))
+        .map(|c| c.id.clone())
+}
+
+fn finished(app: &AppHandle, id: &str, outcome: Result<&str, &str>) {
+    let Some(started) = STARTED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_mut()
+        .and_then(|started| started.remove(id))
+    else {
+        return;
+    };
+    let Some((_, name, component_type)) = NOTIFIED
+        .iter()
+        .find(|(operation, ..)| *operation == started.operation)
+    else {
+        return;
+    };
+    let now = clock::now_ms();
+    if now.saturating_sub(started.started_ms) < MIN_DURATION_MS || !in_background(app) {
+        return;
+    }
+    let (title, body) = match outcome {
+        Ok(message) => (format!("{} finished", name), message),
+        Err(error) => (format!("{} failed", name), error),
+    };
+    let shown = app.notification().builder().title(title).body(body).show();
+    if let Err(e) = shown {
+        eprintln!("Could not show a notification: {}", e);
+        return;
+    }
+    // The components sit behind a tokio mutex, and locking that blocking
+    // panics on a runtime worker, so look the component up on a thread of its own
+    let app = app.clone();
+    let operation_id = id.to_string();
+    std::thread::spawn(move || {
+        *PENDING.lock().unwrap_or_else(|e| e.into_inner()) = component_for(&app, component_type)
+            .map(|component_id| Pending {
+                component_id,
+                operation_id,
+                notified_ms: now,
+            });
+    });
+}
+
+fn on_operation(app: &AppHandle, event: OperationEvent) {
+    match event {
+        OperationEvent::OperationStarted { id, operation, .. } => {
+            if NOTIFIED.iter().any(|(o, ..)| *o == operation) {
+                STARTED
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .get_or_insert_with(HashMap::new)
+                    .insert(
+                        id,
+                        Started {
+                            operation,
+                            started_ms: clock::now_ms(),
+                        },
+                    );
+            }
+        }
+        OperationEvent::Completed { id, message, .. } => finished(app, &id, Ok(&message)),
+        // Stopped on request: the user knows
+        OperationEvent::Failed {
+            id,
+            cancelled: true,
+            ..
+        } => {
+            if let Some(started) = STARTED.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+                started.remove(&id);
+            }
+        }
+        OperationEvent::Failed { id, error, .. } => finished(app, &id, Err(&error)),
+        _ => {}
+    }
+}
+
+pub fn init(app: &AppHandle) {
+    let handle = app.clone();
+    app.listen_any("operation", move |event| {
+        if let Ok(operation) = serde_json::from_str::<OperationEvent>(event.payload()) {
+            on_operation(&handle, operation);
+        }
+    });
+}
+
+// A window coming to the front soon after a notification: show what it was about
+pub fn window_event(window: &Window, event: &WindowEvent) {
+    if !matches!(event, WindowEvent::Focused(true)) {
+        return;
+    }
+    let Some(pending) = PENDING.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+        return;
+    };
+    if clock::now_ms().saturating_sub(pending.notified_ms) > FOLLOW_UP_MS {
+        return;
+    }
+    panels::emit(
+        window.app_handle(),
+        &pending.component_id,
+        "focus-component",
+        serde_json::json!({
+            "component_id": pending.component_id,
+            "operation_id": pending.operation_id,
+        }),
+    );
+}
@@ -0,0 +1,341 @@
+// First-run onboarding
+//
+// A fresh install walks the user through four questions: which persona fits,
+// any accessibility needs, how strictly to confirm changes and whether to use
+// voice. Answers are saved as they are given, so closing the app mid-way
+// picks up at the first unanswered step next time. Earlier answers suggest the
+// defaults for later ones (a voice-first persona suggests voice input), and
+// finishing writes them all to the profile and the safety policy at once.
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::personas::{self, ConfirmationStrictness};
+use crate::safety::SafetyPolicy;
+use crate::userprofile::{self, UserProfile};
+use crate::{clock, storage, tasks, themes, AppState};
+
+const STATE_FILE: &str = "onboarding.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnboardingStep {
+    Persona,
+    Accessibility,
+    Confirmation,
+    Voice,
+}
+
+const STEPS: &[OnboardingStep] = &[
+    OnboardingStep::Persona,
+    OnboardingStep::Accessibility,
+    OnboardingStep::Confirmation,
+    OnboardingStep::Voice,
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilityNeeds {
+    pub font_scale: f32,
+    pub high_contrast: bool,
+    pub reduced_motion: bool,
+    pub screen_reader: bool,
+}
+
+impl Default for AccessibilityNeeds {
+    fn default() -> Self {
+        AccessibilityNeeds {
+            font_scale: 1.0,
+            high_contrast: false,
+            reduced_motion: false,
+            screen_reader: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Answers {
+    pub persona: Option<String>,
+    pub accessibility: Option<AccessibilityNeeds>,
+    pub confirmation: Option<ConfirmationStrictness>,
+    pub voice: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct OnboardingState {
+    started: Option<u64>,
+    completed: Option<u64>,
+    answers: Answers,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Choice {
+    pub id: String,
+    pub label: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepGuide {
+    pub step: OnboardingStep,
+    pub title: String,
+    pub question: String,
+    // Empty for steps answered with a form rather than a pick
+    pub choices: Vec<Choice>,
+    // What "continue" without changing anything would answer
+    pub suggested: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnboardingStatus {
+    // No profile yet and onboarding never finished
+    pub needed: bool,
+    pub completed: Option<u64>,
+    pub answers: Answers,
+    pub next_step: Option<StepGuide>,
+    pub steps: Vec<OnboardingStep>,
+}
+
+fn load() -> OnboardingState {
+    storage::load(STATE_FILE).unwrap_or_default()
+}
+
+fn save(state: &OnboardingState) -> anyhow::Result<()> {
+    storage::save(STATE_FILE, state).map(|_| ())
+}
+
+fn choice(id: &str, label: &str, description: &str) -> Choice {
+    Choice {
+        id: id.to_string(),
+        label: label.to_string(),
+        description: description.to_string(),
+    }
+}
+
+fn persona(answers: &Answers) -> &'static personas::Persona {
+    personas::resolve(answers.persona.as_deref())
+}
+
+// What each step defaults to, given the answers so far
+fn suggested(step: OnboardingStep, answers: &Answers) -> serde_json::Value {
+    let persona = persona(answers);
+    match step {
+        OnboardingStep::Persona => serde_json::json!(persona.id),
+        OnboardingStep::Accessibility => serde_json::json!(AccessibilityNeeds {
+            font_scale: persona.font_scale,
+            reduced_motion: !persona.animations(),
+            screen_reader: persona.layout == "linear",
+            ..AccessibilityNeeds::default()
+        }),
+        OnboardingStep::Confirmation => serde_json::json!(persona.confirmation),
+        OnboardingStep::Voice => serde_json::json!(persona.voice_first),
+    }
+}
+
+pub fn guide(step: OnboardingStep, answers: &Answers) -> StepGuide {
+    let (title, question, choices) = match step {
+        OnboardingStep::Persona => (
+            "Who are you?",
+            "Pick whoever sounds most like you. It only sets starting points; everything can be changed later.",
+            personas::PERSONAS
+                .iter()
+                .map(|p| choice(p.id, p.name, p.summary))
+                .collect(),
+        ),
+        OnboardingStep::Accessibility => (
+            "Seeing and reading",
+            "Would larger text, higher contrast, less motion or screen reader support help?",
+            Vec::new(),
+        ),
+        OnboardingStep::Confirmation => (
+            "Asking before changes",
+            "How often should I check with you before changing your system?",
+            vec![
+                choice(
+                    "relaxed",
+                    "Only when it matters",
+                    "Ask before anything that can't be undone",
+                ),
+                choice(
+                    "standard",
+                    "Before every change",
+                    "Ask before anything that changes the system",
+                ),
+                choice(
+                    "strict",
+                    "Every time, carefully",
+                    "Ask before every change and have you type a phrase before anything that can't be undone",
+                ),
+            ],
+        ),
+        OnboardingStep::Voice => (
+            "Talking",
+            "Would you like to talk to me as well as type?",
+            vec![
+                choice("true", "Yes", "Use the microphone for voice input"),
+                choice("false", "Not now", "Keyboard only; voice can be turned on later"),
+            ],
+        ),
+    };
+    StepGuide {
+        step,
+        title: title.to_string(),
+        question: question.to_string(),
+        choices,
+        suggested: suggested(step, answers),
+    }
+}
+
+fn answered(step: OnboardingStep, answers: &Answers) -> bool {
+    match step {
+        OnboardingStep::Persona => answers.persona.is_some(),
+        OnboardingStep::Accessibility => answers.accessibility.is_some(),
+        OnboardingStep::Confirmation => answers.confirmation.is_some(),
+        OnboardingStep::Voice => answers.voice.is_some(),
+    }
+}
+
+fn next_step(answers: &Answers) -> Option<OnboardingStep> {
+    STEPS.iter().copied().find(|s| !answered(*s, answers))
+}
+
+fn status_of(state: &OnboardingState, has_profile: bool) -> OnboardingStatus {
+    OnboardingStatus {
+        needed: state.completed.is_none() && !has_profile,
+        completed: state.completed,
+        answers: state.answers.clone(),
+        next_step: next_step(&state.answers).map(|s| guide(s, &state.answers)),
+        steps: STEPS.to_vec(),
+    }
+}
+
+pub fn status(app_state: &AppState) -> OnboardingStatus {
+    let has_profile = app_state.user_profile.blocking_lock().is_some();
+    status_of(&load(), has_profile)
+}
+
+// Record the answer to `step`; null takes the suggestion. Steps can be
+// revisited, but not answered ahead of the ones before them
+pub fn answer(
+    app_state: &AppState,
+    step: OnboardingStep,
+    value: serde_json::Value,
+) -> anyhow::Result<OnboardingStatus> {
+    let mut state = load();
+    if let Some(next) = next_step(&state.answers) {
+        if STEPS.iter().position(|s| *s == step) > STEPS.iter().position(|s| *s == next) {
+            bail!("Answer \"{}\" first", guide(next, &state.answers).title);
+        }
+    }
+    let value = if value.is_null() {
+        suggested(step, &state.answers)
+    } else {
+        value
+    };
+    let answers = &mut state.answers;
+    match step {
+        OnboardingStep::Persona => {
+            let query = value.as_str().unwrap_or_default();
+            let persona =
+                personas::find(query).ok_or_else(|| anyhow!("Unknown persona '{}'", query))?;
+            // A different persona suggests different answers for the rest
+            if answers.persona.as_deref() != Some(persona.id) {
+                *answers = Answers::default();
+            }
+            answers.persona = Some(persona.id.to_string());
+        }
+        OnboardingStep::Accessibility => {
+            let mut needs: AccessibilityNeeds = serde_json::from_value(value)?;
+            needs.font_scale = needs.font_scale.clamp(0.75, 3.0);
+            answers.accessibility = Some(needs);
+        }
+        OnboardingStep::Confirmation => answers.confirmation = Some(serde_json::from_value(value)?),
+        OnboardingStep::Voice => {
+            answers.voice = Some(match value {
+                serde_json::Value::Bool(b) => b,
+                serde_json::Value::String(s) => s == "true",
+                _ => bail!("Answer voice with true or false"),
+            })
+        }
+    }
+    state.started.get_or_insert_with(now);
+    save(&state)?;
+    Ok(status(app_state))
+}
+
+fn policy_for(strictness: ConfirmationStrictness) -> SafetyPolicy {
+    SafetyPolicy {
+        confirm_reversible: strictness != ConfirmationStrictness::Relaxed,
+        destructive_requires_phrase: strictness == ConfirmationStrictness::Strict,
+        ..SafetyPolicy::load()
+    }
+}
+
+// Write every answer to the profile and safety policy
+pub fn finish(app_state: &AppState) -> anyhow::Result<UserProfile> {
+    let mut state = load();
+    if let Some(next) = next_step(&state.answers) {
+        bail!("Answer \"{}\" first", guide(next, &state.answers).title);
+    }
+    let answers = state.answers.clone();
+    let mut profile = app_state.user_profile.blocking_lock();
+    let profile = profile.get_or_insert_with(UserProfile::default);
+    profile.persona = persona(&answers).id.to_string();
+    if !profile.preferences.is_object() {
+        profile.preferences = serde_json::json!({});
+    }
+    profile.preferences["accessibility"] = serde_json::to_value(&answers.accessibility)?;
+    profile.preferences["voice_input"] = serde_json::json!(answers.voice);
+    if let Some(strictness) = answers.confirmation {
+        policy_for(strictness).save()?;
+    }
+    userprofile::save(profile)?;
+    state.completed = Some(clock::now());
+    save(&state)?;
+    Ok(profile.clone())
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_onboarding(app: AppHandle) -> OnboardingStatus {
+    tasks::blocking_value(&app, "Get onboarding", |state| status(state)).await
+}
+
+#[tauri::command]
+pub async fn answer_onboarding_step(
+    step: OnboardingStep,
+    value: Option<serde_json::Value>,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Answer onboarding step", move |state| {
+        crate::respond(answer(state, step, value.unwrap_or_default()))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn finish_onboarding(app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Finish onboarding", move |state| {
+        let profile = finish(state);
+        // A high-contrast answer changes the theme straight away
+        if profile.is_ok() {
+            let _ = themes::refresh(&handle);
+        }
+        crate::respond(profile)
+    })
+    .await
+}
+
+// Forget the answers and start over; the profile keeps its current settings
+#[tauri::command]
+pub async fn restart_onboarding(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Restart onboarding", |_| {
+        crate::respond(save(&OnboardingState::default()))
+    })
+    .await
+}
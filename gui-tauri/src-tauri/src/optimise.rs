@@ -0,0 +1,265 @@
+// Nix store deduplication: run it, report what it saved, and say when it's worth it
+//
+// `nix-store --optimise` replaces identical files with hard links into
+// /nix/store/.links. Each run records the store size and how much was freed,
+// and the freed share is used to project what the next run would save from
+// the growth since; a run is recommended once that projection passes a
+// threshold. Regular runs can be scheduled through nix.optimise.
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+use crate::nixgen::{self, NixModule, NixOption, Target};
+use crate::{
+    clock, jsonstream, managed, nixconf, progress, sessions, storage, system, tasks, timers,
+};
+
+const STATE_FILE: &str = "optimise.json";
+const LINKS_DIR: &str = "/nix/store/.links";
+const GIB: u64 = 1024 * 1024 * 1024;
+// Recommend a run once it should free at least this much
+const RECOMMEND_THRESHOLD_BYTES: u64 = 2 * GIB;
+// Share of a never-optimised store that deduplication typically frees
+const DEFAULT_SAVINGS_RATIO: f64 = 0.2;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct OptimiseState {
+    last_run: Option<u64>,
+    // Total NAR size of the store right after the last run
+    store_bytes_after: u64,
+    // Freed bytes as a share of the store size before the last run
+    savings_ratio: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimiseReport {
+    pub disk_used_before: u64,
+    pub disk_used_after: u64,
+    pub freed_bytes: u64,
+    // As reported by nix-store: files newly replaced by hard links
+    pub files_linked: u64,
+    pub reported_freed_bytes: u64,
+    // Distinct file contents shared through /nix/store/.links
+    pub link_entries: usize,
+    pub duration_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimiseRecommendation {
+    pub recommended: bool,
+    pub reason: String,
+    pub store_bytes: u64,
+    pub projected_savings_bytes: u64,
+    pub threshold_bytes: u64,
+    pub last_run: Option<u64>,
+    pub auto_optimise: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimiseSchedule {
+    pub on_calendar: String,
+    pub next_runs: Vec<String>,
+    pub module_path: PathBuf,
+}
+
+// Bytes used on the filesystem holding the store
+fn disk_used() -> anyhow::Result<u64> {
+    let output = system::run("df", &["-B1", "--output=used", "/nix/store"])?;
+    output
+        .lines()
+        .nth(1)
+        .and_then(|l| l.trim().parse().ok())
+        .ok_or_else(|| anyhow!("Could not read the disk usage of /nix/store"))
+}
+
+#[derive(Deserialize)]
+struct PathInfo {
+    #[serde(rename = "narSize", default)]
+    nar_size: u64,
+}
+
+// Sum of NAR sizes of every valid store path; hard links aren't counted
+// twice by df but are here, which is what projections need
+pub fn store_bytes() -> anyhow::Result<u64> {
+    let mut total = 0;
+    // Older nix prints a list of objects, newer an object keyed by path
+    // (with null for invalid paths); either way one entry at a time
+    jsonstream::run(
+        "nix",
+        &["path-info", "--all", "--json"],
+        |_, info: Option<PathInfo>| total += info.map_or(0, |i| i.nar_size),
+    )?;
+    Ok(total)
+}
+
+fn link_entries() -> usize {
+    fs::read_dir(LINKS_DIR).map(|d| d.count()).unwrap_or(0)
+}
+
+// "12.34 MiB freed by hard-linking 567 files"
+fn parse_summary(output: &str) -> (u64, u64) {
+    let Some(line) = output.lines().find(|l| l.contains("freed by hard-linking")) else {
+        return (0, 0);
+    };
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let amount: f64 = words.first().and_then(|w| w.parse().ok()).unwrap_or(0.0);
+    let unit = match words.get(1).copied() {
+        Some("KiB") => 1024.0,
+        Some("MiB") => 1024.0 * 1024.0,
+        Some("GiB") => GIB as f64,
+        _ => 1.0,
+    };
+    let files = words
+        .iter()
+        .position(|w| *w == "hard-linking")
+        .and_then(|i| words.get(i + 1))
+        .and_then(|w| w.parse().ok())
+        .unwrap_or(0);
+    ((amount * unit) as u64, files)
+}
+
+fn auto_optimise_enabled() -> bool {
+    nixconf::list()
+        .map(|settings| {
+            settings
+                .iter()
+                .any(|s| s.name == "auto-optimise-store" && s.value == serde_json::json!(true))
+        })
+        .unwrap_or(false)
+}
+
+// One audited operation, so another session's rebuild waits for it
+pub fn run() -> anyhow::Result<OptimiseReport> {
+    sessions::exclusive("Deduplicate the Nix store", deduplicate)
+}
+
+fn deduplicate() -> anyhow::Result<OptimiseReport> {
+    let started = clock::now();
+    let before = disk_used()?;
+    let store_before = store_bytes().unwrap_or(0);
+    // The summary goes to stderr
+    let output = system::run_privileged("sh", &["-c", "nix-store --optimise 2>&1"])?;
+    let after = disk_used()?;
+    let (reported_freed_bytes, files_linked) = parse_summary(&output);
+    let freed_bytes = before.saturating_sub(after).max(reported_freed_bytes);
+
+    storage::save_data(
+        STATE_FILE,
+        &OptimiseState {
+            last_run: Some(clock::now()),
+            store_bytes_after: store_bytes().unwrap_or(0),
+            savings_ratio: (store_before > 0).then(|| freed_bytes as f64 / store_before as f64),
+        },
+    )?;
+    Ok(OptimiseReport {
+        disk_used_before: before,
+        disk_used_after: after,
+        freed_bytes,
+        files_linked,
+        reported_freed_bytes,
+        link_entries: link_entries(),
+        duration_secs: clock::now().saturating_sub(started),
+    })
+}
+
+pub fn recommendation() -> anyhow::Result<OptimiseRecommendation> {
+    let state: OptimiseState = storage::load_data(STATE_FILE)?;
+    let store = store_bytes()?;
+    let auto_optimise = auto_optimise_enabled();
+    // Only what was added since the last run can still have duplicates
+    let unoptimised = store.saturating_sub(state.store_bytes_after);
+    let ratio = state.savings_ratio.unwrap_or(DEFAULT_SAVINGS_RATIO);
+    let projected = (unoptimised as f64 * ratio) as u64;
+    let gib = |bytes: u64| bytes as f64 / GIB as f64;
+    let (recommended, reason) = if auto_optimise {
+        (
+            false,
+            "auto-optimise-store is on, so new files are deduplicated as they are built"
+                .to_string(),
+        )
+    } else if projected >= RECOMMEND_THRESHOLD_BYTES {
+        (
+            true,
+            format!(
+                "Deduplicating should free about {:.1} GiB ({:.1} GiB added since the last run)",
+                gib(projected),
+                gib(unoptimised)
+            ),
+        )
+    } else {
+        (
+            false,
+            format!(
+                "Only about {:.1} GiB to gain; worth it from {:.0} GiB",
+                gib(projected),
+                gib(RECOMMEND_THRESHOLD_BYTES)
+            ),
+        )
+    };
+    Ok(OptimiseRecommendation {
+        recommended,
+        reason,
+        store_bytes: store,
+        projected_savings_bytes: projected,
+        threshold_bytes: RECOMMEND_THRESHOLD_BYTES,
+        last_run: state.last_run,
+        auto_optimise,
+    })
+}
+
+// Declare nix.optimise with a schedule in plain words ("every sunday at 3am")
+pub fn schedule(request: &str) -> anyhow::Result<OptimiseSchedule> {
+    let on_calendar = timers::parse_schedule(request)?;
+    let next_runs = timers::next_runs(&on_calendar)?;
+    let mut module = NixModule::new(
+        "store-optimise",
+        "scheduled store deduplication",
+        Target::Nixos,
+    );
+    module.set(NixOption::new("nix.optimise.automatic", nixgen::bool(true)));
+    module.set(NixOption::new(
+        "nix.optimise.dates",
+        nixgen::string_list(&[&on_calendar]),
+    ));
+    Ok(OptimiseSchedule {
+        module_path: sessions::exclusive("Schedule store deduplication", || module.write())?,
+        on_calendar,
+        next_runs,
+    })
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn optimise_store(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Optimise store", |_| {
+        crate::respond(progress::track(
+            "optimise",
+            "Deduplicating the Nix store",
+            run,
+        ))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_optimise_recommendation(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Get optimise recommendation", |_| {
+        crate::respond(recommendation())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn schedule_store_optimise(schedule: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Schedule store optimise", move |_| {
+        if let Some(refusal) = managed::guard("configure", "Schedule store deduplication") {
+            return refusal;
+        }
+        crate::respond(self::schedule(&schedule))
+    })
+    .await
+}
@@ -0,0 +1,293 @@
+// Command palette search (Ctrl+K)
+//
+// One fuzzy search over everything the palette can jump to: the registered
+// actions, packages from recent installs and removals, layouts (presets and
+// saved ones), the app's settings pages and the Nix settings it manages.
+// Every entry is scored against its title and keywords with the same fuzzy
+// matcher package search uses, weighted by kind so an action outranks an
+// equally close setting, and carries the invocation that carries it out.
+// With an empty query the palette shows the actions and recent packages.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tauri::AppHandle;
+
+use crate::actions::{self, Invocation};
+use crate::nlp::Intent;
+use crate::safety::BlastRadius;
+use crate::{fuzzy, history, layouts, nixconf, tasks, AppState};
+
+const DEFAULT_LIMIT: usize = 20;
+// Below this an entry isn't worth showing
+const MIN_SCORE: f32 = 0.45;
+const RECENT_PACKAGES: usize = 20;
+
+// (section, title, keywords)
+const SETTINGS_PAGES: &[(&str, &str, &[&str])] = &[
+    (
+        "appearance",
+        "Appearance settings",
+        &["theme", "colours", "colors", "font"],
+    ),
+    (
+        "shortcuts",
+        "Keyboard shortcuts",
+        &["keys", "hotkeys", "bindings"],
+    ),
+    (
+        "voice",
+        "Voice settings",
+        &["microphone", "speech", "whisper", "wake word"],
+    ),
+    (
+        "accessibility",
+        "Accessibility settings",
+        &["screen reader", "contrast", "motion"],
+    ),
+    (
+        "privacy",
+        "Privacy settings",
+        &["data", "history", "retention"],
+    ),
+    (
+        "wellbeing",
+        "Wellbeing settings",
+        &["breaks", "pauses", "focus"],
+    ),
+    (
+        "maintenance",
+        "Maintenance windows",
+        &["schedule", "updates", "when"],
+    ),
+    ("plugins", "Plugins", &["extensions", "docker"]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    Action,
+    Package,
+    Layout,
+    Setting,
+}
+
+impl EntryKind {
+    // How far a match of this kind counts against the others
+    fn weight(self) -> f32 {
+        match self {
+            EntryKind::Action => 1.0,
+            EntryKind::Layout => 0.95,
+            EntryKind::Setting => 0.9,
+            EntryKind::Package => 0.85,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaletteEntry {
+    pub kind: EntryKind,
+    pub id: String,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub score: f32,
+    pub shortcut: Option<String>,
+    pub risk: BlastRadius,
+    pub invocation: Invocation,
+}
+
+struct Candidate {
+    entry: PaletteEntry,
+    keywords: Vec<String>,
+}
+
+fn candidate(
+    kind: EntryKind,
+    id: &str,
+    title: &str,
+    subtitle: Option<String>,
+    keywords: Vec<String>,
+    invocation: Invocation,
+) -> Candidate {
+    Candidate {
+        entry: PaletteEntry {
+            kind,
+            id: id.to_string(),
+            title: title.to_string(),
+            subtitle,
+            score: 0.0,
+            shortcut: None,
+            risk: BlastRadius::ReadOnly,
+            invocation,
+        },
+        keywords,
+    }
+}
+
+fn perform(action: &str, params: serde_json::Value) -> Invocation {
+    Invocation::Command {
+        command: "perform_action".to_string(),
+        args: serde_json::json!({"action": action, "params": params}),
+    }
+}
+
+fn action_candidates(state: &AppState) -> Vec<Candidate> {
+    actions::all(state)
+        .into_iter()
+        .map(|action| Candidate {
+            entry: PaletteEntry {
+                kind: EntryKind::Action,
+                id: action.id,
+                title: action.title,
+                subtitle: None,
+                score: 0.0,
+                shortcut: action.shortcut,
+                risk: action.risk,
+                invocation: action.invocation,
+            },
+            keywords: action.keywords,
+        })
+        .collect()
+}
+
+// Packages from the latest installs and removals, newest first; selecting
+// one searches for it, so its row offers install or remove as usual
+fn package_candidates() -> Vec<Candidate> {
+    let mut seen = HashSet::new();
+    let mut candidates = Vec::new();
+    for entry in history::load().iter().rev() {
+        let (packages, verb) = match &entry.intent {
+            Intent::Install { packages } => (packages, "Installed"),
+            Intent::Remove { packages } => (packages, "Removed"),
+            _ => continue,
+        };
+        for package in packages {
+            if candidates.len() == RECENT_PACKAGES || !seen.insert(package.clone()) {
+                continue;
+            }
+            candidates.push(candidate(
+                EntryKind::Package,
+                &format!("package:{}", package),
+                package,
+                Some(format!("{} recently", verb)),
+                Vec::new(),
+                perform("search", serde_json::json!({"query": package})),
+            ));
+        }
+    }
+    candidates
+}
+
+fn layout_candidates() -> Vec<Candidate> {
+    let switch = |id: &str| Invocation::Command {
+        command: "switch_layout".to_string(),
+        args: serde_json::json!({"layout_id": id}),
+    };
+    let mut candidates: Vec<Candidate> = layouts::presets()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|preset| {
+            let mut keywords = preset.aliases.clone();
+            keywords.push(preset.id.clone());
+            candidate(
+                EntryKind::Layout,
+                &format!("layout:{}", preset.id),
+                &format!("{} layout", preset.name),
+                Some(preset.description.clone()),
+                keywords,
+                switch(&preset.id),
+            )
+        })
+        .collect();
+    if let Ok(saved) = layouts::saved() {
+        candidates.extend(saved.layouts.into_iter().map(|layout| {
+            candidate(
+                EntryKind::Layout,
+                &format!("layout:{}", layout.id),
+                &format!("{} layout", layout.name),
+                Some("Saved layout".to_string()),
+                vec![layout.id.clone()],
+                switch(&layout.id),
+            )
+        }));
+    }
+    candidates
+}
+
+fn setting_candidates() -> Vec<Candidate> {
+    let open = |args: serde_json::Value| Invocation::Frontend {
+        action: "open-settings".to_string(),
+        args,
+    };
+    let pages = SETTINGS_PAGES.iter().map(|(section, title, keywords)| {
+        candidate(
+            EntryKind::Setting,
+            &format!("settings:{}", section),
+            title,
+            None,
+            keywords.iter().map(|k| k.to_string()).collect(),
+            open(serde_json::json!({"section": section})),
+        )
+    });
+    let nix = nixconf::SETTINGS.iter().map(|spec| {
+        candidate(
+            EntryKind::Setting,
+            &format!("nix-setting:{}", spec.name),
+            spec.name,
+            Some(spec.explanation.to_string()),
+            vec![spec.name.replace('-', " ")],
+            open(serde_json::json!({"section": "nix", "setting": spec.name})),
+        )
+    });
+    pages.chain(nix).collect()
+}
+
+// The best match over the title, its words and the keywords
+fn score(query: &str, candidate: &Candidate) -> f32 {
+    std::iter::once(candidate.entry.title.as_str())
+        .chain(candidate.entry.title.split_whitespace())
+        .chain(candidate.keywords.iter().map(String::as_str))
+        .map(|text| fuzzy::score(query, text))
+        .fold(0.0, f32::max)
+}
+
+pub fn search(state: &AppState, query: &str, limit: usize) -> Vec<PaletteEntry> {
+    let query = query.trim();
+    let mut candidates = action_candidates(state);
+    candidates.extend(package_candidates());
+    if query.is_empty() {
+        return candidates
+            .into_iter()
+            .map(|c| c.entry)
+            .take(limit)
+            .collect();
+    }
+    candidates.extend(layout_candidates());
+    candidates.extend(setting_candidates());
+    let mut entries: Vec<PaletteEntry> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let score = score(query, &candidate);
+            (score >= MIN_SCORE).then(|| PaletteEntry {
+                score: score * candidate.entry.kind.weight(),
+                ..candidate.entry
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| b.score.total_cmp(&a.score));
+    entries.truncate(limit);
+    entries
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn palette_search(
+    query: String,
+    limit: Option<usize>,
+    app: AppHandle,
+) -> Vec<PaletteEntry> {
+    tasks::blocking_value(&app, "Palette search", move |state| {
+        search(state, &query, limit.unwrap_or(DEFAULT_LIMIT))
+    })
+    .await
+}
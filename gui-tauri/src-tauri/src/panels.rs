@@ -0,0 +1,351 @@
+// Detachable panels: components popped out into their own windows
+//
+// Any component can be detached into a window of its own (a build log on a
+// second screen, the generation timeline next to an editor). The frontend
+// loads the same page with ?panel=<component id> and renders just that one.
+// The backend remembers which components are detached, so state updates and
+// events for a component go to the window showing it, and keeps the geometry
+// of every window, so the detached panels come back where they were on the
+// next start. Closing a panel window docks the component again.
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::{
+    AppHandle, Emitter, LogicalPosition, LogicalSize, Manager, WebviewUrl, WebviewWindowBuilder,
+    Window, WindowEvent,
+};
+use tokio::sync::Mutex;
+
+use crate::{storage, tasks, ComponentState};
+
+const WINDOWS_FILE: &str = "windows.json";
+const MAIN_WINDOW: &str = "main";
+const PANEL_PREFIX: &str = "panel-";
+const DEFAULT_WIDTH: f64 = 480.0;
+const DEFAULT_HEIGHT: f64 = 600.0;
+
+// Logical pixels, so a panel keeps its size across scale factors
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Geometry {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    #[serde(default)]
+    pub maximized: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct WindowStore {
+    // By window label
+    #[serde(default)]
+    geometry: BTreeMap<String, Geometry>,
+    // Ids of the components shown in windows of their own
+    #[serde(default)]
+    detached: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PanelInfo {
+    pub component_id: String,
+    pub window: String,
+    pub geometry: Option<Geometry>,
+}
+
+static STORE: Mutex<Option<WindowStore>> = Mutex::const_new(None);
+
+fn with_store<T>(f: impl FnOnce(&mut WindowStore) -> T) -> T {
+    let mut store = STORE.blocking_lock();
+    let store = store.get_or_insert_with(|| {
+        storage::load(WINDOWS_FILE).unwrap_or_else(|e| {
+            eprintln!("Could not read the window layout: {}", e);
+            WindowStore::default()
+        })
+    });
+    f(store)
+}
+
+fn save() {
+    let result = with_store(|store| storage::save(WINDOWS_FILE, store));
+    if let Err(e) = result {
+        eprintln!("Could not save the window layout: {}", e);
+    }
+}
+
+// Window labels only take letters, digits and -/:_
+fn label(component_id: &str) -> String {
+    let id: String = component_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}{}", PANEL_PREFIX, id)
+}
+
+fn is_detached(component_id: &str) -> bool {
+    with_store(|store| store.detached.iter().any(|id| id == component_id))
+}
+
+// The label of the window showing a component; a detached one whose panel
+// hasn't opened yet is still in the main window
+pub fn window_for(app: &AppHandle, component_id: &str) -> String {
+    let panel = label(component_id);
+    if is_detached(component_id) && app.get_webview_window(&panel).is_some() {
+        panel
+    } else {
+        MAIN_WINDOW.to_string()
+    }
+}
+
+// Send an event for a component to the window showing it
+pub fn emit<S: Serialize + Clone>(app: &AppHandle, component_id: &str, event: &str, payload: S) {
+    if let Err(e) = app.emit_to(window_for(app, component_id).as_str(), event, payload) {
+        eprintln!("Could not send {} to {}: {}", event, component_id, e);
+    }
+}
+
+fn info(component_id: &str) -> PanelInfo {
+    let window = label(component_id);
+    PanelInfo {
+        component_id: component_id.to_string(),
+        geometry: with_store(|store| store.geometry.get(&window).copied()),
+        window,
+    }
+}
+
+pub fn list() -> Vec<PanelInfo> {
+    with_store(|store| store.detached.clone())
+        .iter()
+        .map(|id| info(id))
+        .collect()
+}
+
+fn open(app: &AppHandle, component: &ComponentState) -> anyhow::Result<()> {
+    let window = label(&component.id);
+    if app.get_webview_window(&window).is_some() {
+        return Ok(());
+    }
+    let url = format!("index.html?panel={}", component.id);
+    let mut builder = WebviewWindowBuilder::new(app, &window, WebviewUrl::App(url.into()))
+        .title(format!("Luminous Nix - {}", component.component_type))
+        .inner_size(DEFAULT_WIDTH, DEFAULT_HEIGHT);
+    if let Some(geometry) = with_store(|store| store.geometry.get(&window).copied()) {
+        builder = builder
+            .position(geometry.x, geometry.y)
+            .inner_size(geometry.width, geometry.height)
+            .maximized(geometry.maximized);
+    }
+    builder.build()?;
+    Ok(())
+}
+
+// Pop a component out into its own window
+pub fn detach(app: &AppHandle, component: &ComponentState) -> anyhow::Result<PanelInfo> {
+    match app.get_webview_window(&label(&component.id)) {
+        Some(window) => window.set_focus()?,
+        None => open(app, component)?,
+    }
+    let added = with_store(|store| {
+        let added = !store.detached.contains(&component.id);
+        if added {
+            store.detached.push(component.id.clone());
+        }
+        added
+    });
+    if added {
+        save();
+        let _ = app.emit_to(
+            MAIN_WINDOW,
+            "panel-detached",
+            serde_json::json!({"component_id": component.id}),
+        );
+    }
+    Ok(info(&component.id))
+}
+
+// Forget a detached component and tell the main window to show it again
+fn forget(app: &AppHandle, component_id: &str) -> bool {
+    let removed = with_store(|store| {
+        let before = store.detached.len();
+        store.detached.retain(|id| id != component_id);
+        store.detached.len() != before
+    });
+    if removed {
+        save();
+        let _ = app.emit_to(
+            MAIN_WINDOW,
+            "panel-docked",
+            serde_json::json!({"component_id": component_id}),
+        );
+    }
+    removed
+}
+
+// Put a detached component back into the main window
+pub fn dock(app: &AppHandle, component_id: &str) -> anyhow::Result<()> {
+    if !forget(app, component_id) {
+        bail!("{} isn't detached", component_id);
+    }
+    if let Some(window) = app.get_webview_window(&label(component_id)) {
+        // destroy() skips CloseRequested, which would dock it a second time
+        window.destroy()?;
+    }
+    Ok(())
+}
+
+// After a layout change: panels of components the layout has open, the
+// others go back to the main window
+pub fn reconcile(app: &AppHandle, components: &[ComponentState]) {
+    for id in with_store(|store| store.detached.clone()) {
+        let result = match components.iter().find(|c| c.id == id) {
+            Some(component) => open(app, component),
+            None => dock(app, &id),
+        };
+        if let Err(e) = result {
+            eprintln!("Could not update the panel for {}: {}", id, e);
+        }
+    }
+}
+
+fn geometry(window: &Window) -> Option<Geometry> {
+    let scale = window.scale_factor().ok()?;
+    let position: LogicalPosition<f64> = window.outer_position().ok()?.to_logical(scale);
+    let size: LogicalSize<f64> = window.inner_size().ok()?.to_logical(scale);
+    Some(Geometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        maximized: window.is_maximized().unwrap_or(false),
+    })
+}
+
+// Kept in memory while a window moves and written out when it closes
+pub fn window_event(window: &Window, event: &WindowEvent) {
+    let tracked = window.label() == MAIN_WINDOW || window.label().starts_with(PANEL_PREFIX);
+    if !tracked {
+        return;
+    }
+    match event {
+        WindowEvent::Moved(_) | WindowEvent::Resized(_) => {
+            if let Some(geometry) = geometry(window) {
+                // A maximized window keeps the size it will go back to
+                with_store(|store| {
+                    let entry = store
+                        .geometry
+                        .entry(window.label().to_string())
+                        .or_insert(geometry);
+                    if geometry.maximized {
+                        entry.maximized = true;
+                    } else {
+                        *entry = geometry;
+                    }
+                });
+            }
+        }
+        // The user closed a panel: dock its component again
+        WindowEvent::CloseRequested { .. } => {
+            let component = with_store(|store| {
+                store
+                    .detached
+                    .iter()
+                    .find(|id| label(id) == window.label())
+                    .cloned()
+            });
+            if let Some(id) = component {
+                forget(window.app_handle(), &id);
+            }
+        }
+        WindowEvent::Destroyed => {
+            save();
+            // Panels don't outlive the main window; they stay detached and
+            // reopen with it on the next start
+            if window.label() == MAIN_WINDOW {
+                window.app_handle().exit(0);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Put the main window where it was and reopen the detached panels
+pub fn restore(app: &AppHandle, components: &[ComponentState]) {
+    if let (Some(window), Some(geometry)) = (
+        app.get_webview_window(MAIN_WINDOW),
+        with_store(|store| store.geometry.get(MAIN_WINDOW).copied()),
+    ) {
+        let _ = window.set_position(LogicalPosition::new(geometry.x, geometry.y));
+        let _ = window.set_size(LogicalSize::new(geometry.width, geometry.height));
+        if geometry.maximized {
+            let _ = window.maximize();
+        }
+    }
+    // Panels of components from another layout open once it is switched to
+    for component in components.iter().filter(|c| is_detached(&c.id)) {
+        if let Err(e) = open(app, component) {
+            eprintln!("Could not reopen the panel for {}: {}", component.id, e);
+        }
+    }
+}
+
+// ========== Tauri Commands ==========
+
+// Windows are created off the main thread; a synchronous command would block
+// the event loop the new window needs
+#[tauri::command]
+pub async fn detach_component(component_id: String, app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Detach component", move |state| {
+        let component = state
+            .components
+            .blocking_lock()
+            .iter()
+            .find(|c| c.id == component_id)
+            .cloned();
+        let result = component
+            .ok_or_else(|| anyhow!("There is no component \"{}\"", component_id))
+            .and_then(|component| detach(&handle, &component));
+        crate::respond(result)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn dock_component(component_id: String, app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Dock component", move |_| {
+        crate::respond(dock(&handle, &component_id))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn list_detached_panels(app: AppHandle) -> Vec<PanelInfo> {
+    tasks::blocking_value(&app, "List detached panels", |_| list()).await
+}
+
+// Deliver an event to a component in whichever window shows it
+#[tauri::command]
+pub async fn send_component_event(
+    component_id: String,
+    event: String,
+    payload: serde_json::Value,
+    app: AppHandle,
+) {
+    let handle = app.clone();
+    tasks::blocking_value(&app, "Send component event", move |_| {
+        emit(
+            &handle,
+            &component_id,
+            "component-event",
+            serde_json::json!({"component_id": component_id, "event": event, "payload": payload}),
+        )
+    })
+    .await
+}
@@ -0,0 +1,245 @@
+// The ten core personas and how the interface adapts to each
+//
+// A persona is a starting point, not a mode: it sets default verbosity, font
+// scale, confirmation strictness and pacing, and live signals such as
+// cognitive load adjust from there.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verbosity {
+    Minimal,
+    Concise,
+    Balanced,
+    Detailed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfirmationStrictness {
+    // Only destructive actions ask first
+    Relaxed,
+    Standard,
+    // Everything that changes the system asks first, with the risk read out
+    Strict,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pacing {
+    Fast,
+    Steady,
+    Gentle,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Persona {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub summary: &'static str,
+    pub verbosity: Verbosity,
+    pub font_scale: f32,
+    pub confirmation: ConfirmationStrictness,
+    pub pacing: Pacing,
+    // Layout id switch_layout uses when asked for the persona's default
+    pub layout: &'static str,
+    pub voice_first: bool,
+    pub plain_language: bool,
+}
+
+pub const DEFAULT_PERSONA: &str = "carlos";
+
+pub const PERSONAS: &[Persona] = &[
+    Persona {
+        id: "grandma_rose",
+        name: "Grandma Rose",
+        summary: "75, new to computers, prefers talking to typing. Wants warm, jargon-free guidance.",
+        verbosity: Verbosity::Detailed,
+        font_scale: 1.4,
+        confirmation: ConfirmationStrictness::Strict,
+        pacing: Pacing::Gentle,
+        layout: "minimal",
+        voice_first: true,
+        plain_language: true,
+    },
+    Persona {
+        id: "maya",
+        name: "Maya",
+        summary: "16, ADHD. Needs fast answers with no distractions or walls of text.",
+        verbosity: Verbosity::Minimal,
+        font_scale: 1.0,
+        confirmation: ConfirmationStrictness::Relaxed,
+        pacing: Pacing::Fast,
+        layout: "focus",
+        voice_first: false,
+        plain_language: true,
+    },
+    Persona {
+        id: "david",
+        name: "David",
+        summary: "42, tired parent. Needs things to just work and to be told what happened, briefly.",
+        verbosity: Verbosity::Concise,
+        font_scale: 1.1,
+        confirmation: ConfirmationStrictness::Standard,
+        pacing: Pacing::Steady,
+        layout: "minimal",
+        voice_first: false,
+        plain_language: true,
+    },
+    Persona {
+        id: "dr_sarah",
+        name: "Dr. Sarah",
+        summary: "35, researcher. Wants precise, reproducible results and the exact commands behind them.",
+        verbosity: Verbosity::Detailed,
+        font_scale: 1.0,
+        confirmation: ConfirmationStrictness::Standard,
+        pacing: Pacing::Steady,
+        layout: "dense",
+        voice_first: false,
+        plain_language: false,
+    },
+    Persona {
+        id: "alex",
+        name: "Alex",
+        summary: "28, blind developer using a screen reader. Needs predictable structure and everything announced.",
+        verbosity: Verbosity::Balanced,
+        font_scale: 1.0,
+        confirmation: ConfirmationStrictness::Standard,
+        pacing: Pacing::Fast,
+        layout: "linear",
+        voice_first: true,
+        plain_language: false,
+    },
+    Persona {
+        id: "carlos",
+        name: "Carlos",
+        summary: "52, switching careers into tech. Learns by doing and likes to understand why.",
+        verbosity: Verbosity::Balanced,
+        font_scale: 1.1,
+        confirmation: ConfirmationStrictness::Standard,
+        pacing: Pacing::Steady,
+        layout: "default",
+        voice_first: false,
+        plain_language: true,
+    },
+    Persona {
+        id: "priya",
+        name: "Priya",
+        summary: "34, single mother working in short bursts between interruptions. Needs to resume where she left off.",
+        verbosity: Verbosity::Concise,
+        font_scale: 1.0,
+        confirmation: ConfirmationStrictness::Standard,
+        pacing: Pacing::Fast,
+        layout: "default",
+        voice_first: false,
+        plain_language: true,
+    },
+    Persona {
+        id: "jamie",
+        name: "Jamie",
+        summary: "19, privacy advocate. Wants to know exactly what runs and what touches the network.",
+        verbosity: Verbosity::Detailed,
+        font_scale: 1.0,
+        confirmation: ConfirmationStrictness::Strict,
+        pacing: Pacing::Steady,
+        layout: "dense",
+        voice_first: false,
+        plain_language: false,
+    },
+    Persona {
+        id: "viktor",
+        name: "Viktor",
+        summary: "67, English is his second language. Needs short, simple sentences and no idioms.",
+        verbosity: Verbosity::Concise,
+        font_scale: 1.25,
+        confirmation: ConfirmationStrictness::Strict,
+        pacing: Pacing::Gentle,
+        layout: "minimal",
+        voice_first: false,
+        plain_language: true,
+    },
+    Persona {
+        id: "luna",
+        name: "Luna",
+        summary: "14, autistic. Needs predictable, literal responses and an interface that doesn't change unexpectedly.",
+        verbosity: Verbosity::Balanced,
+        font_scale: 1.0,
+        confirmation: ConfirmationStrictness::Standard,
+        pacing: Pacing::Steady,
+        layout: "linear",
+        voice_first: false,
+        plain_language: true,
+    },
+];
+
+// Accepts ids and display names ("Dr. Sarah", "grandma rose")
+pub fn find(query: &str) -> Option<&'static Persona> {
+    let key: String = query
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '_')
+        .map(|c| if c == ' ' { '_' } else { c })
+        .collect();
+    PERSONAS
+        .iter()
+        .find(|p| p.id == key || p.id.split('_').any(|part| part == key))
+}
+
+pub fn resolve(id: Option<&str>) -> &'static Persona {
+    id.and_then(find)
+        .or_else(|| find(DEFAULT_PERSONA))
+        .unwrap_or(&PERSONAS[0])
+}
+
+impl Verbosity {
+    pub fn shorter(self) -> Verbosity {
+        match self {
+            Verbosity::Minimal | Verbosity::Concise => Verbosity::Minimal,
+            Verbosity::Balanced => Verbosity::Concise,
+            Verbosity::Detailed => Verbosity::Balanced,
+        }
+    }
+
+    pub fn longer(self) -> Verbosity {
+        match self {
+            Verbosity::Minimal => Verbosity::Concise,
+            Verbosity::Concise => Verbosity::Balanced,
+            Verbosity::Balanced | Verbosity::Detailed => Verbosity::Detailed,
+        }
+    }
+}
+
+impl Persona {
+    // Layouts shouldn't rearrange themselves for Luna or screen reader users
+    pub fn animations(&self) -> bool {
+        self.pacing != Pacing::Gentle && self.layout != "linear"
+    }
+
+    // How many options or suggestions to offer at once
+    pub fn max_choices(&self) -> u32 {
+        match self.verbosity {
+            Verbosity::Minimal => 3,
+            Verbosity::Concise => 4,
+            Verbosity::Balanced => 6,
+            Verbosity::Detailed => 8,
+        }
+    }
+
+    // How long to leave a response up before moving on, in milliseconds
+    pub fn response_delay_ms(&self) -> u64 {
+        match self.pacing {
+            Pacing::Fast => 0,
+            Pacing::Steady => 400,
+            Pacing::Gentle => 1200,
+        }
+    }
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn list_personas() -> Vec<Persona> {
+    PERSONAS.to_vec()
+}
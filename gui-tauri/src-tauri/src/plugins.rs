@@ -0,0 +1,447 @@
+// Intent plugin registry
+//
+// Plugins contribute phrases that map to their own actions ("start my
+// containers" -> docker/start) plus capabilities that are merged into the
+// frontend components, and may bring component types of their own. Built-in plugins implement IntentPlugin directly;
+// manifest plugins are JSON files in the config dir whose actions run a fixed
+// argv, so they can be added without rebuilding the app.
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+use crate::components::ComponentType;
+use crate::nlp::{self, Intent};
+use crate::{managed, storage, system, tasks};
+
+const PLUGINS_DIR: &str = "plugins";
+
+// A phrase such as "start my containers" or "restart the {name} container";
+// each {placeholder} captures one argument for the handler
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntentPattern {
+    pub phrase: String,
+    pub action: String,
+}
+
+// Capability added to every component of `component_type`, or to all of them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapabilityDeclaration {
+    pub component_type: Option<String>,
+    pub capability: String,
+}
+
+pub trait IntentPlugin: Send {
+    fn id(&self) -> &str;
+    fn patterns(&self) -> Vec<IntentPattern>;
+    fn capabilities(&self) -> Vec<CapabilityDeclaration>;
+    // Component types the plugin adds to the registry
+    fn component_types(&self) -> Vec<ComponentType> {
+        Vec::new()
+    }
+    fn handle(&self, action: &str, args: &[String]) -> anyhow::Result<serde_json::Value>;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginSummary {
+    pub id: String,
+    pub builtin: bool,
+    pub patterns: Vec<IntentPattern>,
+    pub capabilities: Vec<CapabilityDeclaration>,
+    pub component_types: Vec<String>,
+}
+
+// ---------- Manifest plugins ----------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestAction {
+    pub phrases: Vec<String>,
+    // argv to run; "{0}", "{1}", ... are replaced by the captured arguments
+    pub command: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginManifest {
+    pub id: String,
+    #[serde(default)]
+    pub capabilities: Vec<CapabilityDeclaration>,
+    #[serde(default)]
+    pub component_types: Vec<ComponentType>,
+    pub actions: std::collections::BTreeMap<String, ManifestAction>,
+}
+
+impl IntentPlugin for PluginManifest {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn patterns(&self) -> Vec<IntentPattern> {
+        self.actions
+            .iter()
+            .flat_map(|(action, spec)| {
+                spec.phrases.iter().map(move |phrase| IntentPattern {
+                    phrase: phrase.clone(),
+                    action: action.clone(),
+                })
+            })
+            .collect()
+    }
+
+    fn capabilities(&self) -> Vec<CapabilityDeclaration> {
+        self.capabilities.clone()
+    }
+
+    fn component_types(&self) -> Vec<ComponentType> {
+        self.component_types.clone()
+    }
+
+    fn handle(&self, action: &str, args: &[String]) -> anyhow::Result<serde_json::Value> {
+        let spec = self
+            .actions
+            .get(action)
+            .ok_or_else(|| anyhow!("{} has no action '{}'", self.id, action))?;
+        let argv: Vec<String> = spec
+            .command
+            .iter()
+            .map(|part| {
+                args.iter()
+                    .enumerate()
+                    .fold(part.clone(), |part, (i, arg)| {
+                        part.replace(&format!("{{{}}}", i), arg)
+                    })
+            })
+            .collect();
+        let (program, rest) = argv
+            .split_first()
+            .ok_or_else(|| anyhow!("{}/{} has an empty command", self.id, action))?;
+        let rest: Vec<&str> = rest.iter().map(String::as_str).collect();
+        let output = system::run(program, &rest)?;
+        Ok(serde_json::json!({"output": output}))
+    }
+}
+
+fn manifest_dir() -> PathBuf {
+    storage::config_dir().join(PLUGINS_DIR)
+}
+
+fn manifest_path(id: &str) -> PathBuf {
+    manifest_dir().join(format!("{}.json", id))
+}
+
+fn load_manifests() -> Vec<PluginManifest> {
+    let Ok(entries) = fs::read_dir(manifest_dir()) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|e| storage::read_json::<Option<PluginManifest>>(&e.path()).ok()?)
+        .collect()
+}
+
+// ---------- Built-in plugins ----------
+
+// Starts, stops and lists the user's Docker containers
+struct DockerPlugin;
+
+impl DockerPlugin {
+    fn containers(status: &str) -> anyhow::Result<Vec<String>> {
+        let filter = format!("status={}", status);
+        Ok(system::run(
+            "docker",
+            &["ps", "-a", "--filter", &filter, "--format", "{{.Names}}"],
+        )?
+        .lines()
+        .map(str::to_string)
+        .collect())
+    }
+
+    fn each(verb: &str, names: Vec<String>) -> anyhow::Result<serde_json::Value> {
+        if names.is_empty() {
+            return Ok(serde_json::json!({"containers": []}));
+        }
+        let mut args = vec![verb];
+        args.extend(names.iter().map(String::as_str));
+        system::run("docker", &args)?;
+        Ok(serde_json::json!({"containers": names}))
+    }
+}
+
+impl IntentPlugin for DockerPlugin {
+    fn id(&self) -> &str {
+        "docker"
+    }
+
+    fn patterns(&self) -> Vec<IntentPattern> {
+        let pattern = |phrase: &str, action: &str| IntentPattern {
+            phrase: phrase.to_string(),
+            action: action.to_string(),
+        };
+        vec![
+            pattern("start my containers", "start_all"),
+            pattern("start all containers", "start_all"),
+            pattern("stop my containers", "stop_all"),
+            pattern("stop all containers", "stop_all"),
+            pattern("list my containers", "list"),
+            pattern("show my containers", "list"),
+            pattern("what containers are running", "list"),
+            pattern("start the {name} container", "start"),
+            pattern("start container {name}", "start"),
+            pattern("stop the {name} container", "stop"),
+            pattern("stop container {name}", "stop"),
+            pattern("restart the {name} container", "restart"),
+            pattern("restart container {name}", "restart"),
+        ]
+    }
+
+    fn capabilities(&self) -> Vec<CapabilityDeclaration> {
+        vec![
+            CapabilityDeclaration {
+                component_type: Some("SearchInput".to_string()),
+                capability: "containers".to_string(),
+            },
+            CapabilityDeclaration {
+                component_type: Some("ResultsList".to_string()),
+                capability: "container-actions".to_string(),
+            },
+        ]
+    }
+
+    fn handle(&self, action: &str, args: &[String]) -> anyhow::Result<serde_json::Value> {
+        let named = || -> anyhow::Result<Vec<String>> {
+            match args.first() {
+                Some(name) if !name.is_empty() => Ok(vec![name.clone()]),
+                _ => bail!("Which container?"),
+            }
+        };
+        match action {
+            "start_all" => Self::each("start", Self::containers("exited")?),
+            "stop_all" => Self::each("stop", Self::containers("running")?),
+            "list" => Ok(serde_json::json!({"containers": Self::containers("running")?})),
+            "start" => Self::each("start", named()?),
+            "stop" => Self::each("stop", named()?),
+            "restart" => Self::each("restart", named()?),
+            other => bail!("docker has no action '{}'", other),
+        }
+    }
+}
+
+fn builtin_plugins() -> Vec<Box<dyn IntentPlugin>> {
+    let mut plugins: Vec<Box<dyn IntentPlugin>> = Vec::new();
+    if system::find_in_path("docker").is_some() {
+        plugins.push(Box::new(DockerPlugin));
+    }
+    plugins
+}
+
+// ---------- Registry ----------
+
+// Match "restart the {name} container" against text; returns the captures
+fn match_pattern(pattern: &str, text: &str) -> Option<Vec<String>> {
+    let mut literals: Vec<&str> = Vec::new();
+    let mut rest = pattern;
+    while let Some(open) = rest.find('{') {
+        let close = open + rest[open..].find('}')?;
+        literals.push(&rest[..open]);
+        rest = &rest[close + 1..];
+    }
+    literals.push(rest);
+
+    let mut remaining = text.strip_prefix(literals[0])?;
+    let mut captures = Vec::new();
+    for (i, literal) in literals.iter().enumerate().skip(1) {
+        let end = if i == literals.len() - 1 {
+            // The last literal has to end the text
+            remaining.strip_suffix(literal)?.len()
+        } else if literal.is_empty() {
+            return None;
+        } else {
+            remaining.find(literal)?
+        };
+        let capture = remaining[..end].trim();
+        if capture.is_empty() {
+            return None;
+        }
+        captures.push(capture.to_string());
+        remaining = &remaining[end + literal.len()..];
+    }
+    if literals.len() == 1 && !remaining.is_empty() {
+        return None;
+    }
+    Some(captures)
+}
+
+#[derive(Default)]
+pub struct PluginRegistry {
+    plugins: Vec<(bool, Box<dyn IntentPlugin>)>,
+}
+
+impl PluginRegistry {
+    pub fn load() -> Self {
+        let mut registry = PluginRegistry::default();
+        for plugin in builtin_plugins() {
+            registry.plugins.push((true, plugin));
+        }
+        for manifest in load_manifests() {
+            let _ = registry.register(Box::new(manifest));
+        }
+        registry
+    }
+
+    pub fn register(&mut self, plugin: Box<dyn IntentPlugin>) -> anyhow::Result<()> {
+        let id = plugin.id().to_string();
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            bail!("Plugin ids may only contain letters, digits and '-'");
+        }
+        if self.plugins.iter().any(|(_, p)| p.id() == id) {
+            bail!("A plugin called '{}' is already registered", id);
+        }
+        if plugin.patterns().is_empty() {
+            bail!("{} doesn't declare any phrases", id);
+        }
+        self.plugins.push((false, plugin));
+        Ok(())
+    }
+
+    pub fn unregister(&mut self, id: &str) -> anyhow::Result<()> {
+        match self.plugins.iter().position(|(_, p)| p.id() == id) {
+            Some(index) if self.plugins[index].0 => bail!("Built-in plugins cannot be removed"),
+            Some(index) => {
+                self.plugins.remove(index);
+                Ok(())
+            }
+            None => bail!("No plugin called '{}'", id),
+        }
+    }
+
+    pub fn list(&self) -> Vec<PluginSummary> {
+        self.plugins
+            .iter()
+            .map(|(builtin, plugin)| PluginSummary {
+                id: plugin.id().to_string(),
+                builtin: *builtin,
+                patterns: plugin.patterns(),
+                capabilities: plugin.capabilities(),
+                component_types: plugin
+                    .component_types()
+                    .into_iter()
+                    .map(|t| t.component_type)
+                    .collect(),
+            })
+            .collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn IntentPlugin> {
+        self.plugins.iter().map(|(_, plugin)| plugin.as_ref())
+    }
+
+    // The first plugin phrase that matches the whole query
+    pub fn parse(&self, query: &str) -> Option<Intent> {
+        let text = nlp::normalize(query);
+        self.plugins.iter().find_map(|(_, plugin)| {
+            plugin.patterns().into_iter().find_map(|pattern| {
+                match_pattern(&pattern.phrase.to_lowercase(), &text).map(|args| Intent::Plugin {
+                    plugin: plugin.id().to_string(),
+                    action: pattern.action,
+                    args,
+                })
+            })
+        })
+    }
+
+    pub fn handle(
+        &self,
+        plugin: &str,
+        action: &str,
+        args: &[String],
+    ) -> anyhow::Result<serde_json::Value> {
+        let (_, plugin) = self
+            .plugins
+            .iter()
+            .find(|(_, p)| p.id() == plugin)
+            .ok_or_else(|| anyhow!("The '{}' plugin is not installed", plugin))?;
+        plugin.handle(action, args)
+    }
+
+    // Capabilities every plugin adds to components of this type
+    pub fn capabilities_for(&self, component_type: &str) -> Vec<String> {
+        let mut capabilities: Vec<String> = Vec::new();
+        for (_, plugin) in &self.plugins {
+            for declaration in plugin.capabilities() {
+                let applies = declaration
+                    .component_type
+                    .as_deref()
+                    .is_none_or(|t| t == component_type);
+                if applies && !capabilities.contains(&declaration.capability) {
+                    capabilities.push(declaration.capability);
+                }
+            }
+        }
+        capabilities
+    }
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn list_plugins(app: AppHandle) -> Vec<PluginSummary> {
+    tasks::blocking_value(&app, "List plugins", |state| {
+        state.plugins.blocking_lock().list()
+    })
+    .await
+}
+
+// Register a manifest plugin, and its component types, and keep it for the
+// next start
+#[tauri::command]
+pub async fn register_plugin(manifest: PluginManifest, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Register plugin", move |state| {
+        if let Some(refusal) = managed::guard("plugin", &format!("Add the {} plugin", manifest.id))
+        {
+            return refusal;
+        }
+        let path = manifest_path(&manifest.id);
+        let mut plugins = state.plugins.blocking_lock();
+        let result = plugins
+            .register(Box::new(manifest.clone()))
+            .and_then(|()| {
+                let registered = state
+                    .component_types
+                    .blocking_lock()
+                    .register_plugin(&manifest);
+                if registered.is_err() {
+                    let _ = plugins.unregister(&manifest.id);
+                }
+                registered
+            })
+            .and_then(|()| storage::write_json(&path, &manifest))
+            .map(|()| path);
+        crate::respond(result)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn unregister_plugin(id: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Unregister plugin", move |state| {
+        if let Some(refusal) = managed::guard("plugin", &format!("Remove the {} plugin", id)) {
+            return refusal;
+        }
+        let result = state
+            .plugins
+            .blocking_lock()
+            .unregister(&id)
+            .and_then(|()| {
+                state.component_types.blocking_lock().unregister_plugin(&id);
+                let path = manifest_path(&id);
+                if path.exists() {
+                    fs::remove_file(&path)?;
+                }
+                Ok(())
+            });
+        crate::respond(result)
+    })
+    .await
+}
@@ -0,0 +1,434 @@
+// Power management and laptop tuning
+//
+// Detects laptop hardware, proposes a TLP / auto-cpufreq / powertop based power
+// profile as NixOS options, and measures its battery impact by comparing
+// discharge-rate samples recorded before and after the profile was applied.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::nixgen::{self, NixModule, NixOption, Target};
+use crate::sandbox::Simulation;
+use crate::{clock, hardware, storage, system, tasks};
+
+pub const HISTORY_FILE: &str = "power-history.json";
+const MAX_SAMPLES: usize = 5000;
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PowerTool {
+    Tlp,
+    AutoCpufreq,
+    Powertop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerProfile {
+    Battery,
+    Balanced,
+    Performance,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Battery {
+    pub name: String,
+    pub status: String,
+    pub capacity_percent: Option<u32>,
+    pub power_draw_watts: Option<f64>,
+    // Wear: current full charge relative to design capacity
+    pub health_percent: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerStatus {
+    pub is_laptop: bool,
+    pub on_battery: bool,
+    pub batteries: Vec<Battery>,
+    pub cpu_vendor: Option<String>,
+    pub active_services: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerPlan {
+    pub tool: PowerTool,
+    pub profile: PowerProfile,
+    pub options: Vec<NixOption>,
+    pub notes: Vec<String>,
+    pub module_preview: String,
+    // Evaluation of the would-be configuration; nothing is written until it passes
+    #[serde(default)]
+    pub simulation: Simulation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerSample {
+    pub timestamp: u64,
+    pub on_battery: bool,
+    pub power_draw_watts: Option<f64>,
+    pub capacity_percent: Option<u32>,
+}
+
+// Samples plus the moments a profile was applied, so impact can be attributed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PowerHistory {
+    pub samples: Vec<PowerSample>,
+    pub applied: Vec<(u64, PowerTool, PowerProfile)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatteryImpact {
+    pub tool: PowerTool,
+    pub profile: PowerProfile,
+    pub applied_at: u64,
+    pub baseline_watts: Option<f64>,
+    pub tuned_watts: Option<f64>,
+    pub change_percent: Option<f64>,
+    pub samples_before: usize,
+    pub samples_after: usize,
+    pub summary: String,
+}
+
+fn read_number(path: &Path) -> Option<f64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn read_battery(dir: &Path) -> Battery {
+    let name = dir
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    // Drivers expose either energy_* (µWh, power_now in µW) or charge_* (µAh, current_now in µA)
+    let power_draw_watts = read_number(&dir.join("power_now"))
+        .map(|uw| uw / 1_000_000.0)
+        .or_else(|| {
+            let current = read_number(&dir.join("current_now"))?;
+            let voltage = read_number(&dir.join("voltage_now"))?;
+            Some(current * voltage / 1e12)
+        });
+    let (full, design) = match read_number(&dir.join("energy_full")) {
+        Some(full) => (Some(full), read_number(&dir.join("energy_full_design"))),
+        None => (
+            read_number(&dir.join("charge_full")),
+            read_number(&dir.join("charge_full_design")),
+        ),
+    };
+    Battery {
+        name,
+        status: fs::read_to_string(dir.join("status"))
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default(),
+        capacity_percent: read_number(&dir.join("capacity")).map(|c| c as u32),
+        power_draw_watts,
+        health_percent: full
+            .zip(design)
+            .filter(|(_, d)| *d > 0.0)
+            .map(|(f, d)| (f / d * 1000.0).round() / 10.0),
+    }
+}
+
+fn batteries() -> Vec<Battery> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir("/sys/class/power_supply")
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|e| e.file_name().to_string_lossy().starts_with("BAT"))
+                .map(|e| e.path())
+                .collect()
+        })
+        .unwrap_or_default();
+    dirs.sort();
+    dirs.iter().map(|d| read_battery(d)).collect()
+}
+
+fn service_active(unit: &str) -> bool {
+    system::run("systemctl", &["is-active", "--quiet", unit]).is_ok()
+}
+
+pub fn status() -> PowerStatus {
+    let batteries = batteries();
+    PowerStatus {
+        is_laptop: hardware::is_laptop(),
+        on_battery: batteries.iter().any(|b| b.status == "Discharging"),
+        batteries,
+        cpu_vendor: hardware::cpu_vendor(),
+        active_services: ["tlp", "auto-cpufreq", "power-profiles-daemon", "thermald"]
+            .iter()
+            .filter(|u| service_active(u))
+            .map(|u| u.to_string())
+            .collect(),
+    }
+}
+
+fn tlp_settings(profile: PowerProfile) -> Vec<(&'static str, &'static str)> {
+    let (bat_governor, bat_epp, bat_boost) = match profile {
+        PowerProfile::Battery => ("powersave", "power", "0"),
+        PowerProfile::Balanced => ("powersave", "balance_power", "0"),
+        PowerProfile::Performance => ("performance", "balance_performance", "1"),
+    };
+    vec![
+        ("CPU_SCALING_GOVERNOR_ON_AC", "performance"),
+        ("CPU_SCALING_GOVERNOR_ON_BAT", bat_governor),
+        ("CPU_ENERGY_PERF_POLICY_ON_AC", "performance"),
+        ("CPU_ENERGY_PERF_POLICY_ON_BAT", bat_epp),
+        ("CPU_BOOST_ON_BAT", bat_boost),
+        (
+            "PLATFORM_PROFILE_ON_BAT",
+            if profile == PowerProfile::Battery {
+                "low-power"
+            } else {
+                "balanced"
+            },
+        ),
+        // Charge thresholds preserve battery health on supported laptops
+        ("START_CHARGE_THRESH_BAT0", "75"),
+        ("STOP_CHARGE_THRESH_BAT0", "85"),
+    ]
+}
+
+pub fn propose(profile: PowerProfile, tool: Option<PowerTool>) -> PowerPlan {
+    let current = status();
+    let tool = tool.unwrap_or(PowerTool::Tlp);
+    let mut notes = Vec::new();
+    if !current.is_laptop {
+        notes.push("No battery detected; power tuning mostly matters on laptops.".to_string());
+    }
+
+    let mut options = Vec::new();
+    match tool {
+        PowerTool::Tlp => {
+            let settings: Vec<String> = tlp_settings(profile)
+                .into_iter()
+                .map(|(k, v)| {
+                    let value = if v.parse::<u32>().is_ok() {
+                        v.to_string()
+                    } else {
+                        nixgen::string(v)
+                    };
+                    format!("    {} = {};", k, value)
+                })
+                .collect();
+            options.push(NixOption::new("services.tlp.enable", nixgen::bool(true)));
+            options.push(NixOption::new(
+                "services.tlp.settings",
+                format!("{{\n{}\n  }}", settings.join("\n")),
+            ));
+            options.push(
+                NixOption::new("services.power-profiles-daemon.enable", nixgen::bool(false))
+                    .with_comment("power-profiles-daemon conflicts with TLP"),
+            );
+        }
+        PowerTool::AutoCpufreq => {
+            let (governor, turbo) = match profile {
+                PowerProfile::Battery => ("powersave", "never"),
+                PowerProfile::Balanced => ("powersave", "auto"),
+                PowerProfile::Performance => ("performance", "auto"),
+            };
+            options.push(NixOption::new(
+                "services.auto-cpufreq.enable",
+                nixgen::bool(true),
+            ));
+            options.push(NixOption::new(
+                "services.auto-cpufreq.settings",
+                format!(
+                    "{{\n    battery = {{ governor = {}; turbo = {}; }};\n    charger = {{ governor = \"performance\"; turbo = \"auto\"; }};\n  }}",
+                    nixgen::string(governor),
+                    nixgen::string(turbo)
+                ),
+            ));
+            options.push(
+                NixOption::new("services.power-profiles-daemon.enable", nixgen::bool(false))
+                    .with_comment("power-profiles-daemon conflicts with auto-cpufreq"),
+            );
+        }
+        PowerTool::Powertop => {
+            options.push(
+                NixOption::new("powerManagement.powertop.enable", nixgen::bool(true))
+                    .with_comment("Applies powertop --auto-tune at boot"),
+            );
+            notes.push(
+                "powertop auto-tune can make some USB mice and keyboards sleep too eagerly."
+                    .to_string(),
+            );
+        }
+    }
+    if current.cpu_vendor.as_deref() == Some("GenuineIntel") {
+        options.push(NixOption::new(
+            "services.thermald.enable",
+            nixgen::bool(true),
+        ));
+    }
+    let active = |unit: &str| current.active_services.iter().any(|s| s == unit);
+    if (active("tlp") && tool == PowerTool::AutoCpufreq)
+        || (active("auto-cpufreq") && tool == PowerTool::Tlp)
+    {
+        notes.push("Another power daemon is active; remove it from your configuration to avoid both fighting over CPU settings.".to_string());
+    }
+    notes.push(
+        "Battery impact is measured from samples taken over your next sessions on battery."
+            .to_string(),
+    );
+
+    let mut module = NixModule::new("power", "power management", Target::Nixos);
+    for option in &options {
+        module.set(option.clone());
+    }
+    PowerPlan {
+        tool,
+        profile,
+        options,
+        notes,
+        module_preview: module.render(),
+        simulation: module.simulate(),
+    }
+}
+
+pub fn apply(plan: &PowerPlan) -> anyhow::Result<PathBuf> {
+    let mut module = NixModule::new("power", "power management", Target::Nixos);
+    for option in &plan.options {
+        module.set(option.clone());
+    }
+    let path = module.write()?;
+    let mut history: PowerHistory = storage::load_data(HISTORY_FILE)?;
+    history
+        .applied
+        .push((clock::now(), plan.tool, plan.profile));
+    storage::save_data(HISTORY_FILE, &history)?;
+    Ok(path)
+}
+
+pub fn record_sample() -> anyhow::Result<()> {
+    let batteries = batteries();
+    if batteries.is_empty() {
+        return Ok(());
+    }
+    let on_battery = batteries.iter().any(|b| b.status == "Discharging");
+    let draw: Vec<f64> = batteries
+        .iter()
+        .filter_map(|b| b.power_draw_watts)
+        .collect();
+    let mut history: PowerHistory = storage::load_data(HISTORY_FILE)?;
+    history.samples.push(PowerSample {
+        timestamp: clock::now(),
+        on_battery,
+        power_draw_watts: (!draw.is_empty()).then(|| draw.iter().sum()),
+        capacity_percent: batteries[0].capacity_percent,
+    });
+    if history.samples.len() > MAX_SAMPLES {
+        let excess = history.samples.len() - MAX_SAMPLES;
+        history.samples.drain(..excess);
+    }
+    storage::save_data(HISTORY_FILE, &history)?;
+    Ok(())
+}
+
+// Record a sample every few minutes while the app runs (laptops only)
+pub fn start_sampler() {
+    if !hardware::is_laptop() {
+        return;
+    }
+    std::thread::spawn(|| loop {
+        let _ = record_sample();
+        std::thread::sleep(SAMPLE_INTERVAL);
+    });
+}
+
+fn mean_draw(samples: &[&PowerSample]) -> Option<f64> {
+    let values: Vec<f64> = samples.iter().filter_map(|s| s.power_draw_watts).collect();
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+// Compare average on-battery draw before and after the most recent profile change
+pub fn impact() -> anyhow::Result<Option<BatteryImpact>> {
+    let history: PowerHistory = storage::load_data(HISTORY_FILE)?;
+    let Some(&(applied_at, tool, profile)) = history.applied.last() else {
+        return Ok(None);
+    };
+    let previous = history
+        .applied
+        .iter()
+        .rev()
+        .nth(1)
+        .map(|(t, _, _)| *t)
+        .unwrap_or(0);
+    let on_battery: Vec<&PowerSample> = history.samples.iter().filter(|s| s.on_battery).collect();
+    let before: Vec<&PowerSample> = on_battery
+        .iter()
+        .copied()
+        .filter(|s| s.timestamp >= previous && s.timestamp < applied_at)
+        .collect();
+    let after: Vec<&PowerSample> = on_battery
+        .iter()
+        .copied()
+        .filter(|s| s.timestamp >= applied_at)
+        .collect();
+    let baseline_watts = mean_draw(&before);
+    let tuned_watts = mean_draw(&after);
+    let change_percent = baseline_watts
+        .zip(tuned_watts)
+        .filter(|(b, _)| *b > 0.0)
+        .map(|(b, t)| ((t - b) / b * 1000.0).round() / 10.0);
+    let summary = match (baseline_watts, tuned_watts, change_percent) {
+        (Some(b), Some(t), Some(c)) => format!(
+            "On battery the system drew {:.1} W before and {:.1} W after ({:+.1}%).",
+            b, t, c
+        ),
+        (None, _, _) => {
+            "No on-battery samples from before the change to compare against.".to_string()
+        }
+        _ => "Not enough on-battery time since the change yet; check back after a few sessions."
+            .to_string(),
+    };
+    Ok(Some(BatteryImpact {
+        tool,
+        profile,
+        applied_at,
+        baseline_watts,
+        tuned_watts,
+        change_percent,
+        samples_before: before.len(),
+        samples_after: after.len(),
+        summary,
+    }))
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_power_status(app: AppHandle) -> PowerStatus {
+    tasks::blocking_value(&app, "Get power status", |_| status()).await
+}
+
+#[tauri::command]
+pub async fn propose_power_profile(
+    profile: PowerProfile,
+    tool: Option<PowerTool>,
+    app: AppHandle,
+) -> PowerPlan {
+    tasks::blocking_value(&app, "Propose power profile", move |_| {
+        propose(profile, tool)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn apply_power_profile(plan: PowerPlan, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Apply power profile", move |_| {
+        crate::respond(apply(&plan).map(|path| {
+            serde_json::json!({
+                "module_path": path,
+                "next_step": "Rebuild the system to activate the power profile",
+            })
+        }))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_battery_impact(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Get battery impact", |_| crate::respond(impact())).await
+}
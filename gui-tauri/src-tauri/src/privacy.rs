@@ -0,0 +1,245 @@
+// Privacy controls for everything that learns from or reads into the user
+//
+// Each collector can be switched off on its own: keystroke timing (typing
+// analysis), emotion estimates from speech, writing-style detection, concept
+// mastery, learned phrasings and the action history. The call sites check
+// `allowed` before invoking a collector, so a disabled one never runs rather
+// than running and having its output dropped. History is also kept only as
+// long as the retention setting says. purge_all_data deletes every personal
+// store in the data dir and then checks that none of them is left.
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+use crate::{clock, context, history, sessions, storage, tasks, AppState};
+
+const SETTINGS_FILE: &str = "privacy.json";
+const DAY: u64 = 24 * 60 * 60;
+// Stores in the data dir that describe the user rather than the system
+const PERSONAL_DATA: &[&str] = &[
+    "history.json",
+    "phrasings.json",
+    "adaptation-log.json",
+    "user-profile.json",
+    // Autosave snapshots carry the profile and what was on screen
+    "workspace.json",
+    "workspace-recovery.json",
+    // Operations this user ran, when there is no shared audit log
+    "audit.jsonl",
+    // Reminder text is whatever the user typed
+    "reminders.json",
+    // Battery and charge samples show when and where the machine was used
+    "power-history.json",
+];
+// Directories of them: recorded test scenarios hold what was typed
+const PERSONAL_DIRS: &[&str] = &["scenarios"];
+// Copies userprofile::load keeps from before a schema migration
+const PROFILE_BACKUP_PREFIX: &str = "user-profile.v";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Collector {
+    TypingAnalysis,
+    VoiceEmotion,
+    StyleDetection,
+    ExpertiseTracking,
+    PhrasingLearning,
+    History,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrivacySettings {
+    // Keystroke counts and timings sent with typed requests
+    pub typing_analysis: bool,
+    // Emotion estimates from the tone of voice input; off until the user opts in
+    pub voice_emotion: bool,
+    // Writing-style averages that pick the personality
+    pub style_detection: bool,
+    // Concept mastery that adapts verbosity
+    pub expertise_tracking: bool,
+    // Phrasings that keep needing clarification, for alias suggestions
+    pub phrasing_learning: bool,
+    // None keeps history up to its size limit; 0 keeps none at all
+    pub history_retention_days: Option<u64>,
+}
+
+impl Default for PrivacySettings {
+    fn default() -> Self {
+        PrivacySettings {
+            typing_analysis: true,
+            voice_emotion: false,
+            style_detection: true,
+            expertise_tracking: true,
+            phrasing_learning: true,
+            history_retention_days: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurgeReport {
+    pub deleted: Vec<String>,
+    // Every personal store, confirmed absent after deleting
+    pub verified_absent: Vec<String>,
+}
+
+pub fn settings() -> PrivacySettings {
+    storage::load(SETTINGS_FILE).unwrap_or_default()
+}
+
+impl PrivacySettings {
+    pub fn allows(&self, collector: Collector) -> bool {
+        match collector {
+            Collector::TypingAnalysis => self.typing_analysis,
+            Collector::VoiceEmotion => self.voice_emotion,
+            Collector::StyleDetection => self.style_detection,
+            Collector::ExpertiseTracking => self.expertise_tracking,
+            Collector::PhrasingLearning => self.phrasing_learning,
+            Collector::History => self.history_retention_days != Some(0),
+        }
+    }
+}
+
+// The enforcement hook: check before invoking `collector`. Nothing is learned
+// into a data dir that belongs to another user.
+pub fn allowed(collector: Collector) -> bool {
+    sessions::isolated() && settings().allows(collector)
+}
+
+// History older than this (Unix seconds) is not kept
+pub fn retention_cutoff() -> Option<u64> {
+    settings()
+        .history_retention_days
+        .map(|days| clock::now().saturating_sub(days * DAY))
+}
+
+fn personal_files() -> Vec<PathBuf> {
+    let dir = storage::data_dir();
+    let mut files: Vec<PathBuf> = PERSONAL_DATA.iter().map(|name| dir.join(name)).collect();
+    files.extend(PERSONAL_DIRS.iter().map(|name| dir.join(name)));
+    if let Ok(entries) = fs::read_dir(&dir) {
+        files.extend(entries.flatten().map(|e| e.path()).filter(|p| {
+            p.file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with(PROFILE_BACKUP_PREFIX))
+        }));
+    }
+    // Interrupted atomic writes leave these behind
+    let temporary: Vec<PathBuf> = files.iter().map(|f| f.with_extension("tmp")).collect();
+    files.extend(temporary);
+    files
+}
+
+// Delete every personal store and what this session holds in memory
+pub fn purge(state: &AppState) -> anyhow::Result<PurgeReport> {
+    state.interaction_history.blocking_lock().clear();
+    *state.conversation.blocking_lock() = context::ConversationContext::default();
+    *state.user_profile.blocking_lock() = None;
+    purge_files()
+}
+
+fn purge_files() -> anyhow::Result<PurgeReport> {
+    let mut deleted = Vec::new();
+    for path in personal_files() {
+        if path.is_dir() {
+            fs::remove_dir_all(&path)?;
+            deleted.push(path.display().to_string());
+        } else if path.exists() {
+            fs::remove_file(&path)?;
+            deleted.push(path.display().to_string());
+        }
+    }
+    let (remaining, absent): (Vec<PathBuf>, Vec<PathBuf>) =
+        personal_files().into_iter().partition(|p| p.exists());
+    if !remaining.is_empty() {
+        let names: Vec<String> = remaining.iter().map(|p| p.display().to_string()).collect();
+        bail!("Could not delete {}", names.join(", "));
+    }
+    Ok(PurgeReport {
+        deleted,
+        verified_absent: absent.iter().map(|p| p.display().to_string()).collect(),
+    })
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_privacy_settings(app: AppHandle) -> PrivacySettings {
+    tasks::blocking_value(&app, "Get privacy settings", |_| settings()).await
+}
+
+// Save the toggles and apply a shorter retention to what is already stored
+#[tauri::command]
+pub async fn set_privacy_settings(settings: PrivacySettings, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set privacy settings", move |state| {
+        let result = storage::save(SETTINGS_FILE, &settings).and_then(|_| {
+            for interaction in state.interaction_history.blocking_lock().iter_mut() {
+                if let Some(map) = interaction.as_object_mut() {
+                    if !settings.allows(Collector::TypingAnalysis) {
+                        map.remove("keystrokes");
+                    }
+                    if !settings.allows(Collector::VoiceEmotion) {
+                        map.remove("prosody");
+                    }
+                }
+            }
+            history::prune(retention_cutoff())?;
+            Ok(settings)
+        });
+        crate::respond(result)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn purge_all_data(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Purge all data", |state| crate::respond(purge(state))).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{adaptation, aliases, autosave, power, reminders, testing, userprofile};
+
+    // Every store named by a *_FILE const that holds something about the user.
+    // A new one goes here and into PERSONAL_DATA together.
+    #[test]
+    fn every_personal_store_is_purged() {
+        let stores = [
+            history::HISTORY_FILE,
+            aliases::PHRASINGS_FILE,
+            adaptation::LOG_FILE,
+            userprofile::PROFILE_FILE,
+            autosave::SNAPSHOT_FILE,
+            autosave::RECOVERY_FILE,
+            sessions::AUDIT_FILE,
+            reminders::REMINDERS_FILE,
+            power::HISTORY_FILE,
+        ];
+        for store in stores {
+            assert!(PERSONAL_DATA.contains(&store), "{store} is not purged");
+        }
+        assert!(PERSONAL_DIRS.contains(&testing::SCENARIO_DIR));
+    }
+
+    #[test]
+    fn purge_leaves_no_personal_store_and_keeps_settings() {
+        storage::with_root(&storage::scratch_dir("privacy"), || {
+            let dir = storage::data_dir();
+            fs::create_dir_all(dir.join(testing::SCENARIO_DIR)).unwrap();
+            for name in PERSONAL_DATA {
+                fs::write(dir.join(name), "{}").unwrap();
+            }
+            fs::write(dir.join("user-profile.v1.json"), "{}").unwrap();
+            fs::write(dir.join("reminders.tmp"), "{}").unwrap();
+            storage::save(SETTINGS_FILE, &PrivacySettings::default()).unwrap();
+
+            let report = purge_files().unwrap();
+            assert_eq!(report.deleted.len(), PERSONAL_DATA.len() + 3);
+            assert!(personal_files().iter().all(|p| !p.exists()));
+            assert!(storage::config_dir().join(SETTINGS_FILE).exists());
+        });
+    }
+}
@@ -0,0 +1,111 @@
+// Scripts run in a window that answer back
+//
+// A webview's eval is fire and forget. A probe wraps the script in an async
+// function whose value, or the error it threw, comes back through the
+// probe_result command under the probe's id, and waits for that answer up to a
+// timeout. Waiting blocks, so probes run inside tasks, never on the event loop
+// that delivers the answer.
+
+use anyhow::{anyhow, bail, Context};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Mutex;
+
+use crate::panels;
+
+// Each component's root element carries its id in this attribute
+pub const COMPONENT_ATTRIBUTE: &str = "data-component-id";
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+type Answer = Result<serde_json::Value, String>;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static PENDING: Mutex<Option<HashMap<u64, Sender<Answer>>>> = Mutex::const_new(None);
+
+// A JS expression for the root element of a component, or null
+pub fn component_element(component_id: &str) -> String {
+    let selector = format!(
+        "[{}={}]",
+        COMPONENT_ATTRIBUTE,
+        serde_json::to_string(component_id).unwrap_or_default()
+    );
+    format!(
+        "document.querySelector({})",
+        serde_json::to_string(&selector).unwrap_or_default()
+    )
+}
+
+// Run `body`, the body of an async JS function, in a window and return what it
+// returns; anything JSON can hold
+pub fn eval(
+    app: &AppHandle,
+    window: &str,
+    body: &str,
+    timeout: Duration,
+) -> anyhow::Result<serde_json::Value> {
+    let webview = app
+        .get_webview_window(window)
+        .ok_or_else(|| anyhow!("There is no window \"{}\"", window))?;
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let (sender, receiver) = mpsc::channel();
+    PENDING
+        .blocking_lock()
+        .get_or_insert_with(HashMap::new)
+        .insert(id, sender);
+    let script = format!(
+        "(async function(){{{}\n}})().then(\
+         function(v){{return {{ok:true,value:v===undefined?null:v}};}},\
+         function(e){{return {{ok:false,value:String(e&&e.message||e)}};}}\
+         ).then(function(r){{window.__TAURI_INTERNALS__.invoke('probe_result',{{id:{},ok:r.ok,value:r.value}});}});",
+        body, id
+    );
+    let result = webview
+        .eval(&script)
+        .with_context(|| format!("Running a script in {}", window))
+        .and_then(|()| match receiver.recv_timeout(timeout) {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(error)) => bail!("The script failed in {}: {}", window, error),
+            Err(_) => bail!(
+                "{} didn't answer within {} seconds",
+                window,
+                timeout.as_secs_f32()
+            ),
+        });
+    if let Some(pending) = PENDING.blocking_lock().as_mut() {
+        pending.remove(&id);
+    }
+    result
+}
+
+// In whichever window shows the component
+pub fn eval_in_component(
+    app: &AppHandle,
+    component_id: &str,
+    body: &str,
+    timeout: Duration,
+) -> anyhow::Result<serde_json::Value> {
+    eval(app, &panels::window_for(app, component_id), body, timeout)
+}
+
+// ========== Tauri Commands ==========
+
+// Where the page answers a probe
+#[tauri::command]
+pub async fn probe_result(id: u64, ok: bool, value: serde_json::Value) {
+    let sender = PENDING
+        .lock()
+        .await
+        .as_mut()
+        .and_then(|pending| pending.remove(&id));
+    if let Some(sender) = sender {
+        let answer = if ok {
+            Ok(value)
+        } else {
+            Err(value.as_str().unwrap_or_default().to_string())
+        };
+        let _ = sender.send(answer);
+    }
+}
@@ -0,0 +1,263 @@
+// Process inspector with package attribution
+//
+// Lists running processes with their memory use, maps each executable back to
+// the Nix store path and package it came from (and which profile provides
+// it), and offers kill or restart-through-systemd actions.
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::{inventory, managed, system, tasks};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Provenance {
+    SystemProfile,
+    UserProfile,
+    // In the store but not linked from a profile (nix shell, services, ...)
+    NixStore,
+    OutsideStore,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: String,
+    pub command: String,
+    pub uid: u32,
+    pub rss_mb: u64,
+    pub store_path: Option<String>,
+    pub package: Option<String>,
+    pub version: Option<String>,
+    pub provenance: Provenance,
+    // systemd unit that owns the process, if any ("foo.service", user or system)
+    pub unit: Option<String>,
+    pub user_unit: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageUsage {
+    pub package: String,
+    pub processes: usize,
+    pub rss_mb: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessReport {
+    pub processes: Vec<ProcessInfo>,
+    pub by_package: Vec<PackageUsage>,
+}
+
+fn status_field(status: &str, name: &str) -> Option<String> {
+    status
+        .lines()
+        .find_map(|l| l.strip_prefix(name))
+        .map(|v| v.trim().to_string())
+}
+
+// "/nix/store/<hash>-firefox-128.0/bin/firefox" -> "/nix/store/<hash>-firefox-128.0"
+fn store_root(path: &Path) -> Option<PathBuf> {
+    let rest = path.strip_prefix("/nix/store").ok()?;
+    let first = rest.components().next()?;
+    Some(Path::new("/nix/store").join(first))
+}
+
+// The profile whose bin/ resolves to the same store path
+fn provenance(exe: &Path, root: Option<&Path>) -> Provenance {
+    let Some(root) = root else {
+        return if exe.as_os_str().is_empty() {
+            Provenance::Unknown
+        } else {
+            Provenance::OutsideStore
+        };
+    };
+    let Some(binary) = exe.file_name() else {
+        return Provenance::NixStore;
+    };
+    let same = |profile: PathBuf| {
+        fs::canonicalize(profile.join("bin").join(binary))
+            .ok()
+            .and_then(|p| store_root(&p))
+            .is_some_and(|r| r == root)
+    };
+    if same(PathBuf::from("/run/current-system/sw")) {
+        Provenance::SystemProfile
+    } else if same(system::home_dir().join(".nix-profile"))
+        || same(PathBuf::from(format!(
+            "/etc/profiles/per-user/{}",
+            system::username()
+        )))
+    {
+        Provenance::UserProfile
+    } else {
+        Provenance::NixStore
+    }
+}
+
+// "0::/user.slice/.../app.slice/foo.service" -> ("foo.service", user)
+fn systemd_unit(pid: u32) -> Option<(String, bool)> {
+    let cgroup = fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    let path = cgroup.lines().find_map(|l| l.strip_prefix("0::"))?;
+    let unit = path
+        .rsplit('/')
+        .find(|part| part.ends_with(".service") || part.ends_with(".scope"))?;
+    let user = path.contains("/user@");
+    unit.ends_with(".service").then(|| (unit.to_string(), user))
+}
+
+fn read_process(pid: u32) -> Option<ProcessInfo> {
+    let dir = PathBuf::from(format!("/proc/{}", pid));
+    let status = fs::read_to_string(dir.join("status")).ok()?;
+    let name = status_field(&status, "Name:")?;
+    let rss_kb: u64 = status_field(&status, "VmRSS:")
+        .and_then(|v| v.split_whitespace().next()?.parse().ok())
+        .unwrap_or(0);
+    // Kernel threads have no memory map of their own
+    if rss_kb == 0 {
+        return None;
+    }
+    let uid = status_field(&status, "Uid:")
+        .and_then(|v| v.split_whitespace().next()?.parse().ok())
+        .unwrap_or(0);
+    let command = fs::read(dir.join("cmdline"))
+        .map(|raw| {
+            String::from_utf8_lossy(&raw)
+                .split('\0')
+                .filter(|a| !a.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_default();
+    // Other users' processes hide their exe link; fall back to argv[0]
+    let exe = fs::read_link(dir.join("exe"))
+        .unwrap_or_else(|_| PathBuf::from(command.split_whitespace().next().unwrap_or("")));
+    let exe = fs::canonicalize(&exe).unwrap_or(exe);
+    let root = store_root(&exe);
+    let root_str = root.as_ref().map(|r| r.to_string_lossy().into_owned());
+    let (unit, user_unit) = match systemd_unit(pid) {
+        Some((unit, user)) => (Some(unit), user),
+        None => (None, false),
+    };
+    Some(ProcessInfo {
+        pid,
+        name,
+        command,
+        uid,
+        rss_mb: rss_kb / 1024,
+        package: root_str.as_deref().map(inventory::store_path_name),
+        version: root_str.as_deref().and_then(inventory::store_path_version),
+        provenance: provenance(&exe, root.as_deref()),
+        store_path: root_str,
+        unit,
+        user_unit,
+    })
+}
+
+pub fn list() -> anyhow::Result<ProcessReport> {
+    let mut processes: Vec<ProcessInfo> = fs::read_dir("/proc")?
+        .flatten()
+        .filter_map(|e| e.file_name().to_string_lossy().parse::<u32>().ok())
+        .filter_map(read_process)
+        .collect();
+    processes.sort_by_key(|p| std::cmp::Reverse(p.rss_mb));
+
+    let mut usage: BTreeMap<String, (usize, u64)> = BTreeMap::new();
+    for process in &processes {
+        let key = process
+            .package
+            .clone()
+            .unwrap_or_else(|| process.name.clone());
+        let entry = usage.entry(key).or_default();
+        entry.0 += 1;
+        entry.1 += process.rss_mb;
+    }
+    let mut by_package: Vec<PackageUsage> = usage
+        .into_iter()
+        .map(|(package, (processes, rss_mb))| PackageUsage {
+            package,
+            processes,
+            rss_mb,
+        })
+        .collect();
+    by_package.sort_by_key(|p| std::cmp::Reverse(p.rss_mb));
+
+    Ok(ProcessReport {
+        processes,
+        by_package,
+    })
+}
+
+fn current_uid() -> Option<u32> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    status_field(&status, "Uid:")?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+pub fn kill(pid: u32, force: bool) -> anyhow::Result<()> {
+    let process = read_process(pid).ok_or_else(|| anyhow!("Process {} is not running", pid))?;
+    if pid == std::process::id() {
+        bail!("That is this application");
+    }
+    let signal = if force { "-KILL" } else { "-TERM" };
+    let id = pid.to_string();
+    if Some(process.uid) == current_uid() {
+        system::run("kill", &[signal, &id])?;
+    } else {
+        system::run_privileged("kill", &[signal, &id])?;
+    }
+    Ok(())
+}
+
+// Restart through systemd; processes without a unit can't be restarted safely
+pub fn restart(pid: u32) -> anyhow::Result<String> {
+    let process = read_process(pid).ok_or_else(|| anyhow!("Process {} is not running", pid))?;
+    let Some(unit) = process.unit else {
+        bail!(
+            "{} is not managed by a systemd service, so it can only be closed and started again by hand",
+            process.name
+        );
+    };
+    if process.user_unit {
+        system::run("systemctl", &["--user", "restart", &unit])?;
+    } else {
+        system::run_privileged("systemctl", &["restart", &unit])?;
+    }
+    Ok(unit)
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn list_processes(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "List processes", |_| crate::respond(list())).await
+}
+
+#[tauri::command]
+pub async fn kill_process(pid: u32, force: bool, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Kill process", move |_| {
+        if let Some(refusal) = managed::guard("processes", &format!("Stop process {}", pid)) {
+            return refusal;
+        }
+        crate::respond(kill(pid, force))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn restart_process(pid: u32, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Restart process", move |_| {
+        if let Some(refusal) = managed::guard("processes", &format!("Restart process {}", pid)) {
+            return refusal;
+        }
+        crate::respond(restart(pid))
+    })
+    .await
+}
@@ -0,0 +1,301 @@
+// Multi-profile package management
+//
+// Install/remove/list can target the system profile (declaratively, through a
+// generated environment.systemPackages module), the per-user profile, or any
+// registered project profile (`nix profile --profile <path>`).
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+use crate::inventory::{self, InventoryItem};
+use crate::nixgen::{self, NixModule, NixOption, Target};
+use crate::{managed, retry, storage, system, tasks};
+
+const REGISTRY_FILE: &str = "profiles.json";
+const SYSTEM_PACKAGES_FILE: &str = "system-packages.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileKind {
+    System,
+    User,
+    Project,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    pub kind: ProfileKind,
+    pub path: PathBuf,
+}
+
+// Known profiles plus which one commands target by default
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileRegistry {
+    pub profiles: Vec<Profile>,
+    pub active: String,
+}
+
+impl Default for ProfileRegistry {
+    fn default() -> Self {
+        ProfileRegistry {
+            profiles: builtin_profiles(),
+            active: "user".to_string(),
+        }
+    }
+}
+
+fn builtin_profiles() -> Vec<Profile> {
+    vec![
+        Profile {
+            id: "system".to_string(),
+            name: "System".to_string(),
+            kind: ProfileKind::System,
+            path: PathBuf::from("/nix/var/nix/profiles/system"),
+        },
+        Profile {
+            id: "user".to_string(),
+            name: format!("{} (user)", system::username()),
+            kind: ProfileKind::User,
+            path: system::home_dir().join(".nix-profile"),
+        },
+    ]
+}
+
+impl ProfileRegistry {
+    // Built-in profiles are always present; project profiles come from disk
+    pub fn load() -> Self {
+        let mut registry: ProfileRegistry = storage::load(REGISTRY_FILE).unwrap_or_default();
+        for builtin in builtin_profiles().into_iter().rev() {
+            if !registry.profiles.iter().any(|p| p.id == builtin.id) {
+                registry.profiles.insert(0, builtin);
+            }
+        }
+        registry
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        storage::save(REGISTRY_FILE, self).map(|_| ())
+    }
+
+    // Resolve an explicit profile id, falling back to the active one
+    pub fn resolve(&self, id: Option<&str>) -> anyhow::Result<Profile> {
+        let id = id.unwrap_or(&self.active);
+        self.profiles
+            .iter()
+            .find(|p| p.id == id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Unknown profile '{}'", id))
+    }
+}
+
+fn load_system_packages() -> Vec<String> {
+    storage::load(SYSTEM_PACKAGES_FILE).unwrap_or_default()
+}
+
+fn write_system_packages(packages: &[String]) -> anyhow::Result<PathBuf> {
+    storage::save(SYSTEM_PACKAGES_FILE, &packages)?;
+    let mut module = NixModule::new(
+        "system-packages",
+        "packages installed through the assistant",
+        Target::Nixos,
+    );
+    module.set(NixOption::new(
+        "environment.systemPackages",
+        format!("with pkgs; {}", nixgen::list(packages)),
+    ));
+    module.write()
+}
+
+// Result of an install/remove, with what the user still has to do (if anything)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileChange {
+    pub profile: Profile,
+    pub package: String,
+    pub module_path: Option<PathBuf>,
+    pub next_step: Option<String>,
+    // Set when the install only went through after a retry strategy
+    #[serde(default)]
+    pub recovered_with: Option<String>,
+}
+
+pub fn install(profile: &Profile, package: &str) -> anyhow::Result<ProfileChange> {
+    if package.trim().is_empty() {
+        bail!("No package given");
+    }
+    let mut recovered_with = None;
+    let module_path = match profile.kind {
+        ProfileKind::System => {
+            let mut packages = load_system_packages();
+            if !packages.iter().any(|p| p == package) {
+                packages.push(package.to_string());
+                packages.sort();
+            }
+            Some(write_system_packages(&packages)?)
+        }
+        ProfileKind::User | ProfileKind::Project => {
+            let path = profile.path.to_string_lossy();
+            let installable = format!("nixpkgs#{}", package);
+            recovered_with = retry::run(
+                "nix",
+                &["profile", "install", "--profile", &path, &installable],
+                false,
+            )?
+            .succeeded_with;
+            None
+        }
+    };
+    Ok(ProfileChange {
+        next_step: module_path
+            .as_ref()
+            .map(|_| "Rebuild the system to install it for all users".to_string()),
+        profile: profile.clone(),
+        package: package.to_string(),
+        module_path,
+        recovered_with,
+    })
+}
+
+pub fn remove(profile: &Profile, package: &str) -> anyhow::Result<ProfileChange> {
+    let module_path = match profile.kind {
+        ProfileKind::System => {
+            let mut packages = load_system_packages();
+            let before = packages.len();
+            packages.retain(|p| p != package);
+            if packages.len() == before {
+                bail!(
+                    "{} was not installed through the assistant; remove it from configuration.nix",
+                    package
+                );
+            }
+            Some(write_system_packages(&packages)?)
+        }
+        ProfileKind::User | ProfileKind::Project => {
+            let path = profile.path.to_string_lossy();
+            system::run("nix", &["profile", "remove", "--profile", &path, package])?;
+            None
+        }
+    };
+    Ok(ProfileChange {
+        next_step: module_path
+            .as_ref()
+            .map(|_| "Rebuild the system to finish removing it".to_string()),
+        profile: profile.clone(),
+        package: package.to_string(),
+        module_path,
+        recovered_with: None,
+    })
+}
+
+pub fn list(profile: &Profile) -> anyhow::Result<Vec<InventoryItem>> {
+    match profile.kind {
+        ProfileKind::System => Ok(load_system_packages()
+            .into_iter()
+            .map(|name| InventoryItem {
+                id: name.clone(),
+                name,
+                source: inventory::Source::NixProfile,
+                version: None,
+                location: Some(profile.path.to_string_lossy().into_owned()),
+            })
+            .collect()),
+        ProfileKind::User | ProfileKind::Project => {
+            let path = profile.path.to_string_lossy();
+            let output = system::run("nix", &["profile", "list", "--profile", &path, "--json"])?;
+            Ok(inventory::parse_profile_list(&output))
+        }
+    }
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn list_profiles(app: AppHandle) -> ProfileRegistry {
+    tasks::blocking_value(&app, "List profiles", |state| {
+        state.profiles.blocking_lock().clone()
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn register_profile(name: String, path: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Register profile", move |state| {
+        if let Some(refusal) = managed::guard("configure", &format!("Add the {} profile", name)) {
+            return refusal;
+        }
+        let mut registry = state.profiles.blocking_lock();
+        let id = name
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect::<String>();
+        if registry.profiles.iter().any(|p| p.id == id) {
+            return serde_json::json!({"success": false, "error": format!("Profile '{}' already exists", id)});
+        }
+        let profile = Profile {
+            id,
+            name,
+            kind: ProfileKind::Project,
+            path: PathBuf::from(path),
+        };
+        registry.profiles.push(profile.clone());
+        crate::respond(registry.save().map(|_| profile))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn unregister_profile(id: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Unregister profile", move |state| {
+        if let Some(refusal) = managed::guard("configure", &format!("Remove the {} profile", id)) {
+            return refusal;
+        }
+        let mut registry = state.profiles.blocking_lock();
+        match registry.resolve(Some(&id)) {
+            Ok(profile) if profile.kind != ProfileKind::Project => serde_json::json!({
+                "success": false,
+                "error": "Built-in profiles cannot be removed",
+            }),
+            Ok(_) => {
+                registry.profiles.retain(|p| p.id != id);
+                if registry.active == id {
+                    registry.active = "user".to_string();
+                }
+                crate::respond(registry.save())
+            }
+            Err(e) => crate::respond::<()>(Err(e)),
+        }
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn set_active_profile(id: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set active profile", move |state| {
+        if let Some(refusal) = managed::guard("configure", &format!("Switch to the {} profile", id))
+        {
+            return refusal;
+        }
+        let mut registry = state.profiles.blocking_lock();
+        match registry.resolve(Some(&id)) {
+            Ok(profile) => {
+                registry.active = profile.id.clone();
+                crate::respond(registry.save().map(|_| profile))
+            }
+            Err(e) => crate::respond::<()>(Err(e)),
+        }
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn list_profile_packages(profile: Option<String>, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "List profile packages", move |state| {
+        let resolved = state.profiles.blocking_lock().resolve(profile.as_deref());
+        crate::respond(resolved.and_then(|p| list(&p)))
+    })
+    .await
+}
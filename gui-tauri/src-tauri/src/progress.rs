@@ -0,0 +1,446 @@
+// One typed event protocol for every long-running operation
+//
+// Updates, installs, plans, downloads, store optimisation and index refreshes
+// all report through a Reporter, which emits "operation" events tagged by
+// type: OperationStarted, any number of Progress, Step and LogLine, then
+// exactly one Completed or Failed. Every event carries the operation id, so a
+// frontend can follow several jobs at once and take each result from its
+// Completed event instead of waiting on the command that started it. Messages
+// are humanized in the active tone. get_operations lists what is still
+// running, for a window that opens halfway through, and cancel_operation
+// stops one. An operation reported from a background task shares the task's
+// cancellation flag, so cancelling it also kills the program it waits on.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Mutex;
+
+use crate::{clock, tasks, tone};
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+// The operations currently running, by id
+static RUNNING: Mutex<Option<HashMap<String, Running>>> = Mutex::const_new(None);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum OperationEvent {
+    OperationStarted {
+        id: String,
+        // What kind of operation this is: "update", "install", "plan", ...
+        operation: String,
+        message: String,
+        // False when there is no meaningful total; show a spinner, not a bar
+        determinate: bool,
+        total: Option<u64>,
+        cancellable: bool,
+    },
+    Progress {
+        id: String,
+        // 0-100, for operations with a total
+        pct: Option<f32>,
+        // The step being worked on
+        phase: String,
+        current: u64,
+        total: Option<u64>,
+        message: String,
+    },
+    // One step of an operation made of several, e.g. a plan
+    Step {
+        id: String,
+        index: usize,
+        status: StepStatus,
+        message: Option<String>,
+        // What the step produced once it has ended
+        result: Option<serde_json::Value>,
+    },
+    LogLine {
+        id: String,
+        line: String,
+    },
+    Completed {
+        id: String,
+        message: String,
+        // What the operation produced, when it has something to hand back
+        result: Option<serde_json::Value>,
+    },
+    Failed {
+        id: String,
+        error: String,
+        // Stopped on request rather than by an error
+        cancelled: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Skipped,
+    // Waiting for a maintenance window; the steps after it still run
+    Staged,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationInfo {
+    pub id: String,
+    pub operation: String,
+    pub message: String,
+    pub pct: Option<f32>,
+    pub phase: Option<String>,
+    pub cancellable: bool,
+    pub started_ms: u64,
+}
+
+struct Running {
+    cancelled: Arc<AtomicBool>,
+    info: OperationInfo,
+}
+
+pub struct Reporter {
+    id: String,
+    total: Option<u64>,
+    cancelled: Arc<AtomicBool>,
+    done: bool,
+}
+
+// Called once at startup; until then reporting is a no-op
+pub fn init(app: AppHandle) {
+    let _ = APP.set(app);
+}
+
+fn emit(event: OperationEvent) {
+    if let Some(app) = APP.get() {
+        let _ = app.emit("operation", event);
+    }
+}
+
+impl Reporter {
+    pub fn start(
+        operation: &str,
+        message: &str,
+        total: Option<u64>,
+        cancellable: bool,
+    ) -> Reporter {
+        let id = format!("{}-{}", operation, NEXT_ID.fetch_add(1, Ordering::Relaxed));
+        let cancelled = tasks::current().unwrap_or_default();
+        let message = tone::progress(tone::current(), message);
+        RUNNING
+            .blocking_lock()
+            .get_or_insert_with(HashMap::new)
+            .insert(
+                id.clone(),
+                Running {
+                    cancelled: cancelled.clone(),
+                    info: OperationInfo {
+                        id: id.clone(),
+                        operation: operation.to_string(),
+                        message: message.clone(),
+                        pct: total.map(|_| 0.0),
+                        phase: None,
+                        cancellable,
+                        started_ms: clock::now_ms(),
+                    },
+                },
+            );
+        emit(OperationEvent::OperationStarted {
+            id: id.clone(),
+            operation: operation.to_string(),
+            message,
+            determinate: total.is_some(),
+            total,
+            cancellable,
+        });
+        Reporter {
+            id,
+            total,
+            cancelled,
+            done: false,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    // `current` units of `total` done, now working on `phase`
+    pub fn advance(&mut self, current: u64, phase: &str) {
+        let pct = self
+            .total
+            .filter(|&total| total > 0)
+            .map(|total| (current as f32 / total as f32 * 100.0).min(100.0));
+        let message = tone::progress(tone::current(), phase);
+        if let Some(running) = RUNNING
+            .blocking_lock()
+            .as_mut()
+            .and_then(|r| r.get_mut(&self.id))
+        {
+            running.info.pct = pct;
+            running.info.phase = Some(phase.to_string());
+            running.info.message = message.clone();
+        }
+        emit(OperationEvent::Progress {
+            id: self.id.clone(),
+            pct,
+            phase: phase.to_string(),
+            current,
+            total: self.total,
+            message,
+        });
+    }
+
+    pub fn step(
+        &self,
+        index: usize,
+        status: StepStatus,
+        message: Option<String>,
+        result: Option<serde_json::Value>,
+    ) {
+        emit(OperationEvent::Step {
+            id: self.id.clone(),
+            index,
+            status,
+            message,
+            result,
+        });
+    }
+
+    // A line of output worth showing in the operation's log
+    pub fn log(&self, line: &str) {
+        emit(OperationEvent::LogLine {
+            id: self.id.clone(),
+            line: line.to_string(),
+        });
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn end(mut self, event: OperationEvent) {
+        self.done = true;
+        // Off the running list before anyone hears it ended
+        if let Some(running) = RUNNING.blocking_lock().as_mut() {
+            running.remove(&self.id);
+        }
+        emit(event);
+    }
+
+    pub fn succeed(self, message: &str) {
+        let event = OperationEvent::Completed {
+            id: self.id.clone(),
+            message: tone::success(tone::current(), message),
+            result: None,
+        };
+        self.end(event);
+    }
+
+    // Succeed, handing `result` to whoever follows the operation
+    pub fn complete<T: Serialize>(self, message: &str, result: &T) {
+        let event = OperationEvent::Completed {
+            id: self.id.clone(),
+            message: tone::success(tone::current(), message),
+            result: serde_json::to_value(result).ok(),
+        };
+        self.end(event);
+    }
+
+    pub fn fail(self, error: &str) {
+        let event = OperationEvent::Failed {
+            id: self.id.clone(),
+            error: tone::error(tone::current(), error),
+            cancelled: false,
+        };
+        self.end(event);
+    }
+
+    pub fn cancel(self, message: &str) {
+        let event = OperationEvent::Failed {
+            id: self.id.clone(),
+            error: message.to_string(),
+            cancelled: true,
+        };
+        self.end(event);
+    }
+
+    // Complete with the value or fail with the error of `result`
+    pub fn finish<T: Serialize>(self, result: &anyhow::Result<T>, message: &str) {
+        match result {
+            Ok(value) => self.complete(message, value),
+            Err(e) => self.fail(&e.to_string()),
+        }
+    }
+}
+
+impl Drop for Reporter {
+    fn drop(&mut self) {
+        if let Some(running) = RUNNING.blocking_lock().as_mut() {
+            running.remove(&self.id);
+        }
+        // Returned early or panicked without saying how it ended
+        if !self.done {
+            emit(OperationEvent::Failed {
+                id: self.id.clone(),
+                error: "Stopped unexpectedly".to_string(),
+                cancelled: false,
+            });
+        }
+    }
+}
+
+// Report an operation without steps from start to finish
+pub fn track<T: Serialize>(
+    operation: &str,
+    message: &str,
+    f: impl FnOnce() -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let reporter = Reporter::start(operation, message, None, false);
+    let result = f();
+    reporter.finish(&result, message);
+    result
+}
+
+pub fn running() -> Vec<OperationInfo> {
+    let mut operations: Vec<OperationInfo> = RUNNING
+        .blocking_lock()
+        .iter()
+        .flat_map(|r| r.values())
+        .map(|running| running.info.clone())
+        .collect();
+    operations.sort_by_key(|o| o.started_ms);
+    operations
+}
+
+// Ask a cancellable operation to stop at its next safe point
+pub fn cancel(id: &str) -> bool {
+    match RUNNING.blocking_lock().as_ref().and_then(|r| r.get(id)) {
+        Some(running) if running.info.cancellable => {
+            running.cancelled.store(true, Ordering::SeqCst);
+            true
+        }
+        _ => false,
+    }
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_operations(app: AppHandle) -> Vec<OperationInfo> {
+    tasks::blocking_value(&app, "Get operations", |_| running()).await
+}
+
+#[tauri::command]
+pub async fn cancel_operation(id: String, app: AppHandle) -> bool {
+    tasks::blocking_value(&app, "Cancel operation", move |_| cancel(&id)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tests share RUNNING, so each only looks at its own operations
+    fn info(id: &str) -> Option<OperationInfo> {
+        running().into_iter().find(|o| o.id == id)
+    }
+
+    #[test]
+    fn operations_are_listed_until_they_end() {
+        let mut reporter = Reporter::start("test", "Testing", Some(4), true);
+        let id = reporter.id().to_string();
+        assert!(id.starts_with("test-"));
+        let started = info(&id).unwrap();
+        assert_eq!(started.operation, "test");
+        assert_eq!(started.pct, Some(0.0));
+        assert!(started.cancellable);
+
+        reporter.advance(1, "first step");
+        let advanced = info(&id).unwrap();
+        assert_eq!(advanced.pct, Some(25.0));
+        assert_eq!(advanced.phase.as_deref(), Some("first step"));
+        // Going past the total doesn't go past 100%
+        reporter.advance(9, "overrun");
+        assert_eq!(info(&id).unwrap().pct, Some(100.0));
+
+        reporter.succeed("Tested");
+        assert!(info(&id).is_none());
+    }
+
+    #[test]
+    fn operations_without_a_total_have_no_percentage() {
+        let mut reporter = Reporter::start("test", "Testing", None, false);
+        let id = reporter.id().to_string();
+        assert_eq!(info(&id).unwrap().pct, None);
+        reporter.advance(3, "step");
+        assert_eq!(info(&id).unwrap().pct, None);
+        reporter.fail("Broke");
+
+        let mut empty = Reporter::start("test", "Testing", Some(0), false);
+        empty.advance(0, "step");
+        assert_eq!(info(empty.id()).unwrap().pct, None);
+        empty.cancel("Stopped");
+    }
+
+    #[test]
+    fn dropped_reporters_leave_the_list() {
+        let reporter = Reporter::start("test", "Testing", None, false);
+        let id = reporter.id().to_string();
+        let other = Reporter::start("test", "Testing", None, false);
+        assert_ne!(other.id(), id);
+        drop(reporter);
+        assert!(info(&id).is_none());
+        assert!(info(other.id()).is_some());
+    }
+
+    #[test]
+    fn only_cancellable_operations_can_be_cancelled() {
+        let cancellable = Reporter::start("test", "Testing", None, true);
+        let fixed = Reporter::start("test", "Testing", None, false);
+        assert!(cancel(cancellable.id()));
+        assert!(cancellable.is_cancelled());
+        assert!(!cancel(fixed.id()));
+        assert!(!fixed.is_cancelled());
+        assert!(!cancel("test-unknown"));
+        cancellable.cancel("Stopped");
+        fixed.succeed("Tested");
+    }
+
+    #[test]
+    fn cancelling_an_operation_cancels_the_task_it_runs_in() {
+        let flag = Arc::new(AtomicBool::new(false));
+        tasks::within(Some(flag.clone()), || {
+            let reporter = Reporter::start("test", "Testing", None, true);
+            assert!(!tasks::cancelled());
+            assert!(cancel(reporter.id()));
+            assert!(tasks::cancelled());
+            reporter.cancel("Stopped");
+        });
+        assert!(flag.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn step_events_name_their_status_in_snake_case() {
+        let event = OperationEvent::Step {
+            id: "plan-1".to_string(),
+            index: 2,
+            status: StepStatus::Staged,
+            message: None,
+            result: None,
+        };
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["type"], "Step");
+        assert_eq!(value["status"], "staged");
+    }
+
+    #[test]
+    fn track_hands_back_the_result() {
+        assert_eq!(track("tracked", "Testing", || Ok(7)).unwrap(), 7);
+        let failed = track::<()>("tracked", "Testing", || anyhow::bail!("Broke"));
+        assert_eq!(failed.unwrap_err().to_string(), "Broke");
+        assert!(running().iter().all(|o| o.operation != "tracked"));
+    }
+}
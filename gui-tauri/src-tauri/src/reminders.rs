@@ -0,0 +1,504 @@
+// Reminders bound to system events
+//
+// "Remind me to clean up generations after the next successful rebuild" or
+// "ping me when that build finishes" become reminders whose trigger is a
+// system event: a new system generation, a process exiting, the next boot or a
+// point in time. They live in reminders.json and a background watcher delivers
+// them as desktop notifications. Process reminders are also handed to a
+// transient user unit so they still fire after the window is closed.
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::{boot, bootcheck, clock, processes, storage, system, tasks};
+
+pub const REMINDERS_FILE: &str = "reminders.json";
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+// Delivered reminders are kept this long so the frontend can show them
+const KEEP_DELIVERED: u64 = 7 * 24 * 60 * 60;
+// Processes that count as "a build" when no pid is given
+const BUILD_COMMANDS: &[&str] = &[
+    "nixos-rebuild",
+    "nix build",
+    "nix-build",
+    "nix develop",
+    "nix flake check",
+    "home-manager switch",
+    "nix-env -i",
+    "nix profile install",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Trigger {
+    // A system generation newer than `after_generation` appears
+    NextRebuild { after_generation: u32 },
+    ProcessExit { pid: u32, name: String },
+    NextBoot { boot_id: String },
+    // Unix seconds
+    At { timestamp: u64 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reminder {
+    pub id: u64,
+    pub message: String,
+    pub trigger: Trigger,
+    pub created: u64,
+    pub delivered: Option<u64>,
+    // A user unit will post the notification itself, even without the app
+    pub detached: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ReminderStore {
+    next_id: u64,
+    reminders: Vec<Reminder>,
+}
+
+fn load() -> ReminderStore {
+    storage::load_data(REMINDERS_FILE).unwrap_or_default()
+}
+
+fn save(store: &ReminderStore) -> anyhow::Result<()> {
+    storage::save_data(REMINDERS_FILE, store).map(|_| ())
+}
+
+fn latest_generation() -> u32 {
+    boot::list_generations()
+        .last()
+        .map(|g| g.number)
+        .unwrap_or(0)
+}
+
+fn process_alive(pid: u32) -> bool {
+    Path::new(&format!("/proc/{}", pid)).exists()
+}
+
+impl Trigger {
+    pub fn describe(&self) -> String {
+        match self {
+            Trigger::NextRebuild { .. } => "after the next successful rebuild".to_string(),
+            Trigger::ProcessExit { name, .. } => format!("when {} finishes", name),
+            Trigger::NextBoot { .. } => "after the next reboot".to_string(),
+            Trigger::At { timestamp } => {
+                let minutes = timestamp.saturating_sub(clock::now()).div_ceil(60);
+                match minutes {
+                    0..=1 => "in a minute".to_string(),
+                    2..=119 => format!("in {} minutes", minutes),
+                    _ => format!("in {} hours", minutes.div_ceil(60)),
+                }
+            }
+        }
+    }
+
+    fn fired(&self) -> bool {
+        match self {
+            Trigger::NextRebuild { after_generation } => latest_generation() > *after_generation,
+            Trigger::ProcessExit { pid, .. } => !process_alive(*pid),
+            Trigger::NextBoot { boot_id } => bootcheck::boot_id() != *boot_id,
+            Trigger::At { timestamp } => clock::now() >= *timestamp,
+        }
+    }
+}
+
+// The running build the user most likely means by "that build"
+fn running_build() -> anyhow::Result<(u32, String)> {
+    let mut builds: Vec<processes::ProcessInfo> = processes::list()?
+        .processes
+        .into_iter()
+        .filter(|p| BUILD_COMMANDS.iter().any(|c| p.command.contains(c)))
+        .collect();
+    // The outermost command started first and has the lowest pid
+    builds.sort_by_key(|p| p.pid);
+    let build = builds
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("I don't see a build running right now"))?;
+    let name = build
+        .command
+        .split_whitespace()
+        .take(3)
+        .map(|part| part.rsplit('/').next().unwrap_or(part))
+        .collect::<Vec<_>>()
+        .join(" ");
+    Ok((build.pid, name))
+}
+
+// "when that 2-hour build finishes", "when it's done" as a byte range
+fn build_clause(message: &str) -> Option<(usize, usize)> {
+    let start = message.find("when ")?;
+    let tail = &message[start..];
+    let end = ["finishes", "is done", "'s done", "completes", "ends"]
+        .iter()
+        .filter_map(|word| tail.find(word).map(|at| at + word.len()))
+        .min()?;
+    Some((start, start + end))
+}
+
+// "in 20 minutes", "in an hour"
+fn parse_delay(words: &[&str]) -> Option<u64> {
+    let pos = words.iter().position(|w| *w == "in")?;
+    let count = match *words.get(pos + 1)? {
+        "a" | "an" | "one" => 1,
+        "two" => 2,
+        "half" => return (words.get(pos + 3) == Some(&"hour")).then_some(30 * 60),
+        number => number.parse().ok()?,
+    };
+    let unit = match words.get(pos + 2)?.trim_end_matches('s') {
+        "second" | "sec" => 1,
+        "minute" | "min" => 60,
+        "hour" => 60 * 60,
+        "day" => 24 * 60 * 60,
+        _ => return None,
+    };
+    Some(count * unit)
+}
+
+// Split a request into the reminder text and its trigger
+pub fn parse(text: &str) -> anyhow::Result<(String, Trigger)> {
+    let mut message = text.trim().trim_end_matches(['.', '!', '?']).to_lowercase();
+    for prefix in [
+        "remind me to",
+        "remind me",
+        "ping me",
+        "notify me",
+        "tell me",
+    ] {
+        if let Some(rest) = message.strip_prefix(prefix) {
+            message = rest.trim().to_string();
+            break;
+        }
+    }
+    // Process reminders survive closing the window anyway
+    for aside in [
+        "even if i close the window",
+        "even if i close the app",
+        "even if the app is closed",
+    ] {
+        message = message.replace(aside, "");
+    }
+
+    let trigger_phrases: &[(&str, &str)] = &[
+        ("after the next successful rebuild", "rebuild"),
+        ("after the next rebuild", "rebuild"),
+        ("after my next rebuild", "rebuild"),
+        ("next time i rebuild", "rebuild"),
+        ("after the next update", "rebuild"),
+        ("after the rebuild", "rebuild"),
+        ("after the next reboot", "boot"),
+        ("after i reboot", "boot"),
+        ("next time i boot", "boot"),
+        ("after reboot", "boot"),
+    ];
+    let found = trigger_phrases
+        .iter()
+        .find_map(|(phrase, kind)| {
+            message
+                .find(phrase)
+                .map(|at| (at, at + phrase.len(), *kind))
+        })
+        .or_else(|| build_clause(&message).map(|(start, end)| (start, end, "build")));
+
+    let (text_part, trigger) = match found {
+        Some((start, end, kind)) => {
+            let rest = format!("{} {}", &message[..start], &message[end..]);
+            let trigger = match kind {
+                "rebuild" => Trigger::NextRebuild {
+                    after_generation: latest_generation(),
+                },
+                "boot" => Trigger::NextBoot {
+                    boot_id: bootcheck::boot_id(),
+                },
+                _ => {
+                    let (pid, name) = running_build()?;
+                    Trigger::ProcessExit { pid, name }
+                }
+            };
+            (rest, trigger)
+        }
+        None => {
+            let words: Vec<&str> = message.split_whitespace().collect();
+            let Some(delay) = parse_delay(&words) else {
+                bail!("I couldn't tell when to remind you; try \"after the next rebuild\", \"when the build finishes\" or \"in 20 minutes\"");
+            };
+            let pos = words.iter().position(|w| *w == "in").unwrap_or(words.len());
+            let span = if words.get(pos + 1) == Some(&"half") {
+                4
+            } else {
+                3
+            };
+            let end = (pos + span).min(words.len());
+            let rest = [&words[..pos], &words[end..]].concat().join(" ");
+            (
+                rest,
+                Trigger::At {
+                    timestamp: clock::now() + delay,
+                },
+            )
+        }
+    };
+
+    let mut text_part = text_part.split_whitespace().collect::<Vec<_>>().join(" ");
+    for lead in ["to ", "that ", "about "] {
+        if let Some(rest) = text_part.strip_prefix(lead) {
+            text_part = rest.to_string();
+        }
+    }
+    let message = if text_part.is_empty() {
+        match &trigger {
+            Trigger::ProcessExit { name, .. } => format!("{} has finished", name),
+            Trigger::NextRebuild { .. } => "The rebuild finished".to_string(),
+            Trigger::NextBoot { .. } => "You asked to be reminded after this reboot".to_string(),
+            Trigger::At { .. } => "Time's up".to_string(),
+        }
+    } else {
+        let mut chars = text_part.chars();
+        chars
+            .next()
+            .map(|c| c.to_uppercase().collect::<String>() + chars.as_str())
+            .unwrap_or_default()
+    };
+    Ok((message, trigger))
+}
+
+fn unit_name(id: u64) -> String {
+    format!("luminous-reminder-{}", id)
+}
+
+// Wait for the process in a transient user unit so closing the app doesn't lose it
+fn detach(id: u64, pid: u32, message: &str) -> bool {
+    let pid = pid.to_string();
+    system::run(
+        "systemd-run",
+        &[
+            "--user",
+            "--collect",
+            "--unit",
+            &unit_name(id),
+            "sh",
+            "-c",
+            "tail --pid=\"$1\" -f /dev/null; notify-send 'Luminous Nix' \"$2\"",
+            "sh",
+            &pid,
+            message,
+        ],
+    )
+    .is_ok()
+}
+
+pub fn list() -> Vec<Reminder> {
+    load().reminders
+}
+
+pub fn add(message: &str, trigger: Trigger) -> anyhow::Result<Reminder> {
+    if message.trim().is_empty() {
+        bail!("The reminder needs some text");
+    }
+    if let Trigger::ProcessExit { pid, name } = &trigger {
+        if !process_alive(*pid) {
+            bail!("{} has already finished", name);
+        }
+    }
+    let mut store = load();
+    store.next_id += 1;
+    let id = store.next_id;
+    // A test scenario's reminders stay in its sandbox rather than in systemd
+    let detached = match &trigger {
+        Trigger::ProcessExit { pid, .. } if storage::root().is_none() => detach(id, *pid, message),
+        _ => false,
+    };
+    let reminder = Reminder {
+        id,
+        message: message.trim().to_string(),
+        trigger,
+        created: clock::now(),
+        delivered: None,
+        detached,
+    };
+    store.reminders.push(reminder.clone());
+    save(&store)?;
+    Ok(reminder)
+}
+
+pub fn add_from_text(text: &str) -> anyhow::Result<Reminder> {
+    let (message, trigger) = parse(text)?;
+    add(&message, trigger)
+}
+
+pub fn cancel(id: u64) -> anyhow::Result<()> {
+    let mut store = load();
+    let reminder = store
+        .reminders
+        .iter()
+        .find(|r| r.id == id)
+        .ok_or_else(|| anyhow!("No reminder {}", id))?;
+    if reminder.detached && reminder.delivered.is_none() {
+        let _ = system::run(
+            "systemctl",
+            &["--user", "stop", &format!("{}.service", unit_name(id))],
+        );
+    }
+    store.reminders.retain(|r| r.id != id);
+    save(&store)
+}
+
+// Whether the text reads as a reminder request rather than a package action
+pub fn is_request(text: &str) -> bool {
+    let text = text.trim().to_lowercase();
+    ["remind me", "ping me", "notify me"]
+        .iter()
+        .any(|p| text.starts_with(p))
+}
+
+fn deliver(app: &AppHandle, reminder: &Reminder) {
+    // A detached unit has already posted the notification
+    if !reminder.detached {
+        let _ = app
+            .notification()
+            .builder()
+            .title("Luminous Nix")
+            .body(&reminder.message)
+            .show();
+    }
+    let _ = app.emit("reminder", reminder);
+}
+
+fn check(app: &AppHandle) -> anyhow::Result<()> {
+    let mut store = load();
+    let mut changed = false;
+    for reminder in store.reminders.iter_mut() {
+        if reminder.delivered.is_none() && reminder.trigger.fired() {
+            deliver(app, reminder);
+            reminder.delivered = Some(clock::now());
+            changed = true;
+        }
+    }
+    let cutoff = clock::now().saturating_sub(KEEP_DELIVERED);
+    let before = store.reminders.len();
+    store
+        .reminders
+        .retain(|r| r.delivered.is_none_or(|at| at >= cutoff));
+    if changed || store.reminders.len() != before {
+        save(&store)?;
+    }
+    Ok(())
+}
+
+// Check pending reminders for as long as the app runs, including any whose
+// event happened while it was closed
+pub fn start_watcher(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let _ = check(&app);
+        std::thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn list_reminders(app: AppHandle) -> Vec<Reminder> {
+    tasks::blocking_value(&app, "List reminders", |_| list()).await
+}
+
+// Either free text ("remind me to ... after the next rebuild") or an explicit trigger
+#[tauri::command]
+pub async fn add_reminder(
+    text: Option<String>,
+    message: Option<String>,
+    trigger: Option<Trigger>,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Add reminder", move |_| {
+        let result = match (text, message, trigger) {
+            (_, Some(message), Some(trigger)) => add(&message, trigger),
+            (Some(text), _, _) => add_from_text(&text),
+            _ => Err(anyhow!(
+                "Give either the reminder text or a message and trigger"
+            )),
+        };
+        match result {
+            Ok(reminder) => serde_json::json!({
+                "success": true,
+                "data": reminder,
+                "when": reminder.trigger.describe(),
+            }),
+            Err(e) => serde_json::json!({"success": false, "error": e.to_string()}),
+        }
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn cancel_reminder(id: u64, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Cancel reminder", move |_| crate::respond(cancel(id))).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str) -> Vec<&str> {
+        text.split_whitespace().collect()
+    }
+
+    #[test]
+    fn delays_are_read_in_seconds() {
+        assert_eq!(parse_delay(&words("in 20 minutes")), Some(20 * 60));
+        assert_eq!(parse_delay(&words("check in an hour")), Some(60 * 60));
+        assert_eq!(parse_delay(&words("in half an hour")), Some(30 * 60));
+        assert_eq!(parse_delay(&words("in two days")), Some(2 * 24 * 60 * 60));
+        assert_eq!(parse_delay(&words("in the kitchen")), None);
+    }
+
+    #[test]
+    fn build_clauses_end_at_the_first_finishing_word() {
+        let message = "check the logs when that build finishes please";
+        let (start, end) = build_clause(message).unwrap();
+        assert_eq!(&message[start..end], "when that build finishes");
+        assert_eq!(build_clause("check the logs"), None);
+    }
+
+    #[test]
+    fn timed_requests_keep_the_text_around_the_delay() {
+        let before = clock::now();
+        let (message, trigger) = parse("Remind me to stretch in 20 minutes!").unwrap();
+        assert_eq!(message, "Stretch");
+        let Trigger::At { timestamp } = trigger else {
+            panic!("expected a timer, got {:?}", trigger);
+        };
+        assert!(timestamp >= before + 20 * 60 && timestamp <= clock::now() + 20 * 60);
+
+        let (message, _) = parse("ping me in half an hour").unwrap();
+        assert_eq!(message, "Time's up");
+    }
+
+    #[test]
+    fn requests_without_a_trigger_are_refused() {
+        assert!(parse("remind me to stretch").is_err());
+        assert!(is_request("Remind me to stretch"));
+        assert!(!is_request("install firefox"));
+    }
+
+    #[test]
+    fn reminders_are_stored_until_cancelled() {
+        storage::with_root(&storage::scratch_dir("reminders"), || {
+            assert!(add(" ", Trigger::At { timestamp: 0 }).is_err());
+            let first = add("Stretch", Trigger::At { timestamp: 0 }).unwrap();
+            let second = add_from_text("remind me to drink water in 5 minutes").unwrap();
+            assert_ne!(first.id, second.id);
+            assert!(!first.detached);
+            assert_eq!(list().len(), 2);
+
+            cancel(first.id).unwrap();
+            assert!(cancel(first.id).is_err());
+            let left = list();
+            assert_eq!(left.len(), 1);
+            assert_eq!(left[0].message, "Drink water");
+        });
+    }
+}
@@ -0,0 +1,311 @@
+// Remote unlock of encrypted machines over SSH from the initrd
+//
+// Validates that the network comes up early enough to be reachable, generates
+// a dedicated initrd host key, writes the boot.initrd.network module and hands
+// the user a test plan to follow while they still have console access.
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::nixgen::{self, NixModule, NixOption, Target};
+use crate::sandbox::Simulation;
+use crate::{encryption, secrets, system, tasks};
+
+const DEFAULT_PORT: u16 = 2222;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkCheck {
+    pub interface: Option<String>,
+    pub driver: Option<String>,
+    pub address: Option<String>,
+    pub gateway: Option<String>,
+    pub dhcp: bool,
+    pub issues: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnlockPlan {
+    pub port: u16,
+    pub authorized_keys: Vec<String>,
+    pub network: NetworkCheck,
+    pub warnings: Vec<String>,
+    pub module_preview: String,
+    // Evaluation of the would-be configuration; nothing is written until it passes
+    #[serde(default)]
+    pub simulation: Simulation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnlockSetup {
+    pub module_path: String,
+    pub host_key: String,
+    pub host_key_fingerprint: Option<String>,
+    pub test_plan: Vec<String>,
+}
+
+fn default_route() -> Option<(String, String)> {
+    let output = system::run("ip", &["-json", "route", "show", "default"]).ok()?;
+    let routes: serde_json::Value = serde_json::from_str(&output).ok()?;
+    let route = routes.get(0)?;
+    Some((
+        route.get("dev")?.as_str()?.to_string(),
+        route.get("gateway")?.as_str()?.to_string(),
+    ))
+}
+
+// (address/prefix, assigned by DHCP)
+fn ipv4_address(interface: &str) -> Option<(String, bool)> {
+    let output = system::run("ip", &["-json", "-4", "addr", "show", "dev", interface]).ok()?;
+    let parsed: serde_json::Value = serde_json::from_str(&output).ok()?;
+    let info = parsed.get(0)?.get("addr_info")?.get(0)?;
+    let address = format!(
+        "{}/{}",
+        info.get("local")?.as_str()?,
+        info.get("prefixlen")?.as_u64()?
+    );
+    let dynamic = info
+        .get("dynamic")
+        .and_then(|d| d.as_bool())
+        .unwrap_or(false);
+    Some((address, dynamic))
+}
+
+fn nic_driver(interface: &str) -> Option<String> {
+    let link = fs::read_link(format!("/sys/class/net/{}/device/driver", interface)).ok()?;
+    link.file_name().map(|n| n.to_string_lossy().into_owned())
+}
+
+pub fn check_network() -> NetworkCheck {
+    let mut issues = Vec::new();
+    let route = default_route();
+    if route.is_none() {
+        issues.push(
+            "No default route; the initrd would not be reachable from other networks".to_string(),
+        );
+    }
+    let interface = route.as_ref().map(|(dev, _)| dev.clone());
+    let driver = interface.as_deref().and_then(nic_driver);
+    if interface.is_some() && driver.is_none() {
+        issues.push(
+            "Could not find the network driver, so it cannot be added to the initrd".to_string(),
+        );
+    }
+    if interface.as_deref().is_some_and(|i| i.starts_with("wl")) {
+        issues.push(
+            "The default route uses WiFi, which the initrd cannot bring up; use a wired connection"
+                .to_string(),
+        );
+    }
+    let address = interface.as_deref().and_then(ipv4_address);
+    if interface.is_some() && address.is_none() {
+        issues.push("The interface has no IPv4 address".to_string());
+    }
+    NetworkCheck {
+        dhcp: address.as_ref().is_some_and(|(_, dynamic)| *dynamic),
+        address: address.map(|(a, _)| a),
+        gateway: route.map(|(_, gw)| gw),
+        interface,
+        driver,
+        issues,
+    }
+}
+
+fn valid_public_key(key: &str) -> bool {
+    let mut parts = key.split_whitespace();
+    let kind = parts.next().unwrap_or("");
+    let body = parts.next().unwrap_or("");
+    (kind.starts_with("ssh-") || kind.starts_with("ecdsa-") || kind.starts_with("sk-"))
+        && body.len() > 40
+        && body
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+/=".contains(c))
+}
+
+// Kernel ip= parameter for a static address: ip=<addr>::<gw>:<mask>::<iface>:off
+fn static_ip_param(network: &NetworkCheck) -> Option<String> {
+    let (address, prefix) = network.address.as_deref()?.split_once('/')?;
+    let prefix: u32 = prefix.parse().ok()?;
+    let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+    let mask = std::net::Ipv4Addr::from(mask);
+    Some(format!(
+        "ip={}::{}:{}::{}:off",
+        address,
+        network.gateway.as_deref().unwrap_or(""),
+        mask,
+        network.interface.as_deref()?
+    ))
+}
+
+fn build_module(plan: &UnlockPlan) -> NixModule {
+    let mut module = NixModule::new("remote-unlock", "SSH unlock from the initrd", Target::Nixos);
+    module.set(NixOption::new(
+        "boot.initrd.network.enable",
+        nixgen::bool(true),
+    ));
+    if let Some(driver) = &plan.network.driver {
+        module.set(
+            NixOption::new(
+                "boot.initrd.availableKernelModules",
+                nixgen::string_list(&[driver.as_str()]),
+            )
+            .with_comment("Network driver, so the interface exists before the root is unlocked"),
+        );
+    }
+    if !plan.network.dhcp {
+        if let Some(param) = static_ip_param(&plan.network) {
+            module.set(NixOption::new(
+                "boot.kernelParams",
+                nixgen::string_list(&[param.as_str()]),
+            ));
+        }
+    }
+    module.set(NixOption::new(
+        "boot.initrd.network.ssh.enable",
+        nixgen::bool(true),
+    ));
+    module.set(
+        NixOption::new("boot.initrd.network.ssh.port", plan.port.to_string())
+            .with_comment("Separate port so clients don't mix up the initrd and system host keys"),
+    );
+    let keys: Vec<&str> = plan.authorized_keys.iter().map(String::as_str).collect();
+    module.set(NixOption::new(
+        "boot.initrd.network.ssh.authorizedKeys",
+        nixgen::string_list(&keys),
+    ));
+    module.set(NixOption::new(
+        "boot.initrd.network.ssh.hostKeys",
+        nixgen::string_list(&[secrets::INITRD_HOST_KEY]),
+    ));
+    module.set(
+        NixOption::new(
+            "boot.initrd.network.postCommands",
+            nixgen::string("echo 'cryptsetup-askpass' >> /root/.profile"),
+        )
+        .with_comment("Drop straight into the passphrase prompt after logging in"),
+    );
+    module
+}
+
+pub fn plan(authorized_keys: Vec<String>, port: Option<u16>) -> anyhow::Result<UnlockPlan> {
+    let encrypted = encryption::status(false)?.encrypted;
+    if encrypted.is_empty() {
+        bail!("No LUKS volumes found; remote unlock only makes sense for encrypted disks");
+    }
+    let authorized_keys: Vec<String> = authorized_keys
+        .into_iter()
+        .map(|k| k.trim().to_string())
+        .filter(|k| !k.is_empty())
+        .collect();
+    if authorized_keys.is_empty() {
+        bail!("Add at least one SSH public key that may unlock the machine");
+    }
+    if let Some(bad) = authorized_keys.iter().find(|k| !valid_public_key(k)) {
+        bail!(
+            "Not a valid SSH public key: {}",
+            bad.chars().take(40).collect::<String>()
+        );
+    }
+
+    let port = port.unwrap_or(DEFAULT_PORT);
+    let mut warnings = Vec::new();
+    if port == 22 {
+        warnings.push(
+            "Port 22 clashes with the regular SSH server's host key in known_hosts; 2222 avoids that"
+                .to_string(),
+        );
+    }
+    warnings.push(format!(
+        "The NixOS firewall is not active in the initrd; allow TCP {} on any router or external firewall",
+        port
+    ));
+    warnings.push(
+        "The initrd host key is stored unencrypted on /boot; it only protects the unlock session"
+            .to_string(),
+    );
+
+    let network = check_network();
+    let mut plan = UnlockPlan {
+        port,
+        authorized_keys,
+        network,
+        warnings,
+        module_preview: String::new(),
+        simulation: Simulation::default(),
+    };
+    let module = build_module(&plan);
+    plan.module_preview = module.render();
+    plan.simulation = module.simulate();
+    Ok(plan)
+}
+
+fn test_plan(plan: &UnlockPlan, fingerprint: Option<&str>) -> Vec<String> {
+    let address = plan
+        .network
+        .address
+        .as_deref()
+        .and_then(|a| a.split('/').next())
+        .unwrap_or("<server-ip>");
+    let mut steps = vec![
+        "Import the module and run `nixos-rebuild boot` (not switch) so the change applies on the next boot".to_string(),
+        "Keep console or physical access available for the first test".to_string(),
+        "Reboot and wait at the passphrase prompt".to_string(),
+        format!("From another machine run: ssh -p {} root@{}", plan.port, address),
+    ];
+    if let Some(fingerprint) = fingerprint {
+        steps.push(format!(
+            "Check the host key fingerprint matches: {}",
+            fingerprint
+        ));
+    }
+    steps.extend([
+        "Enter the disk passphrase; the session closes and boot continues".to_string(),
+        "Once booted, confirm the regular SSH server still works".to_string(),
+        "Reboot once more and unlock remotely without touching the console before relying on it"
+            .to_string(),
+    ]);
+    steps
+}
+
+pub fn apply(plan: &UnlockPlan) -> anyhow::Result<UnlockSetup> {
+    if !plan.network.issues.is_empty() {
+        bail!(
+            "Fix the network issues first: {}",
+            plan.network.issues.join("; ")
+        );
+    }
+    let key = secrets::generate_initrd_host_key()?;
+    let fingerprint =
+        secrets::public_key_fingerprint(Path::new(&format!("{}.pub", key.location.display()))).ok();
+    let module_path = build_module(plan).write()?;
+    Ok(UnlockSetup {
+        module_path: module_path.to_string_lossy().into_owned(),
+        host_key: key.location.to_string_lossy().into_owned(),
+        test_plan: test_plan(plan, fingerprint.as_deref()),
+        host_key_fingerprint: fingerprint,
+    })
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn plan_remote_unlock(
+    authorized_keys: Vec<String>,
+    port: Option<u16>,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Plan remote unlock", move |_| {
+        crate::respond(plan(authorized_keys, port))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn apply_remote_unlock(plan: UnlockPlan, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Apply remote unlock", move |_| {
+        crate::respond(apply(&plan))
+    })
+    .await
+}
@@ -0,0 +1,216 @@
+// Retry transient Nix failures with alternative strategies
+//
+// A network blip, an unreachable binary cache or a corrupted download
+// shouldn't end an update. Each failure is classified, and if it is transient
+// the command is run again with the next strategy that suits it: the same
+// command after a pause, a binary cache mirror, building from source, or
+// fewer parallel builds. The report says which strategy finally worked.
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::system;
+
+// Mirrors of cache.nixos.org signed with the same key, so no extra trust is needed
+const MIRRORS: &[&str] = &[
+    "https://mirrors.ustc.edu.cn/nix-channels/store",
+    "https://mirror.sjtu.edu.cn/nix-channels/store",
+];
+
+const MAX_ATTEMPTS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    Network,
+    SubstituterDown,
+    HashMismatch,
+    ResourceExhausted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Strategy {
+    Initial,
+    Retry,
+    Mirror(&'static str),
+    Fallback,
+    Refresh,
+    LowerParallelism,
+}
+
+impl Strategy {
+    fn id(self) -> String {
+        match self {
+            Strategy::Initial => "initial".to_string(),
+            Strategy::Retry => "retry".to_string(),
+            Strategy::Mirror(url) => format!("mirror:{}", url),
+            Strategy::Fallback => "build-from-source".to_string(),
+            Strategy::Refresh => "refresh-downloads".to_string(),
+            Strategy::LowerParallelism => "fewer-parallel-builds".to_string(),
+        }
+    }
+
+    fn description(self) -> String {
+        match self {
+            Strategy::Initial => "Ran the command as given".to_string(),
+            Strategy::Retry => "Tried again after a short pause".to_string(),
+            Strategy::Mirror(url) => format!("Downloaded from the mirror {}", url),
+            Strategy::Fallback => "Built from source what couldn't be downloaded".to_string(),
+            Strategy::Refresh => "Discarded cached downloads and fetched them again".to_string(),
+            Strategy::LowerParallelism => "Ran one build at a time to use less memory".to_string(),
+        }
+    }
+
+    fn args(self) -> Vec<&'static str> {
+        match self {
+            Strategy::Initial | Strategy::Retry => vec![],
+            Strategy::Mirror(url) => vec!["--option", "substituters", url],
+            Strategy::Fallback => vec!["--fallback"],
+            Strategy::Refresh => vec!["--refresh"],
+            Strategy::LowerParallelism => vec!["--max-jobs", "1"],
+        }
+    }
+}
+
+// Strategies worth trying for a failure, in order
+fn strategies(kind: FailureKind) -> Vec<Strategy> {
+    let mirrors = MIRRORS.iter().copied().map(Strategy::Mirror);
+    match kind {
+        FailureKind::Network => std::iter::once(Strategy::Retry)
+            .chain(mirrors)
+            .chain([Strategy::Fallback])
+            .collect(),
+        FailureKind::SubstituterDown => mirrors.chain([Strategy::Fallback]).collect(),
+        FailureKind::HashMismatch => std::iter::once(Strategy::Refresh)
+            .chain(mirrors)
+            .chain([Strategy::Fallback])
+            .collect(),
+        FailureKind::ResourceExhausted => vec![Strategy::LowerParallelism],
+    }
+}
+
+// Only failures another attempt could fix; anything else is reported as-is
+pub fn classify(error: &str) -> Option<FailureKind> {
+    let error = error.to_lowercase();
+    let any = |needles: &[&str]| needles.iter().any(|n| error.contains(n));
+    if any(&[
+        "hash mismatch",
+        "nar hash",
+        "does not have a valid signature",
+    ]) {
+        Some(FailureKind::HashMismatch)
+    } else if any(&[
+        "cannot connect to substituter",
+        "disabling binary cache",
+        "cache.nixos.org",
+    ]) && any(&[
+        "unable to download",
+        "http error",
+        "disabling",
+        "cannot connect",
+    ]) {
+        Some(FailureKind::SubstituterDown)
+    } else if any(&[
+        "could not resolve host",
+        "couldn't resolve host",
+        "connection timed out",
+        "connection reset",
+        "timeout was reached",
+        "unable to download",
+        "network is unreachable",
+        "temporary failure in name resolution",
+    ]) {
+        Some(FailureKind::Network)
+    } else if any(&[
+        "out of memory",
+        "cannot allocate memory",
+        "killed by signal 9",
+        "signal: killed",
+    ]) {
+        Some(FailureKind::ResourceExhausted)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attempt {
+    pub strategy: String,
+    pub description: String,
+    pub command: String,
+    pub failure: Option<FailureKind>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetryReport {
+    pub output: String,
+    pub attempts: Vec<Attempt>,
+    // Set when the command only succeeded after a retry
+    pub succeeded_with: Option<String>,
+}
+
+// Run a Nix command, retrying transient failures; `privileged` goes through pkexec
+pub fn run(program: &str, args: &[&str], privileged: bool) -> anyhow::Result<RetryReport> {
+    let mut report = RetryReport::default();
+    let mut tried: Vec<Strategy> = Vec::new();
+    let mut strategy = Strategy::Initial;
+    loop {
+        let mut full: Vec<&str> = args.to_vec();
+        full.extend(strategy.args());
+        if strategy == Strategy::Retry {
+            std::thread::sleep(Duration::from_secs(5));
+        }
+        let result = if privileged {
+            system::run_privileged(program, &full)
+        } else {
+            system::run(program, &full)
+        };
+        let command = format!("{} {}", program, full.join(" "));
+        match result {
+            Ok(output) => {
+                if !report.attempts.is_empty() {
+                    report.succeeded_with = Some(strategy.description());
+                }
+                report.attempts.push(Attempt {
+                    strategy: strategy.id(),
+                    description: strategy.description(),
+                    command,
+                    failure: None,
+                    error: None,
+                });
+                report.output = output;
+                return Ok(report);
+            }
+            Err(e) => {
+                let message = e.to_string();
+                let failure = classify(&message);
+                report.attempts.push(Attempt {
+                    strategy: strategy.id(),
+                    description: strategy.description(),
+                    command,
+                    failure,
+                    error: Some(message.clone()),
+                });
+                tried.push(strategy);
+                let next = failure
+                    .and_then(|kind| strategies(kind).into_iter().find(|s| !tried.contains(s)));
+                match next {
+                    Some(next) if report.attempts.len() < MAX_ATTEMPTS => strategy = next,
+                    _ if report.attempts.len() == 1 => return Err(e),
+                    _ => {
+                        let also: Vec<String> =
+                            tried.iter().skip(1).map(|s| s.description()).collect();
+                        return Err(anyhow!(
+                            "{} (retried {} time(s): {})",
+                            message,
+                            also.len(),
+                            also.join("; ")
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
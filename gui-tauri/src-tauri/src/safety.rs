@@ -0,0 +1,560 @@
+// Blast-radius classification and confirmation policies
+//
+// Every intent is classified as read-only, reversible or destructive. Classes
+// that need confirmation only run with a single-use token the backend issued
+// for exactly those intents, so a lone call can never delete generations.
+// Each confirmation carries a structured risk summary (reversibility,
+// downtime, disk and network use) plus one sentence built from it, so every
+// frontend and screen reader presents the same facts. Requests that came by
+// voice are held to more: a misheard word can't be taken back like a typo, so
+// removing and rolling back count as destructive too, and the classes the
+// policy names also get a phrase to say back before they run.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+use crate::nlp::Intent;
+use crate::{i18n, managed, storage, tasks};
+
+pub const POLICY_FILE: &str = "safety-policy.json";
+const TOKEN_LIFETIME: Duration = Duration::from_secs(5 * 60);
+const RANDOM_SOURCE: &str = "/dev/urandom";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlastRadius {
+    ReadOnly,
+    Reversible,
+    Destructive,
+}
+
+pub fn classify(intent: &Intent) -> BlastRadius {
+    match intent {
+        Intent::Search { .. }
+        | Intent::ListInstalled
+        | Intent::Explain { .. }
+        | Intent::Unknown => BlastRadius::ReadOnly,
+        // Each of these creates a new generation or file that can be undone
+        Intent::Install { .. }
+        | Intent::Remove { .. }
+        | Intent::Update
+        | Intent::Rollback { .. }
+        | Intent::Configure { .. }
+        | Intent::SetDefaultApp { .. }
+        | Intent::ScaffoldProject { .. } => BlastRadius::Reversible,
+        // The old default or the base configuration can be picked again
+        Intent::SetBootDefault { .. } | Intent::SwitchSpecialisation { .. } => {
+            BlastRadius::Reversible
+        }
+        // Plugins can't tell us what their actions touch, so assume they change state
+        Intent::Plugin { .. } => BlastRadius::Reversible,
+        // Deleted generations cannot be rolled back to
+        Intent::GarbageCollect | Intent::DeleteGenerations { .. } => BlastRadius::Destructive,
+    }
+}
+
+// The class of a spoken request, where taking something away is destructive
+pub fn classify_spoken(intent: &Intent) -> BlastRadius {
+    match intent {
+        Intent::Remove { .. } | Intent::Rollback { .. } => BlastRadius::Destructive,
+        _ => classify(intent),
+    }
+}
+
+// What to say to confirm `intents`: "yes, remove firefox and vlc"
+pub fn spoken_phrase(intents: &[Intent]) -> String {
+    let parts: Vec<String> = intents
+        .iter()
+        .map(|intent| match intent {
+            Intent::Remove { packages } => format!("remove {}", packages.join(" and ")),
+            Intent::Rollback { generation: None } => "roll back".to_string(),
+            Intent::Rollback {
+                generation: Some(generation),
+            } => format!("roll back to {}", generation),
+            Intent::GarbageCollect => "delete old generations".to_string(),
+            Intent::DeleteGenerations { generations } => {
+                let numbers: Vec<String> = generations.iter().map(u32::to_string).collect();
+                format!("delete generations {}", numbers.join(" and "))
+            }
+            other => other.describe().to_lowercase(),
+        })
+        .collect();
+    format!("yes, {}", parts.join(" and "))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Downtime {
+    #[default]
+    None,
+    // Changed services restart while switching
+    ServiceRestart,
+    Reboot,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkUse {
+    #[default]
+    None,
+    Light,
+    // Downloads packages or whole system closures
+    Heavy,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiskChange {
+    #[default]
+    Unchanged,
+    Grows,
+    Shrinks,
+    // Some steps grow it, others shrink it
+    Mixed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskSummary {
+    pub blast_radius: BlastRadius,
+    pub reversible: bool,
+    pub downtime: Downtime,
+    pub disk: DiskChange,
+    // Estimated change in MiB when it can be known up front
+    pub disk_delta_mb: Option<i64>,
+    pub network: NetworkUse,
+    // The same facts as one sentence, for TTS and plain-text frontends
+    pub spoken: String,
+}
+
+impl Default for RiskSummary {
+    fn default() -> Self {
+        assess(&[])
+    }
+}
+
+fn intent_risk(intent: &Intent) -> (Downtime, DiskChange, NetworkUse) {
+    match intent {
+        Intent::Install { .. } => (Downtime::None, DiskChange::Grows, NetworkUse::Heavy),
+        // Removing only unlinks; space comes back at the next garbage collection
+        Intent::Remove { .. } => (Downtime::None, DiskChange::Unchanged, NetworkUse::None),
+        Intent::Update => (
+            Downtime::ServiceRestart,
+            DiskChange::Grows,
+            NetworkUse::Heavy,
+        ),
+        Intent::Rollback { .. } => (
+            Downtime::ServiceRestart,
+            DiskChange::Unchanged,
+            NetworkUse::None,
+        ),
+        Intent::GarbageCollect | Intent::DeleteGenerations { .. } => {
+            (Downtime::None, DiskChange::Shrinks, NetworkUse::None)
+        }
+        // Takes effect at the next boot
+        Intent::SetBootDefault { .. } => (Downtime::None, DiskChange::Unchanged, NetworkUse::None),
+        Intent::SwitchSpecialisation { .. } => (
+            Downtime::ServiceRestart,
+            DiskChange::Unchanged,
+            NetworkUse::None,
+        ),
+        Intent::Search { .. } => (Downtime::None, DiskChange::Unchanged, NetworkUse::Light),
+        Intent::ScaffoldProject { .. } => (Downtime::None, DiskChange::Grows, NetworkUse::Light),
+        Intent::Plugin { .. } => (Downtime::None, DiskChange::Unchanged, NetworkUse::Light),
+        Intent::ListInstalled
+        | Intent::Explain { .. }
+        | Intent::Configure { .. }
+        | Intent::SetDefaultApp { .. }
+        | Intent::Unknown => (Downtime::None, DiskChange::Unchanged, NetworkUse::None),
+    }
+}
+
+fn spoken(risk: &RiskSummary) -> String {
+    let m = i18n::message;
+    let mut parts = vec![if risk.reversible {
+        m("risk-reversible", &[])
+    } else {
+        m("risk-irreversible", &[])
+    }];
+    parts.push(match risk.downtime {
+        Downtime::None => m("risk-downtime-none", &[]),
+        Downtime::ServiceRestart => m("risk-downtime-restart", &[]),
+        Downtime::Reboot => m("risk-downtime-reboot", &[]),
+    });
+    let delta = risk.disk_delta_mb.map(|d| d.unsigned_abs().to_string());
+    parts.push(match (risk.disk, delta) {
+        (DiskChange::Unchanged, _) => m("risk-disk-unchanged", &[]),
+        (DiskChange::Grows, Some(mb)) => m("risk-disk-grows-by", &[("size", &mb)]),
+        (DiskChange::Grows, None) => m("risk-disk-grows", &[]),
+        (DiskChange::Shrinks, Some(mb)) => m("risk-disk-shrinks-by", &[("size", &mb)]),
+        (DiskChange::Shrinks, None) => m("risk-disk-shrinks", &[]),
+        (DiskChange::Mixed, _) => m("risk-disk-mixed", &[]),
+    });
+    parts.push(match risk.network {
+        NetworkUse::None => m("risk-network-none", &[]),
+        NetworkUse::Light => m("risk-network-light", &[]),
+        NetworkUse::Heavy => m("risk-network-heavy", &[]),
+    });
+    parts.join(" ")
+}
+
+// The combined risk of running all of `intents`
+pub fn assess(intents: &[Intent]) -> RiskSummary {
+    let blast_radius = intents
+        .iter()
+        .map(classify)
+        .max()
+        .unwrap_or(BlastRadius::ReadOnly);
+    let mut downtime = Downtime::None;
+    let mut network = NetworkUse::None;
+    let mut disk = DiskChange::Unchanged;
+    for intent in intents {
+        let (d, change, n) = intent_risk(intent);
+        downtime = downtime.max(d);
+        network = network.max(n);
+        disk = match (disk, change) {
+            (current, DiskChange::Unchanged) => current,
+            (DiskChange::Unchanged, next) => next,
+            (current, next) if current == next => current,
+            _ => DiskChange::Mixed,
+        };
+    }
+    let mut risk = RiskSummary {
+        blast_radius,
+        reversible: blast_radius != BlastRadius::Destructive,
+        downtime,
+        disk,
+        disk_delta_mb: None,
+        network,
+        spoken: String::new(),
+    };
+    risk.spoken = spoken(&risk);
+    risk
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SafetyPolicy {
+    pub confirm_reversible: bool,
+    // Destructive intents always need confirmation; this only adds a typed phrase
+    pub destructive_requires_phrase: bool,
+    // Classes (as classified for speech) that are confirmed by saying a phrase
+    // when the request came by voice
+    pub spoken_confirmation: Vec<BlastRadius>,
+}
+
+impl Default for SafetyPolicy {
+    fn default() -> Self {
+        SafetyPolicy {
+            confirm_reversible: false,
+            destructive_requires_phrase: false,
+            spoken_confirmation: vec![BlastRadius::Destructive],
+        }
+    }
+}
+
+impl SafetyPolicy {
+    pub fn load() -> Self {
+        storage::load(POLICY_FILE).unwrap_or_default()
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        storage::save(POLICY_FILE, self).map(|_| ())
+    }
+
+    pub fn needs_confirmation(&self, radius: BlastRadius) -> bool {
+        match radius {
+            BlastRadius::ReadOnly => false,
+            BlastRadius::Reversible => self.confirm_reversible,
+            BlastRadius::Destructive => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmationRequest {
+    pub token: String,
+    pub blast_radius: BlastRadius,
+    pub summary: Vec<String>,
+    pub risk: RiskSummary,
+    // Text the user has to type, when the policy asks for it
+    pub phrase: Option<String>,
+    // For a request made by voice: what to say to confirm it. The visual
+    // confirmation still works for anyone who can't or won't speak it
+    pub spoken_phrase: Option<String>,
+    pub expires_in_secs: u64,
+}
+
+struct Pending {
+    intents: Vec<Intent>,
+    phrase: Option<String>,
+    issued: Instant,
+}
+
+// Outstanding confirmation tokens, kept in AppState
+#[derive(Default)]
+pub struct ConfirmationGate {
+    pending: HashMap<String, Pending>,
+}
+
+// 128 bits from the kernel's random source, so tokens can't be guessed
+fn new_token() -> std::io::Result<String> {
+    let mut bytes = [0u8; 16];
+    File::open(RANDOM_SOURCE)?.read_exact(&mut bytes)?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+impl ConfirmationGate {
+    pub fn request(
+        &mut self,
+        intents: Vec<Intent>,
+        policy: &SafetyPolicy,
+        spoken: bool,
+    ) -> std::io::Result<ConfirmationRequest> {
+        self.pending
+            .retain(|_, p| p.issued.elapsed() < TOKEN_LIFETIME);
+        let risk = assess(&intents);
+        let blast_radius = risk.blast_radius;
+        let phrase = (blast_radius == BlastRadius::Destructive
+            && policy.destructive_requires_phrase)
+            .then(|| "yes, delete permanently".to_string());
+        let spoken_phrase = (spoken
+            && intents
+                .iter()
+                .map(classify_spoken)
+                .max()
+                .is_some_and(|radius| policy.spoken_confirmation.contains(&radius)))
+        .then(|| spoken_phrase(&intents));
+        let token = new_token()?;
+        let request = ConfirmationRequest {
+            token: token.clone(),
+            blast_radius,
+            summary: intents.iter().map(Intent::describe).collect(),
+            risk,
+            phrase: phrase.clone(),
+            spoken_phrase,
+            expires_in_secs: TOKEN_LIFETIME.as_secs(),
+        };
+        self.pending.insert(
+            token,
+            Pending {
+                intents,
+                phrase,
+                issued: Instant::now(),
+            },
+        );
+        Ok(request)
+    }
+
+    // Single use: the token is consumed even when the intents don't match
+    pub fn redeem(&mut self, token: &str, intents: &[Intent], phrase: Option<&str>) -> bool {
+        let Some(pending) = self.pending.remove(token) else {
+            return false;
+        };
+        pending.issued.elapsed() < TOKEN_LIFETIME
+            && pending.intents == intents
+            && pending
+                .phrase
+                .as_deref()
+                .is_none_or(|expected| phrase.map(str::trim) == Some(expected))
+    }
+}
+
+// Check the confirmation policy for `intents`; Err carries the response to send back.
+// `"input": "voice"` in the options marks a spoken request.
+pub fn guard(
+    gate: &mut ConfirmationGate,
+    intents: &[Intent],
+    options: &serde_json::Value,
+) -> Result<(), serde_json::Value> {
+    let policy = SafetyPolicy::load();
+    let spoken = options.get("input").and_then(|v| v.as_str()) == Some("voice");
+    let radius = if spoken { classify_spoken } else { classify };
+    let needed: Vec<Intent> = intents
+        .iter()
+        .filter(|i| policy.needs_confirmation(radius(i)))
+        .cloned()
+        .collect();
+    if needed.is_empty() {
+        return Ok(());
+    }
+    let text = |name: &str| options.get(name).and_then(|v| v.as_str());
+    if let Some(token) = text("confirmation_token") {
+        if gate.redeem(token, &needed, text("confirmation_phrase")) {
+            return Ok(());
+        }
+    }
+    // Without a token nothing can be confirmed, so nothing runs
+    match gate.request(needed, &policy, spoken) {
+        Ok(request) => Err(serde_json::json!({
+            "success": false,
+            "needs_confirmation": true,
+            "confirmation": request,
+        })),
+        Err(e) => Err(serde_json::json!({
+            "success": false,
+            "error": format!("Could not issue a confirmation token: {}", e),
+        })),
+    }
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn classify_intent(intent: Intent) -> BlastRadius {
+    classify(&intent)
+}
+
+#[tauri::command]
+pub async fn assess_intents(intents: Vec<Intent>, app: AppHandle) -> RiskSummary {
+    tasks::blocking_value(&app, "Assess intents", move |_| assess(&intents)).await
+}
+
+#[tauri::command]
+pub async fn get_safety_policy(app: AppHandle) -> SafetyPolicy {
+    tasks::blocking_value(&app, "Get safety policy", |_| SafetyPolicy::load()).await
+}
+
+#[tauri::command]
+pub async fn set_safety_policy(policy: SafetyPolicy, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set safety policy", move |_| {
+        if let Some(refusal) = managed::guard("safety_policy", "Change the safety policy") {
+            return refusal;
+        }
+        crate::respond(policy.save())
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remove(names: &[&str]) -> Intent {
+        Intent::Remove {
+            packages: names.iter().map(|n| n.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn only_deleting_generations_is_destructive() {
+        assert_eq!(
+            classify(&Intent::Search {
+                query: "vim".to_string()
+            }),
+            BlastRadius::ReadOnly
+        );
+        assert_eq!(classify(&remove(&["vim"])), BlastRadius::Reversible);
+        assert_eq!(classify(&Intent::Update), BlastRadius::Reversible);
+        assert_eq!(classify(&Intent::GarbageCollect), BlastRadius::Destructive);
+        assert_eq!(
+            classify(&Intent::DeleteGenerations {
+                generations: vec![3]
+            }),
+            BlastRadius::Destructive
+        );
+    }
+
+    #[test]
+    fn spoken_requests_treat_taking_away_as_destructive() {
+        assert_eq!(classify_spoken(&remove(&["vim"])), BlastRadius::Destructive);
+        assert_eq!(
+            classify_spoken(&Intent::Rollback { generation: None }),
+            BlastRadius::Destructive
+        );
+        assert_eq!(classify_spoken(&Intent::Update), BlastRadius::Reversible);
+    }
+
+    #[test]
+    fn spoken_phrase_names_what_happens() {
+        assert_eq!(
+            spoken_phrase(&[remove(&["firefox", "vlc"])]),
+            "yes, remove firefox and vlc"
+        );
+        assert_eq!(
+            spoken_phrase(&[
+                Intent::Rollback {
+                    generation: Some(3)
+                },
+                Intent::GarbageCollect
+            ]),
+            "yes, roll back to 3 and delete old generations"
+        );
+    }
+
+    #[test]
+    fn default_policy_confirms_only_destructive() {
+        let policy = SafetyPolicy::default();
+        assert!(!policy.needs_confirmation(BlastRadius::ReadOnly));
+        assert!(!policy.needs_confirmation(BlastRadius::Reversible));
+        assert!(policy.needs_confirmation(BlastRadius::Destructive));
+    }
+
+    #[test]
+    fn tokens_are_redeemed_once() {
+        let mut gate = ConfirmationGate::default();
+        let intents = vec![Intent::GarbageCollect];
+        let request = gate
+            .request(intents.clone(), &SafetyPolicy::default(), false)
+            .unwrap();
+        assert_eq!(request.blast_radius, BlastRadius::Destructive);
+        assert_eq!(request.phrase, None);
+        assert_eq!(request.spoken_phrase, None);
+        assert_eq!(request.token.len(), 32);
+        assert!(gate.redeem(&request.token, &intents, None));
+        assert!(!gate.redeem(&request.token, &intents, None));
+    }
+
+    #[test]
+    fn tokens_only_confirm_the_intents_they_were_issued_for() {
+        let mut gate = ConfirmationGate::default();
+        let policy = SafetyPolicy::default();
+        let request = gate
+            .request(vec![Intent::GarbageCollect], &policy, false)
+            .unwrap();
+        let other = vec![Intent::DeleteGenerations {
+            generations: vec![1, 2],
+        }];
+        assert!(!gate.redeem(&request.token, &other, None));
+        // A failed attempt uses the token up
+        assert!(!gate.redeem(&request.token, &[Intent::GarbageCollect], None));
+        assert!(!gate.redeem("not-a-token", &[Intent::GarbageCollect], None));
+
+        let first = gate.request(other.clone(), &policy, false).unwrap();
+        let second = gate.request(other.clone(), &policy, false).unwrap();
+        assert_ne!(first.token, second.token);
+    }
+
+    #[test]
+    fn phrases_are_checked_when_the_policy_asks() {
+        let mut gate = ConfirmationGate::default();
+        let policy = SafetyPolicy {
+            destructive_requires_phrase: true,
+            ..SafetyPolicy::default()
+        };
+        let intents = vec![Intent::GarbageCollect];
+        let request = gate.request(intents.clone(), &policy, false).unwrap();
+        assert_eq!(request.phrase.as_deref(), Some("yes, delete permanently"));
+        assert!(!gate.redeem(&request.token, &intents, None));
+
+        let request = gate.request(intents.clone(), &policy, false).unwrap();
+        assert!(!gate.redeem(&request.token, &intents, Some("yes")));
+
+        let request = gate.request(intents.clone(), &policy, false).unwrap();
+        assert!(gate.redeem(&request.token, &intents, Some(" yes, delete permanently ")));
+    }
+
+    #[test]
+    fn voice_requests_get_a_phrase_to_say() {
+        let mut gate = ConfirmationGate::default();
+        let policy = SafetyPolicy::default();
+        let intents = vec![remove(&["firefox"])];
+        let spoken = gate.request(intents.clone(), &policy, true).unwrap();
+        assert_eq!(spoken.spoken_phrase.as_deref(), Some("yes, remove firefox"));
+        let typed = gate.request(intents, &policy, false).unwrap();
+        assert_eq!(typed.spoken_phrase, None);
+    }
+}
@@ -0,0 +1,214 @@
+// Evaluate a generated module against the real configuration before writing it
+//
+// The candidate module is rendered into a scratch directory and imported on
+// top of the current system (or home-manager) configuration, with the managed
+// copy it would replace disabled. Evaluating the result catches misspelled
+// options, type errors and failed assertions at plan time instead of at
+// rebuild time. Nothing outside the scratch directory is touched.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::evalpool::{self, Priority};
+use crate::nixgen::{self, NixModule, Target};
+use crate::system;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SimulationStatus {
+    Passed,
+    Failed,
+    // Couldn't evaluate (no nix, no configuration found, or the current
+    // configuration is already broken); the change wasn't checked
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Simulation {
+    pub status: SimulationStatus,
+    pub errors: Vec<String>,
+    pub note: Option<String>,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        skipped("Not evaluated")
+    }
+}
+
+impl Simulation {
+    pub fn failed(&self) -> bool {
+        self.status == SimulationStatus::Failed
+    }
+}
+
+fn skipped(note: &str) -> Simulation {
+    Simulation {
+        status: SimulationStatus::Skipped,
+        errors: Vec::new(),
+        note: Some(note.to_string()),
+    }
+}
+
+fn nix_path_literal(path: &Path) -> String {
+    // Paths with spaces can't be bare Nix path literals
+    format!("(/. + {})", nixgen::string(&path.display().to_string()))
+}
+
+fn hostname() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|h| h.trim().to_string())
+        .unwrap_or_default()
+}
+
+// Expression for the configuration with `extra` imported and `replaced` disabled;
+// with no extra module this is the current configuration as-is
+fn expression(target: Target, extra: Option<&Path>, replaced: &Path) -> Option<String> {
+    let overlay = match extra {
+        Some(extra) => format!(
+            "{{ imports = [ {} ]; disabledModules = [ {} ]; }}",
+            nix_path_literal(extra),
+            nix_path_literal(replaced)
+        ),
+        None => "{ }".to_string(),
+    };
+    match target {
+        Target::Nixos => {
+            let flake = Path::new("/etc/nixos/flake.nix");
+            if flake.exists() {
+                return Some(format!(
+                    "((builtins.getFlake \"/etc/nixos\").nixosConfigurations.{}.extendModules {{ modules = [ {} ]; }}).config.system.build.toplevel.drvPath",
+                    nixgen::attr(&hostname()),
+                    overlay
+                ));
+            }
+            let config = std::env::var("NIXOS_CONFIG")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from("/etc/nixos/configuration.nix"));
+            config.exists().then(|| {
+                format!(
+                    "(import <nixpkgs/nixos> {{ configuration = {{ imports = [ {} {} ]; }}; }}).config.system.build.toplevel.drvPath",
+                    nix_path_literal(&config),
+                    overlay
+                )
+            })
+        }
+        Target::HomeManager => {
+            let config = system::xdg_config_home().join("home-manager/home.nix");
+            config.exists().then(|| {
+                format!(
+                    "(import <home-manager/modules> {{ pkgs = import <nixpkgs> {{ }}; configuration = {{ imports = [ {} {} ]; }}; }}).activationPackage.drvPath",
+                    nix_path_literal(&config),
+                    overlay
+                )
+            })
+        }
+    }
+}
+
+// The messages worth showing from nix's stderr: the innermost error and any failed assertions
+fn error_lines(stderr: &str) -> Vec<String> {
+    let mut errors = Vec::new();
+    if let Some(at) = stderr.rfind("error:") {
+        let message = stderr[at + "error:".len()..]
+            .lines()
+            .map(str::trim)
+            .take_while(|l| !l.starts_with('…'))
+            .filter(|l| !l.is_empty() && !l.starts_with("- "))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !message.is_empty() {
+            errors.push(message);
+        }
+    }
+    errors.extend(
+        stderr
+            .lines()
+            .map(str::trim)
+            .filter_map(|l| l.strip_prefix("- "))
+            .map(String::from),
+    );
+    errors
+}
+
+// Evaluations of a change the user is about to apply are interactive
+fn evaluate(expression: &str) -> Result<(), String> {
+    let expression = expression.to_string();
+    evalpool::run(Priority::Interactive, move || Ok(evaluate_now(&expression)))
+        .unwrap_or_else(|e| Err(e.to_string()))
+}
+
+fn evaluate_now(expression: &str) -> Result<(), String> {
+    let output = Command::new("nix-instantiate")
+        .args([
+            "--eval",
+            "--extra-experimental-features",
+            "nix-command flakes",
+            "--expr",
+            expression,
+        ])
+        .output()
+        .map_err(|e| format!("failed to start nix-instantiate: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).into_owned())
+    }
+}
+
+fn scratch_dir(module: &NixModule) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "luminous-nix-sandbox-{}-{}",
+        std::process::id(),
+        module.name
+    ))
+}
+
+pub fn simulate(module: &NixModule) -> Simulation {
+    if system::find_in_path("nix-instantiate").is_none() {
+        return skipped("Nix isn't available, so the change couldn't be evaluated first");
+    }
+    let scratch = scratch_dir(module);
+    let candidate = scratch.join(format!("{}.nix", module.name));
+    if let Err(e) =
+        fs::create_dir_all(&scratch).and_then(|_| fs::write(&candidate, module.render()))
+    {
+        return skipped(&format!("Couldn't prepare the sandbox: {}", e));
+    }
+    let Some(with_change) = expression(module.target, Some(&candidate), &module.path()) else {
+        let _ = fs::remove_dir_all(&scratch);
+        return skipped("No configuration found to evaluate the change against");
+    };
+    let result = evaluate(&with_change);
+    let _ = fs::remove_dir_all(&scratch);
+
+    let Err(stderr) = result else {
+        return Simulation {
+            status: SimulationStatus::Passed,
+            errors: Vec::new(),
+            note: None,
+        };
+    };
+    if stderr.contains("was not found in the Nix search path") {
+        return skipped("The channels needed to evaluate this configuration aren't on NIX_PATH");
+    }
+    // Don't blame the change for errors the configuration already has
+    let baseline = expression(module.target, None, &module.path()).map(|e| evaluate(&e));
+    if let Some(Err(existing)) = baseline {
+        return Simulation {
+            status: SimulationStatus::Skipped,
+            errors: error_lines(&existing),
+            note: Some(
+                "Your current configuration doesn't evaluate either, so this change couldn't be checked on its own"
+                    .to_string(),
+            ),
+        };
+    }
+    Simulation {
+        status: SimulationStatus::Failed,
+        errors: error_lines(&stderr),
+        note: Some("Nothing was written; fix these before applying".to_string()),
+    }
+}
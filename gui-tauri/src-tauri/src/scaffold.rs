@@ -0,0 +1,177 @@
+// Flake template scaffolding
+//
+// Lists the templates a flake exposes (`nix flake show templates`), instantiates
+// one into a directory and optionally initializes git, so "start a new Rust
+// project" can be answered in one step.
+
+use anyhow::{anyhow, bail, Context};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::{managed, system, tasks};
+
+const DEFAULT_SOURCE: &str = "templates";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlakeTemplate {
+    pub name: String,
+    pub description: String,
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScaffoldResult {
+    pub template: FlakeTemplate,
+    pub path: PathBuf,
+    pub files: Vec<String>,
+    pub git_initialized: bool,
+    pub next_steps: Vec<String>,
+}
+
+pub fn list_templates(source: &str) -> anyhow::Result<Vec<FlakeTemplate>> {
+    let output = system::run("nix", &["flake", "show", source, "--json"])?;
+    let parsed: serde_json::Value = serde_json::from_str(&output)?;
+    let mut templates: Vec<FlakeTemplate> = parsed
+        .get("templates")
+        .and_then(|t| t.as_object())
+        .map(|map| {
+            map.iter()
+                .map(|(name, info)| FlakeTemplate {
+                    name: name.clone(),
+                    description: info
+                        .get("description")
+                        .and_then(|d| d.as_str())
+                        .unwrap_or("")
+                        .to_string(),
+                    source: source.to_string(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(templates)
+}
+
+// Resolve "rust", "Python" or "a haskell project" to a concrete template
+pub fn resolve_template(query: &str, templates: &[FlakeTemplate]) -> Option<FlakeTemplate> {
+    let query = query.to_lowercase();
+    let words: Vec<&str> = query
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+        .filter(|w| !w.is_empty())
+        .collect();
+    templates
+        .iter()
+        .find(|t| words.contains(&t.name.to_lowercase().as_str()))
+        .or_else(|| {
+            templates.iter().find(|t| {
+                let name = t.name.to_lowercase();
+                words.iter().any(|w| name.split('-').any(|part| part == *w))
+            })
+        })
+        .or_else(|| {
+            templates.iter().find(|t| {
+                let description = t.description.to_lowercase();
+                words
+                    .iter()
+                    .any(|w| w.len() > 2 && description.split_whitespace().any(|d| d == *w))
+            })
+        })
+        .cloned()
+}
+
+fn list_files(path: &Path) -> Vec<String> {
+    let mut files: Vec<String> = fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.file_name().to_string_lossy().into_owned())
+                .filter(|name| name != ".git")
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+pub fn scaffold(
+    template: &str,
+    path: &Path,
+    source: Option<&str>,
+    init_git: bool,
+) -> anyhow::Result<ScaffoldResult> {
+    let source = source.unwrap_or(DEFAULT_SOURCE);
+    let templates = list_templates(source)?;
+    let template = resolve_template(template, &templates)
+        .ok_or_else(|| anyhow!("No template in {} matches '{}'", source, template))?;
+
+    if path.exists() && fs::read_dir(path)?.next().is_some() {
+        bail!("{} already exists and is not empty", path.display());
+    }
+    fs::create_dir_all(path).with_context(|| format!("creating {}", path.display()))?;
+    let reference = format!("{}#{}", template.source, template.name);
+    system::run_in(path, "nix", &["flake", "init", "-t", &reference])?;
+
+    let git_initialized = init_git && !path.join(".git").exists();
+    if git_initialized {
+        system::run_in(path, "git", &["init", "--quiet"])?;
+        // Flakes only see tracked files, so stage everything right away
+        system::run_in(path, "git", &["add", "--all"])?;
+    }
+
+    let mut next_steps = vec![format!("cd {}", path.display())];
+    if path.join("flake.nix").exists() {
+        next_steps.push("nix develop".to_string());
+    }
+    if path.join(".envrc").exists() {
+        next_steps.push("direnv allow".to_string());
+    }
+
+    Ok(ScaffoldResult {
+        files: list_files(path),
+        path: path.to_path_buf(),
+        template,
+        git_initialized,
+        next_steps,
+    })
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn list_flake_templates(source: Option<String>, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "List flake templates", move |_| {
+        crate::respond(list_templates(source.as_deref().unwrap_or(DEFAULT_SOURCE)))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn scaffold_project(
+    template: String,
+    path: String,
+    source: Option<String>,
+    init_git: Option<bool>,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Scaffold project", move |_| {
+        if let Some(refusal) = managed::guard(
+            "scaffold_project",
+            &format!("Start a new {} project", template),
+        ) {
+            return refusal;
+        }
+        let path = match path.strip_prefix("~/") {
+            Some(rest) => system::home_dir().join(rest),
+            None => PathBuf::from(path),
+        };
+        crate::respond(scaffold(
+            &template,
+            &path,
+            source.as_deref(),
+            init_git.unwrap_or(true),
+        ))
+    })
+    .await
+}
@@ -0,0 +1,376 @@
+// Screenshots of the app, a whole window or one component
+//
+// A webview can't render itself to an image, so the window's part of the
+// screen is captured with the desktop's screenshot tool: grim on Wayland
+// compositors that support it, maim or ImageMagick's import on X11. Wayland
+// clients aren't told where their windows are, so there the window's box comes
+// from the compositor (sway or Hyprland; others don't say, so there is no
+// screenshot), and only once it confirms that the focused window is ours. The
+// box of a component comes from its element in the page. The window is raised
+// first, so the picture shows the app rather than whatever was covering it.
+// ai_get_screenshot sends the picture as raw bytes behind a short header with
+// everything else about it; see encode.
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::{clock, panels, probe, system};
+
+// Time for the compositor to draw the raised window
+const RAISE_DELAY: Duration = Duration::from_millis(150);
+
+// Screen pixels as the screenshot tool takes them: physical on X11, logical
+// (the compositor's layout) on Wayland
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Region {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Screenshot {
+    // Sent after the header rather than inside it
+    #[serde(skip)]
+    pub png: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    // Unix milliseconds
+    pub taken_at: u64,
+    pub window: String,
+    pub component_id: Option<String>,
+    pub region: Region,
+}
+
+// The page's box for a component, in CSS pixels
+#[derive(Debug, Deserialize)]
+struct ElementBox {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    ratio: f64,
+}
+
+fn wayland() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+// The compositor's focused window: whose it is, what it's called and where
+#[derive(Debug, PartialEq)]
+struct Focused {
+    pid: u32,
+    title: String,
+    region: Region,
+}
+
+// From `hyprctl activewindow -j`
+fn hyprland_focused(window: &serde_json::Value) -> Option<Focused> {
+    Some(Focused {
+        pid: window["pid"].as_u64()? as u32,
+        title: window["title"].as_str()?.to_string(),
+        region: Region {
+            x: window["at"][0].as_i64()? as i32,
+            y: window["at"][1].as_i64()? as i32,
+            width: window["size"][0].as_u64()? as u32,
+            height: window["size"][1].as_u64()? as u32,
+        },
+    })
+}
+
+fn focused_node(node: &serde_json::Value) -> Option<&serde_json::Value> {
+    if node["focused"].as_bool() == Some(true) {
+        return Some(node);
+    }
+    ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node[*key].as_array())
+        .flatten()
+        .find_map(focused_node)
+}
+
+// From `swaymsg -t get_tree`: the focused container's box, and where the
+// window's content sits inside it
+fn sway_focused(tree: &serde_json::Value) -> Option<Focused> {
+    let node = focused_node(tree)?;
+    let (outer, content) = (&node["rect"], &node["window_rect"]);
+    Some(Focused {
+        pid: node["pid"].as_u64()? as u32,
+        title: node["name"].as_str()?.to_string(),
+        region: Region {
+            x: (outer["x"].as_i64()? + content["x"].as_i64()?) as i32,
+            y: (outer["y"].as_i64()? + content["y"].as_i64()?) as i32,
+            width: content["width"].as_u64()? as u32,
+            height: content["height"].as_u64()? as u32,
+        },
+    })
+}
+
+fn compositor_focused() -> anyhow::Result<Focused> {
+    let (program, args, parse): (&str, &[&str], fn(&serde_json::Value) -> Option<Focused>) =
+        if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+            ("hyprctl", &["activewindow", "-j"], hyprland_focused)
+        } else if std::env::var_os("SWAYSOCK").is_some() {
+            ("swaymsg", &["-t", "get_tree"], sway_focused)
+        } else {
+            bail!("Screenshots on Wayland need sway or Hyprland; other compositors don't say where windows are");
+        };
+    let output = system::run(program, args)?;
+    let value: serde_json::Value = serde_json::from_str(&output)?;
+    parse(&value).ok_or_else(|| anyhow!("{} didn't say which window has the focus", program))
+}
+
+// Where the window is on screen. On Wayland it has just been asked to take the
+// focus, which the compositor may refuse, so its focused window is only used
+// when it is this one
+fn window_region(window: &tauri::WebviewWindow) -> anyhow::Result<Region> {
+    if wayland() {
+        let focused = compositor_focused()?;
+        if focused.pid != std::process::id() || focused.title != window.title()? {
+            bail!(
+                "Another window kept the focus, so the screenshot would show it instead of {}",
+                window.label()
+            );
+        }
+        return Ok(focused.region);
+    }
+    let position = window.inner_position()?;
+    let size = window.inner_size()?;
+    Ok(Region {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    })
+}
+
+fn component_region(app: &AppHandle, component_id: &str, window: Region) -> anyhow::Result<Region> {
+    let script = format!(
+        "var e={};if(!e)throw new Error('it is not on screen');\
+         var r=e.getBoundingClientRect();\
+         return {{x:r.left,y:r.top,width:r.width,height:r.height,ratio:window.devicePixelRatio}};",
+        probe::component_element(component_id)
+    );
+    let value = probe::eval_in_component(app, component_id, &script, probe::DEFAULT_TIMEOUT)?;
+    let element: ElementBox = serde_json::from_value(value)?;
+    // grim takes logical pixels, which CSS pixels already are
+    let ratio = if wayland() { 1.0 } else { element.ratio };
+    // Only the part inside the window is on screen
+    let left = (element.x * ratio).max(0.0);
+    let top = (element.y * ratio).max(0.0);
+    let right = ((element.x + element.width) * ratio).min(window.width as f64);
+    let bottom = ((element.y + element.height) * ratio).min(window.height as f64);
+    if right - left < 1.0 || bottom - top < 1.0 {
+        bail!("{} is scrolled out of view", component_id);
+    }
+    Ok(Region {
+        x: window.x + left.round() as i32,
+        y: window.y + top.round() as i32,
+        width: (right - left).round() as u32,
+        height: (bottom - top).round() as u32,
+    })
+}
+
+// The screenshot tools for this session, with their arguments for a region
+fn tools(region: Region) -> Vec<(&'static str, Vec<String>)> {
+    let Region {
+        x,
+        y,
+        width,
+        height,
+    } = region;
+    if wayland() {
+        return vec![(
+            "grim",
+            vec![
+                "-g".to_string(),
+                format!("{},{} {}x{}", x, y, width, height),
+                "-".to_string(),
+            ],
+        )];
+    }
+    let geometry = format!("{}x{}+{}+{}", width, height, x, y);
+    vec![
+        ("maim", vec!["-g".to_string(), geometry.clone()]),
+        (
+            "import",
+            vec![
+                "-window".to_string(),
+                "root".to_string(),
+                "-crop".to_string(),
+                geometry,
+                "png:-".to_string(),
+            ],
+        ),
+    ]
+}
+
+fn grab(region: Region) -> anyhow::Result<Vec<u8>> {
+    let tools = tools(region);
+    let (program, args) = tools
+        .iter()
+        .find(|(program, _)| system::find_in_path(program).is_some())
+        .ok_or_else(|| {
+            let names: Vec<&str> = tools.iter().map(|(program, _)| *program).collect();
+            anyhow!("Taking a screenshot needs {} installed", names.join(" or "))
+        })?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    system::run_bytes(program, &args)
+}
+
+// Width and height from the PNG header
+fn dimensions(png: &[u8]) -> anyhow::Result<(u32, u32)> {
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    if png.len() < 24 || !png.starts_with(SIGNATURE) || &png[12..16] != b"IHDR" {
+        bail!("The screenshot tool didn't produce a PNG");
+    }
+    let field = |at: usize| u32::from_be_bytes([png[at], png[at + 1], png[at + 2], png[at + 3]]);
+    Ok((field(16), field(20)))
+}
+
+// The main window, or just the component with `component_id` wherever it is shown
+pub fn capture(app: &AppHandle, component_id: Option<&str>) -> anyhow::Result<Screenshot> {
+    let label = match component_id {
+        Some(id) => panels::window_for(app, id),
+        None => "main".to_string(),
+    };
+    let window = app
+        .get_webview_window(&label)
+        .ok_or_else(|| anyhow!("There is no window \"{}\"", label))?;
+    if window.is_minimized()? {
+        window.unminimize()?;
+    }
+    window.show()?;
+    window.set_focus()?;
+    std::thread::sleep(RAISE_DELAY);
+
+    let mut region = window_region(&window)?;
+    if let Some(id) = component_id {
+        region = component_region(app, id, region)?;
+    }
+    let png = grab(region)?;
+    let (width, height) = dimensions(&png)?;
+    Ok(Screenshot {
+        png,
+        width,
+        height,
+        taken_at: clock::now_ms(),
+        window: label,
+        component_id: component_id.map(String::from),
+        region,
+    })
+}
+
+// For sending as raw bytes: the length of the JSON header as 4 big-endian
+// bytes, the header (everything but the image), then the PNG
+pub fn encode(shot: &Screenshot) -> anyhow::Result<Vec<u8>> {
+    let header = serde_json::to_vec(shot)?;
+    let mut bytes = Vec::with_capacity(4 + header.len() + shot.png.len());
+    bytes.extend_from_slice(&(header.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(&header);
+    bytes.extend_from_slice(&shot.png);
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&width.to_be_bytes());
+        png.extend_from_slice(&height.to_be_bytes());
+        png
+    }
+
+    #[test]
+    fn hyprland_names_the_focused_windows_owner() {
+        let window = json!({
+            "pid": 4242,
+            "title": "Luminous Nix",
+            "at": [10, 20],
+            "size": [800, 600],
+        });
+        let focused = hyprland_focused(&window).unwrap();
+        assert_eq!(focused.pid, 4242);
+        assert_eq!(focused.title, "Luminous Nix");
+        assert_eq!(
+            focused.region,
+            Region {
+                x: 10,
+                y: 20,
+                width: 800,
+                height: 600
+            }
+        );
+        // Nothing focused: hyprctl answers with an empty object
+        assert_eq!(hyprland_focused(&json!({})), None);
+    }
+
+    #[test]
+    fn sway_finds_the_focused_node_and_its_content() {
+        let tree = json!({
+            "focused": false,
+            "nodes": [{
+                "focused": false,
+                "nodes": [],
+                "floating_nodes": [{
+                    "focused": true,
+                    "pid": 99,
+                    "name": "Luminous Nix",
+                    "rect": {"x": 100, "y": 50, "width": 810, "height": 630},
+                    "window_rect": {"x": 5, "y": 25, "width": 800, "height": 600},
+                }],
+            }],
+        });
+        let focused = sway_focused(&tree).unwrap();
+        assert_eq!(focused.pid, 99);
+        assert_eq!(
+            focused.region,
+            Region {
+                x: 105,
+                y: 75,
+                width: 800,
+                height: 600
+            }
+        );
+        assert_eq!(sway_focused(&json!({"focused": false, "nodes": []})), None);
+    }
+
+    #[test]
+    fn dimensions_come_from_the_png_header() {
+        assert_eq!(dimensions(&png(640, 480)).unwrap(), (640, 480));
+        assert!(dimensions(b"not a png at all, just some text").is_err());
+    }
+
+    #[test]
+    fn encode_puts_the_details_before_the_image() {
+        let image = png(2, 1);
+        let shot = Screenshot {
+            png: image.clone(),
+            width: 2,
+            height: 1,
+            taken_at: 1_700_000_000_000,
+            window: "main".to_string(),
+            component_id: None,
+            region: Region {
+                x: 0,
+                y: 0,
+                width: 2,
+                height: 1,
+            },
+        };
+        let bytes = encode(&shot).unwrap();
+        let length = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+        let header: serde_json::Value = serde_json::from_slice(&bytes[4..4 + length]).unwrap();
+        assert_eq!(header["width"], 2);
+        assert_eq!(header["taken_at"], 1_700_000_000_000u64);
+        assert_eq!(header["region"]["height"], 1);
+        assert!(header.get("png").is_none());
+        assert_eq!(&bytes[4 + length..], image.as_slice());
+    }
+}
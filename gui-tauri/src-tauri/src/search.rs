@@ -0,0 +1,288 @@
+// Package search with fuzzy "did you mean" suggestions
+//
+// Runs the regular nix search and, when it finds nothing or the query looks
+// misspelled, ranks names from a cached package index by fuzzy similarity.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Emitter};
+
+use crate::evalpool::{self, Priority};
+use crate::indexdelta::{self, Revision};
+use crate::nix::{self, Package};
+use crate::{clock, fuzzy, progress, storage, tasks};
+
+const INDEX_FILE: &str = "package-index.json";
+const INDEX_MAX_AGE_SECS: u64 = 7 * 24 * 60 * 60;
+const SUGGESTION_THRESHOLD: f32 = 0.6;
+const MAX_SUGGESTIONS: usize = 5;
+// Matches per "search-results" event when streaming
+const STREAM_BATCH: usize = 200;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PackageIndex {
+    pub built_at: u64,
+    // Attribute names, e.g. "firefox", "python312Packages.numpy"
+    pub attrs: Vec<String>,
+    // nixpkgs revision the attributes were listed from, when known
+    #[serde(default)]
+    pub revision: Option<Revision>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefreshKind {
+    // nixpkgs hasn't moved since the last refresh
+    Unchanged,
+    Delta,
+    Full,
+    // Metered connection and no delta available; the old index is kept
+    Deferred,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexRefresh {
+    pub kind: RefreshKind,
+    pub revision: Option<String>,
+    pub added: usize,
+    pub removed: usize,
+    pub total: usize,
+    pub bytes_downloaded: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub attr: String,
+    pub score: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchOutcome {
+    pub query: String,
+    pub results: Vec<Package>,
+    pub did_you_mean: Vec<Suggestion>,
+}
+
+// Set while a background refresh is queued or running, so a burst of
+// searches against a stale index only triggers one
+static REFRESHING: AtomicBool = AtomicBool::new(false);
+
+// Load the cached index. A missing index is built on the spot; a stale one is
+// used as is while it is refreshed in the background
+pub fn load_index() -> anyhow::Result<PackageIndex> {
+    let index: PackageIndex = storage::load_data(INDEX_FILE)?;
+    if index.attrs.is_empty() {
+        evalpool::run(Priority::Interactive, || refresh_index(false))?;
+        return storage::load_data(INDEX_FILE);
+    }
+    if clock::now().saturating_sub(index.built_at) >= INDEX_MAX_AGE_SECS
+        && !REFRESHING.swap(true, Ordering::SeqCst)
+    {
+        evalpool::spawn(Priority::Background, || {
+            let _ = refresh_index(false);
+            REFRESHING.store(false, Ordering::SeqCst);
+        });
+    }
+    Ok(index)
+}
+
+fn full_index(revision: Option<Revision>) -> anyhow::Result<PackageIndex> {
+    // Only the attribute names are kept; descriptions are dropped as they stream by
+    let mut attrs = Vec::new();
+    nix::search_each("^", |p| attrs.push(p.attr))?;
+    attrs.sort();
+    Ok(PackageIndex {
+        built_at: clock::now(),
+        attrs,
+        revision,
+    })
+}
+
+// Bring the index up to the current nixpkgs revision, applying only the
+// packages added or removed since the indexed one when that can be worked out.
+// Runs its evaluations directly; callers put the whole refresh on the pool
+pub fn refresh_index(force_full: bool) -> anyhow::Result<IndexRefresh> {
+    let mut index: PackageIndex = storage::load_data(INDEX_FILE)?;
+    let current = indexdelta::current_revision();
+    let previous = index.revision.clone();
+    let mut kind = RefreshKind::Full;
+    let (mut added, mut removed, mut bytes) = (0, 0, 0);
+
+    match (&previous, &current) {
+        (Some(old), Some(new)) if !force_full && !index.attrs.is_empty() && old == new => {
+            kind = RefreshKind::Unchanged;
+            index.built_at = clock::now();
+        }
+        (Some(old), Some(new)) if !force_full && !index.attrs.is_empty() => {
+            let sets: BTreeSet<String> = index
+                .attrs
+                .iter()
+                .filter_map(|a| a.split_once('.').map(|(set, _)| set.to_string()))
+                .collect();
+            match indexdelta::between(old, new, &sets).ok().flatten() {
+                Some(delta) => {
+                    kind = RefreshKind::Delta;
+                    let mut attrs: BTreeSet<String> = index.attrs.drain(..).collect();
+                    for attr in &delta.removed {
+                        removed += usize::from(attrs.remove(attr));
+                    }
+                    for attr in delta.added {
+                        added += usize::from(attrs.insert(attr));
+                    }
+                    index.attrs = attrs.into_iter().collect();
+                    index.revision = current.clone();
+                    index.built_at = clock::now();
+                    bytes = delta.bytes;
+                }
+                None if indexdelta::metered() => kind = RefreshKind::Deferred,
+                None => index = full_index(current.clone())?,
+            }
+        }
+        _ => index = full_index(current.clone())?,
+    }
+    if kind != RefreshKind::Deferred {
+        storage::save_data(INDEX_FILE, &index)?;
+    }
+    Ok(IndexRefresh {
+        kind,
+        revision: index.revision.map(|r| r.rev),
+        added,
+        removed,
+        total: index.attrs.len(),
+        bytes_downloaded: bytes,
+    })
+}
+
+pub fn suggestions(query: &str, index: &PackageIndex) -> Vec<Suggestion> {
+    let query_len = fuzzy::squash(query).len();
+    let mut ranked: Vec<Suggestion> = index
+        .attrs
+        .iter()
+        // Nested sets (python3Packages.*) only when the user typed a dot
+        .filter(|attr| query.contains('.') || !attr.contains('.'))
+        .filter(|attr| attr.len() + 6 >= query_len)
+        .map(|attr| Suggestion {
+            score: fuzzy::score(query, attr),
+            attr: attr.clone(),
+        })
+        .filter(|s| s.score >= SUGGESTION_THRESHOLD)
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(a.attr.len().cmp(&b.attr.len()))
+    });
+    ranked.truncate(MAX_SUGGESTIONS);
+    ranked
+}
+
+pub fn search(query: &str) -> anyhow::Result<SearchOutcome> {
+    let query = query.trim();
+    let interactive =
+        |query: String| evalpool::run(Priority::Interactive, move || nix::search(&query));
+    let mut results = interactive(query.to_string())?;
+    // "fire fox" is two regexes to nix; retry as a single word
+    if results.is_empty() && query.contains(' ') {
+        results = interactive(fuzzy::squash(query))?;
+    }
+    let squashed = fuzzy::squash(query);
+    let exact = results.iter().any(|p| fuzzy::squash(&p.attr) == squashed);
+    let did_you_mean = if exact {
+        Vec::new()
+    } else {
+        load_index()
+            .map(|index| suggestions(query, &index))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|s| fuzzy::squash(&s.attr) != squashed)
+            .collect()
+    };
+    Ok(SearchOutcome {
+        query: query.to_string(),
+        results,
+        did_you_mean,
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchBatch {
+    pub query: String,
+    pub packages: Vec<Package>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchFinished {
+    pub query: String,
+    pub total: usize,
+    pub error: Option<String>,
+}
+
+// Emit matches to the frontend in batches as nix finds them, so long result
+// lists start showing immediately and are never held in full
+pub fn stream(query: String, app: AppHandle) {
+    evalpool::spawn(Priority::Interactive, move || {
+        let mut batch = Vec::with_capacity(STREAM_BATCH);
+        let flush = |batch: &mut Vec<Package>| {
+            let _ = app.emit(
+                "search-results",
+                SearchBatch {
+                    query: query.clone(),
+                    packages: std::mem::take(batch),
+                },
+            );
+        };
+        let mut reporter =
+            progress::Reporter::start("search", &format!("Searching for {}", query), None, false);
+        let mut found = 0;
+        let result = nix::search_each(query.trim(), |package| {
+            batch.push(package);
+            found += 1;
+            if batch.len() >= STREAM_BATCH {
+                flush(&mut batch);
+                reporter.advance(found, &format!("{} found so far", found));
+            }
+        });
+        reporter.finish(&result, &format!("Searching for {}", query));
+        if !batch.is_empty() {
+            flush(&mut batch);
+        }
+        let (total, error) = match result {
+            Ok(total) => (total, None),
+            Err(e) => (0, Some(e.to_string())),
+        };
+        let _ = app.emit(
+            "search-finished",
+            SearchFinished {
+                query: query.clone(),
+                total,
+                error,
+            },
+        );
+    });
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn search_packages_streaming(query: String, app: AppHandle) -> bool {
+    let handle = app.clone();
+    tasks::blocking_value(&app, "Search packages streaming", move |_| {
+        stream(query, handle);
+        true
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn refresh_package_index(force_full: Option<bool>, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Refresh package index", move |_| {
+        let force_full = force_full.unwrap_or(false);
+        crate::respond(evalpool::run(Priority::Background, move || {
+            progress::track("index_refresh", "Refreshing the package index", || {
+                refresh_index(force_full)
+            })
+        }))
+    })
+    .await
+}
@@ -0,0 +1,152 @@
+// Key material generated on the user's behalf
+//
+// Keys never pass through the frontend: the backend only generates them in
+// place with root-owned permissions and reports where they live.
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::system;
+
+// A passphrase received from the frontend. It can be deserialized but never
+// serialized, and Debug output is redacted so it cannot end up in logs.
+#[derive(Clone, Deserialize)]
+#[serde(transparent)]
+pub struct Passphrase(String);
+
+impl Passphrase {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Passphrase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Passphrase(<redacted>)")
+    }
+}
+
+// Hand a passphrase to a tool that only accepts it as a file: the file lives
+// on the per-user tmpfs, is readable by nobody else and is removed right after
+pub fn with_key_file<T>(
+    passphrase: &Passphrase,
+    f: impl FnOnce(&Path) -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_dir());
+    let Some(runtime_dir) = runtime_dir else {
+        bail!("No private runtime directory (XDG_RUNTIME_DIR) to hand over the key safely");
+    };
+    let path = runtime_dir.join(format!("luminous-key-{}", std::process::id()));
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)?;
+    let written = file.write_all(passphrase.expose().as_bytes());
+    drop(file);
+    let result = written.map_err(anyhow::Error::from).and_then(|_| f(&path));
+    let _ = fs::remove_file(&path);
+    result
+}
+
+// sbctl's default PKI bundle, which lanzaboote reads as `pkiBundle`
+pub const SECURE_BOOT_PKI: &str = "/var/lib/sbctl";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratedKeys {
+    pub location: PathBuf,
+    pub created: bool,
+}
+
+// Run sbctl (through nixpkgs when it is not installed yet), as root unless
+// the subcommand only reads state
+pub fn sbctl(args: &[&str], privileged: bool) -> anyhow::Result<String> {
+    let run = if privileged {
+        system::run_privileged
+    } else {
+        system::run
+    };
+    if system::find_in_path("sbctl").is_some() {
+        return run("sbctl", args);
+    }
+    let mut full = vec!["run", "nixpkgs#sbctl", "--"];
+    full.extend_from_slice(args);
+    run("nix", &full)
+}
+
+pub fn secure_boot_keys_exist() -> bool {
+    let keys = Path::new(SECURE_BOOT_PKI).join("keys");
+    ["PK", "KEK", "db"]
+        .iter()
+        .all(|name| keys.join(name).join(format!("{}.key", name)).exists())
+}
+
+// Create the platform, key-exchange and signature database keys (once)
+pub fn generate_secure_boot_keys() -> anyhow::Result<GeneratedKeys> {
+    if secure_boot_keys_exist() {
+        return Ok(GeneratedKeys {
+            location: PathBuf::from(SECURE_BOOT_PKI),
+            created: false,
+        });
+    }
+    sbctl(&["create-keys"], true)?;
+    if !secure_boot_keys_exist() {
+        bail!(
+            "sbctl finished but the keys are missing from {}",
+            SECURE_BOOT_PKI
+        );
+    }
+    Ok(GeneratedKeys {
+        location: PathBuf::from(SECURE_BOOT_PKI),
+        created: true,
+    })
+}
+
+// Host key baked into the initrd for remote unlock. It sits unencrypted on
+// /boot, so it must never be the machine's regular SSH host key.
+pub const INITRD_HOST_KEY: &str = "/etc/secrets/initrd/ssh_host_ed25519_key";
+
+pub fn generate_initrd_host_key() -> anyhow::Result<GeneratedKeys> {
+    let key = Path::new(INITRD_HOST_KEY);
+    if key.exists() {
+        return Ok(GeneratedKeys {
+            location: key.to_path_buf(),
+            created: false,
+        });
+    }
+    if let Some(dir) = key.parent() {
+        system::run_privileged("install", &["-d", "-m", "0700", &dir.to_string_lossy()])?;
+    }
+    system::run_privileged(
+        "ssh-keygen",
+        &[
+            "-t",
+            "ed25519",
+            "-N",
+            "",
+            "-C",
+            "initrd",
+            "-f",
+            INITRD_HOST_KEY,
+        ],
+    )?;
+    Ok(GeneratedKeys {
+        location: key.to_path_buf(),
+        created: true,
+    })
+}
+
+// SHA256 fingerprint of a public key, for verifying the first connection
+pub fn public_key_fingerprint(public_key: &Path) -> anyhow::Result<String> {
+    // The key directory is root-only
+    let output =
+        system::run_privileged("ssh-keygen", &["-l", "-f", &public_key.to_string_lossy()])?;
+    Ok(output.trim().to_string())
+}
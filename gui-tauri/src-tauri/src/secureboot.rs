@@ -0,0 +1,357 @@
+// Secure Boot setup assistant (lanzaboote)
+//
+// Walks through prerequisites, key generation, NixOS configuration, signature
+// verification, key enrollment and enabling Secure Boot in firmware. Every
+// step verifies its result before the next one unlocks, and the setup can be
+// aborted at any point to return to plain systemd-boot.
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::boot::{self, Bootloader};
+use crate::nixgen::{self, NixModule, NixOption, Target};
+use crate::{secrets, storage, system, tasks};
+
+const STATE_FILE: &str = "secureboot.json";
+const MODULE_NAME: &str = "secureboot";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    Prerequisites,
+    GenerateKeys,
+    Configure,
+    VerifySigning,
+    EnrollKeys,
+    EnableInFirmware,
+}
+
+const STEPS: &[Step] = &[
+    Step::Prerequisites,
+    Step::GenerateKeys,
+    Step::Configure,
+    Step::VerifySigning,
+    Step::EnrollKeys,
+    Step::EnableInFirmware,
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SetupState {
+    pub completed: Vec<Step>,
+    pub module_path: Option<String>,
+    pub log: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Check {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FirmwareStatus {
+    pub installed: bool,
+    pub setup_mode: bool,
+    pub secure_boot: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetupOverview {
+    pub state: SetupState,
+    pub next_step: Option<Step>,
+    pub firmware: FirmwareStatus,
+    pub prerequisites: Vec<Check>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepResult {
+    pub step: Step,
+    pub checks: Vec<Check>,
+    pub next_action: Option<String>,
+}
+
+fn load_state() -> SetupState {
+    storage::load(STATE_FILE).unwrap_or_default()
+}
+
+fn check(name: &str, ok: bool, detail: impl Into<String>) -> Check {
+    Check {
+        name: name.to_string(),
+        ok,
+        detail: detail.into(),
+    }
+}
+
+pub fn firmware_status() -> FirmwareStatus {
+    let Ok(output) = secrets::sbctl(&["status", "--json"], false) else {
+        return FirmwareStatus::default();
+    };
+    serde_json::from_str::<serde_json::Value>(&output)
+        .map(|status| {
+            let flag = |name: &str| status.get(name).and_then(|v| v.as_bool()).unwrap_or(false);
+            FirmwareStatus {
+                installed: flag("installed"),
+                setup_mode: flag("setup_mode"),
+                secure_boot: flag("secure_boot"),
+            }
+        })
+        .unwrap_or_default()
+}
+
+pub fn prerequisites() -> Vec<Check> {
+    let uefi = Path::new("/sys/firmware/efi").exists();
+    let bootloader = boot::detect_bootloader();
+    let generations = boot::list_generations();
+    vec![
+        check(
+            "uefi",
+            uefi,
+            if uefi {
+                "The system booted in UEFI mode"
+            } else {
+                "Secure Boot needs UEFI; this system booted in legacy BIOS mode"
+            },
+        ),
+        check(
+            "systemd-boot",
+            bootloader == Bootloader::SystemdBoot,
+            "lanzaboote replaces systemd-boot; GRUB setups are not supported",
+        ),
+        check(
+            "fallback-generation",
+            generations.len() >= 2,
+            "Keep at least one older generation so you can boot without lanzaboote if needed",
+        ),
+    ]
+}
+
+fn lanzaboote_module() -> NixModule {
+    let mut module = NixModule::new(MODULE_NAME, "Secure Boot via lanzaboote", Target::Nixos);
+    module.set(
+        NixOption::new("boot.loader.systemd-boot.enable", "lib.mkForce false")
+            .with_comment("lanzaboote takes over from systemd-boot"),
+    );
+    module.set(
+        NixOption::new("boot.lanzaboote.enable", nixgen::bool(true))
+            .with_comment("Requires the lanzaboote flake input and its nixosModules.lanzaboote"),
+    );
+    module.set(NixOption::new(
+        "boot.lanzaboote.pkiBundle",
+        nixgen::string(secrets::SECURE_BOOT_PKI),
+    ));
+    module.set(NixOption::new(
+        "environment.systemPackages",
+        "[ pkgs.sbctl ]",
+    ));
+    module
+}
+
+// Files sbctl reports as unsigned; empty means everything on the ESP is signed
+fn unsigned_files() -> anyhow::Result<Vec<String>> {
+    let output = secrets::sbctl(&["verify"], true)?;
+    Ok(output
+        .lines()
+        .filter(|l| l.contains("is not signed"))
+        .map(|l| l.trim_start_matches(['✗', ' ']).to_string())
+        .collect())
+}
+
+fn run(step: Step) -> anyhow::Result<StepResult> {
+    let mut next_action = None;
+    let checks = match step {
+        Step::Prerequisites => prerequisites(),
+        Step::GenerateKeys => {
+            let keys = secrets::generate_secure_boot_keys()?;
+            vec![check(
+                "keys",
+                secrets::secure_boot_keys_exist(),
+                format!(
+                    "{} keys in {}",
+                    if keys.created {
+                        "Created"
+                    } else {
+                        "Reusing existing"
+                    },
+                    keys.location.display()
+                ),
+            )]
+        }
+        Step::Configure => {
+            let path = lanzaboote_module().write()?;
+            next_action = Some(format!(
+                "Import {} in your configuration, add the lanzaboote input, then rebuild",
+                path.display()
+            ));
+            vec![check("module", path.exists(), path.display().to_string())]
+        }
+        Step::VerifySigning => {
+            let unsigned = unsigned_files()?;
+            let detail = if unsigned.is_empty() {
+                "Every boot file is signed".to_string()
+            } else {
+                format!(
+                    "Not signed yet: {}. Rebuild with lanzaboote first",
+                    unsigned.join(", ")
+                )
+            };
+            vec![check("signatures", unsigned.is_empty(), detail)]
+        }
+        Step::EnrollKeys => {
+            if !firmware_status().setup_mode {
+                bail!(
+                    "The firmware is not in Setup Mode. Reboot into the firmware settings, \
+                     clear the Secure Boot keys (Setup Mode), then run this step again"
+                );
+            }
+            // Microsoft's keys keep option ROMs (e.g. GPU firmware) bootable
+            secrets::sbctl(&["enroll-keys", "--microsoft"], true)?;
+            let status = firmware_status();
+            next_action =
+                Some("Reboot into the firmware settings and enable Secure Boot".to_string());
+            vec![check(
+                "enrolled",
+                !status.setup_mode,
+                "Keys are enrolled when the firmware has left Setup Mode",
+            )]
+        }
+        Step::EnableInFirmware => {
+            let status = firmware_status();
+            vec![check(
+                "secure-boot",
+                status.secure_boot,
+                if status.secure_boot {
+                    "Secure Boot is active"
+                } else {
+                    "Secure Boot is still disabled; enable it in the firmware settings and reboot"
+                },
+            )]
+        }
+    };
+    Ok(StepResult {
+        step,
+        checks,
+        next_action,
+    })
+}
+
+pub fn overview() -> SetupOverview {
+    let state = load_state();
+    let next_step = STEPS.iter().copied().find(|s| !state.completed.contains(s));
+    SetupOverview {
+        next_step,
+        firmware: firmware_status(),
+        prerequisites: prerequisites(),
+        state,
+    }
+}
+
+// Run one step; it only unlocks once every earlier step has verified
+pub fn run_step(step: Step) -> anyhow::Result<StepResult> {
+    let mut state = load_state();
+    let position = STEPS.iter().position(|s| *s == step).unwrap_or(0);
+    if let Some(missing) = STEPS[..position]
+        .iter()
+        .find(|s| !state.completed.contains(s))
+    {
+        bail!("Finish the {:?} step first", missing);
+    }
+
+    let result = run(step)?;
+    let passed = result.checks.iter().all(|c| c.ok);
+    state.log.push(format!(
+        "{:?}: {}",
+        step,
+        if passed {
+            "verified"
+        } else {
+            "failed verification"
+        }
+    ));
+    if step == Step::Configure {
+        state.module_path = Some(lanzaboote_module().path().to_string_lossy().into_owned());
+    }
+    if passed && !state.completed.contains(&step) {
+        state.completed.push(step);
+    }
+    storage::save(STATE_FILE, &state)?;
+    if !passed {
+        let failed = result
+            .checks
+            .iter()
+            .find(|c| !c.ok)
+            .ok_or_else(|| anyhow!("Verification failed"))?;
+        bail!("{}", failed.detail);
+    }
+    Ok(result)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbortResult {
+    pub removed_module: bool,
+    pub rebuilt: bool,
+    pub manual_steps: Vec<String>,
+}
+
+// Undo the configuration so the machine goes back to plain systemd-boot
+pub fn abort(rebuild: bool) -> anyhow::Result<AbortResult> {
+    let state = load_state();
+    let module_path = lanzaboote_module().path();
+    let removed_module = module_path.exists();
+    if removed_module {
+        fs::remove_file(&module_path)?;
+    }
+
+    let mut manual_steps = Vec::new();
+    if state.completed.contains(&Step::EnrollKeys) {
+        manual_steps.push(
+            "Your keys are enrolled in firmware: disable Secure Boot there, or restore the factory keys, before booting unsigned kernels"
+                .to_string(),
+        );
+    }
+    if state.completed.contains(&Step::GenerateKeys) {
+        manual_steps.push(format!(
+            "The generated keys remain in {}; keep them if you plan to retry",
+            secrets::SECURE_BOOT_PKI
+        ));
+    }
+    let rebuilt = rebuild && removed_module;
+    if rebuilt {
+        system::run_privileged("nixos-rebuild", &["switch"])?;
+    } else if removed_module {
+        manual_steps.push("Rebuild the system to switch back to systemd-boot".to_string());
+    }
+
+    storage::save(STATE_FILE, &SetupState::default())?;
+    Ok(AbortResult {
+        removed_module,
+        rebuilt,
+        manual_steps,
+    })
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_secure_boot_status(app: AppHandle) -> SetupOverview {
+    tasks::blocking_value(&app, "Get secure boot status", |_| overview()).await
+}
+
+#[tauri::command]
+pub async fn run_secure_boot_step(step: Step, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Run secure boot step", move |_| {
+        crate::respond(run_step(step))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn abort_secure_boot_setup(rebuild: bool, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Abort secure boot setup", move |_| {
+        crate::respond(abort(rebuild))
+    })
+    .await
+}
@@ -0,0 +1,200 @@
+// Service dependency graph
+//
+// Builds the wants/requires/after graph of enabled systemd units with the
+// health of each one, so it is clear why stopping one service takes others
+// down with it.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use tauri::AppHandle;
+
+use crate::{system, tasks};
+
+const PROPERTIES: &str = "Id,Description,ActiveState,SubState,Result,Wants,Requires,BindsTo,After";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Health {
+    Healthy,
+    Starting,
+    Stopping,
+    Inactive,
+    Failed,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    Wants,
+    Requires,
+    BindsTo,
+    After,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceNode {
+    pub id: String,
+    pub description: String,
+    pub health: Health,
+    pub state: String,
+    pub enabled: bool,
+    // Units that stop too when this one stops (Requires/BindsTo dependents)
+    pub cascades_to: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ServiceEdge {
+    pub from: String,
+    pub to: String,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceGraph {
+    pub user: bool,
+    pub nodes: Vec<ServiceNode>,
+    pub edges: Vec<ServiceEdge>,
+}
+
+pub fn systemctl(user: bool, args: &[&str]) -> anyhow::Result<String> {
+    let mut full = Vec::with_capacity(args.len() + 2);
+    if user {
+        full.push("--user");
+    }
+    full.push("--no-pager");
+    full.extend_from_slice(args);
+    system::run("systemctl", &full)
+}
+
+fn enabled_units(user: bool) -> anyhow::Result<Vec<String>> {
+    let output = systemctl(
+        user,
+        &[
+            "list-unit-files",
+            "--state=enabled",
+            "--plain",
+            "--no-legend",
+        ],
+    )?;
+    Ok(output
+        .lines()
+        .filter_map(|l| l.split_whitespace().next())
+        // Template units (foo@.service) have no running instance of their own
+        .filter(|unit| !unit.contains("@."))
+        .map(String::from)
+        .collect())
+}
+
+// `systemctl show a b c` prints one key=value block per unit, blank-line separated
+fn show(user: bool, units: &[String]) -> anyhow::Result<Vec<BTreeMap<String, String>>> {
+    let property = format!("--property={}", PROPERTIES);
+    let mut args = vec!["show", property.as_str()];
+    args.extend(units.iter().map(String::as_str));
+    let output = systemctl(user, &args)?;
+    Ok(output
+        .split("\n\n")
+        .map(|block| {
+            block
+                .lines()
+                .filter_map(|l| l.split_once('='))
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<BTreeMap<_, _>>()
+        })
+        .filter(|props| props.contains_key("Id"))
+        .collect())
+}
+
+pub fn health(active: &str, result: &str) -> Health {
+    match active {
+        "active" | "reloading" => Health::Healthy,
+        "activating" => Health::Starting,
+        "deactivating" => Health::Stopping,
+        "failed" => Health::Failed,
+        "inactive" if result != "success" && !result.is_empty() => Health::Failed,
+        "inactive" => Health::Inactive,
+        _ => Health::Unknown,
+    }
+}
+
+pub fn graph(user: bool) -> anyhow::Result<ServiceGraph> {
+    let enabled: BTreeSet<String> = enabled_units(user)?.into_iter().collect();
+    let units: Vec<String> = enabled.iter().cloned().collect();
+    let shown = if units.is_empty() {
+        Vec::new()
+    } else {
+        show(user, &units)?
+    };
+
+    let mut nodes: BTreeMap<String, ServiceNode> = BTreeMap::new();
+    let mut edges: BTreeSet<ServiceEdge> = BTreeSet::new();
+    for props in &shown {
+        let get = |key: &str| props.get(key).cloned().unwrap_or_default();
+        let id = get("Id");
+        for (key, kind) in [
+            ("Wants", EdgeKind::Wants),
+            ("Requires", EdgeKind::Requires),
+            ("BindsTo", EdgeKind::BindsTo),
+            ("After", EdgeKind::After),
+        ] {
+            for target in get(key).split_whitespace() {
+                // Keep the graph readable: only edges between the units we show
+                if enabled.contains(target) {
+                    edges.insert(ServiceEdge {
+                        from: id.clone(),
+                        to: target.to_string(),
+                        kind,
+                    });
+                }
+            }
+        }
+        nodes.insert(
+            id.clone(),
+            ServiceNode {
+                health: health(&get("ActiveState"), &get("Result")),
+                state: format!("{} ({})", get("ActiveState"), get("SubState")),
+                description: get("Description"),
+                enabled: true,
+                cascades_to: Vec::new(),
+                id,
+            },
+        );
+    }
+
+    // Requires/BindsTo edges point at what a unit depends on; follow them
+    // backwards (transitively) to see who goes down when a unit stops
+    let mut dependents: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for edge in &edges {
+        if matches!(edge.kind, EdgeKind::Requires | EdgeKind::BindsTo) {
+            dependents.entry(&edge.to).or_default().push(&edge.from);
+        }
+    }
+    for (id, node) in nodes.iter_mut() {
+        let mut seen: BTreeSet<&str> = BTreeSet::new();
+        let mut queue: Vec<&str> = vec![id.as_str()];
+        while let Some(unit) = queue.pop() {
+            for dependent in dependents.get(unit).into_iter().flatten() {
+                if *dependent != id.as_str() && seen.insert(dependent) {
+                    queue.push(dependent);
+                }
+            }
+        }
+        node.cascades_to = seen.into_iter().map(String::from).collect();
+    }
+
+    Ok(ServiceGraph {
+        user,
+        nodes: nodes.into_values().collect(),
+        edges: edges.into_iter().collect(),
+    })
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn service_graph(user: Option<bool>, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Service graph", move |_| {
+        crate::respond(graph(user.unwrap_or(false)))
+    })
+    .await
+}
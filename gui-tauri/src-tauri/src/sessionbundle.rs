@@ -0,0 +1,321 @@
+// Session export and import: everything personal in one file
+//
+// For moving to another machine or keeping a copy before a reinstall. The
+// bundle holds the user profile, the saved layouts and layout presets, the
+// theme, the aliases and, when asked for, the history. Importing merges it into
+// what is here: new items are added, identical ones left alone, and items that
+// exist on both sides with different content are conflicts, settled one by one
+// or all the same way (keeping what is here unless told otherwise). A dry run
+// reports what an import would do without changing anything.
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::history::HistoryEntry;
+use crate::layouts::LayoutPreset;
+use crate::userprofile::{self, UserProfile};
+use crate::{
+    aliases, clock, history, layouts, managed, privacy, sessions, shortcuts, storage, tasks,
+    themes, AppState,
+};
+
+const FORMAT: &str = "luminous-nix-session";
+const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionBundle {
+    pub format: String,
+    pub schema_version: u32,
+    pub exported_at: u64,
+    pub exported_by: String,
+    // Kept as written, so an older profile or theme is migrated on import
+    pub profile: Option<serde_json::Value>,
+    pub layouts: Vec<serde_json::Value>,
+    pub layout_presets: Vec<LayoutPreset>,
+    pub theme: Option<serde_json::Value>,
+    pub aliases: Vec<aliases::Alias>,
+    // Only when the export included it
+    pub history: Option<Vec<HistoryEntry>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Resolution {
+    #[default]
+    KeepExisting,
+    UseImported,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportOptions {
+    // For every conflict without a choice of its own
+    pub resolution: Resolution,
+    // By item id: "profile", "theme", "layout:<id>", "preset:<id>", "alias:<phrase>"
+    pub choices: BTreeMap<String, Resolution>,
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Added,
+    Unchanged,
+    // A conflict, settled one way or the other
+    KeptExisting,
+    Replaced,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportItem {
+    pub id: String,
+    pub outcome: Outcome,
+    // Why it was skipped
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub items: Vec<ImportItem>,
+    pub history_added: usize,
+    pub dry_run: bool,
+}
+
+pub fn export(state: &AppState, path: &Path, include_history: bool) -> anyhow::Result<()> {
+    let profile = state.user_profile.blocking_lock().clone();
+    let bundle = SessionBundle {
+        format: FORMAT.to_string(),
+        schema_version: SCHEMA_VERSION,
+        exported_at: clock::now(),
+        exported_by: sessions::current().user.clone(),
+        profile: profile.map(serde_json::to_value).transpose()?,
+        layouts: layouts::stored()?,
+        layout_presets: layouts::presets()?,
+        theme: Some(serde_json::to_value(themes::active())?),
+        aliases: aliases::list(),
+        history: include_history.then(history::load),
+    };
+    storage::write_json(path, &bundle)
+}
+
+struct Merge<'a> {
+    options: &'a ImportOptions,
+    report: ImportReport,
+}
+
+impl Merge<'_> {
+    // Record what happens to one item; true when the imported one goes in
+    fn resolve(
+        &mut self,
+        id: String,
+        existing: Option<&serde_json::Value>,
+        imported: &serde_json::Value,
+    ) -> bool {
+        let outcome = match existing {
+            None => Outcome::Added,
+            Some(existing) if existing == imported => Outcome::Unchanged,
+            Some(_) => match self
+                .options
+                .choices
+                .get(&id)
+                .copied()
+                .unwrap_or(self.options.resolution)
+            {
+                Resolution::KeepExisting => Outcome::KeptExisting,
+                Resolution::UseImported => Outcome::Replaced,
+            },
+        };
+        self.report.items.push(ImportItem {
+            id,
+            outcome,
+            detail: None,
+        });
+        matches!(outcome, Outcome::Added | Outcome::Replaced)
+    }
+
+    fn skip(&mut self, id: String, detail: String) {
+        self.report.items.push(ImportItem {
+            id,
+            outcome: Outcome::Skipped,
+            detail: Some(detail),
+        });
+    }
+
+    // Items matched by key; true when any went in
+    fn keyed<T: Serialize>(
+        &mut self,
+        kind: &str,
+        existing: &mut Vec<T>,
+        imported: Vec<T>,
+        key: impl Fn(&T) -> String,
+    ) -> anyhow::Result<bool> {
+        let mut changed = false;
+        for item in imported {
+            let name = key(&item);
+            let position = existing.iter().position(|e| key(e) == name);
+            let current = position
+                .map(|i| serde_json::to_value(&existing[i]))
+                .transpose()?;
+            let id = format!("{}:{}", kind, name);
+            if self.resolve(id, current.as_ref(), &serde_json::to_value(&item)?) {
+                changed = true;
+                match position {
+                    Some(i) => existing[i] = item,
+                    None => existing.push(item),
+                }
+            }
+        }
+        Ok(changed)
+    }
+}
+
+// Two profiles differ in what they hold, not in when they were saved
+fn comparable(profile: &UserProfile) -> anyhow::Result<serde_json::Value> {
+    let mut value = serde_json::to_value(profile)?;
+    value["updated_at"] = serde_json::json!(0);
+    Ok(value)
+}
+
+pub fn import(
+    app: &AppHandle,
+    path: &Path,
+    options: &ImportOptions,
+) -> anyhow::Result<ImportReport> {
+    if !path.exists() {
+        bail!("{} doesn't exist", path.display());
+    }
+    let bundle: SessionBundle = storage::read_json(path)?;
+    if bundle.format != FORMAT {
+        bail!("{} isn't a Luminous Nix session export", path.display());
+    }
+    if bundle.schema_version > SCHEMA_VERSION {
+        bail!(
+            "{} comes from a newer version of Luminous Nix",
+            path.display()
+        );
+    }
+    let write = !options.dry_run;
+    let mut merge = Merge {
+        options,
+        report: ImportReport {
+            dry_run: options.dry_run,
+            ..ImportReport::default()
+        },
+    };
+
+    if let Some(doc) = bundle.profile {
+        let imported = userprofile::migrate(doc).context("Importing the profile")?;
+        let state = app.state::<AppState>();
+        let current = state.user_profile.blocking_lock().clone();
+        let current = current.as_ref().map(comparable).transpose()?;
+        if merge.resolve(
+            "profile".to_string(),
+            current.as_ref(),
+            &comparable(&imported)?,
+        ) && write
+        {
+            userprofile::save(&imported)?;
+            *state.user_profile.blocking_lock() = Some(imported);
+            shortcuts::register_global(app);
+        }
+    }
+
+    if let Some(doc) = bundle.theme {
+        let imported = themes::migrate(doc).context("Importing the theme")?;
+        // The default theme isn't a choice anyone made
+        let current = Some(serde_json::to_value(themes::active())?)
+            .filter(|t| *t != serde_json::to_value(themes::Theme::default()).unwrap_or_default());
+        if merge.resolve(
+            "theme".to_string(),
+            current.as_ref(),
+            &serde_json::to_value(&imported)?,
+        ) && write
+        {
+            themes::set(app, imported)?;
+        }
+    }
+
+    let mut saved = layouts::stored()?;
+    let mut valid = Vec::new();
+    for value in bundle.layouts {
+        let id = value["id"].as_str().unwrap_or_default().to_string();
+        match layouts::check(&value) {
+            Ok(_) => valid.push(value),
+            Err(problems) => merge.skip(format!("layout:{}", id), problems.join("; ")),
+        }
+    }
+    let key = |v: &serde_json::Value| v["id"].as_str().unwrap_or_default().to_string();
+    if merge.keyed("layout", &mut saved, valid, key)? && write {
+        layouts::set_stored(&saved)?;
+    }
+
+    let mut presets = layouts::presets()?;
+    if merge.keyed("preset", &mut presets, bundle.layout_presets, |p| {
+        p.id.clone()
+    })? && write
+    {
+        layouts::set_presets(&presets)?;
+    }
+
+    let mut current = aliases::list();
+    if merge.keyed("alias", &mut current, bundle.aliases, |a| a.phrase.clone())? && write {
+        aliases::set_all(current)?;
+    }
+
+    if let Some(entries) = bundle.history {
+        if privacy::allowed(privacy::Collector::History) {
+            let (merged, added) = history::merge(&entries);
+            if added > 0 && write {
+                history::replace(&merged)?;
+            }
+            merge.report.history_added = added;
+        } else {
+            merge.skip(
+                "history".to_string(),
+                "History is turned off in the privacy settings".to_string(),
+            );
+        }
+    }
+    Ok(merge.report)
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn export_session(
+    path: String,
+    include_history: Option<bool>,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Export session", move |state| {
+        let path = PathBuf::from(path);
+        crate::respond(export(state, &path, include_history.unwrap_or(false)).map(|()| path))
+    })
+    .await
+}
+
+// Merge an exported session into this one; with `dry_run` only the report
+#[tauri::command]
+pub async fn import_session(
+    path: String,
+    options: Option<ImportOptions>,
+    app: AppHandle,
+) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Import session", move |_| {
+        if let Some(refusal) = managed::guard("import_session", "Import a session bundle") {
+            return refusal;
+        }
+        crate::respond(import(
+            &handle,
+            Path::new(&path),
+            &options.unwrap_or_default(),
+        ))
+    })
+    .await
+}
@@ -0,0 +1,464 @@
+// Concurrent sessions on a shared machine
+//
+// Every running instance is one session of one local user. Conversation,
+// interaction history and the rest of AppState live in that process, and
+// personalization lives in that user's data dir, so two people (or a user and
+// an admin) never see each other's context. What they do share is the system:
+// every privileged command, and every intent that runs several of them like an
+// update, holds a machine-wide lock while it runs. A session finding the lock
+// taken waits and says whose operation it is waiting for. Each operation is
+// appended to the audit log with the user and session that ran it.
+//
+// Sessions of different users meet in SHARED_DIR, which root sets up (the
+// NixOS module does, with tmpfiles). Without it each user's sessions meet in
+// their own runtime dir and only wait for each other. Files there are created
+// with O_EXCL|O_NOFOLLOW and only trusted when their owner is the user they
+// claim to be from, so nobody can point another user's writes elsewhere.
+
+use anyhow::{anyhow, bail, Context};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{ErrorKind, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+use crate::{clock, progress, storage, system, tasks};
+
+// Root-owned and sticky, with a root-owned lock file in it
+const SHARED_DIR: &str = "/run/luminous-nix";
+const LOCK_FILE: &str = "system.lock";
+const SESSION_PREFIX: &str = "session-";
+// Who holds the lock, written by the holder next to its session file
+const HOLDER_PREFIX: &str = "holder-";
+pub const AUDIT_FILE: &str = "audit.jsonl";
+// Shared by every user when an admin has created it writable for them;
+// otherwise each user's operations are logged in their own data dir
+const SYSTEM_AUDIT_DIR: &str = "/var/log/luminous-nix";
+const MAX_WAIT: Duration = Duration::from_secs(15 * 60);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    pub user: String,
+    pub uid: u32,
+    pub pid: u32,
+    pub started_at: u64,
+}
+
+// Written by whoever holds the lock
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Holder {
+    pub session: Session,
+    pub operation: String,
+    pub since: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: u64,
+    pub user: String,
+    pub uid: u32,
+    pub session: String,
+    pub operation: String,
+    // Privileged commands run as part of the operation
+    pub commands: Vec<String>,
+    // Time spent waiting for another session's operation
+    pub waited_secs: u64,
+    pub duration_secs: u64,
+    pub succeeded: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionsStatus {
+    pub current: Session,
+    // Other live sessions on this machine, of any user
+    pub others: Vec<Session>,
+    pub lock_holder: Option<Holder>,
+    // False when the data dir belongs to someone else (sudo keeping HOME, say);
+    // learning then stays off instead of writing into their profile
+    pub isolated: bool,
+    pub audit_log: PathBuf,
+}
+
+static CURRENT: OnceLock<Session> = OnceLock::new();
+
+thread_local! {
+    // Commands of the operation this thread holds the lock for
+    static HELD: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+pub fn current() -> &'static Session {
+    CURRENT.get_or_init(|| {
+        let pid = std::process::id();
+        let user = system::username();
+        Session {
+            id: format!("{}-{}", user, pid),
+            user,
+            uid: fs::metadata("/proc/self").map(|m| m.uid()).unwrap_or(0),
+            pid,
+            started_at: clock::now(),
+        }
+    })
+}
+
+// A real directory of `owner` in which nobody else can replace entries: not
+// writable by others, or sticky
+fn trusted(dir: &Path, owner: u32) -> bool {
+    fs::symlink_metadata(dir).is_ok_and(|m| {
+        m.is_dir() && m.uid() == owner && (m.mode() & 0o022 == 0 || m.mode() & 0o1000 != 0)
+    })
+}
+
+fn is_shared(dir: &Path) -> bool {
+    dir == Path::new(SHARED_DIR)
+}
+
+// Where sessions meet: SHARED_DIR for every user, or else a private dir in
+// this user's runtime dir
+fn shared_dir() -> anyhow::Result<PathBuf> {
+    if trusted(Path::new(SHARED_DIR), 0) {
+        return Ok(PathBuf::from(SHARED_DIR));
+    }
+    let uid = current().uid;
+    let runtime = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| trusted(dir, uid))
+        .ok_or_else(|| {
+            anyhow!(
+                "Neither {} nor a private runtime directory (XDG_RUNTIME_DIR) is set up",
+                SHARED_DIR
+            )
+        })?;
+    let dir = runtime.join("luminous-nix");
+    match fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e).with_context(|| format!("creating {}", dir.display())),
+    }
+    if !trusted(&dir, uid) {
+        bail!(
+            "{} isn't a private directory of {}",
+            dir.display(),
+            current().user
+        );
+    }
+    Ok(dir)
+}
+
+// Create a file afresh, replacing a stale one of this user; O_EXCL and
+// O_NOFOLLOW refuse whatever someone else put there, symlinks included
+fn create(path: &Path, mode: u32) -> anyhow::Result<File> {
+    if let Ok(meta) = fs::symlink_metadata(path) {
+        if meta.uid() != current().uid {
+            bail!("{} belongs to another user", path.display());
+        }
+        fs::remove_file(path).with_context(|| format!("removing {}", path.display()))?;
+    }
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(mode)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(path)
+        .with_context(|| format!("creating {}", path.display()))
+}
+
+// The live sessions' files starting with `prefix`; a file only counts when it
+// belongs to the user it claims to be from
+fn read_all<T: DeserializeOwned>(prefix: &str, session: impl Fn(&T) -> &Session) -> Vec<T> {
+    let Ok(entries) = shared_dir().and_then(|dir| Ok(fs::read_dir(dir)?)) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().starts_with(prefix))
+        .filter_map(|e| {
+            // Not following symlinks
+            let meta = e.metadata().ok()?;
+            let item: T = serde_json::from_slice(&fs::read(e.path()).ok()?).ok()?;
+            let owner = session(&item);
+            // Files of sessions that have exited stay behind until their pid is reused
+            (meta.is_file() && meta.uid() == owner.uid && alive(owner.pid)).then_some(item)
+        })
+        .collect()
+}
+
+pub fn alive(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+// Announce this session to the others; called once at startup
+pub fn register() -> anyhow::Result<()> {
+    let session = current();
+    let path = shared_dir()?.join(format!("{}{}.json", SESSION_PREFIX, session.pid));
+    create(&path, 0o644)?
+        .write_all(&serde_json::to_vec(session)?)
+        .with_context(|| format!("writing {}", path.display()))
+}
+
+pub fn others() -> Vec<Session> {
+    read_all(SESSION_PREFIX, |session: &Session| session)
+        .into_iter()
+        .filter(|s| s.pid != current().pid)
+        .collect()
+}
+
+// Whether personalization read and written here belongs to this session's user
+pub fn isolated() -> bool {
+    fs::metadata(storage::data_dir()).map_or(true, |m| m.uid() == current().uid)
+}
+
+fn holder_path() -> anyhow::Result<PathBuf> {
+    Ok(shared_dir()?.join(format!("{}{}.json", HOLDER_PREFIX, current().pid)))
+}
+
+pub fn holder() -> Option<Holder> {
+    read_all(HOLDER_PREFIX, |holder: &Holder| &holder.session)
+        .into_iter()
+        .next()
+}
+
+// Only ever read: the lock is flock(2) on it. In SHARED_DIR root creates it,
+// so no user can make it unopenable for the others
+fn open_lock() -> anyhow::Result<File> {
+    let dir = shared_dir()?;
+    let path = dir.join(LOCK_FILE);
+    let file = match OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(&path)
+    {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound && !is_shared(&dir) => create(&path, 0o600)?,
+        Err(e) => return Err(e).with_context(|| format!("opening {}", path.display())),
+    };
+    let meta = file.metadata()?;
+    let owner = if is_shared(&dir) { 0 } else { current().uid };
+    if !meta.is_file() || meta.uid() != owner {
+        bail!(
+            "{} isn't a lock file this session can trust",
+            path.display()
+        );
+    }
+    Ok(file)
+}
+
+fn describe(holder: Option<Holder>) -> String {
+    match holder {
+        Some(h) if h.session.pid == current().pid => {
+            format!("another task in this session (\"{}\")", h.operation)
+        }
+        Some(h) => format!("{}'s session (\"{}\")", h.session.user, h.operation),
+        None => "another session".to_string(),
+    }
+}
+
+fn acquire(operation: &str) -> anyhow::Result<File> {
+    let file = open_lock()?;
+    let started = Instant::now();
+    let mut waiting: Option<progress::Reporter> = None;
+    loop {
+        match file.try_lock() {
+            Ok(()) => break,
+            Err(TryLockError::WouldBlock) => {
+                let message = format!("Waiting for {} to finish", describe(holder()));
+                if started.elapsed() >= MAX_WAIT {
+                    if let Some(reporter) = waiting.take() {
+                        reporter.fail(&message);
+                    }
+                    bail!(
+                        "The system is busy with {}; try again when it finishes",
+                        describe(holder())
+                    );
+                }
+                waiting.get_or_insert_with(|| {
+                    progress::Reporter::start("wait-for-lock", &message, None, false)
+                });
+                std::thread::sleep(POLL_INTERVAL);
+            }
+            Err(TryLockError::Error(e)) => return Err(e).context("taking the system lock"),
+        }
+    }
+    if let Some(reporter) = waiting {
+        reporter.succeed(&format!("Starting \"{}\"", operation));
+    }
+    let holder = Holder {
+        session: current().clone(),
+        operation: operation.to_string(),
+        since: clock::now(),
+    };
+    create(&holder_path()?, 0o644)?.write_all(&serde_json::to_vec(&holder)?)?;
+    Ok(file)
+}
+
+// Marks this thread as holding the lock until dropped. A panicking operation
+// unmarks it too, or the pool thread would skip the lock on every later call.
+struct Holding;
+
+impl Holding {
+    fn start() -> Holding {
+        HELD.with(|held| *held.borrow_mut() = Some(Vec::new()));
+        Holding
+    }
+
+    // The commands run while holding it
+    fn finish(self) -> Vec<String> {
+        HELD.with(|held| held.borrow_mut().take())
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for Holding {
+    fn drop(&mut self) {
+        HELD.with(|held| held.borrow_mut().take());
+    }
+}
+
+// Run a system mutation while holding the machine-wide lock, and audit it.
+// Nested calls on the same thread join the operation already holding it.
+pub fn exclusive<T>(operation: &str, f: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    if HELD.with(|held| held.borrow().is_some()) {
+        return f();
+    }
+    let started = Instant::now();
+    let file = acquire(operation)?;
+    let waited = started.elapsed();
+    let holding = Holding::start();
+    let result = f();
+    let commands = holding.finish();
+    if let Ok(path) = holder_path() {
+        let _ = fs::remove_file(path);
+    }
+    drop(file);
+
+    let session = current();
+    let entry = AuditEntry {
+        timestamp: clock::now(),
+        user: session.user.clone(),
+        uid: session.uid,
+        session: session.id.clone(),
+        operation: operation.to_string(),
+        commands,
+        waited_secs: waited.as_secs(),
+        duration_secs: (started.elapsed() - waited).as_secs(),
+        succeeded: result.is_ok(),
+        error: result.as_ref().err().map(|e| e.to_string()),
+    };
+    if let Err(e) = audit(&entry) {
+        eprintln!("Could not write the audit log: {}", e);
+    }
+    result
+}
+
+// A privileged command, as its own operation or as a step of the one held
+pub fn command<T>(command: &str, f: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    exclusive(command, || {
+        HELD.with(|held| {
+            if let Some(commands) = held.borrow_mut().as_mut() {
+                commands.push(command.to_string());
+            }
+        });
+        f()
+    })
+}
+
+fn audit_path() -> PathBuf {
+    let shared = Path::new(SYSTEM_AUDIT_DIR).join(AUDIT_FILE);
+    let writable = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&shared)
+        .is_ok();
+    if writable {
+        shared
+    } else {
+        storage::data_dir().join(AUDIT_FILE)
+    }
+}
+
+fn audit(entry: &AuditEntry) -> anyhow::Result<()> {
+    let path = audit_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&path)
+        .with_context(|| format!("opening {}", path.display()))?;
+    // One line per entry, so concurrent appends from several sessions don't interleave
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+    file.write_all(&line)?;
+    Ok(())
+}
+
+// The most recent `limit` entries, oldest first
+pub fn audit_log(limit: usize) -> Vec<AuditEntry> {
+    let text = fs::read_to_string(audit_path()).unwrap_or_default();
+    let entries: Vec<AuditEntry> = text
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    entries[entries.len().saturating_sub(limit)..].to_vec()
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_sessions(app: AppHandle) -> SessionsStatus {
+    tasks::blocking_value(&app, "Get sessions", |_| SessionsStatus {
+        current: current().clone(),
+        others: others(),
+        lock_holder: holder(),
+        isolated: isolated(),
+        audit_log: audit_path(),
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_audit_log(limit: Option<usize>, app: AppHandle) -> Vec<AuditEntry> {
+    tasks::blocking_value(&app, "Get audit log", move |_| {
+        audit_log(limit.unwrap_or(100))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn holding() -> bool {
+        HELD.with(|held| held.borrow().is_some())
+    }
+
+    #[test]
+    fn commands_join_the_operation_already_holding_the_lock() {
+        let holding = Holding::start();
+        command("nix-collect-garbage", || Ok(())).unwrap();
+        command("nixos-rebuild switch", || Ok(())).unwrap();
+        assert_eq!(
+            holding.finish(),
+            vec!["nix-collect-garbage", "nixos-rebuild switch"]
+        );
+        assert!(!holding());
+    }
+
+    #[test]
+    fn a_panicking_operation_stops_holding_the_lock() {
+        let result = std::panic::catch_unwind(|| {
+            let _holding = Holding::start();
+            panic!("operation failed");
+        });
+        assert!(result.is_err());
+        assert!(!holding());
+    }
+}
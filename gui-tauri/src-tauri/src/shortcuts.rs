@@ -0,0 +1,511 @@
+// Keyboard shortcuts: built-in defaults, per-profile overrides, and conflicts
+//
+// Every app action has a default accelerator; the user's changes are stored
+// as overrides in the profile's preferences, so they travel with an exported
+// profile. Some actions work from anywhere by default; a few more (focusing
+// the search box, toggling voice input) can be made global per profile. Global
+// shortcuts are registered with the OS and re-registered after every change. A new binding is checked against the app's other bindings
+// (refused) and against the desktop environment's own shortcuts where those
+// can be read: GNOME (gsettings), KDE (kglobalshortcutsrc) and sway/i3
+// configs (flagged, and only saved when forced).
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+
+use crate::userprofile::{self, UserProfile};
+use crate::{managed, system, tasks, voice, AppState};
+
+const PREFERENCE_KEY: &str = "shortcuts";
+const GLOBAL_PREFERENCE_KEY: &str = "global_shortcuts";
+
+// (action, description, default accelerator, global)
+const ACTIONS: &[(&str, &str, &str, bool)] = &[
+    ("focus-search", "Jump to the search box", "Ctrl+K", false),
+    (
+        "voice-input",
+        "Start or stop voice input",
+        "Ctrl+Shift+V",
+        false,
+    ),
+    ("switch-layout", "Cycle through layouts", "Ctrl+L", false),
+    ("open-settings", "Open settings", "Ctrl+Comma", false),
+    ("undo-last", "Undo the last change", "Ctrl+Z", false),
+    (
+        "cancel-operation",
+        "Cancel the running operation",
+        "Escape",
+        false,
+    ),
+    (
+        "begin-pause",
+        "Take a breathing pause",
+        "Ctrl+Shift+P",
+        false,
+    ),
+    (
+        "summon",
+        "Show Luminous Nix from anywhere",
+        "Super+Shift+N",
+        true,
+    ),
+    (
+        "quick-search",
+        "Search packages from anywhere",
+        "Ctrl+Alt+Space",
+        true,
+    ),
+    (
+        "push-to-talk",
+        "Hold to speak into the search box, from anywhere",
+        "Ctrl+Alt+V",
+        true,
+    ),
+];
+
+// Actions that mean something with the window hidden, so may be global
+const CAN_BE_GLOBAL: &[&str] = &[
+    "focus-search",
+    "voice-input",
+    "summon",
+    "quick-search",
+    "push-to-talk",
+];
+
+const MODIFIERS: [&str; 4] = ["Ctrl", "Alt", "Shift", "Super"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conflict {
+    // "app" for another action here, otherwise the desktop environment
+    pub source: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Binding {
+    pub action: String,
+    pub description: String,
+    pub accelerator: String,
+    pub default: String,
+    pub global: bool,
+    pub can_be_global: bool,
+    pub conflicts: Vec<Conflict>,
+}
+
+// "ctrl + shift + k", "<Control><Shift>k", "Meta+K" -> "Ctrl+Shift+K"
+pub fn normalize(accelerator: &str) -> anyhow::Result<String> {
+    let mut text = accelerator.trim().to_string();
+    // GNOME's <Primary><Shift>k form
+    if text.starts_with('<') {
+        text = text.replace('>', "+").replace('<', "");
+    }
+    let mut modifiers = Vec::new();
+    let mut key = None;
+    for part in text.split('+').map(str::trim).filter(|p| !p.is_empty()) {
+        let modifier = match part.to_lowercase().as_str() {
+            "ctrl" | "control" | "primary" | "cmdorctrl" | "commandorcontrol" => Some("Ctrl"),
+            "alt" | "option" | "mod1" => Some("Alt"),
+            "shift" => Some("Shift"),
+            "super" | "meta" | "win" | "cmd" | "command" | "mod4" | "logo" => Some("Super"),
+            _ => None,
+        };
+        match modifier {
+            Some(m) if !modifiers.contains(&m) => modifiers.push(m),
+            Some(_) => {}
+            None if key.is_none() => key = Some(part),
+            None => bail!("'{}' has more than one key", accelerator),
+        }
+    }
+    let key = key.ok_or_else(|| anyhow!("'{}' has no key, only modifiers", accelerator))?;
+    let key = match key.to_lowercase().as_str() {
+        "," | "comma" => "Comma".to_string(),
+        "." | "period" => "Period".to_string(),
+        " " | "space" => "Space".to_string(),
+        "esc" | "escape" => "Escape".to_string(),
+        "return" | "enter" => "Enter".to_string(),
+        k if k.chars().count() == 1 => k.to_uppercase(),
+        k => {
+            let mut chars = k.chars();
+            let first = chars.next().map(|c| c.to_uppercase().to_string());
+            first.unwrap_or_default() + chars.as_str()
+        }
+    };
+    let mut parts: Vec<&str> = MODIFIERS
+        .iter()
+        .copied()
+        .filter(|m| modifiers.contains(m))
+        .collect();
+    parts.push(&key);
+    Ok(parts.join("+"))
+}
+
+fn overrides(profile: &UserProfile) -> BTreeMap<String, String> {
+    profile
+        .preferences
+        .get(PREFERENCE_KEY)
+        .and_then(|o| serde_json::from_value(o.clone()).ok())
+        .unwrap_or_default()
+}
+
+// Actions made global, or made local, against their default
+fn scopes(profile: &UserProfile) -> BTreeMap<String, bool> {
+    profile
+        .preferences
+        .get(GLOBAL_PREFERENCE_KEY)
+        .and_then(|o| serde_json::from_value(o.clone()).ok())
+        .unwrap_or_default()
+}
+
+fn store(
+    profile: &mut UserProfile,
+    overrides: &BTreeMap<String, String>,
+    scopes: &BTreeMap<String, bool>,
+) -> anyhow::Result<()> {
+    if !profile.preferences.is_object() {
+        profile.preferences = serde_json::json!({});
+    }
+    profile.preferences[PREFERENCE_KEY] = serde_json::to_value(overrides)?;
+    profile.preferences[GLOBAL_PREFERENCE_KEY] = serde_json::to_value(scopes)?;
+    userprofile::save(profile)
+}
+
+// ========== Desktop environment shortcuts ==========
+
+// GNOME stores lists like ['<Super>Left', '<Primary><Alt>t']
+fn gnome() -> Vec<(String, String)> {
+    let mut found = Vec::new();
+    for schema in [
+        "org.gnome.desktop.wm.keybindings",
+        "org.gnome.shell.keybindings",
+        "org.gnome.settings-daemon.plugins.media-keys",
+        "org.gnome.mutter.keybindings",
+    ] {
+        let Ok(output) = system::run("gsettings", &["list-recursively", schema]) else {
+            continue;
+        };
+        for line in output.lines() {
+            let mut fields = line.splitn(3, ' ');
+            let (Some(_), Some(name), Some(value)) = (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            for accelerator in value
+                .trim_matches(|c| c == '[' || c == ']')
+                .split(',')
+                .map(|a| a.trim().trim_matches('\''))
+                .filter(|a| a.starts_with('<'))
+            {
+                if let Ok(accelerator) = normalize(accelerator) {
+                    found.push((accelerator, format!("GNOME: {}", name)));
+                }
+            }
+        }
+    }
+    found
+}
+
+// name=Meta+E\tAlt+F2,default,Friendly Name
+fn kde() -> Vec<(String, String)> {
+    let path = system::xdg_config_home().join("kglobalshortcutsrc");
+    let Ok(text) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let mut found = Vec::new();
+    for line in text.lines() {
+        let Some((name, value)) = line.split_once('=') else {
+            continue;
+        };
+        if name.starts_with("_k_") {
+            continue;
+        }
+        let mut fields = value.split(',');
+        let active = fields.next().unwrap_or("");
+        let label = fields.nth(1).unwrap_or(name);
+        for accelerator in active.split('\t').filter(|a| !a.is_empty() && *a != "none") {
+            if let Ok(accelerator) = normalize(accelerator) {
+                found.push((accelerator, format!("KDE: {}", label)));
+            }
+        }
+    }
+    found
+}
+
+// bindsym $mod+Return exec foot
+fn sway_or_i3() -> Vec<(String, String)> {
+    let config = system::xdg_config_home();
+    let mut found = Vec::new();
+    for (wm, path) in [
+        ("sway", config.join("sway/config")),
+        ("i3", config.join("i3/config")),
+    ] {
+        let Ok(text) = fs::read_to_string(path) else {
+            continue;
+        };
+        let mut variables = BTreeMap::new();
+        for line in text.lines().map(str::trim) {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                ["set", name, value, ..] => {
+                    variables.insert(name.to_string(), value.to_string());
+                }
+                ["bindsym", rest @ ..] => {
+                    let Some(keys) = rest.iter().find(|w| !w.starts_with("--")) else {
+                        continue;
+                    };
+                    let mut keys = keys.to_string();
+                    for (name, value) in &variables {
+                        keys = keys.replace(name.as_str(), value);
+                    }
+                    if let Ok(accelerator) = normalize(&keys) {
+                        found.push((accelerator, format!("{}: {}", wm, line)));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    found
+}
+
+pub fn desktop_shortcuts() -> Vec<(String, String)> {
+    let mut all = gnome();
+    all.extend(kde());
+    all.extend(sway_or_i3());
+    all
+}
+
+// ========== Bindings ==========
+
+// Each action's accelerator and scope, before looking for conflicts
+fn assign(overrides: &BTreeMap<String, String>, scopes: &BTreeMap<String, bool>) -> Vec<Binding> {
+    ACTIONS
+        .iter()
+        .map(|(action, description, default, global)| Binding {
+            action: action.to_string(),
+            description: description.to_string(),
+            accelerator: overrides
+                .get(*action)
+                .cloned()
+                .unwrap_or_else(|| default.to_string()),
+            default: default.to_string(),
+            global: scopes.get(*action).copied().unwrap_or(*global),
+            can_be_global: CAN_BE_GLOBAL.contains(action),
+            conflicts: Vec::new(),
+        })
+        .collect()
+}
+
+fn resolve(overrides: &BTreeMap<String, String>, scopes: &BTreeMap<String, bool>) -> Vec<Binding> {
+    let desktop = desktop_shortcuts();
+    let mut bindings = assign(overrides, scopes);
+    let accelerators: Vec<(String, String)> = bindings
+        .iter()
+        .map(|b| (b.action.clone(), b.accelerator.clone()))
+        .collect();
+    for binding in bindings.iter_mut() {
+        for (action, accelerator) in &accelerators {
+            if *action != binding.action && *accelerator == binding.accelerator {
+                binding.conflicts.push(Conflict {
+                    source: "app".to_string(),
+                    name: action.clone(),
+                });
+            }
+        }
+        for (accelerator, name) in &desktop {
+            if *accelerator == binding.accelerator {
+                binding.conflicts.push(Conflict {
+                    source: "desktop".to_string(),
+                    name: name.clone(),
+                });
+            }
+        }
+    }
+    bindings
+}
+
+pub fn bindings(state: &AppState) -> Vec<Binding> {
+    let profile = state.user_profile.blocking_lock();
+    resolve(
+        &profile.as_ref().map(overrides).unwrap_or_default(),
+        &profile.as_ref().map(scopes).unwrap_or_default(),
+    )
+}
+
+// The current bindings without the conflict check, which reads the desktop's
+// shortcuts; for listing actions quickly
+pub fn assigned(state: &AppState) -> Vec<Binding> {
+    let profile = state.user_profile.blocking_lock();
+    assign(
+        &profile.as_ref().map(overrides).unwrap_or_default(),
+        &profile.as_ref().map(scopes).unwrap_or_default(),
+    )
+}
+
+// (Re-)register the global shortcuts with the OS
+pub fn register_global(app: &AppHandle) {
+    let bindings = bindings(&app.state::<AppState>());
+    let shortcuts = app.global_shortcut();
+    let _ = shortcuts.unregister_all();
+    for binding in bindings.into_iter().filter(|b| b.global) {
+        let action = binding.action.clone();
+        let registered =
+            shortcuts.on_shortcut(binding.accelerator.as_str(), move |app, _, event| {
+                // The microphone is open for as long as the keys are held
+                if action == "push-to-talk" {
+                    voice::push_to_talk(app, event.state == ShortcutState::Pressed);
+                    return;
+                }
+                if event.state != ShortcutState::Pressed {
+                    return;
+                }
+                // Voice input from anywhere goes into the search box, like push-to-talk
+                if action == "voice-input" {
+                    voice::toggle_listening(app);
+                    return;
+                }
+                if ["summon", "quick-search", "focus-search"].contains(&action.as_str()) {
+                    if let Some(window) = app.get_webview_window("main") {
+                        let _ = window.show();
+                        let _ = window.set_focus();
+                    }
+                }
+                let _ = app.emit("shortcut", &action);
+            });
+        if let Err(e) = registered {
+            eprintln!("Could not register {}: {}", binding.accelerator, e);
+        }
+    }
+}
+
+// Bind `action` to `accelerator` in the current profile, from anywhere when
+// `global` says so. Clashes with the app's own bindings are refused; clashes
+// with the desktop need `force`
+pub fn set(
+    state: &AppState,
+    action: &str,
+    accelerator: &str,
+    global: Option<bool>,
+    force: bool,
+) -> anyhow::Result<Vec<Binding>> {
+    if !ACTIONS.iter().any(|(a, ..)| *a == action) {
+        bail!("There is no action '{}'", action);
+    }
+    if global == Some(true) && !CAN_BE_GLOBAL.contains(&action) {
+        bail!("{} only works inside the window", action);
+    }
+    let accelerator = normalize(accelerator)?;
+    let mut profile = state.user_profile.blocking_lock();
+    let profile = profile.get_or_insert_with(UserProfile::default);
+    let mut overrides = overrides(profile);
+    let mut scopes = scopes(profile);
+    overrides.insert(action.to_string(), accelerator.clone());
+    if let Some(global) = global {
+        scopes.insert(action.to_string(), global);
+    }
+    let bindings = resolve(&overrides, &scopes);
+    let binding = bindings
+        .iter()
+        .find(|b| b.action == action)
+        .expect("action was checked above");
+    let names = |source: &str| -> Vec<String> {
+        binding
+            .conflicts
+            .iter()
+            .filter(|c| c.source == source)
+            .map(|c| c.name.clone())
+            .collect()
+    };
+    let app = names("app");
+    if !app.is_empty() {
+        bail!("{} is already used for {}", accelerator, app.join(", "));
+    }
+    let desktop = names("desktop");
+    if !desktop.is_empty() && !force {
+        bail!(
+            "{} is already taken by the desktop ({}); save anyway to override it here",
+            accelerator,
+            desktop.join(", ")
+        );
+    }
+    // Keep the stored overrides to actual changes
+    overrides.retain(|action, accelerator| {
+        ACTIONS
+            .iter()
+            .any(|(a, _, default, _)| a == action && default != accelerator)
+    });
+    scopes.retain(|action, global| {
+        ACTIONS
+            .iter()
+            .any(|(a, _, _, default)| a == action && default != global)
+    });
+    store(profile, &overrides, &scopes)?;
+    Ok(bindings)
+}
+
+pub fn reset(state: &AppState, action: Option<&str>) -> anyhow::Result<Vec<Binding>> {
+    let mut profile = state.user_profile.blocking_lock();
+    let profile = profile.get_or_insert_with(UserProfile::default);
+    let mut overrides = overrides(profile);
+    let mut scopes = scopes(profile);
+    match action {
+        Some(action) => {
+            overrides.remove(action);
+            scopes.remove(action);
+        }
+        None => {
+            overrides.clear();
+            scopes.clear();
+        }
+    }
+    store(profile, &overrides, &scopes)?;
+    Ok(resolve(&overrides, &scopes))
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn list_shortcuts(app: AppHandle) -> Vec<Binding> {
+    tasks::blocking_value(&app, "List shortcuts", |state| bindings(state)).await
+}
+
+#[tauri::command]
+pub async fn set_shortcut(
+    action: String,
+    accelerator: String,
+    global: Option<bool>,
+    force: Option<bool>,
+    app: AppHandle,
+) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Set shortcut", move |state| {
+        if let Some(refusal) = managed::guard("shortcuts", "Change keyboard shortcuts") {
+            return refusal;
+        }
+        let result = set(state, &action, &accelerator, global, force.unwrap_or(false));
+        if result.is_ok() {
+            register_global(&handle);
+        }
+        crate::respond(result)
+    })
+    .await
+}
+
+// Back to the default for one action, or for all of them
+#[tauri::command]
+pub async fn reset_shortcuts(action: Option<String>, app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Reset shortcuts", move |state| {
+        if let Some(refusal) = managed::guard("shortcuts", "Change keyboard shortcuts") {
+            return refusal;
+        }
+        let result = reset(state, action.as_deref());
+        if result.is_ok() {
+            register_global(&handle);
+        }
+        crate::respond(result)
+    })
+    .await
+}
@@ -0,0 +1,161 @@
+// On-disk locations and JSON persistence for backend state
+//
+// Paths match the fs plugin scope in tauri.conf.json so the frontend can read
+// the same files. A replayed test scenario keeps its stores in a folder of its
+// own: with_root moves both dirs there for the thread running the replay only,
+// so the rest of the app, and the user's files, are untouched.
+
+use anyhow::Context;
+use serde::{de::DeserializeOwned, Serialize};
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::system;
+
+thread_local! {
+    // Set on a thread while it runs inside with_root
+    static ROOT: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+// Puts the previous root back however the closure ends
+struct RestoreRoot(Option<PathBuf>);
+
+impl Drop for RestoreRoot {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        ROOT.with(|root| *root.borrow_mut() = previous);
+    }
+}
+
+// Where this thread's stores are kept instead of the user's, inside with_root
+pub fn root() -> Option<PathBuf> {
+    ROOT.with(|root| root.borrow().clone())
+}
+
+// Run `f` with this thread's stores under `dir`; other threads keep the user's own
+pub fn with_root<T>(dir: &Path, f: impl FnOnce() -> T) -> T {
+    let previous = ROOT.with(|root| root.replace(Some(dir.to_path_buf())));
+    let _restore = RestoreRoot(previous);
+    f()
+}
+
+// An empty folder of its own for a test to keep its stores in
+#[cfg(test)]
+pub fn scratch_dir(name: &str) -> PathBuf {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    static NEXT: AtomicU32 = AtomicU32::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "luminous-nix-test-{}-{}-{}",
+        std::process::id(),
+        name,
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("creating a scratch dir");
+    dir
+}
+
+pub fn config_dir() -> PathBuf {
+    match root() {
+        Some(dir) => dir.join("config"),
+        None => system::xdg_config_home().join("luminous-nix"),
+    }
+}
+
+// History and other machine-generated state, as opposed to user settings
+pub fn data_dir() -> PathBuf {
+    match root() {
+        Some(dir) => dir.join("data"),
+        None => system::xdg_data_home().join("luminous-nix"),
+    }
+}
+
+// Load a JSON document from the config dir, falling back to the default when absent
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> anyhow::Result<T> {
+    read_json(&config_dir().join(name))
+}
+
+pub fn save<T: Serialize>(name: &str, value: &T) -> anyhow::Result<PathBuf> {
+    let path = config_dir().join(name);
+    write_json(&path, value)?;
+    Ok(path)
+}
+
+pub fn load_data<T: DeserializeOwned + Default>(name: &str) -> anyhow::Result<T> {
+    read_json(&data_dir().join(name))
+}
+
+pub fn save_data<T: Serialize>(name: &str, value: &T) -> anyhow::Result<PathBuf> {
+    let path = data_dir().join(name);
+    write_json(&path, value)?;
+    Ok(path)
+}
+
+pub fn read_json<T: DeserializeOwned + Default>(path: &Path) -> anyhow::Result<T> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let raw = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    serde_json::from_str(&raw).with_context(|| format!("parsing {}", path.display()))
+}
+
+// Write pretty JSON atomically (temp file + rename) so a crash never leaves half a file
+pub fn write_json<T: Serialize>(path: &Path, value: &T) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+    }
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(value)?)
+        .with_context(|| format!("writing {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("replacing {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::thread;
+
+    #[test]
+    fn with_root_moves_only_this_threads_stores() {
+        let dir = scratch_dir("storage");
+        let user = thread::spawn(config_dir).join().unwrap();
+        with_root(&dir, || {
+            assert_eq!(config_dir(), dir.join("config"));
+            assert_eq!(data_dir(), dir.join("data"));
+            // Another thread at the same time still has the user's dirs
+            assert_eq!(thread::spawn(config_dir).join().unwrap(), user);
+        });
+        assert_eq!(root(), None);
+        assert_eq!(config_dir(), user);
+    }
+
+    #[test]
+    fn with_root_nests_and_survives_a_panic() {
+        let outer = scratch_dir("storage");
+        let inner = scratch_dir("storage");
+        with_root(&outer, || {
+            let panicked = std::panic::catch_unwind(|| with_root(&inner, || panic!("inside")));
+            assert!(panicked.is_err());
+            assert_eq!(root(), Some(outer.clone()));
+        });
+        assert_eq!(root(), None);
+    }
+
+    #[test]
+    fn stores_round_trip_and_default_when_absent() {
+        let dir = scratch_dir("storage");
+        with_root(&dir, || {
+            let missing: serde_json::Value = load("missing.json").unwrap();
+            assert!(missing.is_null());
+            let path = save_data("state.json", &json!({"a": 1})).unwrap();
+            assert_eq!(path, dir.join("data").join("state.json"));
+            let loaded: serde_json::Value = load_data("state.json").unwrap();
+            assert_eq!(loaded, json!({"a": 1}));
+            // Nothing half-written is left behind
+            assert!(!path.with_extension("tmp").exists());
+        });
+    }
+}
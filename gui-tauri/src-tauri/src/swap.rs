@@ -0,0 +1,307 @@
+// Swap and zram configuration helper
+//
+// Reviews the active swap/zram setup, recommends settings from detected RAM and
+// the declared workload, checks whether hibernation can work, and writes the
+// declarative change.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::nixgen::{self, NixModule, NixOption, Target};
+use crate::{sessions, tasks};
+
+const SWAPFILE: &str = "/var/lib/swapfile";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapDevice {
+    pub path: String,
+    pub kind: String,
+    pub size_mb: u64,
+    pub used_mb: u64,
+    pub priority: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapStatus {
+    pub ram_mb: u64,
+    pub devices: Vec<SwapDevice>,
+    pub zram_enabled: bool,
+    pub zram_algorithm: Option<String>,
+    pub swappiness: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Workload {
+    Desktop,
+    Development,
+    Gaming,
+    Server,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapRecommendation {
+    pub zram: bool,
+    pub zram_memory_percent: u32,
+    // Size of a disk swapfile in MiB; 0 means none
+    pub swapfile_mb: u64,
+    pub swappiness: u32,
+    pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HibernationCheck {
+    pub supported_by_kernel: bool,
+    pub blocked_by_lockdown: bool,
+    pub disk_swap_mb: u64,
+    pub enough_disk_swap: bool,
+    pub issues: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapReview {
+    pub current: SwapStatus,
+    pub recommendation: SwapRecommendation,
+    pub hibernation: Option<HibernationCheck>,
+    pub module_preview: String,
+}
+
+fn meminfo_kb(key: &str) -> Option<u64> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    meminfo
+        .lines()
+        .find(|l| l.starts_with(key))
+        .and_then(|l| l.split_whitespace().nth(1))
+        .and_then(|v| v.parse().ok())
+}
+
+pub fn ram_mb() -> u64 {
+    meminfo_kb("MemTotal:").unwrap_or(0) / 1024
+}
+
+pub fn status() -> SwapStatus {
+    let swaps = fs::read_to_string("/proc/swaps").unwrap_or_default();
+    let devices: Vec<SwapDevice> = swaps
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let f: Vec<&str> = line.split_whitespace().collect();
+            if f.len() < 5 {
+                return None;
+            }
+            Some(SwapDevice {
+                path: f[0].to_string(),
+                kind: if f[0].contains("zram") {
+                    "zram".to_string()
+                } else {
+                    f[1].to_string()
+                },
+                size_mb: f[2].parse::<u64>().unwrap_or(0) / 1024,
+                used_mb: f[3].parse::<u64>().unwrap_or(0) / 1024,
+                priority: f[4].parse().unwrap_or(0),
+            })
+        })
+        .collect();
+    let zram_algorithm = fs::read_to_string("/sys/block/zram0/comp_algorithm")
+        .ok()
+        .and_then(|s| {
+            // The active algorithm is the bracketed one: "lzo [zstd] lz4"
+            s.split_whitespace()
+                .find(|a| a.starts_with('['))
+                .map(|a| a.trim_matches(['[', ']']).to_string())
+        });
+    SwapStatus {
+        ram_mb: ram_mb(),
+        zram_enabled: devices.iter().any(|d| d.kind == "zram"),
+        devices,
+        zram_algorithm,
+        swappiness: fs::read_to_string("/proc/sys/vm/swappiness")
+            .ok()
+            .and_then(|s| s.trim().parse().ok()),
+    }
+}
+
+pub fn recommend(ram_mb: u64, workload: Workload, hibernate: bool) -> SwapRecommendation {
+    let ram_gb = ram_mb.div_ceil(1024);
+    let mut reasons = Vec::new();
+
+    let zram_memory_percent = match (ram_gb, workload) {
+        (0..=4, _) => 100,
+        (_, Workload::Server) => 25,
+        (5..=16, _) => 50,
+        _ => 25,
+    };
+    reasons.push(format!(
+        "{} GiB RAM: compressed zram swap sized at {}% of RAM keeps memory pressure from freezing the system",
+        ram_gb, zram_memory_percent
+    ));
+
+    let swapfile_mb = if hibernate {
+        reasons.push(
+            "Hibernation writes all of RAM to disk, so the swapfile matches RAM size plus headroom"
+                .to_string(),
+        );
+        ram_mb + ram_mb / 4
+    } else if workload == Workload::Development && ram_gb <= 16 {
+        reasons.push(
+            "Large builds can exceed RAM; a small disk swapfile catches what zram cannot hold"
+                .to_string(),
+        );
+        8 * 1024
+    } else {
+        0
+    };
+
+    let swappiness = match workload {
+        Workload::Gaming => 10,
+        Workload::Server => 60,
+        // zram is cheap to swap into, so desktops benefit from a higher value
+        Workload::Desktop | Workload::Development => 100,
+    };
+    reasons.push(
+        format!(
+            "vm.swappiness = {} suits a {:?} workload with zram",
+            swappiness, workload
+        )
+        .to_lowercase(),
+    );
+
+    SwapRecommendation {
+        zram: true,
+        zram_memory_percent,
+        swapfile_mb,
+        swappiness,
+        reasons,
+    }
+}
+
+pub fn check_hibernation(current: &SwapStatus, planned_swapfile_mb: u64) -> HibernationCheck {
+    let supported_by_kernel = fs::read_to_string("/sys/power/state")
+        .map(|s| s.contains("disk"))
+        .unwrap_or(false);
+    let lockdown = fs::read_to_string("/sys/kernel/security/lockdown").unwrap_or_default();
+    let blocked_by_lockdown = !lockdown.contains("[none]") && !lockdown.is_empty();
+    let existing_disk_swap: u64 = current
+        .devices
+        .iter()
+        .filter(|d| d.kind != "zram")
+        .map(|d| d.size_mb)
+        .sum();
+    let disk_swap_mb = existing_disk_swap.max(planned_swapfile_mb);
+    let enough_disk_swap = disk_swap_mb >= current.ram_mb;
+
+    let mut issues = Vec::new();
+    if !supported_by_kernel {
+        issues.push(
+            "The kernel does not offer suspend-to-disk (/sys/power/state lacks 'disk')."
+                .to_string(),
+        );
+    }
+    if blocked_by_lockdown {
+        issues
+            .push("Kernel lockdown (often enabled by Secure Boot) blocks hibernation.".to_string());
+    }
+    if !enough_disk_swap {
+        issues.push(format!(
+            "Disk swap ({} MiB) is smaller than RAM ({} MiB); zram cannot be used to hibernate.",
+            disk_swap_mb, current.ram_mb
+        ));
+    }
+    issues.push(
+        "Swap with randomEncryption cannot be resumed from; use a LUKS-backed swap instead."
+            .to_string(),
+    );
+    if Path::new(SWAPFILE).exists() || planned_swapfile_mb > 0 {
+        issues.push(format!(
+            "Resuming from a swapfile needs boot.kernelParams = [ \"resume_offset=<n>\" ]; after the rebuild, get <n> with `filefrag -v {}` (or `btrfs inspect-internal map-swapfile -r` on btrfs).",
+            SWAPFILE
+        ));
+    }
+
+    HibernationCheck {
+        supported_by_kernel,
+        blocked_by_lockdown,
+        disk_swap_mb,
+        enough_disk_swap,
+        issues,
+    }
+}
+
+fn build_module(rec: &SwapRecommendation, hibernate: bool) -> NixModule {
+    let mut module = NixModule::new("swap", "swap and zram", Target::Nixos);
+    module.set(NixOption::new("zramSwap.enable", nixgen::bool(rec.zram)));
+    module.set(NixOption::new("zramSwap.algorithm", nixgen::string("zstd")));
+    module.set(NixOption::new(
+        "zramSwap.memoryPercent",
+        rec.zram_memory_percent.to_string(),
+    ));
+    module.set(NixOption::new(
+        "boot.kernel.sysctl.\"vm.swappiness\"",
+        rec.swappiness.to_string(),
+    ));
+    if rec.swapfile_mb > 0 {
+        module.set(
+            NixOption::new(
+                "swapDevices",
+                format!(
+                    "[ {{ device = {}; size = {}; priority = 1; }} ]",
+                    nixgen::string(SWAPFILE),
+                    rec.swapfile_mb
+                ),
+            )
+            .with_comment("Low priority so zram is used first"),
+        );
+    }
+    if hibernate {
+        module.set(
+            NixOption::new("boot.resumeDevice", "config.fileSystems.\"/\".device")
+                .with_comment("The swapfile lives on the root filesystem"),
+        );
+    }
+    module
+}
+
+pub fn review(workload: Workload, hibernate: bool) -> SwapReview {
+    let current = status();
+    let recommendation = recommend(current.ram_mb, workload, hibernate);
+    let hibernation = hibernate.then(|| check_hibernation(&current, recommendation.swapfile_mb));
+    SwapReview {
+        module_preview: build_module(&recommendation, hibernate).render(),
+        current,
+        recommendation,
+        hibernation,
+    }
+}
+
+// Declare the recommended swap; one audited operation, like a rebuild
+pub fn apply(recommendation: &SwapRecommendation, hibernate: bool) -> anyhow::Result<PathBuf> {
+    sessions::exclusive("Configure swap", || {
+        build_module(recommendation, hibernate).write()
+    })
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn review_swap(workload: Workload, hibernate: bool, app: AppHandle) -> SwapReview {
+    tasks::blocking_value(&app, "Review swap", move |_| review(workload, hibernate)).await
+}
+
+#[tauri::command]
+pub async fn apply_swap(
+    recommendation: SwapRecommendation,
+    hibernate: bool,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Apply swap", move |_| {
+        crate::respond(apply(&recommendation, hibernate).map(|path| {
+            serde_json::json!({
+                "module_path": path,
+                "next_step": "Rebuild the system; the swapfile is created on activation",
+            })
+        }))
+    })
+    .await
+}
@@ -0,0 +1,204 @@
+// Thin wrappers around the external tools the backend drives (nix, xdg-mime, ...)
+
+use anyhow::{bail, Context};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::{demo, managed, sessions, tasks};
+
+// How often a task's program is checked for cancellation
+const CANCEL_POLL: Duration = Duration::from_millis(50);
+
+// Run a program and return its stdout, failing with stderr on a non-zero exit
+pub fn run(program: &str, args: &[&str]) -> anyhow::Result<String> {
+    let mut command = Command::new(program);
+    command.args(args);
+    output_of(program, command)
+}
+
+// Same as `run`, for programs writing binary data (images, archives)
+pub fn run_bytes(program: &str, args: &[&str]) -> anyhow::Result<Vec<u8>> {
+    let mut command = Command::new(program);
+    command.args(args);
+    stdout_of(program, command)
+}
+
+// Same as `run`, but inside the given working directory
+pub fn run_in(dir: &Path, program: &str, args: &[&str]) -> anyhow::Result<String> {
+    let mut command = Command::new(program);
+    command.args(args).current_dir(dir);
+    output_of(program, command)
+}
+
+fn output_of(program: &str, command: Command) -> anyhow::Result<String> {
+    stdout_of(program, command).map(|stdout| String::from_utf8_lossy(&stdout).into_owned())
+}
+
+fn stdout_of(program: &str, mut command: Command) -> anyhow::Result<Vec<u8>> {
+    if tasks::cancelled() {
+        bail!("Cancelled before {} started", program);
+    }
+    let output = match tasks::current() {
+        Some(cancelled) => wait_cancellable(program, command, &cancelled)?,
+        None => command
+            .output()
+            .with_context(|| format!("failed to start {}", program))?,
+    };
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(
+            "{} exited with {}: {}",
+            program,
+            output.status,
+            stderr.trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+fn drain<R: Read + Send + 'static>(pipe: Option<R>) -> JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        buffer
+    })
+}
+
+// Inside a task: wait for the program, killing it if the task is cancelled
+fn wait_cancellable(
+    program: &str,
+    mut command: Command,
+    cancelled: &AtomicBool,
+) -> anyhow::Result<Output> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to start {}", program))?;
+    // Drained meanwhile, so a program with a lot to say never blocks on a full pipe
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        // Fails for programs running as root, which then finish first
+        if cancelled.load(Ordering::SeqCst) && child.kill().is_ok() {
+            let _ = child.wait();
+            bail!("{} was cancelled", program);
+        }
+        std::thread::sleep(CANCEL_POLL);
+    };
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+// Run a program as root through polkit so the GUI itself never needs privileges.
+// Other sessions' privileged commands wait until this one is done.
+pub fn run_privileged(program: &str, args: &[&str]) -> anyhow::Result<String> {
+    let mut full = vec![program];
+    full.extend_from_slice(args);
+    let command = full.join(" ");
+    if demo::active() {
+        bail!("Nothing runs as root during a demo ({})", command);
+    }
+    managed::check_privileged(&command)?;
+    sessions::command(&command, || run("pkexec", &full))
+}
+
+// Like `run_privileged`, feeding `input` on stdin so secrets never appear in argv
+pub fn run_privileged_with_input(
+    program: &str,
+    args: &[&str],
+    input: &[u8],
+) -> anyhow::Result<String> {
+    let command = format!("{} {}", program, args.join(" "));
+    if demo::active() {
+        bail!("Nothing runs as root during a demo ({})", command);
+    }
+    managed::check_privileged(&command)?;
+    sessions::command(&command, || {
+        let mut child = Command::new("pkexec")
+            .arg(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to start {}", program))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(input)?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!(
+                "{} exited with {}: {}",
+                program,
+                output.status,
+                stderr.trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    })
+}
+
+// Locate an executable on PATH
+pub fn find_in_path(program: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+pub fn home_dir() -> PathBuf {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/"))
+}
+
+pub fn username() -> String {
+    std::env::var("USER").unwrap_or_else(|_| "root".to_string())
+}
+
+// XDG_CONFIG_HOME with the usual ~/.config fallback
+pub fn xdg_config_home() -> PathBuf {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home_dir().join(".config"))
+}
+
+// XDG_DATA_HOME with the usual ~/.local/share fallback
+pub fn xdg_data_home() -> PathBuf {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home_dir().join(".local/share"))
+}
+
+// Every directory that may contain share/applications style data on NixOS
+pub fn xdg_data_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![xdg_data_home()];
+    match std::env::var_os("XDG_DATA_DIRS") {
+        Some(paths) => dirs.extend(std::env::split_paths(&paths)),
+        None => {
+            dirs.push(home_dir().join(".nix-profile/share"));
+            dirs.push(PathBuf::from(format!(
+                "/etc/profiles/per-user/{}/share",
+                username()
+            )));
+            dirs.push(PathBuf::from("/run/current-system/sw/share"));
+        }
+    }
+    dirs
+}
@@ -0,0 +1,160 @@
+// Backend work off the IPC thread
+//
+// Commands that touch the system hand their work to `blocking`, which runs it
+// on the async runtime's blocking pool while the command awaits it, so the
+// window and every other command stay responsive during a long nix operation.
+// Each task has an id and a cancellation flag, which the operations it reports
+// share: cancelling one with progress::cancel_operation sets it, the program
+// the task is waiting on in system::run is killed, and every later program it
+// would start fails straight away, so the work stops at its next step. Work
+// handed on to other threads (the evaluation pool) takes the flag with it.
+// Programs running as root can't be killed from here; they finish first.
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::Mutex;
+
+use crate::{clock, AppState};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskInfo {
+    pub id: u64,
+    pub label: String,
+    pub started_ms: u64,
+    pub cancelled: bool,
+}
+
+struct Task {
+    label: String,
+    started_ms: u64,
+    cancelled: Arc<AtomicBool>,
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static TASKS: Mutex<BTreeMap<u64, Task>> = Mutex::const_new(BTreeMap::new());
+
+thread_local! {
+    // The flag of the task running on this blocking thread
+    static CURRENT: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+// Puts back the thread's previous task when the work ends, even by panicking
+struct Current(Option<Arc<AtomicBool>>);
+
+impl Current {
+    fn enter(cancelled: Option<Arc<AtomicBool>>) -> Current {
+        Current(CURRENT.with(|current| current.replace(cancelled)))
+    }
+}
+
+impl Drop for Current {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+// The cancellation flag of the task this code runs in, if any
+pub fn current() -> Option<Arc<AtomicBool>> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+pub fn cancelled() -> bool {
+    current().is_some_and(|flag| flag.load(Ordering::SeqCst))
+}
+
+// Run `work` on this thread as part of the task `cancelled` belongs to, for
+// work another thread queued; see `current`
+pub fn within<T>(cancelled: Option<Arc<AtomicBool>>, work: impl FnOnce() -> T) -> T {
+    let _current = Current::enter(cancelled);
+    work()
+}
+
+async fn spawn<T: Send + 'static>(
+    app: &AppHandle,
+    label: &str,
+    work: impl FnOnce(&State<AppState>) -> T + Send + 'static,
+) -> tauri::Result<T> {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let cancelled = Arc::new(AtomicBool::new(false));
+    TASKS.lock().await.insert(
+        id,
+        Task {
+            label: label.to_string(),
+            started_ms: clock::now_ms(),
+            cancelled: cancelled.clone(),
+        },
+    );
+    let app = app.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        within(Some(cancelled), || work(&app.state::<AppState>()))
+    })
+    .await;
+    TASKS.lock().await.remove(&id);
+    result
+}
+
+// Run synchronous work with the app state on the blocking pool
+pub async fn blocking<T: Send + 'static>(
+    app: &AppHandle,
+    label: &str,
+    work: impl FnOnce(&State<AppState>) -> T + Send + 'static,
+) -> anyhow::Result<T> {
+    spawn(app, label, work)
+        .await
+        .map_err(|e| anyhow!("{} stopped unexpectedly: {}", label, e))
+}
+
+// `blocking` for commands answering with a plain value; a panic in the work
+// goes on in the command, as if the command had run the work itself
+pub async fn blocking_value<T: Send + 'static>(
+    app: &AppHandle,
+    label: &str,
+    work: impl FnOnce(&State<AppState>) -> T + Send + 'static,
+) -> T {
+    match spawn(app, label, work).await {
+        Ok(value) => value,
+        Err(tauri::Error::JoinError(e)) if e.is_panic() => {
+            std::panic::resume_unwind(e.into_panic())
+        }
+        Err(e) => panic!("{} stopped unexpectedly: {}", label, e),
+    }
+}
+
+// `blocking` for commands answering with a response envelope
+pub async fn blocking_json(
+    app: &AppHandle,
+    label: &str,
+    work: impl FnOnce(&State<AppState>) -> serde_json::Value + Send + 'static,
+) -> serde_json::Value {
+    match blocking(app, label, work).await {
+        Ok(response) => response,
+        Err(e) => crate::respond::<()>(Err(e)),
+    }
+}
+
+pub async fn list() -> Vec<TaskInfo> {
+    TASKS
+        .lock()
+        .await
+        .iter()
+        .map(|(id, task)| TaskInfo {
+            id: *id,
+            label: task.label.clone(),
+            started_ms: task.started_ms,
+            cancelled: task.cancelled.load(Ordering::SeqCst),
+        })
+        .collect()
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn list_tasks() -> Vec<TaskInfo> {
+    list().await
+}
@@ -0,0 +1,535 @@
+// Inline terminal with optional supervision
+//
+// Each terminal is a real shell on a pseudo-terminal; its output is emitted as
+// "terminal-output" events and input arrives through write_terminal. With
+// supervision on, the line being typed is reconstructed from the keystrokes
+// and reviewed: risky commands get a "terminal-annotation" with the risk and,
+// where there is one, the declarative way to do the same thing. Pressing
+// enter on a command that changes the system is held back until the user
+// confirms it. Shell-side editing (tab completion, history recall) isn't
+// visible from here, so supervision is advice, not a guarantee.
+
+use anyhow::{anyhow, Context};
+use portable_pty::{native_pty_system, Child, ChildKiller, CommandBuilder, MasterPty, PtySize};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Mutex;
+
+use crate::safety::BlastRadius;
+use crate::{explain, managed, system, tasks};
+
+const READ_BUFFER: usize = 8192;
+const GC_SNIPPET: &str = "nix.gc = { automatic = true; options = \"--delete-older-than 30d\"; };";
+
+struct Session {
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+    supervised: bool,
+    // The command line as typed since the last enter
+    line: String,
+    // Skipping the rest of an escape sequence (arrow keys and the like)
+    in_escape: bool,
+    review: Option<Review>,
+}
+
+static SESSIONS: Mutex<Option<HashMap<u64, Session>>> = Mutex::const_new(None);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alternative {
+    pub description: String,
+    // Nix to add to the configuration instead, when it can be written out
+    pub snippet: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Review {
+    pub command: String,
+    pub risk: BlastRadius,
+    pub annotation: String,
+    pub alternative: Option<Alternative>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerminalInfo {
+    pub id: u64,
+    pub shell: String,
+    pub supervised: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WriteOutcome {
+    // Set when enter was held back; confirm by writing "\r" with confirmed
+    pub held: Option<Review>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TerminalOutput {
+    id: u64,
+    data: Vec<u8>,
+}
+
+fn review(
+    command: &str,
+    risk: BlastRadius,
+    annotation: &str,
+    alternative: Option<(&str, Option<String>)>,
+) -> Review {
+    Review {
+        command: command.to_string(),
+        risk,
+        annotation: annotation.to_string(),
+        alternative: alternative.map(|(description, snippet)| Alternative {
+            description: description.to_string(),
+            snippet,
+        }),
+    }
+}
+
+// "nixpkgs.firefox", "nixpkgs#firefox" -> "firefox"
+fn package_names(args: &[String]) -> Vec<String> {
+    args.iter()
+        .filter(|a| !a.starts_with('-'))
+        .map(|a| {
+            let a = a.rsplit_once('#').map_or(a.as_str(), |(_, attr)| attr);
+            a.strip_prefix("nixpkgs.").unwrap_or(a).to_string()
+        })
+        .collect()
+}
+
+fn has_flag(args: &[String], flags: &[&str]) -> bool {
+    args.iter().any(|a| {
+        flags.contains(&a.as_str())
+            // Bundled short flags like -iA
+            || (a.starts_with('-') && !a.starts_with("--") && flags.iter().any(|f| {
+                f.len() == 2 && !f.starts_with("--") && a[1..].contains(&f[1..])
+            }))
+    })
+}
+
+// One command of a line, without pipes or separators
+fn review_command(command: &str) -> Option<Review> {
+    let mut tokens = explain::tokenize(command);
+    if tokens.first().map(String::as_str) == Some("sudo") {
+        tokens.remove(0);
+    }
+    let (program, args) = tokens.split_first()?;
+    let program = program.rsplit('/').next().unwrap_or(program);
+    let sub = args.first().map(String::as_str).unwrap_or("");
+    let packages = |args: &[String]| package_names(args).join(" ");
+    match program {
+        "nix-env" if has_flag(args, &["-i", "--install"]) => Some(review(
+            command,
+            BlastRadius::Reversible,
+            "nix-env installs are imperative: they aren't in your configuration and are easy to lose track of",
+            Some((
+                "Add the package to your configuration instead",
+                Some(format!(
+                    "environment.systemPackages = with pkgs; [ {} ];",
+                    packages(args)
+                )),
+            )),
+        )),
+        "nix" if sub == "profile" && args.get(1).is_some_and(|a| a == "install") => Some(review(
+            command,
+            BlastRadius::Reversible,
+            "Profile installs live outside your configuration",
+            Some((
+                "Add the package to your configuration instead",
+                Some(format!(
+                    "environment.systemPackages = with pkgs; [ {} ];",
+                    packages(&args[2..])
+                )),
+            )),
+        )),
+        "nix-env" if has_flag(args, &["-e", "--uninstall"]) => Some(review(
+            command,
+            BlastRadius::Reversible,
+            "Removes a package installed imperatively",
+            Some((
+                "If it came from your configuration, remove it from environment.systemPackages instead",
+                None,
+            )),
+        )),
+        "nix-env" if has_flag(args, &["--delete-generations"]) => Some(review(
+            command,
+            BlastRadius::Destructive,
+            "Deleted generations can't be rolled back to",
+            Some((
+                "Let NixOS clean up old generations on a schedule",
+                Some(GC_SNIPPET.to_string()),
+            )),
+        )),
+        "nix-collect-garbage" if has_flag(args, &["-d", "--delete-old", "--delete-older-than"]) => {
+            Some(review(
+                command,
+                BlastRadius::Destructive,
+                "Deleting old generations means you can no longer roll back to them",
+                Some((
+                    "Let NixOS clean up old generations on a schedule",
+                    Some(GC_SNIPPET.to_string()),
+                )),
+            ))
+        }
+        "nix-channel" if has_flag(args, &["--add", "--update", "--remove"]) => Some(review(
+            command,
+            BlastRadius::Reversible,
+            "Channels change what nixpkgs means for every later rebuild, without a record of which version",
+            Some((
+                "Pin nixpkgs as a flake input so the version is locked",
+                Some(
+                    "inputs.nixpkgs.url = \"github:NixOS/nixpkgs/nixos-unstable\";".to_string(),
+                ),
+            )),
+        )),
+        "systemctl" if !has_flag(args, &["--user"]) && matches!(sub, "enable" | "disable" | "mask") => {
+            let unit = args[1..]
+                .iter()
+                .find(|a| !a.starts_with('-'))
+                .map_or("", |u| u.trim_end_matches(".service"));
+            Some(review(
+                command,
+                BlastRadius::Reversible,
+                "NixOS manages which units are enabled; this is undone by the next rebuild",
+                Some((
+                    "Set it in your configuration instead",
+                    Some(if sub == "enable" {
+                        format!("systemd.services.{}.wantedBy = [ \"multi-user.target\" ];", unit)
+                    } else {
+                        format!("systemd.services.{}.enable = false;", unit)
+                    }),
+                )),
+            ))
+        }
+        "pip" | "pip3" if sub == "install" => Some(review(
+            command,
+            BlastRadius::Reversible,
+            "pip installs into places Nix doesn't track and often fails on NixOS",
+            Some((
+                "Use a Python with the packages included",
+                Some(format!(
+                    "environment.systemPackages = [ (pkgs.python3.withPackages (ps: with ps; [ {} ])) ];",
+                    packages(&args[1..])
+                )),
+            )),
+        )),
+        "rm" if has_flag(args, &["-r", "-R", "--recursive"]) && has_flag(args, &["-f", "--force"]) => {
+            Some(review(
+                command,
+                BlastRadius::Destructive,
+                "Deletes everything under these paths without asking, for good",
+                None,
+            ))
+        }
+        "dd" | "wipefs" | "shred" => Some(review(
+            command,
+            BlastRadius::Destructive,
+            "Overwrites data on disk; what was there can't be recovered",
+            None,
+        )),
+        p if p.starts_with("mkfs") => Some(review(
+            command,
+            BlastRadius::Destructive,
+            "Formatting erases everything on the device",
+            Some(("Describe file systems declaratively with disko", None)),
+        )),
+        // Files in /etc (other than the configuration itself) are generated
+        _ if args
+            .iter()
+            .any(|a| a.starts_with("/etc/") && !a.starts_with("/etc/nixos/"))
+            && matches!(program, "vi" | "vim" | "nvim" | "nano" | "tee" | "cp" | "mv" | "sed") =>
+        {
+            let path = args
+                .iter()
+                .find(|a| a.starts_with("/etc/"))
+                .map(|a| a.trim_start_matches("/etc/"))
+                .unwrap_or("");
+            Some(review(
+                command,
+                BlastRadius::Reversible,
+                "Files in /etc are generated by NixOS and are replaced on the next rebuild",
+                Some((
+                    "Manage the file from your configuration instead",
+                    Some(format!("environment.etc.\"{}\".text = '' ... '';", path)),
+                )),
+            ))
+        }
+        _ => None,
+    }
+}
+
+// The riskiest command on a line
+pub fn review_line(line: &str) -> Option<Review> {
+    let line = line.trim();
+    let piped_to_shell = line.split('|').skip(1).any(|part| {
+        matches!(
+            part.split_whitespace().next(),
+            Some("sh" | "bash" | "zsh" | "sudo")
+        )
+    });
+    if piped_to_shell && (line.contains("curl") || line.contains("wget")) {
+        return Some(review(
+            line,
+            BlastRadius::Destructive,
+            "Piping a downloaded script into a shell runs code you haven't read",
+            Some((
+                "Package it with a Nix derivation, or download and read it first",
+                None,
+            )),
+        ));
+    }
+    line.split(['|', ';', '&'])
+        .filter_map(review_command)
+        .max_by_key(|r| r.risk)
+        .map(|r| Review {
+            command: line.to_string(),
+            ..r
+        })
+}
+
+fn sessions() -> tokio::sync::MutexGuard<'static, Option<HashMap<u64, Session>>> {
+    SESSIONS.blocking_lock()
+}
+
+fn size(cols: u16, rows: u16) -> PtySize {
+    PtySize {
+        rows: rows.max(1),
+        cols: cols.max(1),
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+pub fn open(
+    app: &AppHandle,
+    cols: u16,
+    rows: u16,
+    supervised: bool,
+) -> anyhow::Result<TerminalInfo> {
+    let shell = std::env::var("SHELL")
+        .ok()
+        .or_else(|| system::find_in_path("bash").map(|p| p.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "/bin/sh".to_string());
+    let pair = native_pty_system()
+        .openpty(size(cols, rows))
+        .context("Couldn't open a terminal")?;
+    let mut command = CommandBuilder::new(&shell);
+    command.cwd(system::home_dir());
+    command.env("TERM", "xterm-256color");
+    let child = pair
+        .slave
+        .spawn_command(command)
+        .with_context(|| format!("Couldn't start {}", shell))?;
+    // The shell holds its own end; ours would keep the terminal open after it exits
+    drop(pair.slave);
+    let mut reader = pair
+        .master
+        .try_clone_reader()
+        .context("Couldn't read from the terminal")?;
+    let writer = pair
+        .master
+        .take_writer()
+        .context("Couldn't write to the terminal")?;
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    sessions().get_or_insert_with(HashMap::new).insert(
+        id,
+        Session {
+            master: pair.master,
+            writer,
+            child,
+            supervised,
+            line: String::new(),
+            in_escape: false,
+            review: None,
+        },
+    );
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let mut buffer = [0u8; READ_BUFFER];
+        // Raw bytes: a UTF-8 character can be split across two reads
+        while let Ok(n @ 1..) = reader.read(&mut buffer) {
+            let _ = app.emit(
+                "terminal-output",
+                TerminalOutput {
+                    id,
+                    data: buffer[..n].to_vec(),
+                },
+            );
+        }
+        let code = sessions()
+            .as_mut()
+            .and_then(|s| s.remove(&id))
+            .and_then(|mut session| session.child.wait().ok())
+            .map(|status| status.exit_code());
+        let _ = app.emit("terminal-exit", serde_json::json!({"id": id, "code": code}));
+    });
+    Ok(TerminalInfo {
+        id,
+        shell,
+        supervised,
+    })
+}
+
+impl Session {
+    // Follow the line being typed; true at enter
+    fn track(&mut self, c: char) -> bool {
+        if self.in_escape {
+            // CSI sequences end with a letter or ~
+            self.in_escape = !(c.is_ascii_alphabetic() || c == '~');
+            return false;
+        }
+        match c {
+            '\r' | '\n' => return true,
+            '\x1b' => self.in_escape = true,
+            '\x7f' | '\x08' => {
+                self.line.pop();
+            }
+            // Ctrl-C and Ctrl-U drop the line
+            '\x03' | '\x15' => self.line.clear(),
+            c if !c.is_control() => self.line.push(c),
+            _ => {}
+        }
+        false
+    }
+}
+
+fn with_session<T>(
+    id: u64,
+    f: impl FnOnce(&mut Session) -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let mut sessions = sessions();
+    let session = sessions
+        .as_mut()
+        .and_then(|s| s.get_mut(&id))
+        .ok_or_else(|| anyhow!("Terminal {} isn't open", id))?;
+    f(session)
+}
+
+pub fn write(
+    app: &AppHandle,
+    id: u64,
+    data: &str,
+    confirmed: bool,
+) -> anyhow::Result<WriteOutcome> {
+    with_session(id, |session| {
+        if !session.supervised {
+            session.writer.write_all(data.as_bytes())?;
+            return Ok(WriteOutcome { held: None });
+        }
+        let mut sent = 0;
+        let mut held = None;
+        for (i, c) in data.char_indices() {
+            if !session.track(c) {
+                continue;
+            }
+            let review = review_line(&session.line);
+            if !confirmed
+                && review
+                    .as_ref()
+                    .is_some_and(|r| r.risk != BlastRadius::ReadOnly)
+            {
+                // What was typed before the enter goes through; the enter and
+                // anything after it are dropped until the user confirms
+                session.writer.write_all(data[sent..i].as_bytes())?;
+                sent = data.len();
+                held = review;
+                break;
+            }
+            session.line.clear();
+        }
+        session.writer.write_all(data[sent..].as_bytes())?;
+        session.writer.flush()?;
+
+        let review = held.clone().or_else(|| review_line(&session.line));
+        if review != session.review {
+            session.review = review.clone();
+            let _ = app.emit(
+                "terminal-annotation",
+                serde_json::json!({"id": id, "review": review}),
+            );
+        }
+        Ok(WriteOutcome { held })
+    })
+}
+
+pub fn close(id: u64) -> anyhow::Result<()> {
+    let session = sessions().as_mut().and_then(|s| s.remove(&id));
+    let Some(mut session) = session else {
+        return Err(anyhow!("Terminal {} isn't open", id));
+    };
+    let _ = session.child.kill();
+    let _ = session.child.wait();
+    Ok(())
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn open_terminal(
+    cols: u16,
+    rows: u16,
+    supervised: Option<bool>,
+    app: AppHandle,
+) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Open terminal", move |_| {
+        if let Some(refusal) = managed::guard("terminal", "Open a terminal") {
+            return refusal;
+        }
+        crate::respond(open(&handle, cols, rows, supervised.unwrap_or(true)))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn write_terminal(
+    id: u64,
+    data: String,
+    confirmed: Option<bool>,
+    app: AppHandle,
+) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Write terminal", move |_| {
+        crate::respond(write(&handle, id, &data, confirmed.unwrap_or(false)))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn resize_terminal(id: u64, cols: u16, rows: u16, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Resize terminal", move |_| {
+        crate::respond(with_session(id, |session| {
+            session.master.resize(size(cols, rows))
+        }))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn set_terminal_supervision(id: u64, enabled: bool, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set terminal supervision", move |_| {
+        crate::respond(with_session(id, |session| {
+            session.supervised = enabled;
+            session.line.clear();
+            Ok(())
+        }))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn close_terminal(id: u64, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Close terminal", move |_| crate::respond(close(id))).await
+}
+
+// Review a command without a terminal, e.g. one pasted into chat
+#[tauri::command]
+pub async fn review_terminal_command(command: String) -> Option<Review> {
+    review_line(&command)
+}
@@ -0,0 +1,598 @@
+// Recorded test scenarios, replayed to catch regressions
+//
+// While recording, every interaction that goes through the backend (typed
+// requests, component state updates, layout switches, and the clicks and
+// typing of the AI testing commands) is written down with when it happened,
+// what it answered and the component state it left. Both recording and replay
+// run against the demo's pretend Nix system, started fresh each time, so a
+// scenario never touches the real one and always starts from the same place.
+// The settings that shape answers (safety policy, language, personality,
+// aliases) are kept with the scenario when recording. A replay runs with
+// those and nothing else of the user's: its thread keeps its stores in a
+// folder of its own, so it answers the same on any machine, and what it
+// stores, like reminders, is thrown away afterwards. The rest of the app keeps
+// using the user's files throughout.
+// Replay puts the recorded starting state back, runs the steps in order
+// (confirmations answered the way they were) and reports every place the
+// answer or the resulting state differs from the recording. Values that differ
+// on every run, like confirmation tokens and timestamps, aren't compared.
+
+use anyhow::{anyhow, bail, Context};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, State};
+use tokio::sync::Mutex;
+
+use crate::{
+    aliases, clock, context, current_persona, demo, i18n, layouts, panels, safety, storage, tasks,
+    timers, tone, uidriver, AppState, ComponentState, Layout,
+};
+
+pub const SCENARIO_DIR: &str = "scenarios";
+const SANDBOX_DIR: &str = "scenario-sandbox";
+// Config files that change what an answer says
+const PINNED: &[&str] = &[
+    safety::POLICY_FILE,
+    i18n::SETTINGS_FILE,
+    tone::SETTINGS_FILE,
+    aliases::ALIASES_FILE,
+];
+const SCHEMA_VERSION: u32 = 1;
+// Fields that change from run to run
+const VOLATILE_KEYS: &[&str] = &[
+    "token",
+    "confirmation_token",
+    "timestamp",
+    "created_at",
+    "started_at",
+    "updated_at",
+    "taken_at",
+    "operation_id",
+    "duration_ms",
+    "elapsed_ms",
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub components: Vec<ComponentState>,
+    pub current_layout: Option<Layout>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Action {
+    Query {
+        query: String,
+        options: Option<serde_json::Value>,
+        // Answers the confirmation the step before asked for
+        #[serde(default)]
+        confirm: bool,
+    },
+    SetComponentState {
+        component_id: String,
+        state: serde_json::Value,
+    },
+    SwitchLayout {
+        layout_id: String,
+    },
+    Click {
+        component_id: String,
+        selector: Option<String>,
+    },
+    Type {
+        component_id: String,
+        text: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedStep {
+    // Since recording started
+    pub at_ms: u64,
+    pub action: Action,
+    pub response: serde_json::Value,
+    pub state: Snapshot,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scenario {
+    pub schema_version: u32,
+    pub name: String,
+    // Unix milliseconds
+    pub recorded_at: u64,
+    // The PINNED files as they were, by name; replays use these
+    #[serde(default)]
+    pub settings: BTreeMap<String, serde_json::Value>,
+    pub initial: Snapshot,
+    pub steps: Vec<RecordedStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioInfo {
+    pub name: String,
+    pub path: PathBuf,
+    pub steps: usize,
+    pub recorded_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Divergence {
+    pub step: usize,
+    // "response/success" or "state/components/0/state/query"
+    pub path: String,
+    pub expected: Option<serde_json::Value>,
+    pub actual: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayReport {
+    pub scenario: String,
+    pub steps: usize,
+    pub passed: bool,
+    pub divergences: Vec<Divergence>,
+    pub duration_ms: u64,
+}
+
+struct Recording {
+    scenario: Scenario,
+    started_ms: u64,
+}
+
+static RECORDING: Mutex<Option<Recording>> = Mutex::const_new(None);
+
+pub fn recording() -> bool {
+    RECORDING.blocking_lock().is_some()
+}
+
+pub fn snapshot(state: &AppState) -> Snapshot {
+    Snapshot {
+        components: state.components.blocking_lock().clone(),
+        current_layout: state.current_layout.blocking_lock().clone(),
+    }
+}
+
+fn scenario_dir() -> PathBuf {
+    storage::data_dir().join(SCENARIO_DIR)
+}
+
+fn scenario_path(name: &str) -> anyhow::Result<PathBuf> {
+    let slug = timers::slug(name);
+    if slug.is_empty() {
+        bail!("A scenario needs a name");
+    }
+    Ok(scenario_dir().join(format!("{}.json", slug)))
+}
+
+fn pinned_settings() -> BTreeMap<String, serde_json::Value> {
+    PINNED
+        .iter()
+        .filter_map(|name| {
+            let value: serde_json::Value = storage::load(name).ok()?;
+            (!value.is_null()).then(|| (name.to_string(), value))
+        })
+        .collect()
+}
+
+// A fresh folder for a replay's stores, holding only `settings`. Only the
+// replay creates and removes it; nothing else ever stores there
+fn sandbox(settings: &BTreeMap<String, serde_json::Value>) -> anyhow::Result<PathBuf> {
+    let dir = storage::data_dir().join(SANDBOX_DIR);
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&dir)
+        .with_context(|| format!("creating {}", dir.display()))?;
+    let saved = storage::with_root(&dir, || {
+        settings
+            .iter()
+            .try_for_each(|(name, value)| storage::save(name, value).map(|_| ()))
+    });
+    if let Err(e) = saved {
+        let _ = fs::remove_dir_all(&dir);
+        return Err(e);
+    }
+    Ok(dir)
+}
+
+pub fn start(state: &AppState, name: &str) -> anyhow::Result<()> {
+    scenario_path(name)?;
+    let mut recording = RECORDING.blocking_lock();
+    if recording.is_some() {
+        bail!("A scenario is already being recorded");
+    }
+    if !demo::start_mock() {
+        bail!("Leave the demo before recording a scenario");
+    }
+    *state.conversation.blocking_lock() = context::ConversationContext::default();
+    let started_ms = clock::now_ms();
+    *recording = Some(Recording {
+        scenario: Scenario {
+            schema_version: SCHEMA_VERSION,
+            name: name.trim().to_string(),
+            recorded_at: started_ms,
+            settings: pinned_settings(),
+            initial: snapshot(state),
+            steps: Vec::new(),
+        },
+        started_ms,
+    });
+    Ok(())
+}
+
+// Write down one interaction and the state it left; nothing while not recording
+pub fn record(action: Action, response: &serde_json::Value, state: Snapshot) {
+    let mut recording = RECORDING.blocking_lock();
+    let Some(recording) = recording.as_mut() else {
+        return;
+    };
+    // The token is asked for again on replay
+    let action = match action {
+        Action::Query { query, options, .. } => {
+            let confirm = options
+                .as_ref()
+                .is_some_and(|o| o.get("confirmation_token").is_some());
+            let options = options.map(|mut options| {
+                if let Some(fields) = options.as_object_mut() {
+                    fields.remove("confirmation_token");
+                    fields.remove("confirmation_phrase");
+                }
+                options
+            });
+            Action::Query {
+                query,
+                options,
+                confirm,
+            }
+        }
+        action => action,
+    };
+    recording.scenario.steps.push(RecordedStep {
+        at_ms: clock::now_ms().saturating_sub(recording.started_ms),
+        action,
+        response: response.clone(),
+        state,
+    });
+}
+
+pub fn stop() -> anyhow::Result<ScenarioInfo> {
+    let recording = RECORDING
+        .blocking_lock()
+        .take()
+        .ok_or_else(|| anyhow!("No scenario is being recorded"))?;
+    demo::stop_mock();
+    let scenario = recording.scenario;
+    let path = scenario_path(&scenario.name)?;
+    storage::write_json(&path, &scenario)?;
+    Ok(ScenarioInfo {
+        name: scenario.name,
+        path,
+        steps: scenario.steps.len(),
+        recorded_at: scenario.recorded_at,
+    })
+}
+
+pub fn list() -> Vec<ScenarioInfo> {
+    let Ok(entries) = fs::read_dir(scenario_dir()) else {
+        return Vec::new();
+    };
+    let mut scenarios: Vec<ScenarioInfo> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "json"))
+        .filter_map(|path| {
+            let scenario: Scenario = load(&path).ok()?;
+            Some(ScenarioInfo {
+                name: scenario.name,
+                steps: scenario.steps.len(),
+                recorded_at: scenario.recorded_at,
+                path,
+            })
+        })
+        .collect();
+    scenarios.sort_by(|a, b| a.name.cmp(&b.name));
+    scenarios
+}
+
+fn load(path: &Path) -> anyhow::Result<Scenario> {
+    let raw = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let scenario: Scenario =
+        serde_json::from_str(&raw).with_context(|| format!("parsing {}", path.display()))?;
+    if scenario.schema_version > SCHEMA_VERSION {
+        bail!(
+            "{} was recorded by a newer version of Luminous Nix",
+            path.display()
+        );
+    }
+    Ok(scenario)
+}
+
+// A saved scenario by name, or a scenario file anywhere
+fn find(scenario: &str) -> anyhow::Result<Scenario> {
+    let path = Path::new(scenario);
+    if path.is_file() {
+        return load(path);
+    }
+    let path = scenario_path(scenario)?;
+    if !path.is_file() {
+        bail!("There is no scenario \"{}\"", scenario);
+    }
+    load(&path)
+}
+
+fn normalize(value: &serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(fields) => serde_json::Value::Object(
+            fields
+                .iter()
+                .filter(|(key, _)| !VOLATILE_KEYS.contains(&key.as_str()))
+                .map(|(key, value)| (key.clone(), normalize(value)))
+                .collect(),
+        ),
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.iter().map(normalize).collect())
+        }
+        other => other.clone(),
+    }
+}
+
+// Every place `actual` differs from `expected`, down to the single field
+fn diff(
+    step: usize,
+    path: &str,
+    expected: Option<&serde_json::Value>,
+    actual: Option<&serde_json::Value>,
+    divergences: &mut Vec<Divergence>,
+) {
+    match (expected, actual) {
+        (Some(serde_json::Value::Object(expected)), Some(serde_json::Value::Object(actual))) => {
+            let mut keys: Vec<&String> = expected.keys().chain(actual.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let path = format!("{}/{}", path, key);
+                diff(step, &path, expected.get(key), actual.get(key), divergences);
+            }
+        }
+        (Some(serde_json::Value::Array(expected)), Some(serde_json::Value::Array(actual))) => {
+            for i in 0..expected.len().max(actual.len()) {
+                let path = format!("{}/{}", path, i);
+                diff(step, &path, expected.get(i), actual.get(i), divergences);
+            }
+        }
+        (expected, actual) if expected != actual => divergences.push(Divergence {
+            step,
+            path: path.to_string(),
+            expected: expected.cloned(),
+            actual: actual.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+fn apply(state: &AppState, snapshot: &Snapshot) {
+    *state.components.blocking_lock() = snapshot.components.clone();
+    *state.current_layout.blocking_lock() = snapshot.current_layout.clone();
+}
+
+fn perform(
+    app: &AppHandle,
+    state: &State<AppState>,
+    action: &Action,
+    previous: Option<&serde_json::Value>,
+) -> serde_json::Value {
+    match action {
+        Action::Query {
+            query,
+            options,
+            confirm,
+        } => {
+            let mut options = options.clone();
+            if *confirm {
+                // Whatever the step before was asked to confirm this time
+                let request = previous.map(|r| r["confirmation"].clone());
+                let request = request.unwrap_or_default();
+                let fields = options.get_or_insert_with(|| serde_json::json!({}));
+                fields["confirmation_token"] = request["token"].clone();
+                fields["confirmation_phrase"] = request["phrase"].clone();
+            }
+            crate::answer_query(query.clone(), options, state)
+        }
+        Action::SetComponentState {
+            component_id,
+            state: new_state,
+        } => {
+            let mut components = state.components.blocking_lock();
+            let found = match components.iter_mut().find(|c| c.id == *component_id) {
+                Some(component) => {
+                    component.state = new_state.clone();
+                    true
+                }
+                None => false,
+            };
+            drop(components);
+            if found {
+                panels::emit(
+                    app,
+                    component_id,
+                    "component-state",
+                    serde_json::json!({"id": component_id, "state": new_state}),
+                );
+            }
+            serde_json::json!(found)
+        }
+        Action::SwitchLayout { layout_id } => {
+            let persona = current_persona(state);
+            crate::respond(layouts::switch(state, layout_id, persona))
+        }
+        Action::Click {
+            component_id,
+            selector,
+        } => crate::respond(uidriver::click(app, component_id, selector.as_deref())),
+        Action::Type { component_id, text } => {
+            crate::respond(uidriver::type_text(app, component_id, text))
+        }
+    }
+}
+
+fn run_steps(
+    app: &AppHandle,
+    state: &State<AppState>,
+    scenario: &Scenario,
+    paced: bool,
+) -> anyhow::Result<Vec<Divergence>> {
+    let mut divergences = Vec::new();
+    let mut previous: Option<serde_json::Value> = None;
+    let mut last_ms = 0;
+    for (index, step) in scenario.steps.iter().enumerate() {
+        if paced {
+            std::thread::sleep(Duration::from_millis(step.at_ms.saturating_sub(last_ms)));
+        }
+        last_ms = step.at_ms;
+        let response = perform(app, state, &step.action, previous.as_ref());
+        let expected = serde_json::json!({
+            "response": normalize(&step.response),
+            "state": normalize(&serde_json::to_value(&step.state)?),
+        });
+        let actual = serde_json::json!({
+            "response": normalize(&response),
+            "state": normalize(&serde_json::to_value(snapshot(state))?),
+        });
+        diff(index, "", Some(&expected), Some(&actual), &mut divergences);
+        previous = Some(response);
+    }
+
+    Ok(divergences)
+}
+
+// Run a scenario against a fresh pretend system; `paced` keeps the recorded
+// pauses between steps, otherwise they run back to back
+pub fn replay(
+    app: &AppHandle,
+    state: &State<AppState>,
+    name: &str,
+    paced: bool,
+) -> anyhow::Result<ReplayReport> {
+    let scenario = find(name)?;
+    if recording() {
+        bail!("Stop recording before replaying a scenario");
+    }
+    // Also keeps a second replay from taking over this one's sandbox
+    if !demo::start_mock() {
+        bail!("Leave the demo before replaying a scenario");
+    }
+    let dir = sandbox(&scenario.settings).inspect_err(|_| demo::stop_mock())?;
+    let started_ms = clock::now_ms();
+    // Put back afterwards, so a replay leaves the workspace as it was
+    let workspace = snapshot(state);
+    let conversation = state.conversation.blocking_lock().clone();
+    *state.conversation.blocking_lock() = context::ConversationContext::default();
+    apply(state, &scenario.initial);
+
+    let result = storage::with_root(&dir, || run_steps(app, state, &scenario, paced));
+
+    demo::stop_mock();
+    let _ = fs::remove_dir_all(&dir);
+    apply(state, &workspace);
+    *state.conversation.blocking_lock() = conversation;
+    let divergences = result?;
+    Ok(ReplayReport {
+        scenario: scenario.name,
+        steps: scenario.steps.len(),
+        passed: divergences.is_empty(),
+        divergences,
+        duration_ms: clock::now_ms().saturating_sub(started_ms),
+    })
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn start_test_recording(name: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Start test recording", move |state| {
+        crate::respond(start(state, &name))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn stop_test_recording(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Stop test recording", |_| crate::respond(stop())).await
+}
+
+#[tauri::command]
+pub async fn list_test_scenarios(app: AppHandle) -> Vec<ScenarioInfo> {
+    tasks::blocking_value(&app, "List test scenarios", |_| list()).await
+}
+
+// By name, or the path of a scenario file
+#[tauri::command]
+pub async fn replay_test_scenario(
+    scenario: String,
+    paced: Option<bool>,
+    app: AppHandle,
+) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Replay scenario", move |state| {
+        let response = crate::respond(replay(&handle, state, &scenario, paced.unwrap_or(false)));
+        // The workspace is back as it was before the replay
+        panels::reconcile(&handle, &state.components.blocking_lock());
+        response
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn pinned_settings_only_take_what_shapes_answers() {
+        let dir = storage::scratch_dir("testing");
+        storage::with_root(&dir, || {
+            storage::save(i18n::SETTINGS_FILE, &json!({"language": "de"})).unwrap();
+            storage::save("theme.json", &json!({"name": "dark"})).unwrap();
+            let settings = pinned_settings();
+            assert_eq!(settings.len(), 1);
+            assert_eq!(settings[i18n::SETTINGS_FILE], json!({"language": "de"}));
+        });
+    }
+
+    #[test]
+    fn replays_see_their_settings_and_nothing_else() {
+        let dir = storage::scratch_dir("testing");
+        storage::with_root(&dir, || {
+            storage::save("theme.json", &json!({"name": "dark"})).unwrap();
+            let settings =
+                BTreeMap::from([(i18n::SETTINGS_FILE.to_string(), json!({"language": "es"}))]);
+            let replay_dir = sandbox(&settings).unwrap();
+            assert_eq!(replay_dir, storage::data_dir().join(SANDBOX_DIR));
+            storage::with_root(&replay_dir, || {
+                assert_eq!(i18n::current(), "es");
+                let theme: serde_json::Value = storage::load("theme.json").unwrap();
+                assert!(theme.is_null());
+            });
+            // A fresh one each time
+            let again = sandbox(&BTreeMap::new()).unwrap();
+            storage::with_root(&again, || assert!(pinned_settings().is_empty()));
+        });
+    }
+
+    #[test]
+    fn volatile_values_are_left_out_of_comparisons() {
+        let response =
+            json!({"success": true, "confirmation": {"token": "ab12", "summary": ["x"]}});
+        assert_eq!(
+            normalize(&response),
+            normalize(
+                &json!({"success": true, "confirmation": {"token": "cd34", "summary": ["x"]}})
+            )
+        );
+    }
+}
@@ -0,0 +1,496 @@
+// The active theme: design tokens checked, kept and put on screen
+//
+// A theme is a set of design tokens (colours, sizes, fonts, motion) that the
+// frontend turns into CSS custom properties. Every token is checked against
+// the token schema below before it is kept: the name has to be one the
+// frontend knows, and the value has to be of the token's kind, so a typo or a
+// pasted stylesheet can't break the page. The active theme is stored in the
+// config dir and set on every page as it loads, so it is there from startup.
+// Themes can be exported to a file and imported from one to share them.
+//
+// The tokens sit on top of a light, dark or high-contrast palette. In `auto`
+// mode the palette follows the desktop's colour scheme and high-contrast
+// setting, and changes with them while the app runs. When the user's
+// accessibility needs ask for high contrast, that palette is forced whatever
+// the mode, and the theme's own colours are left out.
+
+use anyhow::{anyhow, bail, Context};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tauri::{AppHandle, Manager, Webview};
+use tokio::sync::Mutex;
+
+use crate::onboarding::AccessibilityNeeds;
+use crate::{a11ycheck, storage, system, tasks, AppState};
+
+const THEME_FILE: &str = "theme.json";
+const SCHEMA_VERSION: u32 = 1;
+
+// What the desktop asks for, as last seen
+static SYSTEM: Mutex<Option<SystemAppearance>> = Mutex::const_new(None);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeMode {
+    // Follow the desktop
+    #[default]
+    Auto,
+    Light,
+    Dark,
+    HighContrast,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    Color,
+    // A CSS length: px, rem, em or %
+    Length,
+    Number,
+    // A CSS duration: ms or s
+    Duration,
+    FontFamily,
+}
+
+// The token schema: every token the frontend reads, and its kind
+const TOKENS: &[(&str, TokenKind)] = &[
+    ("background", TokenKind::Color),
+    ("surface", TokenKind::Color),
+    ("border", TokenKind::Color),
+    ("text", TokenKind::Color),
+    ("text-muted", TokenKind::Color),
+    ("primary", TokenKind::Color),
+    ("primary-text", TokenKind::Color),
+    ("link", TokenKind::Color),
+    ("success", TokenKind::Color),
+    ("warning", TokenKind::Color),
+    ("error", TokenKind::Color),
+    ("focus-ring", TokenKind::Color),
+    ("font-family", TokenKind::FontFamily),
+    ("font-mono", TokenKind::FontFamily),
+    ("font-size", TokenKind::Length),
+    ("line-height", TokenKind::Number),
+    ("radius", TokenKind::Length),
+    ("spacing", TokenKind::Length),
+    ("target-size", TokenKind::Length),
+    ("motion-duration", TokenKind::Duration),
+];
+
+const LIGHT: &[(&str, &str)] = &[
+    ("background", "#ffffff"),
+    ("surface", "#f6f8fa"),
+    ("border", "#d0d7de"),
+    ("text", "#1f2328"),
+    ("text-muted", "#57606a"),
+    ("primary", "#0969da"),
+    ("primary-text", "#ffffff"),
+    ("link", "#0969da"),
+    ("success", "#1a7f37"),
+    ("warning", "#9a6700"),
+    ("error", "#cf222e"),
+    ("focus-ring", "#0969da"),
+];
+
+const DARK: &[(&str, &str)] = &[
+    ("background", "#0d1117"),
+    ("surface", "#161b22"),
+    ("border", "#30363d"),
+    ("text", "#e6edf3"),
+    ("text-muted", "#9198a1"),
+    ("primary", "#58a6ff"),
+    ("primary-text", "#0d1117"),
+    ("link", "#58a6ff"),
+    ("success", "#3fb950"),
+    ("warning", "#d29922"),
+    ("error", "#f85149"),
+    ("focus-ring", "#58a6ff"),
+];
+
+// Every pair the self-test checks is above 7:1
+const HIGH_CONTRAST: &[(&str, &str)] = &[
+    ("background", "#000000"),
+    ("surface", "#000000"),
+    ("border", "#ffffff"),
+    ("text", "#ffffff"),
+    ("text-muted", "#e0e0e0"),
+    ("primary", "#ffff00"),
+    ("primary-text", "#000000"),
+    ("link", "#ffff00"),
+    ("success", "#00ff00"),
+    ("warning", "#ffb000"),
+    ("error", "#ff6b6b"),
+    ("focus-ring", "#ffff00"),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Theme {
+    pub schema_version: u32,
+    pub name: String,
+    #[serde(default)]
+    pub mode: ThemeMode,
+    // Token name (as in the schema) to CSS value
+    pub tokens: BTreeMap<String, String>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            schema_version: SCHEMA_VERSION,
+            name: "Default".to_string(),
+            mode: ThemeMode::Auto,
+            tokens: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SystemAppearance {
+    pub dark: bool,
+    pub high_contrast: bool,
+}
+
+// The theme as shown: the palette in use with the theme's tokens on top
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EffectiveTheme {
+    pub name: String,
+    pub mode: ThemeMode,
+    // Light, Dark or HighContrast: the palette actually in use
+    pub palette: ThemeMode,
+    pub system: SystemAppearance,
+    // High contrast forced by the user's accessibility needs
+    pub forced_high_contrast: bool,
+    pub tokens: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenSpec {
+    pub name: String,
+    pub kind: TokenKind,
+}
+
+// "textMuted", "text_muted" and "--text-muted" all name text-muted
+fn canonical(name: &str) -> Option<&'static str> {
+    let normalize = |s: &str| {
+        s.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase()
+    };
+    let wanted = normalize(name);
+    TOKENS
+        .iter()
+        .map(|(token, _)| *token)
+        .find(|token| normalize(token) == wanted)
+}
+
+fn kind(name: &str) -> Option<TokenKind> {
+    TOKENS.iter().find(|(t, _)| *t == name).map(|(_, k)| *k)
+}
+
+fn number_with_unit(value: &str, units: &[&str]) -> bool {
+    units.iter().any(|unit| {
+        value
+            .strip_suffix(unit)
+            .and_then(|n| n.parse::<f32>().ok())
+            .is_some_and(|n| n.is_finite() && n >= 0.0)
+    })
+}
+
+fn valid_value(kind: TokenKind, value: &str) -> bool {
+    let value = value.trim();
+    match kind {
+        TokenKind::Color => a11ycheck::parse_color(value).is_some(),
+        TokenKind::Length => value == "0" || number_with_unit(value, &["px", "rem", "em", "%"]),
+        TokenKind::Number => value.parse::<f32>().is_ok_and(|n| n.is_finite() && n > 0.0),
+        TokenKind::Duration => number_with_unit(value, &["ms", "s"]),
+        // Family names, quoted or not, separated by commas; nothing that ends the declaration
+        TokenKind::FontFamily => {
+            !value.is_empty()
+                && value.len() <= 200
+                && !value
+                    .chars()
+                    .any(|c| ";{}<>\\".contains(c) || c.is_control())
+        }
+    }
+}
+
+// Check `tokens` against the schema, naming every token that doesn't fit
+pub fn validate(tokens: &serde_json::Value) -> anyhow::Result<BTreeMap<String, String>> {
+    let fields = tokens
+        .as_object()
+        .ok_or_else(|| anyhow!("Theme tokens must be an object of name: value"))?;
+    let mut valid = BTreeMap::new();
+    let mut problems = Vec::new();
+    for (name, value) in fields {
+        let Some(token) = canonical(name) else {
+            problems.push(format!("\"{}\" isn't a theme token", name));
+            continue;
+        };
+        let kind = kind(token).unwrap_or(TokenKind::Color);
+        let Some(value) = value.as_str().filter(|v| valid_value(kind, v)) else {
+            let kind = serde_json::to_value(kind).unwrap_or_default();
+            problems.push(format!(
+                "{} = {} isn't a valid {}",
+                token,
+                value,
+                kind.as_str().unwrap_or("value")
+            ));
+            continue;
+        };
+        valid.insert(token.to_string(), value.trim().to_string());
+    }
+    if !problems.is_empty() {
+        bail!(
+            "The theme doesn't match the token schema: {}",
+            problems.join("; ")
+        );
+    }
+    Ok(valid)
+}
+
+// Bring a stored or imported theme up to the current schema and check it
+pub fn migrate(doc: serde_json::Value) -> anyhow::Result<Theme> {
+    if !doc.is_object() {
+        bail!("A theme must be a JSON object");
+    }
+    let version = doc
+        .get("schema_version")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0) as u32;
+    if version > SCHEMA_VERSION {
+        bail!(
+            "This theme was saved by a newer version (schema {}, this version reads up to {})",
+            version,
+            SCHEMA_VERSION
+        );
+    }
+    let mode = doc
+        .get("mode")
+        .and_then(|m| serde_json::from_value(m.clone()).ok())
+        .unwrap_or_default();
+    // Before the schema, the file was just the tokens
+    let (name, tokens) = match version {
+        0 => ("Custom".to_string(), doc),
+        _ => (
+            doc.get("name")
+                .and_then(|n| n.as_str())
+                .unwrap_or("Custom")
+                .to_string(),
+            doc.get("tokens").cloned().unwrap_or_default(),
+        ),
+    };
+    Ok(Theme {
+        schema_version: SCHEMA_VERSION,
+        name,
+        mode,
+        tokens: validate(&tokens)?,
+    })
+}
+
+pub fn active() -> Theme {
+    storage::load::<Option<serde_json::Value>>(THEME_FILE)
+        .ok()
+        .flatten()
+        .and_then(|doc| migrate(doc).ok())
+        .unwrap_or_default()
+}
+
+fn gsetting(schema: &str, key: &str) -> Option<String> {
+    system::run("gsettings", &["get", schema, key])
+        .ok()
+        .map(|value| value.trim().trim_matches('\'').to_string())
+}
+
+// Ask the desktop; `dark` is what the window reports, when it knows
+fn detect(dark: Option<bool>) -> SystemAppearance {
+    let gtk_theme = std::env::var("GTK_THEME")
+        .ok()
+        .or_else(|| gsetting("org.gnome.desktop.interface", "gtk-theme"))
+        .unwrap_or_default()
+        .to_lowercase();
+    let high_contrast = gsetting("org.gnome.desktop.a11y.interface", "high-contrast")
+        .is_some_and(|v| v == "true")
+        || gtk_theme.contains("highcontrast");
+    let dark = dark.unwrap_or_else(|| {
+        gsetting("org.gnome.desktop.interface", "color-scheme").is_some_and(|v| v == "prefer-dark")
+            || gtk_theme.contains("dark")
+    });
+    SystemAppearance {
+        dark,
+        high_contrast,
+    }
+}
+
+pub fn system() -> SystemAppearance {
+    *SYSTEM.blocking_lock().get_or_insert_with(|| detect(None))
+}
+
+pub fn resolve(theme: &Theme, needs: &AccessibilityNeeds) -> EffectiveTheme {
+    let system = system();
+    let forced_high_contrast = needs.high_contrast && theme.mode != ThemeMode::HighContrast;
+    let palette = match theme.mode {
+        _ if needs.high_contrast => ThemeMode::HighContrast,
+        ThemeMode::Auto if system.high_contrast => ThemeMode::HighContrast,
+        ThemeMode::Auto if system.dark => ThemeMode::Dark,
+        ThemeMode::Auto => ThemeMode::Light,
+        mode => mode,
+    };
+    let colors = match palette {
+        ThemeMode::Dark => DARK,
+        ThemeMode::HighContrast => HIGH_CONTRAST,
+        _ => LIGHT,
+    };
+    let mut tokens: BTreeMap<String, String> = colors
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    for (name, value) in &theme.tokens {
+        // High contrast keeps its own colours; sizes and fonts still apply
+        if palette == ThemeMode::HighContrast && kind(name) == Some(TokenKind::Color) {
+            continue;
+        }
+        tokens.insert(name.clone(), value.clone());
+    }
+    EffectiveTheme {
+        name: theme.name.clone(),
+        mode: theme.mode,
+        palette,
+        system,
+        forced_high_contrast,
+        tokens,
+    }
+}
+
+pub fn effective(state: &AppState) -> EffectiveTheme {
+    resolve(&active(), &a11ycheck::needs(state))
+}
+
+// Script setting each token as a CSS custom property on the page, clearing
+// the ones the theme leaves out, and naming the palette for the stylesheet
+fn script(theme: &EffectiveTheme) -> anyhow::Result<String> {
+    let names: Vec<&str> = TOKENS.iter().map(|(name, _)| *name).collect();
+    let palette = serde_json::to_value(theme.palette)?;
+    let scheme = if theme.palette == ThemeMode::Light {
+        "light"
+    } else {
+        "dark"
+    };
+    Ok(format!(
+        "(function(t,all,p,cs){{var e=document.documentElement,s=e.style;all.forEach(function(k){{if(!(k in t))s.removeProperty('--'+k);}});for(var k in t)s.setProperty('--'+k,t[k]);e.dataset.theme=p;s.colorScheme=cs;}})({},{},{},'{}');",
+        serde_json::to_string(&theme.tokens)?,
+        serde_json::to_string(&names)?,
+        palette,
+        scheme
+    ))
+}
+
+// Put the theme on a page that has just loaded
+pub fn apply(webview: &Webview) {
+    // The window knows the desktop's scheme before any theme change is seen
+    if SYSTEM.blocking_lock().is_none() {
+        let dark = webview
+            .window()
+            .theme()
+            .ok()
+            .map(|t| t == tauri::Theme::Dark);
+        *SYSTEM.blocking_lock() = Some(detect(dark));
+    }
+    let theme = effective(&webview.app_handle().state::<AppState>());
+    if let Ok(script) = script(&theme) {
+        let _ = webview.eval(&script);
+    }
+}
+
+// Show the current theme in every window
+pub fn refresh(app: &AppHandle) -> anyhow::Result<EffectiveTheme> {
+    let theme = effective(&app.state::<AppState>());
+    let script = script(&theme)?;
+    for window in app.webview_windows().values() {
+        window.eval(&script)?;
+    }
+    Ok(theme)
+}
+
+// The desktop switched between light and dark, and maybe high contrast
+pub fn system_changed(app: &AppHandle, theme: tauri::Theme) {
+    let appearance = detect(Some(theme == tauri::Theme::Dark));
+    let previous = SYSTEM.blocking_lock().replace(appearance);
+    if previous != Some(appearance) && active().mode == ThemeMode::Auto {
+        let _ = refresh(app);
+    }
+}
+
+pub fn set(app: &AppHandle, theme: Theme) -> anyhow::Result<EffectiveTheme> {
+    storage::save(THEME_FILE, &theme)?;
+    refresh(app)
+}
+
+pub fn export(path: &Path) -> anyhow::Result<()> {
+    storage::write_json(path, &active())
+}
+
+pub fn import(app: &AppHandle, path: &Path) -> anyhow::Result<EffectiveTheme> {
+    if !path.exists() {
+        bail!("{} doesn't exist", path.display());
+    }
+    let doc: serde_json::Value = storage::read_json(path)?;
+    let theme = migrate(doc).with_context(|| format!("Importing {}", path.display()))?;
+    set(app, theme)
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_theme(app: AppHandle) -> Theme {
+    tasks::blocking_value(&app, "Get theme", |_| active()).await
+}
+
+#[tauri::command]
+pub async fn get_effective_theme(app: AppHandle) -> EffectiveTheme {
+    let handle = app.clone();
+    tasks::blocking_value(&app, "Get effective theme", move |_| {
+        effective(&handle.state::<AppState>())
+    })
+    .await
+}
+
+// `auto` follows the desktop; high contrast is still forced by the profile
+#[tauri::command]
+pub async fn set_theme_mode(mode: ThemeMode, app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Set theme mode", move |_| {
+        crate::respond(set(&handle, Theme { mode, ..active() }))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_theme_schema() -> Vec<TokenSpec> {
+    TOKENS
+        .iter()
+        .map(|(name, kind)| TokenSpec {
+            name: name.to_string(),
+            kind: *kind,
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub async fn export_theme(path: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Export theme", move |_| {
+        let path = std::path::PathBuf::from(path);
+        crate::respond(export(&path).map(|()| path))
+    })
+    .await
+}
+
+// Replace the active theme with an exported one
+#[tauri::command]
+pub async fn import_theme(file: String, app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Import theme", move |_| {
+        crate::respond(import(&handle, Path::new(&file)))
+    })
+    .await
+}
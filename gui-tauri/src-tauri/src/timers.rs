@@ -0,0 +1,372 @@
+// Natural-language systemd timers
+//
+// Turns requests like "back up my photos folder every night at 2am" into a
+// declarative timer + service pair, validating the OnCalendar expression with
+// systemd-analyze and showing the next runs before anything is written.
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::nixgen::{self, NixModule, NixOption, Target};
+use crate::sandbox::Simulation;
+use crate::{managed, system, tasks};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimerPlan {
+    pub name: String,
+    pub description: String,
+    pub on_calendar: String,
+    pub command: String,
+    // nixpkgs attributes the command needs on PATH
+    pub packages: Vec<String>,
+    // User timers go to home-manager, system timers to the NixOS config
+    pub user_level: bool,
+    pub next_runs: Vec<String>,
+    pub module_preview: String,
+    // Evaluation of the would-be configuration; nothing is written until it passes
+    #[serde(default)]
+    pub simulation: Simulation,
+}
+
+fn parse_hour(word: &str) -> Option<(u32, u32)> {
+    let word = word.trim_end_matches([',', '.']);
+    let (digits, pm, am) = if let Some(d) = word.strip_suffix("pm") {
+        (d, true, false)
+    } else if let Some(d) = word.strip_suffix("am") {
+        (d, false, true)
+    } else {
+        (word, false, false)
+    };
+    let (hour, minute) = match digits.split_once(':') {
+        Some((h, m)) => (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?),
+        None => (digits.parse::<u32>().ok()?, 0),
+    };
+    let hour = match (hour, pm, am) {
+        (12, false, true) => 0,
+        (h, true, _) if h < 12 => h + 12,
+        (h, _, _) => h,
+    };
+    (hour < 24 && minute < 60).then_some((hour, minute))
+}
+
+// Time of day mentioned after "at", or implied by words like "night"/"morning"
+fn time_of_day(text: &str) -> Option<(u32, u32)> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let explicit = words.windows(2).find_map(|w| {
+        if w[0] != "at" {
+            return None;
+        }
+        match w[1] {
+            "midnight" => Some((0, 0)),
+            "noon" => Some((12, 0)),
+            other => parse_hour(other),
+        }
+    });
+    explicit.or_else(|| {
+        if text.contains("night") {
+            Some((2, 0))
+        } else if text.contains("morning") {
+            Some((8, 0))
+        } else if text.contains("evening") {
+            Some((19, 0))
+        } else {
+            None
+        }
+    })
+}
+
+const WEEKDAYS: &[(&str, &str)] = &[
+    ("monday", "Mon"),
+    ("tuesday", "Tue"),
+    ("wednesday", "Wed"),
+    ("thursday", "Thu"),
+    ("friday", "Fri"),
+    ("saturday", "Sat"),
+    ("sunday", "Sun"),
+];
+
+// Translate the schedule part of a request into an OnCalendar expression
+pub fn parse_schedule(text: &str) -> anyhow::Result<String> {
+    let text = text.to_lowercase();
+    let words: Vec<&str> = text.split_whitespace().collect();
+
+    // "every 15 minutes", "every 6 hours"
+    if let Some(pos) = words.iter().position(|w| *w == "every") {
+        if let (Some(n), Some(unit)) = (
+            words.get(pos + 1).and_then(|n| n.parse::<u32>().ok()),
+            words.get(pos + 2),
+        ) {
+            if n == 0 {
+                bail!("An interval of zero makes no sense");
+            }
+            return match unit.trim_end_matches('s') {
+                "minute" | "min" => Ok(format!("*:0/{}", n)),
+                "hour" => Ok(format!("0/{}:00", n)),
+                "day" => Ok(format!("*-*-1/{} 00:00:00", n)),
+                _ => Err(anyhow!("Unsupported interval unit '{}'", unit)),
+            };
+        }
+    }
+
+    let (hour, minute) = time_of_day(&text).unwrap_or((0, 0));
+    let time = format!("{:02}:{:02}:00", hour, minute);
+
+    if text.contains("every minute") {
+        return Ok("*:*:00".to_string());
+    }
+    if text.contains("hourly") || text.contains("every hour") {
+        return Ok("hourly".to_string());
+    }
+    if text.contains("weekday") {
+        return Ok(format!("Mon..Fri *-*-* {}", time));
+    }
+    if text.contains("weekend") {
+        return Ok(format!("Sat,Sun *-*-* {}", time));
+    }
+    if let Some((_, short)) = WEEKDAYS.iter().find(|(day, _)| text.contains(day)) {
+        return Ok(format!("{} *-*-* {}", short, time));
+    }
+    if text.contains("weekly") || text.contains("every week") {
+        return Ok(format!("Mon *-*-* {}", time));
+    }
+    if text.contains("monthly") || text.contains("every month") {
+        return Ok(format!("*-*-01 {}", time));
+    }
+    if text.contains("daily")
+        || text.contains("every day")
+        || text.contains("every night")
+        || text.contains("nightly")
+        || time_of_day(&text).is_some()
+    {
+        return Ok(format!("*-*-* {}", time));
+    }
+    bail!("Could not find a schedule in '{}'", text)
+}
+
+// Well-known folder words -> XDG user directories
+fn resolve_folder(word: &str) -> String {
+    let home = system::home_dir();
+    let dir = match word {
+        "photos" | "pictures" | "images" => "Pictures",
+        "documents" | "docs" => "Documents",
+        "music" => "Music",
+        "videos" | "movies" => "Videos",
+        "downloads" => "Downloads",
+        "desktop" => "Desktop",
+        "home" => "",
+        other => other,
+    };
+    home.join(dir)
+        .to_string_lossy()
+        .trim_end_matches('/')
+        .to_string()
+}
+
+pub fn slug(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>()
+        .split('-')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+// (name, description, command, packages, user_level) for tasks we can infer
+fn infer_task(text: &str) -> Option<(String, String, String, Vec<String>, bool)> {
+    let text = text.to_lowercase();
+    let words: Vec<&str> = text.split_whitespace().collect();
+
+    if text.contains("back up") || text.contains("backup") {
+        let start = words.iter().position(|w| *w == "up" || *w == "backup")? + 1;
+        let folder = words[start..]
+            .iter()
+            .find(|w| !matches!(**w, "my" | "the" | "folder" | "directory"))?;
+        let source = resolve_folder(folder);
+        let target = system::home_dir().join("Backups").join(folder);
+        return Some((
+            format!("backup-{}", slug(folder)),
+            format!("Back up {}", source),
+            format!(
+                "mkdir -p {target} && rsync -a --delete {source}/ {target}/",
+                target = target.display(),
+                source = source
+            ),
+            vec!["rsync".to_string(), "coreutils".to_string()],
+            true,
+        ));
+    }
+    if text.contains("garbage") || text.contains("clean up") || text.contains("cleanup") {
+        return Some((
+            "nix-gc".to_string(),
+            "Collect old Nix store garbage".to_string(),
+            "nix-collect-garbage --delete-older-than 30d".to_string(),
+            vec!["nix".to_string()],
+            false,
+        ));
+    }
+    if text.contains("optimise") || text.contains("optimize") || text.contains("dedup") {
+        return Some((
+            "nix-optimise".to_string(),
+            "Deduplicate the Nix store".to_string(),
+            "nix-store --optimise".to_string(),
+            vec!["nix".to_string()],
+            false,
+        ));
+    }
+    None
+}
+
+// Run systemd-analyze to validate the expression and list the next three elapses
+pub fn next_runs(on_calendar: &str) -> anyhow::Result<Vec<String>> {
+    let output = system::run(
+        "systemd-analyze",
+        &["calendar", "--iterations=3", on_calendar],
+    )
+    .map_err(|e| anyhow!("Invalid schedule '{}': {}", on_calendar, e))?;
+    Ok(output
+        .lines()
+        .map(str::trim)
+        .filter_map(|l| {
+            l.strip_prefix("Next elapse:")
+                .or_else(|| l.strip_prefix("Iter. #2:"))
+                .or_else(|| l.strip_prefix("Iter. #3:"))
+        })
+        .map(|t| t.trim().to_string())
+        .collect())
+}
+
+fn build_module(plan: &TimerPlan) -> NixModule {
+    let name = nixgen::attr(&plan.name);
+    let packages = plan.packages.join(" ");
+    let script = nixgen::string(&plan.command);
+    if plan.user_level {
+        let mut module = NixModule::new(
+            &format!("timer-{}", plan.name),
+            &plan.description,
+            Target::HomeManager,
+        );
+        module.set(NixOption::new(
+            format!("systemd.user.services.{}", name),
+            format!(
+                "{{\n    Unit.Description = {desc};\n    Service = {{\n      Type = \"oneshot\";\n      Environment = [ \"PATH=${{lib.makeBinPath (with pkgs; [ {packages} ])}}\" ];\n      ExecStart = toString (pkgs.writeShellScript {name} {script});\n    }};\n  }}",
+                desc = nixgen::string(&plan.description),
+                packages = packages,
+                name = nixgen::string(&plan.name),
+                script = script
+            ),
+        ));
+        module.set(NixOption::new(
+            format!("systemd.user.timers.{}", name),
+            format!(
+                "{{\n    Unit.Description = {desc};\n    Timer = {{ OnCalendar = {cal}; Persistent = true; }};\n    Install.WantedBy = [ \"timers.target\" ];\n  }}",
+                desc = nixgen::string(&plan.description),
+                cal = nixgen::string(&plan.on_calendar)
+            ),
+        ));
+        module
+    } else {
+        let mut module = NixModule::new(
+            &format!("timer-{}", plan.name),
+            &plan.description,
+            Target::Nixos,
+        );
+        module.set(NixOption::new(
+            format!("systemd.services.{}", name),
+            format!(
+                "{{\n    description = {desc};\n    serviceConfig.Type = \"oneshot\";\n    path = with pkgs; [ {packages} ];\n    script = {script};\n  }}",
+                desc = nixgen::string(&plan.description),
+                packages = packages,
+                script = script
+            ),
+        ));
+        module.set(NixOption::new(
+            format!("systemd.timers.{}", name),
+            format!(
+                "{{\n    wantedBy = [ \"timers.target\" ];\n    timerConfig = {{ OnCalendar = {cal}; Persistent = true; }};\n  }}",
+                cal = nixgen::string(&plan.on_calendar)
+            ),
+        ));
+        module
+    }
+}
+
+// Build a plan from free text; an explicit command overrides task inference
+pub fn plan(request: &str, command: Option<&str>, name: Option<&str>) -> anyhow::Result<TimerPlan> {
+    let on_calendar = parse_schedule(request)?;
+    let (inferred_name, description, command, packages, user_level) = match command {
+        Some(command) => (
+            slug(command.split_whitespace().next().unwrap_or("task")),
+            format!("Run {}", command),
+            command.to_string(),
+            Vec::new(),
+            true,
+        ),
+        None => infer_task(request).ok_or_else(|| {
+            anyhow!("I understood the schedule but not what to run; please give the command")
+        })?,
+    };
+    let mut plan = TimerPlan {
+        name: name.map(slug).unwrap_or(inferred_name),
+        description,
+        next_runs: next_runs(&on_calendar)?,
+        on_calendar,
+        command,
+        packages,
+        user_level,
+        module_preview: String::new(),
+        simulation: Simulation::default(),
+    };
+    let module = build_module(&plan);
+    plan.module_preview = module.render();
+    plan.simulation = module.simulate();
+    Ok(plan)
+}
+
+// Re-validate the schedule and write the module
+pub fn apply(plan: &TimerPlan) -> anyhow::Result<std::path::PathBuf> {
+    next_runs(&plan.on_calendar)?;
+    build_module(plan).write()
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn plan_timer(
+    request: String,
+    command: Option<String>,
+    name: Option<String>,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Plan timer", move |_| {
+        crate::respond(plan(&request, command.as_deref(), name.as_deref()))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn apply_timer(plan: TimerPlan, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Apply timer", move |_| {
+        if let Some(refusal) = managed::guard("configure", &format!("Schedule {}", plan.name)) {
+            return refusal;
+        }
+        crate::respond(apply(&plan).map(|path| {
+            serde_json::json!({
+                "module_path": path,
+                "next_step": if plan.user_level {
+                    "Run home-manager switch to start the timer"
+                } else {
+                    "Rebuild the system to start the timer"
+                },
+            })
+        }))
+    })
+    .await
+}
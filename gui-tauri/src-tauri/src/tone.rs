@@ -0,0 +1,216 @@
+// Response tone: the same outcome worded in the user's chosen personality style
+//
+// Backend results stay as they are; a styled `message` is added next to them
+// for the frontend (and TTS) to show. Each style has templates for success,
+// error and progress text, with the action or error slotted in. The style is
+// chosen manually or by tonedetect and stored in personality.json in the
+// config dir.
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::{storage, tasks, wellbeing};
+
+pub const SETTINGS_FILE: &str = "personality.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Style {
+    Minimal,
+    #[default]
+    Friendly,
+    Encouraging,
+    Playful,
+    Sacred,
+}
+
+// `{action}`, `{error}` and `{step}` are replaced with the specifics
+struct Templates {
+    success: &'static str,
+    error: &'static str,
+    progress: &'static str,
+}
+
+impl Style {
+    pub const ALL: [Style; 5] = [
+        Style::Minimal,
+        Style::Friendly,
+        Style::Encouraging,
+        Style::Playful,
+        Style::Sacred,
+    ];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            Style::Minimal => "minimal",
+            Style::Friendly => "friendly",
+            Style::Encouraging => "encouraging",
+            Style::Playful => "playful",
+            Style::Sacred => "sacred",
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            Style::Minimal => "Just the facts",
+            Style::Friendly => "Warm and conversational",
+            Style::Encouraging => "Celebrates progress, reassures on mistakes",
+            Style::Playful => "Light-hearted, with the odd emoji",
+            Style::Sacred => "Calm and mindful",
+        }
+    }
+
+    fn templates(self) -> Templates {
+        match self {
+            Style::Minimal => Templates {
+                success: "{action}: done.",
+                error: "{error}",
+                progress: "{step}…",
+            },
+            Style::Friendly => Templates {
+                success: "All set! {action} worked.",
+                error: "Hmm, that didn't work: {error}",
+                progress: "Working on it: {step}…",
+            },
+            Style::Encouraging => Templates {
+                success: "Nice work! {action} is done. You're getting the hang of this.",
+                error: "That didn't work this time: {error}. Errors are how everyone learns; let's try again.",
+                progress: "Making progress: {step}…",
+            },
+            Style::Playful => Templates {
+                success: "Ta-da! {action} ✨",
+                error: "Oops! {error} 🙃 Let's give it another spin.",
+                progress: "Tinkering away: {step} 🔧",
+            },
+            Style::Sacred => Templates {
+                success: "{action} is complete. May it serve you well.",
+                error: "A pause on the path: {error}. Take a breath; we can try again.",
+                progress: "Unfolding gently: {step}…",
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for Style {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim().to_lowercase();
+        Style::ALL
+            .into_iter()
+            .find(|style| style.id() == s)
+            .ok_or_else(|| {
+                let ids: Vec<&str> = Style::ALL.iter().map(|s| s.id()).collect();
+                anyhow!("Unknown style '{}'; choose one of {}", s, ids.join(", "))
+            })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PersonalitySetting {
+    style: Style,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StyleInfo {
+    pub style: Style,
+    pub description: String,
+    pub example: String,
+    pub active: bool,
+    // Sacred is only offered while consciousness tracking is on
+    pub available: bool,
+}
+
+pub fn available(style: Style) -> bool {
+    style != Style::Sacred || wellbeing::sacred_tech_enabled()
+}
+
+pub fn current() -> Style {
+    storage::load::<PersonalitySetting>(SETTINGS_FILE)
+        .map(|s| s.style)
+        .ok()
+        .filter(|style| available(*style))
+        .unwrap_or_default()
+}
+
+pub fn set(style: Style) -> anyhow::Result<()> {
+    if !available(style) {
+        bail!(
+            "The {} style needs consciousness tracking turned on in wellbeing settings",
+            style.id()
+        );
+    }
+    storage::save(SETTINGS_FILE, &PersonalitySetting { style }).map(|_| ())
+}
+
+// Sentence-internal text reads better without its own trailing full stop
+fn fill(template: &str, key: &str, value: &str) -> String {
+    template.replace(key, value.trim().trim_end_matches('.'))
+}
+
+pub fn success(style: Style, action: &str) -> String {
+    fill(style.templates().success, "{action}", action)
+}
+
+pub fn error(style: Style, error: &str) -> String {
+    fill(style.templates().error, "{error}", error)
+}
+
+pub fn progress(style: Style, step: &str) -> String {
+    fill(style.templates().progress, "{step}", step)
+}
+
+// Add a styled `message` to a response envelope for `action`. Responses that
+// are still waiting on the user (confirmations, clarifications) are left alone
+pub fn apply(response: &mut serde_json::Value, action: &str) {
+    let style = current();
+    let Some(map) = response.as_object_mut() else {
+        return;
+    };
+    if map.contains_key("needs_confirmation") || map.contains_key("needs_clarification") {
+        return;
+    }
+    let message = match map.get("success").and_then(|s| s.as_bool()) {
+        Some(true) => success(style, action),
+        _ => match map.get("error").and_then(|e| e.as_str()) {
+            Some(text) => error(style, text),
+            None => return,
+        },
+    };
+    map.insert("message".to_string(), serde_json::json!(message));
+}
+
+pub fn styles() -> Vec<StyleInfo> {
+    let active = current();
+    Style::ALL
+        .into_iter()
+        .map(|style| StyleInfo {
+            style,
+            description: style.describe().to_string(),
+            example: success(style, "Installing firefox"),
+            active: style == active,
+            available: available(style),
+        })
+        .collect()
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_personalities(app: AppHandle) -> Vec<StyleInfo> {
+    tasks::blocking_value(&app, "Get personalities", |_| styles()).await
+}
+
+// A manual choice also stops tonedetect from switching away from it
+#[tauri::command]
+pub async fn set_personality(style: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set personality", move |state| {
+        let result = style.parse().and_then(|style: Style| {
+            set(style)?;
+            crate::tonedetect::manual_choice(state, style)
+        });
+        crate::respond(result.map(|()| styles()))
+    })
+    .await
+}
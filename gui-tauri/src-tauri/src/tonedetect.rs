@@ -0,0 +1,266 @@
+// Infer the tone::Style the user would prefer from how they write
+//
+// Every typed query updates running averages in the profile: message length,
+// emoji, terse command-style phrasing, corrections ("no, I meant ...") and
+// warm phrasing. The style those point to has to stay in the lead for a
+// sustained run of messages before the active style is switched, and every
+// switch is announced in the response. Picking a style by hand stops automatic
+// switching until detection is turned back on.
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::tone::{self, Style};
+use crate::userprofile::{self, UserProfile};
+use crate::{tasks, AppState};
+
+const PREFERENCE_KEY: &str = "personality_detection";
+// Messages seen before any switch is considered
+const MIN_SAMPLES: u32 = 20;
+// Consecutive messages the same style must lead for
+const SUSTAINED: u32 = 15;
+// Weight of the newest message in the running averages
+const SMOOTHING: f32 = 0.1;
+
+const CORRECTION_STARTS: &[&str] = &[
+    "no ",
+    "no,",
+    "not that",
+    "i meant",
+    "i mean",
+    "actually",
+    "wrong",
+    "that's not",
+    "thats not",
+    "undo that",
+];
+const WARM_WORDS: &[&str] = &[
+    "please",
+    "thank",
+    "thanks",
+    "grateful",
+    "appreciate",
+    "kindly",
+    "lovely",
+];
+const EMOTICONS: &[&str] = &[":)", ":-)", ":D", ";)", "<3", ":P"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Evidence {
+    pub disabled: bool,
+    pub samples: u32,
+    pub avg_words: f32,
+    pub emoji_rate: f32,
+    pub terse_rate: f32,
+    pub correction_rate: f32,
+    pub warmth_rate: f32,
+    pub leading: Option<Style>,
+    pub streak: u32,
+    // Set when the user picks a style themselves; detection then only suggests
+    pub manual: Option<Style>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StyleSwitch {
+    pub from: Style,
+    pub to: Style,
+    pub reason: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectionStatus {
+    pub evidence: Evidence,
+    pub suggested: Style,
+    pub active: Style,
+    pub automatic: bool,
+}
+
+fn has_emoji(text: &str) -> bool {
+    EMOTICONS.iter().any(|e| text.contains(e))
+        || text
+            .chars()
+            .any(|c| matches!(c as u32, 0x1F300..=0x1FAFF | 0x2600..=0x27BF | 0x1F000..=0x1F2FF))
+}
+
+fn is_correction(text: &str) -> bool {
+    let text = text.trim().to_lowercase();
+    text == "no" || CORRECTION_STARTS.iter().any(|s| text.starts_with(s))
+}
+
+fn is_warm(text: &str) -> bool {
+    let text = text.to_lowercase();
+    text.split(|c: char| !c.is_alphanumeric())
+        .any(|word| WARM_WORDS.contains(&word))
+}
+
+// "install vim", "gc": a few words, no question or pleasantries
+fn is_terse(text: &str) -> bool {
+    text.split_whitespace().count() <= 3 && !text.contains('?') && !is_warm(text)
+}
+
+fn average(current: f32, observed: f32, samples: u32) -> f32 {
+    // Plain mean until there are enough samples for the running average to settle
+    let weight = (1.0 / samples as f32).max(SMOOTHING);
+    current + (observed - current) * weight
+}
+
+fn flag(value: bool) -> f32 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+impl Evidence {
+    pub fn observe(&mut self, text: &str) {
+        self.samples += 1;
+        let n = self.samples;
+        let words = text.split_whitespace().count() as f32;
+        self.avg_words = average(self.avg_words, words, n);
+        self.emoji_rate = average(self.emoji_rate, flag(has_emoji(text)), n);
+        self.terse_rate = average(self.terse_rate, flag(is_terse(text)), n);
+        self.correction_rate = average(self.correction_rate, flag(is_correction(text)), n);
+        self.warmth_rate = average(self.warmth_rate, flag(is_warm(text)), n);
+
+        let suggested = self.suggested().0;
+        if self.leading == Some(suggested) {
+            self.streak += 1;
+        } else {
+            self.leading = Some(suggested);
+            self.streak = 1;
+        }
+    }
+
+    // The style the evidence points to, and why
+    pub fn suggested(&self) -> (Style, String) {
+        if self.emoji_rate >= 0.25 {
+            (
+                Style::Playful,
+                format!("{:.0}% of your messages use emoji", self.emoji_rate * 100.0),
+            )
+        } else if self.correction_rate >= 0.25 {
+            (
+                Style::Encouraging,
+                "things often needed a second try lately".to_string(),
+            )
+        } else if self.terse_rate >= 0.6 && self.avg_words < 4.0 {
+            (
+                Style::Minimal,
+                "you mostly type short, command-like requests".to_string(),
+            )
+        } else if self.warmth_rate >= 0.4 && self.avg_words >= 8.0 && tone::available(Style::Sacred)
+        {
+            (
+                Style::Sacred,
+                "you tend to write unhurried, gracious messages".to_string(),
+            )
+        } else {
+            (
+                Style::Friendly,
+                "you write in a conversational way".to_string(),
+            )
+        }
+    }
+}
+
+fn evidence(profile: &UserProfile) -> Evidence {
+    profile
+        .preferences
+        .get(PREFERENCE_KEY)
+        .and_then(|e| serde_json::from_value(e.clone()).ok())
+        .unwrap_or_default()
+}
+
+fn store(profile: &mut UserProfile, evidence: &Evidence) -> anyhow::Result<()> {
+    if !profile.preferences.is_object() {
+        profile.preferences = serde_json::json!({});
+    }
+    profile.preferences[PREFERENCE_KEY] = serde_json::to_value(evidence)?;
+    userprofile::save(profile)
+}
+
+// Record a typed query; returns the switch when it tipped the style over
+pub fn observe(state: &AppState, text: &str) -> Option<StyleSwitch> {
+    if text.trim().is_empty() {
+        return None;
+    }
+    let mut profile = state.user_profile.blocking_lock();
+    let profile = profile.get_or_insert_with(UserProfile::default);
+    let mut evidence = evidence(profile);
+    evidence.observe(text);
+
+    let active = tone::current();
+    let (suggested, reason) = evidence.suggested();
+    let switch = (!evidence.disabled
+        && evidence.manual.is_none()
+        && evidence.samples >= MIN_SAMPLES
+        && evidence.streak >= SUSTAINED
+        && suggested != active)
+        .then(|| StyleSwitch {
+            from: active,
+            to: suggested,
+            message: format!(
+                "I've switched to a {} style because {}. You can pick another style in settings at any time.",
+                suggested.id(),
+                reason
+            ),
+            reason,
+        });
+    if let Some(switch) = &switch {
+        if tone::set(switch.to).is_err() {
+            return None;
+        }
+        evidence.streak = 0;
+    }
+    let _ = store(profile, &evidence);
+    switch
+}
+
+// The user chose a style themselves; stop switching away from it
+pub fn manual_choice(state: &AppState, style: Style) -> anyhow::Result<()> {
+    let mut profile = state.user_profile.blocking_lock();
+    let profile = profile.get_or_insert_with(UserProfile::default);
+    let mut evidence = evidence(profile);
+    evidence.manual = Some(style);
+    store(profile, &evidence)
+}
+
+fn status(state: &AppState) -> DetectionStatus {
+    let profile = state.user_profile.blocking_lock();
+    let evidence = profile.as_ref().map(evidence).unwrap_or_default();
+    DetectionStatus {
+        suggested: evidence.suggested().0,
+        active: tone::current(),
+        automatic: !evidence.disabled && evidence.manual.is_none(),
+        evidence,
+    }
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_personality_detection(app: AppHandle) -> DetectionStatus {
+    tasks::blocking_value(&app, "Get personality detection", |state| status(state)).await
+}
+
+// Turning detection on also hands the style back from a manual choice
+#[tauri::command]
+pub async fn set_personality_detection(enabled: bool, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set personality detection", move |state| {
+        let result = {
+            let mut profile = state.user_profile.blocking_lock();
+            let profile = profile.get_or_insert_with(UserProfile::default);
+            let mut evidence = evidence(profile);
+            evidence.disabled = !enabled;
+            if enabled {
+                evidence.manual = None;
+            }
+            store(profile, &evidence)
+        };
+        crate::respond(result.map(|()| status(state)))
+    })
+    .await
+}
@@ -0,0 +1,201 @@
+// System tray icon with quick actions and the system's status
+//
+// The menu offers a few actions from the action registry, under the same
+// titles the palette shows, and a status section: pending flake input
+// updates and the operations still running. It is rebuilt whenever an
+// operation starts or ends, an update check finishes or voice input turns on
+// or off. Voice input toggles right from the tray; every other action opens
+// the window, which carries it out like the palette would.
+
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Emitter, Listener, Manager, Wry};
+
+use crate::actions::{self, Action, Invocation};
+use crate::progress::{self, OperationEvent};
+use crate::{metrics, voice, AppState};
+
+const TRAY_ID: &str = "main";
+// Action registry ids, in menu order
+const TRAY_ACTIONS: &[&str] = &["check-updates", "collect-garbage", "voice-input"];
+const MAX_RUNNING: usize = 3;
+const ACTION_PREFIX: &str = "action:";
+const SHOW: &str = "show";
+const QUIT: &str = "quit";
+
+struct Status {
+    pending_updates: Vec<String>,
+    running: Vec<String>,
+    listening: bool,
+}
+
+fn status() -> Status {
+    Status {
+        pending_updates: metrics::pending_updates(),
+        running: progress::running().into_iter().map(|o| o.message).collect(),
+        listening: voice::listening(),
+    }
+}
+
+fn tray_actions(app: &AppHandle) -> Vec<Action> {
+    let all = actions::all(&app.state::<AppState>());
+    TRAY_ACTIONS
+        .iter()
+        .filter_map(|id| all.iter().find(|a| a.id == *id).cloned())
+        .collect()
+}
+
+fn tooltip(status: &Status) -> String {
+    let mut parts = Vec::new();
+    match status.pending_updates.len() {
+        0 => {}
+        1 => parts.push("1 update available".to_string()),
+        n => parts.push(format!("{} updates available", n)),
+    }
+    if !status.running.is_empty() {
+        parts.push(format!("{} running", status.running.len()));
+    }
+    if parts.is_empty() {
+        "Luminous Nix".to_string()
+    } else {
+        format!("Luminous Nix - {}", parts.join(", "))
+    }
+}
+
+fn menu(app: &AppHandle, status: &Status) -> tauri::Result<Menu<Wry>> {
+    let menu = Menu::new(app)?;
+    // Status lines, shown greyed out
+    let info = |text: &str| MenuItem::new(app, text, false, None::<&str>);
+
+    let updates = match status.pending_updates.as_slice() {
+        [] => "No updates pending".to_string(),
+        inputs => format!("Updates available: {}", inputs.join(", ")),
+    };
+    menu.append(&info(&updates)?)?;
+    if status.running.is_empty() {
+        menu.append(&info("Nothing running")?)?;
+    }
+    for message in status.running.iter().take(MAX_RUNNING) {
+        menu.append(&info(&format!("Running: {}", message))?)?;
+    }
+    if status.running.len() > MAX_RUNNING {
+        menu.append(&info(&format!(
+            "and {} more",
+            status.running.len() - MAX_RUNNING
+        ))?)?;
+    }
+    menu.append(&PredefinedMenuItem::separator(app)?)?;
+
+    for action in tray_actions(app) {
+        let title = if action.id == "voice-input" && status.listening {
+            "Stop voice input".to_string()
+        } else {
+            action.title.clone()
+        };
+        let id = format!("{}{}", ACTION_PREFIX, action.id);
+        menu.append(&MenuItem::with_id(app, id, title, true, None::<&str>)?)?;
+    }
+    menu.append(&PredefinedMenuItem::separator(app)?)?;
+    menu.append(&MenuItem::with_id(
+        app,
+        SHOW,
+        "Show Luminous Nix",
+        true,
+        None::<&str>,
+    )?)?;
+    menu.append(&MenuItem::with_id(app, QUIT, "Quit", true, None::<&str>)?)?;
+    Ok(menu)
+}
+
+fn show(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+fn run(app: &AppHandle, action_id: &str) {
+    let Some(action) = tray_actions(app).into_iter().find(|a| a.id == action_id) else {
+        return;
+    };
+    match &action.invocation {
+        // Like the global voice-input shortcut, without opening the window
+        Invocation::Frontend { action: name, .. } if name == "voice-input" => {
+            voice::toggle_listening(app);
+        }
+        Invocation::Frontend { action: name, .. } => {
+            show(app);
+            let _ = app.emit("shortcut", name);
+        }
+        // The window invokes it, so confirmations apply as in the palette
+        Invocation::Command { .. } => {
+            show(app);
+            let _ = app.emit_to("main", "tray-action", &action);
+        }
+    }
+}
+
+// Rebuild the menu and tooltip; the status can take a moment (the update
+// check may go to the network), so off the calling thread
+pub fn refresh(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let Some(tray) = app.tray_by_id(TRAY_ID) else {
+            return;
+        };
+        let status = status();
+        match menu(&app, &status) {
+            Ok(menu) => {
+                let _ = tray.set_menu(Some(menu));
+                let _ = tray.set_tooltip(Some(tooltip(&status)));
+            }
+            Err(e) => eprintln!("Could not update the tray menu: {}", e),
+        }
+    });
+}
+
+pub fn init(app: &AppHandle) -> tauri::Result<()> {
+    // Filled in by the first refresh
+    let placeholder = Status {
+        pending_updates: Vec::new(),
+        running: Vec::new(),
+        listening: false,
+    };
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&menu(app, &placeholder)?)
+        .tooltip("Luminous Nix")
+        .on_menu_event(|app, event| match event.id().as_ref() {
+            SHOW => show(app),
+            QUIT => app.exit(0),
+            id => {
+                if let Some(action) = id.strip_prefix(ACTION_PREFIX) {
+                    run(app, action);
+                }
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+
+    // Progress events only matter when an operation starts or ends
+    let handle = app.clone();
+    app.listen_any("operation", move |event| {
+        let changed = matches!(
+            serde_json::from_str::<OperationEvent>(event.payload()),
+            Ok(OperationEvent::OperationStarted { .. }
+                | OperationEvent::Completed { .. }
+                | OperationEvent::Failed { .. })
+        );
+        if changed {
+            refresh(&handle);
+        }
+    });
+    for name in ["updates-checked", "push-to-talk"] {
+        let handle = app.clone();
+        app.listen_any(name, move |_| refresh(&handle));
+    }
+    refresh(app);
+    Ok(())
+}
@@ -0,0 +1,205 @@
+// Driving the UI the way a user would, for automated tests
+//
+// Clicks and typing become real DOM events on a component's elements, in the
+// window showing it: pointer and mouse events around the click, and per
+// character a keydown, the value changing through the element's own setter
+// (so frameworks tracking it notice) with an input event, and a keyup. Waiting
+// polls the page until a selector meets a condition; reading text takes what
+// is rendered. State assertions compare the backend's component state with
+// the expected fields.
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::{probe, AppState};
+
+const MAX_WAIT: Duration = Duration::from_secs(60);
+// What the page may take beyond the wait itself to answer
+const ANSWER_MARGIN: Duration = Duration::from_secs(2);
+
+// The component scripts run with `root` bound to the component's element
+const CLICK_SCRIPT: &str = "var el=selector?root.querySelector(selector):root;\
+if(!el)throw new Error('there is nothing to click');\
+el.scrollIntoView({block:'center'});\
+var r=el.getBoundingClientRect(),o={bubbles:true,cancelable:true,view:window,\
+clientX:r.left+r.width/2,clientY:r.top+r.height/2,button:0};\
+el.dispatchEvent(new PointerEvent('pointerdown',o));el.dispatchEvent(new MouseEvent('mousedown',o));\
+if(el.focus)el.focus();\
+el.dispatchEvent(new PointerEvent('pointerup',o));el.dispatchEvent(new MouseEvent('mouseup',o));\
+el.dispatchEvent(new MouseEvent('click',o));return true;";
+
+const TYPE_SCRIPT: &str =
+    "var field='input,textarea,[contenteditable=\"\"],[contenteditable=\"true\"]';\
+var el=root.matches(field)?root:root.querySelector(field);\
+if(!el)throw new Error('it has no field to type into');\
+el.focus();\
+var editable=el.isContentEditable,proto=Object.getPrototypeOf(el),\
+setter=editable?null:Object.getOwnPropertyDescriptor(proto,'value').set;\
+for(var i=0;i<text.length;i++){var ch=text[i],k={key:ch,bubbles:true,cancelable:true};\
+if(!el.dispatchEvent(new KeyboardEvent('keydown',k)))continue;\
+if(editable){el.textContent+=ch;}else{setter.call(el,el.value+ch);}\
+el.dispatchEvent(new InputEvent('input',{data:ch,inputType:'insertText',bubbles:true}));\
+el.dispatchEvent(new KeyboardEvent('keyup',k));}\
+el.dispatchEvent(new Event('change',{bubbles:true}));\
+return editable?el.textContent:el.value;";
+
+const WAIT_SCRIPT: &str = "function met(){var el=document.querySelector(selector);\
+var shown=!!el&&el.getClientRects().length>0&&getComputedStyle(el).visibility!=='hidden';\
+switch(condition.kind){\
+case 'present':return !!el;case 'absent':return !el;\
+case 'visible':return shown;case 'hidden':return !shown;\
+case 'enabled':return !!el&&!el.disabled;case 'disabled':return !!el&&!!el.disabled;\
+case 'contains_text':return !!el&&(el.innerText||el.value||'').indexOf(condition.text)>=0;}\
+throw new Error('unknown condition '+condition.kind);}\
+var start=Date.now();\
+while(!met()){if(Date.now()-start>timeout)return {met:false,waited_ms:Date.now()-start};\
+await new Promise(function(r){setTimeout(r,50);});}\
+return {met:true,waited_ms:Date.now()-start};";
+
+const TEXT_SCRIPT: &str = "return root.matches('input,textarea,select')?root.value:root.innerText;";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Condition {
+    Present,
+    Absent,
+    Visible,
+    Hidden,
+    Enabled,
+    Disabled,
+    ContainsText { text: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaitResult {
+    pub met: bool,
+    pub waited_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mismatch {
+    // JSON pointer into the state: "/filters/query"
+    pub path: String,
+    pub expected: serde_json::Value,
+    pub actual: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateAssertion {
+    pub passed: bool,
+    pub mismatches: Vec<Mismatch>,
+}
+
+// Run `script` with `root` bound to the component's element, plus `vars`
+fn in_component(
+    app: &AppHandle,
+    component_id: &str,
+    vars: &[(&str, serde_json::Value)],
+    script: &str,
+    timeout: Duration,
+) -> anyhow::Result<serde_json::Value> {
+    let mut body = format!("var root={};", probe::component_element(component_id));
+    for (name, value) in vars {
+        body.push_str(&format!("var {}={};", name, value));
+    }
+    body.push_str(&format!(
+        "if(!root)throw new Error({});",
+        serde_json::to_string(&format!("{} is not rendered", component_id))?
+    ));
+    body.push_str(script);
+    probe::eval_in_component(app, component_id, &body, timeout)
+}
+
+// Click a component, or the element matching `selector` inside it
+pub fn click(app: &AppHandle, component_id: &str, selector: Option<&str>) -> anyhow::Result<()> {
+    in_component(
+        app,
+        component_id,
+        &[("selector", serde_json::json!(selector))],
+        CLICK_SCRIPT,
+        probe::DEFAULT_TIMEOUT,
+    )?;
+    Ok(())
+}
+
+// Type into the component's field; returns the field's value afterwards
+pub fn type_text(app: &AppHandle, component_id: &str, text: &str) -> anyhow::Result<String> {
+    let value = in_component(
+        app,
+        component_id,
+        &[("text", serde_json::json!(text))],
+        TYPE_SCRIPT,
+        probe::DEFAULT_TIMEOUT,
+    )?;
+    Ok(value.as_str().unwrap_or_default().to_string())
+}
+
+pub fn wait_for(
+    app: &AppHandle,
+    window: &str,
+    selector: &str,
+    condition: &Condition,
+    timeout: Duration,
+) -> anyhow::Result<WaitResult> {
+    if timeout > MAX_WAIT {
+        bail!("Waiting is limited to {} seconds", MAX_WAIT.as_secs());
+    }
+    let body = format!(
+        "var selector={};var condition={};var timeout={};{}",
+        serde_json::to_string(selector)?,
+        serde_json::to_string(condition)?,
+        timeout.as_millis(),
+        WAIT_SCRIPT
+    );
+    let value = probe::eval(app, window, &body, timeout + ANSWER_MARGIN)?;
+    Ok(serde_json::from_value(value)?)
+}
+
+pub fn text(app: &AppHandle, component_id: &str) -> anyhow::Result<String> {
+    let value = in_component(app, component_id, &[], TEXT_SCRIPT, probe::DEFAULT_TIMEOUT)?;
+    Ok(value.as_str().unwrap_or_default().to_string())
+}
+
+// Every field of `expected` has to be in `actual` with the same value; fields
+// it doesn't mention can be anything
+fn compare(
+    path: &str,
+    expected: &serde_json::Value,
+    actual: Option<&serde_json::Value>,
+    mismatches: &mut Vec<Mismatch>,
+) {
+    match (expected, actual) {
+        (serde_json::Value::Object(fields), Some(serde_json::Value::Object(actual))) => {
+            for (key, value) in fields {
+                let path = format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+                compare(&path, value, actual.get(key), mismatches);
+            }
+        }
+        (expected, actual) if Some(expected) != actual => mismatches.push(Mismatch {
+            path: path.to_string(),
+            expected: expected.clone(),
+            actual: actual.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+pub fn assert_state(
+    state: &AppState,
+    component_id: &str,
+    expected: &serde_json::Value,
+) -> anyhow::Result<StateAssertion> {
+    let components = state.components.blocking_lock();
+    let component = components
+        .iter()
+        .find(|c| c.id == component_id)
+        .ok_or_else(|| anyhow!("There is no component \"{}\"", component_id))?;
+    let mut mismatches = Vec::new();
+    compare("", expected, Some(&component.state), &mut mismatches);
+    Ok(StateAssertion {
+        passed: mismatches.is_empty(),
+        mismatches,
+    })
+}
@@ -0,0 +1,165 @@
+// The user's profile (persona, preferences), persisted across restarts
+//
+// Stored as JSON in the data dir with a schema version. Older documents, and
+// exports from older releases, are migrated step by step on load; the
+// original is kept next to the migrated file in case a migration gets
+// something wrong.
+
+use anyhow::{anyhow, bail, Context};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::{clock, personas, storage, tasks};
+
+pub const PROFILE_FILE: &str = "user-profile.json";
+pub const SCHEMA_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserProfile {
+    pub schema_version: u32,
+    pub id: String,
+    pub persona: String,
+    pub preferences: serde_json::Value,
+    // 0..1, measured by wellbeing::measure while consciousness tracking is on
+    pub consciousness_state: f32,
+    pub updated_at: u64,
+}
+
+impl Default for UserProfile {
+    fn default() -> Self {
+        UserProfile {
+            schema_version: SCHEMA_VERSION,
+            id: "default".to_string(),
+            persona: personas::DEFAULT_PERSONA.to_string(),
+            preferences: serde_json::json!({}),
+            consciousness_state: 0.5,
+            updated_at: clock::now(),
+        }
+    }
+}
+
+// ========== Migrations ==========
+//
+// Each step takes a document at version N-1 and returns it at version N.
+
+// v0 -> v1: the unversioned in-memory shape; preferences could be null
+fn migrate_v1(mut doc: serde_json::Value) -> serde_json::Value {
+    if doc.get("preferences").map_or(true, |p| !p.is_object()) {
+        doc["preferences"] = serde_json::json!({});
+    }
+    if doc
+        .get("consciousness_state")
+        .map_or(true, |c| !c.is_number())
+    {
+        doc["consciousness_state"] = serde_json::json!(0.5);
+    }
+    doc
+}
+
+// v1 -> v2: personas became ids ("Grandma Rose" -> "grandma_rose") and profiles track when they changed
+fn migrate_v2(mut doc: serde_json::Value) -> serde_json::Value {
+    let persona = doc.get("persona").and_then(|p| p.as_str()).unwrap_or("");
+    doc["persona"] = serde_json::json!(personas::resolve(Some(persona)).id);
+    if doc.get("updated_at").is_none() {
+        doc["updated_at"] = serde_json::json!(clock::now());
+    }
+    doc
+}
+
+const MIGRATIONS: &[fn(serde_json::Value) -> serde_json::Value] = &[migrate_v1, migrate_v2];
+
+// Bring a stored or imported document up to the current schema
+pub fn migrate(mut doc: serde_json::Value) -> anyhow::Result<UserProfile> {
+    if !doc.is_object() {
+        bail!("A profile must be a JSON object");
+    }
+    let version = doc
+        .get("schema_version")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0) as u32;
+    if version > SCHEMA_VERSION {
+        bail!(
+            "This profile was saved by a newer version (schema {}, this version reads up to {})",
+            version,
+            SCHEMA_VERSION
+        );
+    }
+    for step in &MIGRATIONS[version as usize..] {
+        doc = step(doc);
+    }
+    doc["schema_version"] = serde_json::json!(SCHEMA_VERSION);
+    serde_json::from_value(doc).context("The profile is missing required fields")
+}
+
+// Load the stored profile, migrating it in place when it is from an older schema
+pub fn load() -> anyhow::Result<Option<UserProfile>> {
+    let doc: Option<serde_json::Value> = storage::load_data(PROFILE_FILE)?;
+    let Some(doc) = doc else {
+        return Ok(None);
+    };
+    let version = doc
+        .get("schema_version")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    let profile = migrate(doc.clone())?;
+    if version < SCHEMA_VERSION as u64 {
+        storage::save_data(&format!("user-profile.v{}.json", version), &doc)?;
+        save(&profile)?;
+    }
+    Ok(Some(profile))
+}
+
+pub fn save(profile: &UserProfile) -> anyhow::Result<()> {
+    let mut profile = profile.clone();
+    profile.updated_at = clock::now();
+    storage::save_data(PROFILE_FILE, &profile).map(|_| ())
+}
+
+pub fn export(profile: &UserProfile, path: &Path) -> anyhow::Result<()> {
+    storage::write_json(path, profile)
+}
+
+pub fn import(path: &Path) -> anyhow::Result<UserProfile> {
+    if !path.exists() {
+        return Err(anyhow!("{} doesn't exist", path.display()));
+    }
+    let doc: serde_json::Value = storage::read_json(path)?;
+    let profile = migrate(doc)?;
+    save(&profile)?;
+    Ok(profile)
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn export_profile(path: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Export profile", move |state| {
+        let profile = state
+            .user_profile
+            .blocking_lock()
+            .clone()
+            .unwrap_or_default();
+        let path = std::path::PathBuf::from(path);
+        crate::respond(export(&profile, &path).map(|()| path))
+    })
+    .await
+}
+
+// Replace the current profile with an exported one, migrating it if it is
+// older; its global shortcuts take over from the old profile's
+#[tauri::command]
+pub async fn import_profile(path: String, app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Import profile", move |state| {
+        let result = import(Path::new(&path)).map(|profile| {
+            *state.user_profile.blocking_lock() = Some(profile.clone());
+            profile
+        });
+        if result.is_ok() {
+            crate::shortcuts::register_global(&handle);
+        }
+        crate::respond(result)
+    })
+    .await
+}
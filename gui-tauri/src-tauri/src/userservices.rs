@@ -0,0 +1,451 @@
+// Declarative user services managed through home-manager
+//
+// Known services (syncthing, gpg-agent, ...) are toggled through their
+// home-manager options; custom scripts become systemd.user.services entries.
+// Everything lands in one generated home-manager module, and the live state of
+// each unit comes from `systemctl --user`.
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::AppHandle;
+
+use crate::nixgen::{self, NixModule, NixOption, Target};
+use crate::timers::slug;
+use crate::{managed, services, storage, system, tasks};
+
+const STATE_FILE: &str = "user-services.json";
+const MODULE_NAME: &str = "user-services";
+
+// A service home-manager has a ready-made module for
+struct KnownService {
+    id: &'static str,
+    title: &'static str,
+    unit: &'static str,
+    option: &'static str,
+    // Extra options set alongside `<option>.enable`
+    extra: &'static [(&'static str, &'static str)],
+}
+
+const KNOWN_SERVICES: &[KnownService] = &[
+    KnownService {
+        id: "syncthing",
+        title: "Syncthing file synchronisation",
+        unit: "syncthing.service",
+        option: "services.syncthing",
+        extra: &[],
+    },
+    KnownService {
+        id: "gpg-agent",
+        title: "GnuPG agent",
+        unit: "gpg-agent.service",
+        option: "services.gpg-agent",
+        extra: &[("services.gpg-agent.enableSshSupport", "true")],
+    },
+    KnownService {
+        id: "ssh-agent",
+        title: "OpenSSH agent",
+        unit: "ssh-agent.service",
+        option: "services.ssh-agent",
+        extra: &[],
+    },
+    KnownService {
+        id: "nextcloud-client",
+        title: "Nextcloud desktop sync",
+        unit: "nextcloud-client.service",
+        option: "services.nextcloud-client",
+        extra: &[("services.nextcloud-client.startInBackground", "true")],
+    },
+    KnownService {
+        id: "dunst",
+        title: "Dunst notification daemon",
+        unit: "dunst.service",
+        option: "services.dunst",
+        extra: &[],
+    },
+    KnownService {
+        id: "redshift",
+        title: "Redshift colour temperature",
+        unit: "redshift.service",
+        option: "services.redshift",
+        extra: &[("services.redshift.provider", "\"geoclue2\"")],
+    },
+    KnownService {
+        id: "emacs",
+        title: "Emacs daemon",
+        unit: "emacs.service",
+        option: "services.emacs",
+        extra: &[],
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    No,
+    OnFailure,
+    Always,
+}
+
+// A user-written service, rendered as systemd.user.services.<name>
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomService {
+    pub name: String,
+    pub description: String,
+    pub command: String,
+    // nixpkgs attributes the command needs on PATH
+    pub packages: Vec<String>,
+    pub restart: RestartPolicy,
+    // Start with the graphical session instead of at login
+    pub graphical: bool,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct UserServicesState {
+    known: BTreeMap<String, bool>,
+    custom: Vec<CustomService>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceKind {
+    Known,
+    Custom,
+    // Declared in home.nix by hand, not through the assistant
+    External,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserService {
+    pub id: String,
+    pub title: String,
+    pub unit: String,
+    pub kind: ServiceKind,
+    // Declared in the generated module
+    pub declared: bool,
+    pub active_state: String,
+    pub sub_state: String,
+    pub health: services::Health,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApplyResult {
+    pub module_path: std::path::PathBuf,
+    pub module_preview: String,
+    pub next_step: String,
+}
+
+fn load_state() -> UserServicesState {
+    storage::load(STATE_FILE).unwrap_or_default()
+}
+
+fn unit_state(unit: &str) -> (String, String, String) {
+    let output = services::systemctl(
+        true,
+        &["show", "--property=ActiveState,SubState,Result", unit],
+    )
+    .unwrap_or_default();
+    let props: BTreeMap<&str, &str> = output.lines().filter_map(|l| l.split_once('=')).collect();
+    let get = |key: &str| props.get(key).copied().unwrap_or("unknown").to_string();
+    (get("ActiveState"), get("SubState"), get("Result"))
+}
+
+fn with_state(id: &str, title: &str, unit: &str, kind: ServiceKind, declared: bool) -> UserService {
+    let (active_state, sub_state, result) = unit_state(unit);
+    UserService {
+        id: id.to_string(),
+        title: title.to_string(),
+        unit: unit.to_string(),
+        kind,
+        declared,
+        health: services::health(&active_state, &result),
+        active_state,
+        sub_state,
+    }
+}
+
+// Home-manager links its units from the store into ~/.config/systemd/user
+fn home_manager_units() -> Vec<String> {
+    let dir = system::xdg_config_home().join("systemd/user");
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|e| {
+            std::fs::read_link(e.path())
+                .map(|target| target.starts_with("/nix/store"))
+                .unwrap_or(false)
+        })
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".service"))
+        .collect()
+}
+
+pub fn list() -> Vec<UserService> {
+    let state = load_state();
+    let mut found: Vec<UserService> = KNOWN_SERVICES
+        .iter()
+        .map(|k| {
+            let declared = state.known.get(k.id).copied().unwrap_or(false);
+            with_state(k.id, k.title, k.unit, ServiceKind::Known, declared)
+        })
+        .collect();
+    for custom in &state.custom {
+        found.push(with_state(
+            &custom.name,
+            &custom.description,
+            &format!("{}.service", custom.name),
+            ServiceKind::Custom,
+            custom.enabled,
+        ));
+    }
+    for unit in home_manager_units() {
+        if found.iter().any(|s| s.unit == unit) {
+            continue;
+        }
+        let id = unit.trim_end_matches(".service").to_string();
+        found.push(with_state(&id, &id, &unit, ServiceKind::External, false));
+    }
+    found
+}
+
+fn custom_option(service: &CustomService) -> NixOption {
+    let wanted_by = if service.graphical {
+        "graphical-session.target"
+    } else {
+        "default.target"
+    };
+    let restart = match service.restart {
+        RestartPolicy::No => "no",
+        RestartPolicy::OnFailure => "on-failure",
+        RestartPolicy::Always => "always",
+    };
+    NixOption::new(
+        format!("systemd.user.services.{}", nixgen::attr(&service.name)),
+        format!(
+            "{{\n    Unit.Description = {desc};\n    Unit.PartOf = [ {target} ];\n    Service = {{\n      Environment = [ \"PATH=${{lib.makeBinPath (with pkgs; [ {packages} ])}}\" ];\n      ExecStart = toString (pkgs.writeShellScript {name} {script});\n      Restart = {restart};\n    }};\n    Install.WantedBy = [ {target} ];\n  }}",
+            desc = nixgen::string(&service.description),
+            target = nixgen::string(wanted_by),
+            packages = service.packages.join(" "),
+            name = nixgen::string(&service.name),
+            script = nixgen::string(&service.command),
+            restart = nixgen::string(restart),
+        ),
+    )
+}
+
+fn build_module(state: &UserServicesState) -> NixModule {
+    let mut module = NixModule::new(MODULE_NAME, "user services", Target::HomeManager);
+    for known in KNOWN_SERVICES {
+        if !state.known.get(known.id).copied().unwrap_or(false) {
+            continue;
+        }
+        module.set(
+            NixOption::new(format!("{}.enable", known.option), nixgen::bool(true))
+                .with_comment(known.title),
+        );
+        for (path, value) in known.extra {
+            module.set(NixOption::new(*path, *value));
+        }
+    }
+    for custom in state.custom.iter().filter(|c| c.enabled) {
+        module.set(custom_option(custom));
+    }
+    module
+}
+
+fn write(state: &UserServicesState) -> anyhow::Result<ApplyResult> {
+    let module = build_module(state);
+    let module_path = module.write()?;
+    storage::save(STATE_FILE, state)?;
+    Ok(ApplyResult {
+        module_path,
+        module_preview: module.render(),
+        next_step: "Run home-manager switch to apply the change".to_string(),
+    })
+}
+
+pub fn set_enabled(id: &str, enabled: bool) -> anyhow::Result<ApplyResult> {
+    let mut state = load_state();
+    if KNOWN_SERVICES.iter().any(|k| k.id == id) {
+        state.known.insert(id.to_string(), enabled);
+    } else if let Some(custom) = state.custom.iter_mut().find(|c| c.name == id) {
+        custom.enabled = enabled;
+    } else {
+        bail!(
+            "'{}' is not managed here; it is declared in your own home-manager configuration",
+            id
+        );
+    }
+    write(&state)
+}
+
+pub fn logs(unit: &str, lines: usize) -> anyhow::Result<Vec<String>> {
+    let unit = if unit.contains('.') {
+        unit.to_string()
+    } else {
+        format!("{}.service", unit)
+    };
+    let count = lines.clamp(1, 2000).to_string();
+    let output = system::run(
+        "journalctl",
+        &[
+            "--user",
+            "--unit",
+            &unit,
+            "--lines",
+            &count,
+            "--no-pager",
+            "--output=short-iso",
+        ],
+    )?;
+    Ok(output.lines().map(String::from).collect())
+}
+
+// Fill in the template for a new custom service without saving it
+pub fn scaffold(
+    name: &str,
+    command: &str,
+    description: Option<&str>,
+    packages: Vec<String>,
+) -> anyhow::Result<CustomService> {
+    let name = slug(name);
+    if name.is_empty() {
+        bail!("The service needs a name");
+    }
+    if command.trim().is_empty() {
+        bail!("The service needs a command to run");
+    }
+    if KNOWN_SERVICES.iter().any(|k| k.id == name) {
+        bail!(
+            "'{}' already has a home-manager module; enable that instead",
+            name
+        );
+    }
+    Ok(CustomService {
+        description: description
+            .map(String::from)
+            .unwrap_or_else(|| format!("Run {}", command)),
+        name,
+        command: command.to_string(),
+        packages,
+        restart: RestartPolicy::OnFailure,
+        graphical: false,
+        enabled: true,
+    })
+}
+
+pub fn save_custom(service: CustomService) -> anyhow::Result<ApplyResult> {
+    let service = CustomService {
+        name: slug(&service.name),
+        ..service
+    };
+    if service.name.is_empty() || service.command.trim().is_empty() {
+        bail!("The service needs a name and a command");
+    }
+    let mut state = load_state();
+    state.custom.retain(|c| c.name != service.name);
+    state.custom.push(service);
+    write(&state)
+}
+
+pub fn remove_custom(name: &str) -> anyhow::Result<ApplyResult> {
+    let mut state = load_state();
+    let before = state.custom.len();
+    state.custom.retain(|c| c.name != name);
+    if state.custom.len() == before {
+        bail!("No custom service named '{}'", name);
+    }
+    write(&state)
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn list_user_services(app: AppHandle) -> Vec<UserService> {
+    tasks::blocking_value(&app, "List user services", |_| list()).await
+}
+
+#[tauri::command]
+pub async fn set_user_service_enabled(
+    id: String,
+    enabled: bool,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set user service enabled", move |_| {
+        if let Some(refusal) = managed::guard("user_services", &format!("Turn {} on or off", id)) {
+            return refusal;
+        }
+        crate::respond(set_enabled(&id, enabled))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_user_service_logs(
+    unit: String,
+    lines: Option<usize>,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Get user service logs", move |_| {
+        crate::respond(logs(&unit, lines.unwrap_or(200)))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn scaffold_user_service(
+    name: String,
+    command: String,
+    description: Option<String>,
+    packages: Option<Vec<String>>,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Scaffold user service", move |_| {
+        crate::respond(
+            scaffold(
+                &name,
+                &command,
+                description.as_deref(),
+                packages.unwrap_or_default(),
+            )
+            .map(|service| {
+                let preview = custom_option(&service);
+                serde_json::json!({
+                    "service": service,
+                    "preview": format!("{} = {};", preview.path, preview.value),
+                })
+            }),
+        )
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn save_user_service(service: CustomService, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Save user service", move |_| {
+        if let Some(refusal) = managed::guard(
+            "user_services",
+            &format!("Save the {} service", service.name),
+        ) {
+            return refusal;
+        }
+        crate::respond(save_custom(service))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn remove_user_service(name: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Remove user service", move |_| {
+        if let Some(refusal) =
+            managed::guard("user_services", &format!("Remove the {} service", name))
+        {
+            return refusal;
+        }
+        crate::respond(remove_custom(&name))
+    })
+    .await
+}
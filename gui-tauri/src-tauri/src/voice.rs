@@ -0,0 +1,638 @@
+// Local speech-to-text with whisper.cpp
+//
+// start_listening records from the microphone (the system default unless
+// another was picked with set_input_device); stop_listening transcribes what
+// was said on this machine and, unless turned off, answers it like a typed
+// query. Holding the push-to-talk shortcut does the same from anywhere, but
+// puts the text into the search box instead. Every transcription is also
+// emitted as a "voice-transcription" event so other parts of the UI can follow
+// along.
+// Models (tiny, base, small: faster or more accurate) are ggml files in the
+// models dir, downloaded and checked by voicemodels. With voice emotion allowed in the privacy
+// settings, the pitch, loudness and pace of each request are measured for the
+// frustration estimate before the recording is dropped. Capture and
+// transcription need the `voice` build feature.
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::{
+    capabilities, levelmeter, managed, privacy, prosody, storage, tasks, voiceconfirm, voicemodels,
+    wakeword, AppState,
+};
+
+const SETTINGS_FILE: &str = "voice.json";
+// whisper.cpp expects 16 kHz mono
+const SAMPLE_RATE: u32 = 16_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WhisperModel {
+    Tiny,
+    Base,
+    Small,
+}
+
+impl WhisperModel {
+    pub const ALL: [WhisperModel; 3] =
+        [WhisperModel::Tiny, WhisperModel::Base, WhisperModel::Small];
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            WhisperModel::Tiny => "ggml-tiny.bin",
+            WhisperModel::Base => "ggml-base.bin",
+            WhisperModel::Small => "ggml-small.bin",
+        }
+    }
+
+    pub fn size_mb(self) -> u64 {
+        match self {
+            WhisperModel::Tiny => 75,
+            WhisperModel::Base => 142,
+            WhisperModel::Small => 466,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VoiceSettings {
+    pub model: WhisperModel,
+    // Spoken language as a code like "en"; None detects it
+    pub language: Option<String>,
+    // Where the ggml model files are; defaults to the data dir
+    pub models_dir: Option<String>,
+    // Answer the transcription as a query, rather than only returning the text
+    pub submit: bool,
+    // Name of the microphone to record from; None follows the system default
+    pub input_device: Option<String>,
+}
+
+impl Default for VoiceSettings {
+    fn default() -> Self {
+        VoiceSettings {
+            model: WhisperModel::Base,
+            language: None,
+            models_dir: None,
+            submit: true,
+            input_device: None,
+        }
+    }
+}
+
+impl VoiceSettings {
+    pub fn models_dir(&self) -> PathBuf {
+        self.models_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| storage::data_dir().join("whisper"))
+    }
+
+    pub fn model_path(&self, model: WhisperModel) -> PathBuf {
+        self.models_dir().join(model.file_name())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelStatus {
+    pub model: WhisperModel,
+    pub path: PathBuf,
+    pub downloaded: bool,
+    pub size_mb: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoiceStatus {
+    // Whether this build can capture and transcribe at all
+    pub available: bool,
+    pub listening: bool,
+    pub model: WhisperModel,
+    pub models: Vec<ModelStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioDevice {
+    pub name: String,
+    pub is_default: bool,
+    // Whether recordings use it, either picked or as the default
+    pub selected: bool,
+    pub channels: u16,
+    pub sample_rate: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transcription {
+    pub text: String,
+    pub model: WhisperModel,
+    pub duration_secs: f32,
+    // The answer to `text`, when it was submitted as a query
+    pub response: Option<serde_json::Value>,
+}
+
+pub fn settings() -> VoiceSettings {
+    storage::load(SETTINGS_FILE).unwrap_or_default()
+}
+
+// Microphone capture and whisper.cpp, only built with the `voice` feature
+#[cfg(feature = "voice")]
+mod engine {
+    use anyhow::{anyhow, bail};
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use cpal::SampleFormat;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::thread::JoinHandle;
+    use std::time::Duration;
+    use tokio::sync::Mutex as AsyncMutex;
+    use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+    use super::{AudioDevice, VoiceSettings, WhisperModel, SAMPLE_RATE};
+
+    // Longer recordings are cut off rather than held in memory indefinitely:
+    // two minutes at up to 48 kHz stereo
+    const MAX_SAMPLES: usize = 120 * 48_000 * 2;
+
+    struct Recording {
+        stop: Arc<AtomicBool>,
+        samples: Arc<Mutex<Vec<f32>>>,
+        channels: usize,
+        rate: u32,
+        thread: JoinHandle<()>,
+    }
+
+    // The samples are filled in from the audio callback and keep a plain mutex
+    static RECORDING: AsyncMutex<Option<Recording>> = AsyncMutex::const_new(None);
+    // Loading a model takes seconds, so the last one stays in memory
+    static LOADED: AsyncMutex<Option<(WhisperModel, WhisperContext)>> = AsyncMutex::const_new(None);
+
+    pub fn listening() -> bool {
+        RECORDING.blocking_lock().is_some()
+    }
+
+    pub fn start() -> anyhow::Result<()> {
+        let mut recording = RECORDING.blocking_lock();
+        if recording.is_some() {
+            bail!("Already listening");
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let samples = Arc::new(Mutex::new(Vec::new()));
+        // cpal streams can't move between threads, so the stream lives in its own
+        let (ready, started) = mpsc::channel::<anyhow::Result<(usize, u32)>>();
+        let thread = {
+            let (stop, samples) = (stop.clone(), samples.clone());
+            std::thread::spawn(move || {
+                let capture = move |data: &[f32]| {
+                    let mut samples = samples.lock().unwrap();
+                    let room = MAX_SAMPLES.saturating_sub(samples.len());
+                    samples.extend_from_slice(&data[..data.len().min(room)]);
+                };
+                let stream = match open_stream(capture) {
+                    Ok((stream, channels, rate)) => {
+                        let _ = ready.send(Ok((channels, rate)));
+                        stream
+                    }
+                    Err(e) => {
+                        let _ = ready.send(Err(e));
+                        return;
+                    }
+                };
+                while !stop.load(Ordering::Relaxed) {
+                    std::thread::sleep(Duration::from_millis(50));
+                }
+                drop(stream);
+            })
+        };
+        let (channels, rate) = started
+            .recv()
+            .map_err(|_| anyhow!("The microphone thread stopped"))??;
+        *recording = Some(Recording {
+            stop,
+            samples,
+            channels,
+            rate,
+            thread,
+        });
+        Ok(())
+    }
+
+    pub fn input_devices() -> anyhow::Result<Vec<AudioDevice>> {
+        let host = cpal::default_host();
+        let default = host.default_input_device().and_then(|d| d.name().ok());
+        let chosen = super::settings().input_device.or_else(|| default.clone());
+        let mut devices = Vec::new();
+        for device in host.input_devices()? {
+            let (Ok(name), Ok(config)) = (device.name(), device.default_input_config()) else {
+                continue;
+            };
+            devices.push(AudioDevice {
+                is_default: Some(&name) == default.as_ref(),
+                selected: Some(&name) == chosen.as_ref(),
+                channels: config.channels(),
+                sample_rate: config.sample_rate().0,
+                name,
+            });
+        }
+        Ok(devices)
+    }
+
+    // The picked microphone, or the default one when none was picked
+    fn input_device() -> anyhow::Result<cpal::Device> {
+        let host = cpal::default_host();
+        match super::settings().input_device {
+            Some(name) => host
+                .input_devices()?
+                .find(|d| d.name().is_ok_and(|n| n == name))
+                .ok_or_else(|| anyhow!("The microphone \"{}\" isn't connected", name)),
+            None => host
+                .default_input_device()
+                .ok_or_else(|| anyhow!("No microphone found")),
+        }
+    }
+
+    // Feed the microphone's samples to `sink` as f32, interleaved. The stream
+    // stops when dropped and can't leave the thread that opened it.
+    pub fn open_stream(
+        mut sink: impl FnMut(&[f32]) + Send + 'static,
+    ) -> anyhow::Result<(cpal::Stream, usize, u32)> {
+        let device = input_device()?;
+        let config = device.default_input_config()?;
+        let channels = config.channels() as usize;
+        let rate = config.sample_rate().0;
+        let on_error = |e: cpal::StreamError| eprintln!("Microphone: {}", e);
+        let stream = match config.sample_format() {
+            SampleFormat::F32 => device.build_input_stream(
+                &config.into(),
+                move |data: &[f32], _: &_| sink(data),
+                on_error,
+                None,
+            )?,
+            SampleFormat::I16 => device.build_input_stream(
+                &config.into(),
+                move |data: &[i16], _: &_| {
+                    let converted: Vec<f32> =
+                        data.iter().map(|s| *s as f32 / i16::MAX as f32).collect();
+                    sink(&converted)
+                },
+                on_error,
+                None,
+            )?,
+            other => bail!("Unsupported microphone sample format {:?}", other),
+        };
+        stream.play()?;
+        Ok((stream, channels, rate))
+    }
+
+    // Interleaved frames at `rate` Hz to 16 kHz mono, by averaging channels and
+    // interpolating linearly
+    pub fn to_whisper_input(samples: &[f32], channels: usize, rate: u32) -> Vec<f32> {
+        let mono: Vec<f32> = samples
+            .chunks(channels.max(1))
+            .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+            .collect();
+        if rate == SAMPLE_RATE || mono.is_empty() {
+            return mono;
+        }
+        let step = rate as f64 / SAMPLE_RATE as f64;
+        let len = (mono.len() as f64 / step) as usize;
+        (0..len)
+            .map(|i| {
+                let position = i as f64 * step;
+                let index = position as usize;
+                let next = mono.get(index + 1).copied().unwrap_or(mono[index]);
+                let fraction = (position - index as f64) as f32;
+                mono[index] + (next - mono[index]) * fraction
+            })
+            .collect()
+    }
+
+    // Stop recording and return 16 kHz mono audio
+    pub fn stop() -> anyhow::Result<Vec<f32>> {
+        let Some(recording) = RECORDING.blocking_lock().take() else {
+            bail!("Not listening");
+        };
+        recording.stop.store(true, Ordering::Relaxed);
+        let _ = recording.thread.join();
+        let samples = recording.samples.lock().unwrap();
+        Ok(to_whisper_input(
+            &samples,
+            recording.channels,
+            recording.rate,
+        ))
+    }
+
+    pub fn transcribe(settings: &VoiceSettings, audio: &[f32]) -> anyhow::Result<String> {
+        let mut loaded = LOADED.blocking_lock();
+        if loaded.as_ref().map(|(model, _)| *model) != Some(settings.model) {
+            let path = settings.model_path(settings.model);
+            if !path.exists() {
+                bail!(
+                    "The {:?} speech model isn't downloaded yet ({})",
+                    settings.model,
+                    path.display()
+                );
+            }
+            let context = WhisperContext::new_with_params(
+                &path.to_string_lossy(),
+                WhisperContextParameters::default(),
+            )
+            .map_err(|e| anyhow!("Could not load {}: {}", path.display(), e))?;
+            *loaded = Some((settings.model, context));
+        }
+        let Some((_, context)) = loaded.as_ref() else {
+            bail!("No speech model loaded");
+        };
+        let mut state = context
+            .create_state()
+            .map_err(|e| anyhow!("Could not start whisper: {}", e))?;
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        params.set_language(Some(settings.language.as_deref().unwrap_or("auto")));
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_special(false);
+        params.set_print_timestamps(false);
+        state
+            .full(params, audio)
+            .map_err(|e| anyhow!("Transcription failed: {}", e))?;
+        let segments = state
+            .full_n_segments()
+            .map_err(|e| anyhow!("Transcription failed: {}", e))?;
+        let mut text = String::new();
+        for segment in 0..segments {
+            let piece = state
+                .full_get_segment_text(segment)
+                .map_err(|e| anyhow!("Transcription failed: {}", e))?;
+            text.push_str(&piece);
+        }
+        Ok(text.trim().to_string())
+    }
+}
+
+#[cfg(feature = "voice")]
+pub use engine::{open_stream, to_whisper_input};
+
+#[cfg(not(feature = "voice"))]
+mod engine {
+    use anyhow::bail;
+
+    use super::{AudioDevice, VoiceSettings};
+
+    const UNAVAILABLE: &str =
+        "This build does not include voice input (enable the `voice` feature)";
+
+    pub fn input_devices() -> anyhow::Result<Vec<AudioDevice>> {
+        bail!(UNAVAILABLE)
+    }
+
+    pub fn listening() -> bool {
+        false
+    }
+
+    pub fn start() -> anyhow::Result<()> {
+        bail!(UNAVAILABLE)
+    }
+
+    pub fn stop() -> anyhow::Result<Vec<f32>> {
+        bail!(UNAVAILABLE)
+    }
+
+    pub fn transcribe(_settings: &VoiceSettings, _audio: &[f32]) -> anyhow::Result<String> {
+        bail!(UNAVAILABLE)
+    }
+}
+
+pub fn status() -> VoiceStatus {
+    let settings = settings();
+    VoiceStatus {
+        available: cfg!(feature = "voice"),
+        listening: engine::listening(),
+        model: settings.model,
+        models: WhisperModel::ALL
+            .iter()
+            .map(|&model| {
+                let path = settings.model_path(model);
+                ModelStatus {
+                    model,
+                    downloaded: path.exists(),
+                    path,
+                    size_mb: model.size_mb(),
+                }
+            })
+            .collect(),
+    }
+}
+
+// Fetch and check a Whisper model through the model manager
+pub fn download(model: WhisperModel) -> anyhow::Result<PathBuf> {
+    voicemodels::download(&voicemodels::whisper_id(model))?;
+    Ok(settings().model_path(model))
+}
+
+// Start recording from the default microphone
+pub fn listen() -> anyhow::Result<()> {
+    capabilities::require("voice")?;
+    engine::start()
+}
+
+// Record from `name` from now on, or from the system default with None
+pub fn select_input_device(name: Option<String>) -> anyhow::Result<Vec<AudioDevice>> {
+    if let Some(name) = &name {
+        if !engine::input_devices()?.iter().any(|d| &d.name == name) {
+            bail!("There is no microphone called \"{}\"", name);
+        }
+    }
+    let settings = VoiceSettings {
+        input_device: name,
+        ..settings()
+    };
+    storage::save(SETTINGS_FILE, &settings)?;
+    engine::input_devices()
+}
+
+// Stop recording, transcribe, and answer it when `submit` is set
+pub fn finish(
+    app: &AppHandle,
+    state: &State<AppState>,
+    submit: bool,
+) -> anyhow::Result<Transcription> {
+    let settings = settings();
+    let audio = engine::stop()?;
+    let duration_secs = audio.len() as f32 / SAMPLE_RATE as f32;
+    if audio.is_empty() {
+        bail!("Nothing was recorded");
+    }
+    let text = engine::transcribe(&settings, &audio)?;
+    let prosody = privacy::allowed(privacy::Collector::VoiceEmotion)
+        .then(|| prosody::extract(&audio, text.split_whitespace().count()));
+    // Only the transcription and a few numbers about the voice are kept
+    drop(audio);
+    let response = (submit && !text.is_empty()).then(|| voiceconfirm::answer(&text, state));
+    if let Some(prosody) = prosody {
+        let success = response
+            .as_ref()
+            .and_then(|r| r["success"].as_bool())
+            .unwrap_or(true);
+        let interaction = serde_json::json!({
+            "type": "voice",
+            "query": text,
+            "success": success,
+            "prosody": prosody,
+        });
+        crate::remember_interaction(
+            interaction,
+            None,
+            &mut state.interaction_history.blocking_lock(),
+        );
+    }
+    let transcription = Transcription {
+        text,
+        model: settings.model,
+        duration_secs,
+        response,
+    };
+    let _ = app.emit("voice-transcription", &transcription);
+    Ok(transcription)
+}
+
+// The search box the user last focused, or the first one; returns its id
+fn fill_search_input(state: &AppState, text: &str) -> Option<String> {
+    let mut components = state.components.blocking_lock();
+    let mut inputs: Vec<_> = components
+        .iter_mut()
+        .filter(|c| c.component_type == "SearchInput")
+        .collect();
+    let index = inputs
+        .iter()
+        .position(|c| c.state["focused"] == true)
+        .unwrap_or(0);
+    let input = inputs.get_mut(index)?;
+    input.state["value"] = text.into();
+    Some(input.id.clone())
+}
+
+// Held down: record. Let go: transcribe into the search box without
+// submitting, whether or not the window has focus
+pub fn push_to_talk(app: &AppHandle, pressed: bool) {
+    // Held keys repeat their press
+    if pressed == engine::listening() {
+        return;
+    }
+    if pressed {
+        match listen() {
+            Ok(()) => {
+                let _ = app.emit("push-to-talk", serde_json::json!({"listening": true}));
+            }
+            Err(e) => {
+                let _ = app.emit("voice-error", e.to_string());
+            }
+        }
+        return;
+    }
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let state = app.state::<AppState>();
+        match finish(&app, &state, false) {
+            Ok(transcription) => {
+                let component = fill_search_input(&state, &transcription.text);
+                let _ = app.emit(
+                    "push-to-talk",
+                    serde_json::json!({
+                        "listening": false,
+                        "component": component,
+                        "text": transcription.text,
+                    }),
+                );
+            }
+            Err(e) => {
+                let _ = app.emit("push-to-talk", serde_json::json!({"listening": false}));
+                let _ = app.emit("voice-error", e.to_string());
+            }
+        }
+    });
+}
+
+// The voice-input shortcut from outside the window: the first press starts
+// recording, the next one transcribes into the search box
+pub fn toggle_listening(app: &AppHandle) {
+    push_to_talk(app, !listening());
+}
+
+pub fn listening() -> bool {
+    engine::listening()
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_voice_settings(app: AppHandle) -> VoiceSettings {
+    tasks::blocking_value(&app, "Get voice settings", |_| settings()).await
+}
+
+#[tauri::command]
+pub async fn set_voice_settings(settings: VoiceSettings, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set voice settings", move |_| {
+        crate::respond(storage::save(SETTINGS_FILE, &settings).map(|_| settings))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_voice_status(app: AppHandle) -> VoiceStatus {
+    tasks::blocking_value(&app, "Get voice status", |_| status()).await
+}
+
+#[tauri::command]
+pub async fn list_audio_devices(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "List audio devices", |_| {
+        crate::respond(engine::input_devices())
+    })
+    .await
+}
+
+// Running microphone users move over to the new device
+#[tauri::command]
+pub async fn set_input_device(name: Option<String>, app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Set input device", move |_| {
+        let result = select_input_device(name).and_then(|devices| {
+            if levelmeter::running() {
+                levelmeter::start(&handle)?;
+            }
+            if wakeword::running() {
+                wakeword::start(&handle)?;
+            }
+            Ok(devices)
+        });
+        crate::respond(result)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn download_voice_model(model: WhisperModel, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Download voice model", move |_| {
+        if let Some(refusal) = managed::guard("voice_models", "Download voice models") {
+            return refusal;
+        }
+        crate::respond(download(model))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn start_listening(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Start listening", |_| {
+        crate::respond(listen().map(|()| status()))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn stop_listening(app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Transcribe", move |state| {
+        crate::respond(finish(&handle, state, settings().submit))
+    })
+    .await
+}
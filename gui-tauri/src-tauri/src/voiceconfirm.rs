@@ -0,0 +1,150 @@
+// Confirming a spoken request by saying the phrase back
+//
+// When the safety policy wants a voice request confirmed by speech, its
+// confirmation carries a spoken phrase ("yes, roll back") and is remembered
+// here. The next thing said is then checked against that phrase instead of
+// being taken as a new request: a match confirms with the backend-issued token,
+// "no" or "cancel" drops it, and anything else is asked again once before
+// giving up. Nothing runs on a mismatch. The visual confirmation keeps working
+// throughout with the same token, for when speaking it back isn't possible.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, State};
+use tokio::sync::Mutex;
+
+use crate::{fuzzy, tasks, AppState};
+
+// How closely the words heard have to match the phrase
+const MATCH: f32 = 0.8;
+const ATTEMPTS: u32 = 2;
+const REFUSALS: &[&str] = &["no", "cancel", "stop", "never mind", "don't", "abort"];
+
+struct Pending {
+    query: String,
+    token: String,
+    // The typed phrase the policy may also require
+    phrase: Option<String>,
+    spoken_phrase: String,
+    expires: Instant,
+    attempts: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingConfirmation {
+    pub query: String,
+    pub spoken_phrase: String,
+    pub attempts_left: u32,
+}
+
+static PENDING: Mutex<Option<Pending>> = Mutex::const_new(None);
+
+fn words(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn matches_phrase(heard: &str, expected: &str) -> bool {
+    let (heard, expected) = (words(heard), words(expected));
+    heard == expected || fuzzy::score(&heard, &expected) >= MATCH
+}
+
+fn refused(heard: &str) -> bool {
+    let heard = words(heard);
+    REFUSALS
+        .iter()
+        .any(|r| heard == *r || heard.starts_with(&format!("{} ", r)))
+}
+
+// Keep the spoken phrase of a confirmation the answer asks for
+fn remember(query: &str, response: &serde_json::Value) {
+    let confirmation = &response["confirmation"];
+    let (Some(token), Some(spoken_phrase)) = (
+        confirmation["token"].as_str(),
+        confirmation["spoken_phrase"].as_str(),
+    ) else {
+        return;
+    };
+    let lifetime = confirmation["expires_in_secs"].as_u64().unwrap_or(60);
+    *PENDING.blocking_lock() = Some(Pending {
+        query: query.to_string(),
+        token: token.to_string(),
+        phrase: confirmation["phrase"].as_str().map(String::from),
+        spoken_phrase: spoken_phrase.to_string(),
+        expires: Instant::now() + Duration::from_secs(lifetime),
+        attempts: 0,
+    });
+}
+
+// Answer what was said: as the confirmation being waited for, or as a request
+pub fn answer(text: &str, state: &State<AppState>) -> serde_json::Value {
+    let pending = PENDING
+        .blocking_lock()
+        .take()
+        .filter(|p| p.expires > Instant::now());
+    let Some(mut pending) = pending else {
+        let options = serde_json::json!({"input": "voice"});
+        let response = crate::answer_query(text.to_string(), Some(options), state);
+        remember(text, &response);
+        return response;
+    };
+    if matches_phrase(text, &pending.spoken_phrase) {
+        let options = serde_json::json!({
+            "input": "voice",
+            "confirmation_token": pending.token,
+            "confirmation_phrase": pending.phrase,
+        });
+        let mut response = crate::answer_query(pending.query, Some(options), state);
+        response["confirmed_by_voice"] = true.into();
+        return response;
+    }
+    if refused(text) {
+        return serde_json::json!({"success": false, "cancelled": true});
+    }
+    pending.attempts += 1;
+    let response = serde_json::json!({
+        "success": false,
+        "needs_confirmation": true,
+        "confirmation_mismatch": true,
+        "heard": text,
+        "expected": pending.spoken_phrase,
+        "attempts_left": ATTEMPTS - pending.attempts,
+    });
+    if pending.attempts < ATTEMPTS {
+        *PENDING.blocking_lock() = Some(pending);
+    }
+    response
+}
+
+pub fn pending() -> Option<PendingConfirmation> {
+    PENDING
+        .blocking_lock()
+        .as_ref()
+        .filter(|p| p.expires > Instant::now())
+        .map(|p| PendingConfirmation {
+            query: p.query.clone(),
+            spoken_phrase: p.spoken_phrase.clone(),
+            attempts_left: ATTEMPTS - p.attempts,
+        })
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_spoken_confirmation(app: AppHandle) -> Option<PendingConfirmation> {
+    tasks::blocking_value(&app, "Get spoken confirmation", |_| pending()).await
+}
+
+// Stop waiting for the phrase, e.g. once the request was confirmed on screen
+#[tauri::command]
+pub async fn cancel_spoken_confirmation(app: AppHandle) -> bool {
+    tasks::blocking_value(&app, "Cancel spoken confirmation", |_| {
+        PENDING.blocking_lock().take().is_some()
+    })
+    .await
+}
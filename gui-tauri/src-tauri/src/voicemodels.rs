@@ -0,0 +1,470 @@
+// Speech models kept on this machine
+//
+// Whisper models turn speech into text and Piper voices turn text back into
+// speech. Both live under the app data dir (Whisper in the voice models dir),
+// so once downloaded, voice works without a network. Downloads go through a
+// partial file and report bytes as operation events and can be cancelled.
+// Each file is checked against the SHA-256 Hugging Face publishes for it, and
+// the verified hash is recorded so later checks need no connection.
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::progress::Reporter;
+use crate::voice::{self, WhisperModel};
+use crate::{capabilities, managed, storage, system, tasks};
+
+const MANIFEST_FILE: &str = "voice-models.json";
+const WHISPER_BASE_URL: &str = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
+const PIPER_BASE_URL: &str = "https://huggingface.co/rhasspy/piper-voices/resolve/main";
+const POLL: Duration = Duration::from_millis(250);
+
+// Piper voices offered for download: id, path in the voices repo, language, size
+const PIPER_VOICES: &[(&str, &str, &str, u64)] = &[
+    (
+        "en_US-lessac-medium",
+        "en/en_US/lessac/medium",
+        "English (US)",
+        63,
+    ),
+    ("en_US-amy-low", "en/en_US/amy/low", "English (US)", 63),
+    (
+        "en_GB-alan-medium",
+        "en/en_GB/alan/medium",
+        "English (UK)",
+        63,
+    ),
+    (
+        "de_DE-thorsten-medium",
+        "de/de_DE/thorsten/medium",
+        "German",
+        63,
+    ),
+    (
+        "es_ES-davefx-medium",
+        "es/es_ES/davefx/medium",
+        "Spanish",
+        63,
+    ),
+    ("fr_FR-siwis-medium", "fr/fr_FR/siwis/medium", "French", 63),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelKind {
+    Whisper,
+    Piper,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelFile {
+    pub name: String,
+    pub path: PathBuf,
+    pub present: bool,
+    pub size_bytes: u64,
+    // Recorded once the download was checked
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelInfo {
+    // "whisper-base", "piper-en_US-lessac-medium", ...
+    pub id: String,
+    pub kind: ModelKind,
+    pub description: String,
+    // Roughly, before it is downloaded
+    pub download_mb: u64,
+    pub downloaded: bool,
+    pub size_bytes: u64,
+    pub files: Vec<ModelFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskUsage {
+    pub whisper_bytes: u64,
+    pub piper_bytes: u64,
+    pub total_bytes: u64,
+    // Space left where the models are stored, when df can tell
+    pub free_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelList {
+    pub models: Vec<ModelInfo>,
+    pub usage: DiskUsage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileCheck {
+    pub name: String,
+    pub expected: String,
+    pub actual: String,
+    pub ok: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Verification {
+    pub id: String,
+    pub ok: bool,
+    pub files: Vec<FileCheck>,
+}
+
+// A model's files: where each one comes from and where it goes
+struct Entry {
+    id: String,
+    kind: ModelKind,
+    description: String,
+    download_mb: u64,
+    files: Vec<(String, PathBuf)>,
+}
+
+pub fn piper_dir() -> PathBuf {
+    storage::data_dir().join("piper")
+}
+
+fn catalog() -> Vec<Entry> {
+    let settings = voice::settings();
+    let whisper = WhisperModel::ALL.iter().map(|&model| {
+        let path = settings.model_path(model);
+        let url = format!("{}/{}", WHISPER_BASE_URL, model.file_name());
+        Entry {
+            id: whisper_id(model),
+            kind: ModelKind::Whisper,
+            description: format!("Whisper {:?} speech recognition", model),
+            download_mb: model.size_mb(),
+            files: vec![(url, path)],
+        }
+    });
+    let piper = PIPER_VOICES
+        .iter()
+        .map(|(voice, repo_path, language, size_mb)| Entry {
+            id: format!("piper-{}", voice),
+            kind: ModelKind::Piper,
+            description: format!("Piper voice {} ({})", voice, language),
+            download_mb: *size_mb,
+            files: [".onnx", ".onnx.json"]
+                .iter()
+                .map(|ext| {
+                    let name = format!("{}{}", voice, ext);
+                    (
+                        format!("{}/{}/{}", PIPER_BASE_URL, repo_path, name),
+                        piper_dir().join(name),
+                    )
+                })
+                .collect(),
+        });
+    whisper.chain(piper).collect()
+}
+
+pub fn whisper_id(model: WhisperModel) -> String {
+    format!("whisper-{:?}", model).to_lowercase()
+}
+
+fn entry(id: &str) -> anyhow::Result<Entry> {
+    catalog()
+        .into_iter()
+        .find(|e| e.id == id)
+        .ok_or_else(|| anyhow!("There is no speech model \"{}\"", id))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn size_of(path: &Path) -> u64 {
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+// Verified hashes by file path
+fn manifest() -> BTreeMap<String, String> {
+    storage::load_data(MANIFEST_FILE).unwrap_or_default()
+}
+
+fn info(entry: &Entry, manifest: &BTreeMap<String, String>) -> ModelInfo {
+    let files: Vec<ModelFile> = entry
+        .files
+        .iter()
+        .map(|(_, path)| ModelFile {
+            name: file_name(path),
+            present: path.exists(),
+            size_bytes: size_of(path),
+            sha256: manifest.get(&path.to_string_lossy().into_owned()).cloned(),
+            path: path.clone(),
+        })
+        .collect();
+    ModelInfo {
+        id: entry.id.clone(),
+        kind: entry.kind,
+        description: entry.description.clone(),
+        download_mb: entry.download_mb,
+        downloaded: files.iter().all(|f| f.present),
+        size_bytes: files.iter().map(|f| f.size_bytes).sum(),
+        files,
+    }
+}
+
+// Free bytes on the filesystem holding `dir`
+fn free_space(dir: &Path) -> Option<u64> {
+    let existing = dir.ancestors().find(|d| d.exists())?;
+    let output = system::run(
+        "df",
+        &["--output=avail", "-B1", &existing.to_string_lossy()],
+    )
+    .ok()?;
+    output.lines().nth(1)?.trim().parse().ok()
+}
+
+pub fn list() -> ModelList {
+    let manifest = manifest();
+    let models: Vec<ModelInfo> = catalog().iter().map(|e| info(e, &manifest)).collect();
+    let bytes = |kind: ModelKind| -> u64 {
+        models
+            .iter()
+            .filter(|m| m.kind == kind)
+            .map(|m| m.size_bytes)
+            .sum()
+    };
+    let (whisper_bytes, piper_bytes) = (bytes(ModelKind::Whisper), bytes(ModelKind::Piper));
+    ModelList {
+        usage: DiskUsage {
+            whisper_bytes,
+            piper_bytes,
+            total_bytes: whisper_bytes + piper_bytes,
+            free_bytes: free_space(&storage::data_dir()),
+        },
+        models,
+    }
+}
+
+// What Hugging Face says about a file: its SHA-256 (LFS files carry it in
+// x-linked-etag) and size, read from the headers along the redirects
+fn remote_file(url: &str) -> anyhow::Result<(Option<String>, Option<u64>)> {
+    let headers = system::run("curl", &["-fsIL", url])?;
+    let header = |name: &str| {
+        headers.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.trim()
+                .eq_ignore_ascii_case(name)
+                .then(|| value.trim().trim_matches('"').to_string())
+        })
+    };
+    let sha256 = header("x-linked-etag")
+        .filter(|h| h.len() == 64 && h.chars().all(|c| c.is_ascii_hexdigit()));
+    let size = header("x-linked-size")
+        .or_else(|| header("content-length"))
+        .and_then(|s| s.parse().ok());
+    Ok((sha256, size))
+}
+
+fn sha256_of(path: &Path) -> anyhow::Result<String> {
+    let output = system::run("sha256sum", &[&path.to_string_lossy()])?;
+    output
+        .split_whitespace()
+        .next()
+        .map(str::to_lowercase)
+        .ok_or_else(|| anyhow!("sha256sum gave no hash for {}", path.display()))
+}
+
+// Fetch one file into `path`, moving the bar from `done` bytes on
+fn fetch(
+    url: &str,
+    path: &Path,
+    reporter: &mut Reporter,
+    done: u64,
+    message: &str,
+) -> anyhow::Result<bool> {
+    // Into a partial file first, so an interrupted download never looks complete
+    let partial = path.with_extension("part");
+    let mut child = Command::new("curl")
+        .args(["-fsSL", "-o", &partial.to_string_lossy(), url])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("Couldn't start curl: {}", e))?;
+    loop {
+        if reporter.is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            let _ = fs::remove_file(&partial);
+            return Ok(false);
+        }
+        if child.try_wait()?.is_some() {
+            break;
+        }
+        reporter.advance(done + size_of(&partial), message);
+        std::thread::sleep(POLL);
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&partial);
+        bail!(
+            "Downloading {} failed: {}",
+            file_name(path),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    fs::rename(&partial, path)?;
+    Ok(true)
+}
+
+// Download whatever of the model is missing, checking each file as it lands
+pub fn download(id: &str) -> anyhow::Result<ModelInfo> {
+    let entry = entry(id)?;
+    let missing: Vec<&(String, PathBuf)> = entry
+        .files
+        .iter()
+        .filter(|(_, path)| !path.exists())
+        .collect();
+    if missing.is_empty() {
+        return Ok(info(&entry, &manifest()));
+    }
+    capabilities::require("model-downloads")?;
+    let mut remote = Vec::new();
+    for (url, path) in &missing {
+        remote.push((url, path, remote_file(url)?));
+    }
+    let total = remote
+        .iter()
+        .map(|(_, _, (_, size))| *size)
+        .sum::<Option<u64>>();
+    let message = format!("Downloading {}", entry.description);
+    let mut reporter = Reporter::start("download-model", &message, total, true);
+    let mut done = 0;
+    let mut hashes = manifest();
+    for (url, path, (expected, size)) in remote {
+        let result: anyhow::Result<bool> = (|| {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            if !fetch(url, path, &mut reporter, done, &message)? {
+                return Ok(false);
+            }
+            let actual = sha256_of(path)?;
+            if let Some(expected) = &expected {
+                if &actual != expected {
+                    let _ = fs::remove_file(path);
+                    bail!(
+                        "{} doesn't match its published checksum and was removed",
+                        file_name(path)
+                    );
+                }
+            }
+            hashes.insert(path.to_string_lossy().into_owned(), actual);
+            Ok(true)
+        })();
+        match result {
+            Ok(true) => done += size.unwrap_or_else(|| size_of(path)),
+            Ok(false) => {
+                reporter.cancel("Download cancelled");
+                storage::save_data(MANIFEST_FILE, &hashes)?;
+                bail!("The download of {} was cancelled", entry.id);
+            }
+            Err(e) => {
+                reporter.fail(&e.to_string());
+                storage::save_data(MANIFEST_FILE, &hashes)?;
+                return Err(e);
+            }
+        }
+    }
+    storage::save_data(MANIFEST_FILE, &hashes)?;
+    reporter.succeed(&format!("Downloaded {}", entry.description));
+    Ok(info(&entry, &hashes))
+}
+
+// Check the files on disk against the recorded hashes, or the published ones
+// for files downloaded before hashes were recorded
+pub fn verify(id: &str) -> anyhow::Result<Verification> {
+    let entry = entry(id)?;
+    let mut hashes = manifest();
+    let mut files = Vec::new();
+    for (url, path) in &entry.files {
+        if !path.exists() {
+            bail!("{} isn't downloaded", entry.id);
+        }
+        let key = path.to_string_lossy().into_owned();
+        let expected = match hashes.get(&key) {
+            Some(hash) => hash.clone(),
+            None => remote_file(url)?
+                .0
+                .ok_or_else(|| anyhow!("No checksum is published for {}", file_name(path)))?,
+        };
+        let actual = sha256_of(path)?;
+        let ok = actual == expected;
+        if ok {
+            hashes.insert(key, actual.clone());
+        }
+        files.push(FileCheck {
+            name: file_name(path),
+            expected,
+            actual,
+            ok,
+        });
+    }
+    storage::save_data(MANIFEST_FILE, &hashes)?;
+    Ok(Verification {
+        id: entry.id,
+        ok: files.iter().all(|f| f.ok),
+        files,
+    })
+}
+
+pub fn delete(id: &str) -> anyhow::Result<ModelList> {
+    let entry = entry(id)?;
+    let mut hashes = manifest();
+    for (_, path) in &entry.files {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        let _ = fs::remove_file(path.with_extension("part"));
+        hashes.remove(&path.to_string_lossy().into_owned());
+    }
+    storage::save_data(MANIFEST_FILE, &hashes)?;
+    Ok(list())
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn list_voice_models(app: AppHandle) -> ModelList {
+    tasks::blocking_value(&app, "List voice models", |_| list()).await
+}
+
+#[tauri::command]
+pub async fn download_model(id: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Download model", move |_| {
+        if let Some(refusal) = managed::guard("voice_models", "Download voice models") {
+            return refusal;
+        }
+        crate::respond(download(&id))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn verify_model(id: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Verify model", move |_| crate::respond(verify(&id))).await
+}
+
+#[tauri::command]
+pub async fn delete_model(id: String, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Delete model", move |_| {
+        if let Some(refusal) = managed::guard("voice_models", "Delete voice models") {
+            return refusal;
+        }
+        crate::respond(delete(&id))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_model_disk_usage(app: AppHandle) -> DiskUsage {
+    tasks::blocking_value(&app, "Get model disk usage", |_| list().usage).await
+}
@@ -0,0 +1,337 @@
+// Hands-free voice input: "Hey Nix"
+//
+// While enabled, a small keyword detector listens to the microphone all the
+// time. Audio goes nowhere else: only after the wake phrase is heard does the
+// full Whisper recording start, and it ends by itself after a pause (or a
+// maximum length) and is transcribed and answered like push-to-talk. The
+// detector is a rustpotter wake word file trained on the phrase; sensitivity
+// trades missed wake-ups against false ones. Whether it is on shows in the
+// state of every component with the "voice" capability. Needs the `wake-word`
+// build feature.
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Mutex;
+
+use crate::{capabilities, storage, tasks, voice, AppState};
+
+const SETTINGS_FILE: &str = "wake-word.json";
+pub const PHRASE: &str = "Hey Nix";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WakeWordSettings {
+    pub enabled: bool,
+    // 0..1; higher wakes more easily, and more often by mistake
+    pub sensitivity: f32,
+    // A rustpotter wake word file; defaults to hey-nix.rpw next to the speech models
+    pub model_path: Option<String>,
+    // The command ends after this much silence
+    pub silence_ms: u64,
+    pub max_command_secs: u64,
+}
+
+impl Default for WakeWordSettings {
+    fn default() -> Self {
+        WakeWordSettings {
+            enabled: false,
+            sensitivity: 0.5,
+            model_path: None,
+            silence_ms: 1200,
+            max_command_secs: 10,
+        }
+    }
+}
+
+impl WakeWordSettings {
+    pub fn model(&self) -> PathBuf {
+        self.model_path
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| voice::settings().models_dir().join("hey-nix.rpw"))
+    }
+
+    // The detector's score threshold
+    fn threshold(&self) -> f32 {
+        (1.0 - self.sensitivity).clamp(0.05, 0.95)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WakeWordStatus {
+    // Whether this build can detect the wake word at all
+    pub available: bool,
+    pub enabled: bool,
+    pub running: bool,
+    pub phrase: String,
+    pub sensitivity: f32,
+    pub model: PathBuf,
+    pub model_present: bool,
+}
+
+static RUNNING: Mutex<Option<Arc<AtomicBool>>> = Mutex::const_new(None);
+
+pub fn settings() -> WakeWordSettings {
+    storage::load(SETTINGS_FILE).unwrap_or_default()
+}
+
+// The detector and command capture, only built with the `wake-word` feature
+#[cfg(feature = "wake-word")]
+mod detector {
+    use anyhow::{anyhow, bail};
+    use rustpotter::{Rustpotter, RustpotterConfig, SampleFormat};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc};
+    use std::time::{Duration, Instant};
+    use tauri::{AppHandle, Emitter, Manager};
+
+    use super::{WakeWordSettings, PHRASE};
+    use crate::{voice, AppState};
+
+    // Quieter than this (RMS) counts as silence while a command is spoken
+    const SILENCE_RMS: f32 = 0.01;
+
+    // Capturing a command after the wake phrase
+    struct Command {
+        started: Instant,
+        last_voice: Instant,
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        if samples.is_empty() {
+            return 0.0;
+        }
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    fn load(settings: &WakeWordSettings) -> anyhow::Result<Rustpotter> {
+        let model = settings.model();
+        if !model.exists() {
+            bail!(
+                "No wake word file for \"{}\" at {}",
+                PHRASE,
+                model.display()
+            );
+        }
+        let mut config = RustpotterConfig::default();
+        config.fmt.sample_rate = 16_000;
+        config.fmt.sample_format = SampleFormat::F32;
+        config.fmt.channels = 1;
+        config.detector.threshold = settings.threshold();
+        let mut detector = Rustpotter::new(&config).map_err(anyhow::Error::msg)?;
+        detector
+            .add_wakeword_from_file("hey_nix", &model.to_string_lossy())
+            .map_err(anyhow::Error::msg)?;
+        Ok(detector)
+    }
+
+    // After a pause or at the length limit, transcribe and answer off this thread
+    fn finish_command(app: &AppHandle) {
+        let app = app.clone();
+        std::thread::spawn(move || {
+            let state = app.state::<AppState>();
+            if let Err(e) = voice::finish(&app, &state, voice::settings().submit) {
+                let _ = app.emit("voice-error", e.to_string());
+            }
+        });
+    }
+
+    pub fn start(
+        app: &AppHandle,
+        settings: WakeWordSettings,
+        stop: Arc<AtomicBool>,
+    ) -> anyhow::Result<()> {
+        // Fail here, not in the thread, when the wake word file is missing
+        let mut detector = load(&settings)?;
+        let (ready, started) = mpsc::channel::<anyhow::Result<()>>();
+        let app = app.clone();
+        std::thread::spawn(move || {
+            let (sender, chunks) = mpsc::channel::<Vec<f32>>();
+            let (stream, channels, rate) = match voice::open_stream(move |data| {
+                let _ = sender.send(data.to_vec());
+            }) {
+                Ok(opened) => {
+                    let _ = ready.send(Ok(()));
+                    opened
+                }
+                Err(e) => {
+                    let _ = ready.send(Err(e));
+                    return;
+                }
+            };
+            let frame = detector.get_samples_per_frame();
+            let silence = Duration::from_millis(settings.silence_ms);
+            let limit = Duration::from_secs(settings.max_command_secs);
+            let mut pending: Vec<f32> = Vec::new();
+            let mut command: Option<Command> = None;
+            while !stop.load(Ordering::Relaxed) {
+                let Ok(chunk) = chunks.recv_timeout(Duration::from_millis(200)) else {
+                    continue;
+                };
+                let audio = voice::to_whisper_input(&chunk, channels, rate);
+                if let Some(current) = command.as_mut() {
+                    if rms(&audio) >= SILENCE_RMS {
+                        current.last_voice = Instant::now();
+                    }
+                    if current.last_voice.elapsed() >= silence || current.started.elapsed() >= limit
+                    {
+                        command = None;
+                        finish_command(&app);
+                    }
+                    continue;
+                }
+                pending.extend(audio);
+                while pending.len() >= frame {
+                    let samples: Vec<f32> = pending.drain(..frame).collect();
+                    let Some(detection) = detector.process_samples(samples) else {
+                        continue;
+                    };
+                    let _ = app.emit(
+                        "wake-word",
+                        serde_json::json!({"phrase": PHRASE, "score": detection.score}),
+                    );
+                    match voice::listen() {
+                        Ok(()) => {
+                            command = Some(Command {
+                                started: Instant::now(),
+                                last_voice: Instant::now(),
+                            });
+                        }
+                        Err(e) => {
+                            let _ = app.emit("voice-error", e.to_string());
+                        }
+                    }
+                    pending.clear();
+                    break;
+                }
+            }
+            drop(stream);
+        });
+        started
+            .recv()
+            .map_err(|_| anyhow!("The wake word thread stopped"))?
+    }
+}
+
+#[cfg(not(feature = "wake-word"))]
+mod detector {
+    use anyhow::bail;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use tauri::AppHandle;
+
+    use super::WakeWordSettings;
+
+    pub fn start(
+        _app: &AppHandle,
+        _settings: WakeWordSettings,
+        _stop: Arc<AtomicBool>,
+    ) -> anyhow::Result<()> {
+        bail!("This build does not include wake word detection (enable the `wake-word` feature)")
+    }
+}
+
+pub fn running() -> bool {
+    RUNNING.blocking_lock().is_some()
+}
+
+// Show whether the wake word is on wherever voice input is offered
+fn reflect(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    for component in state.components.blocking_lock().iter_mut() {
+        if !component.capabilities.iter().any(|c| c.name == "voice") {
+            continue;
+        }
+        if let Some(map) = component.state.as_object_mut() {
+            map.insert(
+                "wake_word".to_string(),
+                serde_json::json!({"active": running(), "phrase": PHRASE}),
+            );
+        }
+    }
+}
+
+fn stop() {
+    if let Some(stop) = RUNNING.blocking_lock().take() {
+        stop.store(true, Ordering::Relaxed);
+    }
+}
+
+// (Re)start with the saved settings, or stay off when disabled
+pub fn start(app: &AppHandle) -> anyhow::Result<()> {
+    let settings = settings();
+    if !(0.0..=1.0).contains(&settings.sensitivity) {
+        bail!("Sensitivity goes from 0 to 1");
+    }
+    stop();
+    let result = if settings.enabled {
+        let flag = Arc::new(AtomicBool::new(false));
+        capabilities::require("wake-word")
+            .and_then(|()| detector::start(app, settings, flag.clone()))
+            .map(|()| {
+                *RUNNING.blocking_lock() = Some(flag);
+            })
+    } else {
+        Ok(())
+    };
+    reflect(app);
+    result
+}
+
+pub fn status() -> WakeWordStatus {
+    let settings = settings();
+    let model = settings.model();
+    WakeWordStatus {
+        available: cfg!(feature = "wake-word"),
+        enabled: settings.enabled,
+        running: running(),
+        phrase: PHRASE.to_string(),
+        sensitivity: settings.sensitivity,
+        model_present: model.exists(),
+        model,
+    }
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_wake_word_settings(app: AppHandle) -> WakeWordSettings {
+    tasks::blocking_value(&app, "Get wake word settings", |_| settings()).await
+}
+
+#[tauri::command]
+pub async fn set_wake_word_settings(
+    settings: WakeWordSettings,
+    app: AppHandle,
+) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Set wake word settings", move |_| {
+        let result = storage::save(SETTINGS_FILE, &settings).and_then(|_| start(&handle));
+        crate::respond(result.map(|()| status()))
+    })
+    .await
+}
+
+// Turn listening for the wake word on or off, keeping the other settings
+#[tauri::command]
+pub async fn set_wake_word(enabled: bool, app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Set wake word", move |_| {
+        let settings = WakeWordSettings {
+            enabled,
+            ..settings()
+        };
+        let result = storage::save(SETTINGS_FILE, &settings).and_then(|_| start(&handle));
+        crate::respond(result.map(|()| status()))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_wake_word_status(app: AppHandle) -> WakeWordStatus {
+    tasks::blocking_value(&app, "Get wake word status", |_| status()).await
+}
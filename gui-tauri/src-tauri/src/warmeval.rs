@@ -0,0 +1,298 @@
+// A persistent Nix evaluator kept warm with nixpkgs loaded
+//
+// Every `nix eval` or `nix search` starts from scratch: fetch the flake,
+// parse nixpkgs, then evaluate. A `nix repl` started once with nixpkgs loaded
+// answers attribute lookups in milliseconds instead. On startup the worker is
+// spawned and `nix search` is run once in the background so its evaluation
+// cache is filled before the first real search. Both are redone when a flake
+// lock or the flake registry changes, since nixpkgs may then be different.
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::AppHandle;
+use tokio::sync::Mutex;
+
+use crate::evalpool::{self, Priority};
+use crate::{clock, nix, storage, system, tasks};
+
+const STATE_FILE: &str = "warm-eval.json";
+const SENTINEL: &str = "__luminous_nix_done__";
+// Loading nixpkgs the first time can take a while on a cold store
+const START_TIMEOUT: Duration = Duration::from_secs(120);
+const EVAL_TIMEOUT: Duration = Duration::from_secs(30);
+
+struct Worker {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    fingerprint: String,
+    started_at: u64,
+    evaluations: u64,
+}
+
+static WORKER: Mutex<Option<Worker>> = Mutex::const_new(None);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct WarmState {
+    // Fingerprint the search cache was last primed for
+    primed_for: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkerStatus {
+    pub running: bool,
+    pub started_at: Option<u64>,
+    pub evaluations: u64,
+    pub fingerprint: String,
+    pub search_cache_primed: bool,
+}
+
+// Files whose change means nixpkgs may resolve to something else
+fn lock_files() -> Vec<PathBuf> {
+    vec![
+        PathBuf::from("/etc/nixos/flake.lock"),
+        PathBuf::from("/etc/nix/registry.json"),
+        system::xdg_config_home().join("nix/registry.json"),
+        system::xdg_config_home().join("home-manager/flake.lock"),
+    ]
+}
+
+fn fingerprint() -> String {
+    lock_files()
+        .iter()
+        .map(|path| {
+            let modified = std::fs::metadata(path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs());
+            format!("{}", modified)
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+// Nix prints strings with its own escapes; apart from `\$` they are JSON's
+fn unquote(line: &str) -> anyhow::Result<String> {
+    serde_json::from_str(&line.trim().replace("\\$", "$"))
+        .with_context(|| format!("Unexpected output from nix repl: {}", line))
+}
+
+impl Worker {
+    fn spawn() -> anyhow::Result<Worker> {
+        let mut child = Command::new("nix")
+            .args([
+                "repl",
+                "--extra-experimental-features",
+                "nix-command flakes",
+            ])
+            .env("NO_COLOR", "1")
+            .env("TERM", "dumb")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("failed to start nix repl")?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        // Errors go to stderr; both are read into one stream of lines so an
+        // evaluation can tell where its output ends
+        let (sender, lines) = mpsc::channel();
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+        let out = sender.clone();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if out.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        std::thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                if sender.send(format!("stderr: {}", line)).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut worker = Worker {
+            child,
+            stdin,
+            lines,
+            fingerprint: fingerprint(),
+            started_at: clock::now(),
+            evaluations: 0,
+        };
+        worker.send(
+            "pkgs = (builtins.getFlake \"nixpkgs\").legacyPackages.${builtins.currentSystem}",
+        )?;
+        // Forces nixpkgs to load now rather than on the first real lookup
+        worker.eval("pkgs.lib.version", START_TIMEOUT)?;
+        Ok(worker)
+    }
+
+    fn send(&mut self, line: &str) -> anyhow::Result<()> {
+        writeln!(self.stdin, "{}", line)?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    // Evaluate to JSON; the sentinel echoed after it marks the end of its output
+    fn eval(&mut self, expression: &str, timeout: Duration) -> anyhow::Result<serde_json::Value> {
+        // Late stderr from an earlier evaluation doesn't belong to this one
+        while self.lines.try_recv().is_ok() {}
+        self.send(&format!("builtins.toJSON ({})", expression))?;
+        self.send(&format!("\"{}\"", SENTINEL))?;
+        let mut output = Vec::new();
+        loop {
+            let Ok(line) = self.lines.recv_timeout(timeout) else {
+                // Still busy with it or gone; either way it can't be reused
+                let _ = self.child.kill();
+                let _ = self.child.wait();
+                bail!("nix repl stopped answering");
+            };
+            if line.contains(SENTINEL) {
+                break;
+            }
+            output.push(line);
+        }
+        self.evaluations += 1;
+        let errors: Vec<&str> = output
+            .iter()
+            .filter_map(|l| l.strip_prefix("stderr: "))
+            .filter(|l| !l.trim().is_empty())
+            .collect();
+        if errors.iter().any(|l| l.trim_start().starts_with("error")) {
+            bail!("{}", errors.join("\n"));
+        }
+        let Some(value) = output
+            .iter()
+            .rev()
+            .find(|l| l.trim_start().starts_with('"'))
+        else {
+            bail!("nix repl gave no result for {}", expression);
+        };
+        Ok(serde_json::from_str(&unquote(value)?)?)
+    }
+
+    fn alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// Attribute paths like "python312Packages.numpy"; anything else could be
+// more than one expression once it reaches the repl
+pub fn is_attr_path(attr: &str) -> bool {
+    !attr.is_empty()
+        && attr
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._-'+".contains(c))
+}
+
+// Start or recycle the worker as needed and evaluate `expression` to JSON
+pub fn eval_json(expression: &str) -> anyhow::Result<serde_json::Value> {
+    let mut worker = WORKER.blocking_lock();
+    let stale = match worker.as_mut() {
+        Some(w) => !w.alive() || w.fingerprint != fingerprint(),
+        None => true,
+    };
+    if stale {
+        // Dropping the old worker kills it
+        *worker = None;
+        *worker = Some(Worker::spawn()?);
+    }
+    let result = worker
+        .as_mut()
+        .expect("worker was just started")
+        .eval(expression, EVAL_TIMEOUT);
+    // A timed-out repl has been killed; start afresh next time
+    if result.is_err() && !worker.as_mut().is_some_and(|w| w.alive()) {
+        *worker = None;
+    }
+    result
+}
+
+// Fill `nix search`'s evaluation cache, unless it's already filled for the
+// current lock files
+fn prime_search_cache() -> anyhow::Result<()> {
+    let current = fingerprint();
+    let state: WarmState = storage::load_data(STATE_FILE)?;
+    if state.primed_for.as_deref() == Some(current.as_str()) {
+        return Ok(());
+    }
+    nix::search_each("^", |_| {})?;
+    storage::save_data(
+        STATE_FILE,
+        &WarmState {
+            primed_for: Some(current),
+        },
+    )
+    .map(|_| ())
+}
+
+// Warm both up in the background; called once at startup
+pub fn start() {
+    if system::find_in_path("nix").is_none() {
+        return;
+    }
+    evalpool::spawn(Priority::Background, || {
+        let _ = eval_json("true");
+    });
+    evalpool::spawn(Priority::Background, || {
+        let _ = prime_search_cache();
+    });
+}
+
+pub fn status() -> WorkerStatus {
+    let current = fingerprint();
+    // Don't wait behind a running evaluation just to report on it
+    let (running, started_at, evaluations) = match WORKER.try_lock() {
+        Ok(mut worker) => match worker.as_mut() {
+            Some(w) => (w.alive(), Some(w.started_at), w.evaluations),
+            None => (false, None, 0),
+        },
+        Err(_) => (true, None, 0),
+    };
+    let primed = storage::load_data::<WarmState>(STATE_FILE)
+        .map(|s| s.primed_for.as_deref() == Some(current.as_str()))
+        .unwrap_or(false);
+    WorkerStatus {
+        running,
+        started_at,
+        evaluations,
+        fingerprint: current,
+        search_cache_primed: primed,
+    }
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_eval_worker_status(app: AppHandle) -> WorkerStatus {
+    tasks::blocking_value(&app, "Get eval worker status", |_| status()).await
+}
+
+// Drop the worker and the primed cache marker, then warm up again
+#[tauri::command]
+pub async fn restart_eval_worker(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Restart eval worker", |_| {
+        *WORKER.blocking_lock() = None;
+        let result = storage::save_data(STATE_FILE, &WarmState::default()).map(|_| {
+            start();
+            status()
+        });
+        crate::respond(result)
+    })
+    .await
+}
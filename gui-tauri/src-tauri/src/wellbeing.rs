@@ -0,0 +1,456 @@
+// Mindful breaks and sacred pauses (opt-in)
+//
+// When enabled, a "break-reminder" is sent after a configurable stretch of
+// continuous use, i.e. activity without a pause of a few minutes. A pause dims
+// and locks the interactive components for a short breathing exercise and
+// unlocks them when it ends. Whether the user took the pause (honored) or
+// dismissed it (skipped) is counted in the profile.
+//
+// Also opt-in, the profile's consciousness_state is computed from measurable
+// signals (see `measure`), and the sacred-tech features, such as the Sacred
+// personality, are only offered while it is tracked.
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Mutex;
+
+use crate::userprofile::{self, UserProfile};
+use crate::{clock, flow, storage, tasks, AppState};
+
+const SETTINGS_FILE: &str = "wellbeing.json";
+const PREFERENCE_KEY: &str = "wellbeing";
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+// A quiet spell this long ends a stretch of continuous use
+const BREAK_GAP_MS: u64 = 5 * 60 * 1000;
+const MAX_PAUSE_SECS: u64 = 30 * 60;
+// Interactions older than this don't count towards consciousness_state
+const METRICS_WINDOW_MS: u64 = 2 * 60 * 60 * 1000;
+// Gaps up to this long keep focus unbroken, as for flow
+const FOCUS_GAP_MS: u64 = 90 * 1000;
+// Recovering from a failure this fast scores 0.5
+const RECOVERY_HALF_MS: f32 = 60_000.0;
+const FORMULA: &str = "0.4 × focus continuity + 0.3 × error recovery + 0.3 × pause adherence";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WellbeingSettings {
+    pub enabled: bool,
+    pub break_after_minutes: u64,
+    // How long before reminding again after a skipped reminder
+    pub snooze_minutes: u64,
+    pub pause_secs: u64,
+    pub breathing: BreathingPattern,
+    // Compute consciousness_state and offer the sacred-tech features
+    pub track_consciousness: bool,
+}
+
+// Seconds per phase of one breath
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BreathingPattern {
+    pub inhale: u32,
+    pub hold: u32,
+    pub exhale: u32,
+}
+
+impl Default for WellbeingSettings {
+    fn default() -> Self {
+        WellbeingSettings {
+            enabled: false,
+            break_after_minutes: 50,
+            snooze_minutes: 15,
+            pause_secs: 60,
+            breathing: BreathingPattern {
+                inhale: 4,
+                hold: 4,
+                exhale: 6,
+            },
+            track_consciousness: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PauseRecord {
+    pub honored: u32,
+    pub skipped: u32,
+    pub last_pause: Option<u64>,
+    pub last_reminder: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pause {
+    pub id: u64,
+    pub started_ms: u64,
+    pub duration_secs: u64,
+    pub breathing: BreathingPattern,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BreakReminder {
+    pub continuous_minutes: u64,
+    pub suggested_pause_secs: u64,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WellbeingStatus {
+    pub settings: WellbeingSettings,
+    pub record: PauseRecord,
+    pub continuous_minutes: u64,
+    pub pause: Option<Pause>,
+}
+
+// Each input is in 0..1; None when there is nothing to measure yet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WellbeingMetrics {
+    pub enabled: bool,
+    pub consciousness_state: Option<f32>,
+    pub focus_continuity: Option<f32>,
+    pub error_recovery: Option<f32>,
+    pub pause_adherence: Option<f32>,
+    pub formula: String,
+}
+
+static ACTIVE: Mutex<Option<Pause>> = Mutex::const_new(None);
+static NEXT_PAUSE: AtomicU64 = AtomicU64::new(1);
+
+pub fn settings() -> WellbeingSettings {
+    storage::load(SETTINGS_FILE).unwrap_or_default()
+}
+
+// How long the user has been active without a real break
+pub fn continuous_use_ms(history: &[serde_json::Value], now_ms: u64) -> u64 {
+    let mut start = None;
+    let mut next = now_ms;
+    for at in history
+        .iter()
+        .rev()
+        .filter_map(|i| i.get("timestamp_ms").and_then(|t| t.as_u64()))
+    {
+        if next.saturating_sub(at) > BREAK_GAP_MS {
+            break;
+        }
+        start = Some(at);
+        next = at;
+    }
+    start.map_or(0, |start| now_ms.saturating_sub(start))
+}
+
+pub fn sacred_tech_enabled() -> bool {
+    settings().track_consciousness
+}
+
+// (timestamp, succeeded) of the interactions within the metrics window
+fn recent(history: &[serde_json::Value], now_ms: u64) -> Vec<(u64, Option<bool>)> {
+    history
+        .iter()
+        .filter_map(|i| {
+            let at = i.get("timestamp_ms").and_then(|t| t.as_u64())?;
+            Some((at, i.get("success").and_then(|s| s.as_bool())))
+        })
+        .filter(|(at, _)| now_ms.saturating_sub(*at) <= METRICS_WINDOW_MS)
+        .collect()
+}
+
+// Share of the gaps between consecutive interactions short enough not to
+// break focus
+fn focus_continuity(recent: &[(u64, Option<bool>)]) -> Option<f32> {
+    let gaps: Vec<u64> = recent
+        .windows(2)
+        .map(|pair| pair[1].0.saturating_sub(pair[0].0))
+        .collect();
+    if gaps.is_empty() {
+        return None;
+    }
+    let unbroken = gaps.iter().filter(|gap| **gap <= FOCUS_GAP_MS).count();
+    Some(unbroken as f32 / gaps.len() as f32)
+}
+
+// Average over failures of 1 / (1 + t / 60 s), t being the time until the
+// next success; a failure nothing succeeded after scores 0
+fn error_recovery(recent: &[(u64, Option<bool>)]) -> Option<f32> {
+    let scores: Vec<f32> = recent
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, success))| *success == Some(false))
+        .map(|(i, (failed_at, _))| {
+            recent[i + 1..]
+                .iter()
+                .find(|(_, success)| *success == Some(true))
+                .map_or(0.0, |(at, _)| {
+                    1.0 / (1.0 + at.saturating_sub(*failed_at) as f32 / RECOVERY_HALF_MS)
+                })
+        })
+        .collect();
+    if scores.is_empty() {
+        // Nothing to recover from
+        return recent.iter().any(|(_, s)| s.is_some()).then_some(1.0);
+    }
+    Some(scores.iter().sum::<f32>() / scores.len() as f32)
+}
+
+fn pause_adherence(record: &PauseRecord) -> Option<f32> {
+    let offered = record.honored + record.skipped;
+    (offered > 0).then(|| record.honored as f32 / offered as f32)
+}
+
+// consciousness_state = 0.4 F + 0.3 R + 0.3 P, where F is focus continuity,
+// R error recovery and P pause adherence over the last two hours. Inputs with
+// nothing to measure count as 0.5, so a new profile starts in the middle.
+pub fn measure(
+    history: &[serde_json::Value],
+    record: &PauseRecord,
+    now_ms: u64,
+) -> WellbeingMetrics {
+    let recent = recent(history, now_ms);
+    let focus = focus_continuity(&recent);
+    let recovery = error_recovery(&recent);
+    let adherence = pause_adherence(record);
+    let value = |input: Option<f32>| input.unwrap_or(0.5);
+    let state = 0.4 * value(focus) + 0.3 * value(recovery) + 0.3 * value(adherence);
+    WellbeingMetrics {
+        enabled: true,
+        consciousness_state: Some(state.clamp(0.0, 1.0)),
+        focus_continuity: focus,
+        error_recovery: recovery,
+        pause_adherence: adherence,
+        formula: FORMULA.to_string(),
+    }
+}
+
+fn record(profile: &UserProfile) -> PauseRecord {
+    profile
+        .preferences
+        .get(PREFERENCE_KEY)
+        .and_then(|r| serde_json::from_value(r.clone()).ok())
+        .unwrap_or_default()
+}
+
+fn update_record(state: &AppState, change: impl FnOnce(&mut PauseRecord)) -> anyhow::Result<()> {
+    let mut profile = state.user_profile.blocking_lock();
+    let profile = profile.get_or_insert_with(UserProfile::default);
+    let mut record = record(profile);
+    change(&mut record);
+    if !profile.preferences.is_object() {
+        profile.preferences = serde_json::json!({});
+    }
+    profile.preferences[PREFERENCE_KEY] = serde_json::to_value(&record)?;
+    userprofile::save(profile)
+}
+
+// Dim and lock (or restore) every interactive component
+fn lock_components(state: &AppState, locked: bool) {
+    for component in state.components.blocking_lock().iter_mut() {
+        if let Some(map) = component.state.as_object_mut() {
+            if locked {
+                map.insert("locked".to_string(), true.into());
+                map.insert("dimmed".to_string(), true.into());
+            } else {
+                map.remove("locked");
+                map.remove("dimmed");
+            }
+        }
+    }
+}
+
+pub fn begin(app: &AppHandle, duration_secs: Option<u64>) -> anyhow::Result<Pause> {
+    let mut active = ACTIVE.blocking_lock();
+    if active.is_some() {
+        bail!("A pause is already in progress");
+    }
+    let settings = settings();
+    let pause = Pause {
+        id: NEXT_PAUSE.fetch_add(1, Ordering::Relaxed),
+        started_ms: clock::now_ms(),
+        duration_secs: duration_secs
+            .unwrap_or(settings.pause_secs)
+            .clamp(10, MAX_PAUSE_SECS),
+        breathing: settings.breathing,
+    };
+    *active = Some(pause.clone());
+    drop(active);
+
+    lock_components(&app.state::<AppState>(), true);
+    let _ = app.emit("pause-started", &pause);
+    let app = app.clone();
+    let (id, duration) = (pause.id, pause.duration_secs);
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_secs(duration));
+        // Only if this pause wasn't ended early
+        let _ = end(&app, id, true);
+    });
+    Ok(pause)
+}
+
+// End pause `id`; `completed` says whether the user stayed for all of it
+pub fn end(app: &AppHandle, id: u64, completed: bool) -> anyhow::Result<()> {
+    {
+        let mut active = ACTIVE.blocking_lock();
+        if active.as_ref().map(|p| p.id) != Some(id) {
+            bail!("That pause has already ended");
+        }
+        *active = None;
+    }
+    let state = app.state::<AppState>();
+    lock_components(&state, false);
+    let _ = app.emit(
+        "pause-ended",
+        serde_json::json!({"id": id, "completed": completed}),
+    );
+    let now = clock::now();
+    update_record(&state, |record| {
+        if completed {
+            record.honored += 1;
+            record.last_pause = Some(now);
+        } else {
+            record.skipped += 1;
+        }
+    })
+}
+
+fn check(app: &AppHandle) {
+    let settings = settings();
+    if !settings.enabled || ACTIVE.blocking_lock().is_some() {
+        return;
+    }
+    let state = app.state::<AppState>();
+    let now_ms = clock::now_ms();
+    let continuous = continuous_use_ms(&state.interaction_history.blocking_lock(), now_ms);
+    if continuous < settings.break_after_minutes * 60 * 1000 {
+        return;
+    }
+    let record = state
+        .user_profile
+        .blocking_lock()
+        .as_ref()
+        .map(record)
+        .unwrap_or_default();
+    let now = now_ms / 1000;
+    let since = |at: Option<u64>| at.map_or(u64::MAX, |at| now.saturating_sub(at));
+    // Not right after a pause, and not again until the snooze has passed
+    if since(record.last_pause) < settings.break_after_minutes * 60
+        || since(record.last_reminder) < settings.snooze_minutes * 60
+    {
+        return;
+    }
+    let minutes = continuous / 60_000;
+    // Waits for a natural break when the user is in flow
+    flow::notify(
+        app,
+        "break-reminder",
+        BreakReminder {
+            continuous_minutes: minutes,
+            suggested_pause_secs: settings.pause_secs,
+            message: format!(
+                "You've been at it for {} minutes. How about a minute to breathe?",
+                minutes
+            ),
+        },
+        false,
+    );
+    let _ = update_record(&state, |record| record.last_reminder = Some(now));
+}
+
+pub fn start_watcher(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        check(&app);
+        std::thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_wellbeing(app: AppHandle) -> WellbeingStatus {
+    tasks::blocking_value(&app, "Get wellbeing", |state| {
+        let record = state
+            .user_profile
+            .blocking_lock()
+            .as_ref()
+            .map(record)
+            .unwrap_or_default();
+        let continuous =
+            continuous_use_ms(&state.interaction_history.blocking_lock(), clock::now_ms());
+        WellbeingStatus {
+            settings: settings(),
+            record,
+            continuous_minutes: continuous / 60_000,
+            pause: ACTIVE.blocking_lock().clone(),
+        }
+    })
+    .await
+}
+
+// Measures and stores consciousness_state when tracking is on
+#[tauri::command]
+pub async fn get_wellbeing_metrics(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Get wellbeing metrics", |state| {
+        if !sacred_tech_enabled() {
+            return crate::respond(Ok(WellbeingMetrics {
+                enabled: false,
+                consciousness_state: None,
+                focus_continuity: None,
+                error_recovery: None,
+                pause_adherence: None,
+                formula: FORMULA.to_string(),
+            }));
+        }
+        let history = state.interaction_history.blocking_lock().clone();
+        let mut profile = state.user_profile.blocking_lock();
+        let profile = profile.get_or_insert_with(UserProfile::default);
+        let metrics = measure(&history, &record(profile), clock::now_ms());
+        let measured = metrics.consciousness_state.unwrap_or(0.5);
+        // Not worth a write for noise
+        let result = if (profile.consciousness_state - measured).abs() >= 0.01 {
+            profile.consciousness_state = measured;
+            userprofile::save(profile)
+        } else {
+            Ok(())
+        };
+        crate::respond(result.map(|()| metrics))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn set_wellbeing_settings(
+    settings: WellbeingSettings,
+    app: AppHandle,
+) -> serde_json::Value {
+    tasks::blocking_json(&app, "Set wellbeing settings", move |_| {
+        crate::respond(storage::save(SETTINGS_FILE, &settings).map(|_| settings))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn begin_pause(duration: Option<u64>, app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "Begin pause", move |_| {
+        crate::respond(begin(&handle, duration))
+    })
+    .await
+}
+
+// Leave a pause before its time is up
+#[tauri::command]
+pub async fn end_pause(id: u64, app: AppHandle) -> serde_json::Value {
+    let handle = app.clone();
+    tasks::blocking_json(&app, "End pause", move |_| {
+        crate::respond(end(&handle, id, false))
+    })
+    .await
+}
+
+// The user dismissed a break reminder instead of pausing
+#[tauri::command]
+pub async fn skip_break(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Skip break", |state| {
+        crate::respond(update_record(state, |record| record.skipped += 1))
+    })
+    .await
+}
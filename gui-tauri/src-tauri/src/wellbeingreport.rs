@@ -0,0 +1,195 @@
+// Wellbeing report: how the last day, week or month of use has gone
+//
+// Built on demand from the persisted history (history.json in the data dir)
+// and nothing else: session lengths, frustration episodes, time spent in flow
+// and how many tasks succeeded, per day (or per hour for "day") and against
+// the period before. Nothing is stored or sent anywhere; wiping the history
+// wipes the report with it.
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::history::{self, HistoryEntry};
+use crate::{clock, flow, tasks};
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+// A quiet spell this long ends a session
+const SESSION_GAP_SECS: u64 = 30 * 60;
+// This many failures this close together, with no success in between, is
+// one frustration episode
+const EPISODE_FAILURES: usize = 3;
+const EPISODE_WINDOW_SECS: u64 = 10 * 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Summary {
+    pub from: u64,
+    pub to: u64,
+    pub sessions: usize,
+    pub total_minutes: u64,
+    pub average_session_minutes: u64,
+    pub longest_session_minutes: u64,
+    pub frustration_episodes: usize,
+    pub flow_minutes: u64,
+    pub tasks: usize,
+    pub tasks_succeeded: usize,
+    // None when there were no tasks
+    pub completion_rate: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WellbeingReport {
+    pub period: String,
+    pub summary: Summary,
+    // The same span just before, for comparison
+    pub previous: Summary,
+    // Per hour for a day, per day otherwise
+    pub buckets: Vec<Summary>,
+}
+
+// Runs of entries without a quiet spell between them
+fn sessions(entries: &[&HistoryEntry]) -> Vec<Vec<u64>> {
+    let mut sessions: Vec<Vec<u64>> = Vec::new();
+    for entry in entries {
+        match sessions.last_mut() {
+            Some(session)
+                if entry.timestamp.saturating_sub(*session.last().unwrap()) <= SESSION_GAP_SECS =>
+            {
+                session.push(entry.timestamp)
+            }
+            _ => sessions.push(vec![entry.timestamp]),
+        }
+    }
+    sessions
+}
+
+fn frustration_episodes(entries: &[&HistoryEntry]) -> usize {
+    let mut failures: Vec<u64> = Vec::new();
+    let mut in_episode = false;
+    let mut episodes = 0;
+    for entry in entries {
+        if entry.succeeded {
+            failures.clear();
+            in_episode = false;
+            continue;
+        }
+        failures.retain(|at| entry.timestamp.saturating_sub(*at) <= EPISODE_WINDOW_SECS);
+        failures.push(entry.timestamp);
+        if failures.len() >= EPISODE_FAILURES && !in_episode {
+            episodes += 1;
+            in_episode = true;
+        }
+    }
+    episodes
+}
+
+// Seconds spent in stretches that meet the same bar as live flow detection
+fn flow_secs(entries: &[&HistoryEntry]) -> u64 {
+    let mut total = 0;
+    let mut stretch: Vec<&HistoryEntry> = Vec::new();
+    let mut close = |stretch: &mut Vec<&HistoryEntry>| {
+        if let (Some(first), Some(last)) = (stretch.first(), stretch.last()) {
+            let length = last.timestamp - first.timestamp;
+            let succeeded = stretch.iter().filter(|e| e.succeeded).count();
+            if length * 1000 >= flow::MIN_FLOW_MS
+                && stretch.len() >= flow::MIN_INTERACTIONS
+                && succeeded as f32 / stretch.len() as f32 >= flow::MIN_SUCCESS_RATE
+            {
+                total += length;
+            }
+        }
+        stretch.clear();
+    };
+    for entry in entries {
+        if let Some(last) = stretch.last() {
+            if (entry.timestamp - last.timestamp) * 1000 > flow::MAX_GAP_MS {
+                close(&mut stretch);
+            }
+        }
+        stretch.push(entry);
+    }
+    close(&mut stretch);
+    total
+}
+
+fn summarize(entries: &[HistoryEntry], from: u64, to: u64) -> Summary {
+    let within: Vec<&HistoryEntry> = entries
+        .iter()
+        .filter(|e| e.timestamp >= from && e.timestamp < to)
+        .collect();
+    let lengths: Vec<u64> = sessions(&within)
+        .iter()
+        .map(|s| s.last().unwrap() - s.first().unwrap())
+        .collect();
+    let total = lengths.iter().sum::<u64>();
+    let succeeded = within.iter().filter(|e| e.succeeded).count();
+    Summary {
+        from,
+        to,
+        sessions: lengths.len(),
+        total_minutes: total / 60,
+        average_session_minutes: if lengths.is_empty() {
+            0
+        } else {
+            total / lengths.len() as u64 / 60
+        },
+        longest_session_minutes: lengths.iter().max().copied().unwrap_or(0) / 60,
+        frustration_episodes: frustration_episodes(&within),
+        flow_minutes: flow_secs(&within) / 60,
+        tasks: within.len(),
+        tasks_succeeded: succeeded,
+        completion_rate: (!within.is_empty()).then(|| succeeded as f32 / within.len() as f32),
+    }
+}
+
+// "day" is today so far; "week" and "month" end today and start at a local
+// midnight
+pub fn report(period: &str) -> anyhow::Result<WellbeingReport> {
+    let (days, bucket) = match period {
+        "day" => (1, HOUR),
+        "week" => (7, DAY),
+        "month" => (30, DAY),
+        other => bail!("Unknown period '{}'; use day, week or month", other),
+    };
+    let now = clock::now();
+    let offset = history::local_offset();
+    let local_now = (now as i64 + offset).max(0) as u64;
+    let midnight = (((local_now / DAY) * DAY) as i64 - offset).max(0) as u64;
+    let from = midnight.saturating_sub((days - 1) * DAY);
+    let to = midnight + DAY;
+
+    let mut entries = history::load();
+    entries.sort_by_key(|e| e.timestamp);
+    let buckets = (from..to)
+        .step_by(bucket as usize)
+        .map(|start| summarize(&entries, start, start + bucket))
+        .collect();
+    Ok(WellbeingReport {
+        period: period.to_string(),
+        summary: summarize(&entries, from, to),
+        previous: summarize(&entries, from.saturating_sub(to - from), from),
+        buckets,
+    })
+}
+
+// ========== Tauri Commands ==========
+
+#[tauri::command]
+pub async fn get_wellbeing_report(period: Option<String>, app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Get wellbeing report", move |_| {
+        crate::respond(report(period.as_deref().unwrap_or("week")))
+    })
+    .await
+}
+
+// Delete everything the report is built from: the persisted history and the
+// interactions of this session
+#[tauri::command]
+pub async fn wipe_wellbeing_report(app: AppHandle) -> serde_json::Value {
+    tasks::blocking_json(&app, "Wipe wellbeing report", |state| {
+        state.interaction_history.blocking_lock().clear();
+        crate::respond(history::clear())
+    })
+    .await
+}